@@ -0,0 +1,227 @@
+# Writes the small ONNX models used by src/graph/onnx_test.rs.
+# The protobuf encoding is done by hand, so neither onnx nor protobuf has to be installed.
+# Run from this directory with: python3 generate_fixtures.py
+#
+# The weights follow the same formula as fixture_value in onnx_test.rs,
+# which lets the tests rebuild the expected graph without parsing anything.
+
+import struct
+
+ONNX_FLOAT = 1
+ATTRIBUTE_FLOAT = 1
+ATTRIBUTE_INT = 2
+
+
+def varint(value):
+    if value < 0:
+        value += 1 << 64
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def field_varint(number, value):
+    return varint(number << 3) + varint(value)
+
+
+def field_bytes(number, value):
+    if isinstance(value, str):
+        value = value.encode("utf-8")
+    return varint((number << 3) | 2) + varint(len(value)) + value
+
+
+def field_float(number, value):
+    return varint((number << 3) | 5) + struct.pack("<f", value)
+
+
+def fixture_value(seed, index):
+    return ((seed * 31 + index * 7) % 13 - 6) * 0.05
+
+
+def tensor(name, dims, seed, raw):
+    count = 1
+    for dim in dims:
+        count *= dim
+    # Tensors with negative dims are written without any data
+    count = max(count, 0)
+    values = [fixture_value(seed, index) for index in range(count)]
+    out = b"".join(field_varint(1, dim) for dim in dims)
+    out += field_varint(2, ONNX_FLOAT)
+    if raw:
+        out += field_bytes(9, struct.pack("<%df" % count, *values))
+    else:
+        out += field_bytes(4, struct.pack("<%df" % count, *values))
+    out += field_bytes(8, name)
+    return out
+
+
+def attribute_int(name, value):
+    return field_bytes(1, name) + field_varint(3, value) + field_varint(20, ATTRIBUTE_INT)
+
+
+def attribute_float(name, value):
+    return field_bytes(1, name) + field_float(2, value) + field_varint(20, ATTRIBUTE_FLOAT)
+
+
+def node(op_type, inputs, outputs, name, attributes=()):
+    out = b"".join(field_bytes(1, value) for value in inputs)
+    out += b"".join(field_bytes(2, value) for value in outputs)
+    out += field_bytes(3, name)
+    out += field_bytes(4, op_type)
+    out += b"".join(field_bytes(5, attribute) for attribute in attributes)
+    return out
+
+
+def value_info(name, dims):
+    shape = b"".join(field_bytes(1, field_varint(1, dim)) for dim in dims)
+    tensor_type = field_varint(1, ONNX_FLOAT) + field_bytes(2, shape)
+    return field_bytes(1, name) + field_bytes(2, field_bytes(1, tensor_type))
+
+
+def model(name, nodes, initializers, inputs, outputs):
+    graph = b"".join(field_bytes(1, value) for value in nodes)
+    graph += field_bytes(2, name)
+    graph += b"".join(field_bytes(5, value) for value in initializers)
+    graph += b"".join(field_bytes(11, value) for value in inputs)
+    graph += b"".join(field_bytes(12, value) for value in outputs)
+
+    out = field_varint(1, 8)
+    out += field_bytes(2, "generate_fixtures.py")
+    out += field_bytes(7, graph)
+    out += field_bytes(8, field_bytes(1, "") + field_varint(2, 13))
+    return out
+
+
+def write(file_name, data):
+    with open(file_name, "wb") as file:
+        file.write(data)
+
+
+# Gemm -> Relu -> Gemm -> Softmax, as exported by PyTorch from nn.Linear with transB = 1.
+# The weights are stored as float_data.
+write(
+    "mlp_gemm.onnx",
+    model(
+        "mlp_gemm",
+        [
+            node("Gemm", ["input", "fc1.weight", "fc1.bias"], ["fc1"], "fc1", [attribute_int("transB", 1)]),
+            node("Relu", ["fc1"], ["relu1"], "relu1"),
+            node(
+                "Gemm",
+                ["relu1", "fc2.weight", "fc2.bias"],
+                ["fc2"],
+                "fc2",
+                [attribute_int("transB", 1), attribute_float("alpha", 0.5), attribute_float("beta", 2.0)],
+            ),
+            node("Softmax", ["fc2"], ["output"], "softmax", [attribute_int("axis", -1)]),
+        ],
+        [
+            tensor("fc1.weight", [8, 4], 1, False),
+            tensor("fc1.bias", [8], 2, False),
+            tensor("fc2.weight", [3, 8], 3, False),
+            tensor("fc2.bias", [3], 4, False),
+        ],
+        [value_info("input", [2, 4])],
+        [value_info("output", [2, 3])],
+    ),
+)
+
+# MatMul -> Add -> Relu -> MatMul -> Softmax, the second MatMul has no bias.
+# The weights are stored as raw_data.
+write(
+    "mlp_matmul.onnx",
+    model(
+        "mlp_matmul",
+        [
+            node("MatMul", ["input", "weights1"], ["matmul1"], "matmul1"),
+            node("Add", ["matmul1", "bias1"], ["add1"], "add1"),
+            node("Relu", ["add1"], ["relu1"], "relu1"),
+            node("MatMul", ["relu1", "weights2"], ["matmul2"], "matmul2"),
+            node("Softmax", ["matmul2"], ["output"], "softmax"),
+        ],
+        [
+            tensor("weights1", [4, 8], 5, True),
+            tensor("bias1", [1, 8], 6, True),
+            tensor("weights2", [8, 3], 7, True),
+        ],
+        [value_info("input", [2, 4])],
+        [value_info("output", [2, 3])],
+    ),
+)
+
+# Gemm -> Sigmoid, Sigmoid is not supported
+write(
+    "unsupported.onnx",
+    model(
+        "unsupported",
+        [
+            node("Gemm", ["input", "fc1.weight", "fc1.bias"], ["fc1"], "fc1", [attribute_int("transB", 1)]),
+            node("Sigmoid", ["fc1"], ["output"], "sigmoid1"),
+        ],
+        [
+            tensor("fc1.weight", [8, 4], 1, False),
+            tensor("fc1.bias", [8], 2, False),
+        ],
+        [value_info("input", [2, 4])],
+        [value_info("output", [2, 8])],
+    ),
+)
+
+# Gemm -> Add, an Add is only supported as the bias of the MatMul right before it
+write(
+    "misplaced_add.onnx",
+    model(
+        "misplaced_add",
+        [
+            node("Gemm", ["input", "fc1.weight", "fc1.bias"], ["fc1"], "fc1", [attribute_int("transB", 1)]),
+            node("Add", ["fc1", "bias2"], ["output"], "add1"),
+        ],
+        [
+            tensor("fc1.weight", [8, 4], 1, False),
+            tensor("fc1.bias", [8], 2, False),
+            tensor("bias2", [8], 3, False),
+        ],
+        [value_info("input", [2, 4])],
+        [value_info("output", [2, 8])],
+    ),
+)
+
+# MatMul -> Add where the weights and the bias have negative dims, and no data
+write(
+    "negative_dims.onnx",
+    model(
+        "negative_dims",
+        [
+            node("MatMul", ["input", "weights1"], ["matmul1"], "matmul1"),
+            node("Add", ["matmul1", "bias1"], ["output"], "add1"),
+        ],
+        [
+            tensor("weights1", [-4, 8], 5, True),
+            tensor("bias1", [1, 8], 6, True),
+        ],
+        [value_info("input", [2, 4])],
+        [value_info("output", [2, 8])],
+    ),
+)
+write(
+    "negative_bias_dims.onnx",
+    model(
+        "negative_bias_dims",
+        [
+            node("MatMul", ["input", "weights1"], ["matmul1"], "matmul1"),
+            node("Add", ["matmul1", "bias1"], ["output"], "add1"),
+        ],
+        [
+            tensor("weights1", [4, 8], 5, True),
+            tensor("bias1", [1, -8], 6, True),
+        ],
+        [value_info("input", [2, 4])],
+        [value_info("output", [2, 8])],
+    ),
+)
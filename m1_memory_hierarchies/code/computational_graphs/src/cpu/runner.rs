@@ -1,3 +1,5 @@
+use half::{bf16, f16};
+
 use crate::shared::{
     benchmark_plot::draw_benchmark_plot,
     configuration::Configuration,
@@ -50,6 +52,42 @@ fn optimized_linear_benchmark(
     Tensor2D::linear_optimized(input, weights, bias, output);
 }
 
+fn blocked_linear_benchmark(
+    input: &mut Tensor2D,
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    output: &mut Tensor2D,
+) {
+    Tensor2D::linear_blocked(input, weights, bias, output);
+}
+
+fn blocked_two_level_linear_benchmark(
+    input: &mut Tensor2D,
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    output: &mut Tensor2D,
+) {
+    Tensor2D::linear_blocked_two_level(input, weights, bias, output);
+}
+
+fn simd_linear_benchmark(
+    input: &mut Tensor2D,
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    output: &mut Tensor2D,
+) {
+    Tensor2D::linear_simd(input, weights, bias, output);
+}
+
+fn packed_linear_benchmark(
+    input: &mut Tensor2D,
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    output: &mut Tensor2D,
+) {
+    Tensor2D::linear_packed(input, weights, bias, output);
+}
+
 fn linear_benchmark(config: &Configuration) {
     let names: Vec<String> = vec![
         "shared::tensor2d::linear".to_string(),
@@ -57,6 +95,10 @@ fn linear_benchmark(config: &Configuration) {
         "shared::tensor2d::linear_preallocated_inline".to_string(),
         "shared::tensor2d::linear_local_accumulation".to_string(),
         "shared::tensor2d::linear_optimized".to_string(),
+        "shared::tensor2d::linear_blocked".to_string(),
+        "shared::tensor2d::linear_blocked_two_level".to_string(),
+        "shared::tensor2d::linear_simd".to_string(),
+        "shared::tensor2d::linear_packed".to_string(),
     ];
 
     let functions: Vec<fn(&mut Tensor2D, &Tensor2D, &Tensor2D, &mut Tensor2D)> = vec![
@@ -65,6 +107,10 @@ fn linear_benchmark(config: &Configuration) {
         inline_linear_benchmark,
         local_accumulation_linear_benchmark,
         optimized_linear_benchmark,
+        blocked_linear_benchmark,
+        blocked_two_level_linear_benchmark,
+        simd_linear_benchmark,
+        packed_linear_benchmark,
     ];
 
     let mut all_measurements: Vec<PerformanceMeasurements> =
@@ -81,9 +127,136 @@ fn linear_benchmark(config: &Configuration) {
     );
 }
 
+fn f64_linear_benchmark(
+    input: &mut Tensor2D<f64>,
+    weights: &Tensor2D<f64>,
+    bias: &Tensor2D<f64>,
+    output: &mut Tensor2D<f64>,
+) {
+    Tensor2D::linear_generic::<f64>(input, weights, bias, output);
+}
+
+fn f32_linear_benchmark(
+    input: &mut Tensor2D,
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    output: &mut Tensor2D,
+) {
+    Tensor2D::linear_generic::<f32>(input, weights, bias, output);
+}
+
+fn f16_f32_accumulation_linear_benchmark(
+    input: &mut Tensor2D<f16>,
+    weights: &Tensor2D<f16>,
+    bias: &Tensor2D<f16>,
+    output: &mut Tensor2D<f16>,
+) {
+    Tensor2D::linear_generic::<f32>(input, weights, bias, output);
+}
+
+fn f16_f16_accumulation_linear_benchmark(
+    input: &mut Tensor2D<f16>,
+    weights: &Tensor2D<f16>,
+    bias: &Tensor2D<f16>,
+    output: &mut Tensor2D<f16>,
+) {
+    Tensor2D::linear_generic::<f16>(input, weights, bias, output);
+}
+
+fn bf16_f32_accumulation_linear_benchmark(
+    input: &mut Tensor2D<bf16>,
+    weights: &Tensor2D<bf16>,
+    bias: &Tensor2D<bf16>,
+    output: &mut Tensor2D<bf16>,
+) {
+    Tensor2D::linear_generic::<f32>(input, weights, bias, output);
+}
+
+// The scale and zero points of a QuantizedTensor2D are only applied once per output element,
+// so the raw i8 values with i32 accumulation show the cost of the inner loop.
+fn i8_i32_accumulation_linear_benchmark(
+    input: &mut Tensor2D<i8>,
+    weights: &Tensor2D<i8>,
+    bias: &Tensor2D<i8>,
+    output: &mut Tensor2D<i8>,
+) {
+    Tensor2D::linear_generic::<i32>(input, weights, bias, output);
+}
+
+// Every element type needs its own call to benchmark_function_vector,
+// the measurements are gathered in a single plot afterwards.
+fn linear_element_types_benchmark(config: &Configuration) {
+    let mut all_measurements: Vec<PerformanceMeasurements> = Vec::<PerformanceMeasurements>::new();
+
+    let mut measurements: Vec<PerformanceMeasurements> =
+        vec![PerformanceMeasurements::default(); 1];
+    benchmark_function_vector(
+        config,
+        vec!["shared::tensor2d::linear_generic - f64".to_string()],
+        vec![f64_linear_benchmark],
+        &mut measurements,
+    );
+    all_measurements.append(&mut measurements);
+
+    let mut measurements: Vec<PerformanceMeasurements> =
+        vec![PerformanceMeasurements::default(); 1];
+    benchmark_function_vector(
+        config,
+        vec!["shared::tensor2d::linear_generic - f32".to_string()],
+        vec![f32_linear_benchmark],
+        &mut measurements,
+    );
+    all_measurements.append(&mut measurements);
+
+    let mut measurements: Vec<PerformanceMeasurements> =
+        vec![PerformanceMeasurements::default(); 2];
+    benchmark_function_vector(
+        config,
+        vec![
+            "shared::tensor2d::linear_generic - f16 (f32 accumulation)".to_string(),
+            "shared::tensor2d::linear_generic - f16 (f16 accumulation)".to_string(),
+        ],
+        vec![
+            f16_f32_accumulation_linear_benchmark,
+            f16_f16_accumulation_linear_benchmark,
+        ],
+        &mut measurements,
+    );
+    all_measurements.append(&mut measurements);
+
+    let mut measurements: Vec<PerformanceMeasurements> =
+        vec![PerformanceMeasurements::default(); 1];
+    benchmark_function_vector(
+        config,
+        vec!["shared::tensor2d::linear_generic - bf16 (f32 accumulation)".to_string()],
+        vec![bf16_f32_accumulation_linear_benchmark],
+        &mut measurements,
+    );
+    all_measurements.append(&mut measurements);
+
+    let mut measurements: Vec<PerformanceMeasurements> =
+        vec![PerformanceMeasurements::default(); 1];
+    benchmark_function_vector(
+        config,
+        vec!["shared::tensor2d::linear_generic - i8 (i32 accumulation)".to_string()],
+        vec![i8_i32_accumulation_linear_benchmark],
+        &mut measurements,
+    );
+    all_measurements.append(&mut measurements);
+
+    draw_benchmark_plot(
+        "CPU Benchmark - Linear Element Types",
+        "benchmarks/cpu/",
+        "cpu_linear_element_types_benchmark.png",
+        all_measurements,
+        config.log_scale,
+    );
+}
+
 fn linear(config: &Configuration) {
     if config.run_performance_benchmark {
         linear_benchmark(config);
+        linear_element_types_benchmark(config);
         return;
     }
 
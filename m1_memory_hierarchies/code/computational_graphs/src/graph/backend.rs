@@ -0,0 +1,736 @@
+use std::collections::HashMap;
+
+use crate::shared::graph_operators::GraphOperator;
+use crate::shared::graph_operators::GraphOperator::*;
+use crate::shared::quantized_tensor2d::QuantizedTensor2D;
+use crate::shared::tensor2d::Tensor2D;
+
+use super::fusion::{default_fusion_rules, fuse_graph_operators, FusionReport};
+use super::graph_error::GraphError;
+use super::graph_validation::validate_graph_operators;
+use super::memory_planner::{plan_memory, MemoryPlan, MemoryPlanningStrategy, MemoryReport};
+use super::nodes::{check_buffer_count, Node, NodeOperator};
+use super::shape_inference::{infer_shapes, ShapeReport};
+
+// GraphRunner and GraphRunnerGPU build the same nodes from the graph operators and run
+// them in the same order. What differs is where the tensors live and how a node is run,
+// which is what a Backend does. BackendRunner is the graph runner which drives any of them,
+// GraphRunner and GraphRunnerGPU are BackendRunner with the CPU and the wgpu backend.
+// Another backend, like one running the kernels with SIMD, only has to allocate tensors,
+// dispatch the nodes and hand the output back.
+pub trait Backend {
+    // Handed to every call instead of being kept by the backend, like the GPU handles
+    // given to every method of GraphRunnerGPU
+    type Context;
+    type Tensor;
+    // The dispatches of a run are recorded in here and then submitted together,
+    // which is a command encoder for wgpu
+    type Commands;
+
+    // Used in the panic messages, like "CPU" or "GPU"
+    const NAME: &'static str;
+
+    // Rejects the operators the backend has no kernels for. This is checked
+    // before fusion, so node is an index into the graph the user handed us.
+    fn check_supported_operators(graph_operators: &[GraphOperator]) -> Result<(), GraphError>;
+
+    // The label names the allocation, which only shows up in the GPU debugging tools
+    fn upload(&mut self, context: &Self::Context, label: &str, tensor: &Tensor2D) -> Self::Tensor;
+
+    fn allocate(
+        &mut self,
+        context: &Self::Context,
+        label: &str,
+        row_count: usize,
+        column_count: usize,
+    ) -> Self::Tensor;
+
+    // The int8 tensors of the quantized operators are kept by the backend, apart from
+    // the data buffers. Returns the index the node refers to the tensor by.
+    fn upload_quantized(&mut self, context: &Self::Context, tensor: QuantizedTensor2D) -> usize;
+
+    fn shape(tensor: &Self::Tensor) -> (usize, usize);
+
+    // Only within the memory already allocated for the tensor, see memory_planner
+    fn set_dimensions(tensor: &mut Self::Tensor, row_count: usize, column_count: usize);
+
+    // Gives the tensor a new number of rows, allocating more memory if it doesn't fit.
+    // A bias has its first row repeated, see BackendRunner::set_input_batch_with.
+    fn resize_rows(
+        &mut self,
+        context: &Self::Context,
+        label: &str,
+        tensor: &mut Self::Tensor,
+        row_count: usize,
+        repeat_first_row: bool,
+    );
+
+    // Called after the data buffers were resized, for the memory the backend keeps itself
+    fn resize_batch(&mut self, _nodes: &[Node], _row_count: usize) {}
+
+    // Overwrites the first elements of the tensor with the values of input
+    fn write(&mut self, context: &Self::Context, tensor: &mut Self::Tensor, input: &Tensor2D);
+
+    fn begin(&mut self, context: &Self::Context) -> Self::Commands;
+
+    fn dispatch(
+        &mut self,
+        context: &Self::Context,
+        node: &Node,
+        tensors: &mut [Self::Tensor],
+        commands: &mut Self::Commands,
+    ) -> Result<(), GraphError>;
+
+    fn submit(&mut self, context: &Self::Context, commands: Self::Commands);
+
+    // Waits for the submitted commands to finish and copies the tensor to the host
+    #[allow(async_fn_in_trait)]
+    async fn read(&mut self, context: &Self::Context, tensor: &mut Self::Tensor) -> Tensor2D;
+}
+
+// The fields are public for the methods only one of the backends has, which are
+// implemented in graph_runner and graph_runner_gpu
+pub struct BackendRunner<B: Backend> {
+    pub backend: B,
+    pub graph_operators_are_valid: bool,
+    pub nodes: Vec<Node>,
+    pub data_buffers: Vec<B::Tensor>,
+    pub data_buffers_are_valid: bool,
+    // Which fusions fired when the runner was built, empty if fuse_operators wasn't set
+    pub fusion_report: FusionReport,
+    // The shapes and costs of the operators after fusion, used to allocate the buffers
+    pub shape_report: ShapeReport,
+    // Set by plan_memory_with. The intermediate buffers are reused, so the graph can only be run forward.
+    pub memory_plan: Option<MemoryPlan>,
+    // The data buffer every operator reads its input from, after fusion. Lets us look at the
+    // intermediate values of a run, see quantization::calibrate_input_ranges.
+    pub operator_input_indices: Vec<usize>,
+}
+
+impl<B: Backend> BackendRunner<B> {
+    // Returns the first problem found by validate_graph_operators if the graph is invalid
+    pub fn with_backend(
+        backend: B,
+        context: &B::Context,
+        graph_operators: &[GraphOperator],
+        fuse_operators: bool,
+    ) -> Result<Self, GraphError> {
+        validate_graph_operators(graph_operators)?;
+        B::check_supported_operators(graph_operators)?;
+
+        let mut runner: BackendRunner<B> = BackendRunner {
+            backend,
+            graph_operators_are_valid: true,
+            nodes: Vec::<Node>::new(),
+            data_buffers: Vec::<B::Tensor>::new(),
+            data_buffers_are_valid: false,
+            fusion_report: FusionReport::default(),
+            shape_report: ShapeReport::default(),
+            memory_plan: None,
+            operator_input_indices: Vec::<usize>::new(),
+        };
+
+        // Fusion is a rewrite of the graph, see graph::fusion
+        let graph_operators: Vec<GraphOperator> = if fuse_operators {
+            let (fused_operators, fusion_report) =
+                fuse_graph_operators(graph_operators, &default_fusion_rules());
+            runner.fusion_report = fusion_report;
+            fused_operators
+        } else {
+            graph_operators.to_vec()
+        };
+
+        let shape_report: ShapeReport = infer_shapes(&graph_operators)?;
+        runner.compute_nodes(context, &graph_operators, &shape_report)?;
+        runner.shape_report = shape_report;
+        runner.data_buffers_are_valid = true;
+
+        Ok(runner)
+    }
+
+    pub fn fusion_report(&self) -> &FusionReport {
+        &self.fusion_report
+    }
+
+    pub fn shape_report(&self) -> &ShapeReport {
+        &self.shape_report
+    }
+
+    fn get_new_key(operator_counts: &mut HashMap<NodeOperator, u32>, key: &NodeOperator) -> String {
+        let value: &mut u32 = operator_counts.entry(key.clone()).or_insert(0);
+        let index: u32 = *value;
+        *value = index + 1;
+        format!("{:?}_{}", key, index)
+    }
+
+    // The input of an operator is the buffer of the transfer node right before it.
+    // The graph has been validated, so these errors mean the nodes were built wrong.
+    fn verify_previous_node_and_get_index(
+        nodes: &[Node],
+        operator_index: usize,
+    ) -> Result<usize, GraphError> {
+        match nodes.last() {
+            Some(previous_node) if previous_node.operator == NodeOperator::Transfer => {
+                check_buffer_count(previous_node, 1)?;
+                Ok(previous_node.buffer_indices[0])
+            }
+            _ => Err(GraphError::MissingInput {
+                node: operator_index,
+            }),
+        }
+    }
+
+    fn get_named_buffer_index(
+        named_buffers: &HashMap<String, usize>,
+        operator_index: usize,
+        name: &str,
+    ) -> Result<usize, GraphError> {
+        named_buffers
+            .get(name)
+            .copied()
+            .ok_or_else(|| GraphError::UnknownTensor {
+                node: operator_index,
+                name: name.to_string(),
+            })
+    }
+
+    fn upload(&mut self, context: &B::Context, label: String, tensor: &Tensor2D) -> usize {
+        let tensor: B::Tensor = self.backend.upload(context, &label, tensor);
+        self.data_buffers.push(tensor);
+        self.data_buffers.len() - 1
+    }
+
+    fn allocate(&mut self, context: &B::Context, label: String, shape: (usize, usize)) -> usize {
+        let tensor: B::Tensor = self.backend.allocate(context, &label, shape.0, shape.1);
+        self.data_buffers.push(tensor);
+        self.data_buffers.len() - 1
+    }
+
+    // Every operator which computes something is followed by a transfer node,
+    // which hands its output on to the next operator
+    fn push_node(
+        &mut self,
+        operator_counts: &mut HashMap<NodeOperator, u32>,
+        name: String,
+        key: NodeOperator,
+        buffer_indices: Vec<usize>,
+    ) {
+        let output_index: usize = buffer_indices[buffer_indices.len() - 1];
+        self.nodes.push(Node::new(name, key, buffer_indices));
+
+        let key: NodeOperator = NodeOperator::Transfer;
+        let new_key: String = Self::get_new_key(operator_counts, &key);
+        self.nodes.push(Node::new(new_key, key, vec![output_index]));
+    }
+
+    // This is made a lot more complicated by reusing buffers
+    // If each node owned its own buffers with no reusage
+    // We would need to keep less track of buffers
+    fn compute_nodes(
+        &mut self,
+        context: &B::Context,
+        graph_operators: &[GraphOperator],
+        shape_report: &ShapeReport,
+    ) -> Result<(), GraphError> {
+        if !self.graph_operators_are_valid {
+            panic!("Invalid graph being sent to compute_nodes!");
+        }
+
+        let mut operator_counts: HashMap<NodeOperator, u32> = HashMap::<NodeOperator, u32>::new();
+
+        // The buffer index of every tensor given a name by a Store operator
+        let mut named_buffers: HashMap<String, usize> = HashMap::<String, usize>::new();
+
+        for (operator_index, operator) in graph_operators.iter().enumerate() {
+            let output_shape: (usize, usize) = shape_report.nodes[operator_index].output_shape;
+            let previous_output_index: Option<usize> =
+                self.nodes.last().map(|node| node.buffer_indices[0]);
+
+            match operator {
+                Empty => {}
+                // Maybe put a device to device split in here for simpler code in the other operators
+                HostToDevice { input } => {
+                    let key: NodeOperator = NodeOperator::Input;
+                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
+                    let input_index: usize =
+                        self.upload(context, format!("{}_{}", new_key, "input"), input);
+                    self.push_node(&mut operator_counts, new_key, key, vec![input_index]);
+                }
+                DeviceToHost => {
+                    let key: NodeOperator = NodeOperator::Output;
+                    let input_index: usize =
+                        Self::verify_previous_node_and_get_index(&self.nodes, operator_index)?;
+                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
+                    self.nodes.push(Node::new(new_key, key, vec![input_index]));
+                }
+                // Note that ReLU, Softmax and Activation are not inplace
+                ReLU | Softmax | Activation { .. } => {
+                    let key: NodeOperator = match operator {
+                        ReLU => NodeOperator::ReLU,
+                        Softmax => NodeOperator::Softmax,
+                        Activation { function } => NodeOperator::Activation(*function),
+                        _ => unreachable!(),
+                    };
+                    let input_index: usize =
+                        Self::verify_previous_node_and_get_index(&self.nodes, operator_index)?;
+                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
+
+                    let output_index: usize =
+                        self.allocate(context, format!("{}_{}", new_key, "output"), output_shape);
+
+                    self.push_node(
+                        &mut operator_counts,
+                        new_key,
+                        key,
+                        vec![input_index, output_index],
+                    );
+                }
+                Linear { weights, bias }
+                | LinearReLUFused { weights, bias }
+                | LinearReLUSoftmaxFused { weights, bias }
+                | LinearActivationFused { weights, bias, .. } => {
+                    let key: NodeOperator = match operator {
+                        Linear { .. } => NodeOperator::Linear,
+                        LinearReLUFused { .. } => NodeOperator::LinearReLU,
+                        LinearReLUSoftmaxFused { .. } => NodeOperator::LinearReLUSoftmax,
+                        LinearActivationFused { function, .. } => {
+                            NodeOperator::LinearActivation(*function)
+                        }
+                        _ => unreachable!(),
+                    };
+                    let input_index: usize =
+                        Self::verify_previous_node_and_get_index(&self.nodes, operator_index)?;
+                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
+
+                    let weights_index: usize =
+                        self.upload(context, format!("{}_{}", new_key, "weights"), weights);
+                    let bias_index: usize =
+                        self.upload(context, format!("{}_{}", new_key, "bias"), bias);
+                    let output_index: usize =
+                        self.allocate(context, format!("{}_{}", new_key, "output"), output_shape);
+
+                    self.push_node(
+                        &mut operator_counts,
+                        new_key,
+                        key,
+                        vec![input_index, weights_index, bias_index, output_index],
+                    );
+                }
+                // The target and the labels are kept in a data buffer. The loss is a single value.
+                MeanSquaredError { .. } | SoftmaxCrossEntropy { .. } => {
+                    let (key, target): (NodeOperator, Tensor2D) = match operator {
+                        MeanSquaredError { target } => {
+                            (NodeOperator::MeanSquaredError, target.clone())
+                        }
+                        SoftmaxCrossEntropy { labels } => (
+                            NodeOperator::SoftmaxCrossEntropy,
+                            Tensor2D::from_labels(labels),
+                        ),
+                        _ => unreachable!(),
+                    };
+                    let input_index: usize =
+                        Self::verify_previous_node_and_get_index(&self.nodes, operator_index)?;
+                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
+
+                    let target_index: usize =
+                        self.upload(context, format!("{}_{}", new_key, "target"), &target);
+                    let output_index: usize =
+                        self.allocate(context, format!("{}_{}", new_key, "output"), output_shape);
+
+                    self.push_node(
+                        &mut operator_counts,
+                        new_key,
+                        key,
+                        vec![input_index, target_index, output_index],
+                    );
+                }
+                // The buffer indices are [input, quantized input, weights, bias, output], where
+                // the quantized input and the weights are kept by the backend, see upload_quantized
+                LinearInt8 {
+                    weights,
+                    bias,
+                    input_parameters,
+                }
+                | LinearReLUInt8Fused {
+                    weights,
+                    bias,
+                    input_parameters,
+                } => {
+                    let key: NodeOperator = if let LinearReLUInt8Fused { .. } = operator {
+                        NodeOperator::LinearReLUInt8
+                    } else {
+                        NodeOperator::LinearInt8
+                    };
+
+                    let input_index: usize =
+                        Self::verify_previous_node_and_get_index(&self.nodes, operator_index)?;
+                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
+
+                    let (input_row_count, input_column_count): (usize, usize) =
+                        shape_report.nodes[operator_index].input_shapes[0];
+                    let quantized_input_index: usize = self.backend.upload_quantized(
+                        context,
+                        QuantizedTensor2D::quantize(
+                            &Tensor2D::new(0.0, input_row_count, input_column_count),
+                            *input_parameters,
+                        ),
+                    );
+                    let weights_index: usize =
+                        self.backend.upload_quantized(context, weights.clone());
+
+                    let bias_index: usize =
+                        self.upload(context, format!("{}_{}", new_key, "bias"), bias);
+                    let output_index: usize =
+                        self.allocate(context, format!("{}_{}", new_key, "output"), output_shape);
+
+                    self.push_node(
+                        &mut operator_counts,
+                        new_key,
+                        key,
+                        vec![
+                            input_index,
+                            quantized_input_index,
+                            weights_index,
+                            bias_index,
+                            output_index,
+                        ],
+                    );
+                }
+                // Store doesn't produce a node, it just remembers which buffer
+                // the previous transfer pointed to.
+                Store { name } => {
+                    let input_index: usize =
+                        Self::verify_previous_node_and_get_index(&self.nodes, operator_index)?;
+                    named_buffers.insert(name.clone(), input_index);
+                }
+                // Load makes the named buffer the input of the next operator
+                Load { name } => {
+                    let named_index: usize =
+                        Self::get_named_buffer_index(&named_buffers, operator_index, name)?;
+
+                    let key: NodeOperator = NodeOperator::Transfer;
+                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
+                    self.nodes.push(Node::new(new_key, key, vec![named_index]));
+                }
+                // Concat has the columns of both tensors, see shape_inference
+                Add { name } | Concat { name } | AddReLUFused { name } => {
+                    let key: NodeOperator = match operator {
+                        Add { .. } => NodeOperator::Add,
+                        Concat { .. } => NodeOperator::Concat,
+                        AddReLUFused { .. } => NodeOperator::AddReLU,
+                        _ => unreachable!(),
+                    };
+                    let input_index: usize =
+                        Self::verify_previous_node_and_get_index(&self.nodes, operator_index)?;
+                    let other_index: usize =
+                        Self::get_named_buffer_index(&named_buffers, operator_index, name)?;
+                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
+
+                    let output_index: usize =
+                        self.allocate(context, format!("{}_{}", new_key, "output"), output_shape);
+
+                    self.push_node(
+                        &mut operator_counts,
+                        new_key,
+                        key,
+                        vec![input_index, other_index, output_index],
+                    );
+                }
+                // The buffer indices are [input, weights, bias, other, output]
+                LinearAddFused {
+                    weights,
+                    bias,
+                    name,
+                } => {
+                    let key: NodeOperator = NodeOperator::LinearAdd;
+                    let input_index: usize =
+                        Self::verify_previous_node_and_get_index(&self.nodes, operator_index)?;
+                    let other_index: usize =
+                        Self::get_named_buffer_index(&named_buffers, operator_index, name)?;
+                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
+
+                    let weights_index: usize =
+                        self.upload(context, format!("{}_{}", new_key, "weights"), weights);
+                    let bias_index: usize =
+                        self.upload(context, format!("{}_{}", new_key, "bias"), bias);
+                    let output_index: usize =
+                        self.allocate(context, format!("{}_{}", new_key, "output"), output_shape);
+
+                    self.push_node(
+                        &mut operator_counts,
+                        new_key,
+                        key,
+                        vec![
+                            input_index,
+                            weights_index,
+                            bias_index,
+                            other_index,
+                            output_index,
+                        ],
+                    );
+                }
+            }
+
+            // Every operator reads the output of the transfer before it,
+            // except for HostToDevice, which reads the input it uploaded
+            self.operator_input_indices
+                .push(previous_output_index.unwrap_or(self.nodes[0].buffer_indices[0]));
+        }
+
+        Ok(())
+    }
+
+    // Records every node with the backend and submits them as a single run
+    pub fn submit_operator_commands(&mut self, context: &B::Context) -> Result<(), GraphError> {
+        if !self.graph_operators_are_valid {
+            panic!(
+                "Tried to run the a {} computational graph with an unvalidated graph_operators!",
+                B::NAME
+            );
+        }
+
+        if !self.data_buffers_are_valid {
+            panic!(
+                "Tried to run the a {} computational graph with an unvalidated data_buffers!",
+                B::NAME
+            );
+        }
+
+        let mut commands: B::Commands = self.backend.begin(context);
+        let reshapes: &[(usize, usize, usize, usize)] = self
+            .memory_plan
+            .as_ref()
+            .map_or(&[], |memory_plan| memory_plan.reshapes.as_slice());
+        let mut reshapes = reshapes.iter().peekable();
+        for (node_index, node) in self.nodes.iter().enumerate() {
+            // Shared buffers have to have the shape of the tensor the node is about to write
+            while let Some((_, buffer_index, row_count, column_count)) =
+                reshapes.next_if(|reshape| reshape.0 == node_index)
+            {
+                B::set_dimensions(
+                    &mut self.data_buffers[*buffer_index],
+                    *row_count,
+                    *column_count,
+                );
+            }
+
+            self.backend
+                .dispatch(context, node, &mut self.data_buffers, &mut commands)?;
+        }
+        self.backend.submit(context, commands);
+
+        Ok(())
+    }
+
+    // Runs the graph iteration_count times and returns the output of the last run
+    pub async fn run_with(
+        &mut self,
+        context: &B::Context,
+        iteration_count: usize,
+    ) -> Result<Tensor2D, GraphError> {
+        for _ in 0..iteration_count {
+            self.submit_operator_commands(context)?;
+        }
+
+        let output_index: usize = self.output_index();
+        Ok(self
+            .backend
+            .read(context, &mut self.data_buffers[output_index])
+            .await)
+    }
+
+    // Based on the restrictions we have put on our graph, the last node has to be
+    // the output. The output isn't necessarily the last buffer, a named tensor
+    // can be loaded right before DeviceToHost.
+    pub fn output_index(&self) -> usize {
+        self.nodes[self.nodes.len() - 1].buffer_indices[0]
+    }
+
+    // The data buffers touched by the node. The quantized nodes also hold indices
+    // into the quantized tensors of the backend, see nodes::quantized_linear_buffers.
+    pub fn data_buffer_positions(node: &Node) -> Vec<usize> {
+        match node.operator {
+            NodeOperator::LinearInt8 | NodeOperator::LinearReLUInt8 => vec![0, 3, 4],
+            _ => (0..node.buffer_indices.len()).collect(),
+        }
+    }
+
+    // The positions of the weights and the bias in the buffer indices of the node, if it
+    // has them. The weights of the quantized nodes aren't data buffers.
+    fn parameter_positions(node: &Node) -> (Option<usize>, Option<usize>) {
+        match node.operator {
+            NodeOperator::Linear
+            | NodeOperator::LinearReLU
+            | NodeOperator::LinearReLUSoftmax
+            | NodeOperator::LinearAdd
+            | NodeOperator::LinearActivation(_) => (Some(1), Some(2)),
+            NodeOperator::LinearInt8 | NodeOperator::LinearReLUInt8 => (None, Some(3)),
+            _ => (None, None),
+        }
+    }
+
+    // Lets the intermediate buffers of the graph share memory, see graph::memory_planner.
+    // Every buffer written by a node is an intermediate, except for the output of the graph.
+    // The shared buffers are allocated with room for the largest tensor they will hold,
+    // the rest of the buffers are kept as they are.
+    pub fn plan_memory_with(
+        &mut self,
+        context: &B::Context,
+        strategy: MemoryPlanningStrategy,
+    ) -> Result<MemoryReport, GraphError> {
+        if self.memory_plan.is_some() {
+            return Err(GraphError::MemoryPlanned {
+                operation: "plan the memory again".to_string(),
+            });
+        }
+
+        let node_buffer_indices: Vec<Vec<usize>> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                Self::data_buffer_positions(node)
+                    .iter()
+                    .map(|position| node.buffer_indices[*position])
+                    .collect()
+            })
+            .collect();
+
+        let output_index: usize = self.output_index();
+        let shared_buffers: Vec<usize> = self
+            .nodes
+            .iter()
+            .zip(node_buffer_indices.iter())
+            .filter(|(node, _)| {
+                !matches!(
+                    node.operator,
+                    NodeOperator::Input | NodeOperator::Output | NodeOperator::Transfer
+                )
+            })
+            .map(|(_, buffer_indices)| buffer_indices[buffer_indices.len() - 1])
+            .filter(|buffer_index| *buffer_index != output_index)
+            .collect();
+
+        let buffer_shapes: Vec<(usize, usize)> = self.data_buffers.iter().map(B::shape).collect();
+        let memory_plan: MemoryPlan = plan_memory(
+            &buffer_shapes,
+            &node_buffer_indices,
+            &shared_buffers,
+            strategy,
+        );
+
+        // Buffers which aren't shared are moved to their new index
+        let mut data_buffers: Vec<Option<B::Tensor>> =
+            (0..memory_plan.capacities.len()).map(|_| None).collect();
+        for (buffer_index, buffer) in self.data_buffers.drain(..).enumerate() {
+            if !shared_buffers.contains(&buffer_index) {
+                data_buffers[memory_plan.assignments[buffer_index]] = Some(buffer);
+            }
+        }
+        for (buffer_index, (buffer, capacity)) in data_buffers
+            .into_iter()
+            .zip(memory_plan.capacities.iter())
+            .enumerate()
+        {
+            let buffer: B::Tensor = buffer.unwrap_or_else(|| {
+                self.backend
+                    .allocate(context, &format!("Shared_{}", buffer_index), *capacity, 1)
+            });
+            self.data_buffers.push(buffer);
+        }
+
+        for node in &mut self.nodes {
+            for position in Self::data_buffer_positions(node) {
+                node.buffer_indices[position] =
+                    memory_plan.assignments[node.buffer_indices[position]];
+            }
+        }
+        for buffer_index in &mut self.operator_input_indices {
+            *buffer_index = memory_plan.assignments[*buffer_index];
+        }
+
+        let report: MemoryReport = memory_plan.report.clone();
+        self.memory_plan = Some(memory_plan);
+        Ok(report)
+    }
+
+    // Replaces the input of the graph, which can have a different number of rows than the
+    // input the graph was built with, see graph::compiled_graph. The weights are kept as they
+    // are, every other buffer is resized to the new number of rows. A buffer with room for the
+    // new rows is reused, the rest are grown. The bias is assumed to have the same value in
+    // every row, which is checked by compiled_graph::validate_batch_independence.
+    pub fn set_input_batch_with(
+        &mut self,
+        context: &B::Context,
+        input_batch: &Tensor2D,
+    ) -> Result<(), GraphError> {
+        let input_index: usize = self.nodes[0].buffer_indices[0];
+        let (row_count, column_count): (usize, usize) = B::shape(&self.data_buffers[input_index]);
+        if input_batch.column_count != column_count {
+            return Err(GraphError::DimensionMismatch {
+                node: 0,
+                expected: (input_batch.row_count, column_count),
+                found: (input_batch.row_count, input_batch.column_count),
+            });
+        }
+
+        if input_batch.row_count != row_count {
+            if self.memory_plan.is_some() {
+                return Err(GraphError::MemoryPlanned {
+                    operation: "change the batch size".to_string(),
+                });
+            }
+            self.resize_batch(context, input_batch.row_count);
+        }
+
+        self.backend
+            .write(context, &mut self.data_buffers[input_index], input_batch);
+
+        Ok(())
+    }
+
+    fn resize_batch(&mut self, context: &B::Context, row_count: usize) {
+        let mut weights_indices: Vec<usize> = Vec::<usize>::new();
+        let mut bias_indices: Vec<usize> = Vec::<usize>::new();
+        for node in &self.nodes {
+            let (weights_position, bias_position): (Option<usize>, Option<usize>) =
+                Self::parameter_positions(node);
+            if let Some(position) = weights_position {
+                weights_indices.push(node.buffer_indices[position]);
+            }
+            if let Some(position) = bias_position {
+                bias_indices.push(node.buffer_indices[position]);
+            }
+        }
+
+        for (buffer_index, buffer) in self.data_buffers.iter_mut().enumerate() {
+            if weights_indices.contains(&buffer_index) {
+                continue;
+            }
+            self.backend.resize_rows(
+                context,
+                &format!("Batch_{}", buffer_index),
+                buffer,
+                row_count,
+                bias_indices.contains(&buffer_index),
+            );
+        }
+
+        self.backend.resize_batch(&self.nodes, row_count);
+    }
+
+    // The buffer indices of the (weights, bias) pairs of every linear operator, fused or not,
+    // in the order they appear in the graph. The quantized operators aren't included,
+    // as their weights aren't data buffers.
+    pub fn linear_parameter_indices(&self) -> Vec<(usize, usize)> {
+        self.nodes
+            .iter()
+            .filter_map(|node| match Self::parameter_positions(node) {
+                (Some(weights_position), Some(bias_position)) => Some((
+                    node.buffer_indices[weights_position],
+                    node.buffer_indices[bias_position],
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        graph::{
+            backend::{Backend, BackendRunner},
+            fusion::OperatorKind,
+            graph_error::GraphError,
+            graph_runner::{CPUBackend, GraphRunner},
+            nodes::{Node, NodeOperator},
+        },
+        shared::{
+            graph_operators::GraphOperator, quantized_tensor2d::QuantizedTensor2D,
+            tensor2d::Tensor2D, tensor2d_test_utilities::random_tensor,
+        },
+    };
+
+    // Runs the nodes with the CPU backend, but keeps track of what it was asked to do
+    // and has no kernel for Softmax, like a backend someone has only started on.
+    #[derive(Default)]
+    struct RecordingBackend {
+        cpu: CPUBackend,
+        dispatched: Vec<NodeOperator>,
+        submit_count: usize,
+    }
+
+    impl Backend for RecordingBackend {
+        type Context = ();
+        type Tensor = Tensor2D;
+        type Commands = Vec<NodeOperator>;
+
+        const NAME: &'static str = "Recording";
+
+        fn check_supported_operators(graph_operators: &[GraphOperator]) -> Result<(), GraphError> {
+            for (node, operator) in graph_operators.iter().enumerate() {
+                if let GraphOperator::Softmax = operator {
+                    return Err(GraphError::UnsupportedOperator {
+                        node,
+                        operator: OperatorKind::of(operator),
+                    });
+                }
+            }
+
+            Ok(())
+        }
+
+        fn upload(&mut self, context: &(), label: &str, tensor: &Tensor2D) -> Tensor2D {
+            self.cpu.upload(context, label, tensor)
+        }
+
+        fn allocate(
+            &mut self,
+            context: &(),
+            label: &str,
+            row_count: usize,
+            column_count: usize,
+        ) -> Tensor2D {
+            self.cpu.allocate(context, label, row_count, column_count)
+        }
+
+        fn upload_quantized(&mut self, context: &(), tensor: QuantizedTensor2D) -> usize {
+            self.cpu.upload_quantized(context, tensor)
+        }
+
+        fn shape(tensor: &Tensor2D) -> (usize, usize) {
+            CPUBackend::shape(tensor)
+        }
+
+        fn set_dimensions(tensor: &mut Tensor2D, row_count: usize, column_count: usize) {
+            CPUBackend::set_dimensions(tensor, row_count, column_count);
+        }
+
+        fn resize_rows(
+            &mut self,
+            context: &(),
+            label: &str,
+            tensor: &mut Tensor2D,
+            row_count: usize,
+            repeat_first_row: bool,
+        ) {
+            self.cpu
+                .resize_rows(context, label, tensor, row_count, repeat_first_row);
+        }
+
+        fn write(&mut self, context: &(), tensor: &mut Tensor2D, input: &Tensor2D) {
+            self.cpu.write(context, tensor, input);
+        }
+
+        fn begin(&mut self, _context: &()) -> Vec<NodeOperator> {
+            Vec::<NodeOperator>::new()
+        }
+
+        fn dispatch(
+            &mut self,
+            context: &(),
+            node: &Node,
+            tensors: &mut [Tensor2D],
+            commands: &mut Vec<NodeOperator>,
+        ) -> Result<(), GraphError> {
+            commands.push(node.operator.clone());
+            self.cpu.dispatch(context, node, tensors, &mut ())
+        }
+
+        fn submit(&mut self, _context: &(), commands: Vec<NodeOperator>) {
+            self.dispatched.extend(commands);
+            self.submit_count += 1;
+        }
+
+        async fn read(&mut self, context: &(), tensor: &mut Tensor2D) -> Tensor2D {
+            self.cpu.read(context, tensor).await
+        }
+    }
+
+    fn linear_relu_graph(rng: &mut ChaCha8Rng) -> Vec<GraphOperator> {
+        vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(rng, 4, 8),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(rng, 8, 6),
+                bias: random_tensor(rng, 4, 6),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Linear {
+                weights: random_tensor(rng, 6, 3),
+                bias: random_tensor(rng, 4, 3),
+            },
+            GraphOperator::DeviceToHost,
+        ]
+    }
+
+    #[test]
+    fn custom_backend_matches_cpu() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(1337);
+        let graph_operators: Vec<GraphOperator> = linear_relu_graph(&mut rng);
+
+        let mut expected_runner: GraphRunner = GraphRunner::new(&graph_operators, false, false)
+            .expect("Failed to build the CPU graph runner");
+        let expected: Tensor2D = expected_runner
+            .run()
+            .expect("Failed to run the CPU graph runner");
+
+        let mut runner: BackendRunner<RecordingBackend> =
+            BackendRunner::with_backend(RecordingBackend::default(), &(), &graph_operators, false)
+                .expect("Failed to build the recording graph runner");
+        let output: Tensor2D = pollster::block_on(runner.run_with(&(), 2))
+            .expect("Failed to run the recording graph runner");
+
+        assert_eq!(expected.row_count, output.row_count);
+        assert_eq!(expected.column_count, output.column_count);
+        assert_eq!(expected.data, output.data);
+
+        // Every node is handed to the backend in order, once per run
+        let nodes: Vec<NodeOperator> = runner
+            .nodes
+            .iter()
+            .map(|node| node.operator.clone())
+            .collect();
+        assert_eq!(runner.backend.submit_count, 2);
+        assert_eq!(runner.backend.dispatched.len(), 2 * nodes.len());
+        assert_eq!(runner.backend.dispatched[..nodes.len()], nodes[..]);
+        assert_eq!(runner.backend.dispatched[nodes.len()..], nodes[..]);
+    }
+
+    #[test]
+    fn custom_backend_rejects_unsupported_operators() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(1337);
+        let mut graph_operators: Vec<GraphOperator> = linear_relu_graph(&mut rng);
+        graph_operators.insert(4, GraphOperator::Softmax);
+
+        let result: Result<BackendRunner<RecordingBackend>, GraphError> =
+            BackendRunner::with_backend(RecordingBackend::default(), &(), &graph_operators, false);
+
+        assert_eq!(
+            result.err(),
+            Some(GraphError::UnsupportedOperator {
+                node: 4,
+                operator: OperatorKind::Softmax,
+            })
+        );
+    }
+}
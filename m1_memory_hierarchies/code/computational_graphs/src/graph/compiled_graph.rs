@@ -0,0 +1,123 @@
+use crate::shared::{
+    gpu_utilities::GPUHandles,
+    graph_operators::GraphOperator::{self, *},
+    tensor2d::Tensor2D,
+};
+
+use super::{
+    fusion::OperatorKind, graph_error::GraphError, graph_runner::GraphRunner,
+    graph_runner_gpu::GraphRunnerGPU,
+};
+
+// A graph which is built once and then run with as many inputs as needed. Building the
+// runners copies the weights into their buffers, or uploads them to the GPU, which is
+// a lot more expensive than running a small graph, so it is better to only do it once.
+//
+// The number of rows in the input is the batch dimension and can change from run to run.
+// The input given to HostToDevice only decides the number of columns, and the number of
+// rows the buffers are first allocated with. Every operator works on the rows independently,
+// except for the bias, which has a row per row of the input. To work with any number of
+// rows, the bias has to have the same value in every row, which is then repeated for as
+// many rows as the input has. The losses have a target with a row per row of the input,
+// so they aren't supported, as compiled graphs are only meant for inference.
+
+// Checks that a valid graph can be run with a different number of rows than its input has
+pub fn validate_batch_independence(graph_operators: &[GraphOperator]) -> Result<(), GraphError> {
+    for (node, operator) in graph_operators.iter().enumerate() {
+        let bias: &Tensor2D = match operator {
+            Linear { bias, .. }
+            | LinearReLUFused { bias, .. }
+            | LinearReLUSoftmaxFused { bias, .. }
+            | LinearInt8 { bias, .. }
+            | LinearReLUInt8Fused { bias, .. }
+            | LinearAddFused { bias, .. }
+            | LinearActivationFused { bias, .. } => bias,
+            MeanSquaredError { .. } | SoftmaxCrossEntropy { .. } => {
+                return Err(GraphError::UnsupportedOperator {
+                    node,
+                    operator: OperatorKind::of(operator),
+                });
+            }
+            _ => continue,
+        };
+
+        let first_row: &[f32] = &bias.data[0..bias.column_count];
+        if bias
+            .data
+            .chunks(bias.column_count)
+            .take(bias.row_count)
+            .any(|row| row != first_row)
+        {
+            return Err(GraphError::BatchDependentBias { node });
+        }
+    }
+
+    Ok(())
+}
+
+// The buffers of the runners can be larger than their tensor, see GraphRunner::set_input_batch
+fn trim_output(mut output: Tensor2D) -> Tensor2D {
+    let element_count: usize = output.len();
+    output.data.truncate(element_count);
+    output
+}
+
+pub struct CompiledGraph {
+    graph_runner: GraphRunner,
+}
+
+impl CompiledGraph {
+    pub fn compile(
+        graph_operators: &Vec<GraphOperator>,
+        fuse_operators: bool,
+        parallel: bool,
+    ) -> Result<Self, GraphError> {
+        let graph_runner: GraphRunner =
+            GraphRunner::new(graph_operators, fuse_operators, parallel)?;
+        validate_batch_independence(graph_operators)?;
+
+        Ok(CompiledGraph { graph_runner })
+    }
+
+    pub fn graph_runner(&self) -> &GraphRunner {
+        &self.graph_runner
+    }
+
+    pub fn run(&mut self, input_batch: &Tensor2D) -> Result<Tensor2D, GraphError> {
+        self.graph_runner.set_input_batch(input_batch)?;
+        Ok(trim_output(self.graph_runner.run()?))
+    }
+}
+
+pub struct CompiledGraphGPU {
+    graph_runner: GraphRunnerGPU,
+}
+
+impl CompiledGraphGPU {
+    pub fn compile(
+        gpu_handles: &GPUHandles,
+        graph_operators: &Vec<GraphOperator>,
+        fuse_operators: bool,
+        cache_elements: bool,
+    ) -> Result<Self, GraphError> {
+        let graph_runner: GraphRunnerGPU =
+            GraphRunnerGPU::new(gpu_handles, graph_operators, fuse_operators, cache_elements)?;
+        validate_batch_independence(graph_operators)?;
+
+        Ok(CompiledGraphGPU { graph_runner })
+    }
+
+    pub fn graph_runner(&self) -> &GraphRunnerGPU {
+        &self.graph_runner
+    }
+
+    pub async fn run(
+        &mut self,
+        gpu_handles: &GPUHandles,
+        input_batch: &Tensor2D,
+    ) -> Result<Tensor2D, GraphError> {
+        self.graph_runner
+            .set_input_batch(gpu_handles, input_batch)?;
+        Ok(trim_output(self.graph_runner.run(gpu_handles, 1).await?))
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        graph::{
+            compiled_graph::{validate_batch_independence, CompiledGraph},
+            fusion::OperatorKind,
+            graph_error::GraphError,
+            graph_runner::GraphRunner,
+            quantization::quantize_graph_operators,
+        },
+        shared::{
+            graph_operators::GraphOperator, quantized_tensor2d::QuantizationGranularity,
+            tensor2d::Tensor2D, tensor2d_test_utilities::random_tensor,
+        },
+    };
+
+    const ERROR_TOLERANCE: f32 = 0.00001;
+
+    // A bias with the same random row repeated row_count times
+    fn random_bias(rng: &mut ChaCha8Rng, row_count: usize, column_count: usize) -> Tensor2D {
+        let row: Tensor2D = random_tensor(rng, 1, column_count);
+        let mut bias: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+        for row_index in 0..row_count {
+            bias.data[row_index * column_count..(row_index + 1) * column_count]
+                .copy_from_slice(&row.data);
+        }
+        bias
+    }
+
+    // The same weights and bias values for every batch size. The residual connection
+    // makes sure the named buffers are resized as well.
+    fn residual_graph(input: Tensor2D) -> Vec<GraphOperator> {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(9);
+        let row_count: usize = input.row_count;
+        vec![
+            GraphOperator::HostToDevice { input },
+            GraphOperator::Store {
+                name: String::from("residual"),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(&mut rng, 4, 4),
+                bias: random_bias(&mut rng, row_count, 4),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Add {
+                name: String::from("residual"),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(&mut rng, 4, 3),
+                bias: random_bias(&mut rng, row_count, 3),
+            },
+            GraphOperator::Softmax,
+            GraphOperator::DeviceToHost,
+        ]
+    }
+
+    fn assert_close(expected: &Tensor2D, output: &Tensor2D) {
+        assert_eq!(
+            (expected.row_count, expected.column_count),
+            (output.row_count, output.column_count)
+        );
+        assert_eq!(output.data.len(), output.len());
+        for index in 0..expected.len() {
+            assert!((expected.data[index] - output.data[index]).abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn varying_batch() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(10);
+        for fuse_operators in [false, true] {
+            let mut compiled_graph: CompiledGraph = CompiledGraph::compile(
+                &residual_graph(Tensor2D::new(0.0, 4, 4)),
+                fuse_operators,
+                false,
+            )
+            .unwrap();
+
+            // Shrinking reuses the buffers, growing past the first batch reallocates them
+            for row_count in [4, 1, 7, 2, 7] {
+                let input: Tensor2D = random_tensor(&mut rng, row_count, 4);
+                let expected: Tensor2D =
+                    GraphRunner::new(&residual_graph(input.clone()), fuse_operators, false)
+                        .unwrap()
+                        .run()
+                        .unwrap();
+                let output: Tensor2D = compiled_graph.run(&input).unwrap();
+                assert_close(&expected, &output);
+            }
+        }
+    }
+
+    #[test]
+    fn quantized_batch() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(11);
+        let quantize = |input: Tensor2D| -> Vec<GraphOperator> {
+            quantize_graph_operators(
+                &residual_graph(input),
+                &[],
+                QuantizationGranularity::PerChannel,
+            )
+        };
+
+        // The quantization parameters are calibrated on the input the graph is compiled with
+        let calibration_input: Tensor2D = random_tensor(&mut rng, 2, 4);
+        let mut compiled_graph: CompiledGraph =
+            CompiledGraph::compile(&quantize(calibration_input.clone()), true, false).unwrap();
+        let compiled_operators: Vec<GraphOperator> = quantize(calibration_input);
+
+        for row_count in [2, 5, 1] {
+            let input: Tensor2D = random_tensor(&mut rng, row_count, 4);
+            // The same quantized weights with the input swapped out
+            let mut graph_operators: Vec<GraphOperator> = compiled_operators.clone();
+            graph_operators[0] = GraphOperator::HostToDevice {
+                input: input.clone(),
+            };
+            for operator in &mut graph_operators {
+                if let GraphOperator::Linear { bias, .. } | GraphOperator::LinearInt8 { bias, .. } =
+                    operator
+                {
+                    let column_count: usize = bias.column_count;
+                    let mut batch_bias: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+                    for row_index in 0..row_count {
+                        batch_bias.data[row_index * column_count..(row_index + 1) * column_count]
+                            .copy_from_slice(&bias.data[0..column_count]);
+                    }
+                    *bias = batch_bias;
+                }
+            }
+
+            let expected: Tensor2D = GraphRunner::new(&graph_operators, true, false)
+                .unwrap()
+                .run()
+                .unwrap();
+            let output: Tensor2D = compiled_graph.run(&input).unwrap();
+            assert_close(&expected, &output);
+        }
+    }
+
+    #[test]
+    fn batch_errors() {
+        let mut graph_operators: Vec<GraphOperator> = residual_graph(Tensor2D::new(0.0, 3, 4));
+        assert!(validate_batch_independence(&graph_operators).is_ok());
+
+        let mut compiled_graph: CompiledGraph =
+            CompiledGraph::compile(&graph_operators, false, false).unwrap();
+        assert_eq!(
+            compiled_graph.run(&Tensor2D::new(0.0, 3, 5)).err(),
+            Some(GraphError::DimensionMismatch {
+                node: 0,
+                expected: (3, 4),
+                found: (3, 5),
+            })
+        );
+
+        if let GraphOperator::Linear { bias, .. } = &mut graph_operators[5] {
+            bias.data[bias.column_count] += 1.0;
+        }
+        assert_eq!(
+            CompiledGraph::compile(&graph_operators, false, false).err(),
+            Some(GraphError::BatchDependentBias { node: 5 })
+        );
+
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(0.0, 3, 4),
+            },
+            GraphOperator::MeanSquaredError {
+                target: Tensor2D::new(0.0, 3, 4),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+        assert_eq!(
+            validate_batch_independence(&graph_operators),
+            Err(GraphError::UnsupportedOperator {
+                node: 1,
+                operator: OperatorKind::MeanSquaredError,
+            })
+        );
+    }
+}
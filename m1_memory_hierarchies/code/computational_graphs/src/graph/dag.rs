@@ -0,0 +1,309 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use crate::shared::{graph_operators::GraphOperator, tensor2d::Tensor2D};
+
+use super::{fusion::OperatorKind, graph_error::GraphError};
+
+// The flat Vec<GraphOperator> implicitly feeds the output of every operator into the next.
+// That is a fine way to describe a chain, but not a ResNet-style block, where the input
+// of the block is added to the output of the block. Here every node instead has a name
+// and lists the names of its inputs, which makes the graph a DAG.
+// The runners still work on the flat list, so the DAG is scheduled in topological order
+// and lowered into a Vec<GraphOperator>, with the Store, Load, Add and Concat operators
+// taking care of everything which isn't just the output of the previous operator.
+#[derive(Clone, Debug)]
+pub enum DagOperator {
+    // The input of the graph, becomes HostToDevice. Takes no inputs.
+    Input { input: Tensor2D },
+    // Any of the GraphOperators consuming a single tensor, such as Linear, ReLU or a loss
+    Unary(GraphOperator),
+    // Elementwise sum of all of the inputs
+    Add,
+    // The columns of the inputs, in the order they are listed
+    Concat,
+}
+
+#[derive(Clone, Debug)]
+pub struct DagNode {
+    pub name: String,
+    pub operator: DagOperator,
+    pub inputs: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DagGraph {
+    pub nodes: Vec<DagNode>,
+    // The name of the node which is transferred back to the host
+    pub output: String,
+}
+
+impl DagGraph {
+    pub fn new(output: &str) -> Self {
+        DagGraph {
+            nodes: Vec::<DagNode>::new(),
+            output: output.to_string(),
+        }
+    }
+
+    // The nodes can be added in any order, they are sorted when lowering the graph
+    pub fn add_node(&mut self, name: &str, operator: DagOperator, inputs: &[&str]) {
+        self.nodes.push(DagNode {
+            name: name.to_string(),
+            operator,
+            inputs: inputs.iter().map(|input| input.to_string()).collect(),
+        });
+    }
+
+    fn node_indices(&self) -> HashMap<&str, usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.name.as_str(), index))
+            .collect()
+    }
+
+    // The indices of every node the output depends on, including the output itself.
+    // Nodes the output doesn't depend on are never run.
+    fn live_nodes(&self, node_indices: &HashMap<&str, usize>) -> HashSet<usize> {
+        let mut live: HashSet<usize> = HashSet::<usize>::new();
+        let mut stack: Vec<usize> = node_indices
+            .get(self.output.as_str())
+            .into_iter()
+            .copied()
+            .collect();
+
+        while let Some(index) = stack.pop() {
+            if live.insert(index) {
+                for input in &self.nodes[index].inputs {
+                    stack.extend(node_indices.get(input.as_str()));
+                }
+            }
+        }
+
+        live
+    }
+
+    // Kahn's algorithm. Every node can be scheduled once all of its inputs have been.
+    // Among the nodes ready to be scheduled, the one added first is picked, which makes
+    // the order deterministic. Returns None if the graph has a cycle.
+    pub fn topological_order(&self) -> Option<Vec<usize>> {
+        let node_indices: HashMap<&str, usize> = self.node_indices();
+        let live: HashSet<usize> = self.live_nodes(&node_indices);
+
+        let mut missing_input_counts: Vec<usize> = vec![0; self.nodes.len()];
+        let mut consumers: Vec<Vec<usize>> = vec![Vec::<usize>::new(); self.nodes.len()];
+        for index in live.iter().copied() {
+            for input in &self.nodes[index].inputs {
+                let input_index: usize = node_indices[input.as_str()];
+                missing_input_counts[index] += 1;
+                consumers[input_index].push(index);
+            }
+        }
+
+        let mut ready: BinaryHeap<Reverse<usize>> = live
+            .iter()
+            .filter(|index| missing_input_counts[**index] == 0)
+            .map(|index| Reverse(*index))
+            .collect();
+
+        let mut order: Vec<usize> = Vec::<usize>::with_capacity(live.len());
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(index);
+            for consumer in &consumers[index] {
+                missing_input_counts[*consumer] -= 1;
+                if missing_input_counts[*consumer] == 0 {
+                    ready.push(Reverse(*consumer));
+                }
+            }
+        }
+
+        if order.len() == live.len() {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    // Returns the first problem found. The dimensions are validated after lowering,
+    // by validate_graph_operators.
+    pub fn validate(&self) -> Result<(), GraphError> {
+        let node_indices: HashMap<&str, usize> = self.node_indices();
+        if node_indices.len() != self.nodes.len() {
+            return Err(GraphError::InvalidDag {
+                message: "every node has to have a unique name".to_string(),
+            });
+        }
+
+        if !node_indices.contains_key(self.output.as_str()) {
+            return Err(GraphError::InvalidDag {
+                message: format!("the output {} is not a node in the graph", self.output),
+            });
+        }
+
+        let mut input_node_count: usize = 0;
+        for node in &self.nodes {
+            if let Some(input) = node
+                .inputs
+                .iter()
+                .find(|input| !node_indices.contains_key(input.as_str()))
+            {
+                return Err(GraphError::UnknownInput {
+                    node: node.name.clone(),
+                    input: input.clone(),
+                });
+            }
+
+            let valid_input_count: bool = match &node.operator {
+                DagOperator::Input { .. } => {
+                    input_node_count += 1;
+                    node.inputs.is_empty()
+                }
+                DagOperator::Unary(operator) => {
+                    if matches!(
+                        operator,
+                        GraphOperator::Empty
+                            | GraphOperator::HostToDevice { .. }
+                            | GraphOperator::DeviceToHost
+                            | GraphOperator::Store { .. }
+                            | GraphOperator::Load { .. }
+                            | GraphOperator::Add { .. }
+                            | GraphOperator::Concat { .. }
+                    ) {
+                        return Err(GraphError::InvalidDag {
+                            message: format!(
+                                "the node {} has the operator {:?}, which is handled by the DAG itself",
+                                node.name,
+                                OperatorKind::of(operator)
+                            ),
+                        });
+                    }
+                    node.inputs.len() == 1
+                }
+                DagOperator::Add | DagOperator::Concat => 2 <= node.inputs.len(),
+            };
+
+            if !valid_input_count {
+                return Err(GraphError::InvalidDag {
+                    message: format!(
+                        "the node {} has {} inputs, which is invalid for its operator",
+                        node.name,
+                        node.inputs.len()
+                    ),
+                });
+            }
+        }
+
+        // Our graphs only support a single transfer to the device
+        if input_node_count != 1 {
+            return Err(GraphError::InvalidDag {
+                message: format!(
+                    "expected exactly one input node, found {}",
+                    input_node_count
+                ),
+            });
+        }
+
+        if self.topological_order().is_none() {
+            return Err(GraphError::CyclicGraph);
+        }
+
+        Ok(())
+    }
+
+    // Schedules the nodes in topological order and turns them into a Vec<GraphOperator>.
+    // Returns the first problem found by validate if the graph is invalid.
+    // Whenever a node's first input isn't the output of the node scheduled right before it,
+    // it is loaded by name. Every other input is referenced by name in Add or Concat.
+    // Only the tensors referenced by name are stored, which keeps chains of operators
+    // next to each other, so GraphRunner can still fuse them.
+    pub fn to_graph_operators(&self) -> Result<Vec<GraphOperator>, GraphError> {
+        self.validate()?;
+
+        let order: Vec<usize> = self.topological_order().ok_or(GraphError::CyclicGraph)?;
+
+        // Every operator is paired with the name of the node it produces, if any
+        let mut scheduled: Vec<(Option<&str>, GraphOperator)> =
+            Vec::<(Option<&str>, GraphOperator)>::new();
+        let mut referenced: HashSet<&str> = HashSet::<&str>::new();
+        let mut current: Option<&str> = None;
+
+        for index in order {
+            let node: &DagNode = &self.nodes[index];
+
+            if let Some(first_input) = node.inputs.first() {
+                if current != Some(first_input.as_str()) {
+                    referenced.insert(first_input);
+                    scheduled.push((
+                        None,
+                        GraphOperator::Load {
+                            name: first_input.clone(),
+                        },
+                    ));
+                }
+            }
+
+            match &node.operator {
+                DagOperator::Input { input } => {
+                    scheduled.push((
+                        Some(&node.name),
+                        GraphOperator::HostToDevice {
+                            input: input.clone(),
+                        },
+                    ));
+                }
+                DagOperator::Unary(operator) => {
+                    scheduled.push((Some(&node.name), operator.clone()));
+                }
+                DagOperator::Add | DagOperator::Concat => {
+                    let last_index: usize = node.inputs.len() - 1;
+                    for (input_index, input) in node.inputs.iter().enumerate().skip(1) {
+                        referenced.insert(input);
+                        let name: String = input.clone();
+                        let operator: GraphOperator = if let DagOperator::Add = node.operator {
+                            GraphOperator::Add { name }
+                        } else {
+                            GraphOperator::Concat { name }
+                        };
+
+                        // Only the last operator produces the node
+                        let produced: Option<&str> = if input_index == last_index {
+                            Some(&node.name)
+                        } else {
+                            None
+                        };
+                        scheduled.push((produced, operator));
+                    }
+                }
+            }
+
+            current = Some(&node.name);
+        }
+
+        if current != Some(self.output.as_str()) {
+            referenced.insert(&self.output);
+            scheduled.push((
+                None,
+                GraphOperator::Load {
+                    name: self.output.clone(),
+                },
+            ));
+        }
+
+        let mut graph_operators: Vec<GraphOperator> =
+            Vec::<GraphOperator>::with_capacity(scheduled.len() + referenced.len() + 1);
+        for (produced, operator) in scheduled {
+            graph_operators.push(operator);
+            if let Some(name) = produced.filter(|name| referenced.contains(name)) {
+                graph_operators.push(GraphOperator::Store {
+                    name: name.to_string(),
+                });
+            }
+        }
+        graph_operators.push(GraphOperator::DeviceToHost);
+
+        Ok(graph_operators)
+    }
+}
@@ -0,0 +1,311 @@
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        graph::{
+            dag::{DagGraph, DagOperator},
+            graph_error::GraphError,
+            graph_runner::GraphRunner,
+            graph_validation::validate_graph_operators,
+        },
+        shared::{
+            graph_operators::GraphOperator, tensor2d::Tensor2D,
+            tensor2d_test_utilities::random_tensor,
+        },
+    };
+
+    const ERROR_TOLERANCE: f32 = 0.00001;
+
+    fn assert_tensors_match(expected: &Tensor2D, found: &Tensor2D) {
+        assert_eq!(expected.row_count, found.row_count);
+        assert_eq!(expected.column_count, found.column_count);
+        for index in 0..expected.len() {
+            assert!(
+                (expected.data[index] - found.data[index]).abs() < ERROR_TOLERANCE,
+                "index: {} expected: {} found: {}",
+                index,
+                expected.data[index],
+                found.data[index]
+            );
+        }
+    }
+
+    // x -> linear_0 -> relu_0 -> linear_1 -> add(linear_1, x) -> relu_1
+    // The nodes are added in the order given by node_order, which shouldn't matter.
+    fn residual_block(rng: &mut ChaCha8Rng, node_order: &[usize]) -> (DagGraph, Tensor2D) {
+        let input: Tensor2D = random_tensor(rng, 3, 4);
+        let weights_0: Tensor2D = random_tensor(rng, 4, 4);
+        let bias_0: Tensor2D = random_tensor(rng, 3, 4);
+        let weights_1: Tensor2D = random_tensor(rng, 4, 4);
+        let bias_1: Tensor2D = random_tensor(rng, 3, 4);
+
+        let hidden: Tensor2D = Tensor2D::relu(&Tensor2D::linear(&input, &weights_0, &bias_0));
+        let block: Tensor2D = Tensor2D::linear(&hidden, &weights_1, &bias_1);
+        let mut sum: Tensor2D = Tensor2D::new(0.0, 3, 4);
+        Tensor2D::add_preallocated(&block, &input, &mut sum);
+        let expected: Tensor2D = Tensor2D::relu(&sum);
+
+        let nodes: Vec<(&str, DagOperator, Vec<&str>)> = vec![
+            ("x", DagOperator::Input { input }, vec![]),
+            (
+                "linear_0",
+                DagOperator::Unary(GraphOperator::Linear {
+                    weights: weights_0,
+                    bias: bias_0,
+                }),
+                vec!["x"],
+            ),
+            (
+                "relu_0",
+                DagOperator::Unary(GraphOperator::ReLU),
+                vec!["linear_0"],
+            ),
+            (
+                "linear_1",
+                DagOperator::Unary(GraphOperator::Linear {
+                    weights: weights_1,
+                    bias: bias_1,
+                }),
+                vec!["relu_0"],
+            ),
+            ("add", DagOperator::Add, vec!["linear_1", "x"]),
+            (
+                "relu_1",
+                DagOperator::Unary(GraphOperator::ReLU),
+                vec!["add"],
+            ),
+        ];
+
+        let mut dag: DagGraph = DagGraph::new("relu_1");
+        for index in node_order {
+            let (name, operator, inputs) = &nodes[*index];
+            dag.add_node(name, operator.clone(), inputs);
+        }
+
+        (dag, expected)
+    }
+
+    #[test]
+    fn residual() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(0);
+        let (dag, expected) = residual_block(&mut rng, &[0, 1, 2, 3, 4, 5]);
+        assert_eq!(dag.validate(), Ok(()));
+
+        let graph_operators: Vec<GraphOperator> = dag.to_graph_operators().unwrap();
+        assert!(validate_graph_operators(&graph_operators).is_ok());
+
+        // Only x is used by name, everything else is a chain
+        let store_count: usize = graph_operators
+            .iter()
+            .filter(|operator| matches!(operator, GraphOperator::Store { .. }))
+            .count();
+        assert_eq!(store_count, 1);
+
+        for fuse_operators in [false, true] {
+            let mut graph_runner: GraphRunner =
+                GraphRunner::from_dag(&dag, fuse_operators, false).unwrap();
+            assert_tensors_match(&expected, &graph_runner.run().unwrap());
+        }
+    }
+
+    #[test]
+    fn topological_order() {
+        let orders: [[usize; 6]; 3] = [[0, 1, 2, 3, 4, 5], [5, 4, 3, 2, 1, 0], [3, 5, 0, 4, 2, 1]];
+        for order in orders {
+            let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(1);
+            let (dag, expected) = residual_block(&mut rng, &order);
+
+            let schedule: Vec<usize> = dag.topological_order().unwrap();
+            for (position, index) in schedule.iter().enumerate() {
+                for input in &dag.nodes[*index].inputs {
+                    let input_index: usize = dag
+                        .nodes
+                        .iter()
+                        .position(|node| node.name == *input)
+                        .unwrap();
+                    assert!(schedule[0..position].contains(&input_index));
+                }
+            }
+
+            let mut graph_runner: GraphRunner = GraphRunner::from_dag(&dag, true, false).unwrap();
+            assert_tensors_match(&expected, &graph_runner.run().unwrap());
+        }
+    }
+
+    // Two branches from the same input, concatenated and then projected back down,
+    // with the output of one branch reused by name after the concatenation.
+    #[test]
+    fn concat() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(2);
+        let input: Tensor2D = random_tensor(&mut rng, 5, 3);
+        let weights_a: Tensor2D = random_tensor(&mut rng, 3, 2);
+        let bias_a: Tensor2D = random_tensor(&mut rng, 5, 2);
+        let weights_b: Tensor2D = random_tensor(&mut rng, 3, 4);
+        let bias_b: Tensor2D = random_tensor(&mut rng, 5, 4);
+        let weights_c: Tensor2D = random_tensor(&mut rng, 6, 2);
+        let bias_c: Tensor2D = random_tensor(&mut rng, 5, 2);
+
+        let branch_a: Tensor2D = Tensor2D::linear(&input, &weights_a, &bias_a);
+        let branch_b: Tensor2D = Tensor2D::relu(&Tensor2D::linear(&input, &weights_b, &bias_b));
+        let mut concatenated: Tensor2D = Tensor2D::new(0.0, 5, 6);
+        Tensor2D::concat_columns_preallocated(&branch_a, &branch_b, &mut concatenated);
+        let projected: Tensor2D = Tensor2D::linear(&concatenated, &weights_c, &bias_c);
+        let mut expected: Tensor2D = Tensor2D::new(0.0, 5, 2);
+        Tensor2D::add_preallocated(&projected, &branch_a, &mut expected);
+
+        let mut dag: DagGraph = DagGraph::new("output");
+        dag.add_node("x", DagOperator::Input { input }, &[]);
+        dag.add_node(
+            "a",
+            DagOperator::Unary(GraphOperator::Linear {
+                weights: weights_a,
+                bias: bias_a,
+            }),
+            &["x"],
+        );
+        dag.add_node(
+            "b_linear",
+            DagOperator::Unary(GraphOperator::Linear {
+                weights: weights_b,
+                bias: bias_b,
+            }),
+            &["x"],
+        );
+        dag.add_node("b", DagOperator::Unary(GraphOperator::ReLU), &["b_linear"]);
+        dag.add_node("concat", DagOperator::Concat, &["a", "b"]);
+        dag.add_node(
+            "projected",
+            DagOperator::Unary(GraphOperator::Linear {
+                weights: weights_c,
+                bias: bias_c,
+            }),
+            &["concat"],
+        );
+        dag.add_node("output", DagOperator::Add, &["projected", "a"]);
+
+        // Nodes the output doesn't depend on are skipped
+        dag.add_node("unused", DagOperator::Unary(GraphOperator::ReLU), &["x"]);
+        assert_eq!(dag.topological_order().unwrap().len(), 7);
+
+        for fuse_operators in [false, true] {
+            let mut graph_runner: GraphRunner =
+                GraphRunner::from_dag(&dag, fuse_operators, false).unwrap();
+            assert_tensors_match(&expected, &graph_runner.run().unwrap());
+        }
+    }
+
+    #[test]
+    fn validation() {
+        let mut dag: DagGraph = DagGraph::new("relu");
+        dag.add_node(
+            "x",
+            DagOperator::Input {
+                input: Tensor2D::new(1.0, 2, 2),
+            },
+            &[],
+        );
+        dag.add_node(
+            "relu",
+            DagOperator::Unary(GraphOperator::ReLU),
+            &["missing"],
+        );
+        assert_eq!(
+            dag.validate(),
+            Err(GraphError::UnknownInput {
+                node: "relu".to_string(),
+                input: "missing".to_string(),
+            })
+        );
+
+        // A cycle
+        let mut dag: DagGraph = DagGraph::new("b");
+        dag.add_node(
+            "x",
+            DagOperator::Input {
+                input: Tensor2D::new(1.0, 2, 2),
+            },
+            &[],
+        );
+        dag.add_node("a", DagOperator::Add, &["x", "b"]);
+        dag.add_node("b", DagOperator::Unary(GraphOperator::ReLU), &["a"]);
+        assert_eq!(dag.validate(), Err(GraphError::CyclicGraph));
+        assert!(matches!(
+            dag.to_graph_operators(),
+            Err(GraphError::CyclicGraph)
+        ));
+        assert!(matches!(
+            GraphRunner::from_dag(&dag, false, false),
+            Err(GraphError::CyclicGraph)
+        ));
+
+        // Add with a single input
+        let mut dag: DagGraph = DagGraph::new("a");
+        dag.add_node(
+            "x",
+            DagOperator::Input {
+                input: Tensor2D::new(1.0, 2, 2),
+            },
+            &[],
+        );
+        dag.add_node("a", DagOperator::Add, &["x"]);
+        assert!(matches!(dag.validate(), Err(GraphError::InvalidDag { .. })));
+
+        // The flat graph uses a name before it is stored
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(1.0, 2, 2),
+            },
+            GraphOperator::Add {
+                name: "x".to_string(),
+            },
+            GraphOperator::Store {
+                name: "x".to_string(),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+        assert!(validate_graph_operators(&graph_operators).is_err());
+
+        // Mismatched dimensions for add, but not for concat
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(1.0, 2, 2),
+            },
+            GraphOperator::Store {
+                name: "x".to_string(),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(1.0, 2, 3),
+                bias: Tensor2D::new(1.0, 2, 3),
+            },
+            GraphOperator::Add {
+                name: "x".to_string(),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+        assert!(validate_graph_operators(&graph_operators).is_err());
+
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(1.0, 2, 2),
+            },
+            GraphOperator::Store {
+                name: "x".to_string(),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(1.0, 2, 3),
+                bias: Tensor2D::new(1.0, 2, 3),
+            },
+            GraphOperator::Concat {
+                name: "x".to_string(),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(1.0, 5, 1),
+                bias: Tensor2D::new(1.0, 2, 1),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+        assert!(validate_graph_operators(&graph_operators).is_ok());
+    }
+}
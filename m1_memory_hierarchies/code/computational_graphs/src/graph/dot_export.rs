@@ -0,0 +1,211 @@
+use std::fmt::Write;
+
+use crate::shared::{
+    quantized_tensor2d::QuantizedTensor2D, tensor2d::Tensor2D, tensor2d_gpu::Tensor2DGPU,
+};
+
+use super::{
+    graph_runner::GraphRunner,
+    nodes::{Node, NodeOperator},
+};
+
+// Graphviz export of the nodes built by GraphRunner and GraphRunnerGPU, which makes it a lot
+// easier to compare a fused graph with an unfused one than reading the debug prints.
+// Every node is drawn as a box and every buffer as an ellipse with its index and its shape.
+// The edges go from the buffers a node reads to the node and from the node to the buffer
+// it writes, which is always the last of its buffer indices. The quantized nodes also write
+// the quantized copy of their input before reading it. The transfer nodes only hand
+// their buffer on to the next node, so they are drawn dashed, and the fused operators are
+// filled. An operator fused by hand before being given to the runner is filled as well.
+//
+// Render with: dot -Tsvg graph.dot -o graph.svg
+//
+// After plan_memory the buffers are shared between nodes, so the shapes are those of the
+// shared buffers and a buffer can be written by more than one node.
+
+const FUSED_COLOR: &str = "lightblue";
+const QUANTIZED_COLOR: &str = "lightyellow";
+
+struct DotBuffer {
+    id: String,
+    label: String,
+    quantized: bool,
+}
+
+struct DotNode {
+    name: String,
+    operator: String,
+    fused: bool,
+    transfer: bool,
+    reads: Vec<String>,
+    writes: Vec<String>,
+}
+
+fn data_buffer_id(index: usize) -> String {
+    format!("buffer_{}", index)
+}
+
+fn quantized_buffer_id(index: usize) -> String {
+    format!("quantized_buffer_{}", index)
+}
+
+// The graph name is the only text in the file which isn't generated by the runners
+fn escape_quoted(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn write_dot(graph_name: &str, buffers: &[DotBuffer], nodes: &[DotNode]) -> String {
+    let mut dot: String = String::new();
+    // Writing to a String can't fail
+    writeln!(dot, "digraph \"{}\" {{", escape_quoted(graph_name)).unwrap();
+    writeln!(dot, "    rankdir=TB;").unwrap();
+
+    for buffer in buffers {
+        if buffer.quantized {
+            writeln!(
+                dot,
+                "    \"{}\" [shape=ellipse, style=filled, fillcolor={}, label=\"{}\"];",
+                buffer.id, QUANTIZED_COLOR, buffer.label
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                dot,
+                "    \"{}\" [shape=ellipse, label=\"{}\"];",
+                buffer.id, buffer.label
+            )
+            .unwrap();
+        }
+    }
+
+    for node in nodes {
+        let style: String = if node.fused {
+            format!("style=filled, fillcolor={}, ", FUSED_COLOR)
+        } else if node.transfer {
+            String::from("style=dashed, ")
+        } else {
+            String::new()
+        };
+        writeln!(
+            dot,
+            "    \"{}\" [shape=box, {}label=\"{}\\n{}\"];",
+            node.name, style, node.name, node.operator
+        )
+        .unwrap();
+        for read in &node.reads {
+            writeln!(dot, "    \"{}\" -> \"{}\";", read, node.name).unwrap();
+        }
+        for write in &node.writes {
+            writeln!(dot, "    \"{}\" -> \"{}\";", node.name, write).unwrap();
+        }
+    }
+
+    writeln!(dot, "}}").unwrap();
+    dot
+}
+
+pub fn nodes_to_dot(
+    graph_name: &str,
+    nodes: &[Node],
+    data_buffers: &[Tensor2D],
+    quantized_buffers: &[QuantizedTensor2D],
+) -> String {
+    let mut buffers: Vec<DotBuffer> = data_buffers
+        .iter()
+        .enumerate()
+        .map(|(index, buffer)| DotBuffer {
+            id: data_buffer_id(index),
+            label: format!("{}: {}x{}", index, buffer.row_count, buffer.column_count),
+            quantized: false,
+        })
+        .collect();
+    buffers.extend(
+        quantized_buffers
+            .iter()
+            .enumerate()
+            .map(|(index, buffer)| DotBuffer {
+                id: quantized_buffer_id(index),
+                label: format!(
+                    "i8 {}: {}x{}",
+                    index, buffer.tensor.row_count, buffer.tensor.column_count
+                ),
+                quantized: true,
+            }),
+    );
+
+    let dot_nodes: Vec<DotNode> = nodes
+        .iter()
+        .map(|node| {
+            let data_positions: Vec<usize> = GraphRunner::data_buffer_positions(node);
+            let mut reads: Vec<String> = (0..node.buffer_indices.len())
+                .map(|position| {
+                    if data_positions.contains(&position) {
+                        data_buffer_id(node.buffer_indices[position])
+                    } else {
+                        quantized_buffer_id(node.buffer_indices[position])
+                    }
+                })
+                .collect();
+            let writes: Vec<String> = match node.operator {
+                NodeOperator::Output | NodeOperator::Transfer => vec![],
+                NodeOperator::Input => std::mem::take(&mut reads),
+                // The quantized input is at position 1, see nodes::quantized_linear_buffers
+                NodeOperator::LinearInt8 | NodeOperator::LinearReLUInt8 => {
+                    let output: String = reads.pop().unwrap();
+                    vec![reads.remove(1), output]
+                }
+                _ => vec![reads.pop().unwrap()],
+            };
+
+            DotNode {
+                name: node.name.clone(),
+                operator: format!("{:?}", node.operator),
+                fused: node.operator.is_fused(),
+                transfer: node.operator == NodeOperator::Transfer,
+                reads,
+                writes,
+            }
+        })
+        .collect();
+
+    write_dot(graph_name, &buffers, &dot_nodes)
+}
+
+pub fn nodes_gpu_to_dot(graph_name: &str, nodes: &[Node], data_buffers: &[Tensor2DGPU]) -> String {
+    let buffers: Vec<DotBuffer> = data_buffers
+        .iter()
+        .enumerate()
+        .map(|(index, buffer)| DotBuffer {
+            id: data_buffer_id(index),
+            label: format!("{}: {}x{}", index, buffer.row_count, buffer.column_count),
+            quantized: false,
+        })
+        .collect();
+
+    let dot_nodes: Vec<DotNode> = nodes
+        .iter()
+        .map(|node| {
+            let mut reads: Vec<String> = node
+                .buffer_indices
+                .iter()
+                .map(|index| data_buffer_id(*index))
+                .collect();
+            let writes: Vec<String> = match node.operator {
+                NodeOperator::Output | NodeOperator::Transfer => vec![],
+                NodeOperator::Input => std::mem::take(&mut reads),
+                _ => vec![reads.pop().unwrap()],
+            };
+
+            DotNode {
+                name: node.name.clone(),
+                operator: format!("{:?}", node.operator),
+                fused: node.operator.is_fused(),
+                transfer: node.operator == NodeOperator::Transfer,
+                reads,
+                writes,
+            }
+        })
+        .collect();
+
+    write_dot(graph_name, &buffers, &dot_nodes)
+}
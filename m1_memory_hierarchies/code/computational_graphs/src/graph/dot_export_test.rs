@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        graph::{graph_runner::GraphRunner, quantization::quantize_graph_operators},
+        shared::{
+            graph_operators::GraphOperator, quantized_tensor2d::QuantizationGranularity,
+            tensor2d::Tensor2D,
+        },
+    };
+
+    fn graph_operators() -> Vec<GraphOperator> {
+        vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(0.5, 2, 3),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(0.1, 3, 4),
+                bias: Tensor2D::new(0.0, 2, 4),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::DeviceToHost,
+        ]
+    }
+
+    #[test]
+    fn unfused() {
+        let graph_runner: GraphRunner = GraphRunner::new(&graph_operators(), false, false).unwrap();
+        let dot: String = graph_runner.to_dot("unfused");
+
+        assert!(dot.starts_with("digraph \"unfused\" {"));
+        assert!(dot.trim_end().ends_with('}'));
+        assert!(dot.contains("\"buffer_0\" [shape=ellipse, label=\"0: 2x3\"];"));
+        assert!(dot.contains("\"buffer_3\" [shape=ellipse, label=\"3: 2x4\"];"));
+
+        // The input, the weights and the bias are read, the output is written
+        assert!(dot.contains("\"Input_0\" -> \"buffer_0\";"));
+        for buffer_index in 0..3 {
+            assert!(dot.contains(&format!("\"buffer_{}\" -> \"Linear_0\";", buffer_index)));
+        }
+        assert!(dot.contains("\"Linear_0\" -> \"buffer_3\";"));
+        assert!(dot.contains("\"buffer_3\" -> \"ReLU_0\";"));
+        assert!(dot.contains("\"ReLU_0\" -> \"buffer_4\";"));
+        assert!(dot.contains("\"buffer_4\" -> \"Output_0\";"));
+
+        assert!(dot.contains("\"Transfer_0\" [shape=box, style=dashed,"));
+        assert!(!dot.contains("fillcolor"));
+    }
+
+    #[test]
+    fn fused() {
+        let graph_runner: GraphRunner = GraphRunner::new(&graph_operators(), true, false).unwrap();
+        let dot: String = graph_runner.to_dot("fused");
+
+        assert!(dot.contains(
+            "\"LinearReLU_0\" [shape=box, style=filled, fillcolor=lightblue, \
+             label=\"LinearReLU_0\\nLinearReLU\"];"
+        ));
+        assert!(dot.contains("\"LinearReLU_0\" -> \"buffer_3\";"));
+        assert!(dot.contains("\"buffer_3\" -> \"Output_0\";"));
+        assert!(!dot.contains("buffer_4"));
+        assert!(!dot.contains("ReLU_0\\nReLU\""));
+    }
+
+    #[test]
+    fn quantized() {
+        let quantized: Vec<GraphOperator> =
+            quantize_graph_operators(&graph_operators(), &[], QuantizationGranularity::PerTensor);
+        let graph_runner: GraphRunner = GraphRunner::new(&quantized, false, false).unwrap();
+        let dot: String = graph_runner.to_dot("quantized");
+
+        // The quantized input and the weights are in their own buffers.
+        // The quantized input is written by the node, the weights are read.
+        assert!(dot.contains("label=\"i8 0: 2x3\""));
+        assert!(dot.contains("label=\"i8 1: 3x4\""));
+        assert!(dot.contains("\"LinearInt8_0\" -> \"quantized_buffer_0\";"));
+        assert!(!dot.contains("\"quantized_buffer_0\" -> \"LinearInt8_0\";"));
+        assert!(dot.contains("\"quantized_buffer_1\" -> \"LinearInt8_0\";"));
+        assert!(dot.contains("\"buffer_0\" -> \"LinearInt8_0\";"));
+        assert!(dot.contains("\"buffer_1\" -> \"LinearInt8_0\";"));
+        assert!(dot.contains("\"LinearInt8_0\" -> \"buffer_2\";"));
+    }
+
+    #[test]
+    fn escaped_graph_name() {
+        let graph_runner: GraphRunner = GraphRunner::new(&graph_operators(), false, false).unwrap();
+        let dot: String = graph_runner.to_dot("a \"quoted\" C:\\graph");
+
+        assert!(dot.starts_with("digraph \"a \\\"quoted\\\" C:\\\\graph\" {"));
+    }
+}
@@ -0,0 +1,329 @@
+use std::fmt;
+
+use crate::shared::graph_operators::GraphOperator;
+
+// Operator fusion as a rewrite pass over the list of operators. Instead of the runners
+// peeking ahead for specific operators while building their nodes, every fusion is
+// described by a rule. A rule is a pattern, a sequence of operator kinds which have
+// to appear right after each other, and a rewrite which turns the matched operators
+// into a single fused operator. The runners only have to know how to run the fused
+// operators, not how to find them.
+//
+// The pass walks the operators from the start and tries the rules in order at every
+// position. When a rule fires, the fused operator replaces the matched operators and
+// the rules are tried again at the same position, which lets a fused operator be fused
+// again, such as an elementwise chain collapsing into a single operator. Every rule
+// replaces at least two operators by one, so the pass always terminates.
+//
+// A named tensor can't be fused away, but it doesn't have to be checked for. Store
+// is an operator of its own, so it always breaks up a pattern.
+
+// What a pattern matches against. The fields of the operators don't matter for matching.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OperatorKind {
+    Empty,
+    HostToDevice,
+    DeviceToHost,
+    Linear,
+    ReLU,
+    Softmax,
+    LinearReLU,
+    LinearReLUSoftmax,
+    MeanSquaredError,
+    SoftmaxCrossEntropy,
+    LinearInt8,
+    LinearReLUInt8,
+    Store,
+    Load,
+    Add,
+    Concat,
+    LinearAdd,
+    AddReLU,
+    Activation,
+    LinearActivation,
+}
+
+impl OperatorKind {
+    pub fn of(operator: &GraphOperator) -> Self {
+        match operator {
+            GraphOperator::Empty => OperatorKind::Empty,
+            GraphOperator::HostToDevice { .. } => OperatorKind::HostToDevice,
+            GraphOperator::DeviceToHost => OperatorKind::DeviceToHost,
+            GraphOperator::Linear { .. } => OperatorKind::Linear,
+            GraphOperator::ReLU => OperatorKind::ReLU,
+            GraphOperator::Softmax => OperatorKind::Softmax,
+            GraphOperator::LinearReLUFused { .. } => OperatorKind::LinearReLU,
+            GraphOperator::LinearReLUSoftmaxFused { .. } => OperatorKind::LinearReLUSoftmax,
+            GraphOperator::MeanSquaredError { .. } => OperatorKind::MeanSquaredError,
+            GraphOperator::SoftmaxCrossEntropy { .. } => OperatorKind::SoftmaxCrossEntropy,
+            GraphOperator::LinearInt8 { .. } => OperatorKind::LinearInt8,
+            GraphOperator::LinearReLUInt8Fused { .. } => OperatorKind::LinearReLUInt8,
+            GraphOperator::Store { .. } => OperatorKind::Store,
+            GraphOperator::Load { .. } => OperatorKind::Load,
+            GraphOperator::Add { .. } => OperatorKind::Add,
+            GraphOperator::Concat { .. } => OperatorKind::Concat,
+            GraphOperator::LinearAddFused { .. } => OperatorKind::LinearAdd,
+            GraphOperator::AddReLUFused { .. } => OperatorKind::AddReLU,
+            GraphOperator::Activation { .. } => OperatorKind::Activation,
+            GraphOperator::LinearActivationFused { .. } => OperatorKind::LinearActivation,
+        }
+    }
+}
+
+pub struct FusionRule {
+    pub name: &'static str,
+    pub pattern: &'static [OperatorKind],
+    // Receives exactly the operators matched by the pattern
+    pub rewrite: fn(&[GraphOperator]) -> GraphOperator,
+}
+
+impl FusionRule {
+    pub fn matches(&self, graph_operators: &[GraphOperator]) -> bool {
+        self.pattern.len() <= graph_operators.len()
+            && self
+                .pattern
+                .iter()
+                .zip(graph_operators.iter())
+                .all(|(kind, operator)| *kind == OperatorKind::of(operator))
+    }
+}
+
+// The rewrites below are only ever called with operators matching their pattern
+fn unexpected_operators(rule: &str, graph_operators: &[GraphOperator]) -> ! {
+    panic!(
+        "fusion::{} received operators not matching its pattern: {:?}",
+        rule, graph_operators
+    );
+}
+
+fn linear_relu_softmax(graph_operators: &[GraphOperator]) -> GraphOperator {
+    match &graph_operators[0] {
+        GraphOperator::Linear { weights, bias }
+        | GraphOperator::LinearReLUFused { weights, bias } => {
+            GraphOperator::LinearReLUSoftmaxFused {
+                weights: weights.clone(),
+                bias: bias.clone(),
+            }
+        }
+        _ => unexpected_operators("linear_relu_softmax", graph_operators),
+    }
+}
+
+fn linear_relu(graph_operators: &[GraphOperator]) -> GraphOperator {
+    match &graph_operators[0] {
+        GraphOperator::Linear { weights, bias } => GraphOperator::LinearReLUFused {
+            weights: weights.clone(),
+            bias: bias.clone(),
+        },
+        _ => unexpected_operators("linear_relu", graph_operators),
+    }
+}
+
+fn linear_relu_int8(graph_operators: &[GraphOperator]) -> GraphOperator {
+    match &graph_operators[0] {
+        GraphOperator::LinearInt8 {
+            weights,
+            bias,
+            input_parameters,
+        } => GraphOperator::LinearReLUInt8Fused {
+            weights: weights.clone(),
+            bias: bias.clone(),
+            input_parameters: *input_parameters,
+        },
+        _ => unexpected_operators("linear_relu_int8", graph_operators),
+    }
+}
+
+fn linear_add(graph_operators: &[GraphOperator]) -> GraphOperator {
+    match (&graph_operators[0], &graph_operators[1]) {
+        (GraphOperator::Linear { weights, bias }, GraphOperator::Add { name }) => {
+            GraphOperator::LinearAddFused {
+                weights: weights.clone(),
+                bias: bias.clone(),
+                name: name.clone(),
+            }
+        }
+        _ => unexpected_operators("linear_add", graph_operators),
+    }
+}
+
+fn add_relu(graph_operators: &[GraphOperator]) -> GraphOperator {
+    match &graph_operators[0] {
+        GraphOperator::Add { name } => GraphOperator::AddReLUFused { name: name.clone() },
+        _ => unexpected_operators("add_relu", graph_operators),
+    }
+}
+
+fn linear_activation(graph_operators: &[GraphOperator]) -> GraphOperator {
+    match (&graph_operators[0], &graph_operators[1]) {
+        (GraphOperator::Linear { weights, bias }, GraphOperator::Activation { function }) => {
+            GraphOperator::LinearActivationFused {
+                weights: weights.clone(),
+                bias: bias.clone(),
+                function: *function,
+            }
+        }
+        _ => unexpected_operators("linear_activation", graph_operators),
+    }
+}
+
+// ReLU(ReLU(x)) = ReLU(x), so a ReLU following an operator which ends in a ReLU does nothing
+fn drop_relu(graph_operators: &[GraphOperator]) -> GraphOperator {
+    graph_operators[0].clone()
+}
+
+// The rules used by the runners when fuse_operators is set. The longer patterns come
+// first, otherwise Linear->ReLU would always fire before Linear->ReLU->Softmax could.
+pub fn default_fusion_rules() -> Vec<FusionRule> {
+    vec![
+        FusionRule {
+            name: "LinearReLUSoftmax",
+            pattern: &[
+                OperatorKind::Linear,
+                OperatorKind::ReLU,
+                OperatorKind::Softmax,
+            ],
+            rewrite: linear_relu_softmax,
+        },
+        FusionRule {
+            name: "LinearReLU",
+            pattern: &[OperatorKind::Linear, OperatorKind::ReLU],
+            rewrite: linear_relu,
+        },
+        FusionRule {
+            name: "LinearReLUInt8",
+            pattern: &[OperatorKind::LinearInt8, OperatorKind::ReLU],
+            rewrite: linear_relu_int8,
+        },
+        FusionRule {
+            name: "LinearActivation",
+            pattern: &[OperatorKind::Linear, OperatorKind::Activation],
+            rewrite: linear_activation,
+        },
+        FusionRule {
+            name: "LinearAdd",
+            pattern: &[OperatorKind::Linear, OperatorKind::Add],
+            rewrite: linear_add,
+        },
+        FusionRule {
+            name: "AddReLU",
+            pattern: &[OperatorKind::Add, OperatorKind::ReLU],
+            rewrite: add_relu,
+        },
+        // Elementwise chains
+        FusionRule {
+            name: "ReLUReLU",
+            pattern: &[OperatorKind::ReLU, OperatorKind::ReLU],
+            rewrite: drop_relu,
+        },
+        FusionRule {
+            name: "LinearReLUReLU",
+            pattern: &[OperatorKind::LinearReLU, OperatorKind::ReLU],
+            rewrite: drop_relu,
+        },
+        FusionRule {
+            name: "LinearReLUInt8ReLU",
+            pattern: &[OperatorKind::LinearReLUInt8, OperatorKind::ReLU],
+            rewrite: drop_relu,
+        },
+        FusionRule {
+            name: "AddReLUReLU",
+            pattern: &[OperatorKind::AddReLU, OperatorKind::ReLU],
+            rewrite: drop_relu,
+        },
+        FusionRule {
+            name: "LinearReLUSoftmax",
+            pattern: &[OperatorKind::LinearReLU, OperatorKind::Softmax],
+            rewrite: linear_relu_softmax,
+        },
+    ]
+}
+
+// A rule which fired, and the index of the first operator it matched in the original graph
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fusion {
+    pub rule: &'static str,
+    pub operator_index: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FusionReport {
+    pub operator_count_before: usize,
+    pub operator_count_after: usize,
+    pub fusions: Vec<Fusion>,
+}
+
+impl FusionReport {
+    // The number of times the rule with the given name fired
+    pub fn count(&self, rule: &str) -> usize {
+        self.fusions
+            .iter()
+            .filter(|fusion| fusion.rule == rule)
+            .count()
+    }
+}
+
+impl fmt::Display for FusionReport {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "Operators: {} -> {} ({} fusions)",
+            self.operator_count_before,
+            self.operator_count_after,
+            self.fusions.len()
+        )?;
+        for fusion in &self.fusions {
+            write!(
+                formatter,
+                "\nFused {} at operator {}",
+                fusion.rule, fusion.operator_index
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn fuse_graph_operators(
+    graph_operators: &[GraphOperator],
+    rules: &[FusionRule],
+) -> (Vec<GraphOperator>, FusionReport) {
+    let mut fused: Vec<GraphOperator> = graph_operators.to_vec();
+    // The index in graph_operators of every operator in fused, used for the report
+    let mut original_indices: Vec<usize> = (0..graph_operators.len()).collect();
+    let mut report: FusionReport = FusionReport {
+        operator_count_before: graph_operators.len(),
+        ..Default::default()
+    };
+
+    let mut operator_index: usize = 0;
+    while operator_index < fused.len() {
+        let rule: Option<&FusionRule> = rules
+            .iter()
+            .find(|rule| rule.matches(&fused[operator_index..]));
+
+        match rule {
+            Some(rule) => {
+                if rule.pattern.len() < 2 {
+                    panic!(
+                        "fusion::fuse_graph_operators - the rule {} has to match at least 2 operators!",
+                        rule.name
+                    );
+                }
+
+                let end: usize = operator_index + rule.pattern.len();
+                let fused_operator: GraphOperator = (rule.rewrite)(&fused[operator_index..end]);
+                fused.splice(operator_index..end, [fused_operator]);
+                original_indices.drain((operator_index + 1)..end);
+
+                report.fusions.push(Fusion {
+                    rule: rule.name,
+                    operator_index: original_indices[operator_index],
+                });
+            }
+            None => operator_index += 1,
+        }
+    }
+
+    report.operator_count_after = fused.len();
+    (fused, report)
+}
@@ -0,0 +1,287 @@
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        graph::{
+            fusion::{
+                default_fusion_rules, fuse_graph_operators, Fusion, FusionReport, FusionRule,
+                OperatorKind,
+            },
+            graph_runner::GraphRunner,
+            graph_validation::validate_graph_operators,
+        },
+        shared::{
+            activation::ActivationFunction, graph_operators::GraphOperator, tensor2d::Tensor2D,
+            tensor2d_test_utilities::random_tensor,
+        },
+    };
+
+    const ERROR_TOLERANCE: f32 = 0.00001;
+
+    fn linear(rng: &mut ChaCha8Rng) -> GraphOperator {
+        GraphOperator::Linear {
+            weights: random_tensor(rng, 4, 4),
+            bias: random_tensor(rng, 3, 4),
+        }
+    }
+
+    fn kinds(graph_operators: &[GraphOperator]) -> Vec<OperatorKind> {
+        graph_operators.iter().map(OperatorKind::of).collect()
+    }
+
+    fn fuse(graph_operators: &[GraphOperator]) -> (Vec<GraphOperator>, FusionReport) {
+        fuse_graph_operators(graph_operators, &default_fusion_rules())
+    }
+
+    // x -> linear -> relu -> linear -> add(x) -> relu -> relu -> linear -> relu -> softmax
+    fn residual_graph(rng: &mut ChaCha8Rng) -> Vec<GraphOperator> {
+        vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(rng, 3, 4),
+            },
+            GraphOperator::Store {
+                name: "x".to_string(),
+            },
+            linear(rng),
+            GraphOperator::ReLU,
+            linear(rng),
+            GraphOperator::Add {
+                name: "x".to_string(),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::ReLU,
+            linear(rng),
+            GraphOperator::ReLU,
+            GraphOperator::Softmax,
+            GraphOperator::DeviceToHost,
+        ]
+    }
+
+    #[test]
+    fn linear_relu_softmax() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(0);
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(&mut rng, 3, 4),
+            },
+            linear(&mut rng),
+            GraphOperator::ReLU,
+            linear(&mut rng),
+            GraphOperator::ReLU,
+            GraphOperator::Softmax,
+            GraphOperator::DeviceToHost,
+        ];
+
+        let (fused, report) = fuse(&graph_operators);
+        assert_eq!(
+            kinds(&fused),
+            vec![
+                OperatorKind::HostToDevice,
+                OperatorKind::LinearReLU,
+                OperatorKind::LinearReLUSoftmax,
+                OperatorKind::DeviceToHost
+            ]
+        );
+        assert_eq!(
+            report.fusions,
+            vec![
+                Fusion {
+                    rule: "LinearReLU",
+                    operator_index: 1
+                },
+                Fusion {
+                    rule: "LinearReLUSoftmax",
+                    operator_index: 3
+                },
+            ]
+        );
+        assert_eq!(report.operator_count_before, 7);
+        assert_eq!(report.operator_count_after, 4);
+    }
+
+    #[test]
+    fn residual() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(1);
+        let graph_operators: Vec<GraphOperator> = residual_graph(&mut rng);
+
+        let (fused, report) = fuse(&graph_operators);
+        assert!(validate_graph_operators(&fused).is_ok());
+        assert_eq!(
+            kinds(&fused),
+            vec![
+                OperatorKind::HostToDevice,
+                OperatorKind::Store,
+                OperatorKind::LinearReLU,
+                OperatorKind::LinearAdd,
+                OperatorKind::ReLU,
+                OperatorKind::LinearReLUSoftmax,
+                OperatorKind::DeviceToHost
+            ]
+        );
+        assert_eq!(report.count("LinearAdd"), 1);
+        assert_eq!(report.count("ReLUReLU"), 1);
+        assert_eq!(report.count("LinearReLUSoftmax"), 1);
+
+        // The fused operators compute the same thing
+        let expected: Tensor2D = GraphRunner::new(&graph_operators, false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(&graph_operators, true, false).unwrap();
+        assert_eq!(graph_runner.fusion_report().fusions, report.fusions);
+        let found: Tensor2D = graph_runner.run().unwrap();
+        for index in 0..expected.len() {
+            assert!((expected.data[index] - found.data[index]).abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    // Without a linear operator in front, the add is fused with the ReLU after it instead
+    #[test]
+    fn add_relu() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(2);
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(&mut rng, 3, 4),
+            },
+            GraphOperator::Store {
+                name: "x".to_string(),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Add {
+                name: "x".to_string(),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::ReLU,
+            GraphOperator::ReLU,
+            GraphOperator::DeviceToHost,
+        ];
+
+        let (fused, report) = fuse(&graph_operators);
+        assert_eq!(
+            kinds(&fused),
+            vec![
+                OperatorKind::HostToDevice,
+                OperatorKind::Store,
+                OperatorKind::ReLU,
+                OperatorKind::AddReLU,
+                OperatorKind::DeviceToHost
+            ]
+        );
+        assert_eq!(report.count("AddReLU"), 1);
+        assert_eq!(report.count("AddReLUReLU"), 2);
+
+        let expected: Tensor2D = GraphRunner::new(&graph_operators, false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        let found: Tensor2D = GraphRunner::new(&graph_operators, true, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        for index in 0..expected.len() {
+            assert!((expected.data[index] - found.data[index]).abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    // The activation function is carried over into the fused operator
+    #[test]
+    fn linear_activation() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(4);
+        let function: ActivationFunction = ActivationFunction::LeakyReLU { alpha: 0.2 };
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(&mut rng, 3, 4),
+            },
+            linear(&mut rng),
+            GraphOperator::Activation { function },
+            linear(&mut rng),
+            GraphOperator::ReLU,
+            GraphOperator::Activation {
+                function: ActivationFunction::Tanh,
+            },
+            GraphOperator::DeviceToHost,
+        ];
+
+        let (fused, report) = fuse(&graph_operators);
+        assert!(validate_graph_operators(&fused).is_ok());
+        assert_eq!(
+            kinds(&fused),
+            vec![
+                OperatorKind::HostToDevice,
+                OperatorKind::LinearActivation,
+                OperatorKind::LinearReLU,
+                OperatorKind::Activation,
+                OperatorKind::DeviceToHost
+            ]
+        );
+        assert_eq!(report.count("LinearActivation"), 1);
+        assert_eq!(report.count("LinearReLU"), 1);
+        assert!(matches!(
+            fused[1],
+            GraphOperator::LinearActivationFused { function: fused_function, .. }
+                if fused_function == function
+        ));
+
+        let expected: Tensor2D = GraphRunner::new(&graph_operators, false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        let found: Tensor2D = GraphRunner::new(&graph_operators, true, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        for index in 0..expected.len() {
+            assert!((expected.data[index] - found.data[index]).abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    // The output of the linear operator is given a name, so it has to stay around
+    #[test]
+    fn store_breaks_patterns() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(3);
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(&mut rng, 3, 4),
+            },
+            linear(&mut rng),
+            GraphOperator::Store {
+                name: "hidden".to_string(),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::DeviceToHost,
+        ];
+
+        let (fused, report) = fuse(&graph_operators);
+        assert_eq!(kinds(&fused), kinds(&graph_operators));
+        assert!(report.fusions.is_empty());
+    }
+
+    #[test]
+    fn custom_rules() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(4);
+        let graph_operators: Vec<GraphOperator> = residual_graph(&mut rng);
+
+        let (fused, report) = fuse_graph_operators(&graph_operators, &[]);
+        assert_eq!(kinds(&fused), kinds(&graph_operators));
+        assert_eq!(report.operator_count_before, report.operator_count_after);
+
+        // The pass applies a rule as written, it doesn't check what the rewrite computes
+        let rules: Vec<FusionRule> = vec![FusionRule {
+            name: "ReLUSoftmax",
+            pattern: &[OperatorKind::ReLU, OperatorKind::Softmax],
+            rewrite: |_| GraphOperator::Softmax,
+        }];
+        let (fused, report) = fuse_graph_operators(&graph_operators, &rules);
+        assert_eq!(fused.len(), graph_operators.len() - 1);
+        assert_eq!(
+            report.fusions,
+            vec![Fusion {
+                rule: "ReLUSoftmax",
+                operator_index: 9
+            }]
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt;
+
+use super::fusion::OperatorKind;
+
+// Everything which can be wrong with a graph. Unless stated otherwise, node is the index
+// of the offending operator in the list of operators given to validate_graph_operators
+// or to the runners, before any fusion.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GraphError {
+    // The graph has to begin with HostToDevice and end with DeviceToHost
+    MissingTransfer,
+    // A HostToDevice or DeviceToHost somewhere other than the beginning or the end
+    MisplacedTransfer {
+        node: usize,
+    },
+    // A tensor without any rows or columns
+    EmptyTensor {
+        node: usize,
+    },
+    // expected and found are (row_count, column_count). expected is what the operator
+    // needs its input to be, found is what it actually receives. For the bias of a linear
+    // operator, they are the dimensions of the output and of the bias instead.
+    DimensionMismatch {
+        node: usize,
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    // No operator before the node produces its input
+    MissingInput {
+        node: usize,
+    },
+    // Loss operators have to be right before DeviceToHost
+    MisplacedLoss {
+        node: usize,
+    },
+    InvalidLabel {
+        node: usize,
+        label: usize,
+        class_count: usize,
+    },
+    InvalidQuantization {
+        node: usize,
+        message: String,
+    },
+    InvalidActivation {
+        node: usize,
+        message: String,
+    },
+    // A named tensor used before it was stored
+    UnknownTensor {
+        node: usize,
+        name: String,
+    },
+    DuplicateTensorName {
+        node: usize,
+        name: String,
+    },
+    // The operator exists, but the runner has no kernel for it
+    UnsupportedOperator {
+        node: usize,
+        operator: OperatorKind,
+    },
+    // The rows of the bias differ, so the operator can't be run with
+    // a different number of rows in the input, see graph::compiled_graph
+    BatchDependentBias {
+        node: usize,
+    },
+    // A node of a graph::dag::DagGraph lists an input which isn't a node in the graph.
+    // node and input are the names of the nodes.
+    UnknownInput {
+        node: String,
+        input: String,
+    },
+    // A DagGraph in which a node depends on itself can't be scheduled
+    CyclicGraph,
+    // Anything else which is wrong with a DagGraph, such as two nodes with the same name
+    InvalidDag {
+        message: String,
+    },
+    // A node was handed the wrong number of buffers when the graph was run.
+    // node is the name of the node, such as Linear_2.
+    BufferCountMismatch {
+        node: String,
+        expected: usize,
+        found: usize,
+    },
+    // Quantized nodes have no backward pass. node is the name of the node, such as LinearInt8_2.
+    NotDifferentiable {
+        node: String,
+    },
+    // Planning the memory lets the intermediate buffers share memory, so operation,
+    // such as running backward, can't be done on the runner anymore
+    MemoryPlanned {
+        operation: String,
+    },
+    // The graph given to GraphRunner::store_linear_parameters has a different number
+    // of linear operators than the graph the runner was built from
+    ParameterCountMismatch {
+        expected: usize,
+        found: usize,
+    },
+    // A kernel failed while the graph was run, such as a shader reading out of bounds
+    // on the interpreter. node is the name of the node, such as Linear_2.
+    KernelFailed {
+        node: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::MissingTransfer => write!(
+                formatter,
+                "the graph has to begin with HostToDevice and end with DeviceToHost"
+            ),
+            GraphError::MisplacedTransfer { node } => write!(
+                formatter,
+                "operator {} is a transfer, which is only allowed at the beginning or the end of the graph",
+                node
+            ),
+            GraphError::EmptyTensor { node } => {
+                write!(formatter, "operator {} has a tensor with no elements", node)
+            }
+            GraphError::DimensionMismatch {
+                node,
+                expected,
+                found,
+            } => write!(
+                formatter,
+                "operator {} expected an input with {} rows and {} columns, found {} rows and {} columns",
+                node, expected.0, expected.1, found.0, found.1
+            ),
+            GraphError::MissingInput { node } => {
+                write!(formatter, "no operator produces the input of operator {}", node)
+            }
+            GraphError::MisplacedLoss { node } => write!(
+                formatter,
+                "operator {} is a loss, which has to be right before DeviceToHost",
+                node
+            ),
+            GraphError::InvalidLabel {
+                node,
+                label,
+                class_count,
+            } => write!(
+                formatter,
+                "operator {} has the label {}, but its input only has {} classes",
+                node, label, class_count
+            ),
+            GraphError::InvalidQuantization { node, message } => write!(
+                formatter,
+                "operator {} has invalid quantization parameters: {}",
+                node, message
+            ),
+            GraphError::InvalidActivation { node, message } => write!(
+                formatter,
+                "operator {} has an invalid activation function: {}",
+                node, message
+            ),
+            GraphError::UnknownTensor { node, name } => write!(
+                formatter,
+                "operator {} uses the tensor {} before it was stored",
+                node, name
+            ),
+            GraphError::DuplicateTensorName { node, name } => write!(
+                formatter,
+                "operator {} stores the tensor {}, which was already stored",
+                node, name
+            ),
+            GraphError::UnsupportedOperator { node, operator } => write!(
+                formatter,
+                "operator {} is {:?}, which this runner does not support",
+                node, operator
+            ),
+            GraphError::BatchDependentBias { node } => write!(
+                formatter,
+                "operator {} has a bias with rows which differ, so the batch size can't change",
+                node
+            ),
+            GraphError::UnknownInput { node, input } => write!(
+                formatter,
+                "the node {} has the input {}, which is not a node in the graph",
+                node, input
+            ),
+            GraphError::CyclicGraph => write!(formatter, "the graph has a cycle"),
+            GraphError::InvalidDag { message } => write!(formatter, "{}", message),
+            GraphError::BufferCountMismatch {
+                node,
+                expected,
+                found,
+            } => write!(
+                formatter,
+                "node {} expected {} buffers, received {}",
+                node, expected, found
+            ),
+            GraphError::NotDifferentiable { node } => write!(
+                formatter,
+                "node {} has no backward pass, quantized graphs are for inference only",
+                node
+            ),
+            GraphError::MemoryPlanned { operation } => write!(
+                formatter,
+                "can't {} once the memory of the graph has been planned",
+                operation
+            ),
+            GraphError::ParameterCountMismatch { expected, found } => write!(
+                formatter,
+                "the runner has {} linear operators, the graph has {}",
+                expected, found
+            ),
+            GraphError::KernelFailed { node, message } => {
+                write!(formatter, "the kernel of node {} failed: {}", node, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
@@ -1,319 +1,478 @@
-use std::collections::HashMap;
-
+use crate::shared::quantized_tensor2d::QuantizedTensor2D;
 use crate::shared::tensor2d::Tensor2D;
 
-use super::graph_validation::validate_graph_operators;
+use super::backend::{Backend, BackendRunner};
+use super::dag::DagGraph;
+use super::dot_export::nodes_to_dot;
+use super::graph_error::GraphError;
+use super::memory_planner::{MemoryPlanningStrategy, MemoryReport};
 use super::nodes::{self, Node, NodeOperator};
 
 use crate::shared::graph_operators::GraphOperator;
 use crate::shared::graph_operators::GraphOperator::*;
 
-pub struct GraphRunner {
-    graph_operators_are_valid: bool,
-    nodes: Vec<Node>,
-    nodes_are_valid: bool,
-    data_buffers: Vec<Tensor2D>,
-    data_buffers_are_valid: bool,
-    fuse_operators: bool,
+// The tensors are Tensor2D in main memory and the nodes are run as soon as they
+// are dispatched, so there is nothing to record or to wait for
+#[derive(Default)]
+pub struct CPUBackend {
+    // Split the rows of the linear, ReLU and softmax operators across the threads
+    // of the current rayon pool. Run the graph inside ThreadPool::install
+    // to control the number of threads.
+    pub parallel: bool,
+    // The int8 weights and the scratch buffers for the quantized inputs of the quantized operators
+    pub quantized_buffers: Vec<QuantizedTensor2D>,
+    pub gradient_buffers: Vec<Tensor2D>,
+    pub gradient_buffers_are_valid: bool,
 }
 
-impl GraphRunner {
-    pub fn new(graph_operators: &Vec<GraphOperator>, fuse_operators: bool) -> Self {
-        let mut runner: GraphRunner = GraphRunner {
-            graph_operators_are_valid: false,
-            nodes: Vec::<Node>::new(),
-            nodes_are_valid: false,
-            data_buffers: Vec::<Tensor2D>::new(),
-            data_buffers_are_valid: false,
-            fuse_operators,
-        };
-        runner.graph_operators_are_valid = validate_graph_operators(graph_operators);
+impl Backend for CPUBackend {
+    type Context = ();
+    type Tensor = Tensor2D;
+    type Commands = ();
 
-        runner.compute_nodes(graph_operators, runner.fuse_operators);
-        runner.data_buffers_are_valid = true;
+    const NAME: &'static str = "CPU";
 
-        runner
+    // Every operator has a CPU kernel
+    fn check_supported_operators(_graph_operators: &[GraphOperator]) -> Result<(), GraphError> {
+        Ok(())
     }
 
-    fn get_new_key(operator_counts: &mut HashMap<NodeOperator, u32>, key: &NodeOperator) -> String {
-        let error_message: &str = "Failed to get value from hash map in graph_runner::get_new_key";
-        let value: &mut u32 = operator_counts
-            .get_mut(key)
-            .unwrap_or_else(|| panic!("{}", error_message));
-        let index: u32 = *value;
-        *value = index + 1;
-        format!("{:?}_{}", key, index)
+    fn upload(&mut self, _context: &(), _label: &str, tensor: &Tensor2D) -> Tensor2D {
+        tensor.clone()
     }
 
-    fn verify_previous_node_and_get_index(nodes: &Vec<Node>, key: &NodeOperator) -> usize {
-        let previous_node: &Node = &nodes[nodes.len() - 1];
-        match previous_node.operator {
-            NodeOperator::Transfer => {}
-            _ => {
-                panic!("Invalid node operator! Was expecting DeviceToDevice before {:?}, but found {:?}", *key, previous_node.operator);
-            }
-        }
-
-        if previous_node.buffer_indices.len() == 1 {
-            previous_node.buffer_indices[0]
-        } else {
-            panic!("There was more than one buffer index described in a DeviceToDevice operator. This is currently invalid behavior!");
-        }
+    fn allocate(
+        &mut self,
+        _context: &(),
+        _label: &str,
+        row_count: usize,
+        column_count: usize,
+    ) -> Tensor2D {
+        Tensor2D::new(0.0, row_count, column_count)
     }
 
-    // This is made a lot more complicated by reusing buffers
-    // If each node owned its own buffers with no reusage
-    // We would need to keep less track of buffers
-    fn compute_nodes(&mut self, graph_operators: &Vec<GraphOperator>, fuse_operators: bool) {
-        if !self.graph_operators_are_valid {
-            panic!("Invalid graph being sent to compute_nodes!");
-        }
+    fn upload_quantized(&mut self, _context: &(), tensor: QuantizedTensor2D) -> usize {
+        self.quantized_buffers.push(tensor);
+        self.quantized_buffers.len() - 1
+    }
 
-        let mut operator_counts: HashMap<NodeOperator, u32> = HashMap::<NodeOperator, u32>::new();
-        operator_counts.insert(NodeOperator::Input, 0);
-        operator_counts.insert(NodeOperator::Output, 0);
-        operator_counts.insert(NodeOperator::Transfer, 0);
-        operator_counts.insert(NodeOperator::Linear, 0);
-        operator_counts.insert(NodeOperator::ReLU, 0);
-        operator_counts.insert(NodeOperator::Softmax, 0);
-        operator_counts.insert(NodeOperator::LinearReLU, 0);
-        operator_counts.insert(NodeOperator::LinearReLUSoftmax, 0);
+    fn shape(tensor: &Tensor2D) -> (usize, usize) {
+        (tensor.row_count, tensor.column_count)
+    }
 
-        let mut operator_index: usize = 0;
-        while operator_index < graph_operators.len() {
-            let operator: &GraphOperator = &graph_operators[operator_index];
+    fn set_dimensions(tensor: &mut Tensor2D, row_count: usize, column_count: usize) {
+        tensor.set_dimensions(row_count, column_count);
+    }
 
-            match operator {
-                Empty => {}
-                // Maybe put a device to device split in here for simpler code in the other operators
-                HostToDevice { input } => {
-                    let key: NodeOperator = NodeOperator::Input;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    self.data_buffers.push(input.clone());
-                    let buffer_indices: Vec<usize> = vec![self.data_buffers.len() - 1];
-
-                    let node: Node = Node::new(new_key, key, buffer_indices.clone());
-                    self.nodes.push(node);
-
-                    let key: NodeOperator = NodeOperator::Transfer;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let node: Node = Node::new(new_key, NodeOperator::Transfer, buffer_indices);
-                    self.nodes.push(node);
-                }
-                DeviceToHost => {
-                    let key: NodeOperator = NodeOperator::Output;
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
+    fn resize_rows(
+        &mut self,
+        _context: &(),
+        _label: &str,
+        tensor: &mut Tensor2D,
+        row_count: usize,
+        repeat_first_row: bool,
+    ) {
+        let column_count: usize = tensor.column_count;
+        let element_count: usize = row_count * column_count;
+        if tensor.data.len() < element_count {
+            tensor.data.resize(element_count, 0.0);
+        }
+        tensor.set_dimensions(row_count, column_count);
 
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
+        if repeat_first_row {
+            for row_index in 1..row_count {
+                tensor
+                    .data
+                    .copy_within(0..column_count, row_index * column_count);
+            }
+        }
+    }
 
-                    let buffer_indices: Vec<usize> = vec![input_index];
-                    let node: Node = Node::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
-                }
-                Linear { weights, bias } => {
-                    let mut key: NodeOperator = NodeOperator::Linear;
-
-                    if fuse_operators {
-                        if let ReLU = graph_operators[operator_index + 1] {
-                            match graph_operators[operator_index + 2] {
-                                Softmax => {
-                                    key = NodeOperator::LinearReLUSoftmax;
-                                    operator_index += 2;
-                                }
-                                _ => {
-                                    key = NodeOperator::LinearReLU;
-                                    operator_index += 1;
-                                }
-                            }
-                        }
-                    }
-
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    self.data_buffers.push(weights.clone());
-                    let weights_index: usize = self.data_buffers.len() - 1;
-
-                    self.data_buffers.push(bias.clone());
-                    let bias_index: usize = self.data_buffers.len() - 1;
-
-                    self.data_buffers
-                        .push(Tensor2D::new(0.0, bias.row_count, bias.column_count));
-                    let output_index: usize = self.data_buffers.len() - 1;
-
-                    let buffer_indices: Vec<usize> =
-                        vec![input_index, weights_index, bias_index, output_index];
-                    let node: Node = Node::new(new_key.clone(), key, buffer_indices);
-                    self.nodes.push(node);
-
-                    let key: NodeOperator = NodeOperator::Transfer;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let buffer_indices: Vec<usize> = vec![output_index];
-                    let node: Node = Node::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
-                }
-                // Note this is not inplace
-                ReLU => {
-                    let key: NodeOperator = NodeOperator::ReLU;
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
-
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    let input_buffer: &Tensor2D = &self.data_buffers[input_index];
-                    self.data_buffers.push(Tensor2D::new(
-                        0.0,
-                        input_buffer.row_count,
-                        input_buffer.column_count,
-                    ));
-                    let output_index: usize = self.data_buffers.len() - 1;
-
-                    let buffer_indices: Vec<usize> = vec![input_index, output_index];
-                    let node: Node = Node::new(new_key.clone(), key, buffer_indices);
-                    self.nodes.push(node);
-
-                    let key: NodeOperator = NodeOperator::Transfer;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let buffer_indices: Vec<usize> = vec![output_index];
-                    let node: Node = Node::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
-                }
-                Softmax => {
-                    let key: NodeOperator = NodeOperator::Softmax;
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
-
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    let input_buffer: &Tensor2D = &self.data_buffers[input_index];
-                    // This should be more flexible, but Softmax always outputs a flattened vector
-                    self.data_buffers.push(Tensor2D::new(
-                        0.0,
-                        input_buffer.row_count,
-                        input_buffer.column_count,
-                    ));
-                    let output_index: usize = self.data_buffers.len() - 1;
-
-                    let buffer_indices: Vec<usize> = vec![input_index, output_index];
-                    let node: Node = Node::new(new_key.clone(), key, buffer_indices);
-                    self.nodes.push(node);
-
-                    let key: NodeOperator = NodeOperator::Transfer;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let buffer_indices: Vec<usize> = vec![output_index];
-                    let node: Node = Node::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
-                }
-                LinearReLUFused { weights, bias } => {
-                    let key: NodeOperator = NodeOperator::LinearReLU;
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    self.data_buffers.push(weights.clone());
-                    let weights_index: usize = self.data_buffers.len() - 1;
-
-                    self.data_buffers.push(bias.clone());
-                    let bias_index: usize = self.data_buffers.len() - 1;
-
-                    self.data_buffers
-                        .push(Tensor2D::new(0.0, bias.row_count, bias.column_count));
-                    let output_index: usize = self.data_buffers.len() - 1;
-
-                    let buffer_indices: Vec<usize> =
-                        vec![input_index, weights_index, bias_index, output_index];
-                    let node: Node = Node::new(new_key.clone(), key, buffer_indices);
-                    self.nodes.push(node);
-
-                    let key: NodeOperator = NodeOperator::Transfer;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let buffer_indices: Vec<usize> = vec![output_index];
-                    let node: Node = Node::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
-                }
-                LinearReLUSoftmaxFused { weights, bias } => {
-                    let key: NodeOperator = NodeOperator::LinearReLUSoftmax;
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    self.data_buffers.push(weights.clone());
-                    let weights_index: usize = self.data_buffers.len() - 1;
-
-                    self.data_buffers.push(bias.clone());
-                    let bias_index: usize = self.data_buffers.len() - 1;
-
-                    // This should be more flexible, but Softmax always outputs a flattened vector
-                    self.data_buffers
-                        .push(Tensor2D::new(0.0, bias.row_count, bias.column_count));
-                    let output_index: usize = self.data_buffers.len() - 1;
-
-                    let buffer_indices: Vec<usize> =
-                        vec![input_index, weights_index, bias_index, output_index];
-                    let node: Node = Node::new(new_key.clone(), key, buffer_indices);
-                    self.nodes.push(node);
-
-                    let key: NodeOperator = NodeOperator::Transfer;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let buffer_indices: Vec<usize> = vec![output_index];
-                    let node: Node = Node::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
+    // The quantized inputs are resized like the data buffers
+    fn resize_batch(&mut self, nodes: &[Node], row_count: usize) {
+        for node in nodes {
+            if let NodeOperator::LinearInt8 | NodeOperator::LinearReLUInt8 = node.operator {
+                let tensor: &mut Tensor2D<i8> =
+                    &mut self.quantized_buffers[node.buffer_indices[1]].tensor;
+                let column_count: usize = tensor.column_count;
+                let element_count: usize = row_count * column_count;
+                if tensor.data.len() < element_count {
+                    tensor.data.resize(element_count, 0);
                 }
+                tensor.set_dimensions(row_count, column_count);
             }
-
-            operator_index += 1;
         }
-        self.graph_operators_are_valid = true;
+
+        // The gradient buffers were sized for the old batch
+        self.gradient_buffers.clear();
+        self.gradient_buffers_are_valid = false;
+    }
+
+    fn write(&mut self, _context: &(), tensor: &mut Tensor2D, input: &Tensor2D) {
+        let element_count: usize = input.len();
+        tensor.data[0..element_count].copy_from_slice(&input.data[0..element_count]);
     }
 
+    fn begin(&mut self, _context: &()) {}
+
     // In a more correct system, not meant for teaching/learning
     // we might find the correct data buffers here and pass the correct
     // buffers explicitly to the functions. Or at the very least
     // enforce more correctness in the data passed along to
     // the CPUNodeOperator functions.
-    fn submit_operator_commands(node_vector: &Vec<Node>, data_buffers: &mut [Tensor2D]) {
-        for node in node_vector {
+    fn dispatch(
+        &mut self,
+        _context: &(),
+        node: &Node,
+        data_buffers: &mut [Tensor2D],
+        _commands: &mut (),
+    ) -> Result<(), GraphError> {
+        let parallel: bool = self.parallel;
+        let quantized_buffers: &mut [QuantizedTensor2D] = &mut self.quantized_buffers;
+        match node.operator {
+            NodeOperator::Input => {}
+            NodeOperator::Output => {}
+            NodeOperator::Transfer => {}
+            NodeOperator::Linear => {
+                nodes::linear(node, data_buffers, parallel)?;
+            }
+            NodeOperator::ReLU => {
+                nodes::relu(node, data_buffers, parallel)?;
+            }
+            NodeOperator::Softmax => {
+                nodes::softmax(node, data_buffers, parallel)?;
+            }
+            NodeOperator::LinearReLU => {
+                nodes::linear_relu(node, data_buffers, parallel)?;
+            }
+            NodeOperator::LinearReLUSoftmax => {
+                nodes::linear_relu_softmax(node, data_buffers, parallel)?;
+            }
+            // The losses, the quantized operators, add and concat always run on a single thread
+            NodeOperator::MeanSquaredError => {
+                nodes::mean_squared_error(node, data_buffers)?;
+            }
+            NodeOperator::SoftmaxCrossEntropy => {
+                nodes::softmax_cross_entropy(node, data_buffers)?;
+            }
+            NodeOperator::LinearInt8 => {
+                nodes::linear_int8(node, data_buffers, quantized_buffers)?;
+            }
+            NodeOperator::LinearReLUInt8 => {
+                nodes::linear_relu_int8(node, data_buffers, quantized_buffers)?;
+            }
+            NodeOperator::Add => {
+                nodes::add(node, data_buffers)?;
+            }
+            NodeOperator::Concat => {
+                nodes::concat(node, data_buffers)?;
+            }
+            NodeOperator::LinearAdd => {
+                nodes::linear_add(node, data_buffers, parallel)?;
+            }
+            NodeOperator::AddReLU => {
+                nodes::add_relu(node, data_buffers)?;
+            }
+            NodeOperator::Activation(function) => {
+                nodes::activation(node, data_buffers, parallel, function)?;
+            }
+            NodeOperator::LinearActivation(function) => {
+                nodes::linear_activation(node, data_buffers, parallel, function)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn submit(&mut self, _context: &(), _commands: ()) {}
+
+    async fn read(&mut self, _context: &(), tensor: &mut Tensor2D) -> Tensor2D {
+        tensor.clone()
+    }
+}
+
+pub type GraphRunner = BackendRunner<CPUBackend>;
+
+impl GraphRunner {
+    // Returns the first problem found by validate_graph_operators if the graph is invalid
+    pub fn new(
+        graph_operators: &Vec<GraphOperator>,
+        fuse_operators: bool,
+        parallel: bool,
+    ) -> Result<Self, GraphError> {
+        let backend: CPUBackend = CPUBackend {
+            parallel,
+            ..Default::default()
+        };
+        Self::with_backend(backend, &(), graph_operators, fuse_operators)
+    }
+
+    // The built nodes and buffers in the Graphviz DOT format, see graph::dot_export
+    pub fn to_dot(&self, graph_name: &str) -> String {
+        nodes_to_dot(
+            graph_name,
+            &self.nodes,
+            &self.data_buffers,
+            &self.backend.quantized_buffers,
+        )
+    }
+
+    // Schedules the nodes of the DAG in topological order, see graph::dag
+    pub fn from_dag(
+        dag: &DagGraph,
+        fuse_operators: bool,
+        parallel: bool,
+    ) -> Result<Self, GraphError> {
+        Self::new(&dag.to_graph_operators()?, fuse_operators, parallel)
+    }
+
+    // See BackendRunner::set_input_batch_with
+    pub fn set_input_batch(&mut self, input_batch: &Tensor2D) -> Result<(), GraphError> {
+        self.set_input_batch_with(&(), input_batch)
+    }
+
+    pub fn run(&mut self) -> Result<Tensor2D, GraphError> {
+        self.submit_operator_commands(&())?;
+
+        Ok(self.data_buffers[self.output_index()].clone())
+    }
+
+    // As the intermediate values are overwritten, backward can't be run afterwards.
+    // See BackendRunner::plan_memory_with
+    pub fn plan_memory(
+        &mut self,
+        strategy: MemoryPlanningStrategy,
+    ) -> Result<MemoryReport, GraphError> {
+        let report: MemoryReport = self.plan_memory_with(&(), strategy)?;
+
+        self.backend.gradient_buffers.clear();
+        self.backend.gradient_buffers_are_valid = false;
+
+        Ok(report)
+    }
+
+    // The gradient buffers mirror the data buffers one to one, which lets every
+    // node find the gradients of its buffers with its existing buffer_indices.
+    fn allocate_gradient_buffers(&mut self) {
+        if self.backend.gradient_buffers_are_valid {
+            for buffer in &mut self.backend.gradient_buffers {
+                for element in &mut buffer.data {
+                    *element = 0.0;
+                }
+            }
+            return;
+        }
+
+        self.backend.gradient_buffers = self
+            .data_buffers
+            .iter()
+            .map(|buffer| Tensor2D::new(0.0, buffer.row_count, buffer.column_count))
+            .collect();
+        self.backend.gradient_buffers_are_valid = true;
+    }
+
+    fn submit_backward_operator_commands(
+        node_vector: &[Node],
+        data_buffers: &[Tensor2D],
+        gradient_buffers: &mut [Tensor2D],
+    ) -> Result<(), GraphError> {
+        for node in node_vector.iter().rev() {
             match node.operator {
                 NodeOperator::Input => {}
                 NodeOperator::Output => {}
                 NodeOperator::Transfer => {}
                 NodeOperator::Linear => {
-                    nodes::linear(node, data_buffers);
+                    nodes::linear_backward(node, data_buffers, gradient_buffers)?;
                 }
                 NodeOperator::ReLU => {
-                    nodes::relu(node, data_buffers);
+                    nodes::relu_backward(node, data_buffers, gradient_buffers)?;
                 }
                 NodeOperator::Softmax => {
-                    nodes::softmax(node, data_buffers);
+                    nodes::softmax_backward(node, data_buffers, gradient_buffers)?;
                 }
                 NodeOperator::LinearReLU => {
-                    nodes::linear_relu(node, data_buffers);
+                    nodes::linear_relu_backward(node, data_buffers, gradient_buffers)?;
                 }
                 NodeOperator::LinearReLUSoftmax => {
-                    nodes::linear_relu_softmax(node, data_buffers);
+                    nodes::linear_relu_softmax_backward(node, data_buffers, gradient_buffers)?;
+                }
+                NodeOperator::MeanSquaredError => {
+                    nodes::mean_squared_error_backward(node, data_buffers, gradient_buffers)?;
+                }
+                NodeOperator::SoftmaxCrossEntropy => {
+                    nodes::softmax_cross_entropy_backward(node, data_buffers, gradient_buffers)?;
+                }
+                NodeOperator::LinearInt8 | NodeOperator::LinearReLUInt8 => {
+                    return Err(GraphError::NotDifferentiable {
+                        node: node.name.clone(),
+                    });
+                }
+                NodeOperator::Add => {
+                    nodes::add_backward(node, gradient_buffers)?;
+                }
+                NodeOperator::Concat => {
+                    nodes::concat_backward(node, gradient_buffers)?;
+                }
+                NodeOperator::LinearAdd => {
+                    nodes::linear_add_backward(node, data_buffers, gradient_buffers)?;
+                }
+                NodeOperator::AddReLU => {
+                    nodes::add_relu_backward(node, data_buffers, gradient_buffers)?;
+                }
+                NodeOperator::Activation(function) => {
+                    nodes::activation_backward(node, data_buffers, gradient_buffers, function)?;
+                }
+                NodeOperator::LinearActivation(function) => {
+                    nodes::linear_activation_backward(
+                        node,
+                        data_buffers,
+                        gradient_buffers,
+                        function,
+                    )?;
                 }
             }
         }
+
+        Ok(())
     }
 
-    pub fn run(&mut self) -> Tensor2D {
+    // Reverse-mode automatic differentiation of the most recent call to run().
+    // output_gradient is the gradient of some scalar loss with regards to the output
+    // returned by run(). Afterwards, the gradient of every buffer is available,
+    // most importantly those of the weights and biases, see linear_gradients().
+    pub fn backward(&mut self, output_gradient: &Tensor2D) -> Result<(), GraphError> {
         if !self.graph_operators_are_valid {
+            panic!("Tried to run backward on a CPU computational graph with an unvalidated graph_operators!");
+        }
+
+        if !self.data_buffers_are_valid {
+            panic!("Tried to run backward on a CPU computational graph with an unvalidated data_buffers!");
+        }
+
+        // The intermediate values have been overwritten
+        if self.memory_plan.is_some() {
+            return Err(GraphError::MemoryPlanned {
+                operation: "run backward".to_string(),
+            });
+        }
+
+        let output_index: usize = self.output_index();
+        assert_eq!(
+            self.data_buffers[output_index].len(),
+            output_gradient.len(),
+            "\nMismatch - output.len() & output_gradient.len()\noutput - rows: {} columns: {}.\n output_gradient - rows: {} columns: {}.",
+            self.data_buffers[output_index].row_count,
+            self.data_buffers[output_index].column_count,
+            output_gradient.row_count,
+            output_gradient.column_count
+        );
+
+        self.allocate_gradient_buffers();
+        self.backend.gradient_buffers[output_index]
+            .data
+            .copy_from_slice(&output_gradient.data[0..output_gradient.len()]);
+
+        Self::submit_backward_operator_commands(
+            &self.nodes,
+            &self.data_buffers,
+            &mut self.backend.gradient_buffers,
+        )
+    }
+
+    // The (weights, bias) gradients of every linear operator, fused or not,
+    // in the order they appear in the graph.
+    pub fn linear_gradients(&self) -> Vec<(&Tensor2D, &Tensor2D)> {
+        if !self.backend.gradient_buffers_are_valid {
             panic!(
-                "Tried to run the a CPU computational graph with an unvalidated graph_operators!"
+                "Tried to get the gradients of a CPU computational graph before running backward!"
             );
         }
 
-        if !self.data_buffers_are_valid {
-            panic!("Tried to run the a CPU computational graph with an unvalidated data_buffers!");
+        self.linear_parameter_indices()
+            .iter()
+            .map(|(weights_index, bias_index)| {
+                (
+                    &self.backend.gradient_buffers[*weights_index],
+                    &self.backend.gradient_buffers[*bias_index],
+                )
+            })
+            .collect()
+    }
+
+    // Hands every weights and bias tensor, along with its gradient, to update.
+    // The parameters are enumerated as weights_0, bias_0, weights_1, bias_1, ...
+    // which lets the caller keep state per parameter, like the optimizers do.
+    pub fn update_linear_parameters(
+        &mut self,
+        mut update: impl FnMut(usize, &mut Tensor2D, &Tensor2D),
+    ) {
+        if !self.backend.gradient_buffers_are_valid {
+            panic!(
+                "Tried to update the parameters of a CPU computational graph before running backward!"
+            );
         }
 
-        Self::submit_operator_commands(&self.nodes, &mut self.data_buffers);
+        let parameter_indices: Vec<usize> = self
+            .linear_parameter_indices()
+            .iter()
+            .flat_map(|(weights_index, bias_index)| [*weights_index, *bias_index])
+            .collect();
+
+        for (parameter_index, buffer_index) in parameter_indices.iter().enumerate() {
+            update(
+                parameter_index,
+                &mut self.data_buffers[*buffer_index],
+                &self.backend.gradient_buffers[*buffer_index],
+            );
+        }
+    }
+
+    // The runner works on its own copies of the weights and biases. This writes them back
+    // into the linear operators of the graph the runner was built from, which is how
+    // the results of training end up in the Vec<GraphOperator>.
+    pub fn store_linear_parameters(
+        &self,
+        graph_operators: &mut [GraphOperator],
+    ) -> Result<(), GraphError> {
+        let parameter_indices: Vec<(usize, usize)> = self.linear_parameter_indices();
+        let linear_operator_count: usize = graph_operators
+            .iter()
+            .filter(|operator| {
+                matches!(
+                    operator,
+                    Linear { .. }
+                        | LinearReLUFused { .. }
+                        | LinearReLUSoftmaxFused { .. }
+                        | LinearAddFused { .. }
+                        | LinearActivationFused { .. }
+                )
+            })
+            .count();
+        if linear_operator_count != parameter_indices.len() {
+            return Err(GraphError::ParameterCountMismatch {
+                expected: parameter_indices.len(),
+                found: linear_operator_count,
+            });
+        }
+
+        let mut parameter_index: usize = 0;
+        for operator in graph_operators.iter_mut() {
+            match operator {
+                Linear { weights, bias }
+                | LinearReLUFused { weights, bias }
+                | LinearReLUSoftmaxFused { weights, bias }
+                | LinearAddFused { weights, bias, .. }
+                | LinearActivationFused { weights, bias, .. } => {
+                    let (weights_index, bias_index): (usize, usize) =
+                        parameter_indices[parameter_index];
+                    *weights = self.data_buffers[weights_index].clone();
+                    *bias = self.data_buffers[bias_index].clone();
+                    parameter_index += 1;
+                }
+                _ => {}
+            }
+        }
 
-        // Based on the restrictions we have put on our graph, the last node has to be
-        // the output.
-        self.data_buffers[self.nodes[self.nodes.len() - 2].buffer_indices[0]].clone()
+        Ok(())
     }
 }
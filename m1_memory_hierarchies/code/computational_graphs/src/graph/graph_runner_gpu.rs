@@ -1,483 +1,287 @@
-use std::collections::HashMap;
+use std::sync::Arc;
 
-use wgpu::{BufferSlice, CommandEncoder, ComputePipeline, ShaderModule};
+use wgpu::{BufferSlice, CommandEncoder};
 
 use crate::shared::graph_operators::GraphOperator::*;
+use crate::shared::quantized_tensor2d::QuantizedTensor2D;
 use crate::shared::tensor2d::Tensor2D;
 use crate::shared::tensor2d_gpu::Tensor2DGPU;
-use crate::shared::{gpu_utilities::GPUHandles, graph_operators::GraphOperator};
-
-use super::graph_validation::validate_graph_operators;
-use super::nodes_gpu::{self, NodeGPU, NodeOperatorGPU};
-
-pub struct GraphRunnerGPU {
-    graph_operators_are_valid: bool,
-    nodes: Vec<NodeGPU>,
-    nodes_are_valid: bool,
-    data_buffers: Vec<Tensor2DGPU>,
-    data_buffers_are_valid: bool,
-    fuse_operators: bool,
-    use_cache: bool,
-    shader_cache: HashMap<String, ShaderModule>,
-    pipeline_cache: HashMap<String, ComputePipeline>,
+use crate::shared::{
+    gpu_utilities::GPUHandles, graph_operators::GraphOperator, pipeline_cache::PipelineCache,
+};
+
+use super::backend::{Backend, BackendRunner};
+use super::dag::DagGraph;
+use super::dot_export::nodes_gpu_to_dot;
+use super::fusion::OperatorKind;
+use super::graph_error::GraphError;
+use super::memory_planner::{MemoryPlanningStrategy, MemoryReport};
+use super::nodes::{Node, NodeOperator};
+use super::nodes_gpu;
+
+// The tensors are wgpu buffers. The nodes are recorded in a command encoder,
+// which is submitted once every node of a run has been recorded.
+pub struct GPUBackend {
+    // Without a cache the shaders of a node are compiled every time it is dispatched
+    pub pipeline_cache: Option<Arc<PipelineCache>>,
 }
 
-impl GraphRunnerGPU {
-    pub fn new(
-        gpu_handles: &GPUHandles,
-        graph_operators: &Vec<GraphOperator>,
-        fuse_operators: bool,
-        use_cache: bool,
-    ) -> Self {
-        let mut shader_cache: HashMap<String, ShaderModule> =
-            HashMap::<String, ShaderModule>::new();
-        let mut pipeline_cache: HashMap<String, ComputePipeline> =
-            HashMap::<String, ComputePipeline>::new();
-
-        if use_cache {
-            Self::populate_caches(
-                gpu_handles,
-                fuse_operators,
-                &mut shader_cache,
-                &mut pipeline_cache,
-            );
-        }
-
-        let mut runner: GraphRunnerGPU = GraphRunnerGPU {
-            graph_operators_are_valid: false,
-            nodes: Vec::<NodeGPU>::new(),
-            nodes_are_valid: false,
-            data_buffers: Vec::<Tensor2DGPU>::new(),
-            data_buffers_are_valid: false,
-            fuse_operators,
-            use_cache,
-            shader_cache,
-            pipeline_cache,
-        };
-        runner.graph_operators_are_valid = validate_graph_operators(graph_operators);
-
-        runner.compute_nodes(gpu_handles, graph_operators, fuse_operators);
-        runner
-    }
-
-    fn populate_caches(
+impl GPUBackend {
+    // Compiles the pipelines of every operator up front, so the first run isn't slower
+    // than the rest. A cache shared with another runner might already have them.
+    fn populate_cache(
         gpu_handles: &GPUHandles,
+        pipeline_cache: &PipelineCache,
         fuse_operators: bool,
-        shader_cache: &mut HashMap<String, ShaderModule>,
-        pipeline_cache: &mut HashMap<String, ComputePipeline>,
     ) {
         //Linear,
-        nodes_gpu::build_linear_elements(gpu_handles, shader_cache, pipeline_cache, false);
+        nodes_gpu::build_linear_elements(gpu_handles, pipeline_cache, false);
 
         //ReLU,
-        nodes_gpu::build_relu_elements(gpu_handles, shader_cache, pipeline_cache);
+        nodes_gpu::build_relu_elements(gpu_handles, pipeline_cache);
 
         //Softmax,
-        nodes_gpu::build_softmax_elements(gpu_handles, shader_cache, pipeline_cache);
+        nodes_gpu::build_softmax_elements(gpu_handles, pipeline_cache);
+
+        //Add,
+        nodes_gpu::build_add_elements(gpu_handles, pipeline_cache);
+
+        //Concat,
+        nodes_gpu::build_concat_elements(gpu_handles, pipeline_cache);
+
+        //Activation,
+        nodes_gpu::build_activation_elements(gpu_handles, pipeline_cache);
 
         if fuse_operators {
             //LinearReLU,
-            nodes_gpu::build_linear_elements(gpu_handles, shader_cache, pipeline_cache, true);
-        }
-    }
+            nodes_gpu::build_linear_elements(gpu_handles, pipeline_cache, true);
+
+            //LinearAdd,
+            nodes_gpu::build_linear_add_elements(gpu_handles, pipeline_cache);
+
+            //AddReLU,
+            nodes_gpu::build_add_relu_elements(gpu_handles, pipeline_cache);
 
-    fn get_new_key(
-        operator_counts: &mut HashMap<NodeOperatorGPU, u32>,
-        key: &NodeOperatorGPU,
-    ) -> String {
-        let error_message: &str = "Failed to get value from hash map in graph_runner::get_new_key";
-        let value: &mut u32 = operator_counts
-            .get_mut(key)
-            .unwrap_or_else(|| panic!("{}", error_message));
-        let index: u32 = *value;
-        *value = index + 1;
-        format!("{:?}_{}", key, index)
+            //LinearActivation,
+            nodes_gpu::build_linear_activation_elements(gpu_handles, pipeline_cache);
+        }
     }
+}
 
-    fn verify_previous_node_and_get_index(nodes: &Vec<NodeGPU>, key: &NodeOperatorGPU) -> usize {
-        let previous_node: &NodeGPU = &nodes[nodes.len() - 1];
-        match previous_node.operator {
-            NodeOperatorGPU::DeviceToDevice => {}
-            _ => {
-                panic!("Invalid node operator! Was expecting DeviceToDevice before {:?}, but found {:?}", *key, previous_node.operator);
+impl Backend for GPUBackend {
+    type Context = GPUHandles;
+    type Tensor = Tensor2DGPU;
+    type Commands = CommandEncoder;
+
+    const NAME: &'static str = "GPU";
+
+    // The losses and the quantized operators only exist as CPU kernels. This is checked
+    // before fusion, so node is an index into the graph the user handed us.
+    fn check_supported_operators(graph_operators: &[GraphOperator]) -> Result<(), GraphError> {
+        for (node, operator) in graph_operators.iter().enumerate() {
+            if let MeanSquaredError { .. }
+            | SoftmaxCrossEntropy { .. }
+            | LinearInt8 { .. }
+            | LinearReLUInt8Fused { .. } = operator
+            {
+                return Err(GraphError::UnsupportedOperator {
+                    node,
+                    operator: OperatorKind::of(operator),
+                });
             }
         }
 
-        if previous_node.buffer_indices.len() == 1 {
-            previous_node.buffer_indices[0]
-        } else {
-            panic!("There was more than one buffer index described in a DeviceToDevice operator. This is currently invalid behavior!");
-        }
+        Ok(())
     }
 
-    // This is made a lot more complicated by reusing buffers
-    // If each node owned its own buffers with no reusage
-    // We would need to keep less track of buffers
-    fn compute_nodes(
+    fn upload(&mut self, gpu_handles: &GPUHandles, label: &str, tensor: &Tensor2D) -> Tensor2DGPU {
+        Tensor2DGPU::from_tensor2d(gpu_handles, label, tensor)
+    }
+
+    fn allocate(
         &mut self,
         gpu_handles: &GPUHandles,
-        graph_operators: &Vec<GraphOperator>,
-        fuse_operators: bool,
-    ) {
-        if !self.graph_operators_are_valid {
-            panic!("Invalid graph being sent to compute_nodes!");
-        }
+        label: &str,
+        row_count: usize,
+        column_count: usize,
+    ) -> Tensor2DGPU {
+        Tensor2DGPU::new(gpu_handles, label, 0.0, row_count, column_count)
+    }
 
-        let mut operator_counts: HashMap<NodeOperatorGPU, u32> =
-            HashMap::<NodeOperatorGPU, u32>::new();
-        operator_counts.insert(NodeOperatorGPU::HostToDevice, 0);
-        operator_counts.insert(NodeOperatorGPU::DeviceToHost, 0);
-        operator_counts.insert(NodeOperatorGPU::DeviceToDevice, 0);
-        operator_counts.insert(NodeOperatorGPU::Linear, 0);
-        operator_counts.insert(NodeOperatorGPU::ReLU, 0);
-        operator_counts.insert(NodeOperatorGPU::Softmax, 0);
-        operator_counts.insert(NodeOperatorGPU::LinearReLU, 0);
-        operator_counts.insert(NodeOperatorGPU::LinearReLUSoftmax, 0);
-
-        let mut operator_index: usize = 0;
-        while operator_index < graph_operators.len() {
-            let operator: &GraphOperator = &graph_operators[operator_index];
-
-            match operator {
-                Empty => {}
-                // Maybe put a device to device split in here for simpler code in the other operators
-                HostToDevice { input } => {
-                    let key: NodeOperatorGPU = NodeOperatorGPU::HostToDevice;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    self.data_buffers.push(Tensor2DGPU::from_tensor2d(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "input"),
-                        input,
-                    ));
-                    let buffer_indices: Vec<usize> = vec![self.data_buffers.len() - 1];
-
-                    let node: NodeGPU = NodeGPU::new(new_key, key, buffer_indices.clone());
-                    self.nodes.push(node);
-
-                    let key: NodeOperatorGPU = NodeOperatorGPU::DeviceToDevice;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let node: NodeGPU = NodeGPU::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
-                }
-                DeviceToHost => {
-                    let key: NodeOperatorGPU = NodeOperatorGPU::DeviceToHost;
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
-
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    let buffer_indices: Vec<usize> = vec![input_index];
-                    let node: NodeGPU =
-                        NodeGPU::new(new_key, NodeOperatorGPU::DeviceToHost, buffer_indices);
-                    self.nodes.push(node);
-                }
-                Linear { weights, bias } => {
-                    let mut key: NodeOperatorGPU = NodeOperatorGPU::Linear;
-
-                    if fuse_operators {
-                        if let ReLU = graph_operators[operator_index + 1] {
-                            match graph_operators[operator_index + 2] {
-                                Softmax => {
-                                    key = NodeOperatorGPU::LinearReLUSoftmax;
-                                    operator_index += 2;
-                                }
-                                _ => {
-                                    key = NodeOperatorGPU::LinearReLU;
-                                    operator_index += 1;
-                                }
-                            }
-                        }
-                    }
-
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    self.data_buffers.push(Tensor2DGPU::from_tensor2d(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "weights"),
-                        weights,
-                    ));
-                    let weights_index: usize = self.data_buffers.len() - 1;
-
-                    self.data_buffers.push(Tensor2DGPU::from_tensor2d(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "bias"),
-                        bias,
-                    ));
-                    let bias_index: usize = self.data_buffers.len() - 1;
-
-                    self.data_buffers.push(Tensor2DGPU::new(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "output"),
-                        0.0,
-                        bias.row_count,
-                        bias.column_count,
-                    ));
-                    let output_index: usize = self.data_buffers.len() - 1;
-
-                    let buffer_indices: Vec<usize> =
-                        vec![input_index, weights_index, bias_index, output_index];
-                    let node: NodeGPU = NodeGPU::new(new_key.clone(), key, buffer_indices);
-                    self.nodes.push(node);
-
-                    let key: NodeOperatorGPU = NodeOperatorGPU::DeviceToDevice;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let buffer_indices: Vec<usize> = vec![output_index];
-                    let node: NodeGPU = NodeGPU::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
-                }
-                // Note this is not inplace
-                ReLU => {
-                    let key: NodeOperatorGPU = NodeOperatorGPU::ReLU;
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
-
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    let input_buffer: &Tensor2DGPU = &self.data_buffers[input_index];
-                    self.data_buffers.push(Tensor2DGPU::new(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "output"),
-                        0.0,
-                        input_buffer.row_count,
-                        input_buffer.column_count,
-                    ));
-                    let output_index: usize = self.data_buffers.len() - 1;
-
-                    let buffer_indices: Vec<usize> = vec![input_index, output_index];
-                    let node: NodeGPU = NodeGPU::new(new_key.clone(), key, buffer_indices);
-                    self.nodes.push(node);
-
-                    let key: NodeOperatorGPU = NodeOperatorGPU::DeviceToDevice;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let buffer_indices: Vec<usize> = vec![output_index];
-                    let node: NodeGPU = NodeGPU::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
-                }
-                Softmax => {
-                    let key: NodeOperatorGPU = NodeOperatorGPU::Softmax;
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
-
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    let input_buffer: &Tensor2DGPU = &self.data_buffers[input_index];
-                    // This should be more flexible, but Softmax always outputs a flattened vector
-                    self.data_buffers.push(Tensor2DGPU::new(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "output"),
-                        0.0,
-                        input_buffer.row_count * input_buffer.column_count,
-                        1,
-                    ));
-                    let output_index: usize = self.data_buffers.len() - 1;
-
-                    let buffer_indices: Vec<usize> = vec![input_index, output_index];
-                    let node: NodeGPU = NodeGPU::new(new_key.clone(), key, buffer_indices);
-                    self.nodes.push(node);
-
-                    let key: NodeOperatorGPU = NodeOperatorGPU::DeviceToDevice;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let buffer_indices: Vec<usize> = vec![output_index];
-                    let node: NodeGPU = NodeGPU::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
-                }
-                LinearReLUFused { weights, bias } => {
-                    let key: NodeOperatorGPU = NodeOperatorGPU::LinearReLU;
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    self.data_buffers.push(Tensor2DGPU::from_tensor2d(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "weights"),
-                        weights,
-                    ));
-                    let weights_index: usize = self.data_buffers.len() - 1;
-
-                    self.data_buffers.push(Tensor2DGPU::from_tensor2d(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "bias"),
-                        bias,
-                    ));
-                    let bias_index: usize = self.data_buffers.len() - 1;
-
-                    self.data_buffers.push(Tensor2DGPU::new(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "output"),
-                        0.0,
-                        bias.row_count,
-                        bias.column_count,
-                    ));
-                    let output_index: usize = self.data_buffers.len() - 1;
-
-                    let buffer_indices: Vec<usize> =
-                        vec![input_index, weights_index, bias_index, output_index];
-                    let node: NodeGPU = NodeGPU::new(new_key.clone(), key, buffer_indices);
-                    self.nodes.push(node);
-
-                    let key: NodeOperatorGPU = NodeOperatorGPU::DeviceToDevice;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let buffer_indices: Vec<usize> = vec![output_index];
-                    let node: NodeGPU = NodeGPU::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
-                }
-                LinearReLUSoftmaxFused { weights, bias } => {
-                    let key: NodeOperatorGPU = NodeOperatorGPU::LinearReLUSoftmax;
-                    let input_index: usize =
-                        Self::verify_previous_node_and_get_index(&self.nodes, &key);
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-
-                    self.data_buffers.push(Tensor2DGPU::from_tensor2d(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "weights"),
-                        weights,
-                    ));
-                    let weights_index: usize = self.data_buffers.len() - 1;
-
-                    self.data_buffers.push(Tensor2DGPU::from_tensor2d(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "bias"),
-                        bias,
-                    ));
-                    let bias_index: usize = self.data_buffers.len() - 1;
-
-                    // This should be more flexible, but Softmax always outputs a flattened vector
-                    self.data_buffers.push(Tensor2DGPU::new(
-                        gpu_handles,
-                        &format!("{}_{}", new_key, "output"),
-                        0.0,
-                        bias.row_count,
-                        bias.column_count,
-                    ));
-                    let output_index: usize = self.data_buffers.len() - 1;
-
-                    let buffer_indices: Vec<usize> =
-                        vec![input_index, weights_index, bias_index, output_index];
-                    let node: NodeGPU = NodeGPU::new(new_key.clone(), key, buffer_indices);
-                    self.nodes.push(node);
-
-                    let key: NodeOperatorGPU = NodeOperatorGPU::DeviceToDevice;
-                    let new_key: String = Self::get_new_key(&mut operator_counts, &key);
-                    let buffer_indices: Vec<usize> = vec![output_index];
-                    let node: NodeGPU = NodeGPU::new(new_key, key, buffer_indices);
-                    self.nodes.push(node);
-                }
-            }
+    // Rejected by check_supported_operators
+    fn upload_quantized(&mut self, _gpu_handles: &GPUHandles, _tensor: QuantizedTensor2D) -> usize {
+        unreachable!("GraphRunnerGPU does not support the quantized operators");
+    }
 
-            operator_index += 1;
-        }
-        self.graph_operators_are_valid = true;
+    fn shape(tensor: &Tensor2DGPU) -> (usize, usize) {
+        (tensor.row_count, tensor.column_count)
     }
 
-    fn submit_operator_commands(
+    fn set_dimensions(tensor: &mut Tensor2DGPU, row_count: usize, column_count: usize) {
+        tensor.set_dimensions(row_count, column_count);
+    }
+
+    // Buffers which are too small for the new rows are reallocated on the GPU, the rest are reused
+    fn resize_rows(
+        &mut self,
         gpu_handles: &GPUHandles,
-        use_cache: bool,
-        shader_cache: &HashMap<String, ShaderModule>,
-        pipeline_cache: &HashMap<String, ComputePipeline>,
-        node_vector: &[NodeGPU],
-        data_buffers: &[Tensor2DGPU],
-        encoder: &mut CommandEncoder,
+        label: &str,
+        tensor: &mut Tensor2DGPU,
+        row_count: usize,
+        repeat_first_row: bool,
     ) {
-        for node in node_vector {
-            match node.operator {
-                NodeOperatorGPU::HostToDevice => {
-                    // The graph runner handles transfers itself
-                }
-                NodeOperatorGPU::DeviceToHost => {
-                    // The graph runner handles transfers itself
-                }
-                NodeOperatorGPU::DeviceToDevice => {
-                    // The graph runner handles transfers itself
-                }
-                NodeOperatorGPU::Linear => {
-                    nodes_gpu::linear(
-                        gpu_handles,
-                        use_cache,
-                        shader_cache,
-                        pipeline_cache,
-                        node,
-                        data_buffers,
-                        encoder,
-                        false,
-                    );
-                }
-                NodeOperatorGPU::ReLU => {
-                    nodes_gpu::relu(
-                        gpu_handles,
-                        use_cache,
-                        shader_cache,
-                        pipeline_cache,
-                        node,
-                        data_buffers,
-                        encoder,
-                    );
-                }
-                NodeOperatorGPU::Softmax => {
-                    nodes_gpu::softmax(
-                        gpu_handles,
-                        use_cache,
-                        shader_cache,
-                        pipeline_cache,
-                        node,
-                        data_buffers,
-                        encoder,
-                    );
-                }
-                NodeOperatorGPU::LinearReLU => {
-                    nodes_gpu::linear(
-                        gpu_handles,
-                        use_cache,
-                        shader_cache,
-                        pipeline_cache,
-                        node,
-                        data_buffers,
-                        encoder,
-                        true,
-                    );
-                }
-                NodeOperatorGPU::LinearReLUSoftmax => {
-                    nodes_gpu::linear_relu_softmax(
-                        gpu_handles,
-                        use_cache,
-                        shader_cache,
-                        pipeline_cache,
-                        node,
-                        data_buffers,
-                        encoder,
-                    );
-                }
+        let column_count: usize = tensor.column_count;
+        let element_count: usize = row_count * column_count;
+        let capacity: usize = tensor.storage_buffer.size() as usize / tensor.element_size;
+
+        if repeat_first_row {
+            let mut bias: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+            for row_index in 0..row_count {
+                bias.data[row_index * column_count..(row_index + 1) * column_count]
+                    .copy_from_slice(&tensor.data.data[0..column_count]);
+            }
+            if element_count <= capacity {
+                gpu_handles.queue.write_buffer(
+                    &tensor.storage_buffer,
+                    0,
+                    bytemuck::cast_slice(&bias.data),
+                );
+                tensor.data = bias;
+                tensor.set_dimensions(row_count, column_count);
+            } else {
+                *tensor = Tensor2DGPU::from_tensor2d(gpu_handles, label, &bias);
+            }
+        } else if element_count <= capacity {
+            if tensor.data.data.len() < element_count {
+                tensor.data.data.resize(element_count, 0.0);
             }
+            tensor.set_dimensions(row_count, column_count);
+        } else {
+            *tensor = Tensor2DGPU::new(gpu_handles, label, 0.0, row_count, column_count);
         }
     }
 
-    fn submit_operations(&mut self, gpu_handles: &GPUHandles) {
-        let mut encoder: CommandEncoder = gpu_handles
+    fn write(&mut self, gpu_handles: &GPUHandles, tensor: &mut Tensor2DGPU, input: &Tensor2D) {
+        let element_count: usize = input.len();
+        gpu_handles.queue.write_buffer(
+            &tensor.storage_buffer,
+            0,
+            bytemuck::cast_slice(&input.data[0..element_count]),
+        );
+        tensor.data.data[0..element_count].copy_from_slice(&input.data[0..element_count]);
+    }
+
+    fn begin(&mut self, gpu_handles: &GPUHandles) -> CommandEncoder {
+        gpu_handles
             .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-        {
-            // Submit operator commands to run with the buffers
-            Self::submit_operator_commands(
-                gpu_handles,
-                self.use_cache,
-                &self.shader_cache,
-                &self.pipeline_cache,
-                &self.nodes,
-                &self.data_buffers,
-                &mut encoder,
-            );
-
-            // Submit commands
-            gpu_handles.queue.submit(Some(encoder.finish()));
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None })
+    }
+
+    fn dispatch(
+        &mut self,
+        gpu_handles: &GPUHandles,
+        node: &Node,
+        data_buffers: &mut [Tensor2DGPU],
+        encoder: &mut CommandEncoder,
+    ) -> Result<(), GraphError> {
+        let pipeline_cache: Option<&PipelineCache> = self.pipeline_cache.as_deref();
+        match node.operator {
+            NodeOperator::Input => {
+                // The graph runner handles transfers itself
+            }
+            NodeOperator::Output => {
+                // The graph runner handles transfers itself
+            }
+            NodeOperator::Transfer => {
+                // The graph runner handles transfers itself
+            }
+            NodeOperator::Linear => {
+                nodes_gpu::linear(
+                    gpu_handles,
+                    pipeline_cache,
+                    node,
+                    data_buffers,
+                    encoder,
+                    false,
+                )?;
+            }
+            NodeOperator::ReLU => {
+                nodes_gpu::relu(gpu_handles, pipeline_cache, node, data_buffers, encoder)?;
+            }
+            NodeOperator::Softmax => {
+                nodes_gpu::softmax(gpu_handles, pipeline_cache, node, data_buffers, encoder)?;
+            }
+            NodeOperator::LinearReLU => {
+                nodes_gpu::linear(
+                    gpu_handles,
+                    pipeline_cache,
+                    node,
+                    data_buffers,
+                    encoder,
+                    true,
+                )?;
+            }
+            NodeOperator::LinearReLUSoftmax => {
+                nodes_gpu::linear_relu_softmax(
+                    gpu_handles,
+                    pipeline_cache,
+                    node,
+                    data_buffers,
+                    encoder,
+                )?;
+            }
+            NodeOperator::Add => {
+                nodes_gpu::add(gpu_handles, pipeline_cache, node, data_buffers, encoder)?;
+            }
+            NodeOperator::Concat => {
+                nodes_gpu::concat(gpu_handles, pipeline_cache, node, data_buffers, encoder)?;
+            }
+            NodeOperator::LinearAdd => {
+                nodes_gpu::linear_add(gpu_handles, pipeline_cache, node, data_buffers, encoder)?;
+            }
+            NodeOperator::AddReLU => {
+                nodes_gpu::add_relu(gpu_handles, pipeline_cache, node, data_buffers, encoder)?;
+            }
+            NodeOperator::Activation(function) => {
+                nodes_gpu::activation(
+                    gpu_handles,
+                    pipeline_cache,
+                    node,
+                    data_buffers,
+                    encoder,
+                    function,
+                )?;
+            }
+            NodeOperator::LinearActivation(function) => {
+                nodes_gpu::linear_activation(
+                    gpu_handles,
+                    pipeline_cache,
+                    node,
+                    data_buffers,
+                    encoder,
+                    function,
+                )?;
+            }
+            // Rejected by check_supported_operators
+            NodeOperator::MeanSquaredError
+            | NodeOperator::SoftmaxCrossEntropy
+            | NodeOperator::LinearInt8
+            | NodeOperator::LinearReLUInt8 => {
+                unreachable!(
+                    "GraphRunnerGPU does not support the operator {:?}",
+                    node.operator
+                );
+            }
         }
+
+        Ok(())
     }
 
-    async fn retrieve_output(&mut self, gpu_handles: &GPUHandles) -> Tensor2D {
-        // Transfer result back
-        let last_index: usize = self.data_buffers.len() - 1;
-        let output: &mut Tensor2DGPU = &mut self.data_buffers[last_index];
+    fn submit(&mut self, gpu_handles: &GPUHandles, encoder: CommandEncoder) {
+        gpu_handles.queue.submit(Some(encoder.finish()));
+    }
+
+    async fn read(&mut self, gpu_handles: &GPUHandles, output: &mut Tensor2DGPU) -> Tensor2D {
         let buffer_slice: BufferSlice = output.staging_buffer.slice(..);
         let (sender, receiver) = futures_intrusive::channel::shared::oneshot_channel();
         buffer_slice.map_async(wgpu::MapMode::Read, move |v| sender.send(v).unwrap());
@@ -491,15 +295,103 @@ impl GraphRunnerGPU {
 
         output.data.clone()
     }
+}
 
-    pub async fn run(&mut self, gpu_handles: &GPUHandles, iteration_count: usize) -> Tensor2D {
-        if !self.graph_operators_are_valid {
-            panic!("Failed to validate the computational graph!");
-        }
+pub type GraphRunnerGPU = BackendRunner<GPUBackend>;
+
+impl GraphRunnerGPU {
+    // The runners which cache their pipelines share the cache of the device,
+    // see GPUHandles::pipeline_cache
+    pub fn new(
+        gpu_handles: &GPUHandles,
+        graph_operators: &Vec<GraphOperator>,
+        fuse_operators: bool,
+        use_cache: bool,
+    ) -> Result<Self, GraphError> {
+        let pipeline_cache: Option<Arc<PipelineCache>> = if use_cache {
+            Some(gpu_handles.pipeline_cache.clone())
+        } else {
+            None
+        };
 
-        for _ in 0..iteration_count {
-            self.submit_operations(gpu_handles);
+        Self::build(gpu_handles, graph_operators, fuse_operators, pipeline_cache)
+    }
+
+    // Like new, with a cache of its own, such as one kept on disk
+    pub fn with_pipeline_cache(
+        gpu_handles: &GPUHandles,
+        graph_operators: &[GraphOperator],
+        fuse_operators: bool,
+        pipeline_cache: Arc<PipelineCache>,
+    ) -> Result<Self, GraphError> {
+        Self::build(
+            gpu_handles,
+            graph_operators,
+            fuse_operators,
+            Some(pipeline_cache),
+        )
+    }
+
+    fn build(
+        gpu_handles: &GPUHandles,
+        graph_operators: &[GraphOperator],
+        fuse_operators: bool,
+        pipeline_cache: Option<Arc<PipelineCache>>,
+    ) -> Result<Self, GraphError> {
+        let backend: GPUBackend = GPUBackend { pipeline_cache };
+        let runner: GraphRunnerGPU =
+            Self::with_backend(backend, gpu_handles, graph_operators, fuse_operators)?;
+
+        if let Some(pipeline_cache) = &runner.backend.pipeline_cache {
+            GPUBackend::populate_cache(gpu_handles, pipeline_cache, fuse_operators);
         }
-        self.retrieve_output(gpu_handles).await
+
+        Ok(runner)
+    }
+
+    // The built nodes and buffers in the Graphviz DOT format, see graph::dot_export
+    pub fn to_dot(&self, graph_name: &str) -> String {
+        nodes_gpu_to_dot(graph_name, &self.nodes, &self.data_buffers)
+    }
+
+    // Schedules the nodes of the DAG in topological order, see graph::dag
+    pub fn from_dag(
+        gpu_handles: &GPUHandles,
+        dag: &DagGraph,
+        fuse_operators: bool,
+        use_cache: bool,
+    ) -> Result<Self, GraphError> {
+        Self::new(
+            gpu_handles,
+            &dag.to_graph_operators()?,
+            fuse_operators,
+            use_cache,
+        )
+    }
+
+    // See BackendRunner::plan_memory_with
+    pub fn plan_memory(
+        &mut self,
+        gpu_handles: &GPUHandles,
+        strategy: MemoryPlanningStrategy,
+    ) -> Result<MemoryReport, GraphError> {
+        self.plan_memory_with(gpu_handles, strategy)
+    }
+
+    // See BackendRunner::set_input_batch_with
+    pub fn set_input_batch(
+        &mut self,
+        gpu_handles: &GPUHandles,
+        input_batch: &Tensor2D,
+    ) -> Result<(), GraphError> {
+        self.set_input_batch_with(gpu_handles, input_batch)
+    }
+
+    pub async fn run(
+        &mut self,
+        gpu_handles: &GPUHandles,
+        iteration_count: usize,
+    ) -> Result<Tensor2D, GraphError> {
+        self.run_with(gpu_handles, iteration_count).await
     }
 }
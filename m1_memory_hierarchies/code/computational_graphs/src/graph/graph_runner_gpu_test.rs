@@ -1,44 +1,42 @@
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use crate::{
-        graph::graph_runner_gpu::GraphRunnerGPU,
+        graph::{
+            compiled_graph::{CompiledGraph, CompiledGraphGPU},
+            fusion::OperatorKind,
+            graph_error::GraphError,
+            graph_runner::GraphRunner,
+            graph_runner_gpu::GraphRunnerGPU,
+            graph_runner_interpreter::GraphRunnerInterpreter,
+            memory_planner::MemoryPlanningStrategy,
+        },
         shared::{
+            activation::ActivationFunction,
             gpu_utilities::{initialize_gpu, GPUHandles},
             graph_operators::GraphOperator,
+            pipeline_cache::{PipelineCache, PipelineCacheStatistics},
             tensor2d::Tensor2D,
+            tensor2d_test_utilities::assert_close,
         },
     };
 
     const ERROR_TOLERANCE: f32 = 0.00001;
 
-    // This is for verification purposes only
-    // we don't care about making this fast
-    fn subtract_tensors(left: &Tensor2D, right: &Tensor2D) -> Tensor2D {
-        debug_assert_eq!(
-            left.row_count, right.row_count,
-            "\ninput - rows: {} columns: {}.\n out - rows: {} columns: {}.",
-            left.row_count, left.column_count, right.row_count, right.column_count
-        );
-        debug_assert_eq!(
-            left.column_count, right.column_count,
-            "\nweights - rows: {} columns: {}.\n out - rows: {} columns: {}.",
-            left.row_count, left.column_count, right.row_count, right.column_count
-        );
-
-        let mut out: Tensor2D = Tensor2D::new(0.0, left.row_count, left.column_count);
-
-        for index in 0..(left.row_count * left.column_count) {
-            out.data[index] = left.data[index] - right.data[index];
-        }
-
-        out
+    // The kernels of GraphRunnerGPU run on the shader interpreter, so these tests don't
+    // need a GPU. The tests which are about the GPU itself, such as the pipeline cache,
+    // are ignored unless asked for with cargo test -- --ignored.
+    fn run_on_interpreter(graph_operators: &[GraphOperator], fuse_operators: bool) -> Tensor2D {
+        let mut graph_runner: GraphRunnerInterpreter =
+            GraphRunnerInterpreter::new(graph_operators, fuse_operators).unwrap();
+        graph_runner
+            .run()
+            .unwrap_or_else(|error| panic!("{}", error))
     }
 
     #[test]
     fn linear() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in graph_runner_test::linear() test");
-
         let outer_dimension_range: usize = 8;
         let inner_dimension_range: usize = 8;
 
@@ -61,18 +59,9 @@ mod tests {
                     ];
 
                     let fuse_operators: bool = false;
-                    let cache_elements: bool = false;
-                    let mut graph_runner: GraphRunnerGPU = GraphRunnerGPU::new(
-                        &gpu_handles,
-                        &graph_operators,
-                        fuse_operators,
-                        cache_elements,
-                    );
-                    let output: Tensor2D = pollster::block_on(graph_runner.run(&gpu_handles, 1));
+                    let output: Tensor2D = run_on_interpreter(&graph_operators, fuse_operators);
 
-                    let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
-                    println!("{:?}", difference);
-                    println!("{:?}", difference.data.iter().map(|x| x.abs()).sum::<f32>());
+                    assert_close("linear", &output_cpu.data, &output.data, ERROR_TOLERANCE);
                 }
             }
         }
@@ -80,15 +69,16 @@ mod tests {
 
     #[test]
     fn relu() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in graph_runner_test::relu() test");
-
         let outer_dimension_range: usize = 8;
         let inner_dimension_range: usize = 8;
 
         for outer_dimension in 1..outer_dimension_range {
             for inner_dimension in 1..inner_dimension_range {
-                let input: Tensor2D = Tensor2D::new(0.5, outer_dimension, inner_dimension);
+                // Half of the elements are negative
+                let mut input: Tensor2D = Tensor2D::new(0.5, outer_dimension, inner_dimension);
+                for (index, element) in input.data.iter_mut().enumerate() {
+                    *element *= if index % 2 == 0 { 1.0 } else { -1.0 };
+                }
 
                 let output_cpu: Tensor2D = Tensor2D::relu(&input);
 
@@ -99,27 +89,15 @@ mod tests {
                 ];
 
                 let fuse_operators: bool = false;
-                let cache_elements: bool = false;
-                let mut graph_runner: GraphRunnerGPU = GraphRunnerGPU::new(
-                    &gpu_handles,
-                    &graph_operators,
-                    fuse_operators,
-                    cache_elements,
-                );
-                let output: Tensor2D = pollster::block_on(graph_runner.run(&gpu_handles, 1));
+                let output: Tensor2D = run_on_interpreter(&graph_operators, fuse_operators);
 
-                let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
-                println!("{:?}", difference);
-                println!("{:?}", difference.data.iter().map(|x| x.abs()).sum::<f32>());
+                assert_close("relu", &output_cpu.data, &output.data, ERROR_TOLERANCE);
             }
         }
     }
 
     #[test]
     fn softmax() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in graph_runner_test::softmax() test");
-
         let outer_dimension_range: usize = 8;
         let inner_dimension_range: usize = 8;
 
@@ -136,27 +114,15 @@ mod tests {
                 ];
 
                 let fuse_operators: bool = false;
-                let cache_elements: bool = false;
-                let mut graph_runner: GraphRunnerGPU = GraphRunnerGPU::new(
-                    &gpu_handles,
-                    &graph_operators,
-                    fuse_operators,
-                    cache_elements,
-                );
-                let output: Tensor2D = pollster::block_on(graph_runner.run(&gpu_handles, 1));
+                let output: Tensor2D = run_on_interpreter(&graph_operators, fuse_operators);
 
-                let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
-                println!("{:?}", difference);
-                println!("{:?}", difference.data.iter().map(|x| x.abs()).sum::<f32>());
+                assert_close("softmax", &output_cpu.data, &output.data, ERROR_TOLERANCE);
             }
         }
     }
 
     #[test]
     fn linear_relu() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in graph_runner_test::linear_relu() test");
-
         let outer_dimension_range: usize = 8;
         let inner_dimension_range: usize = 8;
 
@@ -180,18 +146,14 @@ mod tests {
                     ];
 
                     let fuse_operators: bool = false;
-                    let cache_elements: bool = false;
-                    let mut graph_runner: GraphRunnerGPU = GraphRunnerGPU::new(
-                        &gpu_handles,
-                        &graph_operators,
-                        fuse_operators,
-                        cache_elements,
-                    );
-                    let output: Tensor2D = pollster::block_on(graph_runner.run(&gpu_handles, 1));
+                    let output: Tensor2D = run_on_interpreter(&graph_operators, fuse_operators);
 
-                    let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
-                    println!("{:?}", difference);
-                    println!("{:?}", difference.data.iter().map(|x| x.abs()).sum::<f32>());
+                    assert_close(
+                        "linear_relu",
+                        &output_cpu.data,
+                        &output.data,
+                        ERROR_TOLERANCE,
+                    );
                 }
             }
         }
@@ -199,9 +161,6 @@ mod tests {
 
     #[test]
     fn linear_relu_softmax() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in graph_runner_test::linear_relu_softmax() test");
-
         let outer_dimension_range: usize = 8;
         let inner_dimension_range: usize = 8;
 
@@ -226,18 +185,14 @@ mod tests {
                     ];
 
                     let fuse_operators: bool = false;
-                    let cache_elements: bool = false;
-                    let mut graph_runner: GraphRunnerGPU = GraphRunnerGPU::new(
-                        &gpu_handles,
-                        &graph_operators,
-                        fuse_operators,
-                        cache_elements,
-                    );
-                    let output: Tensor2D = pollster::block_on(graph_runner.run(&gpu_handles, 1));
+                    let output: Tensor2D = run_on_interpreter(&graph_operators, fuse_operators);
 
-                    let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
-                    println!("{:?}", difference);
-                    println!("{:?}", difference.data.iter().map(|x| x.abs()).sum::<f32>());
+                    assert_close(
+                        "linear_relu_softmax",
+                        &output_cpu.data,
+                        &output.data,
+                        ERROR_TOLERANCE,
+                    );
                 }
             }
         }
@@ -245,9 +200,6 @@ mod tests {
 
     #[test]
     fn transfers() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in graph_runner_test::linear_relu_softmax() test");
-
         let outer_dimension_range: usize = 8;
         let inner_dimension_range: usize = 8;
 
@@ -263,19 +215,391 @@ mod tests {
                 ];
 
                 let fuse_operators: bool = false;
-                let cache_elements: bool = false;
-                let mut graph_runner: GraphRunnerGPU = GraphRunnerGPU::new(
-                    &gpu_handles,
-                    &graph_operators,
-                    fuse_operators,
-                    cache_elements,
+                let output: Tensor2D = run_on_interpreter(&graph_operators, fuse_operators);
+
+                assert_eq!(expected_output.data, output.data);
+            }
+        }
+    }
+
+    // Add, concat and the fused linear add, which the fusion rules produce from a residual
+    fn residual_graph() -> Vec<GraphOperator> {
+        vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(0.5, 5, 3),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(0.2, 3, 4),
+                bias: Tensor2D::new(-0.1, 5, 4),
+            },
+            GraphOperator::Store {
+                name: String::from("residual"),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(-0.3, 4, 4),
+                bias: Tensor2D::new(0.1, 5, 4),
+            },
+            GraphOperator::Add {
+                name: String::from("residual"),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Concat {
+                name: String::from("residual"),
+            },
+            GraphOperator::DeviceToHost,
+        ]
+    }
+
+    #[test]
+    fn add_and_concat() {
+        let graph_operators: Vec<GraphOperator> = residual_graph();
+        let expected_output: Tensor2D = GraphRunner::new(&graph_operators, false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+
+        for fuse_operators in [false, true] {
+            let output: Tensor2D = run_on_interpreter(&graph_operators, fuse_operators);
+            assert_eq!(output.column_count, expected_output.column_count);
+            assert_close(
+                &format!("fused: {}", fuse_operators),
+                &expected_output.data,
+                &output.data,
+                ERROR_TOLERANCE,
+            );
+        }
+    }
+
+    // activation.wgsl and main_with_activation in linear.wgsl against the CPU kernels
+    #[test]
+    fn activations() {
+        let functions: [ActivationFunction; 6] = [
+            ActivationFunction::GELUErf,
+            ActivationFunction::GELUTanh,
+            ActivationFunction::SiLU,
+            ActivationFunction::Tanh,
+            ActivationFunction::Sigmoid,
+            ActivationFunction::LeakyReLU { alpha: 0.1 },
+        ];
+        for function in functions {
+            let graph_operators: Vec<GraphOperator> = vec![
+                GraphOperator::HostToDevice {
+                    input: Tensor2D::new(0.5, 5, 3),
+                },
+                GraphOperator::Linear {
+                    weights: Tensor2D::new(-0.3, 3, 7),
+                    bias: Tensor2D::new(0.1, 5, 7),
+                },
+                GraphOperator::Activation { function },
+                GraphOperator::DeviceToHost,
+            ];
+            let expected_output: Tensor2D = GraphRunner::new(&graph_operators, false, false)
+                .unwrap()
+                .run()
+                .unwrap();
+
+            for fuse_operators in [false, true] {
+                let output: Tensor2D = run_on_interpreter(&graph_operators, fuse_operators);
+
+                assert_close(
+                    &format!("function: {:?} fused: {}", function, fuse_operators),
+                    &expected_output.data,
+                    &output.data,
+                    ERROR_TOLERANCE,
                 );
-                let output: Tensor2D = pollster::block_on(graph_runner.run(&gpu_handles, 1));
+            }
+        }
+    }
+
+    #[test]
+    fn planned_memory() {
+        let input: Tensor2D = Tensor2D::new(0.5, 5, 3);
+        let mut graph_operators: Vec<GraphOperator> = vec![GraphOperator::HostToDevice { input }];
+        for (inner_dimension, outer_dimension) in [(3, 7), (7, 2), (2, 6), (6, 4)] {
+            graph_operators.push(GraphOperator::Linear {
+                weights: Tensor2D::new(0.5, inner_dimension, outer_dimension),
+                bias: Tensor2D::new(0.1, 5, outer_dimension),
+            });
+            graph_operators.push(GraphOperator::ReLU);
+        }
+        graph_operators.push(GraphOperator::Softmax);
+        graph_operators.push(GraphOperator::DeviceToHost);
+
+        for fuse_operators in [false, true] {
+            let expected_output: Tensor2D = run_on_interpreter(&graph_operators, fuse_operators);
+
+            let mut graph_runner: GraphRunnerInterpreter =
+                GraphRunnerInterpreter::new(&graph_operators, fuse_operators).unwrap();
+            graph_runner
+                .plan_memory(MemoryPlanningStrategy::BestFit)
+                .unwrap();
+            let output: Tensor2D = graph_runner.run().unwrap();
+
+            assert_close(
+                &format!("fused: {}", fuse_operators),
+                &expected_output.data,
+                &output.data,
+                ERROR_TOLERANCE,
+            );
+        }
+    }
 
-                let difference: Tensor2D = Tensor2D::subtraction(&expected_output, &output);
-                println!("{:?}", difference);
-                println!("{:?}", difference.data.iter().map(|x| x.abs()).sum::<f32>());
+    #[test]
+    fn varying_batch() {
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(0.0, 4, 3),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(0.3, 3, 5),
+                bias: Tensor2D::new(0.1, 4, 5),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Softmax,
+            GraphOperator::DeviceToHost,
+        ];
+
+        let fuse_operators: bool = true;
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(&graph_operators, fuse_operators, false).unwrap();
+        let mut interpreter_runner: GraphRunnerInterpreter =
+            GraphRunnerInterpreter::new(&graph_operators, fuse_operators).unwrap();
+        for row_count in [4, 2, 9] {
+            let mut input: Tensor2D = Tensor2D::new(0.0, row_count, 3);
+            for (index, element) in input.data.iter_mut().enumerate() {
+                *element = (index % 7) as f32 * 0.25 - 0.5;
             }
+            graph_runner.set_input_batch(&input).unwrap();
+            interpreter_runner.set_input_batch(&input).unwrap();
+            let expected: Tensor2D = graph_runner.run().unwrap();
+            let output: Tensor2D = interpreter_runner.run().unwrap();
+
+            assert_eq!(output.row_count, row_count);
+            assert_close(
+                &format!("row_count: {}", row_count),
+                &expected.data,
+                &output.data,
+                ERROR_TOLERANCE,
+            );
+        }
+    }
+
+    // The losses and the quantized operators only have CPU kernels
+    #[test]
+    fn unsupported_operators() {
+        let input: Tensor2D = Tensor2D::new(0.5, 2, 3);
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::MeanSquaredError { target: input },
+            GraphOperator::DeviceToHost,
+        ];
+
+        let fuse_operators: bool = true;
+        assert_eq!(
+            GraphRunnerInterpreter::new(&graph_operators, fuse_operators).err(),
+            Some(GraphError::UnsupportedOperator {
+                node: 2,
+                operator: OperatorKind::MeanSquaredError
+            })
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GPU"]
+    fn dot_export() {
+        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
+            .expect("Failed to get GPU handles in graph_runner_test::dot_export() test");
+
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(0.5, 2, 3),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(0.1, 3, 4),
+                bias: Tensor2D::new(0.0, 2, 4),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::DeviceToHost,
+        ];
+
+        let fuse_operators: bool = true;
+        let cache_elements: bool = false;
+        let graph_runner: GraphRunnerGPU = GraphRunnerGPU::new(
+            &gpu_handles,
+            &graph_operators,
+            fuse_operators,
+            cache_elements,
+        )
+        .unwrap();
+        let dot: String = graph_runner.to_dot("fused");
+
+        assert!(dot.contains("\"Input_0\" -> \"buffer_0\";"));
+        assert!(dot.contains("\"buffer_0\" -> \"LinearReLU_0\";"));
+        assert!(dot.contains("\"LinearReLU_0\" -> \"buffer_3\";"));
+        assert!(dot.contains("\"buffer_3\" -> \"Output_0\";"));
+        assert!(dot.contains("\"buffer_3\" [shape=ellipse, label=\"3: 2x4\"];"));
+        assert!(dot.contains("fillcolor=lightblue"));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU"]
+    fn compiled_varying_batch() {
+        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true)).expect(
+            "Failed to get GPU handles in graph_runner_test::compiled_varying_batch() test",
+        );
+
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(0.0, 4, 3),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(0.3, 3, 5),
+                bias: Tensor2D::new(0.1, 4, 5),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Linear {
+                weights: Tensor2D::new(-0.2, 5, 2),
+                bias: Tensor2D::new(0.4, 4, 2),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+
+        let fuse_operators: bool = true;
+        let cache_elements: bool = true;
+        let mut compiled_graph: CompiledGraph =
+            CompiledGraph::compile(&graph_operators, fuse_operators, false).unwrap();
+        let mut compiled_graph_gpu: CompiledGraphGPU = CompiledGraphGPU::compile(
+            &gpu_handles,
+            &graph_operators,
+            fuse_operators,
+            cache_elements,
+        )
+        .unwrap();
+
+        for row_count in [4, 2, 9] {
+            let mut input: Tensor2D = Tensor2D::new(0.0, row_count, 3);
+            for (index, element) in input.data.iter_mut().enumerate() {
+                *element = (index % 7) as f32 * 0.25 - 0.5;
+            }
+            let expected: Tensor2D = compiled_graph.run(&input).unwrap();
+            let output: Tensor2D =
+                pollster::block_on(compiled_graph_gpu.run(&gpu_handles, &input)).unwrap();
+            assert_eq!(output.row_count, row_count);
+            assert_close(
+                &format!("row_count: {}", row_count),
+                &expected.data,
+                &output.data,
+                ERROR_TOLERANCE,
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU"]
+    fn shared_pipeline_cache() {
+        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
+            .expect("Failed to get GPU handles in graph_runner_test::shared_pipeline_cache() test");
+
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(0.5, 3, 4),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(0.2, 4, 6),
+                bias: Tensor2D::new(0.1, 3, 6),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Softmax,
+            GraphOperator::DeviceToHost,
+        ];
+
+        let fuse_operators: bool = true;
+        let cache_elements: bool = true;
+        let mut first_runner: GraphRunnerGPU = GraphRunnerGPU::new(
+            &gpu_handles,
+            &graph_operators,
+            fuse_operators,
+            cache_elements,
+        )
+        .unwrap();
+        let first_statistics: PipelineCacheStatistics = gpu_handles.pipeline_cache.statistics();
+        assert!(0 < first_statistics.misses);
+
+        // The second runner finds every pipeline it needs in the cache of the device
+        let mut second_runner: GraphRunnerGPU = GraphRunnerGPU::new(
+            &gpu_handles,
+            &graph_operators,
+            fuse_operators,
+            cache_elements,
+        )
+        .unwrap();
+        let second_statistics: PipelineCacheStatistics = gpu_handles.pipeline_cache.statistics();
+        assert_eq!(second_statistics.misses, first_statistics.misses);
+        assert!(first_statistics.hits < second_statistics.hits);
+        assert_eq!(
+            second_statistics.modules_compiled,
+            first_statistics.modules_compiled
+        );
+
+        // A cache of its own starts out empty and leaves the one of the device alone
+        let pipeline_cache: Arc<PipelineCache> = Arc::new(PipelineCache::new());
+        let mut third_runner: GraphRunnerGPU = GraphRunnerGPU::with_pipeline_cache(
+            &gpu_handles,
+            &graph_operators,
+            fuse_operators,
+            pipeline_cache.clone(),
+        )
+        .unwrap();
+        assert_eq!(pipeline_cache.statistics().misses, first_statistics.misses);
+        assert_eq!(gpu_handles.pipeline_cache.statistics(), second_statistics);
+
+        let expected: Tensor2D = pollster::block_on(first_runner.run(&gpu_handles, 1)).unwrap();
+        for runner in [&mut second_runner, &mut third_runner] {
+            let output: Tensor2D = pollster::block_on(runner.run(&gpu_handles, 1)).unwrap();
+            assert_eq!(expected.data, output.data);
+        }
+    }
+
+    // The interpreter is only a stand-in if it computes what the GPU computes
+    #[test]
+    #[ignore = "needs a GPU"]
+    fn gpu_matches_interpreter() {
+        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true)).expect(
+            "Failed to get GPU handles in graph_runner_test::gpu_matches_interpreter() test",
+        );
+
+        let mut graph_operators: Vec<GraphOperator> = residual_graph();
+        let last_index: usize = graph_operators.len() - 1;
+        graph_operators.insert(
+            last_index,
+            GraphOperator::Activation {
+                function: ActivationFunction::GELUTanh,
+            },
+        );
+        graph_operators.insert(last_index + 1, GraphOperator::Softmax);
+
+        for fuse_operators in [false, true] {
+            let cache_elements: bool = true;
+            let mut graph_runner: GraphRunnerGPU = GraphRunnerGPU::new(
+                &gpu_handles,
+                &graph_operators,
+                fuse_operators,
+                cache_elements,
+            )
+            .unwrap();
+            let expected: Tensor2D = run_on_interpreter(&graph_operators, fuse_operators);
+            let output: Tensor2D = pollster::block_on(graph_runner.run(&gpu_handles, 1)).unwrap();
+
+            // The exp and tanh of the GPU aren't required to be as accurate as the CPU ones
+            assert_close(
+                &format!("fused: {}", fuse_operators),
+                &expected.data,
+                &output.data,
+                10.0 * ERROR_TOLERANCE,
+            );
         }
     }
 }
@@ -0,0 +1,193 @@
+use crate::shader_interpreter::device::InterpreterDevice;
+use crate::shared::graph_operators::GraphOperator;
+use crate::shared::quantized_tensor2d::QuantizedTensor2D;
+use crate::shared::tensor2d::Tensor2D;
+
+use super::backend::{Backend, BackendRunner};
+use super::graph_error::GraphError;
+use super::graph_runner_gpu::GPUBackend;
+use super::memory_planner::{MemoryPlanningStrategy, MemoryReport};
+use super::nodes::{Node, NodeOperator};
+use super::nodes_interpreter;
+
+// Runs the WGSL kernels of GraphRunnerGPU on the CPU with shader_interpreter, so the GPU
+// path of the graph runner can be tested on machines without a GPU. The tensors live on
+// the host and every node is run as soon as it is dispatched. It is much slower than
+// GraphRunner, which runs the same operators with Rust kernels.
+#[derive(Default)]
+pub struct InterpreterBackend {
+    pub device: InterpreterDevice,
+}
+
+impl Backend for InterpreterBackend {
+    type Context = ();
+    type Tensor = Tensor2D;
+    type Commands = ();
+
+    const NAME: &'static str = "interpreter";
+
+    // The same kernels as GraphRunnerGPU
+    fn check_supported_operators(graph_operators: &[GraphOperator]) -> Result<(), GraphError> {
+        GPUBackend::check_supported_operators(graph_operators)
+    }
+
+    fn upload(&mut self, _context: &(), _label: &str, tensor: &Tensor2D) -> Tensor2D {
+        tensor.clone()
+    }
+
+    fn allocate(
+        &mut self,
+        _context: &(),
+        _label: &str,
+        row_count: usize,
+        column_count: usize,
+    ) -> Tensor2D {
+        Tensor2D::new(0.0, row_count, column_count)
+    }
+
+    // Rejected by check_supported_operators
+    fn upload_quantized(&mut self, _context: &(), _tensor: QuantizedTensor2D) -> usize {
+        unreachable!("GraphRunnerInterpreter does not support the quantized operators");
+    }
+
+    fn shape(tensor: &Tensor2D) -> (usize, usize) {
+        (tensor.row_count, tensor.column_count)
+    }
+
+    fn set_dimensions(tensor: &mut Tensor2D, row_count: usize, column_count: usize) {
+        tensor.set_dimensions(row_count, column_count);
+    }
+
+    fn resize_rows(
+        &mut self,
+        _context: &(),
+        _label: &str,
+        tensor: &mut Tensor2D,
+        row_count: usize,
+        repeat_first_row: bool,
+    ) {
+        let column_count: usize = tensor.column_count;
+        let element_count: usize = row_count * column_count;
+        if tensor.data.len() < element_count {
+            tensor.data.resize(element_count, 0.0);
+        }
+        tensor.set_dimensions(row_count, column_count);
+
+        if repeat_first_row {
+            for row_index in 1..row_count {
+                tensor
+                    .data
+                    .copy_within(0..column_count, row_index * column_count);
+            }
+        }
+    }
+
+    fn write(&mut self, _context: &(), tensor: &mut Tensor2D, input: &Tensor2D) {
+        let element_count: usize = input.len();
+        tensor.data[0..element_count].copy_from_slice(&input.data[0..element_count]);
+    }
+
+    fn begin(&mut self, _context: &()) {}
+
+    fn dispatch(
+        &mut self,
+        _context: &(),
+        node: &Node,
+        data_buffers: &mut [Tensor2D],
+        _commands: &mut (),
+    ) -> Result<(), GraphError> {
+        let device: &mut InterpreterDevice = &mut self.device;
+        match node.operator {
+            NodeOperator::Input => {}
+            NodeOperator::Output => {}
+            NodeOperator::Transfer => {}
+            NodeOperator::Linear => {
+                nodes_interpreter::linear(device, node, data_buffers, false)?;
+            }
+            NodeOperator::ReLU => {
+                nodes_interpreter::relu(device, node, data_buffers)?;
+            }
+            NodeOperator::Softmax => {
+                nodes_interpreter::softmax(device, node, data_buffers)?;
+            }
+            NodeOperator::LinearReLU => {
+                nodes_interpreter::linear(device, node, data_buffers, true)?;
+            }
+            NodeOperator::LinearReLUSoftmax => {
+                nodes_interpreter::linear_relu_softmax(device, node, data_buffers)?;
+            }
+            NodeOperator::Add => {
+                nodes_interpreter::add(device, node, data_buffers)?;
+            }
+            NodeOperator::Concat => {
+                nodes_interpreter::concat(device, node, data_buffers)?;
+            }
+            NodeOperator::LinearAdd => {
+                nodes_interpreter::linear_add(device, node, data_buffers)?;
+            }
+            NodeOperator::AddReLU => {
+                nodes_interpreter::add_relu(device, node, data_buffers)?;
+            }
+            NodeOperator::Activation(function) => {
+                nodes_interpreter::activation(device, node, data_buffers, function)?;
+            }
+            NodeOperator::LinearActivation(function) => {
+                nodes_interpreter::linear_activation(device, node, data_buffers, function)?;
+            }
+            // Rejected by check_supported_operators
+            NodeOperator::MeanSquaredError
+            | NodeOperator::SoftmaxCrossEntropy
+            | NodeOperator::LinearInt8
+            | NodeOperator::LinearReLUInt8 => {
+                unreachable!(
+                    "GraphRunnerInterpreter does not support the operator {:?}",
+                    node.operator
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn submit(&mut self, _context: &(), _commands: ()) {}
+
+    async fn read(&mut self, _context: &(), tensor: &mut Tensor2D) -> Tensor2D {
+        tensor.clone()
+    }
+}
+
+pub type GraphRunnerInterpreter = BackendRunner<InterpreterBackend>;
+
+impl GraphRunnerInterpreter {
+    // Returns the first problem found by validate_graph_operators if the graph is invalid
+    pub fn new(
+        graph_operators: &[GraphOperator],
+        fuse_operators: bool,
+    ) -> Result<Self, GraphError> {
+        Self::with_backend(
+            InterpreterBackend::default(),
+            &(),
+            graph_operators,
+            fuse_operators,
+        )
+    }
+
+    // See BackendRunner::plan_memory_with
+    pub fn plan_memory(
+        &mut self,
+        strategy: MemoryPlanningStrategy,
+    ) -> Result<MemoryReport, GraphError> {
+        self.plan_memory_with(&(), strategy)
+    }
+
+    // See BackendRunner::set_input_batch_with
+    pub fn set_input_batch(&mut self, input_batch: &Tensor2D) -> Result<(), GraphError> {
+        self.set_input_batch_with(&(), input_batch)
+    }
+
+    pub fn run(&mut self) -> Result<Tensor2D, GraphError> {
+        self.submit_operator_commands(&())?;
+
+        Ok(self.data_buffers[self.output_index()].clone())
+    }
+}
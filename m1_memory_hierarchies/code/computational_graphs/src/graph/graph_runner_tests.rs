@@ -1,12 +1,27 @@
 #[cfg(test)]
 mod tests {
 
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+    use rayon::{ThreadPool, ThreadPoolBuilder};
+
     use crate::{
-        graph::graph_runner::GraphRunner,
-        shared::{graph_operators::GraphOperator, tensor2d::Tensor2D},
+        graph::{
+            fusion::OperatorKind,
+            graph_error::GraphError,
+            graph_runner::GraphRunner,
+            graph_validation::validate_graph_operators,
+            nodes::{self, Node, NodeOperator},
+        },
+        shared::{
+            activation::ActivationFunction, graph_operators::GraphOperator, tensor2d::Tensor2D,
+            tensor2d_test_utilities::random_tensor,
+        },
     };
 
     const ERROR_TOLERANCE: f32 = 0.00001;
+    const FINITE_DIFFERENCE_EPSILON: f32 = 0.01;
+    const FINITE_DIFFERENCE_TOLERANCE: f32 = 0.01;
 
     // This is for verification purposes only
     // we don't care about making this fast
@@ -55,9 +70,10 @@ mod tests {
                     ];
 
                     let fuse_operators: bool = false;
+                    let parallel: bool = false;
                     let mut graph_runner: GraphRunner =
-                        GraphRunner::new(&graph_operators, fuse_operators);
-                    let output: Tensor2D = graph_runner.run();
+                        GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+                    let output: Tensor2D = graph_runner.run().unwrap();
 
                     let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
                     println!("{:?}", difference);
@@ -85,9 +101,10 @@ mod tests {
                 ];
 
                 let fuse_operators: bool = false;
+                let parallel: bool = false;
                 let mut graph_runner: GraphRunner =
-                    GraphRunner::new(&graph_operators, fuse_operators);
-                let output: Tensor2D = graph_runner.run();
+                    GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+                let output: Tensor2D = graph_runner.run().unwrap();
 
                 let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
                 println!("{:?}", difference);
@@ -114,9 +131,10 @@ mod tests {
                 ];
 
                 let fuse_operators: bool = false;
+                let parallel: bool = false;
                 let mut graph_runner: GraphRunner =
-                    GraphRunner::new(&graph_operators, fuse_operators);
-                let output: Tensor2D = graph_runner.run();
+                    GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+                let output: Tensor2D = graph_runner.run().unwrap();
 
                 let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
                 println!("{:?}", difference);
@@ -150,9 +168,10 @@ mod tests {
                     ];
 
                     let fuse_operators: bool = false;
+                    let parallel: bool = false;
                     let mut graph_runner: GraphRunner =
-                        GraphRunner::new(&graph_operators, fuse_operators);
-                    let output: Tensor2D = graph_runner.run();
+                        GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+                    let output: Tensor2D = graph_runner.run().unwrap();
 
                     let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
                     println!("{:?}", difference);
@@ -188,9 +207,10 @@ mod tests {
                     ];
 
                     let fuse_operators: bool = false;
+                    let parallel: bool = false;
                     let mut graph_runner: GraphRunner =
-                        GraphRunner::new(&graph_operators, fuse_operators);
-                    let output: Tensor2D = graph_runner.run();
+                        GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+                    let output: Tensor2D = graph_runner.run().unwrap();
 
                     let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
                     println!("{:?}", difference);
@@ -217,9 +237,10 @@ mod tests {
                 ];
 
                 let fuse_operators: bool = false;
+                let parallel: bool = false;
                 let mut graph_runner: GraphRunner =
-                    GraphRunner::new(&graph_operators, fuse_operators);
-                let output: Tensor2D = graph_runner.run();
+                    GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+                let output: Tensor2D = graph_runner.run().unwrap();
 
                 let difference: Tensor2D = Tensor2D::subtraction(&expected_output, &output);
                 println!("{:?}", difference);
@@ -227,4 +248,667 @@ mod tests {
             }
         }
     }
+
+    // Weighting every output element differently keeps the gradient
+    // from summing to zero after a softmax.
+    fn weighted_sum_loss(output: &Tensor2D, loss_weights: &Tensor2D) -> f32 {
+        let mut loss: f32 = 0.0;
+        for index in 0..output.len() {
+            loss += output.data[index] * loss_weights.data[index];
+        }
+        loss
+    }
+
+    fn evaluate_loss(
+        graph_operators: &Vec<GraphOperator>,
+        fuse_operators: bool,
+        loss_weights: &Tensor2D,
+    ) -> f32 {
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(graph_operators, fuse_operators, false).unwrap();
+        let output: Tensor2D = graph_runner.run().unwrap();
+        weighted_sum_loss(&output, loss_weights)
+    }
+
+    fn linear_parameters_mut(
+        operator: &mut GraphOperator,
+    ) -> Option<(&mut Tensor2D, &mut Tensor2D)> {
+        match operator {
+            GraphOperator::Linear { weights, bias }
+            | GraphOperator::LinearReLUFused { weights, bias }
+            | GraphOperator::LinearReLUSoftmaxFused { weights, bias }
+            | GraphOperator::LinearActivationFused { weights, bias, .. } => Some((weights, bias)),
+            _ => None,
+        }
+    }
+
+    fn central_difference(
+        graph_operators: &Vec<GraphOperator>,
+        fuse_operators: bool,
+        loss_weights: &Tensor2D,
+        operator_index: usize,
+        is_bias: bool,
+        element_index: usize,
+    ) -> f32 {
+        let mut losses: [f32; 2] = [0.0; 2];
+        for (loss_index, offset) in [FINITE_DIFFERENCE_EPSILON, -FINITE_DIFFERENCE_EPSILON]
+            .iter()
+            .enumerate()
+        {
+            let mut perturbed: Vec<GraphOperator> = graph_operators.clone();
+            let (weights, bias) = linear_parameters_mut(&mut perturbed[operator_index]).unwrap();
+            let parameter: &mut Tensor2D = if is_bias { bias } else { weights };
+            parameter.data[element_index] += offset;
+            losses[loss_index] = evaluate_loss(&perturbed, fuse_operators, loss_weights);
+        }
+
+        (losses[0] - losses[1]) / (2.0 * FINITE_DIFFERENCE_EPSILON)
+    }
+
+    fn assert_gradients_match_finite_differences(
+        graph_operators: &Vec<GraphOperator>,
+        fuse_operators: bool,
+        rng: &mut ChaCha8Rng,
+    ) {
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(graph_operators, fuse_operators, false).unwrap();
+        let output: Tensor2D = graph_runner.run().unwrap();
+        let loss_weights: Tensor2D = random_tensor(rng, output.row_count, output.column_count);
+        graph_runner.backward(&loss_weights).unwrap();
+        let gradients: Vec<(&Tensor2D, &Tensor2D)> = graph_runner.linear_gradients();
+
+        let operator_indices: Vec<usize> = graph_operators
+            .iter()
+            .enumerate()
+            .filter(|(_, operator)| {
+                matches!(
+                    operator,
+                    GraphOperator::Linear { .. }
+                        | GraphOperator::LinearReLUFused { .. }
+                        | GraphOperator::LinearReLUSoftmaxFused { .. }
+                        | GraphOperator::LinearActivationFused { .. }
+                )
+            })
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(operator_indices.len(), gradients.len());
+
+        for (gradient_index, operator_index) in operator_indices.iter().enumerate() {
+            let (weights_gradient, bias_gradient) = gradients[gradient_index];
+            for (is_bias, gradient) in [(false, weights_gradient), (true, bias_gradient)] {
+                for element_index in 0..gradient.len() {
+                    let expected: f32 = central_difference(
+                        graph_operators,
+                        fuse_operators,
+                        &loss_weights,
+                        *operator_index,
+                        is_bias,
+                        element_index,
+                    );
+                    let found: f32 = gradient.data[element_index];
+                    assert!(
+                        (expected - found).abs() < FINITE_DIFFERENCE_TOLERANCE * expected.abs().max(1.0),
+                        "Gradient mismatch in operator {} (bias: {}) at element {}. Finite difference: {} backward: {}",
+                        operator_index,
+                        is_bias,
+                        element_index,
+                        expected,
+                        found
+                    );
+                }
+            }
+        }
+    }
+
+    fn multilayer_perceptron(
+        rng: &mut ChaCha8Rng,
+        sizes: &[usize],
+        row_count: usize,
+    ) -> Vec<GraphOperator> {
+        let mut graph_operators: Vec<GraphOperator> = vec![GraphOperator::HostToDevice {
+            input: random_tensor(rng, row_count, sizes[0]),
+        }];
+        for layer_index in 1..sizes.len() {
+            graph_operators.push(GraphOperator::Linear {
+                weights: random_tensor(rng, sizes[layer_index - 1], sizes[layer_index]),
+                bias: random_tensor(rng, row_count, sizes[layer_index]),
+            });
+            graph_operators.push(GraphOperator::ReLU);
+        }
+        graph_operators.push(GraphOperator::Softmax);
+        graph_operators.push(GraphOperator::DeviceToHost);
+        graph_operators
+    }
+
+    // The parallel runner has to give the same results as the single threaded one,
+    // fused or not, no matter how many threads the rows are split across.
+    #[test]
+    fn parallel() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(8);
+        for row_count in [1, 3, 17] {
+            let graph_operators: Vec<GraphOperator> =
+                multilayer_perceptron(&mut rng, &[5, 9, 7, 4], row_count);
+
+            for fuse_operators in [false, true] {
+                let expected: Tensor2D = GraphRunner::new(&graph_operators, fuse_operators, false)
+                    .unwrap()
+                    .run()
+                    .unwrap();
+
+                for thread_count in [1, 2, 3] {
+                    let thread_pool: ThreadPool = ThreadPoolBuilder::new()
+                        .num_threads(thread_count)
+                        .build()
+                        .unwrap();
+                    let parallel: bool = true;
+                    let mut graph_runner: GraphRunner =
+                        GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+                    let output: Tensor2D = thread_pool.install(|| graph_runner.run().unwrap());
+
+                    // The softmax sums are added up in a different order
+                    for index in 0..expected.len() {
+                        assert!(
+                            (expected.data[index] - output.data[index]).abs() < ERROR_TOLERANCE,
+                            "rows: {} fused: {} threads: {} index: {} expected: {} found: {}",
+                            row_count,
+                            fuse_operators,
+                            thread_count,
+                            index,
+                            expected.data[index],
+                            output.data[index]
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn backward_linear() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(1);
+        for (row_count, inner_dimension, column_count) in [(1, 1, 1), (2, 3, 4), (5, 4, 3)] {
+            let graph_operators: Vec<GraphOperator> = vec![
+                GraphOperator::HostToDevice {
+                    input: random_tensor(&mut rng, row_count, inner_dimension),
+                },
+                GraphOperator::Linear {
+                    weights: random_tensor(&mut rng, inner_dimension, column_count),
+                    bias: random_tensor(&mut rng, row_count, column_count),
+                },
+                GraphOperator::DeviceToHost,
+            ];
+            assert_gradients_match_finite_differences(&graph_operators, false, &mut rng);
+        }
+    }
+
+    #[test]
+    fn backward_multilayer_perceptron() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(2);
+        let graph_operators: Vec<GraphOperator> = multilayer_perceptron(&mut rng, &[4, 5, 3], 3);
+        assert_gradients_match_finite_differences(&graph_operators, false, &mut rng);
+    }
+
+    #[test]
+    fn backward_multilayer_perceptron_fused() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(3);
+        let graph_operators: Vec<GraphOperator> = multilayer_perceptron(&mut rng, &[4, 5, 3], 3);
+        assert_gradients_match_finite_differences(&graph_operators, true, &mut rng);
+    }
+
+    #[test]
+    fn backward_fused_operators() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(4);
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(&mut rng, 3, 4),
+            },
+            GraphOperator::LinearReLUFused {
+                weights: random_tensor(&mut rng, 4, 5),
+                bias: random_tensor(&mut rng, 3, 5),
+            },
+            GraphOperator::LinearReLUSoftmaxFused {
+                weights: random_tensor(&mut rng, 5, 2),
+                bias: random_tensor(&mut rng, 3, 2),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+        assert_gradients_match_finite_differences(&graph_operators, false, &mut rng);
+    }
+
+    const ACTIVATION_FUNCTIONS: [ActivationFunction; 6] = [
+        ActivationFunction::GELUErf,
+        ActivationFunction::GELUTanh,
+        ActivationFunction::SiLU,
+        ActivationFunction::Tanh,
+        ActivationFunction::Sigmoid,
+        ActivationFunction::LeakyReLU { alpha: 0.1 },
+    ];
+
+    fn linear_activation_graph(
+        rng: &mut ChaCha8Rng,
+        function: ActivationFunction,
+        row_count: usize,
+    ) -> Vec<GraphOperator> {
+        vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(rng, row_count, 4),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(rng, 4, 5),
+                bias: random_tensor(rng, row_count, 5),
+            },
+            GraphOperator::Activation { function },
+            GraphOperator::Linear {
+                weights: random_tensor(rng, 5, 3),
+                bias: random_tensor(rng, row_count, 3),
+            },
+            GraphOperator::DeviceToHost,
+        ]
+    }
+
+    // Every way of running the activation functions has to match applying
+    // the function to the output of the linear operator ourselves
+    #[test]
+    fn activations() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(9);
+        for function in ACTIVATION_FUNCTIONS {
+            let input: Tensor2D = random_tensor(&mut rng, 3, 4);
+            let weights: Tensor2D = random_tensor(&mut rng, 4, 5);
+            let bias: Tensor2D = random_tensor(&mut rng, 3, 5);
+
+            let mut expected: Tensor2D = Tensor2D::linear(&input, &weights, &bias);
+            for value in expected.data.iter_mut() {
+                *value = function.apply(*value);
+            }
+
+            let graph_operators: Vec<GraphOperator> = vec![
+                GraphOperator::HostToDevice { input },
+                GraphOperator::Linear { weights, bias },
+                GraphOperator::Activation { function },
+                GraphOperator::DeviceToHost,
+            ];
+
+            for fuse_operators in [false, true] {
+                for parallel in [false, true] {
+                    let mut graph_runner: GraphRunner =
+                        GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+                    assert_eq!(
+                        graph_runner.fusion_report().count("LinearActivation"),
+                        usize::from(fuse_operators)
+                    );
+                    let output: Tensor2D = graph_runner.run().unwrap();
+
+                    for index in 0..expected.len() {
+                        assert!(
+                            (expected.data[index] - output.data[index]).abs() < ERROR_TOLERANCE,
+                            "function: {:?} fused: {} parallel: {} index: {} expected: {} found: {}",
+                            function,
+                            fuse_operators,
+                            parallel,
+                            index,
+                            expected.data[index],
+                            output.data[index]
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn backward_activations() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(10);
+        for function in ACTIVATION_FUNCTIONS {
+            let graph_operators: Vec<GraphOperator> =
+                linear_activation_graph(&mut rng, function, 3);
+            assert_gradients_match_finite_differences(&graph_operators, false, &mut rng);
+            assert_gradients_match_finite_differences(&graph_operators, true, &mut rng);
+        }
+    }
+
+    // The hidden tensor is used three times, so its gradient has to be
+    // accumulated from the linear operator, the add and the concat.
+    #[test]
+    fn backward_residual() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(5);
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(&mut rng, 3, 4),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(&mut rng, 4, 5),
+                bias: random_tensor(&mut rng, 3, 5),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Store {
+                name: "hidden".to_string(),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(&mut rng, 5, 5),
+                bias: random_tensor(&mut rng, 3, 5),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Linear {
+                weights: random_tensor(&mut rng, 5, 5),
+                bias: random_tensor(&mut rng, 3, 5),
+            },
+            GraphOperator::Add {
+                name: "hidden".to_string(),
+            },
+            GraphOperator::Concat {
+                name: "hidden".to_string(),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(&mut rng, 10, 2),
+                bias: random_tensor(&mut rng, 3, 2),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+        assert!(validate_graph_operators(&graph_operators).is_ok());
+        assert_gradients_match_finite_differences(&graph_operators, false, &mut rng);
+        assert_gradients_match_finite_differences(&graph_operators, true, &mut rng);
+    }
+
+    // The fused LinearAdd and AddReLU operators. The first linear operator reads the
+    // same tensor it is added to, so the input and the residual are the same buffer.
+    #[test]
+    fn backward_fused_residual() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(6);
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(&mut rng, 3, 4),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(&mut rng, 4, 4),
+                bias: random_tensor(&mut rng, 3, 4),
+            },
+            GraphOperator::Store {
+                name: "hidden".to_string(),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(&mut rng, 4, 4),
+                bias: random_tensor(&mut rng, 3, 4),
+            },
+            GraphOperator::Add {
+                name: "hidden".to_string(),
+            },
+            GraphOperator::Store {
+                name: "block".to_string(),
+            },
+            GraphOperator::Softmax,
+            GraphOperator::Add {
+                name: "block".to_string(),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::DeviceToHost,
+        ];
+        assert!(validate_graph_operators(&graph_operators).is_ok());
+
+        let graph_runner: GraphRunner = GraphRunner::new(&graph_operators, true, false).unwrap();
+        assert_eq!(graph_runner.fusion_report().count("LinearAdd"), 1);
+        assert_eq!(graph_runner.fusion_report().count("AddReLU"), 1);
+
+        assert_gradients_match_finite_differences(&graph_operators, true, &mut rng);
+    }
+
+    // With a loss operator at the end, the output is a single value and the
+    // weighted sum loss is just a scaling of that value.
+    #[test]
+    fn backward_mean_squared_error() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(9);
+        let mut graph_operators: Vec<GraphOperator> =
+            multilayer_perceptron(&mut rng, &[4, 5, 3], 3);
+        let last_index: usize = graph_operators.len() - 1;
+        graph_operators[last_index] = GraphOperator::MeanSquaredError {
+            target: random_tensor(&mut rng, 3, 3),
+        };
+        graph_operators.push(GraphOperator::DeviceToHost);
+        assert_gradients_match_finite_differences(&graph_operators, false, &mut rng);
+    }
+
+    #[test]
+    fn backward_softmax_cross_entropy() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(10);
+        let mut graph_operators: Vec<GraphOperator> =
+            multilayer_perceptron(&mut rng, &[4, 5, 3], 3);
+        // The softmax is part of the loss operator
+        let softmax_index: usize = graph_operators.len() - 2;
+        graph_operators[softmax_index] = GraphOperator::SoftmaxCrossEntropy {
+            labels: vec![2, 0, 1],
+        };
+        assert_gradients_match_finite_differences(&graph_operators, true, &mut rng);
+    }
+
+    #[test]
+    fn loss_validation() {
+        let input: Tensor2D = Tensor2D::new(0.1, 2, 3);
+        let valid: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::SoftmaxCrossEntropy { labels: vec![0, 2] },
+            GraphOperator::DeviceToHost,
+        ];
+        assert!(validate_graph_operators(&valid).is_ok());
+
+        let label_out_of_range: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            GraphOperator::SoftmaxCrossEntropy { labels: vec![0, 3] },
+            GraphOperator::DeviceToHost,
+        ];
+        assert_eq!(
+            validate_graph_operators(&label_out_of_range),
+            Err(GraphError::InvalidLabel {
+                node: 1,
+                label: 3,
+                class_count: 3
+            })
+        );
+
+        let wrong_label_count: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            GraphOperator::SoftmaxCrossEntropy { labels: vec![0] },
+            GraphOperator::DeviceToHost,
+        ];
+        assert_eq!(
+            validate_graph_operators(&wrong_label_count),
+            Err(GraphError::DimensionMismatch {
+                node: 1,
+                expected: (1, 3),
+                found: (2, 3)
+            })
+        );
+
+        let wrong_target_shape: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            GraphOperator::MeanSquaredError {
+                target: Tensor2D::new(0.0, 3, 2),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+        assert_eq!(
+            validate_graph_operators(&wrong_target_shape),
+            Err(GraphError::DimensionMismatch {
+                node: 1,
+                expected: (3, 2),
+                found: (2, 3)
+            })
+        );
+
+        let loss_not_last: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            GraphOperator::MeanSquaredError {
+                target: input.clone(),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::DeviceToHost,
+        ];
+        assert_eq!(
+            validate_graph_operators(&loss_not_last),
+            Err(GraphError::MisplacedLoss { node: 1 })
+        );
+    }
+
+    #[test]
+    fn graph_errors() {
+        let input: Tensor2D = Tensor2D::new(0.1, 2, 3);
+        let linear = |input_size: usize| GraphOperator::Linear {
+            weights: Tensor2D::new(0.5, input_size, 4),
+            bias: Tensor2D::new(0.1, 2, 4),
+        };
+
+        let missing_transfer: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            linear(3),
+        ];
+        assert_eq!(
+            GraphRunner::new(&missing_transfer, false, false).err(),
+            Some(GraphError::MissingTransfer)
+        );
+
+        let misplaced_transfer: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+        assert_eq!(
+            GraphRunner::new(&misplaced_transfer, false, false).err(),
+            Some(GraphError::MisplacedTransfer { node: 1 })
+        );
+
+        // The second linear operator receives the 4 columns of the first
+        let dimension_mismatch: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            linear(3),
+            GraphOperator::ReLU,
+            linear(3),
+            GraphOperator::DeviceToHost,
+        ];
+        assert_eq!(
+            GraphRunner::new(&dimension_mismatch, true, false).err(),
+            Some(GraphError::DimensionMismatch {
+                node: 3,
+                expected: (2, 3),
+                found: (2, 4)
+            })
+        );
+
+        // The output of a 2x3 input and 3x4 weights is 2x4, which the bias has to match
+        for bias in [Tensor2D::new(0.1, 2, 5), Tensor2D::new(0.1, 5, 4)] {
+            let found: (usize, usize) = (bias.row_count, bias.column_count);
+            let bias_mismatch: Vec<GraphOperator> = vec![
+                GraphOperator::HostToDevice {
+                    input: input.clone(),
+                },
+                GraphOperator::Linear {
+                    weights: Tensor2D::new(0.5, 3, 4),
+                    bias,
+                },
+                GraphOperator::DeviceToHost,
+            ];
+            assert_eq!(
+                GraphRunner::new(&bias_mismatch, false, false).err(),
+                Some(GraphError::DimensionMismatch {
+                    node: 1,
+                    expected: (2, 4),
+                    found
+                })
+            );
+        }
+
+        let empty_before_linear: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            GraphOperator::Empty,
+            linear(3),
+            GraphOperator::DeviceToHost,
+        ];
+        assert_eq!(
+            validate_graph_operators(&empty_before_linear),
+            Err(GraphError::UnsupportedOperator {
+                node: 1,
+                operator: OperatorKind::Empty
+            })
+        );
+
+        let unknown_tensor: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            GraphOperator::Add {
+                name: "residual".to_string(),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+        assert_eq!(
+            validate_graph_operators(&unknown_tensor),
+            Err(GraphError::UnknownTensor {
+                node: 1,
+                name: "residual".to_string()
+            })
+        );
+
+        let invalid_alpha: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: input.clone(),
+            },
+            GraphOperator::Activation {
+                function: ActivationFunction::LeakyReLU { alpha: f32::NAN },
+            },
+            GraphOperator::DeviceToHost,
+        ];
+        assert!(matches!(
+            validate_graph_operators(&invalid_alpha),
+            Err(GraphError::InvalidActivation { node: 1, .. })
+        ));
+
+        let duplicate_name: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice { input },
+            GraphOperator::Store {
+                name: "residual".to_string(),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Store {
+                name: "residual".to_string(),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+        assert_eq!(
+            validate_graph_operators(&duplicate_name),
+            Err(GraphError::DuplicateTensorName {
+                node: 3,
+                name: "residual".to_string()
+            })
+        );
+
+        // The nodes check the buffers they are handed by the runner
+        let mut data_buffers: Vec<Tensor2D> = vec![Tensor2D::new(0.1, 2, 3); 3];
+        let node: Node = Node::new("ReLU_0".to_string(), NodeOperator::ReLU, vec![0, 1, 2]);
+        assert_eq!(
+            nodes::relu(&node, &mut data_buffers, false),
+            Err(GraphError::BufferCountMismatch {
+                node: "ReLU_0".to_string(),
+                expected: 2,
+                found: 3
+            })
+        );
+    }
 }
@@ -1,117 +1,143 @@
+use crate::shared::activation::ActivationFunction;
 use crate::shared::graph_operators::GraphOperator;
 use crate::shared::graph_operators::GraphOperator::*;
+use crate::shared::element::Element;
+use crate::shared::quantized_tensor2d::{QuantizationParameters, QuantizedTensor2D};
 use crate::shared::tensor2d::Tensor2D;
 
-pub fn linear_dimension_check(input: &Tensor2D, weights: &Tensor2D, bias: &Tensor2D) {
-    assert!(
-        0 < input.row_count,
-        "\ninput.row_count must be larger than 0. Current value: {}.",
-        input.row_count
-    );
-
-    assert!(
-        0 < input.column_count,
-        "\ninput.column_count must be larger than 0. Current value: {}.",
-        input.column_count
-    );
-
-    assert!(
-        0 < weights.row_count,
-        "\nweights.row_count must be larger than 0. Current value: {}.",
-        weights.row_count
-    );
-
-    assert!(
-        0 < weights.column_count,
-        "\nweights.column_count must be larger than 0. Current value: {}.",
-        weights.column_count
-    );
-
-    assert!(
-        0 < bias.row_count,
-        "\nbias.row_count must be larger than 0. Current value: {}.",
-        bias.row_count
-    );
-
-    assert!(
-        0 < bias.column_count,
-        "\nbias.column_count must be larger than 0. Current value: {}.",
-        bias.column_count
-    );
-
-    assert_eq!(
-        input.column_count,
-        weights.row_count,
-        "\nMismatch - input.column_count & weights.row_count\ninput - rows: {} columns: {}.\n weights - rows: {} columns: {}.", 
-        input.row_count,
-        input.column_count,
-        weights.row_count,
-        weights.column_count
-    );
+use super::fusion::OperatorKind;
+use super::graph_error::GraphError;
+
+// The weights can be of any element type, to also cover the quantized linear operators.
+// input_dimensions are the (row_count, column_count) of whatever comes into the operator.
+pub fn linear_dimension_check<T: Element>(
+    current_index: usize,
+    input_dimensions: (usize, usize),
+    weights: &Tensor2D<T>,
+    bias: &Tensor2D,
+) -> Result<(), GraphError> {
+    let (input_row_count, input_column_count): (usize, usize) = input_dimensions;
+    if input_row_count == 0
+        || input_column_count == 0
+        || weights.row_count == 0
+        || weights.column_count == 0
+        || bias.row_count == 0
+        || bias.column_count == 0
+    {
+        return Err(GraphError::EmptyTensor {
+            node: current_index,
+        });
+    }
+
+    if input_column_count != weights.row_count {
+        return Err(GraphError::DimensionMismatch {
+            node: current_index,
+            expected: (input_row_count, weights.row_count),
+            found: input_dimensions,
+        });
+    }
+
+    // The bias is added to the output element by element
+    if bias.row_count != input_row_count || bias.column_count != weights.column_count {
+        return Err(GraphError::DimensionMismatch {
+            node: current_index,
+            expected: (input_row_count, weights.column_count),
+            found: (bias.row_count, bias.column_count),
+        });
+    }
+
+    Ok(())
 }
 
 // Every dimension is legal in this operator, it is up to the other operators to reject
-fn validate_host_to_device(current_index: usize, graph: &[GraphOperator]) -> bool {
-    if let HostToDevice { input: _ } = &graph[current_index] {
-        if current_index != 0 {
-            println!("Something went wrong in validate_host_to_device. The HostToDevice operator was not at the beginning of the graph.");
-            return false;
-        }
-    } else {
-        println!("Something went wrong in validate_host_to_device. Current operator was not HostToDevice");
-        return false;
+fn validate_host_to_device(current_index: usize) -> Result<(), GraphError> {
+    if current_index != 0 {
+        return Err(GraphError::MisplacedTransfer {
+            node: current_index,
+        });
     }
 
-    true
+    Ok(())
 }
 
 // Every dimension is legal in this operator, it is up to the other operators to reject.
 // Normally this wouldn't be, but we have elected to overwrite the existing data whenever
 // an output is transferred back to the host.
-fn validate_device_to_host(current_index: usize, graph: &Vec<GraphOperator>) -> bool {
-    if let DeviceToHost = &graph[current_index] {
-        if current_index != (graph.len() - 1) {
-            println!("Something went wrong in validate_device_to_host. Current operator was not DeviceToHost");
-            return false;
-        }
-    } else {
-        println!("Something went wrong in validate_device_to_host. Current operator was not DeviceToHost");
-        return false;
+fn validate_device_to_host(
+    current_index: usize,
+    graph: &[GraphOperator],
+) -> Result<(), GraphError> {
+    if current_index != (graph.len() - 1) {
+        return Err(GraphError::MisplacedTransfer {
+            node: current_index,
+        });
     }
 
-    true
+    Ok(())
 }
 
-fn validate_linear_dimensions(
+fn validate_linear_dimensions<T: Element>(
     current_index: usize,
     graph: &[GraphOperator],
-    current_weights: &Tensor2D,
+    current_weights: &Tensor2D<T>,
     current_bias: &Tensor2D,
-) -> bool {
+) -> Result<(), GraphError> {
     // Search for nearest dimension dictating operation
     for predecessor_index in (0..current_index).rev() {
         match &graph[predecessor_index] {
             HostToDevice { input } => {
-                linear_dimension_check(input, current_weights, current_bias);
-                return true;
-            }
-            Linear { weights: _, bias } => {
-                linear_dimension_check(bias, current_weights, current_bias);
-                return true;
-            }
-            LinearReLUFused { weights: _, bias } => {
-                linear_dimension_check(bias, current_weights, current_bias);
-                return true;
+                return linear_dimension_check(
+                    current_index,
+                    (input.row_count, input.column_count),
+                    current_weights,
+                    current_bias,
+                );
             }
-            LinearReLUSoftmaxFused { weights: _, bias } => {
-                linear_dimension_check(bias, current_weights, current_bias);
-                return true;
+            Linear { weights: _, bias }
+            | LinearReLUFused { weights: _, bias }
+            | LinearReLUSoftmaxFused { weights: _, bias }
+            | LinearInt8 { bias, .. }
+            | LinearReLUInt8Fused { bias, .. }
+            | LinearAddFused { bias, .. }
+            | LinearActivationFused { bias, .. } => {
+                return linear_dimension_check(
+                    current_index,
+                    (bias.row_count, bias.column_count),
+                    current_weights,
+                    current_bias,
+                );
             }
             DeviceToHost => {
-                panic!("Found a DeviceToHost node before a linear layer node. This wasn't part of the contrived example!");
+                return Err(GraphError::MisplacedTransfer {
+                    node: predecessor_index,
+                });
             }
             Empty => {
-                panic!("Found an Empty node before a linear layer node. This wasn't part of the contrived example!");
+                return Err(GraphError::UnsupportedOperator {
+                    node: predecessor_index,
+                    operator: OperatorKind::Empty,
+                });
+            }
+            // Loss operators have to be the last operator before DeviceToHost
+            MeanSquaredError { target: _ } | SoftmaxCrossEntropy { labels: _ } => {
+                return Err(GraphError::MisplacedLoss {
+                    node: predecessor_index,
+                });
+            }
+            // The dimensions of named tensors have to be looked up through their Store operator
+            Load { .. } | Add { .. } | Concat { .. } | AddReLUFused { .. } => {
+                let input_dimensions: (usize, usize) =
+                    find_input_dimensions(predecessor_index + 1, graph).ok_or(
+                        GraphError::MissingInput {
+                            node: current_index,
+                        },
+                    )?;
+                return linear_dimension_check(
+                    current_index,
+                    input_dimensions,
+                    current_weights,
+                    current_bias,
+                );
             }
             _ => {
                 //Predecessor operator was probably ReLU or Softmax
@@ -119,99 +145,387 @@ fn validate_linear_dimensions(
         }
     }
 
-    true
+    Ok(())
+}
+
+// On top of the dimensions, the quantization parameters have to make sense.
+// The weights need either a single set of parameters or one per column, and every
+// scale has to be positive to be able to quantize anything.
+fn validate_quantized_linear(
+    current_index: usize,
+    graph: &[GraphOperator],
+    weights: &QuantizedTensor2D,
+    bias: &Tensor2D,
+    input_parameters: &QuantizationParameters,
+) -> Result<(), GraphError> {
+    if weights.parameters.len() != 1 && weights.parameters.len() != weights.tensor.column_count {
+        return Err(GraphError::InvalidQuantization {
+            node: current_index,
+            message: format!(
+                "the weights had {} sets of quantization parameters for {} columns",
+                weights.parameters.len(),
+                weights.tensor.column_count
+            ),
+        });
+    }
+
+    let scales_are_valid: bool = weights
+        .parameters
+        .iter()
+        .chain(std::iter::once(input_parameters))
+        .all(|parameters| 0.0 < parameters.scale && parameters.scale.is_finite());
+    if !scales_are_valid {
+        return Err(GraphError::InvalidQuantization {
+            node: current_index,
+            message: "found a quantization scale which was not positive and finite".to_string(),
+        });
+    }
+
+    validate_linear_dimensions(current_index, graph, &weights.tensor, bias)
+}
+
+// Every function is defined for any input, but the slope of LeakyReLU has to be a number
+fn validate_activation(
+    current_index: usize,
+    function: &ActivationFunction,
+) -> Result<(), GraphError> {
+    if let ActivationFunction::LeakyReLU { alpha } = function {
+        if !alpha.is_finite() {
+            return Err(GraphError::InvalidActivation {
+                node: current_index,
+                message: format!("the LeakyReLU had the slope {}, which is not finite", alpha),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// The loss operators reduce everything to a single value, so nothing
+// but the transfer back to the host can come after them.
+fn validate_loss_position(current_index: usize, graph: &[GraphOperator]) -> Result<(), GraphError> {
+    if current_index + 2 != graph.len() {
+        return Err(GraphError::MisplacedLoss {
+            node: current_index,
+        });
+    }
+
+    Ok(())
+}
+
+// Search for the nearest operator which dictates the dimensions of the input to current_index.
+// ReLU and Softmax keep the dimensions of their input.
+fn find_input_dimensions(current_index: usize, graph: &[GraphOperator]) -> Option<(usize, usize)> {
+    for predecessor_index in (0..current_index).rev() {
+        match &graph[predecessor_index] {
+            HostToDevice { input } => {
+                return Some((input.row_count, input.column_count));
+            }
+            Linear { weights: _, bias }
+            | LinearReLUFused { weights: _, bias }
+            | LinearReLUSoftmaxFused { weights: _, bias }
+            | LinearInt8 { bias, .. }
+            | LinearReLUInt8Fused { bias, .. }
+            | LinearAddFused { bias, .. }
+            | LinearActivationFused { bias, .. } => {
+                return Some((bias.row_count, bias.column_count));
+            }
+            MeanSquaredError { target: _ } | SoftmaxCrossEntropy { labels: _ } => {
+                return Some((1, 1));
+            }
+            Load { name } => {
+                return find_store_index(predecessor_index, graph, name)
+                    .and_then(|store_index| find_input_dimensions(store_index, graph));
+            }
+            Add { .. } | AddReLUFused { .. } => {
+                return find_input_dimensions(predecessor_index, graph);
+            }
+            Concat { name } => {
+                let (row_count, column_count): (usize, usize) =
+                    find_input_dimensions(predecessor_index, graph)?;
+                let store_index: usize = find_store_index(predecessor_index, graph, name)?;
+                let (_, other_column_count): (usize, usize) =
+                    find_input_dimensions(store_index, graph)?;
+                return Some((row_count, column_count + other_column_count));
+            }
+            _ => {
+                //Predecessor operator was probably ReLU, Softmax or Store
+            }
+        }
+    }
+
+    None
+}
+
+// Same as find_input_dimensions, but not finding anything is an error
+fn require_input_dimensions(
+    current_index: usize,
+    graph: &[GraphOperator],
+) -> Result<(usize, usize), GraphError> {
+    find_input_dimensions(current_index, graph).ok_or(GraphError::MissingInput {
+        node: current_index,
+    })
+}
+
+// The index of the Store operator giving a tensor its name, if it comes before current_index
+fn find_store_index(current_index: usize, graph: &[GraphOperator], name: &str) -> Option<usize> {
+    (0..current_index)
+        .rev()
+        .find(|index| matches!(&graph[*index], Store { name: stored } if stored == name))
+}
+
+// Names have to be unique, otherwise the named tensor a Load, Add or Concat
+// refers to would depend on where in the graph it was.
+fn validate_store(
+    current_index: usize,
+    graph: &[GraphOperator],
+    name: &str,
+) -> Result<(), GraphError> {
+    if find_store_index(current_index, graph, name).is_some() {
+        return Err(GraphError::DuplicateTensorName {
+            node: current_index,
+            name: name.to_string(),
+        });
+    }
+
+    require_input_dimensions(current_index, graph)?;
+
+    Ok(())
+}
+
+// Returns the dimensions of the named tensor, if it was stored before current_index
+fn find_named_dimensions(
+    current_index: usize,
+    graph: &[GraphOperator],
+    name: &str,
+) -> Result<(usize, usize), GraphError> {
+    match find_store_index(current_index, graph, name) {
+        Some(store_index) => require_input_dimensions(store_index, graph),
+        None => Err(GraphError::UnknownTensor {
+            node: current_index,
+            name: name.to_string(),
+        }),
+    }
+}
+
+fn validate_load(
+    current_index: usize,
+    graph: &[GraphOperator],
+    name: &str,
+) -> Result<(), GraphError> {
+    find_named_dimensions(current_index, graph, name)?;
+
+    Ok(())
+}
+
+// The current tensor and the named tensor have to have the same dimensions
+fn validate_add(
+    current_index: usize,
+    graph: &[GraphOperator],
+    name: &str,
+) -> Result<(), GraphError> {
+    let other_dimensions: (usize, usize) = find_named_dimensions(current_index, graph, name)?;
+    let input_dimensions: (usize, usize) = require_input_dimensions(current_index, graph)?;
+
+    if input_dimensions != other_dimensions {
+        return Err(GraphError::DimensionMismatch {
+            node: current_index,
+            expected: other_dimensions,
+            found: input_dimensions,
+        });
+    }
+
+    Ok(())
+}
+
+// The output of the linear operator and the named tensor have to have the same dimensions
+fn validate_linear_add(
+    current_index: usize,
+    graph: &[GraphOperator],
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    name: &str,
+) -> Result<(), GraphError> {
+    validate_linear_dimensions(current_index, graph, weights, bias)?;
+
+    let other_dimensions: (usize, usize) = find_named_dimensions(current_index, graph, name)?;
+    let output_dimensions: (usize, usize) = (bias.row_count, bias.column_count);
+
+    if output_dimensions != other_dimensions {
+        return Err(GraphError::DimensionMismatch {
+            node: current_index,
+            expected: other_dimensions,
+            found: output_dimensions,
+        });
+    }
+
+    Ok(())
+}
+
+// The current tensor and the named tensor have to have the same number of rows
+fn validate_concat(
+    current_index: usize,
+    graph: &[GraphOperator],
+    name: &str,
+) -> Result<(), GraphError> {
+    let (other_row_count, _): (usize, usize) = find_named_dimensions(current_index, graph, name)?;
+    let (row_count, column_count): (usize, usize) = require_input_dimensions(current_index, graph)?;
+
+    if row_count != other_row_count {
+        return Err(GraphError::DimensionMismatch {
+            node: current_index,
+            expected: (other_row_count, column_count),
+            found: (row_count, column_count),
+        });
+    }
+
+    Ok(())
 }
 
-fn validate_relu(current_index: usize, graph: &[GraphOperator]) -> bool {
-    if let ReLU {} = &graph[current_index] {
-    } else {
-        println!("Something went wrong in validate_relu. Current operator was not HostToDevice");
-        return false;
+fn validate_mean_squared_error(
+    current_index: usize,
+    graph: &[GraphOperator],
+    target: &Tensor2D,
+) -> Result<(), GraphError> {
+    validate_loss_position(current_index, graph)?;
+
+    let input_dimensions: (usize, usize) = require_input_dimensions(current_index, graph)?;
+    let target_dimensions: (usize, usize) = (target.row_count, target.column_count);
+
+    if input_dimensions != target_dimensions {
+        return Err(GraphError::DimensionMismatch {
+            node: current_index,
+            expected: target_dimensions,
+            found: input_dimensions,
+        });
     }
 
-    true
+    Ok(())
 }
 
-fn validate_softmax(current_index: usize, graph: &[GraphOperator]) -> bool {
-    if let Softmax {} = &graph[current_index] {
-    } else {
-        println!("Something went wrong in validate_softmax. Current operator was not HostToDevice");
-        return false;
+// There has to be exactly one label per row and every label has to be a valid column index.
+fn validate_softmax_cross_entropy(
+    current_index: usize,
+    graph: &[GraphOperator],
+    labels: &[usize],
+) -> Result<(), GraphError> {
+    validate_loss_position(current_index, graph)?;
+
+    let (row_count, column_count): (usize, usize) = require_input_dimensions(current_index, graph)?;
+
+    if row_count != labels.len() {
+        return Err(GraphError::DimensionMismatch {
+            node: current_index,
+            expected: (labels.len(), column_count),
+            found: (row_count, column_count),
+        });
+    }
+
+    if let Some(label) = labels.iter().find(|label| column_count <= **label) {
+        return Err(GraphError::InvalidLabel {
+            node: current_index,
+            label: *label,
+            class_count: column_count,
+        });
     }
 
-    true
+    Ok(())
 }
 
 // For our contrived example, for a graph to be valid it has to begin
 // with HostToDevice and end with DeviceToHost, perhaps later
 // we will support running the same input in a loop, or
 // running a graph with new input every time.
-fn validate_transfers(graph: &Vec<GraphOperator>) -> bool {
-    let mut found_valid_host_to_device: bool = false;
-    let mut found_valid_device_to_host: bool = false;
+fn validate_transfers(graph: &[GraphOperator]) -> Result<(), GraphError> {
+    let mut found_host_to_device: bool = false;
+    let mut found_device_to_host: bool = false;
 
-    for index in 0..graph.len() {
-        let operator: &GraphOperator = &graph[index];
+    for (index, operator) in graph.iter().enumerate() {
         match operator {
             HostToDevice { input } => {
-                if index == 0 {
-                    if 0 < input.row_count && 0 < input.column_count {
-                        found_valid_host_to_device = true;
-                    }
-                } else {
-                    return false;
+                validate_host_to_device(index)?;
+                if input.row_count == 0 || input.column_count == 0 {
+                    return Err(GraphError::EmptyTensor { node: index });
                 }
+                found_host_to_device = true;
             }
             DeviceToHost => {
-                if index == graph.len() - 1 {
-                    found_valid_device_to_host = true;
-                } else {
-                    return false;
-                }
+                validate_device_to_host(index, graph)?;
+                found_device_to_host = true;
             }
             _ => {}
         }
     }
 
-    found_valid_device_to_host && found_valid_host_to_device
+    if !found_host_to_device || !found_device_to_host {
+        return Err(GraphError::MissingTransfer);
+    }
+
+    Ok(())
 }
 
 // Just for learning purposes the only real requirements we will have will be
 // matching dimensions and each graph beginning with a transfer to device
 // and ending with a transfer from device
 // All validation is retrospective, each operator will look for valid predecessors.
-pub fn validate_graph_operators(graph: &Vec<GraphOperator>) -> bool {
-    let graph_length: usize = graph.len();
-    let mut graph_is_validated: bool = true;
-
-    graph_is_validated = graph_is_validated && validate_transfers(graph);
+// The first problem found is returned.
+pub fn validate_graph_operators(graph: &[GraphOperator]) -> Result<(), GraphError> {
+    validate_transfers(graph)?;
 
     // Scanning graph for valid sizes
-    for current_index in 0..graph_length {
-        let current: &GraphOperator = &graph[current_index];
-
-        let valid_operator: bool = match current {
-            GraphOperator::Empty => {
-                continue;
+    for (current_index, current) in graph.iter().enumerate() {
+        match current {
+            // The transfers were checked by validate_transfers. ReLU and Softmax
+            // keep the dimensions of their input, so anything goes. So do the other
+            // activation functions, but they have a parameter to check.
+            GraphOperator::Empty
+            | GraphOperator::HostToDevice { input: _ }
+            | GraphOperator::DeviceToHost
+            | GraphOperator::ReLU
+            | GraphOperator::Softmax => {}
+            GraphOperator::Linear { weights, bias }
+            | GraphOperator::LinearReLUFused { weights, bias }
+            | GraphOperator::LinearReLUSoftmaxFused { weights, bias } => {
+                validate_linear_dimensions(current_index, graph, weights, bias)?
+            }
+            GraphOperator::MeanSquaredError { target } => {
+                validate_mean_squared_error(current_index, graph, target)?
             }
-            GraphOperator::HostToDevice { input: _ } => {
-                validate_host_to_device(current_index, graph)
+            GraphOperator::SoftmaxCrossEntropy { labels } => {
+                validate_softmax_cross_entropy(current_index, graph, labels)?
             }
-            GraphOperator::DeviceToHost => validate_device_to_host(current_index, graph),
-            GraphOperator::Linear { weights, bias } => {
-                validate_linear_dimensions(current_index, graph, weights, bias)
+            GraphOperator::LinearInt8 {
+                weights,
+                bias,
+                input_parameters,
             }
-            GraphOperator::ReLU => validate_relu(current_index, graph),
-            GraphOperator::Softmax => validate_softmax(current_index, graph),
-            GraphOperator::LinearReLUFused { weights, bias } => {
-                validate_linear_dimensions(current_index, graph, weights, bias)
+            | GraphOperator::LinearReLUInt8Fused {
+                weights,
+                bias,
+                input_parameters,
+            } => validate_quantized_linear(current_index, graph, weights, bias, input_parameters)?,
+            GraphOperator::Store { name } => validate_store(current_index, graph, name)?,
+            GraphOperator::Load { name } => validate_load(current_index, graph, name)?,
+            GraphOperator::Add { name } | GraphOperator::AddReLUFused { name } => {
+                validate_add(current_index, graph, name)?
             }
-            GraphOperator::LinearReLUSoftmaxFused { weights, bias } => {
-                validate_linear_dimensions(current_index, graph, weights, bias)
+            GraphOperator::Concat { name } => validate_concat(current_index, graph, name)?,
+            GraphOperator::LinearAddFused {
+                weights,
+                bias,
+                name,
+            } => validate_linear_add(current_index, graph, weights, bias, name)?,
+            GraphOperator::Activation { function } => validate_activation(current_index, function)?,
+            GraphOperator::LinearActivationFused {
+                weights,
+                bias,
+                function,
+            } => {
+                validate_activation(current_index, function)?;
+                validate_linear_dimensions(current_index, graph, weights, bias)?
             }
-        };
-        graph_is_validated = graph_is_validated && valid_operator;
+        }
     }
 
-    graph_is_validated
+    Ok(())
 }
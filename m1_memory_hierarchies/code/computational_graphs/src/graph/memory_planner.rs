@@ -0,0 +1,246 @@
+use std::fmt;
+
+// The graph runners allocate a new buffer for the output of every operator,
+// which keeps every intermediate value of the graph alive for the entire run.
+// That is needed for backward, but for inference an intermediate value is dead
+// as soon as the last operator reading it has run, after which its buffer can be
+// reused for the output of a later operator. For a deep graph of linear layers,
+// this means two buffers can do the work of hundreds.
+//
+// The planner works on buffer indices and node indices only, which lets the CPU
+// and the GPU runner share it. The runner tells the planner which buffers are
+// shared, meaning they are intermediates which can be reused, and which buffers every
+// node touches. Everything else, such as the input, the weights and the output,
+// keeps a buffer of its own.
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MemoryPlanningStrategy {
+    // Reuse the first free buffer which is large enough, in the order the buffers were
+    // created, otherwise create a new one.
+    Greedy,
+    // Reuse the smallest free buffer which is large enough. If none of the free buffers
+    // are large enough, grow the largest of them instead of creating a new one.
+    BestFit,
+}
+
+// The nodes a shared buffer is alive for, both inclusive. The first node
+// is the one writing the buffer, the last node is the last one reading it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BufferLifetime {
+    pub buffer_index: usize,
+    pub first_node: usize,
+    pub last_node: usize,
+}
+
+// The memory, in bytes, of a graph's buffers with and without the memory plan
+#[derive(Clone, Debug, Default)]
+pub struct MemoryReport {
+    pub buffer_count_before: usize,
+    pub buffer_count_after: usize,
+    pub peak_bytes_before: usize,
+    pub peak_bytes_after: usize,
+    // The most bytes which are ever alive at the same time. No plan can do better.
+    pub lower_bound_bytes: usize,
+}
+
+impl MemoryReport {
+    // The fraction of the memory saved by the plan
+    pub fn memory_savings(&self) -> f32 {
+        if self.peak_bytes_before == 0 {
+            return 0.0;
+        }
+
+        1.0 - self.peak_bytes_after as f32 / self.peak_bytes_before as f32
+    }
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            formatter,
+            "Buffers: {} -> {}",
+            self.buffer_count_before, self.buffer_count_after
+        )?;
+        write!(
+            formatter,
+            "Peak memory: {} bytes -> {} bytes ({:.1}% saved, lower bound {} bytes)",
+            self.peak_bytes_before,
+            self.peak_bytes_after,
+            100.0 * self.memory_savings(),
+            self.lower_bound_bytes
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryPlan {
+    // The new buffer of every old buffer
+    pub assignments: Vec<usize>,
+    // The number of elements in every new buffer
+    pub capacities: Vec<usize>,
+    // Shared buffers hold tensors of different shapes over time. Before running the node
+    // with the given index, the new buffer has to be given the shape of the tensor
+    // the node writes. Sorted by node index.
+    pub reshapes: Vec<(usize, usize, usize, usize)>,
+    pub report: MemoryReport,
+}
+
+// Finds the first and last node touching every shared buffer. node_buffer_indices holds
+// the buffers touched by every node, in the order the nodes are run.
+pub fn compute_lifetimes(
+    node_buffer_indices: &[Vec<usize>],
+    shared_buffers: &[usize],
+) -> Vec<BufferLifetime> {
+    shared_buffers
+        .iter()
+        .map(|buffer_index| {
+            let mut nodes = node_buffer_indices
+                .iter()
+                .enumerate()
+                .filter(|(_, buffer_indices)| buffer_indices.contains(buffer_index))
+                .map(|(node_index, _)| node_index);
+            let first_node: usize = nodes.next().unwrap_or_else(|| {
+                panic!(
+                    "memory_planner::compute_lifetimes - the shared buffer {} is not used by any node!",
+                    buffer_index
+                )
+            });
+            let last_node: usize = nodes.next_back().unwrap_or(first_node);
+
+            BufferLifetime {
+                buffer_index: *buffer_index,
+                first_node,
+                last_node,
+            }
+        })
+        .collect()
+}
+
+// Picks the free buffer a tensor of element_count elements should go into, if any,
+// growing it if needed. Returns None if a new buffer should be created.
+fn pick_free_buffer(
+    strategy: MemoryPlanningStrategy,
+    free_buffers: &[usize],
+    capacities: &mut [usize],
+    element_count: usize,
+) -> Option<usize> {
+    match strategy {
+        MemoryPlanningStrategy::Greedy => free_buffers
+            .iter()
+            .position(|buffer| element_count <= capacities[*buffer]),
+        MemoryPlanningStrategy::BestFit => {
+            let best_fit: Option<usize> = free_buffers
+                .iter()
+                .enumerate()
+                .filter(|(_, buffer)| element_count <= capacities[**buffer])
+                .min_by_key(|(_, buffer)| capacities[**buffer])
+                .map(|(position, _)| position);
+
+            best_fit.or_else(|| {
+                let largest: Option<usize> = free_buffers
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, buffer)| capacities[**buffer])
+                    .map(|(position, _)| position);
+                if let Some(position) = largest {
+                    capacities[free_buffers[position]] = element_count;
+                }
+                largest
+            })
+        }
+    }
+}
+
+// buffer_shapes holds the (row_count, column_count) of every buffer.
+pub fn plan_memory(
+    buffer_shapes: &[(usize, usize)],
+    node_buffer_indices: &[Vec<usize>],
+    shared_buffers: &[usize],
+    strategy: MemoryPlanningStrategy,
+) -> MemoryPlan {
+    let element_counts: Vec<usize> = buffer_shapes
+        .iter()
+        .map(|(row_count, column_count)| row_count * column_count)
+        .collect();
+
+    let mut lifetimes: Vec<BufferLifetime> = compute_lifetimes(node_buffer_indices, shared_buffers);
+    lifetimes.sort_by_key(|lifetime| (lifetime.first_node, lifetime.buffer_index));
+
+    let mut plan: MemoryPlan = MemoryPlan {
+        assignments: vec![usize::MAX; buffer_shapes.len()],
+        ..Default::default()
+    };
+
+    // Every buffer which isn't shared keeps a buffer of its own
+    for (buffer_index, element_count) in element_counts.iter().enumerate() {
+        if !shared_buffers.contains(&buffer_index) {
+            plan.assignments[buffer_index] = plan.capacities.len();
+            plan.capacities.push(*element_count);
+        }
+    }
+
+    // Sweep through the lifetimes in the order they begin. A buffer whose last node comes
+    // before the first node of the current lifetime is free. The output of a node can't
+    // share a buffer with the inputs of the same node, as their lifetimes overlap in it.
+    let mut active: Vec<(usize, usize)> = Vec::<(usize, usize)>::new();
+    let mut free_buffers: Vec<usize> = Vec::<usize>::new();
+    for lifetime in &lifetimes {
+        active.retain(|(last_node, buffer)| {
+            if *last_node < lifetime.first_node {
+                free_buffers.push(*buffer);
+                false
+            } else {
+                true
+            }
+        });
+        // Keep the free buffers in the order they were created, see Greedy
+        free_buffers.sort_unstable();
+
+        let element_count: usize = element_counts[lifetime.buffer_index];
+        let buffer: usize =
+            match pick_free_buffer(strategy, &free_buffers, &mut plan.capacities, element_count) {
+                Some(position) => free_buffers.remove(position),
+                None => {
+                    plan.capacities.push(element_count);
+                    plan.capacities.len() - 1
+                }
+            };
+
+        active.push((lifetime.last_node, buffer));
+        plan.assignments[lifetime.buffer_index] = buffer;
+
+        let (row_count, column_count): (usize, usize) = buffer_shapes[lifetime.buffer_index];
+        plan.reshapes
+            .push((lifetime.first_node, buffer, row_count, column_count));
+    }
+
+    let element_size: usize = std::mem::size_of::<f32>();
+    let persistent_count: usize = element_counts
+        .iter()
+        .enumerate()
+        .filter(|(buffer_index, _)| !shared_buffers.contains(buffer_index))
+        .map(|(_, element_count)| element_count)
+        .sum();
+    let live_shared_count: usize = (0..node_buffer_indices.len())
+        .map(|node_index| {
+            lifetimes
+                .iter()
+                .filter(|lifetime| {
+                    lifetime.first_node <= node_index && node_index <= lifetime.last_node
+                })
+                .map(|lifetime| element_counts[lifetime.buffer_index])
+                .sum::<usize>()
+        })
+        .max()
+        .unwrap_or(0);
+
+    plan.report = MemoryReport {
+        buffer_count_before: buffer_shapes.len(),
+        buffer_count_after: plan.capacities.len(),
+        peak_bytes_before: element_counts.iter().sum::<usize>() * element_size,
+        peak_bytes_after: plan.capacities.iter().sum::<usize>() * element_size,
+        lower_bound_bytes: (persistent_count + live_shared_count) * element_size,
+    };
+
+    plan
+}
@@ -0,0 +1,343 @@
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        graph::{
+            dag::{DagGraph, DagOperator},
+            graph_error::GraphError,
+            graph_runner::GraphRunner,
+            memory_planner::{
+                compute_lifetimes, plan_memory, BufferLifetime, MemoryPlan, MemoryPlanningStrategy,
+                MemoryReport,
+            },
+            quantization::quantize_graph_operators,
+        },
+        shared::{
+            graph_operators::GraphOperator, quantized_tensor2d::QuantizationGranularity,
+            tensor2d::Tensor2D, tensor2d_test_utilities::random_tensor,
+        },
+    };
+
+    const ERROR_TOLERANCE: f32 = 0.00001;
+    const STRATEGIES: [MemoryPlanningStrategy; 2] = [
+        MemoryPlanningStrategy::Greedy,
+        MemoryPlanningStrategy::BestFit,
+    ];
+
+    fn assert_tensors_match(expected: &Tensor2D, found: &Tensor2D) {
+        assert_eq!(expected.row_count, found.row_count);
+        assert_eq!(expected.column_count, found.column_count);
+        for index in 0..expected.len() {
+            assert!(
+                (expected.data[index] - found.data[index]).abs() < ERROR_TOLERANCE,
+                "index: {} expected: {} found: {}",
+                index,
+                expected.data[index],
+                found.data[index]
+            );
+        }
+    }
+
+    // A deep multilayer perceptron where the width of every layer is picked at random,
+    // so the shared buffers have to hold tensors of different sizes.
+    fn deep_mlp(rng: &mut ChaCha8Rng, depth: usize) -> Vec<GraphOperator> {
+        let row_count: usize = 4;
+        let mut width: usize = 8;
+        let mut graph_operators: Vec<GraphOperator> = vec![GraphOperator::HostToDevice {
+            input: random_tensor(rng, row_count, width),
+        }];
+        for _ in 0..depth {
+            let next_width: usize = rng.gen_range(2..16);
+            let mut weights: Tensor2D = random_tensor(rng, width, next_width);
+            for element in &mut weights.data {
+                *element /= (width as f32).sqrt();
+            }
+            let bias: Tensor2D = random_tensor(rng, row_count, next_width);
+            graph_operators.push(GraphOperator::Linear { weights, bias });
+            graph_operators.push(GraphOperator::ReLU);
+            width = next_width;
+        }
+        graph_operators.push(GraphOperator::Softmax);
+        graph_operators.push(GraphOperator::DeviceToHost);
+
+        graph_operators
+    }
+
+    // The buffers of a chain of 6 nodes, 0 is the input, 1 to 5 are the outputs of each node
+    fn chain() -> (Vec<(usize, usize)>, Vec<Vec<usize>>, Vec<usize>) {
+        let buffer_shapes: Vec<(usize, usize)> =
+            vec![(2, 2), (2, 4), (2, 8), (2, 2), (2, 8), (2, 2)];
+        let node_buffer_indices: Vec<Vec<usize>> = vec![
+            vec![0],
+            vec![0, 1],
+            vec![1, 2],
+            vec![2, 3],
+            vec![3, 4],
+            vec![4, 5],
+        ];
+        let shared_buffers: Vec<usize> = vec![1, 2, 3, 4];
+
+        (buffer_shapes, node_buffer_indices, shared_buffers)
+    }
+
+    #[test]
+    fn lifetimes() {
+        let (_, node_buffer_indices, shared_buffers) = chain();
+        let lifetimes: Vec<BufferLifetime> =
+            compute_lifetimes(&node_buffer_indices, &shared_buffers);
+        for (lifetime, buffer_index) in lifetimes.iter().zip(shared_buffers.iter()) {
+            assert_eq!(
+                *lifetime,
+                BufferLifetime {
+                    buffer_index: *buffer_index,
+                    first_node: *buffer_index,
+                    last_node: *buffer_index + 1,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn chain_uses_two_shared_buffers() {
+        let (buffer_shapes, node_buffer_indices, shared_buffers) = chain();
+        for strategy in STRATEGIES {
+            let plan: MemoryPlan = plan_memory(
+                &buffer_shapes,
+                &node_buffer_indices,
+                &shared_buffers,
+                strategy,
+            );
+
+            // The input, the output and two buffers which the intermediates alternate between
+            assert_eq!(plan.capacities.len(), 4);
+            assert_eq!(plan.assignments[1], plan.assignments[3]);
+            assert_eq!(plan.assignments[2], plan.assignments[4]);
+            assert_ne!(plan.assignments[1], plan.assignments[2]);
+            assert_eq!(plan.reshapes.len(), shared_buffers.len());
+
+            let report: MemoryReport = plan.report;
+            assert_eq!(report.buffer_count_before, 6);
+            assert_eq!(report.buffer_count_after, 4);
+            assert!(report.lower_bound_bytes <= report.peak_bytes_after);
+            assert!(report.peak_bytes_after < report.peak_bytes_before);
+        }
+    }
+
+    // Buffers whose lifetimes overlap can never share a buffer, and every buffer has to
+    // be large enough for every tensor it holds.
+    #[test]
+    fn no_overlapping_lifetimes_share_a_buffer() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(0);
+        let node_count: usize = 64;
+        let buffer_shapes: Vec<(usize, usize)> = (0..node_count)
+            .map(|_| (rng.gen_range(1..8), rng.gen_range(1..8)))
+            .collect();
+        // Every node writes its own buffer and reads up to two earlier ones
+        let node_buffer_indices: Vec<Vec<usize>> = (0..node_count)
+            .map(|node_index| {
+                let mut buffer_indices: Vec<usize> = Vec::<usize>::new();
+                if 0 < node_index {
+                    buffer_indices.push(rng.gen_range(0..node_index));
+                    buffer_indices.push(node_index - 1);
+                }
+                buffer_indices.push(node_index);
+                buffer_indices
+            })
+            .collect();
+        let shared_buffers: Vec<usize> = (1..(node_count - 1)).collect();
+
+        let lifetimes: Vec<BufferLifetime> =
+            compute_lifetimes(&node_buffer_indices, &shared_buffers);
+        for strategy in STRATEGIES {
+            let plan: MemoryPlan = plan_memory(
+                &buffer_shapes,
+                &node_buffer_indices,
+                &shared_buffers,
+                strategy,
+            );
+
+            for (buffer_index, (row_count, column_count)) in buffer_shapes.iter().enumerate() {
+                assert!(
+                    row_count * column_count <= plan.capacities[plan.assignments[buffer_index]]
+                );
+            }
+            for left in &lifetimes {
+                for right in &lifetimes {
+                    let overlap: bool =
+                        left.first_node <= right.last_node && right.first_node <= left.last_node;
+                    if left.buffer_index != right.buffer_index && overlap {
+                        assert_ne!(
+                            plan.assignments[left.buffer_index],
+                            plan.assignments[right.buffer_index]
+                        );
+                    }
+                }
+            }
+            assert!(plan.report.lower_bound_bytes <= plan.report.peak_bytes_after);
+        }
+    }
+
+    // Best fit grows a free buffer instead of creating a new one
+    #[test]
+    fn best_fit_grows_free_buffers() {
+        let buffer_shapes: Vec<(usize, usize)> = vec![(1, 1), (1, 2), (1, 2), (1, 8), (1, 1)];
+        let node_buffer_indices: Vec<Vec<usize>> =
+            vec![vec![0, 1], vec![1, 2], vec![2, 3], vec![3, 4]];
+        let shared_buffers: Vec<usize> = vec![1, 2, 3];
+
+        let greedy: MemoryPlan = plan_memory(
+            &buffer_shapes,
+            &node_buffer_indices,
+            &shared_buffers,
+            MemoryPlanningStrategy::Greedy,
+        );
+        assert_eq!(greedy.report.buffer_count_after, 5);
+
+        let best_fit: MemoryPlan = plan_memory(
+            &buffer_shapes,
+            &node_buffer_indices,
+            &shared_buffers,
+            MemoryPlanningStrategy::BestFit,
+        );
+        assert_eq!(best_fit.report.buffer_count_after, 4);
+        assert!(best_fit.report.peak_bytes_after < greedy.report.peak_bytes_after);
+    }
+
+    #[test]
+    fn deep_graph() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(1);
+        let graph_operators: Vec<GraphOperator> = deep_mlp(&mut rng, 128);
+
+        for fuse_operators in [false, true] {
+            let mut graph_runner: GraphRunner =
+                GraphRunner::new(&graph_operators, fuse_operators, false).unwrap();
+            let expected: Tensor2D = graph_runner.run().unwrap();
+
+            for strategy in STRATEGIES {
+                let mut graph_runner: GraphRunner =
+                    GraphRunner::new(&graph_operators, fuse_operators, false).unwrap();
+                let report: MemoryReport = graph_runner.plan_memory(strategy).unwrap();
+                assert!(report.peak_bytes_after < report.peak_bytes_before);
+                assert!(report.buffer_count_after < report.buffer_count_before);
+
+                // Running twice makes sure the shapes of the shared buffers are reset
+                assert_tensors_match(&expected, &graph_runner.run().unwrap());
+                assert_tensors_match(&expected, &graph_runner.run().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn residual_dag() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(2);
+        let mut dag: DagGraph = DagGraph::new("output");
+        dag.add_node(
+            "x",
+            DagOperator::Input {
+                input: random_tensor(&mut rng, 3, 4),
+            },
+            &[],
+        );
+        let mut previous: String = "x".to_string();
+        for block_index in 0..4 {
+            let linear: String = format!("linear_{}", block_index);
+            let relu: String = format!("relu_{}", block_index);
+            let add: String = format!("add_{}", block_index);
+            dag.add_node(
+                &linear,
+                DagOperator::Unary(GraphOperator::Linear {
+                    weights: random_tensor(&mut rng, 4, 4),
+                    bias: random_tensor(&mut rng, 3, 4),
+                }),
+                &[&previous],
+            );
+            dag.add_node(&relu, DagOperator::Unary(GraphOperator::ReLU), &[&linear]);
+            dag.add_node(&add, DagOperator::Add, &[&relu, &previous]);
+            previous = add;
+        }
+        dag.add_node("output", DagOperator::Concat, &[&previous, "x"]);
+
+        for fuse_operators in [false, true] {
+            let expected: Tensor2D = GraphRunner::from_dag(&dag, fuse_operators, false)
+                .unwrap()
+                .run()
+                .unwrap();
+            for strategy in STRATEGIES {
+                let mut graph_runner: GraphRunner =
+                    GraphRunner::from_dag(&dag, fuse_operators, false).unwrap();
+                graph_runner.plan_memory(strategy).unwrap();
+                assert_tensors_match(&expected, &graph_runner.run().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn quantized_graph() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(3);
+        let graph_operators: Vec<GraphOperator> = quantize_graph_operators(
+            &deep_mlp(&mut rng, 16),
+            &[],
+            QuantizationGranularity::PerChannel,
+        );
+
+        let expected: Tensor2D = GraphRunner::new(&graph_operators, true, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(&graph_operators, true, false).unwrap();
+        graph_runner
+            .plan_memory(MemoryPlanningStrategy::BestFit)
+            .unwrap();
+        assert_tensors_match(&expected, &graph_runner.run().unwrap());
+    }
+
+    #[test]
+    fn backward_after_planning() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(4);
+        let graph_operators: Vec<GraphOperator> = deep_mlp(&mut rng, 4);
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(&graph_operators, false, false).unwrap();
+        graph_runner
+            .plan_memory(MemoryPlanningStrategy::BestFit)
+            .unwrap();
+        let output: Tensor2D = graph_runner.run().unwrap();
+        assert_eq!(
+            graph_runner.backward(&Tensor2D::new(1.0, output.row_count, output.column_count)),
+            Err(GraphError::MemoryPlanned {
+                operation: "run backward".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn changes_after_planning() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(5);
+        let graph_operators: Vec<GraphOperator> = deep_mlp(&mut rng, 4);
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(&graph_operators, false, false).unwrap();
+        graph_runner
+            .plan_memory(MemoryPlanningStrategy::BestFit)
+            .unwrap();
+
+        assert_eq!(
+            graph_runner
+                .plan_memory(MemoryPlanningStrategy::Greedy)
+                .err(),
+            Some(GraphError::MemoryPlanned {
+                operation: "plan the memory again".to_string()
+            })
+        );
+
+        // deep_mlp has 4 rows of 8 columns
+        let input_batch: Tensor2D = random_tensor(&mut rng, 5, 8);
+        assert_eq!(
+            graph_runner.set_input_batch(&input_batch),
+            Err(GraphError::MemoryPlanned {
+                operation: "change the batch size".to_string()
+            })
+        );
+    }
+}
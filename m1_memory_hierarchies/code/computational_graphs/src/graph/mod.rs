@@ -1,8 +1,33 @@
+pub mod backend;
+pub mod backend_test;
+pub mod compiled_graph;
+pub mod compiled_graph_test;
+pub mod dag;
+pub mod dag_test;
+pub mod dot_export;
+pub mod dot_export_test;
+pub mod fusion;
+pub mod fusion_test;
+pub mod graph_error;
 pub mod graph_runner;
 pub mod graph_runner_gpu;
 pub mod graph_runner_gpu_test;
+pub mod graph_runner_interpreter;
 pub mod graph_runner_tests;
 pub mod graph_validation;
+pub mod memory_planner;
+pub mod memory_planner_test;
 pub mod nodes;
 pub mod nodes_gpu;
+pub mod nodes_interpreter;
+pub mod onnx;
+pub mod onnx_test;
+pub mod optimizers;
+pub mod optimizers_test;
+pub mod quantization;
+pub mod quantization_test;
 pub mod runner;
+pub mod serialization;
+pub mod serialization_test;
+pub mod shape_inference;
+pub mod shape_inference_test;
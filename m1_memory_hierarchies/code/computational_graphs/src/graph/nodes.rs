@@ -1,6 +1,10 @@
 use std::vec::Drain;
 
-use crate::shared::tensor2d::Tensor2D;
+use crate::shared::{
+    activation::ActivationFunction, quantized_tensor2d::QuantizedTensor2D, tensor2d::Tensor2D,
+};
+
+use super::graph_error::GraphError;
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum NodeOperator {
@@ -12,6 +16,31 @@ pub enum NodeOperator {
     Softmax,
     LinearReLU,
     LinearReLUSoftmax,
+    MeanSquaredError,
+    SoftmaxCrossEntropy,
+    LinearInt8,
+    LinearReLUInt8,
+    Add,
+    Concat,
+    LinearAdd,
+    AddReLU,
+    Activation(ActivationFunction),
+    LinearActivation(ActivationFunction),
+}
+
+impl NodeOperator {
+    // Operators which only exist as the result of fusing others, see graph::fusion
+    pub fn is_fused(&self) -> bool {
+        matches!(
+            self,
+            NodeOperator::LinearReLU
+                | NodeOperator::LinearReLUSoftmax
+                | NodeOperator::LinearReLUInt8
+                | NodeOperator::LinearAdd
+                | NodeOperator::AddReLU
+                | NodeOperator::LinearActivation(_)
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -75,14 +104,26 @@ fn sorted_mutable_references<'a>(
     references
 }
 
-pub fn linear(node: &Node, data_buffers: &mut [Tensor2D]) {
-    if node.buffer_indices.len() != 4 {
-        panic!(
-            "cpu_nodes::linear function expected 1 input buffer, received {}",
-            node.buffer_indices.len()
-        );
+// The graph runners hand every node a fixed number of buffers
+pub fn check_buffer_count(node: &Node, expected: usize) -> Result<(), GraphError> {
+    if node.buffer_indices.len() != expected {
+        return Err(GraphError::BufferCountMismatch {
+            node: node.name.clone(),
+            expected,
+            found: node.buffer_indices.len(),
+        });
     }
 
+    Ok(())
+}
+
+pub fn linear(
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    parallel: bool,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
+
     let mut references: Vec<(usize, &mut Tensor2D)> = sorted_mutable_references(node, data_buffers);
     let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
 
@@ -91,16 +132,17 @@ pub fn linear(node: &Node, data_buffers: &mut [Tensor2D]) {
     let bias: &Tensor2D = drain.next().unwrap().1;
     let output: &mut Tensor2D = drain.next().unwrap().1;
 
-    Tensor2D::linear_optimized(input, weights, bias, output)
+    if parallel {
+        Tensor2D::linear_optimized_parallel(input, weights, bias, output)
+    } else {
+        Tensor2D::linear_optimized(input, weights, bias, output)
+    }
+
+    Ok(())
 }
 
-pub fn relu(node: &Node, data_buffers: &mut [Tensor2D]) {
-    if node.buffer_indices.len() != 2 {
-        panic!(
-            "nodes::relu function expected 1 input buffer, received {}",
-            node.buffer_indices.len()
-        );
-    }
+pub fn relu(node: &Node, data_buffers: &mut [Tensor2D], parallel: bool) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
 
     let mut references: Vec<(usize, &mut Tensor2D)> = sorted_mutable_references(node, data_buffers);
     let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
@@ -112,16 +154,21 @@ pub fn relu(node: &Node, data_buffers: &mut [Tensor2D]) {
     // which has better performance and works on directly on the given input, which has to be mutable.
     // Due to the way the graph is currently setup, this isn't implemented for the CPU graph,
     // but it could be a possible optimization. Wink. Wink.
-    Tensor2D::relu_preallocated(input, output);
+    if parallel {
+        Tensor2D::relu_parallel(input, output);
+    } else {
+        Tensor2D::relu_preallocated(input, output);
+    }
+
+    Ok(())
 }
 
-pub fn softmax(node: &Node, data_buffers: &mut [Tensor2D]) {
-    if node.buffer_indices.len() != 2 {
-        panic!(
-            "nodes::softmax function expected 1 input buffer, received {}",
-            node.buffer_indices.len()
-        );
-    }
+pub fn softmax(
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    parallel: bool,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
 
     let mut references: Vec<(usize, &mut Tensor2D)> = sorted_mutable_references(node, data_buffers);
     let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
@@ -133,17 +180,46 @@ pub fn softmax(node: &Node, data_buffers: &mut [Tensor2D]) {
     // which has better performance and works on directly on the given input, which has to be mutable.
     // Due to the way the graph is currently setup, this isn't implemented for the CPU graph,
     // but it could be a possible optimization. Wink. Wink.
-    Tensor2D::softmax_preallocated(input, output);
+    if parallel {
+        Tensor2D::softmax_parallel(input, output);
+    } else {
+        Tensor2D::softmax_preallocated(input, output);
+    }
+
+    Ok(())
 }
 
-pub fn linear_relu(node: &Node, data_buffers: &mut [Tensor2D]) {
-    if node.buffer_indices.len() != 4 {
-        panic!(
-            "cpu_nodes::linear_relu function expected 1 input buffer, received {}",
-            node.buffer_indices.len()
-        );
+pub fn linear_relu(
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    parallel: bool,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
+
+    let mut references: Vec<(usize, &mut Tensor2D)> = sorted_mutable_references(node, data_buffers);
+    let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
+
+    let input: &Tensor2D = drain.next().unwrap().1;
+    let weights: &Tensor2D = drain.next().unwrap().1;
+    let bias: &Tensor2D = drain.next().unwrap().1;
+    let output: &mut Tensor2D = drain.next().unwrap().1;
+
+    if parallel {
+        Tensor2D::linear_optimized_relu_parallel(input, weights, bias, output);
+    } else {
+        Tensor2D::linear_local_accumulation_relu(input, weights, bias, output);
     }
 
+    Ok(())
+}
+
+pub fn linear_relu_softmax(
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    parallel: bool,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
+
     let mut references: Vec<(usize, &mut Tensor2D)> = sorted_mutable_references(node, data_buffers);
     let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
 
@@ -152,17 +228,46 @@ pub fn linear_relu(node: &Node, data_buffers: &mut [Tensor2D]) {
     let bias: &Tensor2D = drain.next().unwrap().1;
     let output: &mut Tensor2D = drain.next().unwrap().1;
 
-    Tensor2D::linear_local_accumulation_relu(input, weights, bias, output);
+    if parallel {
+        Tensor2D::linear_relu_softmax_parallel(input, weights, bias, output);
+    } else {
+        Tensor2D::linear_relu_softmax_fused_fission(input, weights, bias, output);
+    }
+
+    Ok(())
 }
 
-pub fn linear_relu_softmax(node: &Node, data_buffers: &mut [Tensor2D]) {
-    if node.buffer_indices.len() != 4 {
-        panic!(
-            "cpu_nodes::linear_relu_softmax function expected 1 input buffer, received {}",
-            node.buffer_indices.len()
-        );
+pub fn activation(
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    parallel: bool,
+    function: ActivationFunction,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
+
+    let mut references: Vec<(usize, &mut Tensor2D)> = sorted_mutable_references(node, data_buffers);
+    let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
+
+    let input: &Tensor2D = drain.next().unwrap().1;
+    let output: &mut Tensor2D = drain.next().unwrap().1;
+
+    if parallel {
+        Tensor2D::activation_parallel(input, output, function);
+    } else {
+        Tensor2D::activation_preallocated(input, output, function);
     }
 
+    Ok(())
+}
+
+pub fn linear_activation(
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    parallel: bool,
+    function: ActivationFunction,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
+
     let mut references: Vec<(usize, &mut Tensor2D)> = sorted_mutable_references(node, data_buffers);
     let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
 
@@ -171,5 +276,542 @@ pub fn linear_relu_softmax(node: &Node, data_buffers: &mut [Tensor2D]) {
     let bias: &Tensor2D = drain.next().unwrap().1;
     let output: &mut Tensor2D = drain.next().unwrap().1;
 
-    Tensor2D::linear_relu_softmax_fused_fission(input, weights, bias, output);
+    if parallel {
+        Tensor2D::linear_optimized_activation_parallel(input, weights, bias, output, function);
+    } else {
+        Tensor2D::linear_optimized_activation(input, weights, bias, output, function);
+    }
+
+    Ok(())
+}
+
+pub fn mean_squared_error(node: &Node, data_buffers: &mut [Tensor2D]) -> Result<(), GraphError> {
+    check_buffer_count(node, 3)?;
+
+    let mut references: Vec<(usize, &mut Tensor2D)> = sorted_mutable_references(node, data_buffers);
+    let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
+
+    let input: &Tensor2D = drain.next().unwrap().1;
+    let target: &Tensor2D = drain.next().unwrap().1;
+    let output: &mut Tensor2D = drain.next().unwrap().1;
+
+    output.data[0] = Tensor2D::mean_squared_error(input, target);
+
+    Ok(())
+}
+
+pub fn softmax_cross_entropy(node: &Node, data_buffers: &mut [Tensor2D]) -> Result<(), GraphError> {
+    check_buffer_count(node, 3)?;
+
+    let mut references: Vec<(usize, &mut Tensor2D)> = sorted_mutable_references(node, data_buffers);
+    let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
+
+    let input: &Tensor2D = drain.next().unwrap().1;
+    let labels: &Tensor2D = drain.next().unwrap().1;
+    let output: &mut Tensor2D = drain.next().unwrap().1;
+
+    output.data[0] = Tensor2D::softmax_cross_entropy(input, labels);
+
+    Ok(())
+}
+
+// The quantized nodes have the buffer indices [input, quantized_input, weights, bias, output].
+// quantized_input and weights are indices into quantized_buffers, the rest into data_buffers.
+// The input is quantized into quantized_input, which holds the calibrated input parameters.
+// Borrows the output buffer mutably and the buffers at read_indices immutably.
+// After memory planning the output can be at any index relative to the inputs,
+// so the buffers are split around the output.
+fn split_around_output<'a, const N: usize>(
+    data_buffers: &'a mut [Tensor2D],
+    read_indices: [usize; N],
+    output_index: usize,
+) -> ([&'a Tensor2D; N], &'a mut Tensor2D) {
+    assert!(!read_indices.contains(&output_index));
+    let (head, tail) = data_buffers.split_at_mut(output_index);
+    let (output, tail) = tail.split_first_mut().unwrap();
+    let (head, tail): (&'a [Tensor2D], &'a [Tensor2D]) = (head, tail);
+
+    let inputs: [&'a Tensor2D; N] = read_indices.map(|index| {
+        if index < output_index {
+            &head[index]
+        } else {
+            &tail[index - output_index - 1]
+        }
+    });
+
+    (inputs, output)
+}
+
+fn quantized_linear_buffers<'a>(
+    node: &Node,
+    data_buffers: &'a mut [Tensor2D],
+    quantized_buffers: &'a mut [QuantizedTensor2D],
+) -> Result<
+    (
+        &'a QuantizedTensor2D,
+        &'a QuantizedTensor2D,
+        &'a Tensor2D,
+        &'a mut Tensor2D,
+    ),
+    GraphError,
+> {
+    check_buffer_count(node, 5)?;
+
+    let input_index: usize = node.buffer_indices[0];
+    let quantized_input_index: usize = node.buffer_indices[1];
+    let weights_index: usize = node.buffer_indices[2];
+    let bias_index: usize = node.buffer_indices[3];
+    let output_index: usize = node.buffer_indices[4];
+
+    QuantizedTensor2D::quantize_preallocated(
+        &data_buffers[input_index],
+        &mut quantized_buffers[quantized_input_index],
+    );
+
+    let ([bias], output) = split_around_output(data_buffers, [bias_index], output_index);
+
+    Ok((
+        &quantized_buffers[quantized_input_index],
+        &quantized_buffers[weights_index],
+        bias,
+        output,
+    ))
+}
+
+pub fn linear_int8(
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    quantized_buffers: &mut [QuantizedTensor2D],
+) -> Result<(), GraphError> {
+    let (input, weights, bias, output) =
+        quantized_linear_buffers(node, data_buffers, quantized_buffers)?;
+    QuantizedTensor2D::linear_preallocated(input, weights, bias, output);
+
+    Ok(())
+}
+
+pub fn linear_relu_int8(
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    quantized_buffers: &mut [QuantizedTensor2D],
+) -> Result<(), GraphError> {
+    let (input, weights, bias, output) =
+        quantized_linear_buffers(node, data_buffers, quantized_buffers)?;
+    QuantizedTensor2D::linear_relu_preallocated(input, weights, bias, output);
+
+    Ok(())
+}
+
+// The add and concat nodes have the buffer indices [input, other, output], where other
+// is a named tensor. The input and other can be the same buffer, which is why these don't
+// use sorted_mutable_references. The output buffer is always allocated after both of them.
+fn binary_buffers<'a>(
+    node: &Node,
+    data_buffers: &'a mut [Tensor2D],
+) -> Result<(&'a Tensor2D, &'a Tensor2D, &'a mut Tensor2D), GraphError> {
+    check_buffer_count(node, 3)?;
+
+    let input_index: usize = node.buffer_indices[0];
+    let other_index: usize = node.buffer_indices[1];
+    let output_index: usize = node.buffer_indices[2];
+    let ([input, other], output) =
+        split_around_output(data_buffers, [input_index, other_index], output_index);
+
+    Ok((input, other, output))
+}
+
+pub fn add(node: &Node, data_buffers: &mut [Tensor2D]) -> Result<(), GraphError> {
+    let (input, other, output) = binary_buffers(node, data_buffers)?;
+    Tensor2D::add_preallocated(input, other, output);
+
+    Ok(())
+}
+
+pub fn concat(node: &Node, data_buffers: &mut [Tensor2D]) -> Result<(), GraphError> {
+    let (input, other, output) = binary_buffers(node, data_buffers)?;
+    Tensor2D::concat_columns_preallocated(input, other, output);
+
+    Ok(())
+}
+
+// The buffer indices are [input, weights, bias, other, output]. The input and other
+// can be the same buffer, which is the case for a residual connection around a single
+// linear operator, so they are borrowed together, see split_around_output.
+pub fn linear_add(
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    parallel: bool,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 5)?;
+
+    let ([input, weights, bias, other], output) = split_around_output(
+        data_buffers,
+        [
+            node.buffer_indices[0],
+            node.buffer_indices[1],
+            node.buffer_indices[2],
+            node.buffer_indices[3],
+        ],
+        node.buffer_indices[4],
+    );
+
+    if parallel {
+        Tensor2D::linear_optimized_parallel(input, weights, bias, output);
+    } else {
+        Tensor2D::linear_optimized(input, weights, bias, output);
+    }
+    Tensor2D::add_inplace(output, other);
+
+    Ok(())
+}
+
+pub fn add_relu(node: &Node, data_buffers: &mut [Tensor2D]) -> Result<(), GraphError> {
+    let (input, other, output) = binary_buffers(node, data_buffers)?;
+    Tensor2D::add_relu_preallocated(input, other, output);
+
+    Ok(())
+}
+
+// The backward functions mirror the forward functions above. The gradient buffers
+// are laid out exactly like the data buffers, so the same buffer_indices
+// can be used to find the gradient of every buffer the node touched.
+pub fn linear_backward(
+    node: &Node,
+    data_buffers: &[Tensor2D],
+    gradient_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let weights: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+
+    let mut references: Vec<(usize, &mut Tensor2D)> =
+        sorted_mutable_references(node, gradient_buffers);
+    let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
+
+    let input_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let weights_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let bias_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let output_gradient: &Tensor2D = drain.next().unwrap().1;
+
+    Tensor2D::linear_backward(
+        input,
+        weights,
+        output_gradient,
+        input_gradient,
+        weights_gradient,
+        bias_gradient,
+    );
+
+    Ok(())
+}
+
+pub fn relu_backward(
+    node: &Node,
+    data_buffers: &[Tensor2D],
+    gradient_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
+
+    let output: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+
+    let mut references: Vec<(usize, &mut Tensor2D)> =
+        sorted_mutable_references(node, gradient_buffers);
+    let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
+
+    let input_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let output_gradient: &Tensor2D = drain.next().unwrap().1;
+
+    Tensor2D::relu_backward(output, output_gradient, input_gradient);
+
+    Ok(())
+}
+
+pub fn softmax_backward(
+    node: &Node,
+    data_buffers: &[Tensor2D],
+    gradient_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
+
+    let output: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+
+    let mut references: Vec<(usize, &mut Tensor2D)> =
+        sorted_mutable_references(node, gradient_buffers);
+    let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
+
+    let input_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let output_gradient: &Tensor2D = drain.next().unwrap().1;
+
+    Tensor2D::softmax_backward(output, output_gradient, input_gradient);
+
+    Ok(())
+}
+
+pub fn activation_backward(
+    node: &Node,
+    data_buffers: &[Tensor2D],
+    gradient_buffers: &mut [Tensor2D],
+    function: ActivationFunction,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+
+    let mut references: Vec<(usize, &mut Tensor2D)> =
+        sorted_mutable_references(node, gradient_buffers);
+    let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
+
+    let input_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let output_gradient: &Tensor2D = drain.next().unwrap().1;
+
+    Tensor2D::activation_backward(input, output_gradient, input_gradient, function);
+
+    Ok(())
+}
+
+pub fn linear_relu_backward(
+    node: &Node,
+    data_buffers: &[Tensor2D],
+    gradient_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let weights: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+    let output: &Tensor2D = &data_buffers[node.buffer_indices[3]];
+
+    let mut references: Vec<(usize, &mut Tensor2D)> =
+        sorted_mutable_references(node, gradient_buffers);
+    let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
+
+    let input_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let weights_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let bias_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let output_gradient: &Tensor2D = drain.next().unwrap().1;
+
+    let mut linear_gradient: Tensor2D = Tensor2D::new(0.0, output.row_count, output.column_count);
+    Tensor2D::relu_backward(output, output_gradient, &mut linear_gradient);
+
+    Tensor2D::linear_backward(
+        input,
+        weights,
+        &linear_gradient,
+        input_gradient,
+        weights_gradient,
+        bias_gradient,
+    );
+
+    Ok(())
+}
+
+pub fn linear_relu_softmax_backward(
+    node: &Node,
+    data_buffers: &[Tensor2D],
+    gradient_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let weights: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+    let bias: &Tensor2D = &data_buffers[node.buffer_indices[2]];
+    let output: &Tensor2D = &data_buffers[node.buffer_indices[3]];
+
+    let mut references: Vec<(usize, &mut Tensor2D)> =
+        sorted_mutable_references(node, gradient_buffers);
+    let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
+
+    let input_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let weights_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let bias_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let output_gradient: &Tensor2D = drain.next().unwrap().1;
+
+    // The fused operator never kept the output of the ReLU around, and it can't be
+    // recovered from the output of the softmax, so we recompute it.
+    let mut relu_output: Tensor2D = Tensor2D::new(0.0, output.row_count, output.column_count);
+    Tensor2D::linear_optimized_relu(input, weights, bias, &mut relu_output);
+
+    let mut relu_gradient: Tensor2D = Tensor2D::new(0.0, output.row_count, output.column_count);
+    Tensor2D::softmax_backward(output, output_gradient, &mut relu_gradient);
+
+    let mut linear_gradient: Tensor2D = Tensor2D::new(0.0, output.row_count, output.column_count);
+    Tensor2D::relu_backward(&relu_output, &relu_gradient, &mut linear_gradient);
+
+    Tensor2D::linear_backward(
+        input,
+        weights,
+        &linear_gradient,
+        input_gradient,
+        weights_gradient,
+        bias_gradient,
+    );
+
+    Ok(())
+}
+
+pub fn linear_activation_backward(
+    node: &Node,
+    data_buffers: &[Tensor2D],
+    gradient_buffers: &mut [Tensor2D],
+    function: ActivationFunction,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let weights: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+    let bias: &Tensor2D = &data_buffers[node.buffer_indices[2]];
+    let output: &Tensor2D = &data_buffers[node.buffer_indices[3]];
+
+    let mut references: Vec<(usize, &mut Tensor2D)> =
+        sorted_mutable_references(node, gradient_buffers);
+    let mut drain: Drain<(usize, &mut Tensor2D)> = references.drain(0..references.len());
+
+    let input_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let weights_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let bias_gradient: &mut Tensor2D = drain.next().unwrap().1;
+    let output_gradient: &Tensor2D = drain.next().unwrap().1;
+
+    // The fused operator never kept the output of the linear operator around,
+    // which is the input of the activation function, so we recompute it.
+    let mut linear_output: Tensor2D = Tensor2D::new(0.0, output.row_count, output.column_count);
+    Tensor2D::linear_optimized(input, weights, bias, &mut linear_output);
+
+    let mut linear_gradient: Tensor2D = Tensor2D::new(0.0, output.row_count, output.column_count);
+    Tensor2D::activation_backward(
+        &linear_output,
+        output_gradient,
+        &mut linear_gradient,
+        function,
+    );
+
+    Tensor2D::linear_backward(
+        input,
+        weights,
+        &linear_gradient,
+        input_gradient,
+        weights_gradient,
+        bias_gradient,
+    );
+
+    Ok(())
+}
+
+// The loss nodes don't produce gradients for their targets or labels,
+// they are data, not something we are training.
+pub fn mean_squared_error_backward(
+    node: &Node,
+    data_buffers: &[Tensor2D],
+    gradient_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 3)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let target: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+    let output_gradient: f32 = gradient_buffers[node.buffer_indices[2]].data[0];
+    let input_gradient: &mut Tensor2D = &mut gradient_buffers[node.buffer_indices[0]];
+
+    Tensor2D::mean_squared_error_backward(input, target, output_gradient, input_gradient);
+
+    Ok(())
+}
+
+pub fn softmax_cross_entropy_backward(
+    node: &Node,
+    data_buffers: &[Tensor2D],
+    gradient_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 3)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let labels: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+    let output_gradient: f32 = gradient_buffers[node.buffer_indices[2]].data[0];
+    let input_gradient: &mut Tensor2D = &mut gradient_buffers[node.buffer_indices[0]];
+
+    Tensor2D::softmax_cross_entropy_backward(input, labels, output_gradient, input_gradient);
+
+    Ok(())
+}
+
+// The gradient of an add flows unchanged to both of its inputs. If the input and
+// the other tensor are the same buffer, it receives the gradient twice.
+pub fn add_backward(node: &Node, gradient_buffers: &mut [Tensor2D]) -> Result<(), GraphError> {
+    check_buffer_count(node, 3)?;
+
+    let (input_index, other_index) = (node.buffer_indices[0], node.buffer_indices[1]);
+    let (head, tail) = gradient_buffers.split_at_mut(node.buffer_indices[2]);
+    let output_gradient: &Tensor2D = &tail[0];
+
+    for index in [input_index, other_index] {
+        let input_gradient: &mut Tensor2D = &mut head[index];
+        for (input, output) in input_gradient.data[0..output_gradient.len()]
+            .iter_mut()
+            .zip(&output_gradient.data[0..output_gradient.len()])
+        {
+            *input += output;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn concat_backward(node: &Node, gradient_buffers: &mut [Tensor2D]) -> Result<(), GraphError> {
+    check_buffer_count(node, 3)?;
+
+    let (input_index, other_index) = (node.buffer_indices[0], node.buffer_indices[1]);
+    let (head, tail) = gradient_buffers.split_at_mut(node.buffer_indices[2]);
+    let output_gradient: &Tensor2D = &tail[0];
+
+    let input_column_count: usize = head[input_index].column_count;
+    Tensor2D::concat_columns_backward(output_gradient, 0, &mut head[input_index]);
+    Tensor2D::concat_columns_backward(output_gradient, input_column_count, &mut head[other_index]);
+
+    Ok(())
+}
+
+// The gradient of the add flows unchanged to the named tensor and into the linear operator
+pub fn linear_add_backward(
+    node: &Node,
+    data_buffers: &[Tensor2D],
+    gradient_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 5)?;
+
+    let other_index: usize = node.buffer_indices[3];
+    let output_index: usize = node.buffer_indices[4];
+    let ([output_gradient], other_gradient) =
+        split_around_output(gradient_buffers, [output_index], other_index);
+    Tensor2D::add_inplace(other_gradient, output_gradient);
+
+    let linear_node: Node = Node::new(
+        node.name.clone(),
+        NodeOperator::Linear,
+        vec![
+            node.buffer_indices[0],
+            node.buffer_indices[1],
+            node.buffer_indices[2],
+            output_index,
+        ],
+    );
+    linear_backward(&linear_node, data_buffers, gradient_buffers)
+}
+
+pub fn add_relu_backward(
+    node: &Node,
+    data_buffers: &[Tensor2D],
+    gradient_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 3)?;
+
+    let output: &Tensor2D = &data_buffers[node.buffer_indices[2]];
+    let output_gradient: &Tensor2D = &gradient_buffers[node.buffer_indices[2]];
+    let mut add_gradient: Tensor2D = Tensor2D::new(0.0, output.row_count, output.column_count);
+    Tensor2D::relu_backward(output, output_gradient, &mut add_gradient);
+
+    // Like add_backward, if the input and the other tensor are the same buffer,
+    // it receives the gradient twice.
+    for index in [node.buffer_indices[0], node.buffer_indices[1]] {
+        Tensor2D::add_inplace(&mut gradient_buffers[index], &add_gradient);
+    }
+
+    Ok(())
 }
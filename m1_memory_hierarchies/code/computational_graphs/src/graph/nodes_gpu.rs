@@ -1,5 +1,4 @@
-use core::panic;
-use std::collections::HashMap;
+use std::sync::Arc;
 
 use wgpu::{
     BindGroup, BindGroupLayout, BindingResource, CommandEncoder, ComputePass, ComputePipeline,
@@ -7,35 +6,50 @@ use wgpu::{
 };
 
 use crate::shared::{
+    activation::ActivationFunction,
     gpu_utilities::{create_bind_group, create_compute_pipeline, create_shader_module, GPUHandles},
-    tensor2d_gpu::{LinearUniform, ReluUniform, SoftmaxUniform, Tensor2DGPU},
+    pipeline_cache::PipelineCache,
+    tensor2d_gpu::{
+        ActivationUniform, BinaryUniform, LinearUniform, ReluUniform, SoftmaxUniform, Tensor2DGPU,
+    },
 };
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub enum NodeOperatorGPU {
-    HostToDevice,
-    DeviceToHost,
-    DeviceToDevice,
-    Linear,
-    ReLU,
-    Softmax,
-    LinearReLU,
-    LinearReLUSoftmax,
-}
+use super::graph_error::GraphError;
+use super::nodes::{check_buffer_count, Node};
 
-#[derive(Debug)]
-pub struct NodeGPU {
-    pub name: String,
-    pub operator: NodeOperatorGPU,
-    pub buffer_indices: Vec<usize>,
-}
+const LINEAR_SHADER: &str = include_str!("../shared/shaders/linear.wgsl");
+const RELU_SHADER: &str = include_str!("../shared/shaders/relu.wgsl");
+const ACTIVATION_SHADER: &str = include_str!("../shared/shaders/activation.wgsl");
+const SOFTMAX_SHADER: &str = include_str!("../shared/shaders/softmax.wgsl");
+const ADD_SHADER: &str = include_str!("../shared/shaders/add.wgsl");
+const CONCAT_SHADER: &str = include_str!("../shared/shaders/concat.wgsl");
 
-impl NodeGPU {
-    pub fn new(name: String, operator: NodeOperatorGPU, buffer_indices: Vec<usize>) -> Self {
-        NodeGPU {
-            name,
-            operator,
-            buffer_indices,
+// Without a cache the shader is compiled every time a node is dispatched,
+// which is what the cached graph runners are benchmarked against.
+// The shaders are validated by shader_validation_test, so failing to compile one is a bug.
+fn get_compute_pipeline(
+    gpu_handles: &GPUHandles,
+    pipeline_cache: Option<&PipelineCache>,
+    label: &str,
+    shader_source: &str,
+    entry_point: &str,
+) -> Arc<ComputePipeline> {
+    match pipeline_cache {
+        Some(pipeline_cache) => pipeline_cache
+            .pipeline(gpu_handles, label, shader_source, &[], entry_point)
+            .unwrap_or_else(|error| {
+                panic!(
+                    "Failed to get the {} pipeline in graph::nodes_gpu: {}",
+                    label, error
+                )
+            }),
+        None => {
+            let cs_module: ShaderModule = create_shader_module(gpu_handles, shader_source);
+            Arc::new(create_compute_pipeline(
+                gpu_handles,
+                &cs_module,
+                entry_point,
+            ))
         }
     }
 }
@@ -43,55 +57,37 @@ impl NodeGPU {
 // Linear Layer
 pub fn build_linear_elements(
     gpu_handles: &GPUHandles,
-    shader_cache: &mut HashMap<String, ShaderModule>,
-    pipeline_cache: &mut HashMap<String, ComputePipeline>,
+    pipeline_cache: &PipelineCache,
     use_fused_with_relu: bool,
 ) {
-    let key: String = "Linear".to_string();
-
-    let cs_module: ShaderModule = create_shader_module(
+    get_compute_pipeline(
         gpu_handles,
-        include_str!("../shared/shaders/linear.wgsl"),
+        Some(pipeline_cache),
+        "Linear",
+        LINEAR_SHADER,
+        "main",
     );
 
-    let entry_point: &str = "main";
-    let compute_pipeline: ComputePipeline =
-        create_compute_pipeline(gpu_handles, &cs_module, entry_point);
-
-    shader_cache.insert(key.clone(), cs_module);
-    pipeline_cache.insert(key, compute_pipeline);
-
     if use_fused_with_relu {
-        let cs_module: ShaderModule = create_shader_module(
+        get_compute_pipeline(
             gpu_handles,
-            include_str!("../shared/shaders/linear.wgsl"),
+            Some(pipeline_cache),
+            "LinearReLU",
+            LINEAR_SHADER,
+            "main_with_relu",
         );
-        let key: String = "LinearReLU".to_string();
-        let entry_point: &str = "main_with_relu";
-        let compute_pipeline: ComputePipeline =
-            create_compute_pipeline(gpu_handles, &cs_module, entry_point);
-
-        shader_cache.insert(key.clone(), cs_module);
-        pipeline_cache.insert(key, compute_pipeline);
     }
 }
 
 pub fn linear(
     gpu_handles: &GPUHandles,
-    use_cache: bool,
-    shader_cache: &HashMap<String, ShaderModule>,
-    pipeline_cache: &HashMap<String, ComputePipeline>,
-    node: &NodeGPU,
+    pipeline_cache: Option<&PipelineCache>,
+    node: &Node,
     data_buffers: &[Tensor2DGPU],
     encoder: &mut CommandEncoder,
     use_fused_with_relu: bool,
-) {
-    if node.buffer_indices.len() != 4 {
-        panic!(
-            "nodes::linear function expected 1 input buffer, received {}",
-            node.buffer_indices.len()
-        );
-    }
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
 
     let input: &Tensor2DGPU = &data_buffers[node.buffer_indices[0]];
     let weights: &Tensor2DGPU = &data_buffers[node.buffer_indices[1]];
@@ -114,58 +110,16 @@ pub fn linear(
         output,
     );
 
-    let shader_module: Option<ShaderModule> = if use_cache {
-        None
-    } else {
-        Some(create_shader_module(
+    let compute_pipeline: Arc<ComputePipeline> = if use_fused_with_relu {
+        get_compute_pipeline(
             gpu_handles,
-            include_str!("../shared/shaders/linear.wgsl"),
-        ))
-    };
-
-    let cs_module: &ShaderModule = if use_cache {
-        let key: &str = if use_fused_with_relu {
-            "LinearReLU"
-        } else {
-            "Linear"
-        };
-        if shader_cache.contains_key(key) {
-            &shader_cache[key]
-        } else {
-            panic!("Tried to get a cached {} shader in graph::nodes::linear(), but failed to find it in the shader cache!", key);
-        }
-    } else {
-        shader_module
-            .as_ref()
-            .expect("Failed to get a reference to compute shader module")
-    };
-
-    let pipeline: Option<ComputePipeline> = if !use_cache {
-        let entry_point: &str = if use_fused_with_relu {
-            "main_with_relu"
-        } else {
-            "main"
-        };
-        Some(create_compute_pipeline(gpu_handles, cs_module, entry_point))
-    } else {
-        None
-    };
-
-    let compute_pipeline: &ComputePipeline = if use_cache {
-        let key: &str = if use_fused_with_relu {
-            "LinearReLU"
-        } else {
-            "Linear"
-        };
-        if pipeline_cache.contains_key(key) {
-            &pipeline_cache[key]
-        } else {
-            panic!("Tried to get a cached {} pipeline in graph::nodes::linear(), but failed to find it in the pipeline cache!", key);
-        }
+            pipeline_cache,
+            "LinearReLU",
+            LINEAR_SHADER,
+            "main_with_relu",
+        )
     } else {
-        pipeline
-            .as_ref()
-            .expect("Failed to get a reference to compute pipeline in graph::nodes::linear")
+        get_compute_pipeline(gpu_handles, pipeline_cache, "Linear", LINEAR_SHADER, "main")
     };
 
     // Instantiates the bind group, once again specifying the binding of buffers.
@@ -187,97 +141,197 @@ pub fn linear(
                 "linear_graph"
             }),
         });
-        cpass.set_pipeline(compute_pipeline);
+        cpass.set_pipeline(&compute_pipeline);
         cpass.set_bind_group(0, &bind_group, &[]);
         cpass.insert_debug_marker("linear_graph");
         cpass.dispatch_workgroups(launch_blocks_x, launch_blocks_y, 1); // Number of cells to run, the (x,y,z) size of item being processed
     }
+
+    Ok(())
 }
 
-// ReLU
-pub fn build_relu_elements(
+// Linear followed by Add, the main_with_add entry point of the linear shader
+pub fn build_linear_add_elements(gpu_handles: &GPUHandles, pipeline_cache: &PipelineCache) {
+    get_compute_pipeline(
+        gpu_handles,
+        Some(pipeline_cache),
+        "LinearAdd",
+        LINEAR_SHADER,
+        "main_with_add",
+    );
+}
+
+// The buffer indices are [input, weights, bias, residual, output]
+pub fn linear_add(
     gpu_handles: &GPUHandles,
-    shader_cache: &mut HashMap<String, ShaderModule>,
-    pipeline_cache: &mut HashMap<String, ComputePipeline>,
-) {
-    let key: String = "ReLU".to_string();
+    pipeline_cache: Option<&PipelineCache>,
+    node: &Node,
+    data_buffers: &[Tensor2DGPU],
+    encoder: &mut CommandEncoder,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 5)?;
 
-    let cs_module: ShaderModule =
-        create_shader_module(gpu_handles, include_str!("../shared/shaders/relu.wgsl"));
+    let input: &Tensor2DGPU = &data_buffers[node.buffer_indices[0]];
+    let weights: &Tensor2DGPU = &data_buffers[node.buffer_indices[1]];
+    let bias: &Tensor2DGPU = &data_buffers[node.buffer_indices[2]];
+    let residual: &Tensor2DGPU = &data_buffers[node.buffer_indices[3]];
+    let output: &Tensor2DGPU = &data_buffers[node.buffer_indices[4]];
 
-    let entry_point: &str = "main";
-    let compute_pipeline: ComputePipeline =
-        create_compute_pipeline(gpu_handles, &cs_module, entry_point);
+    let block_size: usize = 8;
+    let launch_blocks_x: u32 = output.row_count.div_ceil(block_size) as u32;
+    let launch_blocks_y: u32 = output.column_count.div_ceil(block_size) as u32;
 
-    shader_cache.insert(key.clone(), cs_module);
-    pipeline_cache.insert(key, compute_pipeline);
+    // The residual has the same dimensions as the output, so the uniform of the linear
+    // shader covers it as well.
+    let uniform_device: LinearUniform = LinearUniform::from_tensor_2d_gpu(
+        gpu_handles,
+        "Linear Add Uniform",
+        input,
+        weights,
+        bias,
+        output,
+    );
+
+    let compute_pipeline: Arc<ComputePipeline> = get_compute_pipeline(
+        gpu_handles,
+        pipeline_cache,
+        "LinearAdd",
+        LINEAR_SHADER,
+        "main_with_add",
+    );
+
+    // Instantiates the bind group, once again specifying the binding of buffers.
+    let bind_group_layout: BindGroupLayout = compute_pipeline.get_bind_group_layout(0);
+    let to_be_bound: Vec<(u32, BindingResource)> = vec![
+        (0, uniform_device.storage_buffer.as_entire_binding()),
+        (1, input.storage_buffer.as_entire_binding()),
+        (2, weights.storage_buffer.as_entire_binding()),
+        (3, bias.storage_buffer.as_entire_binding()),
+        (4, output.storage_buffer.as_entire_binding()),
+        (5, residual.storage_buffer.as_entire_binding()),
+    ];
+    let bind_group: BindGroup = create_bind_group(gpu_handles, &bind_group_layout, to_be_bound);
+
+    {
+        let mut cpass: ComputePass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("linear_add_graph"),
+        });
+        cpass.set_pipeline(&compute_pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.insert_debug_marker("linear_add_graph");
+        cpass.dispatch_workgroups(launch_blocks_x, launch_blocks_y, 1); // Number of cells to run, the (x,y,z) size of item being processed
+    }
+
+    Ok(())
 }
 
-pub fn relu(
+// Linear followed by one of the activation functions, the main_with_activation entry
+// point of the linear shader. The function is in a uniform, so every function shares
+// the same cached pipeline.
+pub fn build_linear_activation_elements(gpu_handles: &GPUHandles, pipeline_cache: &PipelineCache) {
+    get_compute_pipeline(
+        gpu_handles,
+        Some(pipeline_cache),
+        "LinearActivation",
+        LINEAR_SHADER,
+        "main_with_activation",
+    );
+}
+
+pub fn linear_activation(
     gpu_handles: &GPUHandles,
-    use_cache: bool,
-    shader_cache: &HashMap<String, ShaderModule>,
-    pipeline_cache: &HashMap<String, ComputePipeline>,
-    node: &NodeGPU,
+    pipeline_cache: Option<&PipelineCache>,
+    node: &Node,
     data_buffers: &[Tensor2DGPU],
     encoder: &mut CommandEncoder,
-) {
-    if node.buffer_indices.len() != 2 {
-        panic!(
-            "nodes::relu function expected 1 input buffer, received {}",
-            node.buffer_indices.len()
-        );
-    }
+    function: ActivationFunction,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
 
     let input: &Tensor2DGPU = &data_buffers[node.buffer_indices[0]];
-    let output: &Tensor2DGPU = &data_buffers[node.buffer_indices[1]];
+    let weights: &Tensor2DGPU = &data_buffers[node.buffer_indices[1]];
+    let bias: &Tensor2DGPU = &data_buffers[node.buffer_indices[2]];
+    let output: &Tensor2DGPU = &data_buffers[node.buffer_indices[3]];
 
-    let uniform: ReluUniform = ReluUniform::new(gpu_handles, "Relu Uniform", &input.data);
+    let block_size: usize = 8;
+    let launch_blocks_x: u32 = output.row_count.div_ceil(block_size) as u32;
+    let launch_blocks_y: u32 = output.column_count.div_ceil(block_size) as u32;
 
-    // let cs_module: ShaderModule =
-    //     create_shader_module(gpu_handles, include_str!("../shared/shaders/relu.wgsl"));
-    // let compute_pipeline: ComputePipeline =
-    //     create_compute_pipeline(gpu_handles, &cs_module, "main");
+    let uniform_device: LinearUniform = LinearUniform::from_tensor_2d_gpu(
+        gpu_handles,
+        "Linear Activation Uniform",
+        input,
+        weights,
+        bias,
+        output,
+    );
+    let activation_uniform: ActivationUniform = ActivationUniform::new(
+        gpu_handles,
+        "Linear Activation Function Uniform",
+        output.row_count * output.column_count,
+        function,
+    );
 
-    let shader_module: Option<ShaderModule> = if use_cache {
-        None
-    } else {
-        Some(create_shader_module(
-            gpu_handles,
-            include_str!("../shared/shaders/relu.wgsl"),
-        ))
-    };
+    let compute_pipeline: Arc<ComputePipeline> = get_compute_pipeline(
+        gpu_handles,
+        pipeline_cache,
+        "LinearActivation",
+        LINEAR_SHADER,
+        "main_with_activation",
+    );
 
-    let cs_module: &ShaderModule = if use_cache {
-        let key: &str = "ReLU";
-        if shader_cache.contains_key(key) {
-            &shader_cache[key]
-        } else {
-            panic!("Tried to get a cached {} shader in graph::nodes::relu(), but failed to find it in the shader cache!", key);
-        }
-    } else {
-        shader_module
-            .as_ref()
-            .expect("Failed to get a reference to compute shader module in graph::nodes::relu")
-    };
+    // Instantiates the bind group, once again specifying the binding of buffers.
+    let bind_group_layout: BindGroupLayout = compute_pipeline.get_bind_group_layout(0);
+    let to_be_bound: Vec<(u32, BindingResource)> = vec![
+        (0, uniform_device.storage_buffer.as_entire_binding()),
+        (1, input.storage_buffer.as_entire_binding()),
+        (2, weights.storage_buffer.as_entire_binding()),
+        (3, bias.storage_buffer.as_entire_binding()),
+        (4, output.storage_buffer.as_entire_binding()),
+        (6, activation_uniform.storage_buffer.as_entire_binding()),
+    ];
+    let bind_group: BindGroup = create_bind_group(gpu_handles, &bind_group_layout, to_be_bound);
 
-    let pipeline: Option<ComputePipeline> = if use_cache {
-        None
-    } else {
-        Some(create_compute_pipeline(gpu_handles, cs_module, "main"))
-    };
-    let compute_pipeline: &ComputePipeline = if use_cache {
-        let key: &str = "ReLU";
-        if pipeline_cache.contains_key(key) {
-            &pipeline_cache[key]
-        } else {
-            panic!("Tried to get a cached {} pipeline in graph::nodes::relu(), but failed to find it in the pipeline cache!", key);
-        }
-    } else {
-        pipeline
-            .as_ref()
-            .expect("Failed to get a reference to compute pipeline in graph::nodes::relu")
-    };
+    {
+        let mut cpass: ComputePass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("linear_activation_graph"),
+        });
+        cpass.set_pipeline(&compute_pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.insert_debug_marker("linear_activation_graph");
+        cpass.dispatch_workgroups(launch_blocks_x, launch_blocks_y, 1); // Number of cells to run, the (x,y,z) size of item being processed
+    }
+
+    Ok(())
+}
+
+// ReLU
+pub fn build_relu_elements(gpu_handles: &GPUHandles, pipeline_cache: &PipelineCache) {
+    get_compute_pipeline(
+        gpu_handles,
+        Some(pipeline_cache),
+        "ReLU",
+        RELU_SHADER,
+        "main",
+    );
+}
+
+pub fn relu(
+    gpu_handles: &GPUHandles,
+    pipeline_cache: Option<&PipelineCache>,
+    node: &Node,
+    data_buffers: &[Tensor2DGPU],
+    encoder: &mut CommandEncoder,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
+
+    let input: &Tensor2DGPU = &data_buffers[node.buffer_indices[0]];
+    let output: &Tensor2DGPU = &data_buffers[node.buffer_indices[1]];
+
+    let uniform: ReluUniform = ReluUniform::new(gpu_handles, "Relu Uniform", &input.data);
+
+    let compute_pipeline: Arc<ComputePipeline> =
+        get_compute_pipeline(gpu_handles, pipeline_cache, "ReLU", RELU_SHADER, "main");
 
     // Instantiates the bind group, once again specifying the binding of buffers.
     let bind_group_layout: BindGroupLayout = compute_pipeline.get_bind_group_layout(0);
@@ -291,7 +345,7 @@ pub fn relu(
     {
         let mut cpass: ComputePass =
             encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
-        cpass.set_pipeline(compute_pipeline);
+        cpass.set_pipeline(&compute_pipeline);
         cpass.set_bind_group(0, &bind_group, &[]);
         cpass.insert_debug_marker("Relu Graph");
         cpass.dispatch_workgroups(
@@ -300,114 +354,122 @@ pub fn relu(
             1,
         ); // Number of cells to run, the (x,y,z) size of item being processed
     }
+
+    Ok(())
 }
 
-// Softmax
-pub fn build_softmax_elements(
+// The activation functions other than ReLU, which all share activation.wgsl
+pub fn build_activation_elements(gpu_handles: &GPUHandles, pipeline_cache: &PipelineCache) {
+    get_compute_pipeline(
+        gpu_handles,
+        Some(pipeline_cache),
+        "Activation",
+        ACTIVATION_SHADER,
+        "main",
+    );
+}
+
+pub fn activation(
     gpu_handles: &GPUHandles,
-    shader_cache: &mut HashMap<String, ShaderModule>,
-    pipeline_cache: &mut HashMap<String, ComputePipeline>,
-) {
-    let cs_module: ShaderModule =
-        create_shader_module(gpu_handles, include_str!("../shared/shaders/softmax.wgsl"));
-    let entry_point: &str = "single_pass_max";
-    let key: String = format!("Softmax_{}", entry_point);
-    let compute_pipeline: ComputePipeline =
-        create_compute_pipeline(gpu_handles, &cs_module, entry_point);
+    pipeline_cache: Option<&PipelineCache>,
+    node: &Node,
+    data_buffers: &[Tensor2DGPU],
+    encoder: &mut CommandEncoder,
+    function: ActivationFunction,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
+
+    let input: &Tensor2DGPU = &data_buffers[node.buffer_indices[0]];
+    let output: &Tensor2DGPU = &data_buffers[node.buffer_indices[1]];
 
-    pipeline_cache.insert(key, compute_pipeline);
+    let element_count: usize = input.row_count * input.column_count;
+    let block_size: usize = 32;
+    let launch_blocks: u32 = element_count.div_ceil(block_size) as u32;
 
-    let entry_point: &str = "single_pass_sum";
-    let key: String = format!("Softmax_{}", entry_point);
-    let compute_pipeline: ComputePipeline =
-        create_compute_pipeline(gpu_handles, &cs_module, entry_point);
+    let uniform: ActivationUniform =
+        ActivationUniform::new(gpu_handles, "Activation Uniform", element_count, function);
 
-    pipeline_cache.insert(key, compute_pipeline);
+    let compute_pipeline: Arc<ComputePipeline> = get_compute_pipeline(
+        gpu_handles,
+        pipeline_cache,
+        "Activation",
+        ACTIVATION_SHADER,
+        "main",
+    );
 
-    let entry_point: &str = "map";
-    let key: String = format!("Softmax_{}", entry_point);
-    let compute_pipeline: ComputePipeline =
-        create_compute_pipeline(gpu_handles, &cs_module, entry_point);
+    // Instantiates the bind group, once again specifying the binding of buffers.
+    let bind_group_layout: BindGroupLayout = compute_pipeline.get_bind_group_layout(0);
+    let to_be_bound: Vec<(u32, BindingResource)> = vec![
+        (0, uniform.storage_buffer.as_entire_binding()),
+        (1, input.storage_buffer.as_entire_binding()),
+        (2, output.storage_buffer.as_entire_binding()),
+    ];
+    let bind_group: BindGroup = create_bind_group(gpu_handles, &bind_group_layout, to_be_bound);
 
-    pipeline_cache.insert(key, compute_pipeline);
+    {
+        let mut cpass: ComputePass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("activation_graph"),
+        });
+        cpass.set_pipeline(&compute_pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.insert_debug_marker("activation_graph");
+        cpass.dispatch_workgroups(launch_blocks, 1, 1);
+    }
+
+    Ok(())
+}
 
-    shader_cache.insert("Softmax".to_string(), cs_module);
+// Softmax
+pub fn build_softmax_elements(gpu_handles: &GPUHandles, pipeline_cache: &PipelineCache) {
+    for entry_point in ["single_pass_max", "single_pass_sum", "map"] {
+        get_compute_pipeline(
+            gpu_handles,
+            Some(pipeline_cache),
+            "Softmax",
+            SOFTMAX_SHADER,
+            entry_point,
+        );
+    }
 }
 
 pub fn softmax(
     gpu_handles: &GPUHandles,
-    use_cache: bool,
-    shader_cache: &HashMap<String, ShaderModule>,
-    pipeline_cache: &HashMap<String, ComputePipeline>,
-    node: &NodeGPU,
+    pipeline_cache: Option<&PipelineCache>,
+    node: &Node,
     data_buffers: &[Tensor2DGPU],
     encoder: &mut CommandEncoder,
-) {
-    if node.buffer_indices.len() != 2 {
-        panic!(
-            "nodes::softmax function expected 2 input buffer, received {}",
-            node.buffer_indices.len()
-        );
-    }
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
 
     let input: &Tensor2DGPU = &data_buffers[node.buffer_indices[0]];
     let output: &Tensor2DGPU = &data_buffers[node.buffer_indices[1]];
 
-    let uniform: SoftmaxUniform = SoftmaxUniform::new(gpu_handles, "Softmax Uniform", input.len());
-    let global_max: Tensor2DGPU = Tensor2DGPU::new(gpu_handles, "Softmax Global Max", 0.0, 1, 1);
-    let global_offset: Tensor2DGPU =
-        Tensor2DGPU::new(gpu_handles, "Softmax Global Offset", 0.0, 1, 1);
-
-    let shader_module: Option<ShaderModule> = if use_cache {
-        None
-    } else {
-        Some(create_shader_module(
-            gpu_handles,
-            include_str!("../shared/shaders/softmax.wgsl"),
-        ))
-    };
-    let cs_module: &ShaderModule = if use_cache {
-        let key: &str = "Softmax";
-        if shader_cache.contains_key(key) {
-            &shader_cache[key]
-        } else {
-            panic!("Tried to get a cached {} shader in graph::nodes::softmax(), but failed to find it in the shader cache!", key);
-        }
-    } else {
-        shader_module
-            .as_ref()
-            .expect("Failed to get a reference to compute shader module")
-    };
+    let uniform: SoftmaxUniform = SoftmaxUniform::new(
+        gpu_handles,
+        "Softmax Uniform",
+        input.row_count,
+        input.column_count,
+    );
+    let row_max: Tensor2DGPU =
+        Tensor2DGPU::new(gpu_handles, "Softmax Row Max", 0.0, input.row_count, 1);
+    let row_sum: Tensor2DGPU =
+        Tensor2DGPU::new(gpu_handles, "Softmax Row Sum", 0.0, input.row_count, 1);
 
     let to_be_bound: Vec<(u32, BindingResource)> = vec![
         (0, uniform.storage_buffer.as_entire_binding()),
         (1, input.storage_buffer.as_entire_binding()),
-        (2, global_max.storage_buffer.as_entire_binding()),
+        (2, row_max.storage_buffer.as_entire_binding()),
     ];
 
     // Instantiates the bind group, once again specifying the binding of buffers.
     {
-        let max_pipeline: Option<ComputePipeline> = if use_cache {
-            None
-        } else {
-            Some(create_compute_pipeline(
-                gpu_handles,
-                cs_module,
-                "single_pass_max",
-            ))
-        };
-        let max_compute_pipeline: &ComputePipeline = if use_cache {
-            let key: &str = "Softmax_single_pass_max";
-            if pipeline_cache.contains_key(key) {
-                &pipeline_cache[key]
-            } else {
-                panic!("Tried to get a cached {} pipeline in graph::nodes::softmax(), but failed to find it in the pipeline cache!", key);
-            }
-        } else {
-            max_pipeline
-                .as_ref()
-                .expect("Failed to get a reference to compute pipeline in graph::nodes::softmax")
-        };
+        let max_compute_pipeline: Arc<ComputePipeline> = get_compute_pipeline(
+            gpu_handles,
+            pipeline_cache,
+            "Softmax",
+            SOFTMAX_SHADER,
+            "single_pass_max",
+        );
 
         let max_bind_group_layout: BindGroupLayout = max_compute_pipeline.get_bind_group_layout(0);
         let max_bind_group: BindGroup =
@@ -416,40 +478,26 @@ pub fn softmax(
         let mut cpass: ComputePass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
             label: Some("Softmax - Max"),
         });
-        cpass.set_pipeline(max_compute_pipeline);
+        cpass.set_pipeline(&max_compute_pipeline);
         cpass.set_bind_group(0, &max_bind_group, &[]);
         cpass.insert_debug_marker("Softmax Immediate - Max");
-        cpass.dispatch_workgroups(1, 1, 1); // Number of cells to run, the (x,y,z) size of item being processed
+        cpass.dispatch_workgroups(input.row_count as u32, 1, 1); // One workgroup per row
     }
 
     let to_be_bound: Vec<(u32, BindingResource)> = vec![
         (0, uniform.storage_buffer.as_entire_binding()),
         (1, input.storage_buffer.as_entire_binding()),
-        (2, global_max.storage_buffer.as_entire_binding()),
-        (3, global_offset.storage_buffer.as_entire_binding()),
+        (2, row_max.storage_buffer.as_entire_binding()),
+        (3, row_sum.storage_buffer.as_entire_binding()),
     ];
     {
-        let sum_pipeline: Option<ComputePipeline> = if use_cache {
-            None
-        } else {
-            Some(create_compute_pipeline(
-                gpu_handles,
-                cs_module,
-                "single_pass_sum",
-            ))
-        };
-        let sum_compute_pipeline: &ComputePipeline = if use_cache {
-            let key: &str = "Softmax_single_pass_sum";
-            if pipeline_cache.contains_key(key) {
-                &pipeline_cache[key]
-            } else {
-                panic!("Tried to get a cached {} pipeline in graph::nodes::softmax(), but failed to find it in the pipeline cache!", key);
-            }
-        } else {
-            sum_pipeline
-                .as_ref()
-                .expect("Failed to get a reference to compute pipeline in graph::nodes::softmax")
-        };
+        let sum_compute_pipeline: Arc<ComputePipeline> = get_compute_pipeline(
+            gpu_handles,
+            pipeline_cache,
+            "Softmax",
+            SOFTMAX_SHADER,
+            "single_pass_sum",
+        );
 
         let sum_bind_group_layout: BindGroupLayout = sum_compute_pipeline.get_bind_group_layout(0);
         let sum_bind_group: BindGroup =
@@ -458,40 +506,28 @@ pub fn softmax(
         let mut cpass: ComputePass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
             label: Some("Softmax - Sum"),
         });
-        cpass.set_pipeline(sum_compute_pipeline);
+        cpass.set_pipeline(&sum_compute_pipeline);
         cpass.set_bind_group(0, &sum_bind_group, &[]);
         cpass.insert_debug_marker("Softmax Immediate - Sum");
-        cpass.dispatch_workgroups(1, 1, 1); // Number of cells to run, the (x,y,z) size of item being processed
+        cpass.dispatch_workgroups(input.row_count as u32, 1, 1); // One workgroup per row
     }
 
     let to_be_bound: Vec<(u32, BindingResource)> = vec![
         (0, uniform.storage_buffer.as_entire_binding()),
         (1, input.storage_buffer.as_entire_binding()),
-        (3, global_offset.storage_buffer.as_entire_binding()),
+        (2, row_max.storage_buffer.as_entire_binding()),
+        (3, row_sum.storage_buffer.as_entire_binding()),
         (4, output.storage_buffer.as_entire_binding()),
     ];
     let block_size: usize = 32;
     {
-        // let map_compute_pipeline: ComputePipeline =
-        //     create_compute_pipeline(gpu_handles, &cs_module, "map");
-
-        let map_pipeline: Option<ComputePipeline> = if use_cache {
-            None
-        } else {
-            Some(create_compute_pipeline(gpu_handles, cs_module, "map"))
-        };
-        let map_compute_pipeline: &ComputePipeline = if use_cache {
-            let key: &str = "Softmax_map";
-            if pipeline_cache.contains_key(key) {
-                &pipeline_cache[key]
-            } else {
-                panic!("Tried to get a cached {} pipeline in graph::nodes::softmax(), but failed to find it in the pipeline cache!", key);
-            }
-        } else {
-            map_pipeline
-                .as_ref()
-                .expect("Failed to get a reference to compute pipeline in graph::nodes::softmax")
-        };
+        let map_compute_pipeline: Arc<ComputePipeline> = get_compute_pipeline(
+            gpu_handles,
+            pipeline_cache,
+            "Softmax",
+            SOFTMAX_SHADER,
+            "map",
+        );
 
         let map_bind_group_layout: BindGroupLayout = map_compute_pipeline.get_bind_group_layout(0);
         let map_bind_group: BindGroup =
@@ -500,30 +536,25 @@ pub fn softmax(
         let mut cpass: ComputePass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
             label: Some("Softmax - Map"),
         });
-        cpass.set_pipeline(map_compute_pipeline);
+        cpass.set_pipeline(&map_compute_pipeline);
         cpass.set_bind_group(0, &map_bind_group, &[]);
         cpass.insert_debug_marker("Softmax Immediate - Map");
         cpass.dispatch_workgroups(((input.len() + block_size - 1) / block_size) as u32, 1, 1);
         // Number of cells to run, the (x,y,z) size of item being processed
     }
+
+    Ok(())
 }
 
 // LinearReLUSoftmax
 pub fn linear_relu_softmax(
     gpu_handles: &GPUHandles,
-    use_cache: bool,
-    shader_cache: &HashMap<String, ShaderModule>,
-    pipeline_cache: &HashMap<String, ComputePipeline>,
-    node: &NodeGPU,
+    pipeline_cache: Option<&PipelineCache>,
+    node: &Node,
     data_buffers: &[Tensor2DGPU],
     encoder: &mut CommandEncoder,
-) {
-    if node.buffer_indices.len() != 4 {
-        panic!(
-            "nodes::linear_relu_softmax function expected 4 buffers, received {}",
-            node.buffer_indices.len()
-        );
-    }
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
 
     let input: &Tensor2DGPU = &data_buffers[node.buffer_indices[0]];
     let weights: &Tensor2DGPU = &data_buffers[node.buffer_indices[1]];
@@ -552,76 +583,35 @@ pub fn linear_relu_softmax(
         bias,
         &intermediate,
     );
-    let softmax_uniform: SoftmaxUniform =
-        SoftmaxUniform::new(gpu_handles, "Softmax Uniform", intermediate.len());
-    let softmax_global_max: Tensor2DGPU =
-        Tensor2DGPU::new(gpu_handles, "Softmax Global Max", 0.0, 1, 1);
-    let softmax_global_offset: Tensor2DGPU =
-        Tensor2DGPU::new(gpu_handles, "Softmax Global Offset", 0.0, 1, 1);
-
-    let linear_shader_module: Option<ShaderModule> = if use_cache {
-        None
-    } else {
-        Some(create_shader_module(
-            gpu_handles,
-            include_str!("../shared/shaders/linear.wgsl"),
-        ))
-    };
-
-    let linear_cs_module: &ShaderModule = if use_cache {
-        let key: &str = "LinearReLU";
-        if shader_cache.contains_key(key) {
-            &shader_cache[key]
-        } else {
-            panic!("Tried to get a cached {} shader in graph::nodes::linear_relu_softmax(), but failed to find it!", key);
-        }
-    } else {
-        linear_shader_module.as_ref().expect(
-            "Failed to get a reference to compute pipeline in graph::nodes::linear_relu_softmax",
-        )
-    };
-
-    let softmax_shader_module: Option<ShaderModule> = if use_cache {
-        None
-    } else {
-        Some(create_shader_module(
-            gpu_handles,
-            include_str!("../shared/shaders/softmax.wgsl"),
-        ))
-    };
-    let softmax_cs_module: &ShaderModule = if use_cache {
-        let key: &str = "Softmax";
-        if shader_cache.contains_key(key) {
-            &shader_cache[key]
-        } else {
-            panic!("Tried to get a cached {} shader in graph::nodes::linear_relu_softmax(), but failed to find it in the shader cache!", key);
-        }
-    } else {
-        softmax_shader_module.as_ref().expect(
-            "Failed to get a reference to compute pipeline in graph::nodes::linear_relu_softmax",
-        )
-    };
+    let softmax_uniform: SoftmaxUniform = SoftmaxUniform::new(
+        gpu_handles,
+        "Softmax Uniform",
+        intermediate.row_count,
+        intermediate.column_count,
+    );
+    let softmax_row_max: Tensor2DGPU = Tensor2DGPU::new(
+        gpu_handles,
+        "Softmax Row Max",
+        0.0,
+        intermediate.row_count,
+        1,
+    );
+    let softmax_row_sum: Tensor2DGPU = Tensor2DGPU::new(
+        gpu_handles,
+        "Softmax Row Sum",
+        0.0,
+        intermediate.row_count,
+        1,
+    );
 
     {
-        let linear_pipeline: Option<ComputePipeline> = if use_cache {
-            None
-        } else {
-            Some(create_compute_pipeline(
-                gpu_handles,
-                linear_cs_module,
-                "main_with_relu",
-            ))
-        };
-        let linear_compute_pipeline: &ComputePipeline = if use_cache {
-            let key: &str = "LinearReLU";
-            if pipeline_cache.contains_key(key) {
-                &pipeline_cache[key]
-            } else {
-                panic!("Tried to get a cached {} pipeline in graph::nodes::linear_relu_softmax(), but failed to find it!", key);
-            }
-        } else {
-            linear_pipeline.as_ref().expect("Failed to get a reference to compute pipeline in graph::nodes::linear_relu_softmax")
-        };
+        let linear_compute_pipeline: Arc<ComputePipeline> = get_compute_pipeline(
+            gpu_handles,
+            pipeline_cache,
+            "LinearReLU",
+            LINEAR_SHADER,
+            "main_with_relu",
+        );
 
         let to_be_bound: Vec<(u32, BindingResource)> = vec![
             (0, linear_uniform.storage_buffer.as_entire_binding()),
@@ -638,7 +628,7 @@ pub fn linear_relu_softmax(
         let mut cpass: ComputePass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
             label: Some("linear_immediate"),
         });
-        cpass.set_pipeline(linear_compute_pipeline);
+        cpass.set_pipeline(&linear_compute_pipeline);
         cpass.set_bind_group(0, &linear_bind_group, &[]);
         cpass.insert_debug_marker("linear_immediate");
         cpass.dispatch_workgroups(linear_launch_blocks_x, linear_launch_blocks_y, 1);
@@ -649,30 +639,16 @@ pub fn linear_relu_softmax(
         let to_be_bound: Vec<(u32, BindingResource)> = vec![
             (0, softmax_uniform.storage_buffer.as_entire_binding()),
             (1, intermediate.storage_buffer.as_entire_binding()),
-            (2, softmax_global_max.storage_buffer.as_entire_binding()),
+            (2, softmax_row_max.storage_buffer.as_entire_binding()),
         ];
 
-        let max_pipeline: Option<ComputePipeline> = if use_cache {
-            None
-        } else {
-            Some(create_compute_pipeline(
-                gpu_handles,
-                softmax_cs_module,
-                "single_pass_max",
-            ))
-        };
-        let max_compute_pipeline: &ComputePipeline = if use_cache {
-            let key: &str = "Softmax_single_pass_max";
-            if pipeline_cache.contains_key(key) {
-                &pipeline_cache[key]
-            } else {
-                panic!("Tried to get a cached {} pipeline in graph::nodes::softmax(), but failed to find it in the pipeline cache!", key);
-            }
-        } else {
-            max_pipeline
-                .as_ref()
-                .expect("Failed to get a reference to compute pipeline in graph::nodes::softmax")
-        };
+        let max_compute_pipeline: Arc<ComputePipeline> = get_compute_pipeline(
+            gpu_handles,
+            pipeline_cache,
+            "Softmax",
+            SOFTMAX_SHADER,
+            "single_pass_max",
+        );
 
         let max_bind_group_layout: BindGroupLayout = max_compute_pipeline.get_bind_group_layout(0);
         let max_bind_group: BindGroup =
@@ -681,22 +657,27 @@ pub fn linear_relu_softmax(
         let mut cpass: ComputePass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
             label: Some("Softmax - Max"),
         });
-        cpass.set_pipeline(max_compute_pipeline);
+        cpass.set_pipeline(&max_compute_pipeline);
         cpass.set_bind_group(0, &max_bind_group, &[]);
         cpass.insert_debug_marker("Softmax Immediate - Max");
-        cpass.dispatch_workgroups(1, 1, 1); // Number of cells to run, the (x,y,z) size of item being processed
+        cpass.dispatch_workgroups(intermediate.row_count as u32, 1, 1); // One workgroup per row
     }
 
     {
         let to_be_bound: Vec<(u32, BindingResource)> = vec![
             (0, softmax_uniform.storage_buffer.as_entire_binding()),
             (1, intermediate.storage_buffer.as_entire_binding()),
-            (2, softmax_global_max.storage_buffer.as_entire_binding()),
-            (3, softmax_global_offset.storage_buffer.as_entire_binding()),
+            (2, softmax_row_max.storage_buffer.as_entire_binding()),
+            (3, softmax_row_sum.storage_buffer.as_entire_binding()),
         ];
 
-        let sum_compute_pipeline: ComputePipeline =
-            create_compute_pipeline(gpu_handles, softmax_cs_module, "single_pass_sum");
+        let sum_compute_pipeline: Arc<ComputePipeline> = get_compute_pipeline(
+            gpu_handles,
+            pipeline_cache,
+            "Softmax",
+            SOFTMAX_SHADER,
+            "single_pass_sum",
+        );
         let sum_bind_group_layout: BindGroupLayout = sum_compute_pipeline.get_bind_group_layout(0);
         let sum_bind_group: BindGroup =
             create_bind_group(gpu_handles, &sum_bind_group_layout, to_be_bound);
@@ -707,7 +688,7 @@ pub fn linear_relu_softmax(
         cpass.set_pipeline(&sum_compute_pipeline);
         cpass.set_bind_group(0, &sum_bind_group, &[]);
         cpass.insert_debug_marker("Softmax Immediate - Sum");
-        cpass.dispatch_workgroups(1, 1, 1); // Number of cells to run, the (x,y,z) size of item being processed
+        cpass.dispatch_workgroups(intermediate.row_count as u32, 1, 1); // One workgroup per row
     }
 
     let block_size: usize = 32;
@@ -715,31 +696,18 @@ pub fn linear_relu_softmax(
         let to_be_bound: Vec<(u32, BindingResource)> = vec![
             (0, softmax_uniform.storage_buffer.as_entire_binding()),
             (1, intermediate.storage_buffer.as_entire_binding()),
-            (3, softmax_global_offset.storage_buffer.as_entire_binding()),
+            (2, softmax_row_max.storage_buffer.as_entire_binding()),
+            (3, softmax_row_sum.storage_buffer.as_entire_binding()),
             (4, output.storage_buffer.as_entire_binding()),
         ];
 
-        let map_pipeline: Option<ComputePipeline> = if use_cache {
-            None
-        } else {
-            Some(create_compute_pipeline(
-                gpu_handles,
-                softmax_cs_module,
-                "map",
-            ))
-        };
-        let map_compute_pipeline: &ComputePipeline = if use_cache {
-            let key: &str = "Softmax_map";
-            if pipeline_cache.contains_key(key) {
-                &pipeline_cache[key]
-            } else {
-                panic!("Tried to get a cached {} pipeline in graph::nodes::softmax(), but failed to find it in the pipeline cache!", key);
-            }
-        } else {
-            map_pipeline
-                .as_ref()
-                .expect("Failed to get a reference to compute pipeline in graph::nodes::softmax")
-        };
+        let map_compute_pipeline: Arc<ComputePipeline> = get_compute_pipeline(
+            gpu_handles,
+            pipeline_cache,
+            "Softmax",
+            SOFTMAX_SHADER,
+            "map",
+        );
 
         let map_bind_group_layout: BindGroupLayout = map_compute_pipeline.get_bind_group_layout(0);
         let map_bind_group: BindGroup =
@@ -748,7 +716,7 @@ pub fn linear_relu_softmax(
         let mut cpass: ComputePass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
             label: Some("Softmax - Map"),
         });
-        cpass.set_pipeline(map_compute_pipeline);
+        cpass.set_pipeline(&map_compute_pipeline);
         cpass.set_bind_group(0, &map_bind_group, &[]);
         cpass.insert_debug_marker("Softmax Immediate - Map");
         cpass.dispatch_workgroups(
@@ -757,4 +725,145 @@ pub fn linear_relu_softmax(
             1,
         ); // Number of cells to run, the (x,y,z) size of item being processed
     }
+
+    Ok(())
+}
+
+// Add and Concat
+// Both take two tensors and share the same bindings, so they share the same code,
+// only the shader differs.
+pub fn build_add_elements(gpu_handles: &GPUHandles, pipeline_cache: &PipelineCache) {
+    get_compute_pipeline(gpu_handles, Some(pipeline_cache), "Add", ADD_SHADER, "main");
+}
+
+pub fn build_add_relu_elements(gpu_handles: &GPUHandles, pipeline_cache: &PipelineCache) {
+    get_compute_pipeline(
+        gpu_handles,
+        Some(pipeline_cache),
+        "AddReLU",
+        ADD_SHADER,
+        "main_with_relu",
+    );
+}
+
+pub fn build_concat_elements(gpu_handles: &GPUHandles, pipeline_cache: &PipelineCache) {
+    get_compute_pipeline(
+        gpu_handles,
+        Some(pipeline_cache),
+        "Concat",
+        CONCAT_SHADER,
+        "main",
+    );
+}
+
+pub fn add(
+    gpu_handles: &GPUHandles,
+    pipeline_cache: Option<&PipelineCache>,
+    node: &Node,
+    data_buffers: &[Tensor2DGPU],
+    encoder: &mut CommandEncoder,
+) -> Result<(), GraphError> {
+    binary(
+        gpu_handles,
+        pipeline_cache,
+        node,
+        data_buffers,
+        encoder,
+        "Add",
+        ADD_SHADER,
+        "main",
+    )
+}
+
+pub fn add_relu(
+    gpu_handles: &GPUHandles,
+    pipeline_cache: Option<&PipelineCache>,
+    node: &Node,
+    data_buffers: &[Tensor2DGPU],
+    encoder: &mut CommandEncoder,
+) -> Result<(), GraphError> {
+    binary(
+        gpu_handles,
+        pipeline_cache,
+        node,
+        data_buffers,
+        encoder,
+        "AddReLU",
+        ADD_SHADER,
+        "main_with_relu",
+    )
+}
+
+pub fn concat(
+    gpu_handles: &GPUHandles,
+    pipeline_cache: Option<&PipelineCache>,
+    node: &Node,
+    data_buffers: &[Tensor2DGPU],
+    encoder: &mut CommandEncoder,
+) -> Result<(), GraphError> {
+    binary(
+        gpu_handles,
+        pipeline_cache,
+        node,
+        data_buffers,
+        encoder,
+        "Concat",
+        CONCAT_SHADER,
+        "main",
+    )
+}
+
+// The buffer indices are [tensor_a, tensor_b, output], where tensor_b is a named tensor
+fn binary(
+    gpu_handles: &GPUHandles,
+    pipeline_cache: Option<&PipelineCache>,
+    node: &Node,
+    data_buffers: &[Tensor2DGPU],
+    encoder: &mut CommandEncoder,
+    key: &str,
+    shader_source: &str,
+    entry_point: &str,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 3)?;
+
+    let tensor_a: &Tensor2DGPU = &data_buffers[node.buffer_indices[0]];
+    let tensor_b: &Tensor2DGPU = &data_buffers[node.buffer_indices[1]];
+    let output: &Tensor2DGPU = &data_buffers[node.buffer_indices[2]];
+
+    let uniform: BinaryUniform = BinaryUniform::new(
+        gpu_handles,
+        &format!("{} Uniform", key),
+        &tensor_a.data,
+        &tensor_b.data,
+        &output.data,
+    );
+
+    let compute_pipeline: Arc<ComputePipeline> =
+        get_compute_pipeline(gpu_handles, pipeline_cache, key, shader_source, entry_point);
+
+    // Instantiates the bind group, once again specifying the binding of buffers.
+    let bind_group_layout: BindGroupLayout = compute_pipeline.get_bind_group_layout(0);
+    let to_be_bound: Vec<(u32, BindingResource)> = vec![
+        (0, uniform.storage_buffer.as_entire_binding()),
+        (1, tensor_a.storage_buffer.as_entire_binding()),
+        (2, tensor_b.storage_buffer.as_entire_binding()),
+        (3, output.storage_buffer.as_entire_binding()),
+    ];
+    let bind_group: BindGroup = create_bind_group(gpu_handles, &bind_group_layout, to_be_bound);
+
+    {
+        let mut cpass: ComputePass =
+            encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+        cpass.set_pipeline(&compute_pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.insert_debug_marker(key);
+        // One thread per element of the output, the rows are split in blocks of 32
+        cpass.dispatch_workgroups(
+            output.row_count.div_ceil(32) as u32,
+            output.column_count as u32,
+            1,
+        ); // Number of cells to run, the (x,y,z) size of item being processed
+    }
+
+    Ok(())
 }
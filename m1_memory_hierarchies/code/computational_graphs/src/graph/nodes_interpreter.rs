@@ -0,0 +1,484 @@
+use crate::shader_interpreter::{device::InterpreterDevice, executor::InterpreterError};
+use crate::shared::{
+    activation::ActivationFunction,
+    tensor2d::Tensor2D,
+    tensor2d_gpu::{
+        ActivationParameters, BinaryDimensions, LinearDimensions, ReluDimensions, SoftmaxDimensions,
+    },
+};
+
+use super::graph_error::GraphError;
+use super::nodes::{check_buffer_count, Node};
+
+// The nodes of nodes_gpu, dispatched on the shader interpreter instead of the GPU.
+// Every node runs the same shader and entry points with the same bindings and the
+// same number of workgroups as its twin in nodes_gpu, so a test passing here tests
+// the kernels and the launches GraphRunnerGPU uses.
+//
+// The tensors live on the host. The buffers a node reads are copied before the
+// dispatch, as a node can read the same buffer twice, such as adding a stored tensor
+// to itself, and the interpreter needs a mutable slice for every binding.
+
+const LINEAR_SHADER: &str = include_str!("../shared/shaders/linear.wgsl");
+const RELU_SHADER: &str = include_str!("../shared/shaders/relu.wgsl");
+const ACTIVATION_SHADER: &str = include_str!("../shared/shaders/activation.wgsl");
+const SOFTMAX_SHADER: &str = include_str!("../shared/shaders/softmax.wgsl");
+const ADD_SHADER: &str = include_str!("../shared/shaders/add.wgsl");
+const CONCAT_SHADER: &str = include_str!("../shared/shaders/concat.wgsl");
+
+fn kernel_failed(node: &Node, error: InterpreterError) -> GraphError {
+    GraphError::KernelFailed {
+        node: node.name.clone(),
+        message: error.to_string(),
+    }
+}
+
+fn linear_dimensions(
+    input: &Tensor2D,
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    output_row_count: usize,
+    output_column_count: usize,
+) -> LinearDimensions {
+    LinearDimensions {
+        data: [
+            input.row_count as u32,
+            input.column_count as u32,
+            weights.row_count as u32,
+            weights.column_count as u32,
+            bias.row_count as u32,
+            bias.column_count as u32,
+            output_row_count as u32,
+            output_column_count as u32,
+        ],
+    }
+}
+
+fn activation_parameters(
+    element_count: usize,
+    function: ActivationFunction,
+) -> ActivationParameters {
+    ActivationParameters {
+        data: [
+            element_count as u32,
+            function.shader_index(),
+            function.alpha().to_bits(),
+            0,
+        ],
+    }
+}
+
+// The linear shaders have 8x8 workgroups with the rows along x and the columns along y
+fn linear_workgroup_count(row_count: usize, column_count: usize) -> [u32; 3] {
+    let block_size: usize = 8;
+    [
+        row_count.div_ceil(block_size) as u32,
+        column_count.div_ceil(block_size) as u32,
+        1,
+    ]
+}
+
+pub fn linear(
+    device: &mut InterpreterDevice,
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    use_fused_with_relu: bool,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let weights: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+    let bias: &Tensor2D = &data_buffers[node.buffer_indices[2]];
+    let mut input_data: Vec<f32> = input.data.clone();
+    let mut weights_data: Vec<f32> = weights.data.clone();
+    let mut bias_data: Vec<f32> = bias.data.clone();
+    let output: &Tensor2D = &data_buffers[node.buffer_indices[3]];
+    let mut dimensions: LinearDimensions =
+        linear_dimensions(input, weights, bias, output.row_count, output.column_count);
+    let workgroup_count: [u32; 3] = linear_workgroup_count(output.row_count, output.column_count);
+
+    let entry_point: &str = if use_fused_with_relu {
+        "main_with_relu"
+    } else {
+        "main"
+    };
+    let output: &mut Tensor2D = &mut data_buffers[node.buffer_indices[3]];
+    device
+        .dispatch(
+            "linear.wgsl",
+            LINEAR_SHADER,
+            entry_point,
+            workgroup_count,
+            &mut [
+                (0, bytemuck::bytes_of_mut(&mut dimensions)),
+                (1, bytemuck::cast_slice_mut(&mut input_data)),
+                (2, bytemuck::cast_slice_mut(&mut weights_data)),
+                (3, bytemuck::cast_slice_mut(&mut bias_data)),
+                (4, bytemuck::cast_slice_mut(&mut output.data)),
+            ],
+        )
+        .map_err(|error| kernel_failed(node, error))
+}
+
+// The buffer indices are [input, weights, bias, residual, output]
+pub fn linear_add(
+    device: &mut InterpreterDevice,
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 5)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let weights: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+    let bias: &Tensor2D = &data_buffers[node.buffer_indices[2]];
+    let mut input_data: Vec<f32> = input.data.clone();
+    let mut weights_data: Vec<f32> = weights.data.clone();
+    let mut bias_data: Vec<f32> = bias.data.clone();
+    let mut residual_data: Vec<f32> = data_buffers[node.buffer_indices[3]].data.clone();
+    let output: &Tensor2D = &data_buffers[node.buffer_indices[4]];
+    let mut dimensions: LinearDimensions =
+        linear_dimensions(input, weights, bias, output.row_count, output.column_count);
+    let workgroup_count: [u32; 3] = linear_workgroup_count(output.row_count, output.column_count);
+
+    let output: &mut Tensor2D = &mut data_buffers[node.buffer_indices[4]];
+    device
+        .dispatch(
+            "linear.wgsl",
+            LINEAR_SHADER,
+            "main_with_add",
+            workgroup_count,
+            &mut [
+                (0, bytemuck::bytes_of_mut(&mut dimensions)),
+                (1, bytemuck::cast_slice_mut(&mut input_data)),
+                (2, bytemuck::cast_slice_mut(&mut weights_data)),
+                (3, bytemuck::cast_slice_mut(&mut bias_data)),
+                (4, bytemuck::cast_slice_mut(&mut output.data)),
+                (5, bytemuck::cast_slice_mut(&mut residual_data)),
+            ],
+        )
+        .map_err(|error| kernel_failed(node, error))
+}
+
+pub fn linear_activation(
+    device: &mut InterpreterDevice,
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    function: ActivationFunction,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let weights: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+    let bias: &Tensor2D = &data_buffers[node.buffer_indices[2]];
+    let mut input_data: Vec<f32> = input.data.clone();
+    let mut weights_data: Vec<f32> = weights.data.clone();
+    let mut bias_data: Vec<f32> = bias.data.clone();
+    let output: &Tensor2D = &data_buffers[node.buffer_indices[3]];
+    let mut dimensions: LinearDimensions =
+        linear_dimensions(input, weights, bias, output.row_count, output.column_count);
+    let mut parameters: ActivationParameters =
+        activation_parameters(output.row_count * output.column_count, function);
+    let workgroup_count: [u32; 3] = linear_workgroup_count(output.row_count, output.column_count);
+
+    let output: &mut Tensor2D = &mut data_buffers[node.buffer_indices[3]];
+    device
+        .dispatch(
+            "linear.wgsl",
+            LINEAR_SHADER,
+            "main_with_activation",
+            workgroup_count,
+            &mut [
+                (0, bytemuck::bytes_of_mut(&mut dimensions)),
+                (1, bytemuck::cast_slice_mut(&mut input_data)),
+                (2, bytemuck::cast_slice_mut(&mut weights_data)),
+                (3, bytemuck::cast_slice_mut(&mut bias_data)),
+                (4, bytemuck::cast_slice_mut(&mut output.data)),
+                (6, bytemuck::bytes_of_mut(&mut parameters)),
+            ],
+        )
+        .map_err(|error| kernel_failed(node, error))
+}
+
+pub fn relu(
+    device: &mut InterpreterDevice,
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let mut input_data: Vec<f32> = input.data.clone();
+    let mut dimensions: ReluDimensions = ReluDimensions {
+        data: [input.row_count as u32, input.column_count as u32],
+    };
+    // The same launch as nodes_gpu::relu, one workgroup per element
+    let workgroup_count: [u32; 3] = [input.row_count as u32, input.column_count as u32, 1];
+
+    let output: &mut Tensor2D = &mut data_buffers[node.buffer_indices[1]];
+    device
+        .dispatch(
+            "relu.wgsl",
+            RELU_SHADER,
+            "main",
+            workgroup_count,
+            &mut [
+                (0, bytemuck::bytes_of_mut(&mut dimensions)),
+                (1, bytemuck::cast_slice_mut(&mut input_data)),
+                (2, bytemuck::cast_slice_mut(&mut output.data)),
+            ],
+        )
+        .map_err(|error| kernel_failed(node, error))
+}
+
+pub fn activation(
+    device: &mut InterpreterDevice,
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    function: ActivationFunction,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let mut input_data: Vec<f32> = input.data.clone();
+    let element_count: usize = input.row_count * input.column_count;
+    let mut parameters: ActivationParameters = activation_parameters(element_count, function);
+    let block_size: usize = 32;
+    let workgroup_count: [u32; 3] = [element_count.div_ceil(block_size) as u32, 1, 1];
+
+    let output: &mut Tensor2D = &mut data_buffers[node.buffer_indices[1]];
+    device
+        .dispatch(
+            "activation.wgsl",
+            ACTIVATION_SHADER,
+            "main",
+            workgroup_count,
+            &mut [
+                (0, bytemuck::bytes_of_mut(&mut parameters)),
+                (1, bytemuck::cast_slice_mut(&mut input_data)),
+                (2, bytemuck::cast_slice_mut(&mut output.data)),
+            ],
+        )
+        .map_err(|error| kernel_failed(node, error))
+}
+
+// The max pass, the sum pass and the map pass of softmax.wgsl. The dimensions are
+// those of input, output only has to have as many elements.
+fn softmax_passes(
+    device: &mut InterpreterDevice,
+    input: &mut [f32],
+    row_count: usize,
+    column_count: usize,
+    output: &mut [f32],
+) -> Result<(), InterpreterError> {
+    let mut dimensions: SoftmaxDimensions = SoftmaxDimensions {
+        data: [
+            (row_count * column_count) as u32,
+            row_count as u32,
+            column_count as u32,
+            0,
+        ],
+    };
+    let mut row_max: Vec<f32> = vec![0.0; row_count];
+    let mut row_sum: Vec<f32> = vec![0.0; row_count];
+
+    // One workgroup per row
+    device.dispatch(
+        "softmax.wgsl",
+        SOFTMAX_SHADER,
+        "single_pass_max",
+        [row_count as u32, 1, 1],
+        &mut [
+            (0, bytemuck::bytes_of_mut(&mut dimensions)),
+            (1, bytemuck::cast_slice_mut(input)),
+            (2, bytemuck::cast_slice_mut(&mut row_max)),
+        ],
+    )?;
+    device.dispatch(
+        "softmax.wgsl",
+        SOFTMAX_SHADER,
+        "single_pass_sum",
+        [row_count as u32, 1, 1],
+        &mut [
+            (0, bytemuck::bytes_of_mut(&mut dimensions)),
+            (1, bytemuck::cast_slice_mut(input)),
+            (2, bytemuck::cast_slice_mut(&mut row_max)),
+            (3, bytemuck::cast_slice_mut(&mut row_sum)),
+        ],
+    )?;
+
+    let block_size: usize = 32;
+    device.dispatch(
+        "softmax.wgsl",
+        SOFTMAX_SHADER,
+        "map",
+        [(row_count * column_count).div_ceil(block_size) as u32, 1, 1],
+        &mut [
+            (0, bytemuck::bytes_of_mut(&mut dimensions)),
+            (1, bytemuck::cast_slice_mut(input)),
+            (2, bytemuck::cast_slice_mut(&mut row_max)),
+            (3, bytemuck::cast_slice_mut(&mut row_sum)),
+            (4, bytemuck::cast_slice_mut(output)),
+        ],
+    )
+}
+
+pub fn softmax(
+    device: &mut InterpreterDevice,
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 2)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let mut input_data: Vec<f32> = input.data.clone();
+    let row_count: usize = input.row_count;
+    let column_count: usize = input.column_count;
+
+    let output: &mut Tensor2D = &mut data_buffers[node.buffer_indices[1]];
+    softmax_passes(
+        device,
+        &mut input_data,
+        row_count,
+        column_count,
+        &mut output.data,
+    )
+    .map_err(|error| kernel_failed(node, error))
+}
+
+// main_with_relu of the linear shader into an intermediate with the dimensions
+// of the bias, followed by the passes of softmax
+pub fn linear_relu_softmax(
+    device: &mut InterpreterDevice,
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 4)?;
+
+    let input: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let weights: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+    let bias: &Tensor2D = &data_buffers[node.buffer_indices[2]];
+    let mut input_data: Vec<f32> = input.data.clone();
+    let mut weights_data: Vec<f32> = weights.data.clone();
+    let mut bias_data: Vec<f32> = bias.data.clone();
+    let row_count: usize = bias.row_count;
+    let column_count: usize = bias.column_count;
+    let mut intermediate: Vec<f32> = vec![0.0; row_count * column_count];
+    let mut dimensions: LinearDimensions =
+        linear_dimensions(input, weights, bias, row_count, column_count);
+
+    device
+        .dispatch(
+            "linear.wgsl",
+            LINEAR_SHADER,
+            "main_with_relu",
+            linear_workgroup_count(row_count, column_count),
+            &mut [
+                (0, bytemuck::bytes_of_mut(&mut dimensions)),
+                (1, bytemuck::cast_slice_mut(&mut input_data)),
+                (2, bytemuck::cast_slice_mut(&mut weights_data)),
+                (3, bytemuck::cast_slice_mut(&mut bias_data)),
+                (4, bytemuck::cast_slice_mut(&mut intermediate)),
+            ],
+        )
+        .map_err(|error| kernel_failed(node, error))?;
+
+    let output: &mut Tensor2D = &mut data_buffers[node.buffer_indices[3]];
+    softmax_passes(
+        device,
+        &mut intermediate,
+        row_count,
+        column_count,
+        &mut output.data,
+    )
+    .map_err(|error| kernel_failed(node, error))
+}
+
+pub fn add(
+    device: &mut InterpreterDevice,
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    binary(device, node, data_buffers, "add.wgsl", ADD_SHADER, "main")
+}
+
+pub fn add_relu(
+    device: &mut InterpreterDevice,
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    binary(
+        device,
+        node,
+        data_buffers,
+        "add.wgsl",
+        ADD_SHADER,
+        "main_with_relu",
+    )
+}
+
+pub fn concat(
+    device: &mut InterpreterDevice,
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+) -> Result<(), GraphError> {
+    binary(
+        device,
+        node,
+        data_buffers,
+        "concat.wgsl",
+        CONCAT_SHADER,
+        "main",
+    )
+}
+
+// The buffer indices are [tensor_a, tensor_b, output], where tensor_b is a named tensor
+fn binary(
+    device: &mut InterpreterDevice,
+    node: &Node,
+    data_buffers: &mut [Tensor2D],
+    label: &str,
+    shader_source: &str,
+    entry_point: &str,
+) -> Result<(), GraphError> {
+    check_buffer_count(node, 3)?;
+
+    let tensor_a: &Tensor2D = &data_buffers[node.buffer_indices[0]];
+    let tensor_b: &Tensor2D = &data_buffers[node.buffer_indices[1]];
+    let mut tensor_a_data: Vec<f32> = tensor_a.data.clone();
+    let mut tensor_b_data: Vec<f32> = tensor_b.data.clone();
+    let output: &Tensor2D = &data_buffers[node.buffer_indices[2]];
+    let mut dimensions: BinaryDimensions = BinaryDimensions {
+        data: [
+            tensor_a.row_count as u32,
+            tensor_a.column_count as u32,
+            tensor_b.row_count as u32,
+            tensor_b.column_count as u32,
+            output.row_count as u32,
+            output.column_count as u32,
+            0,
+            0,
+        ],
+    };
+    // One thread per element of the output, the rows are split in blocks of 32
+    let workgroup_count: [u32; 3] = [
+        output.row_count.div_ceil(32) as u32,
+        output.column_count as u32,
+        1,
+    ];
+
+    let output: &mut Tensor2D = &mut data_buffers[node.buffer_indices[2]];
+    device
+        .dispatch(
+            label,
+            shader_source,
+            entry_point,
+            workgroup_count,
+            &mut [
+                (0, bytemuck::bytes_of_mut(&mut dimensions)),
+                (1, bytemuck::cast_slice_mut(&mut tensor_a_data)),
+                (2, bytemuck::cast_slice_mut(&mut tensor_b_data)),
+                (3, bytemuck::cast_slice_mut(&mut output.data)),
+            ],
+        )
+        .map_err(|error| kernel_failed(node, error))
+}
@@ -0,0 +1,548 @@
+use std::{fmt, fs, path::Path};
+
+use prost::Message;
+
+use crate::{
+    graph::{graph_error::GraphError, graph_validation::validate_graph_operators},
+    shared::{graph_operators::GraphOperator, tensor2d::Tensor2D},
+};
+
+// Imports small multilayer perceptrons exported as ONNX. Only a chain of
+// Gemm, MatMul (optionally followed by an Add with a constant), Relu and Softmax
+// is supported, which maps directly onto Linear, ReLU and Softmax. Anything else
+// is reported as an error naming the node, instead of being skipped.
+//
+// ONNX files are protobuf. Rather than generating code from onnx.proto, the few
+// messages and fields used here are declared by hand below. Protobuf skips the
+// fields a message doesn't declare, so the rest of the file is ignored.
+//
+// ONNX models have a symbolic batch dimension, but every tensor in a GraphOperator
+// graph has fixed dimensions, and the bias has the dimensions of the output.
+// The input has to be given when importing, and the biases are broadcast to its
+// number of rows.
+
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ModelProto {
+        #[prost(message, optional, tag = "7")]
+        pub graph: Option<GraphProto>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GraphProto {
+        #[prost(message, repeated, tag = "1")]
+        pub node: Vec<NodeProto>,
+        #[prost(string, tag = "2")]
+        pub name: String,
+        #[prost(message, repeated, tag = "5")]
+        pub initializer: Vec<TensorProto>,
+        #[prost(message, repeated, tag = "11")]
+        pub input: Vec<ValueInfoProto>,
+        #[prost(message, repeated, tag = "12")]
+        pub output: Vec<ValueInfoProto>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct NodeProto {
+        #[prost(string, repeated, tag = "1")]
+        pub input: Vec<String>,
+        #[prost(string, repeated, tag = "2")]
+        pub output: Vec<String>,
+        #[prost(string, tag = "3")]
+        pub name: String,
+        #[prost(string, tag = "4")]
+        pub op_type: String,
+        #[prost(message, repeated, tag = "5")]
+        pub attribute: Vec<AttributeProto>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AttributeProto {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(float, tag = "2")]
+        pub f: f32,
+        #[prost(int64, tag = "3")]
+        pub i: i64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TensorProto {
+        #[prost(int64, repeated, tag = "1")]
+        pub dims: Vec<i64>,
+        #[prost(int32, tag = "2")]
+        pub data_type: i32,
+        #[prost(float, repeated, tag = "4")]
+        pub float_data: Vec<f32>,
+        #[prost(string, tag = "8")]
+        pub name: String,
+        #[prost(bytes = "vec", tag = "9")]
+        pub raw_data: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ValueInfoProto {
+        #[prost(string, tag = "1")]
+        pub name: String,
+    }
+}
+
+use proto::{AttributeProto, GraphProto, ModelProto, NodeProto, TensorProto};
+
+// TensorProto.DataType.FLOAT
+const ONNX_FLOAT: i32 = 1;
+
+#[derive(Debug)]
+pub enum OnnxImportError {
+    Io(std::io::Error),
+    Decode(prost::DecodeError),
+    MissingGraph,
+    UnsupportedOperator {
+        node: String,
+        op_type: String,
+    },
+    // An Add which isn't the bias of the MatMul right before it
+    MisplacedAdd {
+        node: String,
+    },
+    UnsupportedAttribute {
+        node: String,
+        attribute: String,
+        value: String,
+    },
+    UnsupportedDataType {
+        tensor: String,
+        data_type: i32,
+    },
+    // The weights and biases have to be initializers, not computed by the graph
+    MissingInitializer {
+        node: String,
+        tensor: String,
+    },
+    // Every node has to consume the output of the node before it
+    NotAChain {
+        node: String,
+        expected_input: String,
+    },
+    ShapeMismatch {
+        node: String,
+        message: String,
+    },
+    // The chain was imported, but the resulting graph is invalid
+    InvalidGraph(GraphError),
+}
+
+impl fmt::Display for OnnxImportError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OnnxImportError::Io(error) => write!(formatter, "failed to read the ONNX file: {}", error),
+            OnnxImportError::Decode(error) => {
+                write!(formatter, "failed to decode the ONNX protobuf: {}", error)
+            }
+            OnnxImportError::MissingGraph => write!(formatter, "the ONNX model has no graph"),
+            OnnxImportError::UnsupportedOperator { node, op_type } => write!(
+                formatter,
+                "node {} uses the unsupported operator {}, only Gemm, MatMul, Add, Relu and Softmax are supported",
+                node, op_type
+            ),
+            OnnxImportError::MisplacedAdd { node } => write!(
+                formatter,
+                "node {} is an Add, which is only supported as the bias of a preceding MatMul",
+                node
+            ),
+            OnnxImportError::UnsupportedAttribute {
+                node,
+                attribute,
+                value,
+            } => write!(
+                formatter,
+                "node {} has the unsupported attribute value {} = {}",
+                node, attribute, value
+            ),
+            OnnxImportError::UnsupportedDataType { tensor, data_type } => write!(
+                formatter,
+                "tensor {} has the unsupported data type {}, only FLOAT ({}) is supported",
+                tensor, data_type, ONNX_FLOAT
+            ),
+            OnnxImportError::MissingInitializer { node, tensor } => write!(
+                formatter,
+                "node {} reads {}, which has to be an initializer",
+                node, tensor
+            ),
+            OnnxImportError::NotAChain {
+                node,
+                expected_input,
+            } => write!(
+                formatter,
+                "node {} doesn't read {}, only graphs where every node consumes the output of the node before it are supported",
+                node, expected_input
+            ),
+            OnnxImportError::ShapeMismatch { node, message } => {
+                write!(formatter, "node {} has mismatched shapes: {}", node, message)
+            }
+            OnnxImportError::InvalidGraph(error) => {
+                write!(formatter, "the imported graph is invalid: {}", error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OnnxImportError {}
+
+impl From<std::io::Error> for OnnxImportError {
+    fn from(error: std::io::Error) -> Self {
+        OnnxImportError::Io(error)
+    }
+}
+
+impl From<GraphError> for OnnxImportError {
+    fn from(error: GraphError) -> Self {
+        OnnxImportError::InvalidGraph(error)
+    }
+}
+
+impl From<prost::DecodeError> for OnnxImportError {
+    fn from(error: prost::DecodeError) -> Self {
+        OnnxImportError::Decode(error)
+    }
+}
+
+// Node names are optional in ONNX, fall back to the name of the output
+fn node_name(node: &NodeProto) -> String {
+    if !node.name.is_empty() {
+        node.name.clone()
+    } else if let Some(output) = node.output.first() {
+        output.clone()
+    } else {
+        node.op_type.clone()
+    }
+}
+
+fn find_attribute<'a>(node: &'a NodeProto, name: &str) -> Option<&'a AttributeProto> {
+    node.attribute
+        .iter()
+        .find(|attribute| attribute.name == name)
+}
+
+fn int_attribute(node: &NodeProto, name: &str, default: i64) -> i64 {
+    find_attribute(node, name).map_or(default, |attribute| attribute.i)
+}
+
+fn float_attribute(node: &NodeProto, name: &str, default: f32) -> f32 {
+    find_attribute(node, name).map_or(default, |attribute| attribute.f)
+}
+
+fn tensor_data(tensor: &TensorProto) -> Result<Vec<f32>, OnnxImportError> {
+    if tensor.data_type != ONNX_FLOAT {
+        return Err(OnnxImportError::UnsupportedDataType {
+            tensor: tensor.name.clone(),
+            data_type: tensor.data_type,
+        });
+    }
+
+    if !tensor.float_data.is_empty() {
+        Ok(tensor.float_data.clone())
+    } else {
+        Ok(tensor
+            .raw_data
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect())
+    }
+}
+
+fn initializer<'a>(
+    graph: &'a GraphProto,
+    node: &NodeProto,
+    name: &str,
+) -> Result<&'a TensorProto, OnnxImportError> {
+    graph
+        .initializer
+        .iter()
+        .find(|tensor| tensor.name == name)
+        .ok_or_else(|| OnnxImportError::MissingInitializer {
+            node: node_name(node),
+            tensor: name.to_string(),
+        })
+}
+
+// The dims of an initializer, which can't be negative
+fn dimensions(
+    node: &NodeProto,
+    name: &str,
+    tensor: &TensorProto,
+) -> Result<Vec<usize>, OnnxImportError> {
+    tensor
+        .dims
+        .iter()
+        .map(|dim| usize::try_from(*dim))
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|_| OnnxImportError::ShapeMismatch {
+            node: node_name(node),
+            message: format!("{} has the negative dims {:?}", name, tensor.dims),
+        })
+}
+
+// A 2D initializer as a Tensor2D, transposed if transpose is set
+fn matrix_initializer(
+    graph: &GraphProto,
+    node: &NodeProto,
+    name: &str,
+    transpose: bool,
+) -> Result<Tensor2D, OnnxImportError> {
+    let tensor: &TensorProto = initializer(graph, node, name)?;
+    let dims: Vec<usize> = dimensions(node, name, tensor)?;
+    if dims.len() != 2 {
+        return Err(OnnxImportError::ShapeMismatch {
+            node: node_name(node),
+            message: format!("{} has dims {:?}, expected 2 dimensions", name, tensor.dims),
+        });
+    }
+    let row_count: usize = dims[0];
+    let column_count: usize = dims[1];
+    let data: Vec<f32> = tensor_data(tensor)?;
+    if Some(data.len()) != row_count.checked_mul(column_count) {
+        return Err(OnnxImportError::ShapeMismatch {
+            node: node_name(node),
+            message: format!(
+                "{} has dims {:?}, but {} elements",
+                name,
+                tensor.dims,
+                data.len()
+            ),
+        });
+    }
+
+    let matrix: Tensor2D = Tensor2D {
+        data,
+        row_count,
+        column_count,
+    };
+    if !transpose {
+        return Ok(matrix);
+    }
+
+    let mut transposed: Tensor2D = Tensor2D::new(0.0, column_count, row_count);
+    for row_index in 0..row_count {
+        for column_index in 0..column_count {
+            transposed.data[column_index * row_count + row_index] =
+                matrix.data[row_index * column_count + column_index];
+        }
+    }
+    Ok(transposed)
+}
+
+// Broadcasts a bias of dims [N], [1, N] or [row_count, N] to row_count x N
+fn bias_initializer(
+    graph: &GraphProto,
+    node: &NodeProto,
+    name: &str,
+    row_count: usize,
+    column_count: usize,
+) -> Result<Tensor2D, OnnxImportError> {
+    let tensor: &TensorProto = initializer(graph, node, name)?;
+    let dims: Vec<usize> = dimensions(node, name, tensor)?;
+    let data: Vec<f32> = tensor_data(tensor)?;
+
+    let mut bias: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+    let broadcast: bool = dims == [column_count] || dims == [1, column_count];
+    if broadcast && data.len() == column_count {
+        for row_index in 0..row_count {
+            bias.data[row_index * column_count..(row_index + 1) * column_count]
+                .copy_from_slice(&data);
+        }
+    } else if dims == [row_count, column_count] && data.len() == row_count * column_count {
+        bias.data.copy_from_slice(&data);
+    } else {
+        return Err(OnnxImportError::ShapeMismatch {
+            node: node_name(node),
+            message: format!(
+                "the bias {} has dims {:?} and {} elements, expected [{}], [1, {}] or [{}, {}]",
+                name,
+                dims,
+                data.len(),
+                column_count,
+                column_count,
+                row_count,
+                column_count
+            ),
+        });
+    }
+
+    Ok(bias)
+}
+
+fn check_chain(node: &NodeProto, current: &str) -> Result<(), OnnxImportError> {
+    if node.input.first().map(String::as_str) != Some(current) {
+        return Err(OnnxImportError::NotAChain {
+            node: node_name(node),
+            expected_input: current.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn check_weights(
+    node: &NodeProto,
+    weights: &Tensor2D,
+    column_count: usize,
+) -> Result<(), OnnxImportError> {
+    if weights.row_count != column_count {
+        return Err(OnnxImportError::ShapeMismatch {
+            node: node_name(node),
+            message: format!(
+                "the input has {} columns, but the weights are {}x{}",
+                column_count, weights.row_count, weights.column_count
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn output_name(node: &NodeProto) -> String {
+    node.output.first().cloned().unwrap_or_default()
+}
+
+pub fn import_onnx_bytes(
+    bytes: &[u8],
+    input: Tensor2D,
+) -> Result<Vec<GraphOperator>, OnnxImportError> {
+    let model: ModelProto = ModelProto::decode(bytes)?;
+    let graph: GraphProto = model.graph.ok_or(OnnxImportError::MissingGraph)?;
+
+    // Older exporters also list the initializers as inputs of the graph
+    let input_name: String = graph
+        .input
+        .iter()
+        .map(|value_info| value_info.name.clone())
+        .find(|name| graph.initializer.iter().all(|tensor| tensor.name != *name))
+        .unwrap_or_default();
+
+    let row_count: usize = input.row_count;
+    let mut column_count: usize = input.column_count;
+    let mut current: String = input_name;
+    let mut graph_operators: Vec<GraphOperator> = vec![GraphOperator::HostToDevice { input }];
+
+    let mut node_index: usize = 0;
+    while node_index < graph.node.len() {
+        let node: &NodeProto = &graph.node[node_index];
+        check_chain(node, &current)?;
+
+        match node.op_type.as_str() {
+            "Gemm" => {
+                let transpose_a: i64 = int_attribute(node, "transA", 0);
+                if transpose_a != 0 {
+                    return Err(OnnxImportError::UnsupportedAttribute {
+                        node: node_name(node),
+                        attribute: "transA".to_string(),
+                        value: transpose_a.to_string(),
+                    });
+                }
+                let transpose_b: bool = int_attribute(node, "transB", 0) != 0;
+                let alpha: f32 = float_attribute(node, "alpha", 1.0);
+                let beta: f32 = float_attribute(node, "beta", 1.0);
+
+                let b_name: &str = node.input.get(1).map_or("", String::as_str);
+                let mut weights: Tensor2D = matrix_initializer(&graph, node, b_name, transpose_b)?;
+                check_weights(node, &weights, column_count)?;
+                column_count = weights.column_count;
+
+                // C is optional
+                let mut bias: Tensor2D = match node.input.get(2) {
+                    Some(c_name) if !c_name.is_empty() => {
+                        bias_initializer(&graph, node, c_name, row_count, column_count)?
+                    }
+                    _ => Tensor2D::new(0.0, row_count, column_count),
+                };
+
+                // Y = alpha * A * B + beta * C
+                for element in &mut weights.data {
+                    *element *= alpha;
+                }
+                for element in &mut bias.data {
+                    *element *= beta;
+                }
+
+                graph_operators.push(GraphOperator::Linear { weights, bias });
+                current = output_name(node);
+            }
+            "MatMul" => {
+                let b_name: &str = node.input.get(1).map_or("", String::as_str);
+                let weights: Tensor2D = matrix_initializer(&graph, node, b_name, false)?;
+                check_weights(node, &weights, column_count)?;
+                column_count = weights.column_count;
+                current = output_name(node);
+
+                // An Add of a constant right after the MatMul is its bias
+                let mut bias: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+                if let Some(add) = graph.node.get(node_index + 1) {
+                    if add.op_type == "Add" && add.input.len() == 2 && add.input.contains(&current)
+                    {
+                        let bias_name: &String = add
+                            .input
+                            .iter()
+                            .find(|name| **name != current)
+                            .unwrap_or(&current);
+                        bias = bias_initializer(&graph, add, bias_name, row_count, column_count)?;
+                        current = output_name(add);
+                        node_index += 1;
+                    }
+                }
+
+                graph_operators.push(GraphOperator::Linear { weights, bias });
+            }
+            "Relu" => {
+                graph_operators.push(GraphOperator::ReLU);
+                current = output_name(node);
+            }
+            "Softmax" => {
+                // Opset 13 changed the default axis from 1 to -1, for 2D tensors they are the same
+                let axis: i64 = int_attribute(node, "axis", -1);
+                if axis != -1 && axis != 1 {
+                    return Err(OnnxImportError::UnsupportedAttribute {
+                        node: node_name(node),
+                        attribute: "axis".to_string(),
+                        value: axis.to_string(),
+                    });
+                }
+                graph_operators.push(GraphOperator::Softmax);
+                current = output_name(node);
+            }
+            // An Add right after a MatMul was already consumed as its bias
+            "Add" => {
+                return Err(OnnxImportError::MisplacedAdd {
+                    node: node_name(node),
+                })
+            }
+            op_type => {
+                return Err(OnnxImportError::UnsupportedOperator {
+                    node: node_name(node),
+                    op_type: op_type.to_string(),
+                })
+            }
+        }
+
+        node_index += 1;
+    }
+
+    if let Some(output) = graph.output.first() {
+        if output.name != current {
+            return Err(OnnxImportError::NotAChain {
+                node: output.name.clone(),
+                expected_input: current,
+            });
+        }
+    }
+
+    graph_operators.push(GraphOperator::DeviceToHost);
+
+    validate_graph_operators(&graph_operators)?;
+
+    Ok(graph_operators)
+}
+
+// input is the input of the graph, which also decides the number of rows of every tensor
+pub fn import_onnx(path: &Path, input: Tensor2D) -> Result<Vec<GraphOperator>, OnnxImportError> {
+    let bytes: Vec<u8> = fs::read(path)?;
+    import_onnx_bytes(&bytes, input)
+}
@@ -0,0 +1,189 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        graph::{
+            graph_runner::GraphRunner,
+            onnx::{import_onnx, import_onnx_bytes, OnnxImportError},
+        },
+        shared::{graph_operators::GraphOperator, tensor2d::Tensor2D},
+    };
+
+    // The fixtures are written by fixtures/onnx/generate_fixtures.py
+    fn fixture_path(file_name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures")
+            .join("onnx")
+            .join(file_name)
+    }
+
+    // Must match fixture_value in generate_fixtures.py, which computes in f64
+    fn fixture_value(seed: usize, index: usize) -> f32 {
+        ((((seed * 31 + index * 7) % 13) as f64 - 6.0) * 0.05) as f32
+    }
+
+    fn fixture_tensor(seed: usize, row_count: usize, column_count: usize) -> Tensor2D {
+        let mut tensor: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+        for (index, element) in tensor.data.iter_mut().enumerate() {
+            *element = fixture_value(seed, index);
+        }
+        tensor
+    }
+
+    // The weights as stored by PyTorch, output features x input features
+    fn fixture_tensor_transposed(seed: usize, row_count: usize, column_count: usize) -> Tensor2D {
+        let stored: Tensor2D = fixture_tensor(seed, column_count, row_count);
+        let mut tensor: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+        for row_index in 0..row_count {
+            for column_index in 0..column_count {
+                tensor.data[row_index * column_count + column_index] =
+                    stored.data[column_index * row_count + row_index];
+            }
+        }
+        tensor
+    }
+
+    // A bias vector repeated for every row
+    fn fixture_bias(seed: usize, row_count: usize, column_count: usize) -> Tensor2D {
+        let vector: Tensor2D = fixture_tensor(seed, 1, column_count);
+        let mut tensor: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+        for row_index in 0..row_count {
+            tensor.data[row_index * column_count..(row_index + 1) * column_count]
+                .copy_from_slice(&vector.data);
+        }
+        tensor
+    }
+
+    fn input() -> Tensor2D {
+        let mut input: Tensor2D = Tensor2D::new(0.1, 2, 4);
+        for element in &mut input.data {
+            *element -= 0.3;
+        }
+        input
+    }
+
+    fn assert_same_graph(expected: &[GraphOperator], found: &[GraphOperator]) {
+        assert_eq!(format!("{:?}", expected), format!("{:?}", found));
+
+        let expected: Tensor2D = GraphRunner::new(&expected.to_vec(), false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        let found: Tensor2D = GraphRunner::new(&found.to_vec(), false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(expected.data, found.data);
+    }
+
+    #[test]
+    fn gemm() {
+        let imported: Vec<GraphOperator> =
+            import_onnx(&fixture_path("mlp_gemm.onnx"), input()).unwrap();
+
+        // The second Gemm has alpha = 0.5 and beta = 2.0
+        let mut weights: Tensor2D = fixture_tensor_transposed(3, 8, 3);
+        for element in &mut weights.data {
+            *element *= 0.5;
+        }
+        let mut bias: Tensor2D = fixture_bias(4, 2, 3);
+        for element in &mut bias.data {
+            *element *= 2.0;
+        }
+
+        let expected: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice { input: input() },
+            GraphOperator::Linear {
+                weights: fixture_tensor_transposed(1, 4, 8),
+                bias: fixture_bias(2, 2, 8),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Linear { weights, bias },
+            GraphOperator::Softmax,
+            GraphOperator::DeviceToHost,
+        ];
+        assert_same_graph(&expected, &imported);
+    }
+
+    #[test]
+    fn matmul_add() {
+        let imported: Vec<GraphOperator> =
+            import_onnx(&fixture_path("mlp_matmul.onnx"), input()).unwrap();
+
+        let expected: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice { input: input() },
+            GraphOperator::Linear {
+                weights: fixture_tensor(5, 4, 8),
+                bias: fixture_bias(6, 2, 8),
+            },
+            GraphOperator::ReLU,
+            // The second MatMul has no Add after it
+            GraphOperator::Linear {
+                weights: fixture_tensor(7, 8, 3),
+                bias: Tensor2D::new(0.0, 2, 3),
+            },
+            GraphOperator::Softmax,
+            GraphOperator::DeviceToHost,
+        ];
+        assert_same_graph(&expected, &imported);
+
+        // The number of rows comes from the input
+        let imported: Vec<GraphOperator> =
+            import_onnx(&fixture_path("mlp_matmul.onnx"), Tensor2D::new(0.1, 5, 4)).unwrap();
+        let output: Tensor2D = GraphRunner::new(&imported, false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(output.row_count, 5);
+        assert_eq!(output.column_count, 3);
+    }
+
+    #[test]
+    fn unsupported() {
+        let error: OnnxImportError =
+            import_onnx(&fixture_path("unsupported.onnx"), input()).unwrap_err();
+        match &error {
+            OnnxImportError::UnsupportedOperator { node, op_type } => {
+                assert_eq!(node, "sigmoid1");
+                assert_eq!(op_type, "Sigmoid");
+            }
+            _ => panic!("Expected UnsupportedOperator, got {:?}", error),
+        }
+        assert!(error.to_string().contains("Sigmoid"));
+
+        // The input has the wrong number of columns for the first Gemm
+        let error: OnnxImportError =
+            import_onnx(&fixture_path("mlp_gemm.onnx"), Tensor2D::new(0.1, 2, 5)).unwrap_err();
+        assert!(matches!(error, OnnxImportError::ShapeMismatch { .. }));
+
+        let error: OnnxImportError =
+            import_onnx(&fixture_path("misplaced_add.onnx"), input()).unwrap_err();
+        match &error {
+            OnnxImportError::MisplacedAdd { node } => assert_eq!(node, "add1"),
+            _ => panic!("Expected MisplacedAdd, got {:?}", error),
+        }
+        assert!(error.to_string().contains("MatMul"));
+
+        for file_name in ["negative_dims.onnx", "negative_bias_dims.onnx"] {
+            let error: OnnxImportError =
+                import_onnx(&fixture_path(file_name), input()).unwrap_err();
+            assert!(
+                matches!(error, OnnxImportError::ShapeMismatch { .. }),
+                "{}: {:?}",
+                file_name,
+                error
+            );
+            assert!(error.to_string().contains("negative"));
+        }
+
+        assert!(matches!(
+            import_onnx_bytes(&[0xFF, 0xFF, 0xFF], input()),
+            Err(OnnxImportError::Decode(_))
+        ));
+        assert!(matches!(
+            import_onnx(&fixture_path("missing.onnx"), input()),
+            Err(OnnxImportError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,236 @@
+use crate::shared::tensor2d::Tensor2D;
+
+use super::graph_error::GraphError;
+use super::graph_runner::GraphRunner;
+
+#[derive(Clone, Debug)]
+pub enum OptimizerAlgorithm {
+    Sgd {
+        learning_rate: f32,
+    },
+    Momentum {
+        learning_rate: f32,
+        momentum: f32,
+    },
+    Adam {
+        learning_rate: f32,
+        beta_1: f32,
+        beta_2: f32,
+        epsilon: f32,
+    },
+}
+
+// The optimizer keeps its state per parameter, which for a GraphRunner means
+// per weights and bias tensor of every linear operator, in the order given by
+// GraphRunner::update_linear_parameters. The state is allocated on the first step.
+// SGD needs no state, momentum keeps a velocity per parameter and Adam
+// keeps a running mean of the gradient and of the squared gradient.
+pub struct Optimizer {
+    algorithm: OptimizerAlgorithm,
+    step_count: usize,
+    first_moments: Vec<Tensor2D>,
+    second_moments: Vec<Tensor2D>,
+}
+
+impl Optimizer {
+    pub fn new(algorithm: OptimizerAlgorithm) -> Self {
+        Optimizer {
+            algorithm,
+            step_count: 0,
+            first_moments: Vec::<Tensor2D>::new(),
+            second_moments: Vec::<Tensor2D>::new(),
+        }
+    }
+
+    pub fn sgd(learning_rate: f32) -> Self {
+        Self::new(OptimizerAlgorithm::Sgd { learning_rate })
+    }
+
+    pub fn momentum(learning_rate: f32, momentum: f32) -> Self {
+        Self::new(OptimizerAlgorithm::Momentum {
+            learning_rate,
+            momentum,
+        })
+    }
+
+    // The default values from the Adam paper are beta_1: 0.9, beta_2: 0.999 and epsilon: 1e-8
+    pub fn adam(learning_rate: f32, beta_1: f32, beta_2: f32, epsilon: f32) -> Self {
+        Self::new(OptimizerAlgorithm::Adam {
+            learning_rate,
+            beta_1,
+            beta_2,
+            epsilon,
+        })
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.step_count
+    }
+
+    // Runs the graph, evaluates the loss on its output, backpropagates and
+    // updates every weights and bias tensor in the graph runner.
+    // loss has to return the value of the loss and the gradient of the loss
+    // with regards to the output of the graph. The returned value is
+    // the loss before the update.
+    pub fn step(
+        &mut self,
+        graph_runner: &mut GraphRunner,
+        loss: impl Fn(&Tensor2D) -> (f32, Tensor2D),
+    ) -> Result<f32, GraphError> {
+        let output: Tensor2D = graph_runner.run()?;
+        let (loss_value, output_gradient): (f32, Tensor2D) = loss(&output);
+        graph_runner.backward(&output_gradient)?;
+        self.update(graph_runner);
+        Ok(loss_value)
+    }
+
+    // The loss to give to step() for graphs which end in a loss operator, like
+    // MeanSquaredError or SoftmaxCrossEntropy. The output of the graph is then
+    // the loss itself, and the gradient of the loss with regards to itself is 1.0.
+    pub fn graph_loss(output: &Tensor2D) -> (f32, Tensor2D) {
+        assert_eq!(
+            output.len(),
+            1,
+            "Optimizer::graph_loss expected the graph to output a single loss value, found - rows: {} columns: {}.",
+            output.row_count,
+            output.column_count
+        );
+
+        let mut output_gradient: Tensor2D = Tensor2D::new(0.0, 1, 1);
+        output_gradient.data[0] = 1.0;
+        (output.data[0], output_gradient)
+    }
+
+    // Updates every weights and bias tensor in the graph runner with
+    // the gradients from the most recent backward pass.
+    pub fn update(&mut self, graph_runner: &mut GraphRunner) {
+        self.step_count += 1;
+        let step_count: i32 = self.step_count as i32;
+
+        let algorithm: OptimizerAlgorithm = self.algorithm.clone();
+        let first_moments: &mut Vec<Tensor2D> = &mut self.first_moments;
+        let second_moments: &mut Vec<Tensor2D> = &mut self.second_moments;
+
+        graph_runner.update_linear_parameters(
+            |parameter_index, parameter, gradient| match algorithm {
+                OptimizerAlgorithm::Sgd { learning_rate } => {
+                    Self::sgd_update(learning_rate, parameter, gradient);
+                }
+                OptimizerAlgorithm::Momentum {
+                    learning_rate,
+                    momentum,
+                } => {
+                    let velocity: &mut Tensor2D =
+                        Self::get_state(first_moments, parameter_index, parameter);
+                    Self::momentum_update(learning_rate, momentum, parameter, gradient, velocity);
+                }
+                OptimizerAlgorithm::Adam {
+                    learning_rate,
+                    beta_1,
+                    beta_2,
+                    epsilon,
+                } => {
+                    let first_moment: &mut Tensor2D =
+                        Self::get_state(first_moments, parameter_index, parameter);
+                    let second_moment: &mut Tensor2D =
+                        Self::get_state(second_moments, parameter_index, parameter);
+                    Self::adam_update(
+                        learning_rate,
+                        beta_1,
+                        beta_2,
+                        epsilon,
+                        step_count,
+                        parameter,
+                        gradient,
+                        first_moment,
+                        second_moment,
+                    );
+                }
+            },
+        );
+    }
+
+    // State buffers are created the first time we see a parameter.
+    // Parameters are always visited in the same order, so this only
+    // happens during the first step.
+    fn get_state<'a>(
+        states: &'a mut Vec<Tensor2D>,
+        parameter_index: usize,
+        parameter: &Tensor2D,
+    ) -> &'a mut Tensor2D {
+        if states.len() <= parameter_index {
+            states.push(Tensor2D::new(
+                0.0,
+                parameter.row_count,
+                parameter.column_count,
+            ));
+        }
+
+        let state: &mut Tensor2D = &mut states[parameter_index];
+        assert_eq!(
+            state.len(),
+            parameter.len(),
+            "The parameters given to the optimizer changed shape between steps!"
+        );
+        state
+    }
+
+    pub fn sgd_update(learning_rate: f32, parameter: &mut Tensor2D, gradient: &Tensor2D) {
+        debug_assert_eq!(parameter.len(), gradient.len());
+
+        for index in 0..parameter.len() {
+            parameter.data[index] -= learning_rate * gradient.data[index];
+        }
+    }
+
+    pub fn momentum_update(
+        learning_rate: f32,
+        momentum: f32,
+        parameter: &mut Tensor2D,
+        gradient: &Tensor2D,
+        velocity: &mut Tensor2D,
+    ) {
+        debug_assert_eq!(parameter.len(), gradient.len());
+        debug_assert_eq!(parameter.len(), velocity.len());
+
+        for index in 0..parameter.len() {
+            velocity.data[index] = momentum * velocity.data[index] + gradient.data[index];
+            parameter.data[index] -= learning_rate * velocity.data[index];
+        }
+    }
+
+    pub fn adam_update(
+        learning_rate: f32,
+        beta_1: f32,
+        beta_2: f32,
+        epsilon: f32,
+        step_count: i32,
+        parameter: &mut Tensor2D,
+        gradient: &Tensor2D,
+        first_moment: &mut Tensor2D,
+        second_moment: &mut Tensor2D,
+    ) {
+        debug_assert_eq!(parameter.len(), gradient.len());
+        debug_assert_eq!(parameter.len(), first_moment.len());
+        debug_assert_eq!(parameter.len(), second_moment.len());
+
+        // The moments start out at zero, which biases them towards zero
+        // for the first steps. These corrections counteract that.
+        let first_moment_correction: f32 = 1.0 - beta_1.powi(step_count);
+        let second_moment_correction: f32 = 1.0 - beta_2.powi(step_count);
+
+        for index in 0..parameter.len() {
+            let gradient_value: f32 = gradient.data[index];
+            first_moment.data[index] =
+                beta_1 * first_moment.data[index] + (1.0 - beta_1) * gradient_value;
+            second_moment.data[index] = beta_2 * second_moment.data[index]
+                + (1.0 - beta_2) * gradient_value * gradient_value;
+
+            let first_moment_corrected: f32 = first_moment.data[index] / first_moment_correction;
+            let second_moment_corrected: f32 = second_moment.data[index] / second_moment_correction;
+
+            parameter.data[index] -=
+                learning_rate * first_moment_corrected / (second_moment_corrected.sqrt() + epsilon);
+        }
+    }
+}
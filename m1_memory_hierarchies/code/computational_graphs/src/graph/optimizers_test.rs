@@ -0,0 +1,235 @@
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        graph::{graph_error::GraphError, graph_runner::GraphRunner, optimizers::Optimizer},
+        shared::{
+            graph_operators::GraphOperator, tensor2d::Tensor2D,
+            tensor2d_test_utilities::random_tensor,
+        },
+    };
+
+    const ERROR_TOLERANCE: f32 = 0.00001;
+    const FITTED_LOSS_TOLERANCE: f32 = 0.001;
+
+    fn mean_squared_error(output: &Tensor2D, target: &Tensor2D) -> (f32, Tensor2D) {
+        let element_count: f32 = output.len() as f32;
+        let mut loss: f32 = 0.0;
+        let mut gradient: Tensor2D = Tensor2D::new(0.0, output.row_count, output.column_count);
+        for index in 0..output.len() {
+            let difference: f32 = output.data[index] - target.data[index];
+            loss += difference * difference / element_count;
+            gradient.data[index] = 2.0 * difference / element_count;
+        }
+        (loss, gradient)
+    }
+
+    // A single linear layer which has to learn the weights and bias
+    // used to generate the target.
+    fn single_layer_problem(rng: &mut ChaCha8Rng) -> (Vec<GraphOperator>, Tensor2D) {
+        let row_count: usize = 8;
+        let input_size: usize = 3;
+        let output_size: usize = 2;
+
+        let input: Tensor2D = random_tensor(rng, row_count, input_size);
+        let true_weights: Tensor2D = random_tensor(rng, input_size, output_size);
+        let true_bias: Tensor2D = random_tensor(rng, row_count, output_size);
+        let target: Tensor2D = Tensor2D::linear(&input, &true_weights, &true_bias);
+
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice { input },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(0.0, input_size, output_size),
+                bias: Tensor2D::new(0.0, row_count, output_size),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+
+        (graph_operators, target)
+    }
+
+    fn fit_single_layer(mut optimizer: Optimizer, step_count: usize) {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(5);
+        let (mut graph_operators, target) = single_layer_problem(&mut rng);
+
+        let fuse_operators: bool = false;
+        let parallel: bool = false;
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+
+        let first_loss: f32 = optimizer
+            .step(&mut graph_runner, |output| {
+                mean_squared_error(output, &target)
+            })
+            .unwrap();
+        for _ in 1..step_count {
+            optimizer
+                .step(&mut graph_runner, |output| {
+                    mean_squared_error(output, &target)
+                })
+                .unwrap();
+        }
+        assert_eq!(optimizer.step_count(), step_count);
+
+        let (last_loss, _) = mean_squared_error(&graph_runner.run().unwrap(), &target);
+        assert!(
+            last_loss < FITTED_LOSS_TOLERANCE,
+            "Failed to fit a single linear layer. First loss: {} last loss: {}",
+            first_loss,
+            last_loss
+        );
+
+        // The trained parameters have to survive being written back into the graph
+        graph_runner
+            .store_linear_parameters(&mut graph_operators)
+            .unwrap();
+        let mut stored_runner: GraphRunner =
+            GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+        let difference: Tensor2D =
+            Tensor2D::subtraction(&graph_runner.run().unwrap(), &stored_runner.run().unwrap());
+        assert!(difference.data.iter().map(|x| x.abs()).sum::<f32>() < ERROR_TOLERANCE);
+    }
+
+    #[test]
+    fn sgd_step() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(6);
+        let (graph_operators, target) = single_layer_problem(&mut rng);
+
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(&graph_operators, false, false).unwrap();
+        let output: Tensor2D = graph_runner.run().unwrap();
+        let (_, output_gradient) = mean_squared_error(&output, &target);
+        graph_runner.backward(&output_gradient).unwrap();
+        let expected: Vec<(Tensor2D, Tensor2D)> = graph_runner
+            .linear_gradients()
+            .iter()
+            .map(|(weights_gradient, bias_gradient)| {
+                let mut weights: Tensor2D = (*weights_gradient).clone();
+                let mut bias: Tensor2D = (*bias_gradient).clone();
+                // All parameters start out as zero
+                weights.data.iter_mut().for_each(|x| *x *= -0.5);
+                bias.data.iter_mut().for_each(|x| *x *= -0.5);
+                (weights, bias)
+            })
+            .collect();
+
+        let mut optimizer: Optimizer = Optimizer::sgd(0.5);
+        optimizer.update(&mut graph_runner);
+
+        let mut updated_operators: Vec<GraphOperator> = graph_operators.clone();
+        graph_runner
+            .store_linear_parameters(&mut updated_operators)
+            .unwrap();
+        if let GraphOperator::Linear { weights, bias } = &updated_operators[1] {
+            let weights_difference: Tensor2D = Tensor2D::subtraction(weights, &expected[0].0);
+            let bias_difference: Tensor2D = Tensor2D::subtraction(bias, &expected[0].1);
+            assert!(weights_difference.data.iter().map(|x| x.abs()).sum::<f32>() < ERROR_TOLERANCE);
+            assert!(bias_difference.data.iter().map(|x| x.abs()).sum::<f32>() < ERROR_TOLERANCE);
+        } else {
+            panic!("The linear operator was not stored back in place!");
+        }
+
+        // Only the HostToDevice, without the linear operator of the runner
+        assert_eq!(
+            graph_runner.store_linear_parameters(&mut updated_operators[0..1]),
+            Err(GraphError::ParameterCountMismatch {
+                expected: 1,
+                found: 0
+            })
+        );
+    }
+
+    #[test]
+    fn sgd() {
+        fit_single_layer(Optimizer::sgd(0.1), 2000);
+    }
+
+    #[test]
+    fn momentum() {
+        fit_single_layer(Optimizer::momentum(0.05, 0.9), 500);
+    }
+
+    #[test]
+    fn adam() {
+        fit_single_layer(Optimizer::adam(0.05, 0.9, 0.999, 1e-8), 1000);
+    }
+
+    #[test]
+    fn softmax_cross_entropy_graph() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(7);
+        let row_count: usize = 6;
+        let input_size: usize = 4;
+        let class_count: usize = 3;
+
+        let labels: Vec<usize> = (0..row_count).map(|row| row % class_count).collect();
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(&mut rng, row_count, input_size),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(&mut rng, input_size, class_count),
+                bias: Tensor2D::new(0.0, row_count, class_count),
+            },
+            GraphOperator::SoftmaxCrossEntropy { labels },
+            GraphOperator::DeviceToHost,
+        ];
+
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(&graph_operators, false, false).unwrap();
+        let mut optimizer: Optimizer = Optimizer::adam(0.05, 0.9, 0.999, 1e-8);
+        let first_loss: f32 = optimizer
+            .step(&mut graph_runner, Optimizer::graph_loss)
+            .unwrap();
+        for _ in 1..500 {
+            optimizer
+                .step(&mut graph_runner, Optimizer::graph_loss)
+                .unwrap();
+        }
+
+        // Every row has a bias of its own, so the classes can always be separated
+        let (last_loss, _) = Optimizer::graph_loss(&graph_runner.run().unwrap());
+        assert!(
+            last_loss < 0.05 && last_loss < first_loss,
+            "Failed to fit a softmax cross-entropy graph. First loss: {} last loss: {}",
+            first_loss,
+            last_loss
+        );
+    }
+
+    #[test]
+    fn mean_squared_error_graph() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(8);
+        let (reference_operators, target) = single_layer_problem(&mut rng);
+        let mut graph_operators: Vec<GraphOperator> = reference_operators.clone();
+        let last_index: usize = graph_operators.len() - 1;
+        graph_operators.insert(
+            last_index,
+            GraphOperator::MeanSquaredError {
+                target: target.clone(),
+            },
+        );
+
+        // The loss computed in the graph, and its gradients, have to match
+        // the loss computed outside of it
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(&graph_operators, false, false).unwrap();
+        let mut reference_runner: GraphRunner =
+            GraphRunner::new(&reference_operators, false, false).unwrap();
+        let mut reference: Optimizer = Optimizer::sgd(0.1);
+        let mut optimizer: Optimizer = Optimizer::sgd(0.1);
+        for _ in 0..10 {
+            let (expected, _) = mean_squared_error(&reference_runner.run().unwrap(), &target);
+            let found: f32 = optimizer
+                .step(&mut graph_runner, Optimizer::graph_loss)
+                .unwrap();
+            reference
+                .step(&mut reference_runner, |output| {
+                    mean_squared_error(output, &target)
+                })
+                .unwrap();
+            assert!((expected - found).abs() < ERROR_TOLERANCE);
+        }
+    }
+}
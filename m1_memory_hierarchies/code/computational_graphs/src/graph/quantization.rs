@@ -0,0 +1,246 @@
+use std::fmt;
+
+use crate::shared::{
+    graph_operators::GraphOperator,
+    quantized_tensor2d::{QuantizationGranularity, QuantizationParameters, QuantizedTensor2D},
+    tensor2d::Tensor2D,
+};
+
+use super::{
+    graph_error::GraphError,
+    graph_runner::{CPUBackend, GraphRunner},
+    graph_validation::validate_graph_operators,
+};
+
+// Post-training quantization of the linear operators in a graph.
+// The weights are quantized once, either per tensor or per channel (column).
+// The input to each linear operator changes every time the graph is run, so instead
+// we run the graph in f32 on a set of calibration inputs, record the smallest and
+// largest value seen in the input to each linear operator and pick the quantization
+// parameters for the input from that range. This is called static quantization,
+// the alternative is computing the input parameters every time the graph is run.
+
+// Returns the smallest and largest value seen in the input to each operator,
+// across all of the calibration inputs. If there are no calibration inputs,
+// the input already in the HostToDevice operator is used.
+pub fn calibrate_input_ranges(
+    graph_operators: &[GraphOperator],
+    calibration_inputs: &[Tensor2D],
+) -> Vec<(f32, f32)> {
+    let graph_input: &Tensor2D = match &graph_operators[0] {
+        GraphOperator::HostToDevice { input } => input,
+        _ => panic!(
+            "quantization::calibrate_input_ranges expected the graph to begin with HostToDevice!"
+        ),
+    };
+
+    let default_inputs: [Tensor2D; 1] = [graph_input.clone()];
+    let calibration_inputs: &[Tensor2D] = if calibration_inputs.is_empty() {
+        &default_inputs
+    } else {
+        calibration_inputs
+    };
+
+    let mut graph_runner: GraphRunner =
+        GraphRunner::with_backend(CPUBackend::default(), &(), graph_operators, false)
+            .unwrap_or_else(|error| {
+                panic!(
+                    "Invalid graph sent to quantization::calibrate_input_ranges! {}",
+                    error
+                )
+            });
+
+    let mut ranges: Vec<(f32, f32)> =
+        vec![(f32::INFINITY, f32::NEG_INFINITY); graph_operators.len()];
+    for calibration_input in calibration_inputs {
+        assert!(
+            calibration_input.row_count == graph_input.row_count
+                && calibration_input.column_count == graph_input.column_count,
+            "\nMismatch - calibration input & graph input\ncalibration input - rows: {} columns: {}.\n graph input - rows: {} columns: {}.",
+            calibration_input.row_count,
+            calibration_input.column_count,
+            graph_input.row_count,
+            graph_input.column_count
+        );
+
+        let result: Result<Tensor2D, GraphError> = graph_runner
+            .set_input_batch(calibration_input)
+            .and_then(|_| graph_runner.run());
+        if let Err(error) = result {
+            panic!(
+                "quantization::calibrate_input_ranges failed to run the graph! {}",
+                error
+            );
+        }
+
+        // Without fusion, the runner keeps the operators of the graph as they are
+        for ((min, max), input_index) in ranges
+            .iter_mut()
+            .zip(graph_runner.operator_input_indices.iter())
+        {
+            let input: &Tensor2D = &graph_runner.data_buffers[*input_index];
+            for value in &input.data[0..input.len()] {
+                *min = min.min(*value);
+                *max = max.max(*value);
+            }
+        }
+    }
+
+    ranges
+}
+
+// Rewrites every Linear and LinearReLUFused operator into LinearInt8 and
+// LinearReLUInt8Fused. LinearReLUSoftmaxFused is left in f32, the softmax would
+// have to dequantize anyway. Every other operator is copied as is.
+pub fn quantize_graph_operators(
+    graph_operators: &[GraphOperator],
+    calibration_inputs: &[Tensor2D],
+    granularity: QuantizationGranularity,
+) -> Vec<GraphOperator> {
+    if let Err(error) = validate_graph_operators(graph_operators) {
+        panic!(
+            "Invalid graph sent to quantization::quantize_graph_operators! {}",
+            error
+        );
+    }
+
+    let input_ranges: Vec<(f32, f32)> = calibrate_input_ranges(graph_operators, calibration_inputs);
+
+    graph_operators
+        .iter()
+        .zip(input_ranges.iter())
+        .map(|(operator, (min, max))| match operator {
+            GraphOperator::Linear { weights, bias } => GraphOperator::LinearInt8 {
+                weights: QuantizedTensor2D::calibrate(weights, granularity),
+                bias: bias.clone(),
+                input_parameters: QuantizationParameters::from_range(*min, *max),
+            },
+            GraphOperator::LinearReLUFused { weights, bias } => {
+                GraphOperator::LinearReLUInt8Fused {
+                    weights: QuantizedTensor2D::calibrate(weights, granularity),
+                    bias: bias.clone(),
+                    input_parameters: QuantizationParameters::from_range(*min, *max),
+                }
+            }
+            _ => operator.clone(),
+        })
+        .collect()
+}
+
+// The bytes needed for the weights of every linear operator, quantized or not
+pub fn linear_weights_size_in_bytes(graph_operators: &[GraphOperator]) -> usize {
+    graph_operators
+        .iter()
+        .map(|operator| match operator {
+            GraphOperator::Linear { weights, .. }
+            | GraphOperator::LinearReLUFused { weights, .. }
+            | GraphOperator::LinearReLUSoftmaxFused { weights, .. }
+            | GraphOperator::LinearAddFused { weights, .. }
+            | GraphOperator::LinearActivationFused { weights, .. } => {
+                weights.len() * std::mem::size_of::<f32>()
+            }
+            GraphOperator::LinearInt8 { weights, .. }
+            | GraphOperator::LinearReLUInt8Fused { weights, .. } => weights.size_in_bytes(),
+            _ => 0,
+        })
+        .sum()
+}
+
+// How much accuracy did quantization cost us, and how much memory did it save.
+// The weights are what has to be streamed from memory for every run of the graph,
+// which is where the memory bandwidth savings come from.
+#[derive(Clone, Debug, Default)]
+pub struct QuantizationReport {
+    pub quantized_operator_count: usize,
+    pub original_weights_bytes: usize,
+    pub quantized_weights_bytes: usize,
+    pub max_absolute_error: f32,
+    pub mean_absolute_error: f32,
+}
+
+impl QuantizationReport {
+    // Runs both graphs with GraphRunner and compares their outputs
+    pub fn new(original: &Vec<GraphOperator>, quantized: &Vec<GraphOperator>) -> Self {
+        let fuse_operators: bool = false;
+        let parallel: bool = false;
+        let expected: Tensor2D = GraphRunner::new(original, fuse_operators, parallel)
+            .and_then(|mut graph_runner| graph_runner.run())
+            .unwrap_or_else(|error| {
+                panic!(
+                    "QuantizationReport::new received an invalid original graph! {}",
+                    error
+                )
+            });
+        let found: Tensor2D = GraphRunner::new(quantized, fuse_operators, parallel)
+            .and_then(|mut graph_runner| graph_runner.run())
+            .unwrap_or_else(|error| {
+                panic!(
+                    "QuantizationReport::new received an invalid quantized graph! {}",
+                    error
+                )
+            });
+        assert_eq!(
+            expected.len(),
+            found.len(),
+            "\nQuantizationReport::new received graphs with different output sizes."
+        );
+
+        let mut max_absolute_error: f32 = 0.0;
+        let mut sum_absolute_error: f32 = 0.0;
+        for index in 0..expected.len() {
+            let error: f32 = (expected.data[index] - found.data[index]).abs();
+            max_absolute_error = max_absolute_error.max(error);
+            sum_absolute_error += error;
+        }
+
+        let quantized_operator_count: usize = quantized
+            .iter()
+            .filter(|operator| {
+                matches!(
+                    operator,
+                    GraphOperator::LinearInt8 { .. } | GraphOperator::LinearReLUInt8Fused { .. }
+                )
+            })
+            .count();
+
+        QuantizationReport {
+            quantized_operator_count,
+            original_weights_bytes: linear_weights_size_in_bytes(original),
+            quantized_weights_bytes: linear_weights_size_in_bytes(quantized),
+            max_absolute_error,
+            mean_absolute_error: sum_absolute_error / expected.len().max(1) as f32,
+        }
+    }
+
+    // The fraction of the weight memory saved, 0.75 when going from f32 to i8
+    // and ignoring the quantization parameters.
+    pub fn memory_savings(&self) -> f32 {
+        if self.original_weights_bytes == 0 {
+            return 0.0;
+        }
+
+        1.0 - self.quantized_weights_bytes as f32 / self.original_weights_bytes as f32
+    }
+}
+
+impl fmt::Display for QuantizationReport {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            formatter,
+            "Quantized linear operators: {}",
+            self.quantized_operator_count
+        )?;
+        writeln!(
+            formatter,
+            "Linear weights: {} bytes -> {} bytes ({:.1}% saved)",
+            self.original_weights_bytes,
+            self.quantized_weights_bytes,
+            100.0 * self.memory_savings()
+        )?;
+        write!(
+            formatter,
+            "Output error - max: {} mean: {}",
+            self.max_absolute_error, self.mean_absolute_error
+        )
+    }
+}
@@ -0,0 +1,267 @@
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        graph::{
+            graph_error::GraphError,
+            graph_runner::GraphRunner,
+            graph_validation::validate_graph_operators,
+            quantization::{calibrate_input_ranges, quantize_graph_operators, QuantizationReport},
+        },
+        shared::{
+            graph_operators::GraphOperator,
+            quantized_tensor2d::{
+                QuantizationGranularity, QuantizationParameters, QuantizedTensor2D,
+            },
+            tensor2d::Tensor2D,
+            tensor2d_test_utilities::random_tensor,
+        },
+    };
+
+    const QUANTIZATION_TOLERANCE: f32 = 0.05;
+
+    // A small multilayer perceptron with the weights scaled down
+    // so the intermediate values stay around -1.0 to 1.0.
+    fn mlp(rng: &mut ChaCha8Rng, row_count: usize) -> Vec<GraphOperator> {
+        let sizes: [usize; 4] = [16, 32, 32, 8];
+        let mut graph_operators: Vec<GraphOperator> = vec![GraphOperator::HostToDevice {
+            input: random_tensor(rng, row_count, sizes[0]),
+        }];
+        for layer_index in 0..(sizes.len() - 1) {
+            let mut weights: Tensor2D =
+                random_tensor(rng, sizes[layer_index], sizes[layer_index + 1]);
+            for element in &mut weights.data {
+                *element /= (sizes[layer_index] as f32).sqrt();
+            }
+            let bias: Tensor2D = random_tensor(rng, row_count, sizes[layer_index + 1]);
+
+            graph_operators.push(GraphOperator::Linear { weights, bias });
+            if layer_index < sizes.len() - 2 {
+                graph_operators.push(GraphOperator::ReLU);
+            }
+        }
+        graph_operators.push(GraphOperator::DeviceToHost);
+
+        graph_operators
+    }
+
+    fn calibration_inputs(rng: &mut ChaCha8Rng, row_count: usize) -> Vec<Tensor2D> {
+        (0..8).map(|_| random_tensor(rng, row_count, 16)).collect()
+    }
+
+    #[test]
+    fn calibration_ranges() {
+        let input: Tensor2D = Tensor2D {
+            data: vec![-2.0, 1.0, 0.5, 3.0],
+            row_count: 2,
+            column_count: 2,
+        };
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice { input },
+            GraphOperator::ReLU,
+            GraphOperator::Linear {
+                weights: Tensor2D::new(0.0, 2, 2),
+                bias: Tensor2D::new(0.0, 2, 2),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+
+        // With no calibration inputs, the graph's own input is used
+        let ranges: Vec<(f32, f32)> = calibrate_input_ranges(&graph_operators, &[]);
+        assert_eq!(ranges[1], (-2.0, 3.0));
+        assert_eq!(ranges[2], (0.0, 3.0));
+        assert_eq!(ranges[3], (0.0, 0.0));
+
+        let calibration_input: Tensor2D = Tensor2D {
+            data: vec![-1.0, 5.0, 0.0, 0.0],
+            row_count: 2,
+            column_count: 2,
+        };
+        let ranges: Vec<(f32, f32)> =
+            calibrate_input_ranges(&graph_operators, &[calibration_input]);
+        assert_eq!(ranges[1], (-1.0, 5.0));
+        assert_eq!(ranges[2], (0.0, 5.0));
+    }
+
+    #[test]
+    fn quantize_mlp() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(6);
+        let row_count: usize = 4;
+        let graph_operators: Vec<GraphOperator> = mlp(&mut rng, row_count);
+        let calibration_inputs: Vec<Tensor2D> = calibration_inputs(&mut rng, row_count);
+
+        for granularity in [
+            QuantizationGranularity::PerTensor,
+            QuantizationGranularity::PerChannel,
+        ] {
+            let quantized: Vec<GraphOperator> =
+                quantize_graph_operators(&graph_operators, &calibration_inputs, granularity);
+            assert_eq!(quantized.len(), graph_operators.len());
+            assert!(validate_graph_operators(&quantized).is_ok());
+            for operator in &quantized {
+                assert!(!matches!(operator, GraphOperator::Linear { .. }));
+                if let GraphOperator::LinearInt8 { weights, .. } = operator {
+                    assert_eq!(weights.granularity(), granularity);
+                }
+            }
+
+            let report: QuantizationReport = QuantizationReport::new(&graph_operators, &quantized);
+            assert_eq!(report.quantized_operator_count, 3);
+            assert!(
+                report.max_absolute_error < QUANTIZATION_TOLERANCE,
+                "Quantization error too large for {:?}: {:?}",
+                granularity,
+                report
+            );
+
+            // i8 is a quarter of f32, but the quantization parameters take up some space too,
+            // especially when quantizing per channel with this few rows in the weights.
+            assert!(0.6 < report.memory_savings() && report.memory_savings() < 0.75);
+        }
+    }
+
+    #[test]
+    fn quantize_fused() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(7);
+        let row_count: usize = 4;
+        let graph_operators: Vec<GraphOperator> = mlp(&mut rng, row_count);
+        let quantized: Vec<GraphOperator> =
+            quantize_graph_operators(&graph_operators, &[], QuantizationGranularity::PerChannel);
+
+        // Fusing LinearInt8 and ReLU in the runner should give the same result
+        let unfused: Tensor2D = GraphRunner::new(&quantized, false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        let fused: Tensor2D = GraphRunner::new(&quantized, true, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(unfused.data[0..unfused.len()], fused.data[0..fused.len()]);
+
+        // As should quantizing a graph which was fused by hand
+        let mut fused_operators: Vec<GraphOperator> = Vec::new();
+        let mut index: usize = 0;
+        while index < graph_operators.len() {
+            match (&graph_operators[index], graph_operators.get(index + 1)) {
+                (GraphOperator::Linear { weights, bias }, Some(GraphOperator::ReLU)) => {
+                    fused_operators.push(GraphOperator::LinearReLUFused {
+                        weights: weights.clone(),
+                        bias: bias.clone(),
+                    });
+                    index += 2;
+                }
+                (operator, _) => {
+                    fused_operators.push(operator.clone());
+                    index += 1;
+                }
+            }
+        }
+        let quantized_fused: Vec<GraphOperator> =
+            quantize_graph_operators(&fused_operators, &[], QuantizationGranularity::PerChannel);
+        assert_eq!(
+            quantized_fused
+                .iter()
+                .filter(|operator| matches!(operator, GraphOperator::LinearReLUInt8Fused { .. }))
+                .count(),
+            2
+        );
+        let output: Tensor2D = GraphRunner::new(&quantized_fused, false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(unfused.data[0..unfused.len()], output.data[0..output.len()]);
+    }
+
+    #[test]
+    fn per_channel_accuracy() {
+        // One output has tiny weights, the other has large weights. Quantized per tensor
+        // the tiny weights all get rounded to the zero point.
+        let input: Tensor2D = Tensor2D::new(0.1, 2, 4);
+        let weights: Tensor2D = Tensor2D {
+            data: vec![0.001, 10.0, -0.002, -20.0, 0.003, 30.0, -0.004, 40.0],
+            row_count: 4,
+            column_count: 2,
+        };
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice { input },
+            GraphOperator::Linear {
+                weights,
+                bias: Tensor2D::new(0.0, 2, 2),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+
+        let expected: Tensor2D = GraphRunner::new(&graph_operators, false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        let small_output_error = |granularity: QuantizationGranularity| -> f32 {
+            let quantized: Vec<GraphOperator> =
+                quantize_graph_operators(&graph_operators, &[], granularity);
+            let output: Tensor2D = GraphRunner::new(&quantized, false, false)
+                .unwrap()
+                .run()
+                .unwrap();
+            (output.data[0] - expected.data[0]).abs() / expected.data[0].abs()
+        };
+
+        assert!(small_output_error(QuantizationGranularity::PerChannel) < 0.05);
+        assert!(small_output_error(QuantizationGranularity::PerTensor) > 0.5);
+    }
+
+    #[test]
+    fn validation() {
+        let weights: Tensor2D = Tensor2D::new(0.1, 4, 3);
+        let graph_operators = |weights: QuantizedTensor2D, scale: f32| -> Vec<GraphOperator> {
+            vec![
+                GraphOperator::HostToDevice {
+                    input: Tensor2D::new(0.1, 2, 4),
+                },
+                GraphOperator::LinearInt8 {
+                    weights,
+                    bias: Tensor2D::new(0.0, 2, 3),
+                    input_parameters: QuantizationParameters {
+                        scale,
+                        zero_point: 0,
+                    },
+                },
+                GraphOperator::DeviceToHost,
+            ]
+        };
+
+        let per_tensor: QuantizedTensor2D =
+            QuantizedTensor2D::calibrate(&weights, QuantizationGranularity::PerTensor);
+        let per_channel: QuantizedTensor2D =
+            QuantizedTensor2D::calibrate(&weights, QuantizationGranularity::PerChannel);
+        assert!(validate_graph_operators(&graph_operators(per_tensor.clone(), 0.01)).is_ok());
+        assert!(validate_graph_operators(&graph_operators(per_channel.clone(), 0.01)).is_ok());
+        assert!(matches!(
+            validate_graph_operators(&graph_operators(per_tensor, 0.0)),
+            Err(GraphError::InvalidQuantization { node: 1, .. })
+        ));
+
+        let mut wrong_count: QuantizedTensor2D = per_channel;
+        wrong_count.parameters.pop();
+        assert!(matches!(
+            validate_graph_operators(&graph_operators(wrong_count, 0.01)),
+            Err(GraphError::InvalidQuantization { node: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn backward_not_supported() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(8);
+        let graph_operators: Vec<GraphOperator> = mlp(&mut rng, 2);
+        let quantized: Vec<GraphOperator> =
+            quantize_graph_operators(&graph_operators, &[], QuantizationGranularity::PerTensor);
+        let mut graph_runner: GraphRunner = GraphRunner::new(&quantized, false, false).unwrap();
+        let output: Tensor2D = graph_runner.run().unwrap();
+        assert!(matches!(
+            graph_runner.backward(&Tensor2D::new(1.0, output.row_count, output.column_count)),
+            Err(GraphError::NotDifferentiable { .. })
+        ));
+    }
+}
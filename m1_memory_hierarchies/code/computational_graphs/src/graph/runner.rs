@@ -1,3 +1,8 @@
+use std::collections::HashMap;
+use std::fs;
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
 use crate::shared::graph_operators::GraphOperator::*;
 use crate::{
     graph::graph_runner::GraphRunner,
@@ -8,13 +13,21 @@ use crate::{
         gpu_utilities::GPUHandles,
         graph_operators::GraphOperator,
         performance_measurement::{
-            benchmark_function_vector_gpu_graph, GraphFunction, PerformanceMeasurements,
+            benchmark_function_vector_gpu_graph, benchmark_graph, GraphFunction,
+            PerformanceMeasurements,
         },
+        quantized_tensor2d::{QuantizationGranularity, QuantizedTensor2D},
         tensor2d::Tensor2D,
     },
 };
 
-use super::{graph_runner_gpu::GraphRunnerGPU, graph_validation};
+use super::{
+    graph_runner_gpu::GraphRunnerGPU,
+    graph_validation,
+    memory_planner::{MemoryPlanningStrategy, MemoryReport},
+    quantization::{quantize_graph_operators, QuantizationReport},
+    shape_inference::{infer_shapes, ShapeReport},
+};
 
 fn cpu_benchmark(
     _gpu_handles: &GPUHandles,
@@ -23,8 +36,12 @@ fn cpu_benchmark(
     output: &mut Tensor2D,
 ) {
     let mut intermediate_output: Tensor2D = Tensor2D::default();
-    if !graph_validation::validate_graph_operators(graph) {
-        panic!("graph::runner::cpu_benchmark() was given an invalid graph!");
+    let mut named_tensors: HashMap<String, Tensor2D> = HashMap::<String, Tensor2D>::new();
+    if let Err(error) = graph_validation::validate_graph_operators(graph) {
+        panic!(
+            "graph::runner::cpu_benchmark() was given an invalid graph! {}",
+            error
+        );
     }
 
     for operator in graph {
@@ -73,6 +90,115 @@ fn cpu_benchmark(
                 );
                 intermediate_output = temp_output;
             }
+            MeanSquaredError { target } => {
+                let loss: f32 = Tensor2D::mean_squared_error(&intermediate_output, target);
+                intermediate_output = Tensor2D::new(0.0, 1, 1);
+                intermediate_output.data[0] = loss;
+            }
+            SoftmaxCrossEntropy { labels } => {
+                let loss: f32 = Tensor2D::softmax_cross_entropy(
+                    &intermediate_output,
+                    &Tensor2D::from_labels(labels),
+                );
+                intermediate_output = Tensor2D::new(0.0, 1, 1);
+                intermediate_output.data[0] = loss;
+            }
+            LinearInt8 {
+                weights,
+                bias,
+                input_parameters,
+            } => {
+                let input: QuantizedTensor2D =
+                    QuantizedTensor2D::quantize(&intermediate_output, *input_parameters);
+                let mut temp_output: Tensor2D =
+                    Tensor2D::new(0.0, bias.row_count, bias.column_count);
+                QuantizedTensor2D::linear_preallocated(&input, weights, bias, &mut temp_output);
+                intermediate_output = temp_output;
+            }
+            LinearReLUInt8Fused {
+                weights,
+                bias,
+                input_parameters,
+            } => {
+                let input: QuantizedTensor2D =
+                    QuantizedTensor2D::quantize(&intermediate_output, *input_parameters);
+                let mut temp_output: Tensor2D =
+                    Tensor2D::new(0.0, bias.row_count, bias.column_count);
+                QuantizedTensor2D::linear_relu_preallocated(
+                    &input,
+                    weights,
+                    bias,
+                    &mut temp_output,
+                );
+                intermediate_output = temp_output;
+            }
+            Store { name } => {
+                named_tensors.insert(name.clone(), intermediate_output.clone());
+            }
+            Load { name } => {
+                intermediate_output = named_tensors[name].clone();
+            }
+            Add { name } => {
+                let mut temp_output: Tensor2D = Tensor2D::new(
+                    0.0,
+                    intermediate_output.row_count,
+                    intermediate_output.column_count,
+                );
+                Tensor2D::add_preallocated(
+                    &intermediate_output,
+                    &named_tensors[name],
+                    &mut temp_output,
+                );
+                intermediate_output = temp_output;
+            }
+            Concat { name } => {
+                let other: &Tensor2D = &named_tensors[name];
+                let mut temp_output: Tensor2D = Tensor2D::new(
+                    0.0,
+                    intermediate_output.row_count,
+                    intermediate_output.column_count + other.column_count,
+                );
+                Tensor2D::concat_columns_preallocated(
+                    &intermediate_output,
+                    other,
+                    &mut temp_output,
+                );
+                intermediate_output = temp_output;
+            }
+            LinearAddFused {
+                weights,
+                bias,
+                name,
+            } => {
+                let mut temp_output: Tensor2D =
+                    Tensor2D::new(0.0, bias.row_count, bias.column_count);
+                Tensor2D::linear_optimized(&intermediate_output, weights, bias, &mut temp_output);
+                Tensor2D::add_inplace(&mut temp_output, &named_tensors[name]);
+                intermediate_output = temp_output;
+            }
+            AddReLUFused { name } => {
+                Tensor2D::add_inplace(&mut intermediate_output, &named_tensors[name]);
+                Tensor2D::relu_inplace_inline(&mut intermediate_output);
+            }
+            Activation { function } => {
+                Tensor2D::activation_inplace(&mut intermediate_output, *function);
+            }
+            LinearActivationFused {
+                weights,
+                bias,
+                function,
+            } => {
+                let mut temp_output: Tensor2D =
+                    Tensor2D::new(0.0, bias.row_count, bias.column_count);
+                Tensor2D::linear_optimized_activation(
+                    &intermediate_output,
+                    weights,
+                    bias,
+                    &mut temp_output,
+                    *function,
+                );
+                intermediate_output = temp_output;
+            }
         }
     }
 
@@ -86,8 +212,41 @@ fn cpu_graph_benchmark(
     output: &mut Tensor2D,
 ) {
     let fuse_operators: bool = true;
-    let mut graph_runner: GraphRunner = GraphRunner::new(graph, fuse_operators);
-    *output = graph_runner.run();
+    let parallel: bool = false;
+    let mut graph_runner: GraphRunner = GraphRunner::new(graph, fuse_operators, parallel).unwrap();
+    *output = graph_runner.run().unwrap();
+}
+
+// The same as cpu_graph_benchmark, but with the rows of the operators split across
+// the threads of the current rayon pool. See cpu_graph_thread_benchmarks for how
+// the number of threads is controlled.
+fn cpu_graph_parallel_benchmark(
+    _gpu_handles: &GPUHandles,
+    graph: &Vec<GraphOperator>,
+    _iteration_count: usize,
+    output: &mut Tensor2D,
+) {
+    let fuse_operators: bool = true;
+    let parallel: bool = true;
+    let mut graph_runner: GraphRunner = GraphRunner::new(graph, fuse_operators, parallel).unwrap();
+    *output = graph_runner.run().unwrap();
+}
+
+// The same as cpu_graph_benchmark, but with the intermediate buffers reused
+// across the graph, see GraphRunner::plan_memory.
+fn cpu_graph_planned_benchmark(
+    _gpu_handles: &GPUHandles,
+    graph: &Vec<GraphOperator>,
+    _iteration_count: usize,
+    output: &mut Tensor2D,
+) {
+    let fuse_operators: bool = true;
+    let parallel: bool = false;
+    let mut graph_runner: GraphRunner = GraphRunner::new(graph, fuse_operators, parallel).unwrap();
+    graph_runner
+        .plan_memory(MemoryPlanningStrategy::BestFit)
+        .unwrap();
+    *output = graph_runner.run().unwrap();
 }
 
 fn immediate_benchmark(
@@ -97,8 +256,11 @@ fn immediate_benchmark(
     output: &mut Tensor2D,
 ) {
     let mut intermediate_output: Tensor2D = Tensor2D::default();
-    if !graph_validation::validate_graph_operators(graph) {
-        panic!("graph::runner::immediate_benchmark() was given an invalid graph!");
+    if let Err(error) = graph_validation::validate_graph_operators(graph) {
+        panic!(
+            "graph::runner::immediate_benchmark() was given an invalid graph! {}",
+            error
+        );
     }
 
     for operator in graph {
@@ -163,6 +325,23 @@ fn immediate_benchmark(
                 ));
                 intermediate_output = temp_output;
             }
+            MeanSquaredError { .. }
+            | SoftmaxCrossEntropy { .. }
+            | LinearInt8 { .. }
+            | LinearReLUInt8Fused { .. }
+            | Store { .. }
+            | Load { .. }
+            | Add { .. }
+            | Concat { .. }
+            | LinearAddFused { .. }
+            | AddReLUFused { .. }
+            | Activation { .. }
+            | LinearActivationFused { .. } => {
+                panic!(
+                    "graph::runner::immediate_benchmark() does not support the operator {:?}",
+                    operator
+                );
+            }
         }
     }
 
@@ -178,8 +357,8 @@ fn graph_benchmark(
     let fuse_operators: bool = false;
     let cache_elements: bool = false;
     let mut graph_runner: GraphRunnerGPU =
-        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements);
-    *output = pollster::block_on(graph_runner.run(gpu_handles, 1));
+        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements).unwrap();
+    *output = pollster::block_on(graph_runner.run(gpu_handles, 1)).unwrap();
 }
 
 fn graph_fused_benchmark(
@@ -191,8 +370,8 @@ fn graph_fused_benchmark(
     let fuse_operators: bool = true;
     let cache_elements: bool = false;
     let mut graph_runner: GraphRunnerGPU =
-        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements);
-    *output = pollster::block_on(graph_runner.run(gpu_handles, 1));
+        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements).unwrap();
+    *output = pollster::block_on(graph_runner.run(gpu_handles, 1)).unwrap();
 }
 
 fn graph_cached_benchmark(
@@ -204,8 +383,8 @@ fn graph_cached_benchmark(
     let fuse_operators: bool = false;
     let cache_elements: bool = true;
     let mut graph_runner: GraphRunnerGPU =
-        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements);
-    *output = pollster::block_on(graph_runner.run(gpu_handles, 1));
+        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements).unwrap();
+    *output = pollster::block_on(graph_runner.run(gpu_handles, 1)).unwrap();
 }
 
 fn graph_cached_fused_benchmark(
@@ -217,8 +396,8 @@ fn graph_cached_fused_benchmark(
     let fuse_operators: bool = true;
     let cache_elements: bool = true;
     let mut graph_runner: GraphRunnerGPU =
-        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements);
-    *output = pollster::block_on(graph_runner.run(gpu_handles, 1));
+        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements).unwrap();
+    *output = pollster::block_on(graph_runner.run(gpu_handles, 1)).unwrap();
 }
 
 fn graph_loop_benchmark(
@@ -230,8 +409,8 @@ fn graph_loop_benchmark(
     let fuse_operators: bool = false;
     let cache_elements: bool = false;
     let mut graph_runner: GraphRunnerGPU =
-        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements);
-    *output = pollster::block_on(graph_runner.run(gpu_handles, iteration_count));
+        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements).unwrap();
+    *output = pollster::block_on(graph_runner.run(gpu_handles, iteration_count)).unwrap();
 }
 
 fn graph_loop_fused_benchmark(
@@ -243,8 +422,8 @@ fn graph_loop_fused_benchmark(
     let fuse_operators: bool = true;
     let cache_elements: bool = false;
     let mut graph_runner: GraphRunnerGPU =
-        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements);
-    *output = pollster::block_on(graph_runner.run(gpu_handles, iteration_count));
+        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements).unwrap();
+    *output = pollster::block_on(graph_runner.run(gpu_handles, iteration_count)).unwrap();
 }
 
 fn graph_loop_cached_benchmark(
@@ -256,8 +435,8 @@ fn graph_loop_cached_benchmark(
     let fuse_operators: bool = false;
     let cache_elements: bool = true;
     let mut graph_runner: GraphRunnerGPU =
-        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements);
-    *output = pollster::block_on(graph_runner.run(gpu_handles, iteration_count));
+        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements).unwrap();
+    *output = pollster::block_on(graph_runner.run(gpu_handles, iteration_count)).unwrap();
 }
 
 fn graph_loop_cached_fused_benchmark(
@@ -269,14 +448,63 @@ fn graph_loop_cached_fused_benchmark(
     let fuse_operators: bool = true;
     let cache_elements: bool = true;
     let mut graph_runner: GraphRunnerGPU =
-        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements);
-    *output = pollster::block_on(graph_runner.run(gpu_handles, iteration_count));
+        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements).unwrap();
+    *output = pollster::block_on(graph_runner.run(gpu_handles, iteration_count)).unwrap();
+}
+
+fn graph_loop_cached_fused_planned_benchmark(
+    gpu_handles: &GPUHandles,
+    graph: &Vec<GraphOperator>,
+    iteration_count: usize,
+    output: &mut Tensor2D,
+) {
+    let fuse_operators: bool = true;
+    let cache_elements: bool = true;
+    let mut graph_runner: GraphRunnerGPU =
+        GraphRunnerGPU::new(gpu_handles, graph, fuse_operators, cache_elements).unwrap();
+    graph_runner
+        .plan_memory(gpu_handles, MemoryPlanningStrategy::BestFit)
+        .unwrap();
+    *output = pollster::block_on(graph_runner.run(gpu_handles, iteration_count)).unwrap();
+}
+
+// The expected cost of every graph measured by graph_benchmarks, before fusion.
+// Compare the FLOPs/byte with the ridge point of the hardware, its peak FLOPs divided
+// by its peak bandwidth, to see whether the graph should be compute or memory bound.
+fn print_expected_graph_costs(config: &Configuration) {
+    let print_costs = |size: usize, depth: usize| {
+        let report: ShapeReport = infer_shapes(&benchmark_graph(size, depth))
+            .unwrap_or_else(|error| panic!("Invalid benchmark graph: {}", error));
+        println!(
+            "{:>8} {:>8} {:>16} {:>16} {:>10.2}",
+            size,
+            depth,
+            report.total_flops(),
+            report.total_bytes(),
+            report.arithmetic_intensity()
+        );
+    };
+
+    println!("expected graph benchmark costs:");
+    println!(
+        "{:>8} {:>8} {:>16} {:>16} {:>10}",
+        "Size", "Depth", "FLOPs", "Bytes", "FLOPs/byte"
+    );
+    for size in &config.loop_range {
+        print_costs(*size, config.default_graph_layer_count);
+    }
+    for depth in &config.graph_depth_range {
+        print_costs(config.default_graph_operator_size, *depth);
+    }
 }
 
 fn graph_benchmarks(config: &Configuration, gpu_handles: &GPUHandles) {
+    print_expected_graph_costs(config);
+
     let names: Vec<String> = vec![
         "graph::runner::cpu".to_string(),
         "graph::runner::cpu_graph".to_string(),
+        "graph::runner::cpu_graph_planned".to_string(),
         "graph::runner::immediate".to_string(),
         "graph::runner::graph".to_string(),
         "graph::runner::graph_fused".to_string(),
@@ -286,6 +514,7 @@ fn graph_benchmarks(config: &Configuration, gpu_handles: &GPUHandles) {
         "graph::runner::graph_loop_fused".to_string(),
         "graph::runner::graph_loop_cached".to_string(),
         "graph::runner::graph_loop_cached_fused".to_string(),
+        "graph::runner::graph_loop_cached_fused_planned".to_string(),
     ];
 
     let functions: Vec<(
@@ -294,6 +523,7 @@ fn graph_benchmarks(config: &Configuration, gpu_handles: &GPUHandles) {
     )> = vec![
         (GraphFunction::Cpu, cpu_benchmark),
         (GraphFunction::Cpu, cpu_graph_benchmark),
+        (GraphFunction::Cpu, cpu_graph_planned_benchmark),
         (GraphFunction::Immediate, immediate_benchmark),
         (GraphFunction::Graph, graph_benchmark),
         (GraphFunction::Graph, graph_fused_benchmark),
@@ -303,6 +533,10 @@ fn graph_benchmarks(config: &Configuration, gpu_handles: &GPUHandles) {
         (GraphFunction::GraphLoop, graph_loop_fused_benchmark),
         (GraphFunction::GraphLoop, graph_loop_cached_benchmark),
         (GraphFunction::GraphLoop, graph_loop_cached_fused_benchmark),
+        (
+            GraphFunction::GraphLoop,
+            graph_loop_cached_fused_planned_benchmark,
+        ),
     ];
 
     let mut all_measurements: Vec<PerformanceMeasurements> =
@@ -366,6 +600,7 @@ fn graph_benchmarks(config: &Configuration, gpu_handles: &GPUHandles) {
         "graph::runner::graph_loop_fused".to_string(),
         "graph::runner::graph_loop_cached".to_string(),
         "graph::runner::graph_loop_cached_fused".to_string(),
+        "graph::runner::graph_loop_cached_fused_planned".to_string(),
     ];
 
     let functions: Vec<(
@@ -380,6 +615,10 @@ fn graph_benchmarks(config: &Configuration, gpu_handles: &GPUHandles) {
         (GraphFunction::GraphLoop, graph_loop_fused_benchmark),
         (GraphFunction::GraphLoop, graph_loop_cached_benchmark),
         (GraphFunction::GraphLoop, graph_loop_cached_fused_benchmark),
+        (
+            GraphFunction::GraphLoop,
+            graph_loop_cached_fused_planned_benchmark,
+        ),
     ];
 
     let mut all_measurements: Vec<PerformanceMeasurements> =
@@ -432,11 +671,102 @@ fn graph_benchmarks(config: &Configuration, gpu_handles: &GPUHandles) {
         config.log_scale,
     );
 
+    // The cached runners share the pipelines of the device, so only the first
+    // runner to use an operator compiles its shaders
+    println!("gpu pipeline cache:");
+    println!("{}", gpu_handles.pipeline_cache.statistics());
+}
+
+// Sweeps the number of threads used by the parallel CPU graph runner.
+// Every sweep runs the entire benchmark inside a rayon pool with that number of threads,
+// so the cost of starting the threads isn't part of the measurements.
+// The single threaded cpu_graph is included as the baseline.
+fn cpu_graph_thread_benchmarks(config: &Configuration, gpu_handles: &GPUHandles) {
+    let max_thread_count: usize = std::thread::available_parallelism()
+        .map(|thread_count| thread_count.get())
+        .unwrap_or(1);
+    let mut thread_counts: Vec<usize> = vec![1];
+    while thread_counts[thread_counts.len() - 1] * 2 <= max_thread_count {
+        thread_counts.push(thread_counts[thread_counts.len() - 1] * 2);
+    }
+
+    let single_threaded_functions: Vec<(
+        GraphFunction,
+        fn(&GPUHandles, &Vec<GraphOperator>, usize, &mut Tensor2D),
+    )> = vec![(GraphFunction::Cpu, cpu_graph_benchmark)];
+    let parallel_functions: Vec<(
+        GraphFunction,
+        fn(&GPUHandles, &Vec<GraphOperator>, usize, &mut Tensor2D),
+    )> = vec![(GraphFunction::Cpu, cpu_graph_parallel_benchmark)];
+
+    for measure_depth in [false, true] {
+        let mut all_measurements: Vec<PerformanceMeasurements> =
+            vec![PerformanceMeasurements::default(); 1];
+        benchmark_function_vector_gpu_graph(
+            config,
+            vec!["graph::runner::cpu_graph".to_string()],
+            gpu_handles,
+            &single_threaded_functions,
+            &mut all_measurements,
+            measure_depth,
+        );
+
+        for thread_count in &thread_counts {
+            let thread_pool: ThreadPool = ThreadPoolBuilder::new()
+                .num_threads(*thread_count)
+                .build()
+                .expect("Failed to build a rayon thread pool in graph::runner::cpu_graph_thread_benchmarks");
+
+            let mut measurements: Vec<PerformanceMeasurements> =
+                vec![PerformanceMeasurements::default(); 1];
+            thread_pool.install(|| {
+                benchmark_function_vector_gpu_graph(
+                    config,
+                    vec![format!(
+                        "graph::runner::cpu_graph_parallel - {} threads",
+                        thread_count
+                    )],
+                    gpu_handles,
+                    &parallel_functions,
+                    &mut measurements,
+                    measure_depth,
+                )
+            });
+            all_measurements.append(&mut measurements);
+        }
+
+        let (title, file_name): (String, &str) = if measure_depth {
+            (
+                format!(
+                    "CPU Graph Threads Benchmark - Depth(x) - Size {}",
+                    config.default_graph_operator_size
+                ),
+                "graphs_cpu_threads_depth_benchmark.png",
+            )
+        } else {
+            (
+                format!(
+                    "CPU Graph Threads Benchmark - Size(x) - Depth {}",
+                    config.default_graph_layer_count
+                ),
+                "graphs_cpu_threads_size_benchmark.png",
+            )
+        };
+
+        draw_benchmark_plot(
+            title.as_str(),
+            "benchmarks/graphs/",
+            file_name,
+            all_measurements,
+            config.log_scale,
+        );
+    }
 }
 
 pub async fn execute(gpu_handles: &GPUHandles, config: &Configuration) {
     if config.run_performance_benchmark {
         graph_benchmarks(config, gpu_handles);
+        cpu_graph_thread_benchmarks(config, gpu_handles);
         return;
     }
 
@@ -481,11 +811,13 @@ pub async fn execute(gpu_handles: &GPUHandles, config: &Configuration) {
     ];
 
     let fuse_operators: bool = true;
+    let parallel: bool = false;
     let cache_elements: bool = true;
     // let mut graph_runner: GraphRunner = GraphRunner::new(&gpu_handles, graph_operators, fuse_operators, cache_elements);
     // let output: Tensor2D = graph_runner.run(&gpu_handles).await;
-    let mut graph_runner: GraphRunner = GraphRunner::new(&graph_operators, fuse_operators);
-    let output: Tensor2D = graph_runner.run();
+    let mut graph_runner: GraphRunner =
+        GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+    let output: Tensor2D = graph_runner.run().unwrap();
     println!("cpu output: {:?}", output);
 
     let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
@@ -495,13 +827,92 @@ pub async fn execute(gpu_handles: &GPUHandles, config: &Configuration) {
         difference.data.iter().map(|x| x.abs()).sum::<f32>()
     );
 
+    // The same graph with the linear operators quantized to int8. With no calibration
+    // inputs the input of the graph is used to find the ranges of the intermediate values.
+    let quantized_operators: Vec<GraphOperator> =
+        quantize_graph_operators(&graph_operators, &[], QuantizationGranularity::PerChannel);
+    println!(
+        "{}",
+        QuantizationReport::new(&graph_operators, &quantized_operators)
+    );
+
+    // The memory used by the graph when the intermediate buffers are reused
+    let mut graph_runner: GraphRunner =
+        GraphRunner::new(&graph_operators, fuse_operators, parallel).unwrap();
+    println!("cpu fusions:");
+    println!("{}", graph_runner.fusion_report());
+    println!("cpu shape report:");
+    println!("{}", graph_runner.shape_report());
+
+    // Before and after fusion diagrams, render with dot -Tsvg, see graph::dot_export
+    let dot_directory: &str = "benchmarks/graphs/";
+    fs::create_dir_all(dot_directory).expect("Failed to create the directory for the DOT files");
+    let unfused_graph_runner: GraphRunner =
+        GraphRunner::new(&graph_operators, false, parallel).unwrap();
+    fs::write(
+        format!("{}graph_unfused.dot", dot_directory),
+        unfused_graph_runner.to_dot("unfused"),
+    )
+    .expect("Failed to write graph_unfused.dot");
+    fs::write(
+        format!("{}graph_fused.dot", dot_directory),
+        graph_runner.to_dot("fused"),
+    )
+    .expect("Failed to write graph_fused.dot");
+    println!("cpu memory plan:");
+    println!(
+        "{}",
+        graph_runner
+            .plan_memory(MemoryPlanningStrategy::BestFit)
+            .unwrap()
+    );
+
+    // The deepest graphs of the benchmarks are where reuse matters the most
+    let depth: usize = config
+        .graph_depth_range
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(128);
+    let mut deep_graph_operators: Vec<GraphOperator> = vec![HostToDevice {
+        input: Tensor2D::new(0.5, 32, 32),
+    }];
+    for _ in 0..depth {
+        deep_graph_operators.push(Linear {
+            weights: Tensor2D::new(0.5, 32, 32),
+            bias: Tensor2D::new(0.1, 32, 32),
+        });
+        deep_graph_operators.push(ReLU);
+    }
+    deep_graph_operators.push(Softmax);
+    deep_graph_operators.push(DeviceToHost);
+
+    for strategy in [
+        MemoryPlanningStrategy::Greedy,
+        MemoryPlanningStrategy::BestFit,
+    ] {
+        let mut graph_runner: GraphRunner =
+            GraphRunner::new(&deep_graph_operators, false, parallel).unwrap();
+        let report: MemoryReport = graph_runner.plan_memory(strategy).unwrap();
+        println!("cpu memory plan - depth {} - {:?}:", depth, strategy);
+        println!("{}", report);
+    }
+
     let mut graph_runner: GraphRunnerGPU = GraphRunnerGPU::new(
         gpu_handles,
         &graph_operators,
         fuse_operators,
         cache_elements,
+    )
+    .unwrap();
+    println!("gpu memory plan:");
+    println!(
+        "{}",
+        graph_runner
+            .plan_memory(gpu_handles, MemoryPlanningStrategy::BestFit)
+            .unwrap()
     );
-    let output: Tensor2D = graph_runner.run(gpu_handles, 1).await;
+    let output: Tensor2D = graph_runner.run(gpu_handles, 1).await.unwrap();
     println!("gpu output: {:?}", output);
 
     let difference: Tensor2D = Tensor2D::subtraction(&output_cpu, &output);
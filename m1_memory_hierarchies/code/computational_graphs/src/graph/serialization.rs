@@ -0,0 +1,733 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::shared::{
+    activation::ActivationFunction,
+    graph_operators::GraphOperator,
+    quantized_tensor2d::{QuantizationParameters, QuantizedTensor2D},
+    tensor2d::Tensor2D,
+};
+
+// A graph is saved as two files. The topology is a small, human readable JSON or TOML
+// file with one entry per operator. The tensors are in a binary weights file next to it,
+// the topology only refers to them by their index in the weights file. That keeps the
+// topology diffable when a model is checked in, and the weights don't have to go
+// through a text format.
+//
+// The weights file is little endian and starts with a header
+// magic: [u8; 4] = b"NGBW"
+// version: u32
+// tensor_count: u32
+// followed by every tensor as
+// data_type: u32 (0 = f32, 1 = i8)
+// row_count: u32
+// column_count: u32
+// data: row_count * column_count elements of data_type
+// Only the active part of a tensor is written, see Tensor2D.
+//
+// Every operator can be saved, including the fused and quantized ones, but only
+// the CPU GraphRunner can run the quantized operators once they are loaded.
+
+const WEIGHTS_MAGIC: &[u8; 4] = b"NGBW";
+const FORMAT_VERSION: u32 = 1;
+
+const DATA_TYPE_F32: u32 = 0;
+const DATA_TYPE_I8: u32 = 1;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TopologyFormat {
+    Json,
+    Toml,
+}
+
+impl TopologyFormat {
+    // .toml files are TOML, everything else is JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => TopologyFormat::Toml,
+            _ => TopologyFormat::Json,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+struct QuantizationParametersRecord {
+    scale: f32,
+    zero_point: i32,
+}
+
+impl From<QuantizationParameters> for QuantizationParametersRecord {
+    fn from(parameters: QuantizationParameters) -> Self {
+        QuantizationParametersRecord {
+            scale: parameters.scale,
+            zero_point: parameters.zero_point,
+        }
+    }
+}
+
+impl From<QuantizationParametersRecord> for QuantizationParameters {
+    fn from(record: QuantizationParametersRecord) -> Self {
+        QuantizationParameters {
+            scale: record.scale,
+            zero_point: record.zero_point,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "name")]
+enum ActivationRecord {
+    GELUErf,
+    GELUTanh,
+    SiLU,
+    Tanh,
+    Sigmoid,
+    LeakyReLU { alpha: f32 },
+}
+
+impl From<ActivationFunction> for ActivationRecord {
+    fn from(function: ActivationFunction) -> Self {
+        match function {
+            ActivationFunction::GELUErf => ActivationRecord::GELUErf,
+            ActivationFunction::GELUTanh => ActivationRecord::GELUTanh,
+            ActivationFunction::SiLU => ActivationRecord::SiLU,
+            ActivationFunction::Tanh => ActivationRecord::Tanh,
+            ActivationFunction::Sigmoid => ActivationRecord::Sigmoid,
+            ActivationFunction::LeakyReLU { alpha } => ActivationRecord::LeakyReLU { alpha },
+        }
+    }
+}
+
+impl From<ActivationRecord> for ActivationFunction {
+    fn from(record: ActivationRecord) -> Self {
+        match record {
+            ActivationRecord::GELUErf => ActivationFunction::GELUErf,
+            ActivationRecord::GELUTanh => ActivationFunction::GELUTanh,
+            ActivationRecord::SiLU => ActivationFunction::SiLU,
+            ActivationRecord::Tanh => ActivationFunction::Tanh,
+            ActivationRecord::Sigmoid => ActivationFunction::Sigmoid,
+            ActivationRecord::LeakyReLU { alpha } => ActivationFunction::LeakyReLU { alpha },
+        }
+    }
+}
+
+// GraphOperator as it is written to the topology file. Every tensor is replaced by
+// its index in the weights file.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "operator")]
+enum OperatorRecord {
+    Empty,
+    HostToDevice {
+        input: usize,
+    },
+    DeviceToHost,
+    Linear {
+        weights: usize,
+        bias: usize,
+    },
+    ReLU,
+    Softmax,
+    LinearReLUFused {
+        weights: usize,
+        bias: usize,
+    },
+    LinearReLUSoftmaxFused {
+        weights: usize,
+        bias: usize,
+    },
+    MeanSquaredError {
+        target: usize,
+    },
+    SoftmaxCrossEntropy {
+        labels: Vec<usize>,
+    },
+    LinearInt8 {
+        weights: usize,
+        weight_parameters: Vec<QuantizationParametersRecord>,
+        bias: usize,
+        input_parameters: QuantizationParametersRecord,
+    },
+    LinearReLUInt8Fused {
+        weights: usize,
+        weight_parameters: Vec<QuantizationParametersRecord>,
+        bias: usize,
+        input_parameters: QuantizationParametersRecord,
+    },
+    Store {
+        name: String,
+    },
+    Load {
+        name: String,
+    },
+    Add {
+        name: String,
+    },
+    Concat {
+        name: String,
+    },
+    LinearAddFused {
+        weights: usize,
+        bias: usize,
+        name: String,
+    },
+    AddReLUFused {
+        name: String,
+    },
+    Activation {
+        function: ActivationRecord,
+    },
+    LinearActivationFused {
+        weights: usize,
+        bias: usize,
+        function: ActivationRecord,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct TopologyRecord {
+    version: u32,
+    // The name of the weights file, relative to the topology file
+    weights: String,
+    operators: Vec<OperatorRecord>,
+}
+
+// The tensors of a graph in the order they are written to the weights file
+#[derive(Default)]
+struct WeightsWriter {
+    tensor_count: u32,
+    bytes: Vec<u8>,
+}
+
+impl WeightsWriter {
+    // The dimensions are stored as u32, larger tensors can't be written
+    fn push_header(
+        &mut self,
+        data_type: u32,
+        row_count: usize,
+        column_count: usize,
+    ) -> Result<usize, Error> {
+        let (stored_row_count, stored_column_count): (u32, u32) =
+            match (u32::try_from(row_count), u32::try_from(column_count)) {
+                (Ok(stored_row_count), Ok(stored_column_count)) => {
+                    (stored_row_count, stored_column_count)
+                }
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "serialization::WeightsWriter - a tensor with {} rows and {} columns is too large for the weights file",
+                            row_count, column_count
+                        ),
+                    ))
+                }
+            };
+        self.bytes.extend_from_slice(&data_type.to_le_bytes());
+        self.bytes
+            .extend_from_slice(&stored_row_count.to_le_bytes());
+        self.bytes
+            .extend_from_slice(&stored_column_count.to_le_bytes());
+
+        let index: usize = self.tensor_count as usize;
+        self.tensor_count += 1;
+        Ok(index)
+    }
+
+    fn push_f32(&mut self, tensor: &Tensor2D) -> Result<usize, Error> {
+        let index: usize =
+            self.push_header(DATA_TYPE_F32, tensor.row_count, tensor.column_count)?;
+        for value in &tensor.data[0..tensor.len()] {
+            self.bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Ok(index)
+    }
+
+    fn push_i8(&mut self, tensor: &Tensor2D<i8>) -> Result<usize, Error> {
+        let index: usize = self.push_header(DATA_TYPE_I8, tensor.row_count, tensor.column_count)?;
+        let element_count: usize = tensor.row_count * tensor.column_count;
+        for value in &tensor.data[0..element_count] {
+            self.bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Ok(index)
+    }
+
+    fn finish(self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::<u8>::with_capacity(12 + self.bytes.len());
+        bytes.extend_from_slice(WEIGHTS_MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.tensor_count.to_le_bytes());
+        bytes.extend_from_slice(&self.bytes);
+        bytes
+    }
+}
+
+enum StoredTensor {
+    F32(Tensor2D),
+    I8(Tensor2D<i8>),
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+// Reads the weights file back into a list of tensors, which the operator records index into
+struct WeightsReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> WeightsReader<'a> {
+    fn take(&mut self, byte_count: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() - self.offset < byte_count {
+            return Err(invalid_data(format!(
+                "serialization::WeightsReader - the weights file ended after {} bytes, expected at least {}",
+                self.bytes.len(),
+                self.offset + byte_count
+            )));
+        }
+        let slice: &'a [u8] = &self.bytes[self.offset..self.offset + byte_count];
+        self.offset += byte_count;
+        Ok(slice)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        let bytes: [u8; 4] = self.take(4)?.try_into().unwrap();
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_tensors(bytes: &'a [u8]) -> Result<Vec<StoredTensor>, Error> {
+        let mut reader: WeightsReader = WeightsReader { bytes, offset: 0 };
+        if reader.take(4)? != WEIGHTS_MAGIC {
+            return Err(invalid_data(
+                "serialization::WeightsReader - not a weights file".to_string(),
+            ));
+        }
+        let version: u32 = reader.read_u32()?;
+        if version != FORMAT_VERSION {
+            return Err(invalid_data(format!(
+                "serialization::WeightsReader - unsupported weights file version {}, expected {}",
+                version, FORMAT_VERSION
+            )));
+        }
+
+        let tensor_count: u32 = reader.read_u32()?;
+        let mut tensors: Vec<StoredTensor> = Vec::<StoredTensor>::new();
+        for _ in 0..tensor_count {
+            let data_type: u32 = reader.read_u32()?;
+            let row_count: usize = reader.read_u32()? as usize;
+            let column_count: usize = reader.read_u32()? as usize;
+            let element_count: usize = row_count.checked_mul(column_count).ok_or_else(|| {
+                invalid_data(format!(
+                    "serialization::WeightsReader - tensor {} has {} rows and {} columns, which is too many elements",
+                    tensors.len(),
+                    row_count,
+                    column_count
+                ))
+            })?;
+
+            let tensor: StoredTensor = match data_type {
+                DATA_TYPE_F32 => StoredTensor::F32(Tensor2D {
+                    data: reader
+                        .take(element_count.checked_mul(4).ok_or_else(|| {
+                            invalid_data(format!(
+                                "serialization::WeightsReader - tensor {} has {} elements, which is too many bytes",
+                                tensors.len(),
+                                element_count
+                            ))
+                        })?)?
+                        .chunks_exact(4)
+                        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+                        .collect(),
+                    row_count,
+                    column_count,
+                }),
+                DATA_TYPE_I8 => StoredTensor::I8(Tensor2D {
+                    data: reader
+                        .take(element_count)?
+                        .iter()
+                        .map(|byte| *byte as i8)
+                        .collect(),
+                    row_count,
+                    column_count,
+                }),
+                _ => {
+                    return Err(invalid_data(format!(
+                        "serialization::WeightsReader - unknown data type {} for tensor {}",
+                        data_type,
+                        tensors.len()
+                    )))
+                }
+            };
+            tensors.push(tensor);
+        }
+
+        if reader.offset != bytes.len() {
+            return Err(invalid_data(format!(
+                "serialization::WeightsReader - {} bytes left after the last tensor",
+                bytes.len() - reader.offset
+            )));
+        }
+
+        Ok(tensors)
+    }
+}
+
+fn quantization_records(
+    parameters: &[QuantizationParameters],
+) -> Vec<QuantizationParametersRecord> {
+    parameters
+        .iter()
+        .map(|parameters| QuantizationParametersRecord::from(*parameters))
+        .collect()
+}
+
+fn to_records(
+    graph_operators: &[GraphOperator],
+    weights: &mut WeightsWriter,
+) -> Result<Vec<OperatorRecord>, Error> {
+    let mut records: Vec<OperatorRecord> = Vec::<OperatorRecord>::new();
+    for operator in graph_operators {
+        let record: OperatorRecord = match operator {
+            GraphOperator::Empty => OperatorRecord::Empty,
+            GraphOperator::HostToDevice { input } => OperatorRecord::HostToDevice {
+                input: weights.push_f32(input)?,
+            },
+            GraphOperator::DeviceToHost => OperatorRecord::DeviceToHost,
+            GraphOperator::Linear {
+                weights: linear_weights,
+                bias,
+            } => OperatorRecord::Linear {
+                weights: weights.push_f32(linear_weights)?,
+                bias: weights.push_f32(bias)?,
+            },
+            GraphOperator::ReLU => OperatorRecord::ReLU,
+            GraphOperator::Softmax => OperatorRecord::Softmax,
+            GraphOperator::LinearReLUFused {
+                weights: linear_weights,
+                bias,
+            } => OperatorRecord::LinearReLUFused {
+                weights: weights.push_f32(linear_weights)?,
+                bias: weights.push_f32(bias)?,
+            },
+            GraphOperator::LinearReLUSoftmaxFused {
+                weights: linear_weights,
+                bias,
+            } => OperatorRecord::LinearReLUSoftmaxFused {
+                weights: weights.push_f32(linear_weights)?,
+                bias: weights.push_f32(bias)?,
+            },
+            GraphOperator::MeanSquaredError { target } => OperatorRecord::MeanSquaredError {
+                target: weights.push_f32(target)?,
+            },
+            GraphOperator::SoftmaxCrossEntropy { labels } => OperatorRecord::SoftmaxCrossEntropy {
+                labels: labels.clone(),
+            },
+            GraphOperator::LinearInt8 {
+                weights: linear_weights,
+                bias,
+                input_parameters,
+            } => OperatorRecord::LinearInt8 {
+                weights: weights.push_i8(&linear_weights.tensor)?,
+                weight_parameters: quantization_records(&linear_weights.parameters),
+                bias: weights.push_f32(bias)?,
+                input_parameters: QuantizationParametersRecord::from(*input_parameters),
+            },
+            GraphOperator::LinearReLUInt8Fused {
+                weights: linear_weights,
+                bias,
+                input_parameters,
+            } => OperatorRecord::LinearReLUInt8Fused {
+                weights: weights.push_i8(&linear_weights.tensor)?,
+                weight_parameters: quantization_records(&linear_weights.parameters),
+                bias: weights.push_f32(bias)?,
+                input_parameters: QuantizationParametersRecord::from(*input_parameters),
+            },
+            GraphOperator::Store { name } => OperatorRecord::Store { name: name.clone() },
+            GraphOperator::Load { name } => OperatorRecord::Load { name: name.clone() },
+            GraphOperator::Add { name } => OperatorRecord::Add { name: name.clone() },
+            GraphOperator::Concat { name } => OperatorRecord::Concat { name: name.clone() },
+            GraphOperator::LinearAddFused {
+                weights: linear_weights,
+                bias,
+                name,
+            } => OperatorRecord::LinearAddFused {
+                weights: weights.push_f32(linear_weights)?,
+                bias: weights.push_f32(bias)?,
+                name: name.clone(),
+            },
+            GraphOperator::AddReLUFused { name } => {
+                OperatorRecord::AddReLUFused { name: name.clone() }
+            }
+            GraphOperator::Activation { function } => OperatorRecord::Activation {
+                function: ActivationRecord::from(*function),
+            },
+            GraphOperator::LinearActivationFused {
+                weights: linear_weights,
+                bias,
+                function,
+            } => OperatorRecord::LinearActivationFused {
+                weights: weights.push_f32(linear_weights)?,
+                bias: weights.push_f32(bias)?,
+                function: ActivationRecord::from(*function),
+            },
+        };
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn f32_tensor(tensors: &[StoredTensor], index: usize) -> Result<Tensor2D, Error> {
+    match tensors.get(index) {
+        Some(StoredTensor::F32(tensor)) => Ok(tensor.clone()),
+        Some(StoredTensor::I8(_)) => Err(invalid_data(format!(
+            "serialization::f32_tensor - tensor {} is i8, expected f32",
+            index
+        ))),
+        None => Err(invalid_data(format!(
+            "serialization::f32_tensor - tensor {} is not in the weights file, which has {} tensors",
+            index,
+            tensors.len()
+        ))),
+    }
+}
+
+fn quantized_tensor(
+    tensors: &[StoredTensor],
+    index: usize,
+    parameters: &[QuantizationParametersRecord],
+) -> Result<QuantizedTensor2D, Error> {
+    let tensor: Tensor2D<i8> = match tensors.get(index) {
+        Some(StoredTensor::I8(tensor)) => tensor.clone(),
+        Some(StoredTensor::F32(_)) => {
+            return Err(invalid_data(format!(
+                "serialization::quantized_tensor - tensor {} is f32, expected i8",
+                index
+            )))
+        }
+        None => {
+            return Err(invalid_data(format!(
+                "serialization::quantized_tensor - tensor {} is not in the weights file, which has {} tensors",
+                index,
+                tensors.len()
+            )))
+        }
+    };
+
+    if parameters.len() != 1 && parameters.len() != tensor.column_count {
+        return Err(invalid_data(format!(
+            "serialization::quantized_tensor - tensor {} has {} columns, but {} sets of quantization parameters",
+            index,
+            tensor.column_count,
+            parameters.len()
+        )));
+    }
+
+    Ok(QuantizedTensor2D {
+        tensor,
+        parameters: parameters
+            .iter()
+            .map(|record| QuantizationParameters::from(*record))
+            .collect(),
+    })
+}
+
+fn from_records(
+    records: &[OperatorRecord],
+    tensors: &[StoredTensor],
+) -> Result<Vec<GraphOperator>, Error> {
+    let mut graph_operators: Vec<GraphOperator> = Vec::<GraphOperator>::new();
+    for record in records {
+        let operator: GraphOperator = match record {
+            OperatorRecord::Empty => GraphOperator::Empty,
+            OperatorRecord::HostToDevice { input } => GraphOperator::HostToDevice {
+                input: f32_tensor(tensors, *input)?,
+            },
+            OperatorRecord::DeviceToHost => GraphOperator::DeviceToHost,
+            OperatorRecord::Linear { weights, bias } => GraphOperator::Linear {
+                weights: f32_tensor(tensors, *weights)?,
+                bias: f32_tensor(tensors, *bias)?,
+            },
+            OperatorRecord::ReLU => GraphOperator::ReLU,
+            OperatorRecord::Softmax => GraphOperator::Softmax,
+            OperatorRecord::LinearReLUFused { weights, bias } => GraphOperator::LinearReLUFused {
+                weights: f32_tensor(tensors, *weights)?,
+                bias: f32_tensor(tensors, *bias)?,
+            },
+            OperatorRecord::LinearReLUSoftmaxFused { weights, bias } => {
+                GraphOperator::LinearReLUSoftmaxFused {
+                    weights: f32_tensor(tensors, *weights)?,
+                    bias: f32_tensor(tensors, *bias)?,
+                }
+            }
+            OperatorRecord::MeanSquaredError { target } => GraphOperator::MeanSquaredError {
+                target: f32_tensor(tensors, *target)?,
+            },
+            OperatorRecord::SoftmaxCrossEntropy { labels } => GraphOperator::SoftmaxCrossEntropy {
+                labels: labels.clone(),
+            },
+            OperatorRecord::LinearInt8 {
+                weights,
+                weight_parameters,
+                bias,
+                input_parameters,
+            } => GraphOperator::LinearInt8 {
+                weights: quantized_tensor(tensors, *weights, weight_parameters)?,
+                bias: f32_tensor(tensors, *bias)?,
+                input_parameters: QuantizationParameters::from(*input_parameters),
+            },
+            OperatorRecord::LinearReLUInt8Fused {
+                weights,
+                weight_parameters,
+                bias,
+                input_parameters,
+            } => GraphOperator::LinearReLUInt8Fused {
+                weights: quantized_tensor(tensors, *weights, weight_parameters)?,
+                bias: f32_tensor(tensors, *bias)?,
+                input_parameters: QuantizationParameters::from(*input_parameters),
+            },
+            OperatorRecord::Store { name } => GraphOperator::Store { name: name.clone() },
+            OperatorRecord::Load { name } => GraphOperator::Load { name: name.clone() },
+            OperatorRecord::Add { name } => GraphOperator::Add { name: name.clone() },
+            OperatorRecord::Concat { name } => GraphOperator::Concat { name: name.clone() },
+            OperatorRecord::LinearAddFused {
+                weights,
+                bias,
+                name,
+            } => GraphOperator::LinearAddFused {
+                weights: f32_tensor(tensors, *weights)?,
+                bias: f32_tensor(tensors, *bias)?,
+                name: name.clone(),
+            },
+            OperatorRecord::AddReLUFused { name } => {
+                GraphOperator::AddReLUFused { name: name.clone() }
+            }
+            OperatorRecord::Activation { function } => GraphOperator::Activation {
+                function: ActivationFunction::from(*function),
+            },
+            OperatorRecord::LinearActivationFused {
+                weights,
+                bias,
+                function,
+            } => GraphOperator::LinearActivationFused {
+                weights: f32_tensor(tensors, *weights)?,
+                bias: f32_tensor(tensors, *bias)?,
+                function: ActivationFunction::from(*function),
+            },
+        };
+        graph_operators.push(operator);
+    }
+    Ok(graph_operators)
+}
+
+// Returns the topology as text and the contents of the weights file.
+// weights_file_name is stored in the topology, see load_graph.
+// Fails if a tensor has more rows or columns than fit in a u32.
+pub fn serialize_graph(
+    graph_operators: &[GraphOperator],
+    format: TopologyFormat,
+    weights_file_name: &str,
+) -> Result<(String, Vec<u8>), Error> {
+    let mut weights: WeightsWriter = WeightsWriter::default();
+    let topology: TopologyRecord = TopologyRecord {
+        version: FORMAT_VERSION,
+        weights: weights_file_name.to_string(),
+        operators: to_records(graph_operators, &mut weights)?,
+    };
+
+    let topology: String = match format {
+        TopologyFormat::Json => serde_json::to_string_pretty(&topology)
+            .expect("serialization::serialize_graph - failed to write the topology as JSON"),
+        TopologyFormat::Toml => toml::to_string_pretty(&topology)
+            .expect("serialization::serialize_graph - failed to write the topology as TOML"),
+    };
+
+    Ok((topology, weights.finish()))
+}
+
+fn parse_topology(topology: &str, format: TopologyFormat) -> Result<TopologyRecord, Error> {
+    let topology: TopologyRecord = match format {
+        TopologyFormat::Json => serde_json::from_str(topology).map_err(|error| {
+            invalid_data(format!(
+                "serialization::parse_topology - invalid JSON: {}",
+                error
+            ))
+        })?,
+        TopologyFormat::Toml => toml::from_str(topology).map_err(|error| {
+            invalid_data(format!(
+                "serialization::parse_topology - invalid TOML: {}",
+                error
+            ))
+        })?,
+    };
+
+    if topology.version != FORMAT_VERSION {
+        return Err(invalid_data(format!(
+            "serialization::parse_topology - unsupported topology version {}, expected {}",
+            topology.version, FORMAT_VERSION
+        )));
+    }
+
+    Ok(topology)
+}
+
+// The inverse of serialize_graph. The name of the weights file in the topology is ignored.
+pub fn deserialize_graph(
+    topology: &str,
+    format: TopologyFormat,
+    weights: &[u8],
+) -> Result<Vec<GraphOperator>, Error> {
+    let topology: TopologyRecord = parse_topology(topology, format)?;
+    let tensors: Vec<StoredTensor> = WeightsReader::read_tensors(weights)?;
+    from_records(&topology.operators, &tensors)
+}
+
+// The weights file is written next to the topology file, with the same name and
+// the extension .weights. The format of the topology is chosen by its extension,
+// see TopologyFormat::from_path.
+pub fn save_graph(graph_operators: &[GraphOperator], topology_path: &Path) -> Result<(), Error> {
+    let weights_path: PathBuf = topology_path.with_extension("weights");
+    let weights_file_name: &str = weights_path
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "serialization::save_graph - {} has no usable file name",
+                    topology_path.display()
+                ),
+            )
+        })?;
+
+    let (topology, weights) = serialize_graph(
+        graph_operators,
+        TopologyFormat::from_path(topology_path),
+        weights_file_name,
+    )?;
+
+    if let Some(directory) = topology_path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(topology_path, topology)?;
+    fs::write(&weights_path, weights)?;
+
+    Ok(())
+}
+
+pub fn load_graph(topology_path: &Path) -> Result<Vec<GraphOperator>, Error> {
+    let format: TopologyFormat = TopologyFormat::from_path(topology_path);
+    let topology: TopologyRecord = parse_topology(&fs::read_to_string(topology_path)?, format)?;
+
+    let weights_path: PathBuf = match topology_path.parent() {
+        Some(directory) => directory.join(&topology.weights),
+        None => PathBuf::from(&topology.weights),
+    };
+    let weights: Vec<u8> = fs::read(weights_path)?;
+    let tensors: Vec<StoredTensor> = WeightsReader::read_tensors(&weights)?;
+
+    from_records(&topology.operators, &tensors)
+}
@@ -0,0 +1,246 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Error, ErrorKind},
+        path::PathBuf,
+    };
+
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        graph::{
+            graph_runner::GraphRunner,
+            quantization::quantize_graph_operators,
+            serialization::{
+                deserialize_graph, load_graph, save_graph, serialize_graph, TopologyFormat,
+            },
+        },
+        shared::{
+            activation::ActivationFunction, graph_operators::GraphOperator,
+            quantized_tensor2d::QuantizationGranularity, tensor2d::Tensor2D,
+            tensor2d_test_utilities::random_tensor,
+        },
+    };
+
+    // Uses every kind of operator with a tensor or a name in it
+    fn residual_graph(rng: &mut ChaCha8Rng) -> Vec<GraphOperator> {
+        vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(rng, 4, 8),
+            },
+            GraphOperator::Store {
+                name: "x".to_string(),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(rng, 8, 8),
+                bias: random_tensor(rng, 4, 8),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::LinearAddFused {
+                weights: random_tensor(rng, 8, 8),
+                bias: random_tensor(rng, 4, 8),
+                name: "x".to_string(),
+            },
+            GraphOperator::Store {
+                name: "block".to_string(),
+            },
+            GraphOperator::Load {
+                name: "x".to_string(),
+            },
+            GraphOperator::Concat {
+                name: "block".to_string(),
+            },
+            GraphOperator::LinearReLUSoftmaxFused {
+                weights: random_tensor(rng, 16, 3),
+                bias: random_tensor(rng, 4, 3),
+            },
+            GraphOperator::SoftmaxCrossEntropy {
+                labels: vec![0, 2, 1, 1],
+            },
+            GraphOperator::DeviceToHost,
+        ]
+    }
+
+    fn mlp(rng: &mut ChaCha8Rng) -> Vec<GraphOperator> {
+        vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(rng, 4, 8),
+            },
+            GraphOperator::Linear {
+                weights: random_tensor(rng, 8, 16),
+                bias: random_tensor(rng, 4, 16),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Linear {
+                weights: random_tensor(rng, 16, 4),
+                bias: random_tensor(rng, 4, 4),
+            },
+            GraphOperator::MeanSquaredError {
+                target: random_tensor(rng, 4, 4),
+            },
+            GraphOperator::DeviceToHost,
+        ]
+    }
+
+    // Tensor2D has no PartialEq, but every field shows up in the debug output
+    fn assert_same_graph(expected: &[GraphOperator], found: &[GraphOperator]) {
+        assert_eq!(format!("{:?}", expected), format!("{:?}", found));
+    }
+
+    // The loaded graph has to compute exactly the same output, not just a close one
+    fn assert_same_output(expected: &[GraphOperator], found: &[GraphOperator]) {
+        let expected: Tensor2D = GraphRunner::new(&expected.to_vec(), false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        let found: Tensor2D = GraphRunner::new(&found.to_vec(), false, false)
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(expected.data, found.data);
+    }
+
+    fn temporary_path(file_name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join("computational_graphs_serialization_test")
+            .join(file_name)
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(0);
+        for graph_operators in [residual_graph(&mut rng), mlp(&mut rng)] {
+            for format in [TopologyFormat::Json, TopologyFormat::Toml] {
+                let (topology, weights) =
+                    serialize_graph(&graph_operators, format, "graph.weights").unwrap();
+                let loaded: Vec<GraphOperator> =
+                    deserialize_graph(&topology, format, &weights).unwrap();
+                assert_same_graph(&graph_operators, &loaded);
+                assert_same_output(&graph_operators, &loaded);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_quantized() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(1);
+        let graph_operators: Vec<GraphOperator> =
+            quantize_graph_operators(&mlp(&mut rng), &[], QuantizationGranularity::PerChannel);
+
+        let (topology, weights) =
+            serialize_graph(&graph_operators, TopologyFormat::Json, "graph.weights").unwrap();
+        let loaded: Vec<GraphOperator> =
+            deserialize_graph(&topology, TopologyFormat::Json, &weights).unwrap();
+        assert_same_graph(&graph_operators, &loaded);
+        assert_same_output(&graph_operators, &loaded);
+    }
+
+    // The functions are nested records, and LeakyReLU has its alpha
+    #[test]
+    fn round_trip_activations() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(4);
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: random_tensor(&mut rng, 4, 8),
+            },
+            GraphOperator::LinearActivationFused {
+                weights: random_tensor(&mut rng, 8, 16),
+                bias: random_tensor(&mut rng, 4, 16),
+                function: ActivationFunction::LeakyReLU { alpha: 0.05 },
+            },
+            GraphOperator::Activation {
+                function: ActivationFunction::GELUTanh,
+            },
+            GraphOperator::DeviceToHost,
+        ];
+
+        for format in [TopologyFormat::Json, TopologyFormat::Toml] {
+            let (topology, weights) =
+                serialize_graph(&graph_operators, format, "graph.weights").unwrap();
+            let loaded: Vec<GraphOperator> =
+                deserialize_graph(&topology, format, &weights).unwrap();
+            assert_same_graph(&graph_operators, &loaded);
+            assert_same_output(&graph_operators, &loaded);
+        }
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(2);
+        let graph_operators: Vec<GraphOperator> = residual_graph(&mut rng);
+
+        for file_name in ["residual.json", "residual.toml"] {
+            let path: PathBuf = temporary_path(file_name);
+            save_graph(&graph_operators, &path).unwrap();
+            assert!(path.with_extension("weights").exists());
+
+            let loaded: Vec<GraphOperator> = load_graph(&path).unwrap();
+            assert_same_graph(&graph_operators, &loaded);
+            assert_same_output(&graph_operators, &loaded);
+        }
+    }
+
+    #[test]
+    fn invalid_files() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(3);
+        let (topology, weights) =
+            serialize_graph(&mlp(&mut rng), TopologyFormat::Json, "graph.weights").unwrap();
+
+        let mut wrong_magic: Vec<u8> = weights.clone();
+        wrong_magic[0] = b'X';
+        assert!(deserialize_graph(&topology, TopologyFormat::Json, &wrong_magic).is_err());
+
+        let truncated: &[u8] = &weights[0..weights.len() - 1];
+        assert!(deserialize_graph(&topology, TopologyFormat::Json, truncated).is_err());
+
+        let mut trailing: Vec<u8> = weights.clone();
+        trailing.push(0);
+        assert!(deserialize_graph(&topology, TopologyFormat::Json, &trailing).is_err());
+
+        // The weights of another graph don't have enough tensors
+        let (_, too_few_weights) = serialize_graph(
+            &[GraphOperator::HostToDevice {
+                input: random_tensor(&mut rng, 4, 8),
+            }],
+            TopologyFormat::Json,
+            "graph.weights",
+        )
+        .unwrap();
+        assert!(deserialize_graph(&topology, TopologyFormat::Json, &too_few_weights).is_err());
+
+        assert!(deserialize_graph(&topology, TopologyFormat::Toml, &weights).is_err());
+        assert!(load_graph(&temporary_path("missing.json")).is_err());
+    }
+
+    #[test]
+    fn too_large_tensors() {
+        // A header for 2^31 x 2^31 f32 without any data, the number of bytes overflows a usize
+        for (row_count, column_count) in [(1u32 << 31, 1u32 << 31), (u32::MAX, u32::MAX)] {
+            let mut weights: Vec<u8> = b"NGBW".to_vec();
+            for value in [1, 1, 0, row_count, column_count] {
+                weights.extend_from_slice(&value.to_le_bytes());
+            }
+            let error: Error = deserialize_graph(
+                r#"{ "version": 1, "weights": "graph.weights", "operators": [] }"#,
+                TopologyFormat::Json,
+                &weights,
+            )
+            .unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+        }
+
+        // The dimensions don't fit in the u32 of the header. Only the header is written
+        // before the dimensions are checked, so the tensor doesn't need any data.
+        let graph_operators: Vec<GraphOperator> = vec![GraphOperator::HostToDevice {
+            input: Tensor2D {
+                data: Vec::<f32>::new(),
+                row_count: 1 << 32,
+                column_count: 1,
+            },
+        }];
+        let error: Error =
+            serialize_graph(&graph_operators, TopologyFormat::Json, "graph.weights").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+}
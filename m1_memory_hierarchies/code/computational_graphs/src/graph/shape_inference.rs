@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::mem::size_of;
+
+use crate::shared::graph_operators::GraphOperator;
+use crate::shared::graph_operators::GraphOperator::*;
+
+use super::fusion::OperatorKind;
+use super::graph_error::GraphError;
+use super::graph_validation::validate_graph_operators;
+
+// Shape inference as a single pass from the start of the graph to the end. Every
+// operator is annotated with the shapes it reads and writes, which is all the runners
+// need to allocate their buffers, and with how much work it does. The work is counted
+// as floating point operations and as the bytes moved to and from memory, assuming
+// nothing stays in cache between operators. Dividing the first by the second gives the
+// arithmetic intensity, which together with the peak compute and bandwidth of the
+// hardware gives a roofline style estimate of how long the graph should take.
+//
+// The counts are estimates. A max, an exponential or a comparison all count as a
+// single operation, and the int8 operators count their integer operations as well.
+
+// (row_count, column_count)
+pub type Shape = (usize, usize);
+
+const F32_BYTES: usize = size_of::<f32>();
+const INT8_BYTES: usize = size_of::<i8>();
+
+// ReLU is a single max per element
+const RELU_FLOPS_PER_ELEMENT: usize = 1;
+// Finding the max, subtracting it, the exponential, the sum and the division
+const SOFTMAX_FLOPS_PER_ELEMENT: usize = 5;
+// The difference, squaring it and adding it to the sum
+const MEAN_SQUARED_ERROR_FLOPS_PER_ELEMENT: usize = 3;
+// Quantizing is a division and a rounding, dequantizing the accumulator is a multiplication
+const QUANTIZE_FLOPS_PER_ELEMENT: usize = 2;
+const DEQUANTIZE_FLOPS_PER_ELEMENT: usize = 1;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NodeShape {
+    pub operator: OperatorKind,
+    // The input of the operator first, followed by every other tensor it reads,
+    // such as the weights and the bias, the target of a loss or a named tensor
+    pub input_shapes: Vec<Shape>,
+    pub output_shape: Shape,
+    pub flops: usize,
+    // Everything read and written by the operator
+    pub bytes: usize,
+}
+
+impl NodeShape {
+    // FLOPs per byte
+    pub fn arithmetic_intensity(&self) -> f64 {
+        if self.bytes == 0 {
+            return 0.0;
+        }
+        self.flops as f64 / self.bytes as f64
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ShapeReport {
+    // One per operator, in the order of the graph the report was made from
+    pub nodes: Vec<NodeShape>,
+}
+
+impl ShapeReport {
+    pub fn total_flops(&self) -> usize {
+        self.nodes.iter().map(|node| node.flops).sum()
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.nodes.iter().map(|node| node.bytes).sum()
+    }
+
+    // FLOPs per byte for the entire graph. Below the ridge point of the hardware,
+    // its peak FLOPs divided by its peak bandwidth, the graph is memory bound.
+    pub fn arithmetic_intensity(&self) -> f64 {
+        let total_bytes: usize = self.total_bytes();
+        if total_bytes == 0 {
+            return 0.0;
+        }
+        self.total_flops() as f64 / total_bytes as f64
+    }
+
+    // The roofline model, every operator takes as long as its FLOPs at peak compute
+    // or its bytes at peak bandwidth, whichever is slower. This is a lower bound,
+    // it assumes the operators are perfectly implemented and have no launch overhead.
+    pub fn roofline_seconds(&self, peak_flops_per_second: f64, peak_bytes_per_second: f64) -> f64 {
+        self.nodes
+            .iter()
+            .map(|node| {
+                let compute_seconds: f64 = node.flops as f64 / peak_flops_per_second;
+                let memory_seconds: f64 = node.bytes as f64 / peak_bytes_per_second;
+                compute_seconds.max(memory_seconds)
+            })
+            .sum()
+    }
+}
+
+impl fmt::Display for ShapeReport {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            formatter,
+            "{:>5} {:<20} {:>12} {:>12} {:>14} {:>14} {:>10}",
+            "Index", "Operator", "Input", "Output", "FLOPs", "Bytes", "FLOPs/byte"
+        )?;
+        for (index, node) in self.nodes.iter().enumerate() {
+            let input: String = node
+                .input_shapes
+                .first()
+                .map_or(String::from("-"), |shape| format_shape(*shape));
+            writeln!(
+                formatter,
+                "{:>5} {:<20} {:>12} {:>12} {:>14} {:>14} {:>10.2}",
+                index,
+                format!("{:?}", node.operator),
+                input,
+                format_shape(node.output_shape),
+                node.flops,
+                node.bytes,
+                node.arithmetic_intensity()
+            )?;
+        }
+        write!(
+            formatter,
+            "{:>5} {:<20} {:>12} {:>12} {:>14} {:>14} {:>10.2}",
+            "",
+            "Total",
+            "",
+            "",
+            self.total_flops(),
+            self.total_bytes(),
+            self.arithmetic_intensity()
+        )
+    }
+}
+
+fn format_shape(shape: Shape) -> String {
+    format!("{}x{}", shape.0, shape.1)
+}
+
+fn element_count(shape: Shape) -> usize {
+    shape.0 * shape.1
+}
+
+// The FLOPs and bytes of a linear operator without anything fused into it.
+// input is m x k, weights are k x n, the bias and the output are m x n.
+fn linear_cost(input: Shape, weights: Shape, weights_element_bytes: usize) -> (usize, usize) {
+    let (m, k): Shape = input;
+    let n: usize = weights.1;
+    let flops: usize = 2 * m * k * n + m * n;
+    let bytes: usize = F32_BYTES * m * k + weights_element_bytes * k * n + 2 * F32_BYTES * m * n;
+    (flops, bytes)
+}
+
+// The output of a linear operator is (input rows, weights columns), which the bias is added to
+fn linear_output_shape(
+    node: usize,
+    current: Shape,
+    weights_shape: Shape,
+    bias_shape: Shape,
+) -> Result<Shape, GraphError> {
+    let output_shape: Shape = (current.0, weights_shape.1);
+    if bias_shape != output_shape {
+        return Err(GraphError::DimensionMismatch {
+            node,
+            expected: output_shape,
+            found: bias_shape,
+        });
+    }
+    Ok(output_shape)
+}
+
+fn node_shape(
+    node: usize,
+    operator: &GraphOperator,
+    current: Shape,
+    named_shapes: &HashMap<String, Shape>,
+) -> Result<NodeShape, GraphError> {
+    let elements: usize = element_count(current);
+    let named_shape = |name: &String| -> Shape { named_shapes[name] };
+
+    let (input_shapes, output_shape, flops, bytes): (Vec<Shape>, Shape, usize, usize) =
+        match operator {
+            Empty => (vec![], current, 0, 0),
+            // The transfers move their tensor between the host and the device
+            HostToDevice { input } => {
+                let shape: Shape = (input.row_count, input.column_count);
+                (vec![], shape, 0, F32_BYTES * element_count(shape))
+            }
+            DeviceToHost => (vec![current], current, 0, F32_BYTES * elements),
+            Linear { weights, bias }
+            | LinearReLUFused { weights, bias }
+            | LinearReLUSoftmaxFused { weights, bias } => {
+                let weights_shape: Shape = (weights.row_count, weights.column_count);
+                let output_shape: Shape = linear_output_shape(
+                    node,
+                    current,
+                    weights_shape,
+                    (bias.row_count, bias.column_count),
+                )?;
+                let (mut flops, bytes): (usize, usize) =
+                    linear_cost(current, weights_shape, F32_BYTES);
+                if let LinearReLUFused { .. } | LinearReLUSoftmaxFused { .. } = operator {
+                    flops += RELU_FLOPS_PER_ELEMENT * element_count(output_shape);
+                }
+                if let LinearReLUSoftmaxFused { .. } = operator {
+                    flops += SOFTMAX_FLOPS_PER_ELEMENT * element_count(output_shape);
+                }
+                (
+                    vec![current, weights_shape, output_shape],
+                    output_shape,
+                    flops,
+                    bytes,
+                )
+            }
+            ReLU => (
+                vec![current],
+                current,
+                RELU_FLOPS_PER_ELEMENT * elements,
+                2 * F32_BYTES * elements,
+            ),
+            Softmax => (
+                vec![current],
+                current,
+                SOFTMAX_FLOPS_PER_ELEMENT * elements,
+                2 * F32_BYTES * elements,
+            ),
+            MeanSquaredError { target } => (
+                vec![current, (target.row_count, target.column_count)],
+                (1, 1),
+                MEAN_SQUARED_ERROR_FLOPS_PER_ELEMENT * elements,
+                F32_BYTES * (2 * elements + 1),
+            ),
+            // The labels are stored as one f32 per row, see Tensor2D::from_labels.
+            // On top of the softmax, every row takes the logarithm of its label.
+            SoftmaxCrossEntropy { labels } => (
+                vec![current, (labels.len(), 1)],
+                (1, 1),
+                SOFTMAX_FLOPS_PER_ELEMENT * elements + 2 * labels.len(),
+                F32_BYTES * (elements + labels.len() + 1),
+            ),
+            // The input is quantized into a scratch buffer, which is written and read again
+            LinearInt8 { weights, bias, .. } | LinearReLUInt8Fused { weights, bias, .. } => {
+                let weights_shape: Shape = (weights.tensor.row_count, weights.tensor.column_count);
+                let output_shape: Shape = linear_output_shape(
+                    node,
+                    current,
+                    weights_shape,
+                    (bias.row_count, bias.column_count),
+                )?;
+                let (mut flops, mut bytes): (usize, usize) =
+                    linear_cost(current, weights_shape, INT8_BYTES);
+                flops += QUANTIZE_FLOPS_PER_ELEMENT * elements
+                    + DEQUANTIZE_FLOPS_PER_ELEMENT * element_count(output_shape);
+                bytes += 2 * INT8_BYTES * elements;
+                if let LinearReLUInt8Fused { .. } = operator {
+                    flops += RELU_FLOPS_PER_ELEMENT * element_count(output_shape);
+                }
+                (
+                    vec![current, weights_shape, output_shape],
+                    output_shape,
+                    flops,
+                    bytes,
+                )
+            }
+            // Store and Load only change which buffer is pointed to
+            Store { .. } => (vec![current], current, 0, 0),
+            Load { name } => (vec![named_shape(name)], named_shape(name), 0, 0),
+            Add { name } | AddReLUFused { name } => {
+                let flops_per_element: usize = if let AddReLUFused { .. } = operator {
+                    1 + RELU_FLOPS_PER_ELEMENT
+                } else {
+                    1
+                };
+                (
+                    vec![current, named_shape(name)],
+                    current,
+                    flops_per_element * elements,
+                    3 * F32_BYTES * elements,
+                )
+            }
+            // A copy, so there is nothing to compute
+            Concat { name } => {
+                let other: Shape = named_shape(name);
+                let output_shape: Shape = (current.0, current.1 + other.1);
+                (
+                    vec![current, other],
+                    output_shape,
+                    0,
+                    2 * F32_BYTES * element_count(output_shape),
+                )
+            }
+            LinearAddFused {
+                weights,
+                bias,
+                name,
+            } => {
+                let weights_shape: Shape = (weights.row_count, weights.column_count);
+                let output_shape: Shape = linear_output_shape(
+                    node,
+                    current,
+                    weights_shape,
+                    (bias.row_count, bias.column_count),
+                )?;
+                let (flops, bytes): (usize, usize) = linear_cost(current, weights_shape, F32_BYTES);
+                (
+                    vec![current, weights_shape, output_shape, named_shape(name)],
+                    output_shape,
+                    flops + element_count(output_shape),
+                    bytes + F32_BYTES * element_count(output_shape),
+                )
+            }
+            Activation { function } => (
+                vec![current],
+                current,
+                function.flops_per_element() * elements,
+                2 * F32_BYTES * elements,
+            ),
+            LinearActivationFused {
+                weights,
+                bias,
+                function,
+            } => {
+                let weights_shape: Shape = (weights.row_count, weights.column_count);
+                let output_shape: Shape = linear_output_shape(
+                    node,
+                    current,
+                    weights_shape,
+                    (bias.row_count, bias.column_count),
+                )?;
+                let (flops, bytes): (usize, usize) = linear_cost(current, weights_shape, F32_BYTES);
+                (
+                    vec![current, weights_shape, output_shape],
+                    output_shape,
+                    flops + function.flops_per_element() * element_count(output_shape),
+                    bytes,
+                )
+            }
+        };
+
+    Ok(NodeShape {
+        operator: OperatorKind::of(operator),
+        input_shapes,
+        output_shape,
+        flops,
+        bytes,
+    })
+}
+
+// The graph is validated first, after which every shape is known to be consistent
+pub fn infer_shapes(graph_operators: &[GraphOperator]) -> Result<ShapeReport, GraphError> {
+    validate_graph_operators(graph_operators)?;
+
+    let mut report: ShapeReport = ShapeReport::default();
+    let mut current: Shape = (0, 0);
+    let mut named_shapes: HashMap<String, Shape> = HashMap::<String, Shape>::new();
+    for (operator_index, operator) in graph_operators.iter().enumerate() {
+        let node: NodeShape = node_shape(operator_index, operator, current, &named_shapes)?;
+        if let Store { name } = operator {
+            named_shapes.insert(name.clone(), current);
+        }
+        current = node.output_shape;
+        report.nodes.push(node);
+    }
+
+    Ok(report)
+}
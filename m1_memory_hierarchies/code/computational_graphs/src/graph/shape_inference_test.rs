@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        graph::{
+            fusion::OperatorKind,
+            graph_error::GraphError,
+            graph_runner::GraphRunner,
+            shape_inference::{infer_shapes, NodeShape, ShapeReport},
+        },
+        shared::{graph_operators::GraphOperator, tensor2d::Tensor2D},
+    };
+
+    // 2x3 -> Linear -> 2x4 -> ReLU -> Linear -> 2x5 -> Softmax
+    fn mlp() -> Vec<GraphOperator> {
+        vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(0.5, 2, 3),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(0.1, 3, 4),
+                bias: Tensor2D::new(0.0, 2, 4),
+            },
+            GraphOperator::ReLU,
+            GraphOperator::Linear {
+                weights: Tensor2D::new(0.1, 4, 5),
+                bias: Tensor2D::new(0.0, 2, 5),
+            },
+            GraphOperator::Softmax,
+            GraphOperator::DeviceToHost,
+        ]
+    }
+
+    #[test]
+    fn mlp_shapes() {
+        let report: ShapeReport = infer_shapes(&mlp()).unwrap();
+        assert_eq!(report.nodes.len(), 6);
+
+        let output_shapes: Vec<(usize, usize)> =
+            report.nodes.iter().map(|node| node.output_shape).collect();
+        assert_eq!(
+            output_shapes,
+            vec![(2, 3), (2, 4), (2, 4), (2, 5), (2, 5), (2, 5)]
+        );
+
+        // 2 * m * k * n for the matrix multiplication and m * n for the bias
+        let linear: &NodeShape = &report.nodes[1];
+        assert_eq!(linear.operator, OperatorKind::Linear);
+        assert_eq!(linear.input_shapes, vec![(2, 3), (3, 4), (2, 4)]);
+        assert_eq!(linear.flops, 2 * 2 * 3 * 4 + 2 * 4);
+        // The input, the weights, the bias and the output
+        assert_eq!(linear.bytes, 4 * (2 * 3 + 3 * 4 + 2 * 4 + 2 * 4));
+
+        let flops: Vec<usize> = report.nodes.iter().map(|node| node.flops).collect();
+        assert_eq!(flops, vec![0, 56, 8, 90, 50, 0]);
+        let bytes: Vec<usize> = report.nodes.iter().map(|node| node.bytes).collect();
+        assert_eq!(bytes, vec![24, 136, 64, 192, 80, 40]);
+        assert_eq!(report.total_flops(), 204);
+        assert_eq!(report.total_bytes(), 536);
+    }
+
+    #[test]
+    fn named_tensors() {
+        let graph_operators: Vec<GraphOperator> = vec![
+            GraphOperator::HostToDevice {
+                input: Tensor2D::new(0.5, 2, 3),
+            },
+            GraphOperator::Store {
+                name: String::from("residual"),
+            },
+            GraphOperator::Linear {
+                weights: Tensor2D::new(0.1, 3, 3),
+                bias: Tensor2D::new(0.0, 2, 3),
+            },
+            GraphOperator::Concat {
+                name: String::from("residual"),
+            },
+            GraphOperator::Load {
+                name: String::from("residual"),
+            },
+            GraphOperator::DeviceToHost,
+        ];
+
+        let report: ShapeReport = infer_shapes(&graph_operators).unwrap();
+        let concat: &NodeShape = &report.nodes[3];
+        assert_eq!(concat.input_shapes, vec![(2, 3), (2, 3)]);
+        assert_eq!(concat.output_shape, (2, 6));
+        assert_eq!(concat.flops, 0);
+        assert_eq!(concat.bytes, 2 * 4 * 2 * 6);
+
+        // Load goes back to the shape of the stored tensor
+        assert_eq!(report.nodes[4].output_shape, (2, 3));
+        assert_eq!(report.nodes[5].output_shape, (2, 3));
+    }
+
+    #[test]
+    fn invalid_graph() {
+        let mut graph_operators: Vec<GraphOperator> = mlp();
+        graph_operators[3] = GraphOperator::Linear {
+            weights: Tensor2D::new(0.1, 3, 5),
+            bias: Tensor2D::new(0.0, 2, 5),
+        };
+        assert_eq!(
+            infer_shapes(&graph_operators),
+            Err(GraphError::DimensionMismatch {
+                node: 3,
+                expected: (2, 3),
+                found: (2, 4),
+            })
+        );
+    }
+
+    // The output has the rows of the input and the columns of the weights, the bias has to match
+    #[test]
+    fn invalid_bias() {
+        for bias in [Tensor2D::new(0.0, 2, 5), Tensor2D::new(0.0, 5, 4)] {
+            let found: (usize, usize) = (bias.row_count, bias.column_count);
+            let mut graph_operators: Vec<GraphOperator> = mlp();
+            graph_operators[1] = GraphOperator::Linear {
+                weights: Tensor2D::new(0.1, 3, 4),
+                bias,
+            };
+            assert_eq!(
+                infer_shapes(&graph_operators),
+                Err(GraphError::DimensionMismatch {
+                    node: 1,
+                    expected: (2, 4),
+                    found,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn fused_runner() {
+        let graph_runner: GraphRunner = GraphRunner::new(&mlp(), true, false).unwrap();
+        let report: &ShapeReport = graph_runner.shape_report();
+
+        // The report is made after fusion, the first Linear and the ReLU are a single node
+        assert_eq!(report.nodes.len(), 5);
+        assert_eq!(report.nodes[1].operator, OperatorKind::LinearReLU);
+        assert_eq!(report.nodes[1].flops, 56 + 8);
+        assert_eq!(report.nodes[1].bytes, 136);
+        assert_eq!(report.nodes.last().unwrap().output_shape, (2, 5));
+    }
+
+    #[test]
+    fn roofline() {
+        let report: ShapeReport = infer_shapes(&mlp()).unwrap();
+        assert!((report.arithmetic_intensity() - 204.0 / 536.0).abs() < 0.00001);
+
+        // With compute for free, the graph takes as long as moving its bytes
+        let memory_bound: f64 = report.roofline_seconds(f64::INFINITY, 536.0);
+        assert!((memory_bound - 1.0).abs() < 0.00001);
+
+        // With infinite bandwidth, the graph takes as long as its FLOPs
+        let compute_bound: f64 = report.roofline_seconds(204.0, f64::INFINITY);
+        assert!((compute_bound - 1.0).abs() < 0.00001);
+    }
+}
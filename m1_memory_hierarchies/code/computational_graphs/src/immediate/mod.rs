@@ -1,3 +1,4 @@
 pub mod nodes;
+pub mod nodes_interpreter;
 pub mod nodes_test;
 pub mod runner;
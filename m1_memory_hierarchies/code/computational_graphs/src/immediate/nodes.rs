@@ -347,12 +347,59 @@ pub async fn softmax(
     input_device: &Tensor2DGPU,
     output_device: &mut Tensor2DGPU,
 ) {
-    let uniform_device: SoftmaxUniform =
-        SoftmaxUniform::new(gpu_handles, "Softmax Uniform", input_device.len());
-    let global_max_device: Tensor2DGPU =
-        Tensor2DGPU::new(gpu_handles, "Softmax Global Max", 0.0, 1, 1);
-    let global_offset_device: Tensor2DGPU =
-        Tensor2DGPU::new(gpu_handles, "Softmax Global Offset", 0.0, 1, 1);
+    softmax_with_map(gpu_handles, input_device, output_device, "map").await;
+}
+
+pub async fn log_softmax_from_tensor_2d(
+    gpu_handles: &GPUHandles,
+    input: &Tensor2D,
+    output: &mut Tensor2D,
+) {
+    let input_device: Tensor2DGPU = Tensor2DGPU::from_tensor2d(gpu_handles, "input", input);
+    let mut output_device: Tensor2DGPU = Tensor2DGPU::from_tensor2d(gpu_handles, "output", output);
+    log_softmax(gpu_handles, &input_device, &mut output_device).await;
+    if output_device.live_data_on_device {
+        output_device.retrieve_results().await;
+    }
+    *output = output_device.data.clone();
+}
+
+// Shares the max and sum passes with softmax, only the final map is different
+pub async fn log_softmax(
+    gpu_handles: &GPUHandles,
+    input_device: &Tensor2DGPU,
+    output_device: &mut Tensor2DGPU,
+) {
+    softmax_with_map(gpu_handles, input_device, output_device, "map_log").await;
+}
+
+// map_entry_point is either map for softmax or map_log for log-softmax
+async fn softmax_with_map(
+    gpu_handles: &GPUHandles,
+    input_device: &Tensor2DGPU,
+    output_device: &mut Tensor2DGPU,
+    map_entry_point: &str,
+) {
+    let uniform_device: SoftmaxUniform = SoftmaxUniform::new(
+        gpu_handles,
+        "Softmax Uniform",
+        input_device.row_count,
+        input_device.column_count,
+    );
+    let row_max_device: Tensor2DGPU = Tensor2DGPU::new(
+        gpu_handles,
+        "Softmax Row Max",
+        0.0,
+        input_device.row_count,
+        1,
+    );
+    let row_sum_device: Tensor2DGPU = Tensor2DGPU::new(
+        gpu_handles,
+        "Softmax Row Sum",
+        0.0,
+        input_device.row_count,
+        1,
+    );
 
     let cs_module: ShaderModule =
         create_shader_module(gpu_handles, include_str!("../shared/shaders/softmax.wgsl"));
@@ -360,7 +407,7 @@ pub async fn softmax(
     let to_be_bound: Vec<(u32, BindingResource)> = vec![
         (0, uniform_device.storage_buffer.as_entire_binding()),
         (1, input_device.storage_buffer.as_entire_binding()),
-        (2, global_max_device.storage_buffer.as_entire_binding()),
+        (2, row_max_device.storage_buffer.as_entire_binding()),
     ];
 
     // Instantiates the bind group, once again specifying the binding of buffers.
@@ -380,14 +427,14 @@ pub async fn softmax(
         cpass.set_pipeline(&max_compute_pipeline);
         cpass.set_bind_group(0, &max_bind_group, &[]);
         cpass.insert_debug_marker("Softmax Immediate - Max");
-        cpass.dispatch_workgroups(1, 1, 1); // Number of cells to run, the (x,y,z) size of item being processed
+        cpass.dispatch_workgroups(input_device.row_count as u32, 1, 1); // One workgroup per row
     }
 
     let to_be_bound: Vec<(u32, BindingResource)> = vec![
         (0, uniform_device.storage_buffer.as_entire_binding()),
         (1, input_device.storage_buffer.as_entire_binding()),
-        (2, global_max_device.storage_buffer.as_entire_binding()),
-        (3, global_offset_device.storage_buffer.as_entire_binding()),
+        (2, row_max_device.storage_buffer.as_entire_binding()),
+        (3, row_sum_device.storage_buffer.as_entire_binding()),
     ];
     {
         let sum_compute_pipeline: ComputePipeline =
@@ -402,19 +449,20 @@ pub async fn softmax(
         cpass.set_pipeline(&sum_compute_pipeline);
         cpass.set_bind_group(0, &sum_bind_group, &[]);
         cpass.insert_debug_marker("Softmax Immediate - Sum");
-        cpass.dispatch_workgroups(1, 1, 1); // Number of cells to run, the (x,y,z) size of item being processed
+        cpass.dispatch_workgroups(input_device.row_count as u32, 1, 1); // One workgroup per row
     }
 
     let to_be_bound: Vec<(u32, BindingResource)> = vec![
         (0, uniform_device.storage_buffer.as_entire_binding()),
         (1, input_device.storage_buffer.as_entire_binding()),
-        (3, global_offset_device.storage_buffer.as_entire_binding()),
+        (2, row_max_device.storage_buffer.as_entire_binding()),
+        (3, row_sum_device.storage_buffer.as_entire_binding()),
         (4, output_device.storage_buffer.as_entire_binding()),
     ];
     let block_size: usize = 32;
     {
         let map_compute_pipeline: ComputePipeline =
-            create_compute_pipeline(gpu_handles, &cs_module, "map");
+            create_compute_pipeline(gpu_handles, &cs_module, map_entry_point);
         let map_bind_group_layout: BindGroupLayout = map_compute_pipeline.get_bind_group_layout(0);
         let map_bind_group: BindGroup =
             create_bind_group(gpu_handles, &map_bind_group_layout, to_be_bound);
@@ -597,12 +645,26 @@ pub async fn linear_relu_softmax_fused(
         bias,
         &intermediate,
     );
-    let softmax_uniform: SoftmaxUniform =
-        SoftmaxUniform::new(gpu_handles, "Softmax Uniform", intermediate.len());
-    let softmax_global_max: Tensor2DGPU =
-        Tensor2DGPU::new(gpu_handles, "Softmax Global Max", 0.0, 1, 1);
-    let softmax_global_offset: Tensor2DGPU =
-        Tensor2DGPU::new(gpu_handles, "Softmax Global Offset", 0.0, 1, 1);
+    let softmax_uniform: SoftmaxUniform = SoftmaxUniform::new(
+        gpu_handles,
+        "Softmax Uniform",
+        intermediate.row_count,
+        intermediate.column_count,
+    );
+    let softmax_row_max: Tensor2DGPU = Tensor2DGPU::new(
+        gpu_handles,
+        "Softmax Row Max",
+        0.0,
+        intermediate.row_count,
+        1,
+    );
+    let softmax_row_sum: Tensor2DGPU = Tensor2DGPU::new(
+        gpu_handles,
+        "Softmax Row Sum",
+        0.0,
+        intermediate.row_count,
+        1,
+    );
 
     let linear_cs_module: ShaderModule = create_shader_module(
         gpu_handles,
@@ -644,7 +706,7 @@ pub async fn linear_relu_softmax_fused(
         let to_be_bound: Vec<(u32, BindingResource)> = vec![
             (0, softmax_uniform.storage_buffer.as_entire_binding()),
             (1, intermediate.storage_buffer.as_entire_binding()),
-            (2, softmax_global_max.storage_buffer.as_entire_binding()),
+            (2, softmax_row_max.storage_buffer.as_entire_binding()),
         ];
 
         let max_compute_pipeline: ComputePipeline =
@@ -659,15 +721,15 @@ pub async fn linear_relu_softmax_fused(
         cpass.set_pipeline(&max_compute_pipeline);
         cpass.set_bind_group(0, &max_bind_group, &[]);
         cpass.insert_debug_marker("Softmax Immediate - Max");
-        cpass.dispatch_workgroups(1, 1, 1); // Number of cells to run, the (x,y,z) size of item being processed
+        cpass.dispatch_workgroups(intermediate.row_count as u32, 1, 1); // One workgroup per row
     }
 
     {
         let to_be_bound: Vec<(u32, BindingResource)> = vec![
             (0, softmax_uniform.storage_buffer.as_entire_binding()),
             (1, intermediate.storage_buffer.as_entire_binding()),
-            (2, softmax_global_max.storage_buffer.as_entire_binding()),
-            (3, softmax_global_offset.storage_buffer.as_entire_binding()),
+            (2, softmax_row_max.storage_buffer.as_entire_binding()),
+            (3, softmax_row_sum.storage_buffer.as_entire_binding()),
         ];
 
         let sum_compute_pipeline: ComputePipeline =
@@ -682,7 +744,7 @@ pub async fn linear_relu_softmax_fused(
         cpass.set_pipeline(&sum_compute_pipeline);
         cpass.set_bind_group(0, &sum_bind_group, &[]);
         cpass.insert_debug_marker("Softmax Immediate - Sum");
-        cpass.dispatch_workgroups(1, 1, 1); // Number of cells to run, the (x,y,z) size of item being processed
+        cpass.dispatch_workgroups(intermediate.row_count as u32, 1, 1); // One workgroup per row
     }
 
     let block_size: usize = 32;
@@ -690,7 +752,8 @@ pub async fn linear_relu_softmax_fused(
         let to_be_bound: Vec<(u32, BindingResource)> = vec![
             (0, softmax_uniform.storage_buffer.as_entire_binding()),
             (1, intermediate.storage_buffer.as_entire_binding()),
-            (3, softmax_global_offset.storage_buffer.as_entire_binding()),
+            (2, softmax_row_max.storage_buffer.as_entire_binding()),
+            (3, softmax_row_sum.storage_buffer.as_entire_binding()),
             (4, output.storage_buffer.as_entire_binding()),
         ];
 
@@ -0,0 +1,268 @@
+use crate::shader_interpreter::{device::InterpreterDevice, executor::InterpreterError};
+use crate::shared::{
+    tensor2d::Tensor2D,
+    tensor2d_gpu::{LinearDimensions, ReluDimensions, SoftmaxDimensions, SumElements, Tensor2DGPU},
+};
+
+// The functions of immediate::nodes, dispatched on the shader interpreter instead of the GPU,
+// with the same shaders, entry points, bindings and number of workgroups. The tensors stay
+// on the host, so there is nothing to transfer and nothing to wait for.
+
+const LINEAR_SHADER: &str = include_str!("../shared/shaders/linear.wgsl");
+const RELU_SHADER: &str = include_str!("../shared/shaders/relu.wgsl");
+const RELU_INLINE_SHADER: &str = include_str!("../shared/shaders/relu_inline.wgsl");
+const SUM_SHADER: &str = include_str!("../shared/shaders/sum.wgsl");
+const SOFTMAX_SHADER: &str = include_str!("../shared/shaders/softmax.wgsl");
+
+pub fn linear(
+    device: &mut InterpreterDevice,
+    entry_point: &str,
+    input: &Tensor2D,
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    output: &mut Tensor2D,
+) -> Result<(), InterpreterError> {
+    let block_size: usize = 8;
+    let launch_blocks_x: u32 = output.row_count.div_ceil(block_size) as u32;
+    let launch_blocks_y: u32 = output.column_count.div_ceil(block_size) as u32;
+
+    let mut dimensions: LinearDimensions = LinearDimensions {
+        data: [
+            input.row_count as u32,
+            input.column_count as u32,
+            weights.row_count as u32,
+            weights.column_count as u32,
+            bias.row_count as u32,
+            bias.column_count as u32,
+            output.row_count as u32,
+            output.column_count as u32,
+        ],
+    };
+    let mut input_data: Vec<f32> = input.data.clone();
+    let mut weights_data: Vec<f32> = weights.data.clone();
+    let mut bias_data: Vec<f32> = bias.data.clone();
+
+    device.dispatch(
+        "linear.wgsl",
+        LINEAR_SHADER,
+        entry_point,
+        [launch_blocks_x, launch_blocks_y, 1],
+        &mut [
+            (0, bytemuck::bytes_of_mut(&mut dimensions)),
+            (1, bytemuck::cast_slice_mut(&mut input_data)),
+            (2, bytemuck::cast_slice_mut(&mut weights_data)),
+            (3, bytemuck::cast_slice_mut(&mut bias_data)),
+            (4, bytemuck::cast_slice_mut(&mut output.data)),
+        ],
+    )
+}
+
+pub fn linear_from_tensor_2d(
+    device: &mut InterpreterDevice,
+    input: &Tensor2D,
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    output: &mut Tensor2D,
+) -> Result<(), InterpreterError> {
+    Tensor2DGPU::linear_assert(input, weights, bias, output);
+
+    linear(device, "main", input, weights, bias, output)
+}
+
+pub fn relu_from_tensor_2d(
+    device: &mut InterpreterDevice,
+    input: &Tensor2D,
+    output: &mut Tensor2D,
+) -> Result<(), InterpreterError> {
+    let mut dimensions: ReluDimensions = ReluDimensions {
+        data: [input.row_count as u32, input.column_count as u32],
+    };
+    let mut input_data: Vec<f32> = input.data.clone();
+
+    // The same launch as immediate::nodes::relu, one workgroup per element
+    device.dispatch(
+        "relu.wgsl",
+        RELU_SHADER,
+        "main",
+        [input.row_count as u32, input.column_count as u32, 1],
+        &mut [
+            (0, bytemuck::bytes_of_mut(&mut dimensions)),
+            (1, bytemuck::cast_slice_mut(&mut input_data)),
+            (2, bytemuck::cast_slice_mut(&mut output.data)),
+        ],
+    )
+}
+
+pub fn relu_inplace(
+    device: &mut InterpreterDevice,
+    data: &mut Tensor2D,
+) -> Result<(), InterpreterError> {
+    let mut dimensions: ReluDimensions = ReluDimensions {
+        data: [data.row_count as u32, data.column_count as u32],
+    };
+    let workgroup_count: [u32; 3] = [data.row_count as u32, data.column_count as u32, 1];
+
+    device.dispatch(
+        "relu_inline.wgsl",
+        RELU_INLINE_SHADER,
+        "main",
+        workgroup_count,
+        &mut [
+            (0, bytemuck::bytes_of_mut(&mut dimensions)),
+            (1, bytemuck::cast_slice_mut(&mut data.data)),
+        ],
+    )
+}
+
+// A single workgroup sums every element, see single_pass_sum in sum.wgsl
+pub fn sum_from_tensor_2d(
+    device: &mut InterpreterDevice,
+    input: &Tensor2D,
+) -> Result<f32, InterpreterError> {
+    let mut elements: SumElements = SumElements {
+        data: [input.len() as u32, 1],
+    };
+    let mut input_data: Vec<f32> = input.data.clone();
+    let mut output: Vec<f32> = vec![0.0; 1];
+
+    device.dispatch(
+        "sum.wgsl",
+        SUM_SHADER,
+        "single_pass_sum",
+        [1, 1, 1],
+        &mut [
+            (0, bytemuck::bytes_of_mut(&mut elements)),
+            (1, bytemuck::cast_slice_mut(&mut input_data)),
+            (2, bytemuck::cast_slice_mut(&mut output)),
+        ],
+    )?;
+
+    Ok(output[0])
+}
+
+pub fn softmax_from_tensor_2d(
+    device: &mut InterpreterDevice,
+    input: &Tensor2D,
+    output: &mut Tensor2D,
+) -> Result<(), InterpreterError> {
+    softmax_with_map(device, input, output, "map")
+}
+
+// Shares the max and sum passes with softmax, only the final map is different
+pub fn log_softmax_from_tensor_2d(
+    device: &mut InterpreterDevice,
+    input: &Tensor2D,
+    output: &mut Tensor2D,
+) -> Result<(), InterpreterError> {
+    softmax_with_map(device, input, output, "map_log")
+}
+
+// map_entry_point is either map for softmax or map_log for log-softmax. The dimensions are
+// those of input, output only has to have as many elements.
+fn softmax_with_map(
+    device: &mut InterpreterDevice,
+    input: &Tensor2D,
+    output: &mut Tensor2D,
+    map_entry_point: &str,
+) -> Result<(), InterpreterError> {
+    let mut dimensions: SoftmaxDimensions = SoftmaxDimensions {
+        data: [
+            input.len() as u32,
+            input.row_count as u32,
+            input.column_count as u32,
+            0,
+        ],
+    };
+    let mut input_data: Vec<f32> = input.data.clone();
+    let mut row_max: Vec<f32> = vec![0.0; input.row_count];
+    let mut row_sum: Vec<f32> = vec![0.0; input.row_count];
+
+    // One workgroup per row
+    device.dispatch(
+        "softmax.wgsl",
+        SOFTMAX_SHADER,
+        "single_pass_max",
+        [input.row_count as u32, 1, 1],
+        &mut [
+            (0, bytemuck::bytes_of_mut(&mut dimensions)),
+            (1, bytemuck::cast_slice_mut(&mut input_data)),
+            (2, bytemuck::cast_slice_mut(&mut row_max)),
+        ],
+    )?;
+    device.dispatch(
+        "softmax.wgsl",
+        SOFTMAX_SHADER,
+        "single_pass_sum",
+        [input.row_count as u32, 1, 1],
+        &mut [
+            (0, bytemuck::bytes_of_mut(&mut dimensions)),
+            (1, bytemuck::cast_slice_mut(&mut input_data)),
+            (2, bytemuck::cast_slice_mut(&mut row_max)),
+            (3, bytemuck::cast_slice_mut(&mut row_sum)),
+        ],
+    )?;
+
+    let block_size: usize = 32;
+    device.dispatch(
+        "softmax.wgsl",
+        SOFTMAX_SHADER,
+        map_entry_point,
+        [input.len().div_ceil(block_size) as u32, 1, 1],
+        &mut [
+            (0, bytemuck::bytes_of_mut(&mut dimensions)),
+            (1, bytemuck::cast_slice_mut(&mut input_data)),
+            (2, bytemuck::cast_slice_mut(&mut row_max)),
+            (3, bytemuck::cast_slice_mut(&mut row_sum)),
+            (4, bytemuck::cast_slice_mut(&mut output.data)),
+        ],
+    )
+}
+
+pub fn linear_relu_softmax_from_tensor_2d(
+    device: &mut InterpreterDevice,
+    input: &Tensor2D,
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    output: &mut Tensor2D,
+) -> Result<(), InterpreterError> {
+    Tensor2DGPU::linear_relu_softmax_assert(input, weights, bias, output);
+
+    let mut intermediate: Tensor2D = Tensor2D::new(0.0, bias.row_count, bias.column_count);
+    linear(device, "main", input, weights, bias, &mut intermediate)?;
+    relu_inplace(device, &mut intermediate)?;
+    softmax_from_tensor_2d(device, &intermediate, output)
+}
+
+pub fn linearrelu_softmax_from_tensor_2d(
+    device: &mut InterpreterDevice,
+    input: &Tensor2D,
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    output: &mut Tensor2D,
+) -> Result<(), InterpreterError> {
+    Tensor2DGPU::linear_relu_softmax_assert(input, weights, bias, output);
+
+    let mut intermediate: Tensor2D = Tensor2D::new(0.0, bias.row_count, bias.column_count);
+    linear(
+        device,
+        "main_with_relu",
+        input,
+        weights,
+        bias,
+        &mut intermediate,
+    )?;
+    softmax_from_tensor_2d(device, &intermediate, output)
+}
+
+// On the GPU, the fused version records every pass in a single command encoder instead of
+// one per function. The interpreter runs every dispatch as it comes, so the passes are
+// the same as those of linearrelu_softmax_from_tensor_2d.
+pub fn linear_relu_softmax_fused_from_tensor_2d(
+    device: &mut InterpreterDevice,
+    input: &Tensor2D,
+    weights: &Tensor2D,
+    bias: &Tensor2D,
+    output: &mut Tensor2D,
+) -> Result<(), InterpreterError> {
+    linearrelu_softmax_from_tensor_2d(device, input, weights, bias, output)
+}
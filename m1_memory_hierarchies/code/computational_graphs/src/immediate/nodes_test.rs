@@ -1,11 +1,13 @@
 #[cfg(test)]
 mod tests {
-    use crate::immediate::nodes::{
-        linear_from_tensor_2d_blocking, linear_relu_softmax_from_tensor_2d_blocking,
-        linear_relu_softmax_fused_from_tensor_2d_blocking,
-        linearrelu_softmax_from_tensor_2d_blocking, relu_from_tensor_2d, softmax_from_tensor_2d,
+    use crate::immediate::nodes;
+    use crate::immediate::nodes_interpreter::{
+        linear_from_tensor_2d, linear_relu_softmax_from_tensor_2d,
+        linear_relu_softmax_fused_from_tensor_2d, linearrelu_softmax_from_tensor_2d,
+        log_softmax_from_tensor_2d, relu_from_tensor_2d, softmax_from_tensor_2d,
         sum_from_tensor_2d,
     };
+    use crate::shader_interpreter::{device::InterpreterDevice, executor::InterpreterError};
     use crate::shared::gpu_utilities::{initialize_gpu, GPUHandles};
     use crate::shared::tensor2d::Tensor2D;
     use crate::shared::tensor2d_gpu::Tensor2DGPU;
@@ -58,12 +60,20 @@ mod tests {
         out
     }
 
+    // The kernels are run on the shader interpreter, so only the tests which are about
+    // the GPU itself need one. Those are ignored unless asked for with cargo test -- --ignored.
     fn linear_and_fused_test_function(
-        gpu_handles: &GPUHandles,
+        device: &mut InterpreterDevice,
         outer_dimension_range: usize,
         inner_dimension_range: usize,
         expected: fn(&Tensor2D, &Tensor2D, &Tensor2D, &mut Tensor2D),
-        test: fn(&GPUHandles, &Tensor2D, &Tensor2D, &Tensor2D, &mut Tensor2D),
+        test: fn(
+            &mut InterpreterDevice,
+            &Tensor2D,
+            &Tensor2D,
+            &Tensor2D,
+            &mut Tensor2D,
+        ) -> Result<(), InterpreterError>,
         is_fused: bool,
     ) {
         for outer_dimension_input in 1..outer_dimension_range {
@@ -91,7 +101,7 @@ mod tests {
                     let expected_result: f32 = output.sum();
                     println!("expected result: {:?}", expected_result);
 
-                    test(&gpu_handles, &input, &weights, &bias, &mut output);
+                    test(device, &input, &weights, &bias, &mut output).unwrap();
 
                     let result: f32 = output.sum();
                     println!("result: {:?}", result);
@@ -112,6 +122,7 @@ mod tests {
     }
 
     #[test]
+    #[ignore = "needs a GPU"]
     fn subtract() {
         let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
             .expect("Failed to get GPU handles in immediate::subtract() test");
@@ -139,8 +150,7 @@ mod tests {
 
     #[test]
     fn sum() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in immediate::sum() test");
+        let mut device: InterpreterDevice = InterpreterDevice::new();
 
         let outer_dimension_range: usize = 33;
         let inner_dimension_range: usize = 33;
@@ -150,7 +160,7 @@ mod tests {
                 let input: Tensor2D = Tensor2D::new(0.5, outer_dimension, inner_dimension);
                 let expected_result: f32 = input.sum();
 
-                let result: f32 = pollster::block_on(sum_from_tensor_2d(&gpu_handles, &input));
+                let result: f32 = sum_from_tensor_2d(&mut device, &input).unwrap();
                 let abs_result_difference: f32 = (expected_result - result).abs();
 
                 assert!(abs_result_difference < ERROR_TOLERANCE);
@@ -160,8 +170,7 @@ mod tests {
 
     #[test]
     fn softmax() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in immediate::softmax() test");
+        let mut device: InterpreterDevice = InterpreterDevice::new();
 
         let dimension_range: usize = 128;
 
@@ -170,7 +179,7 @@ mod tests {
             let expected_result: f32 = Tensor2D::softmax(&input).sum();
 
             let mut output: Tensor2D = Tensor2D::new(0.5, dimension, 1);
-            pollster::block_on(softmax_from_tensor_2d(&gpu_handles, &input, &mut output));
+            softmax_from_tensor_2d(&mut device, &input, &mut output).unwrap();
             let result: f32 = output.sum();
             let abs_result_difference: f32 = (expected_result - result).abs();
 
@@ -178,28 +187,63 @@ mod tests {
         }
     }
 
+    #[test]
+    fn softmax_large_magnitudes() {
+        let mut device: InterpreterDevice = InterpreterDevice::new();
+
+        // More columns than the 32 threads of a workgroup, and inputs
+        // which overflow exp() unless the max of the row is subtracted
+        for column_count in [1, 7, 32, 33, 100] {
+            let input: Tensor2D = Tensor2D::new(1.0e3, 5, column_count);
+            let expected: Tensor2D = Tensor2D::softmax(&input);
+
+            let mut output: Tensor2D = Tensor2D::new(0.0, 5, column_count);
+            softmax_from_tensor_2d(&mut device, &input, &mut output).unwrap();
+            for index in 0..expected.len() {
+                assert!(output.data[index].is_finite());
+                assert!((expected.data[index] - output.data[index]).abs() < ERROR_TOLERANCE);
+            }
+        }
+    }
+
+    #[test]
+    fn log_softmax() {
+        let mut device: InterpreterDevice = InterpreterDevice::new();
+
+        for column_count in [1, 7, 32, 33, 100] {
+            let input: Tensor2D = Tensor2D::new(1.0e3, 5, column_count);
+            let expected: Tensor2D = Tensor2D::log_softmax(&input);
+
+            let mut output: Tensor2D = Tensor2D::new(0.0, 5, column_count);
+            log_softmax_from_tensor_2d(&mut device, &input, &mut output).unwrap();
+            for index in 0..expected.len() {
+                let tolerance: f32 = ERROR_TOLERANCE * expected.data[index].abs().max(1.0);
+                assert!(output.data[index].is_finite());
+                assert!((expected.data[index] - output.data[index]).abs() < tolerance);
+            }
+        }
+    }
+
     #[test]
     fn linear() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in immediate::linear() test");
+        let mut device: InterpreterDevice = InterpreterDevice::new();
 
         let outer_dimension_range: usize = 8;
         let inner_dimension_range: usize = 8;
 
         linear_and_fused_test_function(
-            &gpu_handles,
+            &mut device,
             outer_dimension_range,
             inner_dimension_range,
             Tensor2D::linear_preallocated,
-            linear_from_tensor_2d_blocking,
+            linear_from_tensor_2d,
             false,
         );
     }
 
     #[test]
     fn relu() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in immediate::softmax() test");
+        let mut device: InterpreterDevice = InterpreterDevice::new();
 
         let dimension_range: usize = 128;
 
@@ -208,7 +252,7 @@ mod tests {
             let expected_result: f32 = Tensor2D::relu(&input).sum();
 
             let mut output: Tensor2D = Tensor2D::new(0.5, dimension, 1);
-            pollster::block_on(relu_from_tensor_2d(&gpu_handles, &input, &mut output));
+            relu_from_tensor_2d(&mut device, &input, &mut output).unwrap();
             let result: f32 = output.sum();
             let abs_result_difference: f32 = (expected_result - result).abs();
 
@@ -218,55 +262,89 @@ mod tests {
 
     #[test]
     fn linear_relu_softmax() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in immediate::linear_relu_softmax test");
+        let mut device: InterpreterDevice = InterpreterDevice::new();
 
         let outer_dimension_range: usize = 8;
         let inner_dimension_range: usize = 8;
 
         linear_and_fused_test_function(
-            &gpu_handles,
+            &mut device,
             outer_dimension_range,
             inner_dimension_range,
             Tensor2D::linear_relu_softmax_fused,
-            linear_relu_softmax_from_tensor_2d_blocking,
+            linear_relu_softmax_from_tensor_2d,
             true,
         );
     }
 
     #[test]
     fn linearrelu_softmax() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in immediate::linearrelu_softmax test");
+        let mut device: InterpreterDevice = InterpreterDevice::new();
 
         let outer_dimension_range: usize = 8;
         let inner_dimension_range: usize = 8;
 
         linear_and_fused_test_function(
-            &gpu_handles,
+            &mut device,
             outer_dimension_range,
             inner_dimension_range,
             Tensor2D::linear_relu_softmax_fused,
-            linearrelu_softmax_from_tensor_2d_blocking,
+            linearrelu_softmax_from_tensor_2d,
             true,
         );
     }
 
     #[test]
     fn linear_relu_softmax_fused() {
-        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
-            .expect("Failed to get GPU handles in immediate::linear_relu_softmax_fused test");
+        let mut device: InterpreterDevice = InterpreterDevice::new();
 
         let outer_dimension_range: usize = 8;
         let inner_dimension_range: usize = 8;
 
         linear_and_fused_test_function(
-            &gpu_handles,
+            &mut device,
             outer_dimension_range,
             inner_dimension_range,
             Tensor2D::linear_relu_softmax_fused,
-            linear_relu_softmax_fused_from_tensor_2d_blocking,
+            linear_relu_softmax_fused_from_tensor_2d,
             true,
         );
     }
+
+    // The interpreter is only a stand-in if it computes what the GPU computes
+    #[test]
+    #[ignore = "needs a GPU"]
+    fn gpu_matches_interpreter() {
+        let gpu_handles: GPUHandles = pollster::block_on(initialize_gpu(true))
+            .expect("Failed to get GPU handles in immediate::gpu_matches_interpreter() test");
+        let mut device: InterpreterDevice = InterpreterDevice::new();
+
+        let mut input: Tensor2D = Tensor2D::new(0.0, 5, 37);
+        for (index, element) in input.data.iter_mut().enumerate() {
+            *element = (index % 11) as f32 * 0.5 - 2.5;
+        }
+
+        let expected: f32 = sum_from_tensor_2d(&mut device, &input).unwrap();
+        let result: f32 = pollster::block_on(nodes::sum_from_tensor_2d(&gpu_handles, &input));
+        assert!((expected - result).abs() < ERROR_TOLERANCE * expected.abs().max(1.0));
+
+        let mut expected: Tensor2D = Tensor2D::new(0.0, 5, 37);
+        let mut output: Tensor2D = Tensor2D::new(0.0, 5, 37);
+        softmax_from_tensor_2d(&mut device, &input, &mut expected).unwrap();
+        pollster::block_on(nodes::softmax_from_tensor_2d(
+            &gpu_handles,
+            &input,
+            &mut output,
+        ));
+        let difference: Tensor2D = subtract_tensors(&expected, &output);
+        assert!(difference.data.iter().all(|x| x.abs() < ERROR_TOLERANCE));
+
+        relu_from_tensor_2d(&mut device, &input, &mut expected).unwrap();
+        pollster::block_on(nodes::relu_from_tensor_2d(
+            &gpu_handles,
+            &input,
+            &mut output,
+        ));
+        assert_eq!(expected.data, output.data);
+    }
 }
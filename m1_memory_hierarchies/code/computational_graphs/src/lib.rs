@@ -8,6 +8,7 @@
 mod graph;
 mod immediate;
 mod op_code_compiler;
+mod shader_interpreter;
 mod shared;
 mod cpu;
 
@@ -1 +1,3 @@
 pub mod runner;
+pub mod shader_generator;
+pub mod shader_generator_test;
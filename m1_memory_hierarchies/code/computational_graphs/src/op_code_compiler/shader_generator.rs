@@ -0,0 +1,244 @@
+use std::fmt::{self, Write};
+
+use crate::shared::activation::ActivationFunction;
+
+// Generates a single WGSL compute shader for a chain of operators. Every thread computes
+// one element of the output. The value is handed over from one op code to the next in
+// a generated variable, handover_0, handover_1 and so on, so the intermediate results
+// stay in registers instead of going through memory, like the fused operators of
+// graph::fusion, but for any chain rather than a fixed list of patterns.
+//
+// A linear operator needs a whole row of its input, which the thread computing an element
+// of its output doesn't have if it comes from an earlier op code. So a chain can only
+// have a linear operator as its first op code, everything after it is elementwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OpCode {
+    // The input times the weights plus the bias, like GraphOperator::Linear
+    Linear,
+    // Adds another tensor with the dimensions of the output
+    AddBias,
+    Scale { factor: f32 },
+    ReLU,
+    Activation { function: ActivationFunction },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShaderGenerationError {
+    EmptyChain,
+    // op_code is the index into the chain
+    MisplacedLinear { op_code: usize },
+    // WGSL has no literals for NaN and infinity
+    NonFiniteConstant { op_code: usize, value: f32 },
+}
+
+impl fmt::Display for ShaderGenerationError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderGenerationError::EmptyChain => {
+                write!(formatter, "a shader can't be generated for an empty chain")
+            }
+            ShaderGenerationError::MisplacedLinear { op_code } => write!(
+                formatter,
+                "op code {} is Linear, which is only allowed as the first op code of a chain",
+                op_code
+            ),
+            ShaderGenerationError::NonFiniteConstant { op_code, value } => write!(
+                formatter,
+                "op code {} has the constant {}, which can't be written in WGSL",
+                op_code, value
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShaderGenerationError {}
+
+#[derive(Clone, Debug)]
+pub struct GeneratedShader {
+    pub source: String,
+    // The name of the buffer at every binding in group 0. Binding 0 is the uniform,
+    // which has the layout of the one used by linear.wgsl, see LinearUniform.
+    pub bindings: Vec<String>,
+}
+
+pub const ENTRY_POINT: &str = "main";
+
+const UNIFORM: &str = "struct TensorDimensions {
+    input_row_count: u32,
+    input_column_count: u32,
+    weights_row_count: u32,
+    weights_column_count: u32,
+    bias_row_count: u32,
+    bias_column_count: u32,
+    output_row_count: u32,
+    output_column_count: u32,
+};
+";
+
+// The helper functions of activation.wgsl, which has to be kept in sync with this.
+// They are only emitted if an op code needs them.
+const ERF: &str = "fn erf(x: f32) -> f32 {
+    let t: f32 = 1.0 / (1.0 + 0.3275911 * abs(x));
+    let polynomial: f32 = t * (0.2548296 + t * (-0.28449674 + t * (1.4214138 + t * (-1.453152 + t * 1.0614054))));
+    return sign(x) * (1.0 - polynomial * exp(-x * x));
+}
+";
+
+const STABLE_SIGMOID: &str = "fn stable_sigmoid(x: f32) -> f32 {
+    if (0.0 <= x) {
+        return 1.0 / (1.0 + exp(-x));
+    }
+    let exponential: f32 = exp(x);
+    return exponential / (1.0 + exponential);
+}
+";
+
+const STABLE_TANH: &str = "fn stable_tanh(x: f32) -> f32 {
+    return tanh(clamp(x, -10.0, 10.0));
+}
+";
+
+// Floats are written with Debug, which always has a decimal point or an exponent,
+// so WGSL doesn't mistake them for integers
+fn float_literal(op_code: usize, value: f32) -> Result<String, ShaderGenerationError> {
+    if !value.is_finite() {
+        return Err(ShaderGenerationError::NonFiniteConstant { op_code, value });
+    }
+
+    Ok(format!("({:?})", value))
+}
+
+fn activation_expression(
+    op_code: usize,
+    function: ActivationFunction,
+    x: &str,
+) -> Result<String, ShaderGenerationError> {
+    let expression: String = match function {
+        ActivationFunction::GELUErf => format!("0.5 * {x} * (1.0 + erf({x} * 0.70710678))"),
+        ActivationFunction::GELUTanh => format!(
+            "0.5 * {x} * (1.0 + stable_tanh(0.7978846 * ({x} + 0.044715 * {x} * {x} * {x})))"
+        ),
+        ActivationFunction::SiLU => format!("{x} * stable_sigmoid({x})"),
+        ActivationFunction::Tanh => format!("stable_tanh({x})"),
+        ActivationFunction::Sigmoid => format!("stable_sigmoid({x})"),
+        ActivationFunction::LeakyReLU { alpha } => format!(
+            "select({} * {x}, {x}, 0.0 < {x})",
+            float_literal(op_code, alpha)?
+        ),
+    };
+
+    Ok(expression)
+}
+
+fn helper_functions(op_codes: &[OpCode]) -> String {
+    let uses = |functions: &[ActivationFunction]| {
+        op_codes.iter().any(|op_code| {
+            matches!(op_code, OpCode::Activation { function } if functions.contains(function))
+        })
+    };
+
+    let mut helpers: String = String::new();
+    if uses(&[ActivationFunction::GELUErf]) {
+        helpers.push_str(ERF);
+    }
+    if uses(&[ActivationFunction::SiLU, ActivationFunction::Sigmoid]) {
+        helpers.push_str(STABLE_SIGMOID);
+    }
+    if uses(&[ActivationFunction::GELUTanh, ActivationFunction::Tanh]) {
+        helpers.push_str(STABLE_TANH);
+    }
+    helpers
+}
+
+pub fn generate_shader(op_codes: &[OpCode]) -> Result<GeneratedShader, ShaderGenerationError> {
+    if op_codes.is_empty() {
+        return Err(ShaderGenerationError::EmptyChain);
+    }
+    if let Some(op_code) = op_codes
+        .iter()
+        .skip(1)
+        .position(|op_code| *op_code == OpCode::Linear)
+    {
+        return Err(ShaderGenerationError::MisplacedLinear {
+            op_code: op_code + 1,
+        });
+    }
+
+    let mut bindings: Vec<String> = vec!["dimensions".to_string(), "input".to_string()];
+    let mut body: String = String::new();
+
+    // The first handover is either the result of the matrix multiplication
+    // or the element of the input
+    let mut handover: usize = 0;
+    if op_codes[0] == OpCode::Linear {
+        bindings.push("weights".to_string());
+        bindings.push("bias".to_string());
+        body.push_str("        var handover_0: f32 = 0.0;\n");
+        body.push_str("        for (var inner_dimension: u32 = 0u; inner_dimension < dimensions.input_column_count; inner_dimension += 1u) {\n");
+        body.push_str("            handover_0 += input[output_row_index * dimensions.input_column_count + inner_dimension] * weights[inner_dimension * dimensions.weights_column_count + output_column_index];\n");
+        body.push_str("        }\n");
+        body.push_str("        let handover_1: f32 = handover_0 + bias[output_index];\n");
+        handover = 1;
+    } else {
+        body.push_str("        let handover_0: f32 = input[output_index];\n");
+    }
+
+    let first_elementwise: usize = usize::from(op_codes[0] == OpCode::Linear);
+    for (index, op_code) in op_codes.iter().enumerate().skip(first_elementwise) {
+        let x: String = format!("handover_{}", handover);
+        let expression: String = match op_code {
+            OpCode::Linear => unreachable!("Linear was checked to only be the first op code"),
+            OpCode::AddBias => {
+                let name: String = format!("bias_{}", index);
+                let expression: String = format!("{} + {}[output_index]", x, name);
+                bindings.push(name);
+                expression
+            }
+            OpCode::Scale { factor } => format!("{} * {}", x, float_literal(index, *factor)?),
+            OpCode::ReLU => format!("max(0.0, {})", x),
+            OpCode::Activation { function } => activation_expression(index, *function, &x)?,
+        };
+        handover += 1;
+        // Writing to a String can't fail
+        writeln!(
+            body,
+            "        let handover_{}: f32 = {};",
+            handover, expression
+        )
+        .unwrap();
+    }
+    writeln!(
+        body,
+        "        output[output_index] = handover_{};",
+        handover
+    )
+    .unwrap();
+    bindings.push("output".to_string());
+
+    let mut source: String = UNIFORM.to_string();
+    source.push('\n');
+    for (binding, name) in bindings.iter().enumerate() {
+        let declaration: String = match name.as_str() {
+            "dimensions" => "var<uniform> dimensions: TensorDimensions;".to_string(),
+            "output" => "var<storage, read_write> output: array<f32>;".to_string(),
+            _ => format!("var<storage, read> {}: array<f32>;", name),
+        };
+        writeln!(source, "@group(0) @binding({})\n{}\n", binding, declaration).unwrap();
+    }
+    source.push_str(&helper_functions(op_codes));
+    source.push_str(
+        "
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let output_row_index: u32 = global_id.x;
+    let output_column_index: u32 = global_id.y;
+
+    if (output_row_index < dimensions.output_row_count && output_column_index < dimensions.output_column_count) {
+        let output_index: u32 = output_row_index * dimensions.output_column_count + output_column_index;
+",
+    );
+    source.push_str(&body);
+    source.push_str("    }\n}\n");
+
+    Ok(GeneratedShader { source, bindings })
+}
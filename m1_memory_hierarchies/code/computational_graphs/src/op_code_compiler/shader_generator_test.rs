@@ -0,0 +1,180 @@
+#[cfg(test)]
+mod tests {
+    use naga::{
+        valid::{Capabilities, ValidationFlags, Validator},
+        Module,
+    };
+
+    use crate::{
+        op_code_compiler::shader_generator::{
+            generate_shader, GeneratedShader, OpCode, ShaderGenerationError, ENTRY_POINT,
+        },
+        shared::activation::ActivationFunction,
+    };
+
+    // Parses and validates the shader like wgpu would, but without a GPU
+    fn validate(source: &str) -> Module {
+        let module: Module = naga::front::wgsl::parse_str(source)
+            .unwrap_or_else(|error| panic!("{}\n{}", error.emit_to_string(source), source));
+        Validator::new(ValidationFlags::all(), Capabilities::empty())
+            .validate(&module)
+            .unwrap_or_else(|error| panic!("{:?}\n{}", error, source));
+        module
+    }
+
+    fn elementwise_op_codes() -> Vec<OpCode> {
+        vec![
+            OpCode::AddBias,
+            OpCode::Scale { factor: 0.5 },
+            OpCode::Scale { factor: -2.0 },
+            OpCode::Scale { factor: 1.0e-7 },
+            OpCode::ReLU,
+            OpCode::Activation {
+                function: ActivationFunction::GELUErf,
+            },
+            OpCode::Activation {
+                function: ActivationFunction::GELUTanh,
+            },
+            OpCode::Activation {
+                function: ActivationFunction::SiLU,
+            },
+            OpCode::Activation {
+                function: ActivationFunction::Tanh,
+            },
+            OpCode::Activation {
+                function: ActivationFunction::Sigmoid,
+            },
+            OpCode::Activation {
+                function: ActivationFunction::LeakyReLU { alpha: 0.01 },
+            },
+        ]
+    }
+
+    // Every chain of up to two elementwise op codes, with and without a linear operator first
+    #[test]
+    fn chains_are_valid() {
+        let elementwise: Vec<OpCode> = elementwise_op_codes();
+        let mut chains: Vec<Vec<OpCode>> = vec![vec![]];
+        for first in &elementwise {
+            chains.push(vec![*first]);
+            for second in &elementwise {
+                chains.push(vec![*first, *second]);
+            }
+        }
+
+        for chain in chains {
+            let mut linear_chain: Vec<OpCode> = vec![OpCode::Linear];
+            linear_chain.extend_from_slice(&chain);
+            for op_codes in [chain, linear_chain] {
+                if op_codes.is_empty() {
+                    continue;
+                }
+                let generated: GeneratedShader = generate_shader(&op_codes).unwrap();
+                let module: Module = validate(&generated.source);
+                assert!(module
+                    .entry_points
+                    .iter()
+                    .any(|entry_point| entry_point.name == ENTRY_POINT));
+            }
+        }
+    }
+
+    // The bindings reported by the generator are the ones in the shader
+    #[test]
+    fn bindings() {
+        let op_codes: [OpCode; 4] = [
+            OpCode::Linear,
+            OpCode::AddBias,
+            OpCode::ReLU,
+            OpCode::AddBias,
+        ];
+        let generated: GeneratedShader = generate_shader(&op_codes).unwrap();
+        assert_eq!(
+            generated.bindings,
+            vec![
+                "dimensions",
+                "input",
+                "weights",
+                "bias",
+                "bias_1",
+                "bias_3",
+                "output"
+            ]
+        );
+
+        let module: Module = validate(&generated.source);
+        for (_, variable) in module.global_variables.iter() {
+            let binding: u32 = variable.binding.as_ref().unwrap().binding;
+            assert_eq!(
+                variable.name.as_deref(),
+                Some(generated.bindings[binding as usize].as_str())
+            );
+        }
+        assert_eq!(module.global_variables.len(), generated.bindings.len());
+    }
+
+    // Every op code gets its own variable and the last one is stored
+    #[test]
+    fn handover_variables() {
+        let op_codes: [OpCode; 3] = [
+            OpCode::Scale { factor: 3.0 },
+            OpCode::ReLU,
+            OpCode::Activation {
+                function: ActivationFunction::Tanh,
+            },
+        ];
+        let generated: GeneratedShader = generate_shader(&op_codes).unwrap();
+        validate(&generated.source);
+        assert!(generated
+            .source
+            .contains("let handover_0: f32 = input[output_index];"));
+        assert!(generated
+            .source
+            .contains("let handover_1: f32 = handover_0 * (3.0);"));
+        assert!(generated
+            .source
+            .contains("let handover_2: f32 = max(0.0, handover_1);"));
+        assert!(generated
+            .source
+            .contains("let handover_3: f32 = stable_tanh(handover_2);"));
+        assert!(generated
+            .source
+            .contains("output[output_index] = handover_3;"));
+
+        // Only the helper functions which are used are emitted
+        assert!(generated.source.contains("fn stable_tanh"));
+        assert!(!generated.source.contains("fn erf"));
+        assert!(!generated.source.contains("fn stable_sigmoid"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            generate_shader(&[]).err(),
+            Some(ShaderGenerationError::EmptyChain)
+        );
+        assert_eq!(
+            generate_shader(&[OpCode::Linear, OpCode::ReLU, OpCode::Linear]).err(),
+            Some(ShaderGenerationError::MisplacedLinear { op_code: 2 })
+        );
+        assert!(matches!(
+            generate_shader(&[OpCode::Scale { factor: f32::NAN }]),
+            Err(ShaderGenerationError::NonFiniteConstant { op_code: 0, .. })
+        ));
+        assert_eq!(
+            generate_shader(&[
+                OpCode::ReLU,
+                OpCode::Activation {
+                    function: ActivationFunction::LeakyReLU {
+                        alpha: f32::INFINITY
+                    }
+                }
+            ])
+            .err(),
+            Some(ShaderGenerationError::NonFiniteConstant {
+                op_code: 1,
+                value: f32::INFINITY
+            })
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+
+use super::executor::{InterpreterError, ShaderInterpreter};
+
+// Stands in for the wgpu device and queue when the kernels are run on the interpreter.
+// Parsing and validating a shader is the interpreter's version of compiling a pipeline,
+// so the parsed shaders are kept by their label, like the modules in PipelineCache.
+// The label has to name the shader source, such as "linear.wgsl", not the entry point.
+#[derive(Default)]
+pub struct InterpreterDevice {
+    interpreters: HashMap<String, ShaderInterpreter>,
+}
+
+impl InterpreterDevice {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The number of shaders parsed so far
+    pub fn shader_count(&self) -> usize {
+        self.interpreters.len()
+    }
+
+    // Like encoding a compute pass with set_pipeline, set_bind_group and dispatch_workgroups,
+    // then submitting it and waiting for it. See ShaderInterpreter::dispatch for the bindings.
+    pub fn dispatch(
+        &mut self,
+        label: &str,
+        shader_source: &str,
+        entry_point: &str,
+        workgroup_count: [u32; 3],
+        bindings: &mut [(u32, &mut [u8])],
+    ) -> Result<(), InterpreterError> {
+        if !self.interpreters.contains_key(label) {
+            let interpreter: ShaderInterpreter = ShaderInterpreter::new(label, shader_source)?;
+            self.interpreters.insert(label.to_string(), interpreter);
+        }
+
+        self.interpreters[label].dispatch(entry_point, workgroup_count, bindings)
+    }
+}
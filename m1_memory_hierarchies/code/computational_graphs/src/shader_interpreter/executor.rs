@@ -0,0 +1,422 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Barrier,
+    thread,
+};
+
+use naga::{
+    AddressSpace, Block, EntryPoint, Expression, Function, GlobalVariable, Handle, Module,
+    ScalarKind, ShaderStage, Statement, TypeInner,
+};
+use parking_lot::{Condvar, Mutex};
+
+use crate::shared::shader_validation::{validate_wgsl, ShaderValidationError};
+
+use super::invocation::{Invocation, WorkgroupIds};
+
+// Runs WGSL compute shaders on the CPU by interpreting the IR naga parses them into, the
+// same IR wgpu compiles for the GPU. It is a reference to test kernels against on machines
+// without a GPU, not a fast way of running them, every expression is evaluated one at a time.
+//
+// The workgroups are run one after the other. If the entry point has a barrier, every
+// invocation of a workgroup gets its own thread, which all wait at the barriers, otherwise
+// the invocations are run one after the other. Workgroup memory and the bound buffers are
+// behind locks, so atomics are atomic. Reads and writes which aren't atomic are also done
+// under the lock, but their order between barriers is up to the threads, just like on a GPU.
+// A kernel which relies on the invocations of a workgroup running in lockstep, without
+// barriers, can give different results here than on a GPU.
+//
+// Only the scalar types WGSL allows in compute shaders are supported, and not matrices.
+// Accessing memory out of bounds is an error, instead of being clamped like on the GPU,
+// as it is most likely a bug in the kernel.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InterpreterError {
+    Validation(ShaderValidationError),
+    MissingEntryPoint {
+        entry_point: String,
+    },
+    // Only group 0 is used in this project, see create_bind_group
+    MissingBinding {
+        group: u32,
+        binding: u32,
+    },
+    OutOfBounds {
+        variable: String,
+        offset: usize,
+        length: usize,
+    },
+    Unsupported {
+        feature: String,
+    },
+}
+
+impl fmt::Display for InterpreterError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpreterError::Validation(error) => write!(formatter, "{}", error),
+            InterpreterError::MissingEntryPoint { entry_point } => {
+                write!(
+                    formatter,
+                    "the shader has no compute entry point named {}",
+                    entry_point
+                )
+            }
+            InterpreterError::MissingBinding { group, binding } => write!(
+                formatter,
+                "nothing is bound to group {} binding {}",
+                group, binding
+            ),
+            InterpreterError::OutOfBounds {
+                variable,
+                offset,
+                length,
+            } => write!(
+                formatter,
+                "access at byte {} of {}, which has {} bytes",
+                offset, variable, length
+            ),
+            InterpreterError::Unsupported { feature } => {
+                write!(formatter, "the interpreter doesn't support {}", feature)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InterpreterError {}
+
+impl From<ShaderValidationError> for InterpreterError {
+    fn from(error: ShaderValidationError) -> Self {
+        InterpreterError::Validation(error)
+    }
+}
+
+#[derive(Default)]
+struct BarrierState {
+    // The invocations which haven't returned yet
+    participants: usize,
+    arrived: usize,
+    generation: usize,
+}
+
+// Like std::sync::Barrier, but invocations can leave. An invocation which returns early
+// shouldn't make the rest of its workgroup wait for it forever.
+pub struct WorkgroupBarrier {
+    state: Mutex<BarrierState>,
+    condvar: Condvar,
+}
+
+impl WorkgroupBarrier {
+    fn new(participants: usize) -> Self {
+        WorkgroupBarrier {
+            state: Mutex::new(BarrierState {
+                participants,
+                ..Default::default()
+            }),
+            condvar: Condvar::new(),
+        }
+    }
+
+    fn release(&self, state: &mut BarrierState) {
+        state.arrived = 0;
+        state.generation += 1;
+        self.condvar.notify_all();
+    }
+
+    pub fn wait(&self) {
+        let mut state = self.state.lock();
+        let generation: usize = state.generation;
+        state.arrived += 1;
+        if state.arrived == state.participants {
+            self.release(&mut state);
+        } else {
+            while generation == state.generation {
+                self.condvar.wait(&mut state);
+            }
+        }
+    }
+
+    // Before the next workgroup runs on the same threads
+    fn reset(&self, participants: usize) {
+        let mut state = self.state.lock();
+        state.participants = participants;
+        state.arrived = 0;
+    }
+
+    fn leave(&self) {
+        let mut state = self.state.lock();
+        state.participants -= 1;
+        if 0 < state.arrived && state.arrived == state.participants {
+            self.release(&mut state);
+        }
+    }
+}
+
+fn visit_statements<'a>(block: &'a Block, visit: &mut impl FnMut(&'a Statement)) {
+    for statement in block.iter() {
+        visit(statement);
+        match statement {
+            Statement::Block(block) => visit_statements(block, visit),
+            Statement::If { accept, reject, .. } => {
+                visit_statements(accept, visit);
+                visit_statements(reject, visit);
+            }
+            Statement::Switch { cases, .. } => {
+                for case in cases {
+                    visit_statements(&case.body, visit);
+                }
+            }
+            Statement::Loop {
+                body, continuing, ..
+            } => {
+                visit_statements(body, visit);
+                visit_statements(continuing, visit);
+            }
+            _ => {}
+        }
+    }
+}
+
+// The entry point and every function it calls, directly or through other functions
+fn reachable_functions<'a>(module: &'a Module, entry_point: &'a Function) -> Vec<&'a Function> {
+    let mut functions: Vec<&'a Function> = vec![entry_point];
+    let mut next: usize = 0;
+    while next < functions.len() {
+        visit_statements(&functions[next].body, &mut |statement| {
+            if let Statement::Call { function, .. } = statement {
+                let function: &'a Function = &module.functions[*function];
+                if !functions.iter().any(|known| std::ptr::eq(*known, function)) {
+                    functions.push(function);
+                }
+            }
+        });
+        next += 1;
+    }
+    functions
+}
+
+fn has_barrier(functions: &[&Function]) -> bool {
+    let mut barrier: bool = false;
+    for function in functions {
+        visit_statements(&function.body, &mut |statement| {
+            barrier |= matches!(statement, Statement::Barrier(_));
+        });
+    }
+    barrier
+}
+
+// Like a bind group layout made from the entry point, only the globals it uses need a binding
+fn used_globals(functions: &[&Function]) -> HashSet<Handle<GlobalVariable>> {
+    functions
+        .iter()
+        .flat_map(|function| function.expressions.iter())
+        .filter_map(|(_, expression)| match expression {
+            Expression::GlobalVariable(variable) => Some(*variable),
+            _ => None,
+        })
+        .collect()
+}
+
+// Rejects the types the interpreter can't represent, so it doesn't fail halfway through a dispatch
+fn check_types(module: &Module) -> Result<(), InterpreterError> {
+    for (_, ty) in module.types.iter() {
+        let supported: bool = match ty.inner {
+            TypeInner::Scalar { kind, width }
+            | TypeInner::Vector { kind, width, .. }
+            | TypeInner::Atomic { kind, width } => width == 4 || kind == ScalarKind::Bool,
+            TypeInner::Pointer { .. }
+            | TypeInner::ValuePointer { .. }
+            | TypeInner::Array { .. }
+            | TypeInner::Struct { .. } => true,
+            _ => false,
+        };
+        if !supported {
+            return Err(InterpreterError::Unsupported {
+                feature: format!("the type {:?}", ty.inner),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+pub struct ShaderInterpreter {
+    module: Module,
+}
+
+impl ShaderInterpreter {
+    // The label is only used in error messages, like in shader_validation
+    pub fn new(label: &str, source: &str) -> Result<Self, InterpreterError> {
+        let module: Module = validate_wgsl(label, source)?;
+        check_types(&module)?;
+
+        Ok(ShaderInterpreter { module })
+    }
+
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    fn entry_point(&self, name: &str) -> Result<&EntryPoint, InterpreterError> {
+        self.module
+            .entry_points
+            .iter()
+            .find(|entry_point| {
+                entry_point.name == name && entry_point.stage == ShaderStage::Compute
+            })
+            .ok_or_else(|| InterpreterError::MissingEntryPoint {
+                entry_point: name.to_string(),
+            })
+    }
+
+    pub fn workgroup_size(&self, entry_point: &str) -> Result<[u32; 3], InterpreterError> {
+        Ok(self.entry_point(entry_point)?.workgroup_size)
+    }
+
+    // Runs the entry point like dispatch_workgroups would. The bindings are the binding
+    // numbers in group 0 and the bytes of the buffers bound to them, like create_bind_group.
+    // The buffers which are written by the shader have the results when this returns.
+    pub fn dispatch(
+        &self,
+        entry_point: &str,
+        workgroup_count: [u32; 3],
+        bindings: &mut [(u32, &mut [u8])],
+    ) -> Result<(), InterpreterError> {
+        let entry_point: &EntryPoint = self.entry_point(entry_point)?;
+        let function: &Function = &entry_point.function;
+        let functions: Vec<&Function> = reachable_functions(&self.module, function);
+        let threaded: bool = has_barrier(&functions);
+        let used: HashSet<Handle<GlobalVariable>> = used_globals(&functions);
+
+        let mut bound: HashMap<u32, &mut [u8]> = bindings
+            .iter_mut()
+            .map(|(binding, bytes)| (*binding, &mut **bytes))
+            .collect();
+        let mut buffers: Vec<Option<Mutex<&mut [u8]>>> = Vec::new();
+        for (handle, variable) in self.module.global_variables.iter() {
+            let buffer: Option<Mutex<&mut [u8]>> = match variable.binding {
+                Some(ref binding) if used.contains(&handle) => {
+                    let bytes: &mut [u8] = bound
+                        .remove(&binding.binding)
+                        .filter(|_| binding.group == 0)
+                        .ok_or(InterpreterError::MissingBinding {
+                            group: binding.group,
+                            binding: binding.binding,
+                        })?;
+                    Some(Mutex::new(bytes))
+                }
+                _ => None,
+            };
+            buffers.push(buffer);
+        }
+
+        let workgroup_size: [u32; 3] = entry_point.workgroup_size;
+        let invocation_count: usize = workgroup_size.iter().product::<u32>() as usize;
+        let workgroup_total: usize = workgroup_count
+            .iter()
+            .map(|count| *count as usize)
+            .product();
+        let workgroup_memory: Vec<Option<Mutex<Vec<u8>>>> = self
+            .module
+            .global_variables
+            .iter()
+            .map(|(_, variable)| {
+                (variable.space == AddressSpace::WorkGroup).then(|| {
+                    let size: u32 = self.module.types[variable.ty]
+                        .inner
+                        .size(&self.module.constants);
+                    Mutex::new(vec![0; size as usize])
+                })
+            })
+            .collect();
+
+        // The workgroups are numbered with x changing the fastest, then y, then z
+        let ids = |workgroup_index: usize, local_invocation_index: usize| -> WorkgroupIds {
+            let workgroup: u32 = workgroup_index as u32;
+            let index: u32 = local_invocation_index as u32;
+            WorkgroupIds {
+                workgroup_id: [
+                    workgroup % workgroup_count[0],
+                    workgroup / workgroup_count[0] % workgroup_count[1],
+                    workgroup / (workgroup_count[0] * workgroup_count[1]),
+                ],
+                local_invocation_id: [
+                    index % workgroup_size[0],
+                    index / workgroup_size[0] % workgroup_size[1],
+                    index / (workgroup_size[0] * workgroup_size[1]),
+                ],
+                local_invocation_index: index,
+                workgroup_size,
+                workgroup_count,
+            }
+        };
+
+        if threaded {
+            // The threads are spawned once per dispatch and run every workgroup one after
+            // the other, as spawning them for every workgroup is slow for dispatches of
+            // many small workgroups, such as one workgroup per row. Between two workgroups
+            // the threads wait for each other twice, once all of them are done with a
+            // workgroup, one of them resets the workgroup memory and the barrier.
+            let barrier: WorkgroupBarrier = WorkgroupBarrier::new(invocation_count);
+            let between_workgroups: Barrier = Barrier::new(invocation_count);
+            let first_error: Mutex<Option<InterpreterError>> = Mutex::new(None);
+            thread::scope(|scope| {
+                for index in 0..invocation_count {
+                    let barrier: &WorkgroupBarrier = &barrier;
+                    let between_workgroups: &Barrier = &between_workgroups;
+                    let first_error: &Mutex<Option<InterpreterError>> = &first_error;
+                    let buffers: &[Option<Mutex<&mut [u8]>>] = &buffers;
+                    let workgroup_memory: &[Option<Mutex<Vec<u8>>>] = &workgroup_memory;
+                    let ids = &ids;
+                    scope.spawn(move || {
+                        for workgroup_index in 0..workgroup_total {
+                            if between_workgroups.wait().is_leader() {
+                                // Workgroup memory starts out zeroed for every workgroup
+                                for memory in workgroup_memory.iter().flatten() {
+                                    memory.lock().fill(0);
+                                }
+                                barrier.reset(invocation_count);
+                            }
+                            between_workgroups.wait();
+                            // Every thread sees the same error, so they all stop together
+                            if first_error.lock().is_some() {
+                                break;
+                            }
+
+                            let result: Result<(), InterpreterError> = Invocation::new(
+                                &self.module,
+                                buffers,
+                                workgroup_memory,
+                                Some(barrier),
+                            )
+                            .and_then(|mut invocation| {
+                                invocation.run(function, ids(workgroup_index, index))
+                            });
+                            barrier.leave();
+                            if let Err(error) = result {
+                                first_error.lock().get_or_insert(error);
+                            }
+                        }
+                    });
+                }
+            });
+            if let Some(error) = first_error.into_inner() {
+                return Err(error);
+            }
+        } else {
+            for workgroup_index in 0..workgroup_total {
+                // Workgroup memory starts out zeroed for every workgroup
+                for memory in workgroup_memory.iter().flatten() {
+                    memory.lock().fill(0);
+                }
+                for index in 0..invocation_count {
+                    Invocation::new(&self.module, &buffers, &workgroup_memory, None)?
+                        .run(function, ids(workgroup_index, index))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
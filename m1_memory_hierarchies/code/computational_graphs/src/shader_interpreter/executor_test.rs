@@ -0,0 +1,448 @@
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        op_code_compiler::shader_generator::{
+            generate_shader, GeneratedShader, OpCode, ENTRY_POINT,
+        },
+        shader_interpreter::executor::{InterpreterError, ShaderInterpreter},
+        shared::{
+            activation::ActivationFunction,
+            tensor2d::Tensor2D,
+            tensor2d_test_utilities::{assert_close, random_tensor_with_magnitude},
+        },
+    };
+
+    mod histogram {
+        include!("../../../gpu_histogram/src/specialization.rs");
+    }
+
+    const ERROR_TOLERANCE: f32 = 0.00001;
+
+    fn interpreter(label: &str, source: &str) -> ShaderInterpreter {
+        ShaderInterpreter::new(label, source).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    // The uniform of linear.wgsl and the generated shaders
+    fn linear_dimensions(input: &Tensor2D, weights: &Tensor2D, bias: &Tensor2D) -> Vec<u32> {
+        [
+            input.row_count,
+            input.column_count,
+            weights.row_count,
+            weights.column_count,
+            bias.row_count,
+            bias.column_count,
+            bias.row_count,
+            bias.column_count,
+        ]
+        .iter()
+        .map(|dimension| *dimension as u32)
+        .collect()
+    }
+
+    // The linear shaders have 8x8 workgroups with the rows along x and the columns along y
+    fn linear_workgroup_count(output: &Tensor2D) -> [u32; 3] {
+        [
+            ((output.row_count + 7) / 8) as u32,
+            ((output.column_count + 7) / 8) as u32,
+            1,
+        ]
+    }
+
+    #[test]
+    fn linear() {
+        let shader: ShaderInterpreter =
+            interpreter("linear.wgsl", include_str!("../shared/shaders/linear.wgsl"));
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(0);
+        // Not multiples of the workgroup size, so some of the invocations are out of bounds
+        let input: Tensor2D = random_tensor_with_magnitude(&mut rng, 13, 11, 2.0);
+        let weights: Tensor2D = random_tensor_with_magnitude(&mut rng, 11, 9, 2.0);
+        let bias: Tensor2D = random_tensor_with_magnitude(&mut rng, 13, 9, 2.0);
+        let expected: Tensor2D = Tensor2D::linear(&input, &weights, &bias);
+
+        for (entry_point, expected) in [
+            ("main", expected.clone()),
+            ("main_with_relu", Tensor2D::relu(&expected)),
+        ] {
+            let mut dimensions: Vec<u32> = linear_dimensions(&input, &weights, &bias);
+            let mut input_data: Vec<f32> = input.data.clone();
+            let mut weights_data: Vec<f32> = weights.data.clone();
+            let mut bias_data: Vec<f32> = bias.data.clone();
+            let mut output: Vec<f32> = vec![0.0; expected.data.len()];
+            // The residual and activation bindings are left out, like in their bind group layouts
+            shader
+                .dispatch(
+                    entry_point,
+                    linear_workgroup_count(&expected),
+                    &mut [
+                        (0, bytemuck::cast_slice_mut(&mut dimensions)),
+                        (1, bytemuck::cast_slice_mut(&mut input_data)),
+                        (2, bytemuck::cast_slice_mut(&mut weights_data)),
+                        (3, bytemuck::cast_slice_mut(&mut bias_data)),
+                        (4, bytemuck::cast_slice_mut(&mut output)),
+                    ],
+                )
+                .unwrap_or_else(|error| panic!("{}", error));
+
+            assert_close(entry_point, &expected.data, &output, ERROR_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn activation() {
+        let shader: ShaderInterpreter = interpreter(
+            "activation.wgsl",
+            include_str!("../shared/shaders/activation.wgsl"),
+        );
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(1);
+        let input: Tensor2D = random_tensor_with_magnitude(&mut rng, 5, 19, 2.0);
+        let functions: [ActivationFunction; 6] = [
+            ActivationFunction::GELUErf,
+            ActivationFunction::GELUTanh,
+            ActivationFunction::SiLU,
+            ActivationFunction::Tanh,
+            ActivationFunction::Sigmoid,
+            ActivationFunction::LeakyReLU { alpha: 0.1 },
+        ];
+
+        for function in functions {
+            let expected: Tensor2D = Tensor2D::activation(&input, function);
+            let mut uniform: [u32; 4] = [
+                input.data.len() as u32,
+                function.shader_index(),
+                function.alpha().to_bits(),
+                0,
+            ];
+            let mut input_data: Vec<f32> = input.data.clone();
+            let mut output: Vec<f32> = vec![0.0; input.data.len()];
+            let workgroup_size: [u32; 3] = shader.workgroup_size("main").unwrap();
+            let workgroup_count: u32 =
+                (input.data.len() as u32 + workgroup_size[0] - 1) / workgroup_size[0];
+            shader
+                .dispatch(
+                    "main",
+                    [workgroup_count, 1, 1],
+                    &mut [
+                        (0, bytemuck::cast_slice_mut(&mut uniform)),
+                        (1, bytemuck::cast_slice_mut(&mut input_data)),
+                        (2, bytemuck::cast_slice_mut(&mut output)),
+                    ],
+                )
+                .unwrap_or_else(|error| panic!("{}", error));
+
+            assert_close(
+                &format!("{:?}", function),
+                &expected.data,
+                &output,
+                ERROR_TOLERANCE,
+            );
+        }
+    }
+
+    // The three passes of GraphOperator::Softmax, which wait at barriers within a workgroup
+    #[test]
+    fn softmax() {
+        let shader: ShaderInterpreter = interpreter(
+            "softmax.wgsl",
+            include_str!("../shared/shaders/softmax.wgsl"),
+        );
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(2);
+        let mut input: Tensor2D = random_tensor_with_magnitude(&mut rng, 3, 45, 2.0);
+        // Large enough to overflow exp without subtracting the max
+        input.data[7] = 200.0;
+        let expected: Tensor2D = Tensor2D::softmax(&input);
+
+        let element_count: usize = input.data.len();
+        let mut uniform: [u32; 4] = [
+            element_count as u32,
+            input.row_count as u32,
+            input.column_count as u32,
+            0,
+        ];
+        let mut input_data: Vec<f32> = input.data.clone();
+        let mut row_max: Vec<f32> = vec![0.0; input.row_count];
+        let mut row_sum: Vec<f32> = vec![0.0; input.row_count];
+        let mut output: Vec<f32> = vec![0.0; element_count];
+        let row_workgroups: [u32; 3] = [input.row_count as u32, 1, 1];
+        shader
+            .dispatch(
+                "single_pass_max",
+                row_workgroups,
+                &mut [
+                    (0, bytemuck::cast_slice_mut(&mut uniform)),
+                    (1, bytemuck::cast_slice_mut(&mut input_data)),
+                    (2, bytemuck::cast_slice_mut(&mut row_max)),
+                ],
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+        shader
+            .dispatch(
+                "single_pass_sum",
+                row_workgroups,
+                &mut [
+                    (0, bytemuck::cast_slice_mut(&mut uniform)),
+                    (1, bytemuck::cast_slice_mut(&mut input_data)),
+                    (2, bytemuck::cast_slice_mut(&mut row_max)),
+                    (3, bytemuck::cast_slice_mut(&mut row_sum)),
+                ],
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+        shader
+            .dispatch(
+                "map",
+                [((element_count + 31) / 32) as u32, 1, 1],
+                &mut [
+                    (0, bytemuck::cast_slice_mut(&mut uniform)),
+                    (1, bytemuck::cast_slice_mut(&mut input_data)),
+                    (2, bytemuck::cast_slice_mut(&mut row_max)),
+                    (3, bytemuck::cast_slice_mut(&mut row_sum)),
+                    (4, bytemuck::cast_slice_mut(&mut output)),
+                ],
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        for (row, row_data) in input.data.chunks(input.column_count).enumerate() {
+            assert_eq!(
+                row_max[row],
+                row_data.iter().copied().fold(f32::MIN, f32::max)
+            );
+        }
+        assert_close("softmax", &expected.data, &output, ERROR_TOLERANCE);
+    }
+
+    #[test]
+    fn sum() {
+        let shader: ShaderInterpreter =
+            interpreter("sum.wgsl", include_str!("../shared/shaders/sum.wgsl"));
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(3);
+        let input: Tensor2D = random_tensor_with_magnitude(&mut rng, 1, 101, 2.0);
+
+        let mut uniform: [u32; 2] = [input.data.len() as u32, 1];
+        let mut data: Vec<f32> = input.data.clone();
+        let mut output: Vec<f32> = vec![0.0];
+        shader
+            .dispatch(
+                "single_pass_sum",
+                [1, 1, 1],
+                &mut [
+                    (0, bytemuck::cast_slice_mut(&mut uniform)),
+                    (1, bytemuck::cast_slice_mut(&mut data)),
+                    (2, bytemuck::cast_slice_mut(&mut output)),
+                ],
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        assert_close("sum", &[input.data.iter().sum()], &output, ERROR_TOLERANCE);
+    }
+
+    // histogram.wgsl is left out, as it gives the wrong result on the GPU as well
+    #[test]
+    fn histograms() {
+        let bin_count: usize = 5;
+        let element_count: usize = 1000;
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(4);
+        let input: Vec<f32> = (0..element_count)
+            .map(|_| rng.gen_range(0.0..bin_count as f32))
+            .collect();
+        let mut expected: Vec<u32> = vec![0; bin_count];
+        for value in &input {
+            expected[value.floor() as usize] += 1;
+        }
+
+        let shaders: [(&str, &str, usize); 3] = [
+            (
+                "histogram_atomic.wgsl",
+                include_str!("../../../gpu_histogram/src/histogram_atomic.wgsl"),
+                1,
+            ),
+            (
+                "histogram_shared.wgsl",
+                include_str!("../../../gpu_histogram/src/histogram_shared.wgsl"),
+                1,
+            ),
+            (
+                "histogram_local.wgsl",
+                include_str!("../../../gpu_histogram/src/histogram_local.wgsl"),
+                8,
+            ),
+        ];
+        for (label, base_shader_file, elements_per_thread) in shaders {
+            let source: String =
+                histogram::specialize_shader(base_shader_file, bin_count, elements_per_thread);
+            let shader: ShaderInterpreter = interpreter(label, &source);
+
+            // Like gpu_histogram's histogram function
+            let workgroup_count: u32 = ((element_count / elements_per_thread + 31) / 32) as u32;
+            let mut uniform: [u32; 4] = [element_count as u32, 0, 0, 0];
+            let mut input_data: Vec<f32> = input.clone();
+            let mut output: Vec<u32> = vec![0; bin_count];
+            shader
+                .dispatch(
+                    "histogram",
+                    [workgroup_count, 1, 1],
+                    &mut [
+                        (0, bytemuck::cast_slice_mut(&mut uniform)),
+                        (1, bytemuck::cast_slice_mut(&mut input_data)),
+                        (2, bytemuck::cast_slice_mut(&mut output)),
+                    ],
+                )
+                .unwrap_or_else(|error| panic!("{}", error));
+
+            assert_eq!(expected, output, "{}", label);
+        }
+    }
+
+    #[test]
+    fn generated_shaders() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(5);
+        let input: Tensor2D = random_tensor_with_magnitude(&mut rng, 10, 6, 2.0);
+        let weights: Tensor2D = random_tensor_with_magnitude(&mut rng, 6, 12, 2.0);
+        let bias: Tensor2D = random_tensor_with_magnitude(&mut rng, 10, 12, 2.0);
+        let extra_bias: Tensor2D = random_tensor_with_magnitude(&mut rng, 10, 12, 2.0);
+        let op_codes: [OpCode; 5] = [
+            OpCode::Linear,
+            OpCode::AddBias,
+            OpCode::Scale { factor: 0.5 },
+            OpCode::ReLU,
+            OpCode::Activation {
+                function: ActivationFunction::SiLU,
+            },
+        ];
+
+        let linear: Tensor2D = Tensor2D::linear(&input, &weights, &bias);
+        let expected: Vec<f32> = linear
+            .data
+            .iter()
+            .zip(&extra_bias.data)
+            .map(|(x, bias)| ActivationFunction::SiLU.apply(((x + bias) * 0.5).max(0.0)))
+            .collect();
+
+        let generated: GeneratedShader = generate_shader(&op_codes).unwrap();
+        let shader: ShaderInterpreter = interpreter("generate_shader", &generated.source);
+        let mut buffers: Vec<Vec<u8>> = generated
+            .bindings
+            .iter()
+            .map(|name| match name.as_str() {
+                "dimensions" => {
+                    bytemuck::cast_slice(&linear_dimensions(&input, &weights, &bias)).to_vec()
+                }
+                "input" => bytemuck::cast_slice(&input.data).to_vec(),
+                "weights" => bytemuck::cast_slice(&weights.data).to_vec(),
+                "bias" => bytemuck::cast_slice(&bias.data).to_vec(),
+                "bias_1" => bytemuck::cast_slice(&extra_bias.data).to_vec(),
+                "output" => vec![0; linear.data.len() * 4],
+                name => panic!("Unexpected binding {}", name),
+            })
+            .collect();
+        let mut bindings: Vec<(u32, &mut [u8])> = buffers
+            .iter_mut()
+            .enumerate()
+            .map(|(binding, bytes)| (binding as u32, bytes.as_mut_slice()))
+            .collect();
+        shader
+            .dispatch(ENTRY_POINT, linear_workgroup_count(&linear), &mut bindings)
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        let output: &[f32] = bytemuck::cast_slice(buffers.last().unwrap());
+        assert_close("generate_shader", &expected, output, ERROR_TOLERANCE);
+    }
+
+    // Reverses the input through workgroup memory, which only works if every invocation
+    // has written its element before any of them read
+    #[test]
+    fn workgroup_barrier() {
+        let source: &str = "@group(0) @binding(0)
+var<storage, read_write> data: array<u32>;
+
+var<workgroup> reversed: array<u32, 64>;
+
+@compute @workgroup_size(64, 1, 1)
+fn main(@builtin(local_invocation_index) index: u32, @builtin(workgroup_id) group_id: vec3<u32>) {
+    let offset: u32 = group_id.x * 64u;
+    reversed[63u - index] = data[offset + index];
+    // An early return of some invocations mustn't keep the others waiting
+    if (index == 0u && group_id.x == 1u) {
+        return;
+    }
+    workgroupBarrier();
+    data[offset + index] = reversed[index];
+}
+";
+        let shader: ShaderInterpreter = interpreter("workgroup_barrier", source);
+        let mut data: Vec<u32> = (0..128).collect();
+        shader
+            .dispatch(
+                "main",
+                [2, 1, 1],
+                &mut [(0, bytemuck::cast_slice_mut(&mut data))],
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        let mut expected: Vec<u32> = (0..64).rev().chain((64..128).rev()).collect();
+        // The invocation which returned early didn't write its element
+        expected[64] = 64;
+        assert_eq!(expected, data);
+    }
+
+    #[test]
+    fn errors() {
+        let source: &str = "@group(0) @binding(0)
+var<storage, read_write> data: array<f32>;
+
+@compute @workgroup_size(4, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    data[global_id.x] = f32(global_id.x);
+}
+";
+        let shader: ShaderInterpreter = interpreter("errors", source);
+
+        let mut data: Vec<f32> = vec![0.0; 6];
+        assert_eq!(
+            shader.dispatch(
+                "main",
+                [2, 1, 1],
+                &mut [(0, bytemuck::cast_slice_mut(&mut data))]
+            ),
+            Err(InterpreterError::OutOfBounds {
+                variable: "data".to_string(),
+                offset: 24,
+                length: 24
+            })
+        );
+        // The invocations before the one out of bounds have run
+        assert_eq!(data, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        assert_eq!(
+            shader.dispatch("main", [1, 1, 1], &mut []),
+            Err(InterpreterError::MissingBinding {
+                group: 0,
+                binding: 0
+            })
+        );
+        assert_eq!(
+            shader.dispatch("other", [1, 1, 1], &mut []),
+            Err(InterpreterError::MissingEntryPoint {
+                entry_point: "other".to_string()
+            })
+        );
+        assert!(matches!(
+            ShaderInterpreter::new("errors", &source.replace("f32(global_id.x)", "f32(x)")),
+            Err(InterpreterError::Validation(_))
+        ));
+
+        let matrix: &str = "@group(0) @binding(0)
+var<storage, read_write> data: array<mat2x2<f32>>;
+
+@compute @workgroup_size(1, 1, 1)
+fn main() {
+    data[0] = data[0] * data[0];
+}
+";
+        assert!(matches!(
+            ShaderInterpreter::new("matrix", matrix),
+            Err(InterpreterError::Unsupported { .. })
+        ));
+    }
+}
@@ -0,0 +1,832 @@
+use naga::{
+    ArraySize, AtomicFunction, BinaryOperator, Binding, Block, BuiltIn, Constant, ConstantInner,
+    Expression, Function, Handle, Module, ScalarKind, ScalarValue, Statement, SwitchValue,
+    TypeInner,
+};
+use parking_lot::Mutex;
+
+use super::{
+    executor::{InterpreterError, WorkgroupBarrier},
+    value::{self, Pointee, Pointer, Scalar, Space, Value},
+};
+
+// The ids of an invocation, which the entry point gets through its builtin arguments
+#[derive(Clone, Copy, Debug)]
+pub struct WorkgroupIds {
+    pub workgroup_id: [u32; 3],
+    pub local_invocation_id: [u32; 3],
+    pub local_invocation_index: u32,
+    pub workgroup_size: [u32; 3],
+    pub workgroup_count: [u32; 3],
+}
+
+enum ControlFlow {
+    Next,
+    Break,
+    Continue,
+    Return(Option<Value>),
+}
+
+// A function call. The values of the expressions are kept until they are emitted again,
+// which happens every iteration of a loop.
+struct Frame<'a> {
+    function: &'a Function,
+    arguments: Vec<Value>,
+    locals: Vec<Vec<u8>>,
+    expressions: Vec<Option<Value>>,
+}
+
+fn unsupported(feature: &str) -> InterpreterError {
+    InterpreterError::Unsupported {
+        feature: feature.to_string(),
+    }
+}
+
+fn array_length(module: &Module, length: Handle<Constant>) -> usize {
+    match module.constants[length].inner {
+        ConstantInner::Scalar {
+            value: ScalarValue::Uint(length),
+            ..
+        } => length as usize,
+        ConstantInner::Scalar {
+            value: ScalarValue::Sint(length),
+            ..
+        } => length as usize,
+        _ => 0,
+    }
+}
+
+fn constant_value(module: &Module, constant: Handle<Constant>) -> Value {
+    match module.constants[constant].inner {
+        ConstantInner::Scalar { value, .. } => Value::Scalar(match value {
+            ScalarValue::Sint(value) => Scalar::I32(value as i32),
+            ScalarValue::Uint(value) => Scalar::U32(value as u32),
+            ScalarValue::Float(value) => Scalar::F32(value as f32),
+            ScalarValue::Bool(value) => Scalar::Bool(value),
+        }),
+        ConstantInner::Composite { ref components, .. } => Value::Composite(
+            components
+                .iter()
+                .map(|component| constant_value(module, *component))
+                .collect(),
+        ),
+    }
+}
+
+// Returns None if the value doesn't fit in the bytes
+fn read_value(module: &Module, bytes: &[u8], offset: usize, pointee: Pointee) -> Option<Value> {
+    let ty = match pointee {
+        Pointee::Scalar { kind, width } => {
+            return Scalar::read(bytes, offset, kind, width).map(Value::Scalar)
+        }
+        Pointee::Type(ty) => ty,
+    };
+
+    match module.types[ty].inner {
+        TypeInner::Scalar { kind, width } | TypeInner::Atomic { kind, width } => {
+            Scalar::read(bytes, offset, kind, width).map(Value::Scalar)
+        }
+        TypeInner::Vector { size, kind, width } => (0..size as usize)
+            .map(|component| {
+                Scalar::read(bytes, offset + component * width as usize, kind, width)
+                    .map(Value::Scalar)
+            })
+            .collect::<Option<Vec<Value>>>()
+            .map(Value::Composite),
+        TypeInner::Array {
+            base,
+            size: ArraySize::Constant(length),
+            stride,
+        } => (0..array_length(module, length))
+            .map(|element| {
+                read_value(
+                    module,
+                    bytes,
+                    offset + element * stride as usize,
+                    Pointee::Type(base),
+                )
+            })
+            .collect::<Option<Vec<Value>>>()
+            .map(Value::Composite),
+        TypeInner::Struct { ref members, .. } => members
+            .iter()
+            .map(|member| {
+                read_value(
+                    module,
+                    bytes,
+                    offset + member.offset as usize,
+                    Pointee::Type(member.ty),
+                )
+            })
+            .collect::<Option<Vec<Value>>>()
+            .map(Value::Composite),
+        _ => None,
+    }
+}
+
+fn write_value(
+    module: &Module,
+    bytes: &mut [u8],
+    offset: usize,
+    pointee: Pointee,
+    value: &Value,
+) -> Option<()> {
+    let ty = match pointee {
+        Pointee::Scalar { .. } => return value.scalar().ok()?.write(bytes, offset),
+        Pointee::Type(ty) => ty,
+    };
+
+    let components: &Vec<Value> = match (&module.types[ty].inner, value) {
+        (TypeInner::Scalar { .. } | TypeInner::Atomic { .. }, Value::Scalar(scalar)) => {
+            return scalar.write(bytes, offset)
+        }
+        (_, Value::Composite(components)) => components,
+        _ => return None,
+    };
+    match module.types[ty].inner {
+        TypeInner::Vector { width, .. } => {
+            for (component, value) in components.iter().enumerate() {
+                value
+                    .scalar()
+                    .ok()?
+                    .write(bytes, offset + component * width as usize)?;
+            }
+        }
+        TypeInner::Array { base, stride, .. } => {
+            for (element, value) in components.iter().enumerate() {
+                write_value(
+                    module,
+                    bytes,
+                    offset + element * stride as usize,
+                    Pointee::Type(base),
+                    value,
+                )?;
+            }
+        }
+        TypeInner::Struct { ref members, .. } => {
+            for (member, value) in members.iter().zip(components) {
+                write_value(
+                    module,
+                    bytes,
+                    offset + member.offset as usize,
+                    Pointee::Type(member.ty),
+                    value,
+                )?;
+            }
+        }
+        _ => return None,
+    }
+
+    Some(())
+}
+
+fn vector(components: [u32; 3]) -> Value {
+    Value::Composite(
+        components
+            .iter()
+            .map(|component| Value::Scalar(Scalar::U32(*component)))
+            .collect(),
+    )
+}
+
+fn builtin(built_in: BuiltIn, ids: &WorkgroupIds) -> Result<Value, InterpreterError> {
+    match built_in {
+        BuiltIn::GlobalInvocationId => Ok(vector([
+            ids.workgroup_id[0] * ids.workgroup_size[0] + ids.local_invocation_id[0],
+            ids.workgroup_id[1] * ids.workgroup_size[1] + ids.local_invocation_id[1],
+            ids.workgroup_id[2] * ids.workgroup_size[2] + ids.local_invocation_id[2],
+        ])),
+        BuiltIn::LocalInvocationId => Ok(vector(ids.local_invocation_id)),
+        BuiltIn::LocalInvocationIndex => Ok(Value::Scalar(Scalar::U32(ids.local_invocation_index))),
+        BuiltIn::WorkGroupId => Ok(vector(ids.workgroup_id)),
+        BuiltIn::WorkGroupSize => Ok(vector(ids.workgroup_size)),
+        BuiltIn::NumWorkGroups => Ok(vector(ids.workgroup_count)),
+        _ => Err(InterpreterError::Unsupported {
+            feature: format!("the builtin {:?}", built_in),
+        }),
+    }
+}
+
+// A single invocation of an entry point. Its private memory and function calls are its own,
+// the buffers and the workgroup memory are shared with the other invocations.
+pub struct Invocation<'a, 'm> {
+    module: &'a Module,
+    buffers: &'a [Option<Mutex<&'m mut [u8]>>],
+    workgroup_memory: &'a [Option<Mutex<Vec<u8>>>],
+    private_memory: Vec<Vec<u8>>,
+    frames: Vec<Frame<'a>>,
+    barrier: Option<&'a WorkgroupBarrier>,
+}
+
+impl<'a, 'm> Invocation<'a, 'm> {
+    pub fn new(
+        module: &'a Module,
+        buffers: &'a [Option<Mutex<&'m mut [u8]>>],
+        workgroup_memory: &'a [Option<Mutex<Vec<u8>>>],
+        barrier: Option<&'a WorkgroupBarrier>,
+    ) -> Result<Self, InterpreterError> {
+        let mut private_memory: Vec<Vec<u8>> = Vec::with_capacity(module.global_variables.len());
+        for (index, (_, variable)) in module.global_variables.iter().enumerate() {
+            if buffers[index].is_some() || workgroup_memory[index].is_some() {
+                private_memory.push(Vec::new());
+                continue;
+            }
+
+            let size: u32 = module.types[variable.ty].inner.size(&module.constants);
+            let mut bytes: Vec<u8> = vec![0; size as usize];
+            if let Some(init) = variable.init {
+                write_value(
+                    module,
+                    &mut bytes,
+                    0,
+                    Pointee::Type(variable.ty),
+                    &constant_value(module, init),
+                )
+                .ok_or_else(|| unsupported("the initializer of a private variable"))?;
+            }
+            private_memory.push(bytes);
+        }
+
+        Ok(Invocation {
+            module,
+            buffers,
+            workgroup_memory,
+            private_memory,
+            frames: Vec::new(),
+            barrier,
+        })
+    }
+
+    pub fn run(
+        &mut self,
+        function: &'a Function,
+        ids: WorkgroupIds,
+    ) -> Result<(), InterpreterError> {
+        let mut arguments: Vec<Value> = Vec::with_capacity(function.arguments.len());
+        for argument in &function.arguments {
+            let value: Value = match argument.binding {
+                Some(Binding::BuiltIn(built_in)) => builtin(built_in, &ids)?,
+                // The builtins can also be members of a struct
+                None => match self.module.types[argument.ty].inner {
+                    TypeInner::Struct { ref members, .. } => Value::Composite(
+                        members
+                            .iter()
+                            .map(|member| match member.binding {
+                                Some(Binding::BuiltIn(built_in)) => builtin(built_in, &ids),
+                                _ => Err(unsupported("entry point arguments without a builtin")),
+                            })
+                            .collect::<Result<Vec<Value>, InterpreterError>>()?,
+                    ),
+                    _ => return Err(unsupported("entry point arguments without a builtin")),
+                },
+                Some(Binding::Location { .. }) => {
+                    return Err(unsupported("entry point arguments with a location"))
+                }
+            };
+            arguments.push(value);
+        }
+
+        self.call(function, arguments)?;
+        Ok(())
+    }
+
+    fn frame(&mut self) -> &mut Frame<'a> {
+        self.frames
+            .last_mut()
+            .expect("shader_interpreter::invocation has no function call")
+    }
+
+    fn with_memory<R>(&mut self, space: Space, access: impl FnOnce(&mut [u8]) -> R) -> R {
+        match space {
+            Space::Global(index) => {
+                if let Some(buffer) = &self.buffers[index] {
+                    return access(&mut buffer.lock());
+                }
+                if let Some(memory) = &self.workgroup_memory[index] {
+                    return access(&mut memory.lock());
+                }
+                access(&mut self.private_memory[index])
+            }
+            Space::Local { frame, variable } => access(&mut self.frames[frame].locals[variable]),
+        }
+    }
+
+    fn out_of_bounds(&mut self, space: Space, offset: usize) -> InterpreterError {
+        let length: usize = self.with_memory(space, |bytes| bytes.len());
+        let variable: Option<String> = match space {
+            Space::Global(index) => self
+                .module
+                .global_variables
+                .iter()
+                .nth(index)
+                .and_then(|(_, variable)| variable.name.clone()),
+            Space::Local { frame, variable } => self.frames[frame]
+                .function
+                .local_variables
+                .iter()
+                .nth(variable)
+                .and_then(|(_, variable)| variable.name.clone()),
+        };
+
+        InterpreterError::OutOfBounds {
+            variable: variable.unwrap_or_else(|| "an unnamed variable".to_string()),
+            offset,
+            length,
+        }
+    }
+
+    fn load(&mut self, pointer: Pointer) -> Result<Value, InterpreterError> {
+        let module: &Module = self.module;
+        match self.with_memory(pointer.space, |bytes| {
+            read_value(module, bytes, pointer.offset, pointer.pointee)
+        }) {
+            Some(value) => Ok(value),
+            None => Err(self.out_of_bounds(pointer.space, pointer.offset)),
+        }
+    }
+
+    fn store(&mut self, pointer: Pointer, value: &Value) -> Result<(), InterpreterError> {
+        let module: &Module = self.module;
+        match self.with_memory(pointer.space, |bytes| {
+            write_value(module, bytes, pointer.offset, pointer.pointee, value)
+        }) {
+            Some(()) => Ok(()),
+            None => Err(self.out_of_bounds(pointer.space, pointer.offset)),
+        }
+    }
+
+    fn access_pointer(
+        &mut self,
+        pointer: Pointer,
+        index: usize,
+    ) -> Result<Pointer, InterpreterError> {
+        let ty = match pointer.pointee {
+            Pointee::Type(ty) => ty,
+            Pointee::Scalar { .. } => return Err(unsupported("indexing into a scalar")),
+        };
+
+        let (offset, pointee, length): (usize, Pointee, Option<usize>) =
+            match self.module.types[ty].inner {
+                TypeInner::Array { base, size, stride } => (
+                    index.saturating_mul(stride as usize),
+                    Pointee::Type(base),
+                    match size {
+                        ArraySize::Constant(length) => Some(array_length(self.module, length)),
+                        // Checked when the memory is read or written
+                        ArraySize::Dynamic => None,
+                    },
+                ),
+                TypeInner::Struct { ref members, .. } => match members.get(index) {
+                    Some(member) => (member.offset as usize, Pointee::Type(member.ty), None),
+                    None => return Err(unsupported("a struct member which doesn't exist")),
+                },
+                TypeInner::Vector { size, kind, width } => (
+                    index.saturating_mul(width as usize),
+                    Pointee::Scalar { kind, width },
+                    Some(size as usize),
+                ),
+                _ => return Err(unsupported("indexing into this type")),
+            };
+
+        let offset: usize = pointer.offset.saturating_add(offset);
+        if let Some(length) = length {
+            if length <= index {
+                return Err(self.out_of_bounds(pointer.space, offset));
+            }
+        }
+
+        Ok(Pointer {
+            space: pointer.space,
+            offset,
+            pointee,
+        })
+    }
+
+    fn access(&mut self, base: Value, index: usize) -> Result<Value, InterpreterError> {
+        match base {
+            Value::Pointer(pointer) => Ok(Value::Pointer(self.access_pointer(pointer, index)?)),
+            Value::Composite(mut components) => {
+                if components.len() <= index {
+                    return Err(InterpreterError::OutOfBounds {
+                        variable: "a value".to_string(),
+                        offset: index,
+                        length: components.len(),
+                    });
+                }
+                Ok(components.swap_remove(index))
+            }
+            Value::Scalar(_) => Err(unsupported("indexing into a scalar")),
+        }
+    }
+
+    fn evaluate(&mut self, expression: Handle<Expression>) -> Result<Value, InterpreterError> {
+        if let Some(value) = &self.frame().expressions[expression.index()] {
+            return Ok(value.clone());
+        }
+
+        let value: Value = self.evaluate_uncached(expression)?;
+        self.frame().expressions[expression.index()] = Some(value.clone());
+        Ok(value)
+    }
+
+    fn evaluate_uncached(
+        &mut self,
+        expression: Handle<Expression>,
+    ) -> Result<Value, InterpreterError> {
+        let function: &'a Function = self.frame().function;
+        let module: &'a Module = self.module;
+
+        let value: Value = match function.expressions[expression] {
+            Expression::Access { base, index } => {
+                let base: Value = self.evaluate(base)?;
+                let index: usize = self.evaluate(index)?.as_index()?;
+                self.access(base, index)?
+            }
+            Expression::AccessIndex { base, index } => {
+                let base: Value = self.evaluate(base)?;
+                self.access(base, index as usize)?
+            }
+            Expression::Constant(constant) => constant_value(module, constant),
+            Expression::Splat { size, value } => {
+                Value::Composite(vec![self.evaluate(value)?; size as usize])
+            }
+            Expression::Swizzle {
+                size,
+                vector,
+                pattern,
+            } => match self.evaluate(vector)? {
+                Value::Composite(components) => Value::Composite(
+                    pattern[..size as usize]
+                        .iter()
+                        .map(|component| components[*component as usize].clone())
+                        .collect(),
+                ),
+                _ => return Err(unsupported("swizzling a scalar")),
+            },
+            Expression::Compose { ty, ref components } => {
+                // Vectors can be composed of smaller vectors
+                let flatten: bool = matches!(module.types[ty].inner, TypeInner::Vector { .. });
+                let mut values: Vec<Value> = Vec::with_capacity(components.len());
+                for component in components {
+                    match self.evaluate(*component)? {
+                        Value::Composite(inner) if flatten => values.extend(inner),
+                        value => values.push(value),
+                    }
+                }
+                Value::Composite(values)
+            }
+            Expression::FunctionArgument(index) => self.frame().arguments[index as usize].clone(),
+            Expression::GlobalVariable(variable) => Value::Pointer(Pointer {
+                space: Space::Global(variable.index()),
+                offset: 0,
+                pointee: Pointee::Type(module.global_variables[variable].ty),
+            }),
+            Expression::LocalVariable(variable) => Value::Pointer(Pointer {
+                space: Space::Local {
+                    frame: self.frames.len() - 1,
+                    variable: variable.index(),
+                },
+                offset: 0,
+                pointee: Pointee::Type(function.local_variables[variable].ty),
+            }),
+            Expression::Load { pointer } => {
+                let pointer: Pointer = self.evaluate(pointer)?.pointer()?;
+                self.load(pointer)?
+            }
+            Expression::Unary { op, expr } => value::unary(op, &self.evaluate(expr)?)?,
+            Expression::Binary { op, left, right } => {
+                let left: Value = self.evaluate(left)?;
+                let right: Value = self.evaluate(right)?;
+                value::binary(op, &left, &right)?
+            }
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                let condition: Value = self.evaluate(condition)?;
+                let accept: Value = self.evaluate(accept)?;
+                let reject: Value = self.evaluate(reject)?;
+                match condition {
+                    Value::Scalar(_) => {
+                        if condition.as_bool()? {
+                            accept
+                        } else {
+                            reject
+                        }
+                    }
+                    _ => value::componentwise(&[condition, accept, reject], &|scalars| {
+                        Ok(if scalars[0] == Scalar::Bool(true) {
+                            scalars[1]
+                        } else {
+                            scalars[2]
+                        })
+                    })?,
+                }
+            }
+            Expression::Relational { fun, argument } => {
+                value::relational(fun, &self.evaluate(argument)?)?
+            }
+            Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+                arg3,
+            } => {
+                let mut arguments: Vec<Value> = vec![self.evaluate(arg)?];
+                for argument in [arg1, arg2, arg3].into_iter().flatten() {
+                    arguments.push(self.evaluate(argument)?);
+                }
+                value::math(fun, &arguments)?
+            }
+            Expression::As {
+                expr,
+                kind,
+                convert,
+            } => value::componentwise(&[self.evaluate(expr)?], &|scalars| {
+                Ok(match convert {
+                    Some(_) => scalars[0].convert(kind),
+                    None => Scalar::bitcast(scalars[0].bits(), kind),
+                })
+            })?,
+            Expression::ArrayLength(array) => {
+                let pointer: Pointer = self.evaluate(array)?.pointer()?;
+                let stride: usize = match pointer.pointee {
+                    Pointee::Type(ty) => match module.types[ty].inner {
+                        TypeInner::Array { stride, .. } => stride as usize,
+                        _ => {
+                            return Err(unsupported("the length of something other than an array"))
+                        }
+                    },
+                    Pointee::Scalar { .. } => {
+                        return Err(unsupported("the length of something other than an array"))
+                    }
+                };
+                let length: usize = self.with_memory(pointer.space, |bytes| bytes.len());
+                Value::Scalar(Scalar::U32(
+                    (length.saturating_sub(pointer.offset) / stride) as u32,
+                ))
+            }
+            // Set by the statements making them, when they are run
+            Expression::CallResult(_) | Expression::AtomicResult { .. } => {
+                return Err(unsupported("a result used before it was made"))
+            }
+            ref expression => {
+                return Err(InterpreterError::Unsupported {
+                    feature: format!("the expression {:?}", expression),
+                })
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn atomic(
+        &mut self,
+        pointer: Pointer,
+        function: &AtomicFunction,
+        value: Scalar,
+        compare: Option<Scalar>,
+    ) -> Result<Scalar, InterpreterError> {
+        let kind: ScalarKind = match pointer.pointee {
+            Pointee::Type(ty) => match self.module.types[ty].inner {
+                TypeInner::Atomic { kind, .. } => kind,
+                _ => return Err(unsupported("atomics on something other than an atomic")),
+            },
+            Pointee::Scalar { .. } => {
+                return Err(unsupported("atomics on something other than an atomic"))
+            }
+        };
+
+        // The lock is held from the read to the write, which makes it atomic
+        let result: Result<Option<Scalar>, InterpreterError> =
+            self.with_memory(pointer.space, |bytes| {
+                let old: Scalar = match Scalar::read(bytes, pointer.offset, kind, 4) {
+                    Some(old) => old,
+                    None => return Ok(None),
+                };
+                let binary = |operator: BinaryOperator| {
+                    value::binary(operator, &Value::Scalar(old), &Value::Scalar(value))?.scalar()
+                };
+                let new: Scalar = match function {
+                    AtomicFunction::Add => binary(BinaryOperator::Add)?,
+                    AtomicFunction::Subtract => binary(BinaryOperator::Subtract)?,
+                    AtomicFunction::And => binary(BinaryOperator::And)?,
+                    AtomicFunction::ExclusiveOr => binary(BinaryOperator::ExclusiveOr)?,
+                    AtomicFunction::InclusiveOr => binary(BinaryOperator::InclusiveOr)?,
+                    AtomicFunction::Min => value::math(
+                        naga::MathFunction::Min,
+                        &[Value::Scalar(old), Value::Scalar(value)],
+                    )?
+                    .scalar()?,
+                    AtomicFunction::Max => value::math(
+                        naga::MathFunction::Max,
+                        &[Value::Scalar(old), Value::Scalar(value)],
+                    )?
+                    .scalar()?,
+                    AtomicFunction::Exchange { compare: None } => value,
+                    AtomicFunction::Exchange { compare: Some(_) } => {
+                        if Some(old) == compare {
+                            value
+                        } else {
+                            old
+                        }
+                    }
+                };
+                Ok(new.write(bytes, pointer.offset).map(|_| old))
+            });
+
+        match result? {
+            Some(old) => Ok(old),
+            None => Err(self.out_of_bounds(pointer.space, pointer.offset)),
+        }
+    }
+
+    fn call(
+        &mut self,
+        function: &'a Function,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>, InterpreterError> {
+        let module: &Module = self.module;
+        let mut locals: Vec<Vec<u8>> = Vec::with_capacity(function.local_variables.len());
+        for (_, variable) in function.local_variables.iter() {
+            let size: u32 = module.types[variable.ty].inner.size(&module.constants);
+            let mut bytes: Vec<u8> = vec![0; size as usize];
+            if let Some(init) = variable.init {
+                write_value(
+                    module,
+                    &mut bytes,
+                    0,
+                    Pointee::Type(variable.ty),
+                    &constant_value(module, init),
+                )
+                .ok_or_else(|| unsupported("the initializer of a local variable"))?;
+            }
+            locals.push(bytes);
+        }
+
+        self.frames.push(Frame {
+            function,
+            arguments,
+            locals,
+            expressions: vec![None; function.expressions.len()],
+        });
+        let flow: Result<ControlFlow, InterpreterError> = self.execute_block(&function.body);
+        self.frames.pop();
+
+        match flow? {
+            ControlFlow::Return(value) => Ok(value),
+            _ => Ok(None),
+        }
+    }
+
+    fn execute_block(&mut self, block: &'a Block) -> Result<ControlFlow, InterpreterError> {
+        for statement in block.iter() {
+            let flow: ControlFlow = self.execute(statement)?;
+            if !matches!(flow, ControlFlow::Next) {
+                return Ok(flow);
+            }
+        }
+
+        Ok(ControlFlow::Next)
+    }
+
+    fn execute(&mut self, statement: &'a Statement) -> Result<ControlFlow, InterpreterError> {
+        match *statement {
+            Statement::Emit(ref range) => {
+                for expression in range.clone() {
+                    let value: Value = self.evaluate_uncached(expression)?;
+                    self.frame().expressions[expression.index()] = Some(value);
+                }
+            }
+            Statement::Block(ref block) => return self.execute_block(block),
+            Statement::If {
+                condition,
+                ref accept,
+                ref reject,
+            } => {
+                let block: &'a Block = if self.evaluate(condition)?.as_bool()? {
+                    accept
+                } else {
+                    reject
+                };
+                return self.execute_block(block);
+            }
+            Statement::Switch {
+                selector,
+                ref cases,
+            } => {
+                let selector: Scalar = self.evaluate(selector)?.scalar()?;
+                let matches = |value: &SwitchValue| match (value, selector) {
+                    (SwitchValue::I32(value), Scalar::I32(selector)) => *value == selector,
+                    (SwitchValue::U32(value), Scalar::U32(selector)) => *value == selector,
+                    _ => false,
+                };
+                let first: Option<usize> = cases
+                    .iter()
+                    .position(|case| matches(&case.value))
+                    .or_else(|| {
+                        cases
+                            .iter()
+                            .position(|case| case.value == SwitchValue::Default)
+                    });
+
+                if let Some(first) = first {
+                    for case in &cases[first..] {
+                        match self.execute_block(&case.body)? {
+                            // A break leaves the switch, not the loop around it
+                            ControlFlow::Break => break,
+                            ControlFlow::Next if case.fall_through => continue,
+                            ControlFlow::Next => break,
+                            flow => return Ok(flow),
+                        }
+                    }
+                }
+            }
+            Statement::Loop {
+                ref body,
+                ref continuing,
+                break_if,
+            } => loop {
+                match self.execute_block(body)? {
+                    ControlFlow::Break => break,
+                    ControlFlow::Return(value) => return Ok(ControlFlow::Return(value)),
+                    ControlFlow::Next | ControlFlow::Continue => {}
+                }
+                if let ControlFlow::Return(value) = self.execute_block(continuing)? {
+                    return Ok(ControlFlow::Return(value));
+                }
+                if let Some(break_if) = break_if {
+                    if self.evaluate(break_if)?.as_bool()? {
+                        break;
+                    }
+                }
+            },
+            Statement::Break => return Ok(ControlFlow::Break),
+            Statement::Continue => return Ok(ControlFlow::Continue),
+            Statement::Return { value } => {
+                let value: Option<Value> = match value {
+                    Some(value) => Some(self.evaluate(value)?),
+                    None => None,
+                };
+                return Ok(ControlFlow::Return(value));
+            }
+            Statement::Barrier(_) => {
+                if let Some(barrier) = self.barrier {
+                    barrier.wait();
+                }
+            }
+            Statement::Store { pointer, value } => {
+                let pointer: Pointer = self.evaluate(pointer)?.pointer()?;
+                let value: Value = self.evaluate(value)?;
+                self.store(pointer, &value)?;
+            }
+            Statement::Atomic {
+                pointer,
+                ref fun,
+                value,
+                result,
+            } => {
+                let pointer: Pointer = self.evaluate(pointer)?.pointer()?;
+                let value: Scalar = self.evaluate(value)?.scalar()?;
+                let compare: Option<Scalar> = match *fun {
+                    AtomicFunction::Exchange {
+                        compare: Some(compare),
+                    } => Some(self.evaluate(compare)?.scalar()?),
+                    _ => None,
+                };
+                let old: Scalar = self.atomic(pointer, fun, value, compare)?;
+                let result_value: Value = match compare {
+                    Some(compare) => Value::Composite(vec![
+                        Value::Scalar(old),
+                        Value::Scalar(Scalar::Bool(old == compare)),
+                    ]),
+                    None => Value::Scalar(old),
+                };
+                self.frame().expressions[result.index()] = Some(result_value);
+            }
+            Statement::Call {
+                function,
+                ref arguments,
+                result,
+            } => {
+                let mut values: Vec<Value> = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    values.push(self.evaluate(*argument)?);
+                }
+                let value: Option<Value> = self.call(&self.module.functions[function], values)?;
+                if let (Some(result), Some(value)) = (result, value) {
+                    self.frame().expressions[result.index()] = Some(value);
+                }
+            }
+            ref statement => {
+                return Err(InterpreterError::Unsupported {
+                    feature: format!("the statement {:?}", statement),
+                })
+            }
+        }
+
+        Ok(ControlFlow::Next)
+    }
+}
@@ -0,0 +1,5 @@
+pub mod device;
+pub mod executor;
+pub mod executor_test;
+pub mod invocation;
+pub mod value;
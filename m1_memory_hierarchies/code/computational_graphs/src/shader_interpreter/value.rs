@@ -0,0 +1,511 @@
+use naga::{
+    BinaryOperator, Handle, MathFunction, RelationalFunction, ScalarKind, Type, UnaryOperator,
+    VectorSize,
+};
+
+use super::executor::InterpreterError;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scalar {
+    Bool(bool),
+    I32(i32),
+    U32(u32),
+    F32(f32),
+}
+
+// Where a pointer points. Globals are indexed by their handle in the module, locals by the
+// frame of the function call they belong to and their handle in that function.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Space {
+    Global(usize),
+    Local { frame: usize, variable: usize },
+}
+
+// What a pointer points to. Pointers into vectors point to a scalar, which has no type handle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pointee {
+    Type(Handle<Type>),
+    Scalar { kind: ScalarKind, width: u8 },
+}
+
+// All memory is kept as bytes with the layout of the shader, so the buffers bound to
+// the shader are the same bytes as they would have been on the GPU
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pointer {
+    pub space: Space,
+    pub offset: usize,
+    pub pointee: Pointee,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Scalar(Scalar),
+    // Vectors, arrays and structs
+    Composite(Vec<Value>),
+    Pointer(Pointer),
+}
+
+fn unsupported(feature: String) -> InterpreterError {
+    InterpreterError::Unsupported { feature }
+}
+
+impl Scalar {
+    pub fn read(bytes: &[u8], offset: usize, kind: ScalarKind, width: u8) -> Option<Scalar> {
+        if kind == ScalarKind::Bool {
+            return bytes.get(offset).map(|byte| Scalar::Bool(*byte != 0));
+        }
+
+        let word: [u8; 4] = bytes
+            .get(offset..offset + width as usize)?
+            .try_into()
+            .ok()?;
+        let bits: u32 = u32::from_le_bytes(word);
+        match kind {
+            ScalarKind::Sint => Some(Scalar::I32(bits as i32)),
+            ScalarKind::Uint => Some(Scalar::U32(bits)),
+            ScalarKind::Float => Some(Scalar::F32(f32::from_bits(bits))),
+            ScalarKind::Bool => None,
+        }
+    }
+
+    // Returns None if the scalar doesn't fit in the bytes
+    pub fn write(self, bytes: &mut [u8], offset: usize) -> Option<()> {
+        match self {
+            Scalar::Bool(value) => *bytes.get_mut(offset)? = value as u8,
+            _ => bytes
+                .get_mut(offset..offset + 4)?
+                .copy_from_slice(&self.bits().to_le_bytes()),
+        }
+        Some(())
+    }
+
+    pub fn bits(self) -> u32 {
+        match self {
+            Scalar::Bool(value) => value as u32,
+            Scalar::I32(value) => value as u32,
+            Scalar::U32(value) => value,
+            Scalar::F32(value) => value.to_bits(),
+        }
+    }
+
+    // Converts the value, like u32(x) in WGSL. Floats are clamped to the range of the integer.
+    pub fn convert(self, kind: ScalarKind) -> Scalar {
+        match (self, kind) {
+            (Scalar::Bool(value), ScalarKind::Float) => Scalar::F32(value as u32 as f32),
+            (Scalar::Bool(value), ScalarKind::Sint) => Scalar::I32(value as i32),
+            (Scalar::Bool(value), ScalarKind::Uint) => Scalar::U32(value as u32),
+            (Scalar::I32(value), ScalarKind::Float) => Scalar::F32(value as f32),
+            (Scalar::U32(value), ScalarKind::Float) => Scalar::F32(value as f32),
+            (Scalar::F32(value), ScalarKind::Sint) => Scalar::I32(value as i32),
+            (Scalar::F32(value), ScalarKind::Uint) => Scalar::U32(value as u32),
+            (scalar, ScalarKind::Bool) => Scalar::Bool(match scalar {
+                Scalar::F32(value) => value != 0.0,
+                _ => scalar.bits() != 0,
+            }),
+            (scalar, _) => Scalar::bitcast(scalar.bits(), kind),
+        }
+    }
+
+    // Reinterprets the bits, like bitcast<u32>(x) in WGSL
+    pub fn bitcast(bits: u32, kind: ScalarKind) -> Scalar {
+        match kind {
+            ScalarKind::Bool => Scalar::Bool(bits != 0),
+            ScalarKind::Sint => Scalar::I32(bits as i32),
+            ScalarKind::Uint => Scalar::U32(bits),
+            ScalarKind::Float => Scalar::F32(f32::from_bits(bits)),
+        }
+    }
+}
+
+impl Value {
+    pub fn scalar(&self) -> Result<Scalar, InterpreterError> {
+        match self {
+            Value::Scalar(scalar) => Ok(*scalar),
+            _ => Err(unsupported(format!("{:?} used as a scalar", self))),
+        }
+    }
+
+    pub fn pointer(&self) -> Result<Pointer, InterpreterError> {
+        match self {
+            Value::Pointer(pointer) => Ok(*pointer),
+            _ => Err(unsupported(format!("{:?} used as a pointer", self))),
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, InterpreterError> {
+        match self.scalar()? {
+            Scalar::Bool(value) => Ok(value),
+            scalar => Err(unsupported(format!("{:?} used as a condition", scalar))),
+        }
+    }
+
+    pub fn as_index(&self) -> Result<usize, InterpreterError> {
+        match self.scalar()? {
+            Scalar::U32(value) => Ok(value as usize),
+            // A negative index is out of bounds of anything
+            Scalar::I32(value) => Ok(usize::try_from(value).unwrap_or(usize::MAX)),
+            scalar => Err(unsupported(format!("{:?} used as an index", scalar))),
+        }
+    }
+}
+
+// Applies the function to every component, a scalar argument is used for every component
+pub fn componentwise(
+    arguments: &[Value],
+    function: &dyn Fn(&[Scalar]) -> Result<Scalar, InterpreterError>,
+) -> Result<Value, InterpreterError> {
+    let component_count: Option<usize> = arguments.iter().find_map(|argument| match argument {
+        Value::Composite(components) => Some(components.len()),
+        _ => None,
+    });
+
+    match component_count {
+        None => {
+            let scalars: Vec<Scalar> = arguments
+                .iter()
+                .map(Value::scalar)
+                .collect::<Result<Vec<Scalar>, InterpreterError>>()?;
+            Ok(Value::Scalar(function(&scalars)?))
+        }
+        Some(component_count) => {
+            let mut components: Vec<Value> = Vec::with_capacity(component_count);
+            for component in 0..component_count {
+                let scalars: Vec<Scalar> = arguments
+                    .iter()
+                    .map(|argument| match argument {
+                        Value::Composite(components) => components[component].scalar(),
+                        _ => argument.scalar(),
+                    })
+                    .collect::<Result<Vec<Scalar>, InterpreterError>>()?;
+                components.push(Value::Scalar(function(&scalars)?));
+            }
+            Ok(Value::Composite(components))
+        }
+    }
+}
+
+pub fn unary(operator: UnaryOperator, value: &Value) -> Result<Value, InterpreterError> {
+    componentwise(
+        std::slice::from_ref(value),
+        &|scalars| match (operator, scalars[0]) {
+            (UnaryOperator::Negate, Scalar::F32(value)) => Ok(Scalar::F32(-value)),
+            (UnaryOperator::Negate, Scalar::I32(value)) => Ok(Scalar::I32(value.wrapping_neg())),
+            (UnaryOperator::Not, Scalar::Bool(value)) => Ok(Scalar::Bool(!value)),
+            (UnaryOperator::Not, Scalar::I32(value)) => Ok(Scalar::I32(!value)),
+            (UnaryOperator::Not, Scalar::U32(value)) => Ok(Scalar::U32(!value)),
+            (operator, scalar) => Err(unsupported(format!("{:?} of {:?}", operator, scalar))),
+        },
+    )
+}
+
+fn compare<T: PartialOrd>(operator: BinaryOperator, left: T, right: T) -> Option<Scalar> {
+    let result: bool = match operator {
+        BinaryOperator::Equal => left == right,
+        BinaryOperator::NotEqual => left != right,
+        BinaryOperator::Less => left < right,
+        BinaryOperator::LessEqual => left <= right,
+        BinaryOperator::Greater => left > right,
+        BinaryOperator::GreaterEqual => left >= right,
+        _ => return None,
+    };
+    Some(Scalar::Bool(result))
+}
+
+// Integer division by zero gives the left operand and the remainder is zero, like in WGSL
+fn binary_scalar(
+    operator: BinaryOperator,
+    left: Scalar,
+    right: Scalar,
+) -> Result<Scalar, InterpreterError> {
+    use BinaryOperator::*;
+
+    let result: Option<Scalar> = match (left, right) {
+        (Scalar::F32(left), Scalar::F32(right)) => match operator {
+            Add => Some(Scalar::F32(left + right)),
+            Subtract => Some(Scalar::F32(left - right)),
+            Multiply => Some(Scalar::F32(left * right)),
+            Divide => Some(Scalar::F32(left / right)),
+            Modulo => Some(Scalar::F32(left % right)),
+            _ => compare(operator, left, right),
+        },
+        (Scalar::I32(left), Scalar::I32(right)) => match operator {
+            Add => Some(Scalar::I32(left.wrapping_add(right))),
+            Subtract => Some(Scalar::I32(left.wrapping_sub(right))),
+            Multiply => Some(Scalar::I32(left.wrapping_mul(right))),
+            Divide => Some(Scalar::I32(left.checked_div(right).unwrap_or(left))),
+            Modulo => Some(Scalar::I32(left.checked_rem(right).unwrap_or(0))),
+            And => Some(Scalar::I32(left & right)),
+            InclusiveOr => Some(Scalar::I32(left | right)),
+            ExclusiveOr => Some(Scalar::I32(left ^ right)),
+            _ => compare(operator, left, right),
+        },
+        (Scalar::U32(left), Scalar::U32(right)) => match operator {
+            Add => Some(Scalar::U32(left.wrapping_add(right))),
+            Subtract => Some(Scalar::U32(left.wrapping_sub(right))),
+            Multiply => Some(Scalar::U32(left.wrapping_mul(right))),
+            Divide => Some(Scalar::U32(left.checked_div(right).unwrap_or(left))),
+            Modulo => Some(Scalar::U32(left.checked_rem(right).unwrap_or(0))),
+            And => Some(Scalar::U32(left & right)),
+            InclusiveOr => Some(Scalar::U32(left | right)),
+            ExclusiveOr => Some(Scalar::U32(left ^ right)),
+            ShiftLeft => Some(Scalar::U32(left << (right & 31))),
+            ShiftRight => Some(Scalar::U32(left >> (right & 31))),
+            _ => compare(operator, left, right),
+        },
+        // The shift amount is always unsigned
+        (Scalar::I32(left), Scalar::U32(right)) => match operator {
+            ShiftLeft => Some(Scalar::I32(left << (right & 31))),
+            ShiftRight => Some(Scalar::I32(left >> (right & 31))),
+            _ => None,
+        },
+        (Scalar::Bool(left), Scalar::Bool(right)) => match operator {
+            And | LogicalAnd => Some(Scalar::Bool(left && right)),
+            InclusiveOr | LogicalOr => Some(Scalar::Bool(left || right)),
+            Equal => Some(Scalar::Bool(left == right)),
+            NotEqual => Some(Scalar::Bool(left != right)),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    result.ok_or_else(|| unsupported(format!("{:?} of {:?} and {:?}", operator, left, right)))
+}
+
+pub fn binary(
+    operator: BinaryOperator,
+    left: &Value,
+    right: &Value,
+) -> Result<Value, InterpreterError> {
+    // Most operations are on scalars, which don't need the vectors of componentwise
+    if let (Value::Scalar(left), Value::Scalar(right)) = (left, right) {
+        return Ok(Value::Scalar(binary_scalar(operator, *left, *right)?));
+    }
+
+    componentwise(&[left.clone(), right.clone()], &|scalars| {
+        binary_scalar(operator, scalars[0], scalars[1])
+    })
+}
+
+fn float(scalar: Scalar) -> Result<f32, InterpreterError> {
+    match scalar {
+        Scalar::F32(value) => Ok(value),
+        _ => Err(unsupported(format!("{:?} used as a float", scalar))),
+    }
+}
+
+fn float_components(value: &Value) -> Result<Vec<f32>, InterpreterError> {
+    match value {
+        Value::Composite(components) => components
+            .iter()
+            .map(|component| float(component.scalar()?))
+            .collect(),
+        _ => Ok(vec![float(value.scalar()?)?]),
+    }
+}
+
+fn dot(left: &Value, right: &Value) -> Result<f32, InterpreterError> {
+    Ok(float_components(left)?
+        .iter()
+        .zip(float_components(right)?)
+        .map(|(left, right)| left * right)
+        .sum())
+}
+
+fn float_function(
+    function: MathFunction,
+    arguments: &[Value],
+    apply: impl Fn(&[f32]) -> f32,
+) -> Result<Value, InterpreterError> {
+    componentwise(arguments, &|scalars| {
+        let floats: Vec<f32> = scalars
+            .iter()
+            .map(|scalar| float(*scalar))
+            .collect::<Result<Vec<f32>, InterpreterError>>()
+            .map_err(|_| unsupported(format!("{:?} of {:?}", function, scalars)))?;
+        Ok(Scalar::F32(apply(&floats)))
+    })
+}
+
+fn integer_function(
+    function: MathFunction,
+    arguments: &[Value],
+    apply: impl Fn(u32, bool) -> u32,
+) -> Result<Value, InterpreterError> {
+    componentwise(arguments, &|scalars| match scalars[0] {
+        Scalar::U32(value) => Ok(Scalar::U32(apply(value, false))),
+        Scalar::I32(value) => Ok(Scalar::I32(apply(value as u32, true) as i32)),
+        scalar => Err(unsupported(format!("{:?} of {:?}", function, scalar))),
+    })
+}
+
+fn ordered(
+    function: MathFunction,
+    scalars: &[Scalar],
+    float: fn(f32, f32) -> f32,
+    integer: fn(i64, i64) -> i64,
+) -> Result<Scalar, InterpreterError> {
+    match (scalars[0], scalars[1]) {
+        (Scalar::F32(left), Scalar::F32(right)) => Ok(Scalar::F32(float(left, right))),
+        (Scalar::I32(left), Scalar::I32(right)) => {
+            Ok(Scalar::I32(integer(left as i64, right as i64) as i32))
+        }
+        (Scalar::U32(left), Scalar::U32(right)) => {
+            Ok(Scalar::U32(integer(left as i64, right as i64) as u32))
+        }
+        _ => Err(unsupported(format!("{:?} of {:?}", function, scalars))),
+    }
+}
+
+pub fn math(function: MathFunction, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    use MathFunction::*;
+
+    match function {
+        Abs => componentwise(arguments, &|scalars| match scalars[0] {
+            Scalar::F32(value) => Ok(Scalar::F32(value.abs())),
+            Scalar::I32(value) => Ok(Scalar::I32(value.wrapping_abs())),
+            Scalar::U32(value) => Ok(Scalar::U32(value)),
+            scalar => Err(unsupported(format!("{:?} of {:?}", function, scalar))),
+        }),
+        Min => componentwise(arguments, &|scalars| {
+            ordered(function, scalars, f32::min, i64::min)
+        }),
+        Max => componentwise(arguments, &|scalars| {
+            ordered(function, scalars, f32::max, i64::max)
+        }),
+        Clamp => componentwise(arguments, &|scalars| {
+            let low: Scalar = ordered(function, &scalars[0..2], f32::max, i64::max)?;
+            ordered(function, &[low, scalars[2]], f32::min, i64::min)
+        }),
+        Saturate => float_function(function, arguments, |x| x[0].clamp(0.0, 1.0)),
+        Cos => float_function(function, arguments, |x| x[0].cos()),
+        Cosh => float_function(function, arguments, |x| x[0].cosh()),
+        Sin => float_function(function, arguments, |x| x[0].sin()),
+        Sinh => float_function(function, arguments, |x| x[0].sinh()),
+        Tan => float_function(function, arguments, |x| x[0].tan()),
+        Tanh => float_function(function, arguments, |x| x[0].tanh()),
+        Acos => float_function(function, arguments, |x| x[0].acos()),
+        Asin => float_function(function, arguments, |x| x[0].asin()),
+        Atan => float_function(function, arguments, |x| x[0].atan()),
+        Atan2 => float_function(function, arguments, |x| x[0].atan2(x[1])),
+        Asinh => float_function(function, arguments, |x| x[0].asinh()),
+        Acosh => float_function(function, arguments, |x| x[0].acosh()),
+        Atanh => float_function(function, arguments, |x| x[0].atanh()),
+        Radians => float_function(function, arguments, |x| x[0].to_radians()),
+        Degrees => float_function(function, arguments, |x| x[0].to_degrees()),
+        Ceil => float_function(function, arguments, |x| x[0].ceil()),
+        Floor => float_function(function, arguments, |x| x[0].floor()),
+        Round => float_function(function, arguments, |x| x[0].round_ties_even()),
+        Fract => float_function(function, arguments, |x| x[0] - x[0].floor()),
+        Trunc => float_function(function, arguments, |x| x[0].trunc()),
+        Exp => float_function(function, arguments, |x| x[0].exp()),
+        Exp2 => float_function(function, arguments, |x| x[0].exp2()),
+        Log => float_function(function, arguments, |x| x[0].ln()),
+        Log2 => float_function(function, arguments, |x| x[0].log2()),
+        Pow => float_function(function, arguments, |x| x[0].powf(x[1])),
+        Sqrt => float_function(function, arguments, |x| x[0].sqrt()),
+        InverseSqrt => float_function(function, arguments, |x| 1.0 / x[0].sqrt()),
+        Fma => float_function(function, arguments, |x| x[0].mul_add(x[1], x[2])),
+        Mix => float_function(function, arguments, |x| x[0] * (1.0 - x[2]) + x[1] * x[2]),
+        Step => float_function(
+            function,
+            arguments,
+            |x| if x[0] <= x[1] { 1.0 } else { 0.0 },
+        ),
+        SmoothStep => float_function(function, arguments, |x| {
+            let t: f32 = ((x[2] - x[0]) / (x[1] - x[0])).clamp(0.0, 1.0);
+            t * t * (3.0 - 2.0 * t)
+        }),
+        // Unlike signum, the sign of zero is zero
+        Sign => componentwise(arguments, &|scalars| match scalars[0] {
+            Scalar::F32(0.0) => Ok(Scalar::F32(0.0)),
+            Scalar::F32(value) => Ok(Scalar::F32(value.signum())),
+            Scalar::I32(value) => Ok(Scalar::I32(value.signum())),
+            scalar => Err(unsupported(format!("{:?} of {:?}", function, scalar))),
+        }),
+        Dot => match binary(BinaryOperator::Multiply, &arguments[0], &arguments[1])? {
+            Value::Composite(products) => {
+                let mut sum: Value = products[0].clone();
+                for product in &products[1..] {
+                    sum = binary(BinaryOperator::Add, &sum, product)?;
+                }
+                Ok(sum)
+            }
+            _ => Err(unsupported(format!("{:?} of {:?}", function, arguments))),
+        },
+        Length => Ok(Value::Scalar(Scalar::F32(
+            dot(&arguments[0], &arguments[0])?.sqrt(),
+        ))),
+        Distance => {
+            let difference: Value = binary(BinaryOperator::Subtract, &arguments[0], &arguments[1])?;
+            Ok(Value::Scalar(Scalar::F32(
+                dot(&difference, &difference)?.sqrt(),
+            )))
+        }
+        Normalize => {
+            let length: f32 = dot(&arguments[0], &arguments[0])?.sqrt();
+            binary(
+                BinaryOperator::Divide,
+                &arguments[0],
+                &Value::Scalar(Scalar::F32(length)),
+            )
+        }
+        CountOneBits => integer_function(function, arguments, |x, _| x.count_ones()),
+        ReverseBits => integer_function(function, arguments, |x, _| x.reverse_bits()),
+        CountLeadingZeros => integer_function(function, arguments, |x, _| x.leading_zeros()),
+        CountTrailingZeros => integer_function(function, arguments, |x, _| x.trailing_zeros()),
+        // -1 if there is no such bit
+        FindLsb => integer_function(function, arguments, |x, _| {
+            if x == 0 {
+                u32::MAX
+            } else {
+                x.trailing_zeros()
+            }
+        }),
+        // For negative signed integers it is the most significant 0 bit
+        FindMsb => integer_function(function, arguments, |x, signed| {
+            let x: u32 = if signed && (x as i32) < 0 { !x } else { x };
+            if x == 0 {
+                u32::MAX
+            } else {
+                31 - x.leading_zeros()
+            }
+        }),
+        _ => Err(unsupported(format!("{:?}", function))),
+    }
+}
+
+pub fn relational(
+    function: RelationalFunction,
+    argument: &Value,
+) -> Result<Value, InterpreterError> {
+    match function {
+        RelationalFunction::All | RelationalFunction::Any => {
+            let components: Vec<bool> = match argument {
+                Value::Composite(components) => components
+                    .iter()
+                    .map(Value::as_bool)
+                    .collect::<Result<Vec<bool>, InterpreterError>>()?,
+                _ => vec![argument.as_bool()?],
+            };
+            let result: bool = if function == RelationalFunction::All {
+                components.iter().all(|component| *component)
+            } else {
+                components.iter().any(|component| *component)
+            };
+            Ok(Value::Scalar(Scalar::Bool(result)))
+        }
+        _ => componentwise(std::slice::from_ref(argument), &|scalars| {
+            let x: f32 = float(scalars[0])?;
+            Ok(Scalar::Bool(match function {
+                RelationalFunction::IsNan => x.is_nan(),
+                RelationalFunction::IsInf => x.is_infinite(),
+                RelationalFunction::IsFinite => x.is_finite(),
+                _ => x.is_normal(),
+            }))
+        }),
+    }
+}
+
+pub fn vector_size(size: VectorSize) -> usize {
+    size as usize
+}
@@ -0,0 +1,173 @@
+use std::hash::{Hash, Hasher};
+
+// The elementwise activation functions other than ReLU. ReLU keeps its own operators,
+// as it is the one the rest of the tutorial is built around, while these all share the
+// Activation and LinearActivationFused operators with the function as a parameter.
+// The same functions are implemented in activation.wgsl, with shader_index deciding
+// which one is run, so the two have to be kept in sync.
+#[derive(Clone, Copy, Debug)]
+pub enum ActivationFunction {
+    // x * Φ(x), where Φ is the cumulative distribution function of the standard
+    // normal distribution, Φ(x) = 0.5 * (1 + erf(x / sqrt(2)))
+    GELUErf,
+    // The approximation of GELU used by GPT-2 and BERT
+    // 0.5 * x * (1 + tanh(sqrt(2 / π) * (x + 0.044715 * x^3)))
+    GELUTanh,
+    // x * sigmoid(x), also known as swish
+    SiLU,
+    Tanh,
+    Sigmoid,
+    // x for positive x, alpha * x otherwise
+    LeakyReLU { alpha: f32 },
+}
+
+// sqrt(2 / π)
+const GELU_TANH_SCALE: f32 = 0.797_884_6;
+const GELU_TANH_CUBIC: f32 = 0.044715;
+// 1 / sqrt(2 * π)
+const NORMAL_DENSITY_SCALE: f32 = 0.398_942_3;
+
+// The standard library doesn't have erf, so we use the approximation 7.1.26 from
+// Abramowitz and Stegun, which has an absolute error of at most 1.5e-7.
+// It only needs an exponential, which is also available in WGSL.
+pub fn erf(x: f32) -> f32 {
+    let t: f32 = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let polynomial: f32 = t
+        * (0.254_829_6
+            + t * (-0.284_496_74 + t * (1.421_413_8 + t * (-1.453_152 + t * 1.061_405_4))));
+    let result: f32 = 1.0 - polynomial * (-x * x).exp();
+
+    if x < 0.0 {
+        -result
+    } else {
+        result
+    }
+}
+
+// exp(-x) overflows for large negative x, in which case exp(x) is used instead
+pub fn sigmoid(x: f32) -> f32 {
+    if 0.0 <= x {
+        1.0 / (1.0 + (-x).exp())
+    } else {
+        let exponential: f32 = x.exp();
+        exponential / (1.0 + exponential)
+    }
+}
+
+impl ActivationFunction {
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            ActivationFunction::GELUErf => {
+                0.5 * x * (1.0 + erf(x * std::f32::consts::FRAC_1_SQRT_2))
+            }
+            ActivationFunction::GELUTanh => {
+                let inner: f32 = GELU_TANH_SCALE * (x + GELU_TANH_CUBIC * x * x * x);
+                0.5 * x * (1.0 + inner.tanh())
+            }
+            ActivationFunction::SiLU => x * sigmoid(x),
+            ActivationFunction::Tanh => x.tanh(),
+            ActivationFunction::Sigmoid => sigmoid(x),
+            ActivationFunction::LeakyReLU { alpha } => {
+                if 0.0 < x {
+                    x
+                } else {
+                    alpha * x
+                }
+            }
+        }
+    }
+
+    // The derivative with respect to the input. Unlike ReLU, most of these can't
+    // be recovered from the output, so the backward pass needs the input.
+    pub fn derivative(self, x: f32) -> f32 {
+        match self {
+            ActivationFunction::GELUErf => {
+                let cumulative: f32 = 0.5 * (1.0 + erf(x * std::f32::consts::FRAC_1_SQRT_2));
+                let density: f32 = NORMAL_DENSITY_SCALE * (-0.5 * x * x).exp();
+                cumulative + x * density
+            }
+            ActivationFunction::GELUTanh => {
+                let inner: f32 = GELU_TANH_SCALE * (x + GELU_TANH_CUBIC * x * x * x);
+                let tanh: f32 = inner.tanh();
+                let sech_squared: f32 = 1.0 - tanh * tanh;
+                // For large inputs x * inner_derivative overflows, which times 0 is NaN
+                if sech_squared == 0.0 {
+                    return 0.5 * (1.0 + tanh);
+                }
+                let inner_derivative: f32 = GELU_TANH_SCALE * (1.0 + 3.0 * GELU_TANH_CUBIC * x * x);
+                0.5 * (1.0 + tanh) + 0.5 * x * sech_squared * inner_derivative
+            }
+            ActivationFunction::SiLU => {
+                let sigmoid: f32 = sigmoid(x);
+                sigmoid * (1.0 + x * (1.0 - sigmoid))
+            }
+            ActivationFunction::Tanh => {
+                let tanh: f32 = x.tanh();
+                1.0 - tanh * tanh
+            }
+            ActivationFunction::Sigmoid => {
+                let sigmoid: f32 = sigmoid(x);
+                sigmoid * (1.0 - sigmoid)
+            }
+            ActivationFunction::LeakyReLU { alpha } => {
+                if 0.0 < x {
+                    1.0
+                } else {
+                    alpha
+                }
+            }
+        }
+    }
+
+    // Selects the function in activation.wgsl and in the main_with_activation
+    // entry point of linear.wgsl
+    pub fn shader_index(self) -> u32 {
+        match self {
+            ActivationFunction::GELUErf => 0,
+            ActivationFunction::GELUTanh => 1,
+            ActivationFunction::SiLU => 2,
+            ActivationFunction::Tanh => 3,
+            ActivationFunction::Sigmoid => 4,
+            ActivationFunction::LeakyReLU { .. } => 5,
+        }
+    }
+
+    // The only parameter any of the functions has
+    pub fn alpha(self) -> f32 {
+        match self {
+            ActivationFunction::LeakyReLU { alpha } => alpha,
+            _ => 0.0,
+        }
+    }
+
+    // A rough count for the FLOPs reported by graph::shape_inference. The exponentials
+    // and tanh are counted as a single FLOP each, like in softmax.
+    pub fn flops_per_element(self) -> usize {
+        match self {
+            ActivationFunction::GELUErf => 16,
+            ActivationFunction::GELUTanh => 9,
+            ActivationFunction::SiLU => 4,
+            ActivationFunction::Tanh => 1,
+            ActivationFunction::Sigmoid => 3,
+            ActivationFunction::LeakyReLU { .. } => 2,
+        }
+    }
+}
+
+// alpha is an f32, so the comparisons are on its bits. This makes the function usable
+// in NodeOperator, which is hashed, and means a NaN alpha is equal to itself.
+impl PartialEq for ActivationFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.shader_index() == other.shader_index()
+            && self.alpha().to_bits() == other.alpha().to_bits()
+    }
+}
+
+impl Eq for ActivationFunction {}
+
+impl Hash for ActivationFunction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.shader_index().hash(state);
+        self.alpha().to_bits().hash(state);
+    }
+}
@@ -0,0 +1,174 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::shared::activation::ActivationFunction;
+
+    const ERROR_TOLERANCE: f64 = 0.00001;
+
+    const FUNCTIONS: [ActivationFunction; 6] = [
+        ActivationFunction::GELUErf,
+        ActivationFunction::GELUTanh,
+        ActivationFunction::SiLU,
+        ActivationFunction::Tanh,
+        ActivationFunction::Sigmoid,
+        ActivationFunction::LeakyReLU { alpha: 0.1 },
+    ];
+
+    // The Taylor series of erf. It needs a lot of terms away from 0,
+    // but it is accurate in f64 for the inputs used here.
+    fn reference_erf(x: f64) -> f64 {
+        let mut sum: f64 = 0.0;
+        let mut power: f64 = x;
+        let mut factorial: f64 = 1.0;
+        for n in 0..100 {
+            if 0 < n {
+                power *= x * x;
+                factorial *= n as f64;
+            }
+            let sign: f64 = if n % 2 == 0 { 1.0 } else { -1.0 };
+            sum += sign * power / (factorial * (2 * n + 1) as f64);
+        }
+        sum * 2.0 / std::f64::consts::PI.sqrt()
+    }
+
+    fn reference_sigmoid(x: f64) -> f64 {
+        1.0 / (1.0 + (-x).exp())
+    }
+
+    // The textbook definitions in f64
+    fn reference(function: ActivationFunction, x: f64) -> f64 {
+        match function {
+            ActivationFunction::GELUErf => {
+                0.5 * x * (1.0 + reference_erf(x / std::f64::consts::SQRT_2))
+            }
+            ActivationFunction::GELUTanh => {
+                let scale: f64 = (2.0 / std::f64::consts::PI).sqrt();
+                0.5 * x * (1.0 + (scale * (x + 0.044715 * x.powi(3))).tanh())
+            }
+            ActivationFunction::SiLU => x * reference_sigmoid(x),
+            ActivationFunction::Tanh => x.tanh(),
+            ActivationFunction::Sigmoid => reference_sigmoid(x),
+            ActivationFunction::LeakyReLU { alpha } => {
+                if 0.0 < x {
+                    x
+                } else {
+                    alpha as f64 * x
+                }
+            }
+        }
+    }
+
+    fn inputs() -> impl Iterator<Item = f32> {
+        (-600..=600).map(|index| index as f32 * 0.01)
+    }
+
+    #[test]
+    fn apply() {
+        for function in FUNCTIONS {
+            for x in inputs() {
+                let expected: f64 = reference(function, x as f64);
+                let found: f32 = function.apply(x);
+                assert!(
+                    (expected - found as f64).abs() < ERROR_TOLERANCE,
+                    "function: {:?} x: {} expected: {} found: {}",
+                    function,
+                    x,
+                    expected,
+                    found
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn derivative() {
+        let epsilon: f64 = 0.0001;
+        for function in FUNCTIONS {
+            for x in inputs() {
+                // LeakyReLU has no derivative at 0
+                if x.abs() < 0.005 {
+                    continue;
+                }
+                let x_f64: f64 = x as f64;
+                let expected: f64 = (reference(function, x_f64 + epsilon)
+                    - reference(function, x_f64 - epsilon))
+                    / (2.0 * epsilon);
+                let found: f32 = function.derivative(x);
+                assert!(
+                    (expected - found as f64).abs() < 0.0001,
+                    "function: {:?} x: {} expected: {} found: {}",
+                    function,
+                    x,
+                    expected,
+                    found
+                );
+            }
+        }
+    }
+
+    // Nothing overflows into NaN, and the functions reach their limits
+    #[test]
+    fn large_magnitudes() {
+        for function in FUNCTIONS {
+            for x in [-1.0e30, -1.0e4, 1.0e4, 1.0e30] {
+                let output: f32 = function.apply(x);
+                let derivative: f32 = function.derivative(x);
+                assert!(
+                    output.is_finite() && derivative.is_finite(),
+                    "function: {:?} x: {} output: {} derivative: {}",
+                    function,
+                    x,
+                    output,
+                    derivative
+                );
+            }
+        }
+
+        for gelu in [ActivationFunction::GELUErf, ActivationFunction::GELUTanh] {
+            assert_eq!(gelu.apply(1.0e4), 1.0e4);
+            assert_eq!(gelu.apply(-1.0e4), 0.0);
+        }
+        assert_eq!(ActivationFunction::SiLU.apply(1.0e4), 1.0e4);
+        assert_eq!(ActivationFunction::SiLU.apply(-1.0e4), 0.0);
+        assert_eq!(ActivationFunction::Tanh.apply(1.0e4), 1.0);
+        assert_eq!(ActivationFunction::Tanh.apply(-1.0e4), -1.0);
+        assert_eq!(ActivationFunction::Sigmoid.apply(1.0e4), 1.0);
+        assert_eq!(ActivationFunction::Sigmoid.apply(-1.0e4), 0.0);
+    }
+
+    #[test]
+    fn leaky_relu_alpha() {
+        for alpha in [0.0, 0.01, 0.2, 1.0] {
+            let function: ActivationFunction = ActivationFunction::LeakyReLU { alpha };
+            assert_eq!(function.alpha(), alpha);
+            assert_eq!(function.apply(2.0), 2.0);
+            assert_eq!(function.apply(-2.0), -2.0 * alpha);
+            assert_eq!(function.derivative(2.0), 1.0);
+            assert_eq!(function.derivative(-2.0), alpha);
+        }
+    }
+
+    // The functions are keys in the node operators of the graph runners,
+    // so the alpha of LeakyReLU has to be part of the comparison
+    #[test]
+    fn equality() {
+        let shader_indices: HashSet<u32> = FUNCTIONS
+            .iter()
+            .map(|function| function.shader_index())
+            .collect();
+        assert_eq!(shader_indices.len(), FUNCTIONS.len());
+
+        let functions: HashSet<ActivationFunction> = [
+            ActivationFunction::LeakyReLU { alpha: 0.1 },
+            ActivationFunction::LeakyReLU { alpha: 0.1 },
+            ActivationFunction::LeakyReLU { alpha: 0.2 },
+            ActivationFunction::LeakyReLU { alpha: f32::NAN },
+            ActivationFunction::LeakyReLU { alpha: f32::NAN },
+            ActivationFunction::Tanh,
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(functions.len(), 4);
+    }
+}
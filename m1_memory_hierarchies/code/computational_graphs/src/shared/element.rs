@@ -0,0 +1,339 @@
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Sub};
+
+use half::{bf16, f16};
+
+// The types a Tensor2D can store. Every element type has to be convertible
+// to and from f32 and f64, which is how we move data between tensors of
+// different types and how we get data in and out of the generic kernels.
+// Converting to a smaller type rounds to the nearest representable value,
+// for i8 that means rounding to the nearest integer and saturating at -128 and 127.
+pub trait Element: Copy + Debug + Default + PartialOrd + Send + Sync + 'static {
+    const NAME: &'static str;
+
+    fn from_f32(value: f32) -> Self;
+    fn to_f32(self) -> f32;
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+impl Element for f32 {
+    const NAME: &'static str = "f32";
+
+    #[inline(always)]
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    #[inline(always)]
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    #[inline(always)]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Element for f64 {
+    const NAME: &'static str = "f64";
+
+    #[inline(always)]
+    fn from_f32(value: f32) -> Self {
+        value as f64
+    }
+
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    #[inline(always)]
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    #[inline(always)]
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+impl Element for f16 {
+    const NAME: &'static str = "f16";
+
+    #[inline(always)]
+    fn from_f32(value: f32) -> Self {
+        f16::from_f32(value)
+    }
+
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        f16::to_f32(self)
+    }
+
+    #[inline(always)]
+    fn from_f64(value: f64) -> Self {
+        f16::from_f64(value)
+    }
+
+    #[inline(always)]
+    fn to_f64(self) -> f64 {
+        f16::to_f64(self)
+    }
+}
+
+impl Element for bf16 {
+    const NAME: &'static str = "bf16";
+
+    #[inline(always)]
+    fn from_f32(value: f32) -> Self {
+        bf16::from_f32(value)
+    }
+
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        bf16::to_f32(self)
+    }
+
+    #[inline(always)]
+    fn from_f64(value: f64) -> Self {
+        bf16::from_f64(value)
+    }
+
+    #[inline(always)]
+    fn to_f64(self) -> f64 {
+        bf16::to_f64(self)
+    }
+}
+
+// The raw integer values. The scale and zero point needed to make sense of
+// quantized values live in QuantizedTensor2D.
+impl Element for i8 {
+    const NAME: &'static str = "i8";
+
+    #[inline(always)]
+    fn from_f32(value: f32) -> Self {
+        // Casting from float to integer saturates in Rust
+        value.round() as i8
+    }
+
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    #[inline(always)]
+    fn from_f64(value: f64) -> Self {
+        value.round() as i8
+    }
+
+    #[inline(always)]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Element for i32 {
+    const NAME: &'static str = "i32";
+
+    #[inline(always)]
+    fn from_f32(value: f32) -> Self {
+        value.round() as i32
+    }
+
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    #[inline(always)]
+    fn from_f64(value: f64) -> Self {
+        value.round() as i32
+    }
+
+    #[inline(always)]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+// The type used for summing up the products in a kernel like linear.
+// It doesn't have to be the same type as the elements. Accumulating f16 values
+// in f16 loses precision quickly, so we would usually accumulate in f32,
+// and products of i8 values can only be accumulated safely in i32.
+pub trait Accumulator:
+    Element + Add<Output = Self> + Mul<Output = Self> + Sub<Output = Self>
+{
+    fn zero() -> Self;
+    fn from_element<T: Element>(value: T) -> Self;
+    fn to_element<T: Element>(self) -> T;
+}
+
+// Softmax needs exp() and division, which only makes sense for floats.
+pub trait FloatAccumulator: Accumulator + Div<Output = Self> {
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn negative_infinity() -> Self;
+}
+
+impl Accumulator for f32 {
+    #[inline(always)]
+    fn zero() -> Self {
+        0.0
+    }
+
+    #[inline(always)]
+    fn from_element<T: Element>(value: T) -> Self {
+        value.to_f32()
+    }
+
+    #[inline(always)]
+    fn to_element<T: Element>(self) -> T {
+        T::from_f32(self)
+    }
+}
+
+impl Accumulator for f64 {
+    #[inline(always)]
+    fn zero() -> Self {
+        0.0
+    }
+
+    #[inline(always)]
+    fn from_element<T: Element>(value: T) -> Self {
+        value.to_f64()
+    }
+
+    #[inline(always)]
+    fn to_element<T: Element>(self) -> T {
+        T::from_f64(self)
+    }
+}
+
+impl Accumulator for f16 {
+    #[inline(always)]
+    fn zero() -> Self {
+        f16::ZERO
+    }
+
+    #[inline(always)]
+    fn from_element<T: Element>(value: T) -> Self {
+        f16::from_f32(value.to_f32())
+    }
+
+    #[inline(always)]
+    fn to_element<T: Element>(self) -> T {
+        T::from_f32(self.to_f32())
+    }
+}
+
+impl Accumulator for bf16 {
+    #[inline(always)]
+    fn zero() -> Self {
+        bf16::ZERO
+    }
+
+    #[inline(always)]
+    fn from_element<T: Element>(value: T) -> Self {
+        bf16::from_f32(value.to_f32())
+    }
+
+    #[inline(always)]
+    fn to_element<T: Element>(self) -> T {
+        T::from_f32(self.to_f32())
+    }
+}
+
+// Going through f64 is exact for every i8 and i32 value, which are the
+// element types we would accumulate in i32.
+impl Accumulator for i32 {
+    #[inline(always)]
+    fn zero() -> Self {
+        0
+    }
+
+    #[inline(always)]
+    fn from_element<T: Element>(value: T) -> Self {
+        value.to_f64() as i32
+    }
+
+    #[inline(always)]
+    fn to_element<T: Element>(self) -> T {
+        T::from_f64(self as f64)
+    }
+}
+
+impl FloatAccumulator for f32 {
+    #[inline(always)]
+    fn exp(self) -> Self {
+        f32::exp(self)
+    }
+
+    #[inline(always)]
+    fn ln(self) -> Self {
+        f32::ln(self)
+    }
+
+    #[inline(always)]
+    fn negative_infinity() -> Self {
+        f32::NEG_INFINITY
+    }
+}
+
+impl FloatAccumulator for f64 {
+    #[inline(always)]
+    fn exp(self) -> Self {
+        f64::exp(self)
+    }
+
+    #[inline(always)]
+    fn ln(self) -> Self {
+        f64::ln(self)
+    }
+
+    #[inline(always)]
+    fn negative_infinity() -> Self {
+        f64::NEG_INFINITY
+    }
+}
+
+impl FloatAccumulator for f16 {
+    #[inline(always)]
+    fn exp(self) -> Self {
+        f16::from_f32(self.to_f32().exp())
+    }
+
+    #[inline(always)]
+    fn ln(self) -> Self {
+        f16::from_f32(self.to_f32().ln())
+    }
+
+    #[inline(always)]
+    fn negative_infinity() -> Self {
+        f16::NEG_INFINITY
+    }
+}
+
+impl FloatAccumulator for bf16 {
+    #[inline(always)]
+    fn exp(self) -> Self {
+        bf16::from_f32(self.to_f32().exp())
+    }
+
+    #[inline(always)]
+    fn ln(self) -> Self {
+        bf16::from_f32(self.to_f32().ln())
+    }
+
+    #[inline(always)]
+    fn negative_infinity() -> Self {
+        bf16::NEG_INFINITY
+    }
+}
@@ -1,4 +1,4 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
 
 use wgpu::{
     Adapter, AdapterInfo, BindGroup, BindGroupEntry, BindGroupLayout, BindingResource,
@@ -7,6 +7,7 @@ use wgpu::{
 
 use crate::immediate::nodes::sum_from_tensor_2d;
 
+use super::pipeline_cache::PipelineCache;
 use super::tensor2d::Tensor2D;
 
 pub struct GPUHandles {
@@ -14,6 +15,10 @@ pub struct GPUHandles {
     pub device: Device,
     pub adapter: Adapter,
     pub adapter_info: AdapterInfo,
+    // Shared by every graph runner using this device which caches its pipelines.
+    // Only kept in memory, replace it with PipelineCache::with_directory to keep
+    // the compiled shaders between runs.
+    pub pipeline_cache: Arc<PipelineCache>,
 }
 
 pub async fn self_test() -> bool {
@@ -30,7 +35,8 @@ pub async fn self_test() -> bool {
         force_fallback_adapter: false,
     };
 
-    // `request_adapter` instantiates the general connection to the GPU
+    // `request_adapter` instantiates the general connection to the GPU.
+    // Returns None if the machine has no usable GPU.
     let adapter_option: Option<Adapter> = instance.request_adapter(&adapter_request).await;
 
     match adapter_option {
@@ -53,15 +59,15 @@ pub async fn initialize_gpu(warmup_gpu: bool) -> Option<GPUHandles> {
         dx12_shader_compiler: Default::default(),
     });
 
-    // `request_adapter` instantiates the general connection to the GPU
+    // `request_adapter` instantiates the general connection to the GPU.
+    // Returns None if the machine has no usable GPU.
     let adapter: Adapter = instance
         .request_adapter(&wgpu::RequestAdapterOptions {
             power_preference: wgpu::PowerPreference::HighPerformance,
             compatible_surface: None, // We aren't doing any graphics
             force_fallback_adapter: false,
         })
-        .await
-        .expect("Failed to find a usable GPU!");
+        .await?;
 
     // `request_device` instantiates the feature specific connection to the GPU, defining some parameters,
     //  `features` being the available features.
@@ -75,7 +81,7 @@ pub async fn initialize_gpu(warmup_gpu: bool) -> Option<GPUHandles> {
             None,
         )
         .await
-        .unwrap();
+        .ok()?;
 
     let adapter_info: AdapterInfo = adapter.get_info();
     // skip this on LavaPipe temporarily
@@ -88,6 +94,7 @@ pub async fn initialize_gpu(warmup_gpu: bool) -> Option<GPUHandles> {
         device,
         adapter,
         adapter_info,
+        pipeline_cache: Arc::new(PipelineCache::new()),
     };
 
     if warmup_gpu {
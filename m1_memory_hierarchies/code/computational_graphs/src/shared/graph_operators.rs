@@ -1,4 +1,8 @@
-use super::tensor2d::Tensor2D;
+use super::{
+    activation::ActivationFunction,
+    quantized_tensor2d::{QuantizationParameters, QuantizedTensor2D},
+    tensor2d::Tensor2D,
+};
 
 #[derive(Clone, Debug)]
 pub enum GraphOperator {
@@ -10,4 +14,62 @@ pub enum GraphOperator {
     Softmax,
     LinearReLUFused { weights: Tensor2D, bias: Tensor2D },
     LinearReLUSoftmaxFused { weights: Tensor2D, bias: Tensor2D },
+    // Loss operators reduce the output of the graph to a single value
+    // and are only allowed right before DeviceToHost.
+    MeanSquaredError { target: Tensor2D },
+    SoftmaxCrossEntropy { labels: Vec<usize> },
+    // Produced by graph::quantization::quantize_graph_operators. The input is quantized
+    // with input_parameters when the operator is run, and the products are accumulated
+    // in i32. The output is f32. Only supported by the CPU GraphRunner.
+    LinearInt8 {
+        weights: QuantizedTensor2D,
+        bias: Tensor2D,
+        input_parameters: QuantizationParameters,
+    },
+    LinearReLUInt8Fused {
+        weights: QuantizedTensor2D,
+        bias: Tensor2D,
+        input_parameters: QuantizationParameters,
+    },
+    // Named tensors turn the list of operators into a DAG. Every other operator still
+    // consumes the output of the operator before it, but Store gives that output a name,
+    // Load makes a named tensor the current one again, and Add and Concat combine the
+    // current tensor with a named tensor. This is what residual/skip connections need.
+    // graph::dag lowers a DAG of named nodes into this form in topological order.
+    Store {
+        name: String,
+    },
+    Load {
+        name: String,
+    },
+    // Elementwise current + named, both have to have the same dimensions
+    Add {
+        name: String,
+    },
+    // The columns of the current tensor followed by the columns of the named tensor,
+    // both have to have the same number of rows
+    Concat {
+        name: String,
+    },
+    // Produced by graph::fusion. Linear followed by Add, the named tensor is added
+    // to the output of the linear operator. This is the end of a residual block.
+    LinearAddFused {
+        weights: Tensor2D,
+        bias: Tensor2D,
+        name: String,
+    },
+    // Add followed by ReLU
+    AddReLUFused {
+        name: String,
+    },
+    // An elementwise activation function other than ReLU, see shared::activation
+    Activation {
+        function: ActivationFunction,
+    },
+    // Produced by graph::fusion. Linear followed by Activation.
+    LinearActivationFused {
+        weights: Tensor2D,
+        bias: Tensor2D,
+        function: ActivationFunction,
+    },
 }
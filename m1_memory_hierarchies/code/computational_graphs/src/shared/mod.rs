@@ -1,8 +1,23 @@
+pub mod activation;
+pub mod activation_test;
 pub mod benchmark_plot;
 pub mod configuration;
+pub mod element;
 pub mod gpu_utilities;
 pub mod graph_operators;
 pub mod performance_measurement;
+pub mod pipeline_cache;
+pub mod pipeline_cache_test;
+pub mod quantized_tensor2d;
+pub mod quantized_tensor2d_test;
+pub mod shader_validation;
+pub mod shader_validation_test;
 pub mod tensor2d;
 pub mod tensor2d_gpu;
+pub mod tensor2d_io;
+pub mod tensor2d_io_test;
 pub mod tensor2d_test;
+#[cfg(test)]
+pub mod tensor2d_test_utilities;
+pub mod tensor_nd;
+pub mod tensor_nd_test;
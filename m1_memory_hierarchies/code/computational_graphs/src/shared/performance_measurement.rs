@@ -4,8 +4,8 @@ use rand::prelude::*;
 use rand_chacha::ChaCha8Rng;
 
 use super::{
-    configuration::Configuration, gpu_utilities::GPUHandles, graph_operators::GraphOperator,
-    tensor2d::Tensor2D,
+    configuration::Configuration, element::Element, gpu_utilities::GPUHandles,
+    graph_operators::GraphOperator, tensor2d::Tensor2D,
 };
 
 #[derive(Debug, Default, Clone)]
@@ -53,10 +53,11 @@ impl PerformanceMeasurements {
 //
 // Utility
 //
-pub fn benchmark_function_vector(
+// The tensors are generated as f32 and converted to the element type of the functions
+pub fn benchmark_function_vector<T: Element>(
     config: &Configuration,
     names: Vec<String>,
-    functions: Vec<fn(&mut Tensor2D, &Tensor2D, &Tensor2D, &mut Tensor2D)>,
+    functions: Vec<fn(&mut Tensor2D<T>, &Tensor2D<T>, &Tensor2D<T>, &mut Tensor2D<T>)>,
     all_measurements: &mut Vec<PerformanceMeasurements>,
 ) {
     assert!(functions.len() == all_measurements.len());
@@ -71,10 +72,10 @@ pub fn benchmark_function_vector(
         let function = functions[test_index];
         for (size_index, size) in config.loop_range.iter().enumerate() {
             let size: usize = *size;
-            let mut input: Tensor2D = Tensor2D::new(0.5, size, size);
-            let weights: Tensor2D = Tensor2D::new(1.0, size, size);
-            let bias: Tensor2D = Tensor2D::new(0.1, size, size);
-            let mut out: Tensor2D = Tensor2D::new(0.0, size, size);
+            let mut input: Tensor2D<T> = Tensor2D::new(0.5, size, size).convert();
+            let weights: Tensor2D<T> = Tensor2D::new(1.0, size, size).convert();
+            let bias: Tensor2D<T> = Tensor2D::new(0.1, size, size).convert();
+            let mut out: Tensor2D<T> = Tensor2D::new(0.0, size, size).convert();
 
             let now: Instant = Instant::now();
             for _ in 0..config.loop_count {
@@ -144,18 +145,10 @@ pub enum GraphFunction {
     GraphLoop,
 }
 
-fn benchmark_function_vector_gpu_graph_inner_loop(
-    gpu_handles: &GPUHandles,
-    config: &Configuration,
-    measurement_index: usize,
-    size: usize,
-    depth: usize,
-    function_type: &GraphFunction,
-    function: fn(&GPUHandles, &Vec<GraphOperator>, usize, &mut Tensor2D),
-    performance_measurements: &mut [(u128, usize)],
-    total_elements_per_measurement: &mut [usize],
-    measure_depth: bool,
-) {
+// The graph measured by the graph benchmarks, depth linear operators of size x size
+// with a ReLU after a random selection of them. The seed only depends on the size
+// and the depth, so the same graph is generated for every function being measured.
+pub fn benchmark_graph(size: usize, depth: usize) -> Vec<GraphOperator> {
     let input: Tensor2D = Tensor2D::new(0.5, size, size);
     let mut graph: Vec<GraphOperator> = vec![GraphOperator::HostToDevice { input }];
 
@@ -178,6 +171,23 @@ fn benchmark_function_vector_gpu_graph_inner_loop(
     graph.push(GraphOperator::Softmax);
     graph.push(GraphOperator::DeviceToHost);
 
+    graph
+}
+
+fn benchmark_function_vector_gpu_graph_inner_loop(
+    gpu_handles: &GPUHandles,
+    config: &Configuration,
+    measurement_index: usize,
+    size: usize,
+    depth: usize,
+    function_type: &GraphFunction,
+    function: fn(&GPUHandles, &Vec<GraphOperator>, usize, &mut Tensor2D),
+    performance_measurements: &mut [(u128, usize)],
+    total_elements_per_measurement: &mut [usize],
+    measure_depth: bool,
+) {
+    let graph: Vec<GraphOperator> = benchmark_graph(size, depth);
+
     let mut out: Tensor2D = Tensor2D::new(0.0, size, size);
     match function_type {
         GraphFunction::Cpu | GraphFunction::Immediate | GraphFunction::Graph => {
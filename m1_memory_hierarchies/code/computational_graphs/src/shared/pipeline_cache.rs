@@ -0,0 +1,412 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use naga::Module;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use wgpu::{ComputePipeline, ShaderModule};
+
+use super::gpu_utilities::{create_compute_pipeline, GPUHandles};
+use super::shader_validation::{validate_wgsl, ShaderValidationError};
+
+// Compiled shaders and compute pipelines, which can be shared by every runner on a device,
+// see GPUHandles::pipeline_cache. A pipeline is keyed by a hash of its WGSL source, the
+// specialization constants and the entry point, so two runners asking for the same kernel
+// get the same pipeline, and a shader generated at runtime, like the ones from
+// op_code_compiler, is cached like any other.
+//
+// wgpu 0.16 has no way of getting the pipelines compiled by the driver back out, so what is
+// kept on disk is the step before that, the validated naga module wgpu would otherwise parse
+// the WGSL into. If the cache has a directory, every shader it compiles is written to it,
+// and the next process to ask for the same shader reads it instead of parsing the WGSL.
+
+// Bump this when the artifacts change, such as when naga is updated. Artifacts of
+// another version are compiled again and overwritten.
+const ARTIFACT_VERSION: u32 = 1;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// FNV-1a. The hashes name the files on disk, so unlike std's DefaultHasher
+// they have to be the same for every process and toolchain.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PipelineCacheError {
+    Validation(ShaderValidationError),
+    // The shader has no constant by that name declared on a line of its own, see specialize
+    UnknownConstant { label: String, name: String },
+    // Reading an artifact which isn't there or is out of date isn't an error, writing one is
+    Io { path: PathBuf, message: String },
+}
+
+impl fmt::Display for PipelineCacheError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineCacheError::Validation(error) => write!(formatter, "{}", error),
+            PipelineCacheError::UnknownConstant { label, name } => write!(
+                formatter,
+                "{} has no constant named {} to specialize",
+                label, name
+            ),
+            PipelineCacheError::Io { path, message } => {
+                write!(formatter, "failed to write {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PipelineCacheError {}
+
+impl From<ShaderValidationError> for PipelineCacheError {
+    fn from(error: ShaderValidationError) -> Self {
+        PipelineCacheError::Validation(error)
+    }
+}
+
+// The constants are sorted by name, the order they are given in doesn't matter
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderKey {
+    pub source_hash: u64,
+    pub constants: Vec<(String, String)>,
+}
+
+impl ShaderKey {
+    pub fn new(source: &str, constants: &[(&str, &str)]) -> Self {
+        let mut constants: Vec<(String, String)> = constants
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        constants.sort();
+
+        ShaderKey {
+            source_hash: fnv1a(FNV_OFFSET_BASIS, source.as_bytes()),
+            constants,
+        }
+    }
+
+    // The name of the artifact in the directory of the cache
+    pub fn file_name(&self) -> String {
+        let mut hash: u64 = fnv1a(FNV_OFFSET_BASIS, &self.source_hash.to_le_bytes());
+        for (name, value) in &self.constants {
+            hash = fnv1a(hash, name.as_bytes());
+            hash = fnv1a(hash, &[0]);
+            hash = fnv1a(hash, value.as_bytes());
+            hash = fnv1a(hash, &[0]);
+        }
+        format!("{:016x}.json", hash)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader: ShaderKey,
+    pub entry_point: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelineCacheStatistics {
+    // Pipelines which were already in the cache
+    pub hits: usize,
+    // Pipelines which had to be created
+    pub misses: usize,
+    // Shaders parsed and validated from WGSL
+    pub modules_compiled: usize,
+    // Shaders read from the directory of the cache instead
+    pub modules_loaded: usize,
+}
+
+impl PipelineCacheStatistics {
+    pub fn hit_rate(&self) -> f32 {
+        if self.hits + self.misses == 0 {
+            0.0
+        } else {
+            self.hits as f32 / (self.hits + self.misses) as f32
+        }
+    }
+}
+
+impl fmt::Display for PipelineCacheStatistics {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            formatter,
+            "Pipelines: {} hits, {} misses ({:.1}% hit rate)",
+            self.hits,
+            self.misses,
+            100.0 * self.hit_rate()
+        )?;
+        write!(
+            formatter,
+            "Shader modules: {} compiled, {} loaded from disk",
+            self.modules_compiled, self.modules_loaded
+        )
+    }
+}
+
+// What is written to the directory of the cache. The key is stored along with the module,
+// so a file name shared by two shaders can't hand one of them the other's module.
+#[derive(Serialize, Deserialize)]
+struct ShaderArtifact {
+    version: u32,
+    source_hash: u64,
+    constants: Vec<(String, String)>,
+    module: Module,
+}
+
+// Sets the values of module scope constants declared on a line of their own, such as
+// `const BLOCK_SIZE: u32 = 8u;` in linear.wgsl. This is what overridable constants are for,
+// which wgpu 0.16 doesn't support. Every constant has to be declared by the shader.
+pub fn specialize(
+    label: &str,
+    source: &str,
+    constants: &[(String, String)],
+) -> Result<String, PipelineCacheError> {
+    let mut specialized_constants: Vec<bool> = vec![false; constants.len()];
+    let mut specialized: String = String::with_capacity(source.len());
+
+    for line in source.split_inclusive('\n') {
+        let declared: Option<usize> = line.strip_prefix("const ").and_then(|declaration| {
+            let name: &str = declaration
+                .split(|character: char| {
+                    character == ':' || character == '=' || character.is_whitespace()
+                })
+                .next()
+                .unwrap_or("");
+            constants.iter().position(|(constant, _)| constant == name)
+        });
+
+        match (declared, line.find('='), line.find(';')) {
+            (Some(index), Some(equals), Some(semicolon)) if equals < semicolon => {
+                specialized.push_str(&line[..equals]);
+                specialized.push_str("= ");
+                specialized.push_str(&constants[index].1);
+                specialized.push_str(&line[semicolon..]);
+                specialized_constants[index] = true;
+            }
+            _ => specialized.push_str(line),
+        }
+    }
+
+    match specialized_constants.iter().position(|found| !found) {
+        Some(index) => Err(PipelineCacheError::UnknownConstant {
+            label: label.to_string(),
+            name: constants[index].0.clone(),
+        }),
+        None => Ok(specialized),
+    }
+}
+
+pub struct PipelineCache {
+    directory: Option<PathBuf>,
+    shader_modules: Mutex<HashMap<ShaderKey, Arc<ShaderModule>>>,
+    pipelines: Mutex<HashMap<PipelineKey, Arc<ComputePipeline>>>,
+    statistics: Mutex<PipelineCacheStatistics>,
+}
+
+impl Default for PipelineCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PipelineCache {
+    // Only kept in memory
+    pub fn new() -> Self {
+        PipelineCache {
+            directory: None,
+            shader_modules: Mutex::new(HashMap::<ShaderKey, Arc<ShaderModule>>::new()),
+            pipelines: Mutex::new(HashMap::<PipelineKey, Arc<ComputePipeline>>::new()),
+            statistics: Mutex::new(PipelineCacheStatistics::default()),
+        }
+    }
+
+    // The directory is created if it doesn't exist
+    pub fn with_directory(directory: &Path) -> Result<Self, PipelineCacheError> {
+        fs::create_dir_all(directory).map_err(|error| PipelineCacheError::Io {
+            path: directory.to_path_buf(),
+            message: error.to_string(),
+        })?;
+
+        Ok(PipelineCache {
+            directory: Some(directory.to_path_buf()),
+            ..Self::new()
+        })
+    }
+
+    pub fn directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+
+    pub fn statistics(&self) -> PipelineCacheStatistics {
+        *self.statistics.lock()
+    }
+
+    // The number of pipelines in memory
+    pub fn len(&self) -> usize {
+        self.pipelines.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The label is only used in error messages and in the GPU debugging tools.
+    // The locks aren't held while compiling, so two runners missing the same pipeline
+    // at the same time both compile it, and both count as a miss.
+    pub fn pipeline(
+        &self,
+        gpu_handles: &GPUHandles,
+        label: &str,
+        source: &str,
+        constants: &[(&str, &str)],
+        entry_point: &str,
+    ) -> Result<Arc<ComputePipeline>, PipelineCacheError> {
+        let key: PipelineKey = PipelineKey {
+            shader: ShaderKey::new(source, constants),
+            entry_point: entry_point.to_string(),
+        };
+
+        if let Some(pipeline) = self.pipelines.lock().get(&key) {
+            self.statistics.lock().hits += 1;
+            return Ok(pipeline.clone());
+        }
+        self.statistics.lock().misses += 1;
+
+        let shader_module: Arc<ShaderModule> =
+            self.shader_module_with_key(gpu_handles, label, source, &key.shader)?;
+        let pipeline: Arc<ComputePipeline> = Arc::new(create_compute_pipeline(
+            gpu_handles,
+            &shader_module,
+            entry_point,
+        ));
+        self.pipelines.lock().insert(key, pipeline.clone());
+
+        Ok(pipeline)
+    }
+
+    // Every entry point of a shader shares the same shader module
+    pub fn shader_module(
+        &self,
+        gpu_handles: &GPUHandles,
+        label: &str,
+        source: &str,
+        constants: &[(&str, &str)],
+    ) -> Result<Arc<ShaderModule>, PipelineCacheError> {
+        self.shader_module_with_key(
+            gpu_handles,
+            label,
+            source,
+            &ShaderKey::new(source, constants),
+        )
+    }
+
+    fn shader_module_with_key(
+        &self,
+        gpu_handles: &GPUHandles,
+        label: &str,
+        source: &str,
+        key: &ShaderKey,
+    ) -> Result<Arc<ShaderModule>, PipelineCacheError> {
+        if let Some(shader_module) = self.shader_modules.lock().get(key) {
+            return Ok(shader_module.clone());
+        }
+
+        let module: Module = self.module_with_key(label, source, key)?;
+        let shader_module: Arc<ShaderModule> = Arc::new(gpu_handles.device.create_shader_module(
+            wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Naga(Cow::Owned(module)),
+            },
+        ));
+        self.shader_modules
+            .lock()
+            .insert(key.clone(), shader_module.clone());
+
+        Ok(shader_module)
+    }
+
+    // The naga module of the specialized shader, read from the directory of the cache or
+    // compiled and written to it. This is the part of the cache which doesn't need a GPU.
+    pub fn module(
+        &self,
+        label: &str,
+        source: &str,
+        constants: &[(&str, &str)],
+    ) -> Result<Module, PipelineCacheError> {
+        self.module_with_key(label, source, &ShaderKey::new(source, constants))
+    }
+
+    fn module_with_key(
+        &self,
+        label: &str,
+        source: &str,
+        key: &ShaderKey,
+    ) -> Result<Module, PipelineCacheError> {
+        let path: Option<PathBuf> = self
+            .directory
+            .as_ref()
+            .map(|directory| directory.join(key.file_name()));
+
+        if let Some(module) = path.as_deref().and_then(|path| read_artifact(path, key)) {
+            self.statistics.lock().modules_loaded += 1;
+            return Ok(module);
+        }
+
+        let module: Module = validate_wgsl(label, &specialize(label, source, &key.constants)?)?;
+        self.statistics.lock().modules_compiled += 1;
+
+        match path {
+            Some(path) => {
+                let artifact: ShaderArtifact = ShaderArtifact {
+                    version: ARTIFACT_VERSION,
+                    source_hash: key.source_hash,
+                    constants: key.constants.clone(),
+                    module,
+                };
+                write_artifact(&path, &artifact)?;
+                Ok(artifact.module)
+            }
+            None => Ok(module),
+        }
+    }
+}
+
+// None if the file is missing, unreadable or out of date, which all mean compiling the shader
+fn read_artifact(path: &Path, key: &ShaderKey) -> Option<Module> {
+    let bytes: Vec<u8> = fs::read(path).ok()?;
+    let artifact: ShaderArtifact = serde_json::from_slice(&bytes).ok()?;
+
+    if artifact.version == ARTIFACT_VERSION
+        && artifact.source_hash == key.source_hash
+        && artifact.constants == key.constants
+    {
+        Some(artifact.module)
+    } else {
+        None
+    }
+}
+
+// Written to a temporary file which is then renamed, so another process
+// reading the same artifact never sees half of it
+fn write_artifact(path: &Path, artifact: &ShaderArtifact) -> Result<(), PipelineCacheError> {
+    let to_error = |error: &dyn fmt::Display| PipelineCacheError::Io {
+        path: path.to_path_buf(),
+        message: error.to_string(),
+    };
+
+    let bytes: Vec<u8> = serde_json::to_vec(artifact).map_err(|error| to_error(&error))?;
+    let temporary_path: PathBuf = path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&temporary_path, bytes).map_err(|error| to_error(&error))?;
+    fs::rename(&temporary_path, path).map_err(|error| to_error(&error))?;
+
+    Ok(())
+}
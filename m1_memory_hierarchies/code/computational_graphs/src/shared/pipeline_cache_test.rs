@@ -0,0 +1,224 @@
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use naga::{
+        valid::{Capabilities, ValidationFlags, Validator},
+        Module,
+    };
+
+    use crate::shared::{
+        pipeline_cache::{
+            specialize, PipelineCache, PipelineCacheError, PipelineCacheStatistics, ShaderKey,
+        },
+        shader_validation::ShaderValidationError,
+    };
+
+    const LINEAR_SHADER: &str = include_str!("shaders/linear.wgsl");
+    const SOFTMAX_SHADER: &str = include_str!("shaders/softmax.wgsl");
+
+    // Every test gets a directory of its own, as the tests run in parallel
+    fn empty_directory(name: &str) -> PathBuf {
+        let directory: PathBuf = std::env::temp_dir()
+            .join("computational_graphs_pipeline_cache_test")
+            .join(name);
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+        directory
+    }
+
+    fn artifact_count(directory: &Path) -> usize {
+        std::fs::read_dir(directory)
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().extension() == Some("json".as_ref()))
+            .count()
+    }
+
+    fn entry_points(module: &Module) -> Vec<String> {
+        module
+            .entry_points
+            .iter()
+            .map(|entry_point| entry_point.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn shader_key() {
+        // The hashes name the files on disk, so they have to stay the same. These are the
+        // FNV-1a hashes of "" and "a".
+        assert_eq!(ShaderKey::new("", &[]).source_hash, 0xcbf29ce484222325);
+        assert_eq!(ShaderKey::new("a", &[]).source_hash, 0xaf63dc4c8601ec8c);
+
+        let key: ShaderKey = ShaderKey::new(LINEAR_SHADER, &[("A", "1u"), ("B", "2u")]);
+        assert_eq!(
+            key,
+            ShaderKey::new(LINEAR_SHADER, &[("B", "2u"), ("A", "1u")])
+        );
+
+        let others: Vec<ShaderKey> = vec![
+            ShaderKey::new(LINEAR_SHADER, &[]),
+            ShaderKey::new(LINEAR_SHADER, &[("A", "1u"), ("B", "3u")]),
+            ShaderKey::new(LINEAR_SHADER, &[("A", "1u")]),
+            ShaderKey::new(SOFTMAX_SHADER, &[("A", "1u"), ("B", "2u")]),
+            // The name and value of a constant can't run into each other
+            ShaderKey::new(LINEAR_SHADER, &[("A", "1u"), ("B2", "u")]),
+        ];
+        for other in &others {
+            assert_ne!(key, *other);
+            assert_ne!(key.file_name(), other.file_name());
+        }
+    }
+
+    #[test]
+    fn specialization() {
+        let constants: Vec<(String, String)> = vec![
+            ("BLOCK_SIZE".to_string(), "16u".to_string()),
+            ("TANH_LIMIT".to_string(), "8.0".to_string()),
+        ];
+        let specialized: String = specialize("linear.wgsl", LINEAR_SHADER, &constants).unwrap();
+
+        assert!(specialized.contains("const BLOCK_SIZE: u32 = 16u;\n"));
+        assert!(specialized.contains("const TANH_LIMIT: f32 = 8.0;\n"));
+        assert!(!specialized.contains("const BLOCK_SIZE: u32 = 8u;"));
+        // Nothing else changes
+        assert_eq!(
+            specialized
+                .replace("= 16u;", "= 8u;")
+                .replace("= 8.0;", "= 10.0;"),
+            LINEAR_SHADER
+        );
+
+        let unchanged: String = specialize("linear.wgsl", LINEAR_SHADER, &[]).unwrap();
+        assert_eq!(unchanged, LINEAR_SHADER);
+
+        // A prefix of a constant isn't the constant
+        let result: Result<String, PipelineCacheError> = specialize(
+            "linear.wgsl",
+            LINEAR_SHADER,
+            &[("BLOCK".to_string(), "16u".to_string())],
+        );
+        assert_eq!(
+            result,
+            Err(PipelineCacheError::UnknownConstant {
+                label: "linear.wgsl".to_string(),
+                name: "BLOCK".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn memory_only() {
+        let cache: PipelineCache = PipelineCache::new();
+        assert!(cache.directory().is_none());
+
+        let module: Module = cache
+            .module("softmax.wgsl", SOFTMAX_SHADER, &[("BLOCK_SIZE", "64u")])
+            .unwrap();
+        assert_eq!(
+            entry_points(&module),
+            vec!["single_pass_max", "single_pass_sum", "map", "map_log"]
+        );
+        assert_eq!(
+            cache.statistics(),
+            PipelineCacheStatistics {
+                modules_compiled: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn persistence() {
+        let directory: PathBuf = empty_directory("persistence");
+        let constants: [(&str, &str); 1] = [("BLOCK_SIZE", "4u")];
+
+        let first_cache: PipelineCache = PipelineCache::with_directory(&directory).unwrap();
+        let compiled: Module = first_cache
+            .module("linear.wgsl", LINEAR_SHADER, &constants)
+            .unwrap();
+        first_cache
+            .module("linear.wgsl", LINEAR_SHADER, &[])
+            .unwrap();
+        assert_eq!(first_cache.statistics().modules_compiled, 2);
+        assert_eq!(first_cache.statistics().modules_loaded, 0);
+        assert_eq!(artifact_count(&directory), 2);
+
+        // A new cache, like the next run of the program, reads what the first one wrote
+        let second_cache: PipelineCache = PipelineCache::with_directory(&directory).unwrap();
+        let loaded: Module = second_cache
+            .module("linear.wgsl", LINEAR_SHADER, &constants)
+            .unwrap();
+        assert_eq!(second_cache.statistics().modules_compiled, 0);
+        assert_eq!(second_cache.statistics().modules_loaded, 1);
+
+        assert_eq!(entry_points(&compiled), entry_points(&loaded));
+        assert_eq!(
+            serde_json::to_string(&compiled).unwrap(),
+            serde_json::to_string(&loaded).unwrap()
+        );
+        Validator::new(ValidationFlags::all(), Capabilities::empty())
+            .validate(&loaded)
+            .expect("The module read from disk failed validation");
+    }
+
+    #[test]
+    fn damaged_artifact() {
+        let directory: PathBuf = empty_directory("damaged_artifact");
+        let key: ShaderKey = ShaderKey::new(SOFTMAX_SHADER, &[]);
+        let path: PathBuf = directory.join(key.file_name());
+
+        PipelineCache::with_directory(&directory)
+            .unwrap()
+            .module("softmax.wgsl", SOFTMAX_SHADER, &[])
+            .unwrap();
+        let artifact: Vec<u8> = std::fs::read(&path).unwrap();
+
+        // A truncated file, like one from an older version, is compiled again and replaced
+        std::fs::write(&path, &artifact[..artifact.len() / 2]).unwrap();
+        let cache: PipelineCache = PipelineCache::with_directory(&directory).unwrap();
+        cache.module("softmax.wgsl", SOFTMAX_SHADER, &[]).unwrap();
+        assert_eq!(cache.statistics().modules_compiled, 1);
+        assert_eq!(std::fs::read(&path).unwrap(), artifact);
+
+        // An artifact of another shader under the same name isn't used
+        let linear_artifact: Vec<u8> = {
+            let linear_directory: PathBuf = empty_directory("damaged_artifact_linear");
+            PipelineCache::with_directory(&linear_directory)
+                .unwrap()
+                .module("linear.wgsl", LINEAR_SHADER, &[])
+                .unwrap();
+            std::fs::read(linear_directory.join(ShaderKey::new(LINEAR_SHADER, &[]).file_name()))
+                .unwrap()
+        };
+        std::fs::write(&path, linear_artifact).unwrap();
+        let cache: PipelineCache = PipelineCache::with_directory(&directory).unwrap();
+        let module: Module = cache.module("softmax.wgsl", SOFTMAX_SHADER, &[]).unwrap();
+        assert_eq!(cache.statistics().modules_compiled, 1);
+        assert_eq!(cache.statistics().modules_loaded, 0);
+        assert_eq!(entry_points(&module)[0], "single_pass_max");
+    }
+
+    #[test]
+    fn invalid_shader() {
+        let directory: PathBuf = empty_directory("invalid_shader");
+        let cache: PipelineCache = PipelineCache::with_directory(&directory).unwrap();
+
+        let result: Result<Module, PipelineCacheError> =
+            cache.module("broken.wgsl", "fn main( {}", &[]);
+        assert!(matches!(
+            result,
+            Err(PipelineCacheError::Validation(
+                ShaderValidationError::Parse { .. }
+            ))
+        ));
+
+        // A constant which makes the shader invalid is caught like any other mistake
+        let result: Result<Module, PipelineCacheError> =
+            cache.module("linear.wgsl", LINEAR_SHADER, &[("BLOCK_SIZE", "-1.5")]);
+        assert!(matches!(result, Err(PipelineCacheError::Validation(_))));
+
+        assert_eq!(artifact_count(&directory), 0);
+        assert_eq!(cache.statistics(), PipelineCacheStatistics::default());
+    }
+}
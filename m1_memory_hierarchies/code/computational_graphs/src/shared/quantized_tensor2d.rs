@@ -0,0 +1,276 @@
+use super::tensor2d::Tensor2D;
+
+// Affine quantization maps a real value to an 8-bit integer with
+// quantized = round(value / scale) + zero_point
+// and back again with
+// value = (quantized - zero_point) * scale
+// The zero point makes sure 0.0 is represented exactly, which matters
+// for things like ReLU and zero padding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuantizationParameters {
+    pub scale: f32,
+    pub zero_point: i32,
+}
+
+impl QuantizationParameters {
+    // Calibrates the parameters to cover the range from min to max.
+    // The range is extended to include 0.0 if it doesn't already.
+    pub fn from_range(min: f32, max: f32) -> Self {
+        let min: f32 = min.min(0.0);
+        let max: f32 = max.max(0.0);
+
+        let scale: f32 = if min < max {
+            (max - min) / (i8::MAX as f32 - i8::MIN as f32)
+        } else {
+            1.0
+        };
+        let zero_point: i32 = (i8::MIN as f32 - min / scale)
+            .round()
+            .clamp(i8::MIN as f32, i8::MAX as f32) as i32;
+
+        QuantizationParameters { scale, zero_point }
+    }
+
+    // Calibrates the parameters to cover the smallest and largest value in the tensor
+    pub fn from_tensor(tensor: &Tensor2D) -> Self {
+        let mut min: f32 = f32::INFINITY;
+        let mut max: f32 = f32::NEG_INFINITY;
+        for value in &tensor.data[0..tensor.len()] {
+            min = min.min(*value);
+            max = max.max(*value);
+        }
+
+        Self::from_range(min, max)
+    }
+
+    #[inline(always)]
+    pub fn quantize(&self, value: f32) -> i8 {
+        ((value / self.scale).round() as i32 + self.zero_point)
+            .clamp(i8::MIN as i32, i8::MAX as i32) as i8
+    }
+
+    #[inline(always)]
+    pub fn dequantize(&self, value: i8) -> f32 {
+        (value as i32 - self.zero_point) as f32 * self.scale
+    }
+}
+
+// Per-tensor quantization uses a single set of parameters for the whole tensor.
+// Per-channel quantization uses a set of parameters per column, which for the weights
+// of a linear layer means per output. Outputs with small weights then don't have to
+// share a scale with outputs with large weights.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuantizationGranularity {
+    PerTensor,
+    PerChannel,
+}
+
+// A tensor of i8 values along with the parameters needed to get back to f32.
+// parameters has either a single element, or one element per column.
+#[derive(Clone, Debug)]
+pub struct QuantizedTensor2D {
+    pub tensor: Tensor2D<i8>,
+    pub parameters: Vec<QuantizationParameters>,
+}
+
+impl QuantizedTensor2D {
+    pub fn quantize(tensor: &Tensor2D, parameters: QuantizationParameters) -> Self {
+        Self::quantize_per_channel(tensor, vec![parameters])
+    }
+
+    pub fn quantize_per_channel(
+        tensor: &Tensor2D,
+        parameters: Vec<QuantizationParameters>,
+    ) -> Self {
+        let mut quantized: QuantizedTensor2D = QuantizedTensor2D {
+            tensor: Tensor2D {
+                data: vec![0; tensor.len()],
+                row_count: tensor.row_count,
+                column_count: tensor.column_count,
+            },
+            parameters,
+        };
+        Self::quantize_preallocated(tensor, &mut quantized);
+
+        quantized
+    }
+
+    // Quantizes the input with the parameters already in output
+    pub fn quantize_preallocated(input: &Tensor2D, output: &mut QuantizedTensor2D) {
+        debug_assert_eq!(input.len(), output.len(), "\nMismatch - input.len() & output.len()\ninput - rows: {} columns: {}.\n out - rows: {} columns: {}.", input.row_count, input.column_count, output.tensor.row_count, output.tensor.column_count);
+        output.assert_parameter_count();
+
+        for row in 0..input.row_count {
+            for column in 0..input.column_count {
+                let index: usize = row * input.column_count + column;
+                output.tensor.data[index] = output.parameters(column).quantize(input.data[index]);
+            }
+        }
+    }
+
+    pub fn quantize_min_max(tensor: &Tensor2D) -> Self {
+        Self::calibrate(tensor, QuantizationGranularity::PerTensor)
+    }
+
+    // Quantizes the tensor with parameters covering the smallest and largest values
+    // of either the whole tensor or each column.
+    pub fn calibrate(tensor: &Tensor2D, granularity: QuantizationGranularity) -> Self {
+        match granularity {
+            QuantizationGranularity::PerTensor => {
+                Self::quantize(tensor, QuantizationParameters::from_tensor(tensor))
+            }
+            QuantizationGranularity::PerChannel => {
+                let mut minimums: Vec<f32> = vec![f32::INFINITY; tensor.column_count];
+                let mut maximums: Vec<f32> = vec![f32::NEG_INFINITY; tensor.column_count];
+                for row in 0..tensor.row_count {
+                    for column in 0..tensor.column_count {
+                        let value: f32 = tensor.data[row * tensor.column_count + column];
+                        minimums[column] = minimums[column].min(value);
+                        maximums[column] = maximums[column].max(value);
+                    }
+                }
+
+                let parameters: Vec<QuantizationParameters> = minimums
+                    .iter()
+                    .zip(maximums.iter())
+                    .map(|(min, max)| QuantizationParameters::from_range(*min, *max))
+                    .collect();
+                Self::quantize_per_channel(tensor, parameters)
+            }
+        }
+    }
+
+    pub fn granularity(&self) -> QuantizationGranularity {
+        if self.parameters.len() == 1 {
+            QuantizationGranularity::PerTensor
+        } else {
+            QuantizationGranularity::PerChannel
+        }
+    }
+
+    #[inline(always)]
+    pub fn parameters(&self, column: usize) -> &QuantizationParameters {
+        if self.parameters.len() == 1 {
+            &self.parameters[0]
+        } else {
+            &self.parameters[column]
+        }
+    }
+
+    #[inline(always)]
+    fn assert_parameter_count(&self) {
+        debug_assert!(
+            self.parameters.len() == 1 || self.parameters.len() == self.tensor.column_count,
+            "\nQuantizedTensor2D needs either 1 set of parameters or 1 per column. Found {} for {} columns.",
+            self.parameters.len(),
+            self.tensor.column_count
+        );
+    }
+
+    pub fn dequantize(&self) -> Tensor2D {
+        self.assert_parameter_count();
+
+        let mut output: Tensor2D =
+            Tensor2D::new(0.0, self.tensor.row_count, self.tensor.column_count);
+        for row in 0..self.tensor.row_count {
+            for column in 0..self.tensor.column_count {
+                let index: usize = row * self.tensor.column_count + column;
+                output.data[index] = self.parameters(column).dequantize(self.tensor.data[index]);
+            }
+        }
+
+        output
+    }
+
+    // The size of the quantized data and its parameters
+    pub fn size_in_bytes(&self) -> usize {
+        self.len() * std::mem::size_of::<i8>()
+            + self.parameters.len() * std::mem::size_of::<QuantizationParameters>()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tensor.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tensor.is_empty()
+    }
+
+    // The products of the i8 values, with their zero points subtracted, are summed up in i32.
+    // Since the scales are constant for the whole input and for each column of the weights,
+    // they can be applied once per output element, after the accumulation, along with the f32 bias.
+    // The input has to be quantized per tensor, the weights can be quantized per channel.
+    pub fn linear_preallocated(
+        input: &QuantizedTensor2D,
+        weights: &QuantizedTensor2D,
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+    ) {
+        debug_assert_eq!(input.tensor.column_count, weights.tensor.row_count, "\nMismatch - input.column_count & weights.row_count\ninput - rows: {} columns: {}.\n weights - rows: {} columns: {}.", input.tensor.row_count, input.tensor.column_count, weights.tensor.row_count, weights.tensor.column_count);
+        debug_assert_eq!(bias.len(), output.len(), "\nMismatch - bias.len() & output.len()\nbias - rows: {} columns: {}.\n out - rows: {} columns: {}.", bias.row_count, bias.column_count, output.row_count, output.column_count);
+        debug_assert_eq!(input.parameters.len(), 1, "\nQuantizedTensor2D::linear_preallocated requires the input to be quantized per tensor.");
+        weights.assert_parameter_count();
+
+        let input_zero_point: i32 = input.parameters[0].zero_point;
+        let input_scale: f32 = input.parameters[0].scale;
+
+        let input_data: &[i8] = &input.tensor.data;
+        let weights_data: &[i8] = &weights.tensor.data;
+        let weights_column_count: usize = weights.tensor.column_count;
+        let inner_dimension_count: usize = input.tensor.column_count;
+
+        for row_output in 0..output.row_count {
+            for column_output in 0..output.column_count {
+                let weights_parameters: &QuantizationParameters = weights.parameters(column_output);
+                let weights_zero_point: i32 = weights_parameters.zero_point;
+
+                // The row of the input and the column of the weights
+                let input_row: &[i8] = &input_data
+                    [row_output * inner_dimension_count..(row_output + 1) * inner_dimension_count];
+                let weights_column = weights_data
+                    .iter()
+                    .skip(column_output)
+                    .step_by(weights_column_count);
+
+                let mut result: i32 = 0;
+                for (input_element, weights_element) in input_row.iter().zip(weights_column) {
+                    result += (*input_element as i32 - input_zero_point)
+                        * (*weights_element as i32 - weights_zero_point);
+                }
+
+                let index: usize = row_output * output.column_count + column_output;
+                output.data[index] =
+                    result as f32 * input_scale * weights_parameters.scale + bias.data[index];
+            }
+        }
+    }
+
+    pub fn linear_relu_preallocated(
+        input: &QuantizedTensor2D,
+        weights: &QuantizedTensor2D,
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+    ) {
+        Self::linear_preallocated(input, weights, bias, output);
+        Tensor2D::relu_inplace_inline(output);
+    }
+
+    // The zero point is the quantized value of 0.0
+    pub fn relu_inplace(&mut self) {
+        self.assert_parameter_count();
+
+        for row in 0..self.tensor.row_count {
+            for column in 0..self.tensor.column_count {
+                let zero_point: i8 = self.parameters(column).zero_point as i8;
+                let index: usize = row * self.tensor.column_count + column;
+                self.tensor.data[index] = self.tensor.data[index].max(zero_point);
+            }
+        }
+    }
+
+    // There is no sensible way of doing exponentials in i8,
+    // so softmax dequantizes and runs in f32.
+    pub fn softmax_preallocated(input: &QuantizedTensor2D, output: &mut Tensor2D) {
+        Tensor2D::softmax_preallocated(&input.dequantize(), output);
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+    use crate::shared::{
+        quantized_tensor2d::{QuantizationGranularity, QuantizationParameters, QuantizedTensor2D},
+        tensor2d::Tensor2D,
+    };
+
+    #[test]
+    fn parameters() {
+        let parameters: QuantizationParameters = QuantizationParameters::from_range(-1.0, 3.0);
+        assert!((parameters.scale - 4.0 / 255.0).abs() < 0.00001);
+        assert_eq!(parameters.quantize(-1.0), i8::MIN);
+        assert_eq!(parameters.quantize(3.0), i8::MAX);
+
+        // 0.0 has to survive quantization exactly
+        assert_eq!(parameters.dequantize(parameters.quantize(0.0)), 0.0);
+
+        // Ranges not including 0.0 are extended to include it
+        let parameters: QuantizationParameters = QuantizationParameters::from_range(2.0, 4.0);
+        assert_eq!(parameters.dequantize(parameters.quantize(0.0)), 0.0);
+        assert_eq!(parameters.zero_point, i8::MIN as i32);
+
+        // A tensor of zeros shouldn't divide by zero
+        let parameters: QuantizationParameters = QuantizationParameters::from_range(0.0, 0.0);
+        assert_eq!(parameters.dequantize(parameters.quantize(0.0)), 0.0);
+    }
+
+    #[test]
+    fn round_trip() {
+        let tensor: Tensor2D = Tensor2D::new(-0.37, 5, 7);
+        let quantized: QuantizedTensor2D = QuantizedTensor2D::quantize_min_max(&tensor);
+        let dequantized: Tensor2D = quantized.dequantize();
+
+        // Rounding to the nearest quantized value is off by at most half a step
+        let half_step: f32 = quantized.parameters[0].scale * 0.5 + 0.00001;
+        for index in 0..tensor.len() {
+            assert!((tensor.data[index] - dequantized.data[index]).abs() <= half_step);
+        }
+    }
+
+    #[test]
+    fn per_channel() {
+        // Every column has a very different range
+        let mut tensor: Tensor2D = Tensor2D::new(0.1, 6, 3);
+        for row in 0..tensor.row_count {
+            for column in 0..tensor.column_count {
+                tensor.data[row * 3 + column] *= 10.0_f32.powi(column as i32);
+            }
+        }
+
+        let quantized: QuantizedTensor2D =
+            QuantizedTensor2D::calibrate(&tensor, QuantizationGranularity::PerChannel);
+        assert_eq!(quantized.granularity(), QuantizationGranularity::PerChannel);
+        assert_eq!(quantized.parameters.len(), tensor.column_count);
+        assert!(quantized.parameters[0].scale < quantized.parameters[2].scale);
+
+        let dequantized: Tensor2D = quantized.dequantize();
+        for row in 0..tensor.row_count {
+            for column in 0..tensor.column_count {
+                let index: usize = row * tensor.column_count + column;
+                let half_step: f32 = quantized.parameters(column).scale * 0.5 + 0.00001;
+                assert!((tensor.data[index] - dequantized.data[index]).abs() <= half_step);
+            }
+        }
+
+        let quantized: QuantizedTensor2D =
+            QuantizedTensor2D::calibrate(&tensor, QuantizationGranularity::PerTensor);
+        assert_eq!(quantized.granularity(), QuantizationGranularity::PerTensor);
+        assert_eq!(quantized.parameters.len(), 1);
+    }
+
+    #[test]
+    fn linear() {
+        for size in 1..16 {
+            let input: Tensor2D = Tensor2D::new(0.5 / size as f32, size, size);
+            let weights: Tensor2D = Tensor2D::new(-0.3 / size as f32, size, size);
+            let bias: Tensor2D = Tensor2D::new(0.1, size, size);
+            let expected: Tensor2D = Tensor2D::linear(&input, &weights, &bias);
+
+            let quantized_input: QuantizedTensor2D = QuantizedTensor2D::quantize_min_max(&input);
+            let quantized_weights: QuantizedTensor2D =
+                QuantizedTensor2D::quantize_min_max(&weights);
+            let mut output: Tensor2D = Tensor2D::new(0.0, size, size);
+            QuantizedTensor2D::linear_preallocated(
+                &quantized_input,
+                &quantized_weights,
+                &bias,
+                &mut output,
+            );
+
+            // The error of the quantized inputs is at most half a step each,
+            // and the error of the products adds up over the inner dimension.
+            let input_max: f32 = input.data.iter().fold(0.0, |max, x| x.abs().max(max));
+            let weights_max: f32 = weights.data.iter().fold(0.0, |max, x| x.abs().max(max));
+            let tolerance: f32 = size as f32
+                * (quantized_input.parameters[0].scale * weights_max
+                    + quantized_weights.parameters[0].scale * input_max);
+            for index in 0..expected.len() {
+                assert!(
+                    (expected.data[index] - output.data[index]).abs() <= tolerance,
+                    "size: {} expected: {} found: {} tolerance: {}",
+                    size,
+                    expected.data[index],
+                    output.data[index],
+                    tolerance
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn relu() {
+        let tensor: Tensor2D = Tensor2D::new(-0.5, 4, 3);
+        let mut quantized: QuantizedTensor2D = QuantizedTensor2D::quantize(
+            &tensor,
+            QuantizationParameters::from_range(-6.0, 6.0),
+        );
+        quantized.relu_inplace();
+
+        let expected: Tensor2D = Tensor2D::relu(&tensor);
+        let dequantized: Tensor2D = quantized.dequantize();
+        let half_step: f32 = quantized.parameters[0].scale * 0.5 + 0.00001;
+        for index in 0..expected.len() {
+            assert!((expected.data[index] - dequantized.data[index]).abs() <= half_step);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::fmt;
+
+use naga::{
+    valid::{Capabilities, ValidationFlags, Validator},
+    Module, ShaderStage,
+};
+
+// wgpu only compiles a shader once it is given to a device, so a mistake in a shader,
+// or in a string we build at runtime, isn't found until we run on a GPU. wgpu uses
+// naga to parse and validate WGSL, so we can do the same thing without a device.
+// No capabilities are enabled, which makes this as strict as the least capable backend.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShaderValidationError {
+    // The messages are naga's, with the offending lines of the source
+    Parse { label: String, message: String },
+    Validation { label: String, message: String },
+    MissingEntryPoint { label: String, entry_point: String },
+}
+
+impl fmt::Display for ShaderValidationError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderValidationError::Parse { label, message } => {
+                write!(formatter, "{} failed to parse:\n{}", label, message)
+            }
+            ShaderValidationError::Validation { label, message } => {
+                write!(formatter, "{} failed validation:\n{}", label, message)
+            }
+            ShaderValidationError::MissingEntryPoint { label, entry_point } => write!(
+                formatter,
+                "{} has no compute entry point named {}",
+                label, entry_point
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShaderValidationError {}
+
+// The label is only used in the error messages, use the file name or what built the source
+pub fn validate_wgsl(label: &str, source: &str) -> Result<Module, ShaderValidationError> {
+    let module: Module =
+        naga::front::wgsl::parse_str(source).map_err(|error| ShaderValidationError::Parse {
+            label: label.to_string(),
+            message: error.emit_to_string_with_path(source, label),
+        })?;
+
+    Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .map_err(|error| ShaderValidationError::Validation {
+            label: label.to_string(),
+            message: error.emit_to_string_with_path(source, label),
+        })?;
+
+    Ok(module)
+}
+
+// Also checks that every entry point we create a compute pipeline with is in the shader
+pub fn validate_compute_shader(
+    label: &str,
+    source: &str,
+    entry_points: &[&str],
+) -> Result<Module, ShaderValidationError> {
+    let module: Module = validate_wgsl(label, source)?;
+
+    for entry_point in entry_points {
+        if !module.entry_points.iter().any(|candidate| {
+            candidate.name == *entry_point && candidate.stage == ShaderStage::Compute
+        }) {
+            return Err(ShaderValidationError::MissingEntryPoint {
+                label: label.to_string(),
+                entry_point: entry_point.to_string(),
+            });
+        }
+    }
+
+    Ok(module)
+}
@@ -0,0 +1,234 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        op_code_compiler::{runner::linear_op_codes, shader_generator::generate_shader},
+        shared::shader_validation::{
+            validate_compute_shader, validate_wgsl, ShaderValidationError,
+        },
+    };
+
+    // The histogram shaders are specialized at runtime by gpu_histogram, which is its own
+    // binary, so its specialization function is included here to validate the exact strings it builds
+    mod histogram {
+        include!("../../../gpu_histogram/src/specialization.rs");
+    }
+
+    fn assert_valid(label: &str, source: &str, entry_points: &[&str]) {
+        if let Err(error) = validate_compute_shader(label, source, entry_points) {
+            panic!("{}", error);
+        }
+    }
+
+    #[test]
+    fn computational_graph_shaders() {
+        let shaders: [(&str, &str, &[&str]); 9] = [
+            (
+                "activation.wgsl",
+                include_str!("shaders/activation.wgsl"),
+                &["main"],
+            ),
+            (
+                "add.wgsl",
+                include_str!("shaders/add.wgsl"),
+                &["main", "main_with_relu"],
+            ),
+            (
+                "concat.wgsl",
+                include_str!("shaders/concat.wgsl"),
+                &["main"],
+            ),
+            (
+                "linear.wgsl",
+                include_str!("shaders/linear.wgsl"),
+                &[
+                    "main",
+                    "main_with_relu",
+                    "main_with_add",
+                    "main_with_activation",
+                ],
+            ),
+            ("relu.wgsl", include_str!("shaders/relu.wgsl"), &["main"]),
+            (
+                "relu_inline.wgsl",
+                include_str!("shaders/relu_inline.wgsl"),
+                &["main"],
+            ),
+            (
+                "softmax.wgsl",
+                include_str!("shaders/softmax.wgsl"),
+                &["single_pass_max", "single_pass_sum", "map", "map_log"],
+            ),
+            (
+                "subtraction.wgsl",
+                include_str!("shaders/subtraction.wgsl"),
+                &["main"],
+            ),
+            (
+                "sum.wgsl",
+                include_str!("shaders/sum.wgsl"),
+                &["global_phase", "workgroup_phase", "single_pass_sum"],
+            ),
+        ];
+
+        for (label, source, entry_points) in shaders {
+            assert_valid(label, source, entry_points);
+        }
+    }
+
+    // The shaders compile_linear_shader builds at runtime
+    #[test]
+    fn op_code_compiler_shaders() {
+        for with_relu in [false, true] {
+            let source: String = generate_shader(&linear_op_codes(with_relu))
+                .expect("Failed to generate the linear shader")
+                .source;
+            assert_valid("compile_linear_shader", &source, &["main"]);
+        }
+    }
+
+    #[test]
+    fn gpu_add_shaders() {
+        assert_valid(
+            "add_vectors.wgsl",
+            include_str!("../../../gpu_add/src/add_vectors.wgsl"),
+            &["main"],
+        );
+    }
+
+    // The convolution and matrix multiplication shaders are empty until the hand in
+    // is done, but they are still parsed, so they are validated once they are written
+    #[test]
+    fn gpu_hand_in_shaders() {
+        assert_valid(
+            "vector_add.wgsl",
+            include_str!("../../../gpu_hand_in/src/vector_add.wgsl"),
+            &["vector_add"],
+        );
+
+        let shaders: [(&str, &str); 6] = [
+            (
+                "convolution_naive.wgsl",
+                include_str!("../../../gpu_hand_in/src/convolution_naive.wgsl"),
+            ),
+            (
+                "convolution_padded.wgsl",
+                include_str!("../../../gpu_hand_in/src/convolution_padded.wgsl"),
+            ),
+            (
+                "convolution_shared.wgsl",
+                include_str!("../../../gpu_hand_in/src/convolution_shared.wgsl"),
+            ),
+            (
+                "matrix_multiplication_naive.wgsl",
+                include_str!("../../../gpu_hand_in/src/matrix_multiplication_naive.wgsl"),
+            ),
+            (
+                "matrix_multiplication_padded.wgsl",
+                include_str!("../../../gpu_hand_in/src/matrix_multiplication_padded.wgsl"),
+            ),
+            (
+                "matrix_multiplication_tiled.wgsl",
+                include_str!("../../../gpu_hand_in/src/matrix_multiplication_tiled.wgsl"),
+            ),
+        ];
+
+        for (label, source) in shaders {
+            assert_valid(label, source, &[]);
+        }
+    }
+
+    // Every histogram shader with every combination of the specialization constants.
+    // The bin count and elements per thread used by gpu_histogram's main are among them.
+    #[test]
+    fn gpu_histogram_shaders() {
+        let shaders: [(&str, &str); 7] = [
+            (
+                "histogram.wgsl",
+                include_str!("../../../gpu_histogram/src/histogram.wgsl"),
+            ),
+            (
+                "histogram_atomic.wgsl",
+                include_str!("../../../gpu_histogram/src/histogram_atomic.wgsl"),
+            ),
+            (
+                "histogram_local.wgsl",
+                include_str!("../../../gpu_histogram/src/histogram_local.wgsl"),
+            ),
+            (
+                "histogram_non_coalesced.wgsl",
+                include_str!("../../../gpu_histogram/src/histogram_non_coalesced.wgsl"),
+            ),
+            (
+                "histogram_shared.wgsl",
+                include_str!("../../../gpu_histogram/src/histogram_shared.wgsl"),
+            ),
+            (
+                "histogram_sparse.wgsl",
+                include_str!("../../../gpu_histogram/src/histogram_sparse.wgsl"),
+            ),
+            (
+                "histogram_sparse_unoptimized.wgsl",
+                include_str!("../../../gpu_histogram/src/histogram_sparse_unoptimized.wgsl"),
+            ),
+        ];
+
+        for (label, base_shader_file) in shaders {
+            for bin_count in [1, 5, 32, 1024] {
+                for elements_per_thread in [1, 8, 256] {
+                    let source: String = histogram::specialize_shader(
+                        base_shader_file,
+                        bin_count,
+                        elements_per_thread,
+                    );
+                    let label: String = format!(
+                        "{} with bin_count {} and elements_per_thread {}",
+                        label, bin_count, elements_per_thread
+                    );
+                    assert_valid(&label, &source, &["histogram"]);
+                }
+            }
+        }
+
+        // Without the specialization the constants are missing
+        assert!(matches!(
+            validate_wgsl("histogram_atomic.wgsl", shaders[1].1),
+            Err(ShaderValidationError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let source: &str = "fn add(a: f32, b: f32) -> f32 {
+    return a + b;
+}
+
+@compute @workgroup_size(32, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let value: f32 = add(1.0, 2.0);
+}
+";
+        assert!(validate_compute_shader("errors", source, &["main"]).is_ok());
+        assert_eq!(
+            validate_compute_shader("errors", source, &["add"]).err(),
+            Some(ShaderValidationError::MissingEntryPoint {
+                label: "errors".to_string(),
+                entry_point: "add".to_string()
+            })
+        );
+
+        // The message points to the line with the mistake
+        match validate_wgsl("errors", &source.replace("a + b", "a + c")) {
+            Err(ShaderValidationError::Parse { label, message }) => {
+                assert_eq!(label, "errors");
+                assert!(message.contains("a + c"), "{}", message);
+            }
+            result => panic!("Expected a parse error, found {:?}", result.map(|_| ())),
+        }
+
+        // Parses, but returns a value of the wrong type
+        assert!(matches!(
+            validate_wgsl("errors", &source.replace("return a + b;", "return 1u;")),
+            Err(ShaderValidationError::Validation { .. })
+        ));
+    }
+}
@@ -0,0 +1,76 @@
+// The activation functions of shared::activation::ActivationFunction. function is its
+// shader_index and alpha is only used by LeakyReLU. The Rust versions in
+// shared/activation.rs and activate in linear.wgsl have to be kept in sync with this.
+struct ActivationUniform {
+    element_count: u32,
+    function: u32,
+    alpha: f32,
+    padding: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> activation_uniform: ActivationUniform;
+
+@group(0) @binding(1)
+var<storage, read> input: array<f32>;
+
+@group(0) @binding(2)
+var<storage, read_write> output: array<f32>;
+
+// sqrt(2 / π)
+const GELU_TANH_SCALE: f32 = 0.7978846;
+const GELU_TANH_CUBIC: f32 = 0.044715;
+// tanh is 1.0 in f32 long before this, and some implementations return NaN
+// for large arguments, as they are based on exp
+const TANH_LIMIT: f32 = 10.0;
+
+// Abramowitz and Stegun 7.1.26, as WGSL doesn't have erf either
+fn erf(x: f32) -> f32 {
+    let t: f32 = 1.0 / (1.0 + 0.3275911 * abs(x));
+    let polynomial: f32 = t * (0.2548296 + t * (-0.28449674 + t * (1.4214138 + t * (-1.453152 + t * 1.0614054))));
+    return sign(x) * (1.0 - polynomial * exp(-x * x));
+}
+
+fn stable_sigmoid(x: f32) -> f32 {
+    if (0.0 <= x) {
+        return 1.0 / (1.0 + exp(-x));
+    }
+    let exponential: f32 = exp(x);
+    return exponential / (1.0 + exponential);
+}
+
+fn stable_tanh(x: f32) -> f32 {
+    return tanh(clamp(x, -TANH_LIMIT, TANH_LIMIT));
+}
+
+fn activate(x: f32) -> f32 {
+    switch activation_uniform.function {
+        case 0u: {
+            return 0.5 * x * (1.0 + erf(x * 0.70710678));
+        }
+        case 1u: {
+            return 0.5 * x * (1.0 + stable_tanh(GELU_TANH_SCALE * (x + GELU_TANH_CUBIC * x * x * x)));
+        }
+        case 2u: {
+            return x * stable_sigmoid(x);
+        }
+        case 3u: {
+            return stable_tanh(x);
+        }
+        case 4u: {
+            return stable_sigmoid(x);
+        }
+        default: {
+            return select(activation_uniform.alpha * x, x, 0.0 < x);
+        }
+    }
+}
+
+@compute @workgroup_size(32, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index: u32 = global_id.x;
+
+    if (index < activation_uniform.element_count) {
+        output[index] = activate(input[index]);
+    }
+}
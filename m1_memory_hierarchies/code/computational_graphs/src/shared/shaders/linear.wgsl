@@ -24,6 +24,22 @@ var<storage, read> bias: array<f32>;
 @group(0) @binding(4)
 var<storage, read_write> output: array<f32>;
 
+// Only used by main_with_add. The pipelines of the other entry points leave it out
+// of their bind group layouts.
+@group(0) @binding(5)
+var<storage, read> residual: array<f32>;
+
+// Only used by main_with_activation, see activation.wgsl. element_count is unused here.
+struct ActivationUniform {
+    element_count: u32,
+    function: u32,
+    alpha: f32,
+    padding: u32,
+};
+
+@group(0) @binding(6)
+var<uniform> activation_uniform: ActivationUniform;
+
 const BLOCK_SIZE: u32 = 8u;
 @compute @workgroup_size(8, 8, 1) 
 fn main(
@@ -64,4 +80,93 @@ fn main_with_relu(
 
         output[output_index] = max(0.0, result + bias[output_index]);
     }
+}
+
+@compute @workgroup_size(8, 8, 1) 
+fn main_with_add(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(workgroup_id) group_id: vec3<u32>, 
+    @builtin(local_invocation_id) local_id: vec3<u32>
+    ) {
+    let output_row_index: u32 = global_id.x;
+    let output_column_index: u32 = global_id.y;
+
+    if (output_row_index < dimensions.output_row_count && output_column_index < dimensions.output_column_count) {
+        let output_index: u32 = output_row_index * dimensions.output_column_count + output_column_index;
+
+        var result: f32 = 0.0;
+        for (var inner_dimension: u32 = 0u; inner_dimension < dimensions.input_column_count; inner_dimension += 1u) {
+            result += input[output_row_index * dimensions.input_column_count + inner_dimension] * weights[inner_dimension * dimensions.weights_column_count + output_column_index];
+        }
+
+        output[output_index] = result + bias[output_index] + residual[output_index];
+    }
+}
+
+// WGSL has no includes, so everything below is a copy of activation.wgsl
+// and has to be kept in sync with it
+const GELU_TANH_SCALE: f32 = 0.7978846;
+const GELU_TANH_CUBIC: f32 = 0.044715;
+const TANH_LIMIT: f32 = 10.0;
+
+fn erf(x: f32) -> f32 {
+    let t: f32 = 1.0 / (1.0 + 0.3275911 * abs(x));
+    let polynomial: f32 = t * (0.2548296 + t * (-0.28449674 + t * (1.4214138 + t * (-1.453152 + t * 1.0614054))));
+    return sign(x) * (1.0 - polynomial * exp(-x * x));
+}
+
+fn stable_sigmoid(x: f32) -> f32 {
+    if (0.0 <= x) {
+        return 1.0 / (1.0 + exp(-x));
+    }
+    let exponential: f32 = exp(x);
+    return exponential / (1.0 + exponential);
+}
+
+fn stable_tanh(x: f32) -> f32 {
+    return tanh(clamp(x, -TANH_LIMIT, TANH_LIMIT));
+}
+
+fn activate(x: f32) -> f32 {
+    switch activation_uniform.function {
+        case 0u: {
+            return 0.5 * x * (1.0 + erf(x * 0.70710678));
+        }
+        case 1u: {
+            return 0.5 * x * (1.0 + stable_tanh(GELU_TANH_SCALE * (x + GELU_TANH_CUBIC * x * x * x)));
+        }
+        case 2u: {
+            return x * stable_sigmoid(x);
+        }
+        case 3u: {
+            return stable_tanh(x);
+        }
+        case 4u: {
+            return stable_sigmoid(x);
+        }
+        default: {
+            return select(activation_uniform.alpha * x, x, 0.0 < x);
+        }
+    }
+}
+
+@compute @workgroup_size(8, 8, 1) 
+fn main_with_activation(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(workgroup_id) group_id: vec3<u32>, 
+    @builtin(local_invocation_id) local_id: vec3<u32>
+    ) {
+    let output_row_index: u32 = global_id.x;
+    let output_column_index: u32 = global_id.y;
+
+    if (output_row_index < dimensions.output_row_count && output_column_index < dimensions.output_column_count) {
+        let output_index: u32 = output_row_index * dimensions.output_column_count + output_column_index;
+
+        var result: f32 = 0.0;
+        for (var inner_dimension: u32 = 0u; inner_dimension < dimensions.input_column_count; inner_dimension += 1u) {
+            result += input[output_row_index * dimensions.input_column_count + inner_dimension] * weights[inner_dimension * dimensions.weights_column_count + output_column_index];
+        }
+
+        output[output_index] = activate(result + bias[output_index]);
+    }
 }
\ No newline at end of file
@@ -18,7 +18,7 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let data_column_index: u32 = global_id.y;
     
     if (data_row_index < dimensions.data_row_count && data_column_index < dimensions.data_column_count) {
-        let index: u32 = data_row_index * dimensions.data_row_count + data_column_index;
+        let index: u32 = data_row_index * dimensions.data_column_count + data_column_index;
         output[index] = max(0.0, input[index]);
     }
 }
\ No newline at end of file
@@ -1,7 +1,17 @@
 const BLOCK_SIZE: u32 = 32u;
-
+// The lowest finite f32, the starting value of the max
+const LOWEST: f32 = -3.40282346638528859812e+38f;
+
+// Softmax is computed per row, one row per sample in the batch. The max and the sum
+// are found with one workgroup per row. Subtracting the max of the row before taking the
+// exponential keeps every exponential between 0 and 1, so large inputs can't overflow.
+// The max and the sum are kept apart, as max + log(sum) rounds away most of log(sum)
+// once the max is large.
 struct SoftmaxUniform {
     element_count: u32,
+    row_count: u32,
+    column_count: u32,
+    padding: u32,
 };
 
 @group(0) @binding(0)
@@ -11,34 +21,28 @@ var<uniform> softmax_uniform: SoftmaxUniform;
 var<storage, read> input: array<f32>;
 
 @group(0) @binding(2)
-var<storage, read_write> global_max: array<f32>;
+var<storage, read_write> row_max: array<f32>;
 
+// The sum of exp(input - max) of every row
 @group(0) @binding(3)
-var<storage, read_write> global_offset: array<f32>;
+var<storage, read_write> row_sum: array<f32>;
 
 @group(0) @binding(4)
 var<storage, read_write> output: array<f32>;
 
 var<workgroup> shared_data: array<f32, BLOCK_SIZE>;
 
-@compute @workgroup_size(32, 1, 1) 
+@compute @workgroup_size(32, 1, 1)
 fn single_pass_max(
-    @builtin(workgroup_id) group_id: vec3<u32>, 
+    @builtin(workgroup_id) group_id: vec3<u32>,
     @builtin(local_invocation_id) local_id: vec3<u32>,
     ) {
     let tid: u32 = local_id.x;
-    // In this first section we can use all 32 threads
-    var elements_left: u32 = softmax_uniform.element_count;
-    var i: u32 = tid;
-    var max_value: f32 = -3.00282346638528859812e+37f;
-    // How do we handle the odd case?
-    while (BLOCK_SIZE < elements_left) {
-        max_value = max(max_value, input[i]);
-        elements_left -= BLOCK_SIZE;
-        i += BLOCK_SIZE;
-    }
-    if(tid < elements_left) {
-        max_value = max(max_value, input[i]);
+    let row: u32 = group_id.x;
+    let row_start: u32 = row * softmax_uniform.column_count;
+    var max_value: f32 = LOWEST;
+    for (var column: u32 = tid; column < softmax_uniform.column_count; column += BLOCK_SIZE) {
+        max_value = max(max_value, input[row_start + column]);
     }
 
     shared_data[tid] = max_value;
@@ -52,29 +56,22 @@ fn single_pass_max(
             max_value = max(max_value, shared_data[index]);
             index++;
         }
-        global_max[0] = max_value;
+        row_max[row] = max_value;
     }
 }
 
-@compute @workgroup_size(32, 1, 1) 
+@compute @workgroup_size(32, 1, 1)
 fn single_pass_sum(
-    @builtin(workgroup_id) group_id: vec3<u32>, 
+    @builtin(workgroup_id) group_id: vec3<u32>,
     @builtin(local_invocation_id) local_id: vec3<u32>,
     ) {
     let tid: u32 = local_id.x;
-    // In this first section we can use all 32 threads
-    var elements_left: u32 = softmax_uniform.element_count;
-    var index: u32 = tid;
+    let row: u32 = group_id.x;
+    let row_start: u32 = row * softmax_uniform.column_count;
+    let max_value: f32 = row_max[row];
     var sum_value: f32 = 0.0;
-    let max_value: f32 = global_max[0];
-    // How do we handle the odd case?
-    while (BLOCK_SIZE < elements_left) {
-        sum_value += exp(input[index] - max_value);
-        elements_left -= BLOCK_SIZE;
-        index += BLOCK_SIZE;
-    }
-    if(tid < elements_left) {
-        sum_value += exp(input[index] - max_value);
+    for (var column: u32 = tid; column < softmax_uniform.column_count; column += BLOCK_SIZE) {
+        sum_value += exp(input[row_start + column] - max_value);
     }
 
     shared_data[tid] = sum_value;
@@ -88,19 +85,34 @@ fn single_pass_sum(
             sum_value += shared_data[index];
             index++;
         }
-        global_offset[0] = max_value + log(sum_value);
+        row_sum[row] = sum_value;
     }
 }
 
-@compute @workgroup_size(32, 1, 1) 
+@compute @workgroup_size(32, 1, 1)
 fn map(
-    @builtin(workgroup_id) group_id: vec3<u32>, 
+    @builtin(workgroup_id) group_id: vec3<u32>,
     @builtin(local_invocation_id) local_id: vec3<u32>,
 ) {
-    let tid: u32 = local_id.x;
     let index: u32 = group_id.x * BLOCK_SIZE + local_id.x;
 
     if (index < softmax_uniform.element_count) {
-        output[index] = exp(input[index] - global_offset[0]);
+        let row: u32 = index / softmax_uniform.column_count;
+        output[index] = exp(input[index] - row_max[row]) / row_sum[row];
     }
-}
\ No newline at end of file
+}
+
+// Log-softmax, which is more accurate than taking the log of the softmax,
+// as the very small probabilities don't get rounded to 0 first
+@compute @workgroup_size(32, 1, 1)
+fn map_log(
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+) {
+    let index: u32 = group_id.x * BLOCK_SIZE + local_id.x;
+
+    if (index < softmax_uniform.element_count) {
+        let row: u32 = index / softmax_uniform.column_count;
+        output[index] = (input[index] - row_max[row]) - log(row_sum[row]);
+    }
+}
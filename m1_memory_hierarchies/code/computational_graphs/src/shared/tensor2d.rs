@@ -1,10 +1,42 @@
+use std::ops::Range;
+
+use rayon::prelude::*;
+use wide::f32x8;
+
+use super::{
+    activation::ActivationFunction,
+    element::{Accumulator, Element, FloatAccumulator},
+};
+
+// The block sizes used by the cache blocked linear kernels, see linear_blocked
+const LINEAR_L1_BLOCK_SIZE: usize = 32;
+const LINEAR_L2_BLOCK_SIZE: usize = 128;
+
+// The number of f32's in an f32x8 and the number of rows of the output
+// computed at a time by the microkernel in linear_packed_panels
+const SIMD_LANE_COUNT: usize = 8;
+const MICROKERNEL_ROW_COUNT: usize = 4;
+
+// Loads the first 8 elements of the slice into a SIMD register
+#[inline(always)]
+fn load_f32x8(data: &[f32]) -> f32x8 {
+    let lanes: [f32; SIMD_LANE_COUNT] = data[0..SIMD_LANE_COUNT]
+        .try_into()
+        .expect("load_f32x8 needs at least 8 elements");
+    f32x8::from(lanes)
+}
+
 // We won't enforce it in this tutorial
 // But it is assumed that all the active
 // data in the tensor is located in
 // indices 0 to row_count*column_count
+//
+// The element type defaults to f32, which is what every kernel below
+// is written for. The kernels which work for any element type are in
+// the generic impl block at the end of this file.
 #[derive(Clone, Debug, Default)]
-pub struct Tensor2D {
-    pub data: Vec<f32>,
+pub struct Tensor2D<T = f32> {
+    pub data: Vec<T>,
     pub row_count: usize,
     pub column_count: usize,
 }
@@ -26,6 +58,15 @@ impl Tensor2D {
         }
     }
 
+    // Class labels as a single column, see softmax_cross_entropy
+    pub fn from_labels(labels: &[usize]) -> Self {
+        Tensor2D {
+            data: labels.iter().map(|label| *label as f32).collect(),
+            row_count: labels.len(),
+            column_count: 1,
+        }
+    }
+
     pub fn linear(input: &Tensor2D, weights: &Tensor2D, bias: &Tensor2D) -> Tensor2D {
         // Create a matrix and set all initial values to 0.0
         let mut output: Tensor2D = Tensor2D::new(0.0, input.row_count, weights.column_count);
@@ -34,10 +75,6 @@ impl Tensor2D {
         output
     }
 
-    pub fn len(&self) -> usize {
-        self.row_count * self.column_count
-    }
-
     #[inline(always)]
     fn linear_assert(
         input: &Tensor2D,
@@ -263,6 +300,312 @@ impl Tensor2D {
         }
     }
 
+    // Cache blocking, also known as tiling. Instead of streaming through all of the weights
+    // for every row of the input, we work on a small block of the input, the weights and the output
+    // at a time. Once a block has been loaded into the cache, every element in it gets
+    // reused block size times before it is evicted.
+    // Three blocks of 32x32 f32's take up 12 KB, which fits in most L1 caches.
+    pub fn linear_blocked(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+    ) {
+        Self::linear_assert(input, weights, bias, output);
+
+        // The bias is the starting value of the sums, which are accumulated a block at a time
+        let element_count: usize = output.len();
+        output.data[0..element_count].copy_from_slice(&bias.data[0..element_count]);
+
+        let row_count: usize = output.row_count;
+        let column_count: usize = output.column_count;
+        let inner_dimension_count: usize = input.column_count;
+        Self::linear_blocked_range(
+            input,
+            weights,
+            output,
+            0..row_count,
+            0..column_count,
+            0..inner_dimension_count,
+            LINEAR_L1_BLOCK_SIZE,
+        );
+    }
+
+    // Two levels of blocking. The outer blocks are sized to fit in the L2 cache,
+    // three blocks of 128x128 f32's take up 192 KB, and each of those
+    // is worked through with blocks sized to fit in the L1 cache.
+    pub fn linear_blocked_two_level(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+    ) {
+        Self::linear_assert(input, weights, bias, output);
+
+        let element_count: usize = output.len();
+        output.data[0..element_count].copy_from_slice(&bias.data[0..element_count]);
+
+        let row_count: usize = output.row_count;
+        let column_count: usize = output.column_count;
+        let inner_dimension_count: usize = input.column_count;
+        for row_block in (0..row_count).step_by(LINEAR_L2_BLOCK_SIZE) {
+            let row_end: usize = (row_block + LINEAR_L2_BLOCK_SIZE).min(row_count);
+            for inner_block in (0..inner_dimension_count).step_by(LINEAR_L2_BLOCK_SIZE) {
+                let inner_end: usize =
+                    (inner_block + LINEAR_L2_BLOCK_SIZE).min(inner_dimension_count);
+                for column_block in (0..column_count).step_by(LINEAR_L2_BLOCK_SIZE) {
+                    let column_end: usize = (column_block + LINEAR_L2_BLOCK_SIZE).min(column_count);
+                    Self::linear_blocked_range(
+                        input,
+                        weights,
+                        output,
+                        row_block..row_end,
+                        column_block..column_end,
+                        inner_block..inner_end,
+                        LINEAR_L1_BLOCK_SIZE,
+                    );
+                }
+            }
+        }
+    }
+
+    // Adds input * weights to output for the given ranges, one block at a time.
+    // Inside a block the loops are ordered row, inner dimension, column,
+    // which means the innermost loop walks through a row of the weights and a row of the output.
+    // Both are contiguous in memory, so the compiler is free to vectorize it.
+    #[inline(always)]
+    fn linear_blocked_range(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        output: &mut Tensor2D,
+        rows: Range<usize>,
+        columns: Range<usize>,
+        inner_dimensions: Range<usize>,
+        block_size: usize,
+    ) {
+        for row_block in rows.clone().step_by(block_size) {
+            let row_end: usize = (row_block + block_size).min(rows.end);
+            for inner_block in inner_dimensions.clone().step_by(block_size) {
+                let inner_end: usize = (inner_block + block_size).min(inner_dimensions.end);
+                for column_block in columns.clone().step_by(block_size) {
+                    let column_end: usize = (column_block + block_size).min(columns.end);
+                    for row in row_block..row_end {
+                        let output_start: usize = row * output.column_count;
+                        let output_row: &mut [f32] = &mut output.data
+                            [(output_start + column_block)..(output_start + column_end)];
+                        for inner_dimension in inner_block..inner_end {
+                            let input_value: f32 =
+                                input.data[row * input.column_count + inner_dimension];
+                            let weights_start: usize = inner_dimension * weights.column_count;
+                            let weights_row: &[f32] = &weights.data
+                                [(weights_start + column_block)..(weights_start + column_end)];
+                            for (output_value, weights_value) in
+                                output_row.iter_mut().zip(weights_row)
+                            {
+                                *output_value += input_value * weights_value;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Explicit SIMD with 8 f32's per register, like the f32x8 in m4's sphere_intersection.
+    // Each output row is computed 8 columns at a time. For every element in the input row
+    // we broadcast it to all 8 lanes and multiply it with 8 consecutive weights.
+    // The columns which don't fill up a whole register are handled one at a time.
+    pub fn linear_simd(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+    ) {
+        Self::linear_assert(input, weights, bias, output);
+
+        let column_count: usize = output.column_count;
+        let simd_column_count: usize = column_count - column_count % SIMD_LANE_COUNT;
+        for row_output in 0..output.row_count {
+            let input_row: &[f32] = &input.data
+                [(row_output * input.column_count)..((row_output + 1) * input.column_count)];
+
+            for column_output in (0..simd_column_count).step_by(SIMD_LANE_COUNT) {
+                let mut result: f32x8 = f32x8::ZERO;
+                for (inner_dimension, input_value) in input_row.iter().enumerate() {
+                    let index_weights: usize =
+                        inner_dimension * weights.column_count + column_output;
+                    result = f32x8::splat(*input_value)
+                        .mul_add(load_f32x8(&weights.data[index_weights..]), result);
+                }
+
+                let index: usize = row_output * column_count + column_output;
+                let result: f32x8 = result + load_f32x8(&bias.data[index..]);
+                output.data[index..(index + SIMD_LANE_COUNT)].copy_from_slice(&result.to_array());
+            }
+
+            for column_output in simd_column_count..column_count {
+                let mut result: f32 = 0.0;
+                for (inner_dimension, input_value) in input_row.iter().enumerate() {
+                    result += input_value
+                        * weights.data[inner_dimension * weights.column_count + column_output];
+                }
+
+                let index: usize = row_output * column_count + column_output;
+                output.data[index] = result + bias.data[index];
+            }
+        }
+    }
+
+    // Copies the weights into panels of 8 columns, which is how the GEMM
+    // (general matrix multiplication) libraries like BLIS and OpenBLAS do it.
+    // Panel p holds columns 8 * p to 8 * p + 7 for every row of the weights, one f32x8 per row,
+    // with the last panel padded with zeros. The microkernel can then read the weights
+    // in the exact order it needs them, one register at a time, without any strided access.
+    pub fn pack_weights_panels(weights: &Tensor2D) -> Vec<f32x8> {
+        let panel_count: usize = weights.column_count.div_ceil(SIMD_LANE_COUNT);
+        let mut panels: Vec<f32x8> = Vec::with_capacity(panel_count * weights.row_count);
+        for panel_index in 0..panel_count {
+            let column_start: usize = panel_index * SIMD_LANE_COUNT;
+            let column_end: usize = (column_start + SIMD_LANE_COUNT).min(weights.column_count);
+            for row in 0..weights.row_count {
+                let mut lanes: [f32; SIMD_LANE_COUNT] = [0.0; SIMD_LANE_COUNT];
+                let row_start: usize = row * weights.column_count;
+                lanes[0..(column_end - column_start)].copy_from_slice(
+                    &weights.data[(row_start + column_start)..(row_start + column_end)],
+                );
+                panels.push(f32x8::from(lanes));
+            }
+        }
+
+        panels
+    }
+
+    // Packs the weights and runs linear_packed_panels. The packing is part of the timing
+    // in the benchmarks, if the weights are constant, like in an inference graph,
+    // they would only have to be packed once.
+    pub fn linear_packed(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+    ) {
+        Self::linear_assert(input, weights, bias, output);
+
+        let panels: Vec<f32x8> = Self::pack_weights_panels(weights);
+        Self::linear_packed_panels(input, &panels, bias, output);
+    }
+
+    // The output is computed in blocks of 4 rows by 8 columns, with all 32 sums kept in
+    // 4 registers for the entire inner dimension. For every step of the inner dimension the
+    // microkernel loads a single f32x8 from the panel and reuses it for 4 rows of the input.
+    pub fn linear_packed_panels(
+        input: &Tensor2D,
+        panels: &[f32x8],
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+    ) {
+        let inner_dimension_count: usize = input.column_count;
+        debug_assert_eq!(
+            panels.len(),
+            output.column_count.div_ceil(SIMD_LANE_COUNT) * inner_dimension_count,
+            "\nMismatch - panels.len() & packed weights size\ninput - rows: {} columns: {}.\n out - rows: {} columns: {}.",
+            input.row_count,
+            input.column_count,
+            output.row_count,
+            output.column_count
+        );
+
+        // Without an inner dimension every sum is 0 and the output is the bias.
+        // chunks_exact panics on chunks of 0 elements.
+        if inner_dimension_count == 0 {
+            let element_count: usize = output.len();
+            output.data[0..element_count].copy_from_slice(&bias.data[0..element_count]);
+            return;
+        }
+
+        for (panel_index, panel) in panels.chunks_exact(inner_dimension_count).enumerate() {
+            let column_start: usize = panel_index * SIMD_LANE_COUNT;
+            for row_start in (0..output.row_count).step_by(MICROKERNEL_ROW_COUNT) {
+                // The rows which don't make up a full block get a smaller microkernel
+                match output.row_count - row_start {
+                    1 => Self::linear_packed_store(
+                        &Self::linear_packed_microkernel::<1>(input, panel, row_start),
+                        bias,
+                        output,
+                        row_start,
+                        column_start,
+                    ),
+                    2 => Self::linear_packed_store(
+                        &Self::linear_packed_microkernel::<2>(input, panel, row_start),
+                        bias,
+                        output,
+                        row_start,
+                        column_start,
+                    ),
+                    3 => Self::linear_packed_store(
+                        &Self::linear_packed_microkernel::<3>(input, panel, row_start),
+                        bias,
+                        output,
+                        row_start,
+                        column_start,
+                    ),
+                    _ => Self::linear_packed_store(
+                        &Self::linear_packed_microkernel::<MICROKERNEL_ROW_COUNT>(
+                            input, panel, row_start,
+                        ),
+                        bias,
+                        output,
+                        row_start,
+                        column_start,
+                    ),
+                }
+            }
+        }
+    }
+
+    // ROW_COUNT is a constant, so the loop over the rows is fully unrolled
+    // and the sums can live in registers.
+    #[inline(always)]
+    fn linear_packed_microkernel<const ROW_COUNT: usize>(
+        input: &Tensor2D,
+        panel: &[f32x8],
+        row_start: usize,
+    ) -> [f32x8; ROW_COUNT] {
+        let mut results: [f32x8; ROW_COUNT] = [f32x8::ZERO; ROW_COUNT];
+        for (inner_dimension, weights_lanes) in panel.iter().enumerate() {
+            for (row_offset, result) in results.iter_mut().enumerate() {
+                let input_value: f32 =
+                    input.data[(row_start + row_offset) * input.column_count + inner_dimension];
+                *result = f32x8::splat(input_value).mul_add(*weights_lanes, *result);
+            }
+        }
+
+        results
+    }
+
+    #[inline(always)]
+    fn linear_packed_store(
+        results: &[f32x8],
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+        row_start: usize,
+        column_start: usize,
+    ) {
+        // The padded lanes of the last panel are thrown away
+        let lane_count: usize = (output.column_count - column_start).min(SIMD_LANE_COUNT);
+        for (row_offset, result) in results.iter().enumerate() {
+            let index: usize = (row_start + row_offset) * output.column_count + column_start;
+            let lanes: [f32; SIMD_LANE_COUNT] = result.to_array();
+            for ((output_value, bias_value), lane) in output.data[index..(index + lane_count)]
+                .iter_mut()
+                .zip(&bias.data[index..(index + lane_count)])
+                .zip(lanes)
+            {
+                *output_value = lane + bias_value;
+            }
+        }
+    }
+
     pub fn relu(x: &Tensor2D) -> Tensor2D {
         // Create a matrix and set all initial values to 0.0
         let mut out: Tensor2D = Tensor2D::new(0.0, x.row_count, x.column_count);
@@ -291,73 +634,79 @@ impl Tensor2D {
         }
     }
 
-    pub fn softmax(input: &Tensor2D) -> Tensor2D {
+    // The activation functions other than ReLU, see shared::activation
+    pub fn activation(input: &Tensor2D, function: ActivationFunction) -> Tensor2D {
         let mut output: Tensor2D = Tensor2D::new(0.0, input.row_count, input.column_count);
 
-        Self::softmax_preallocated(input, &mut output);
+        Self::activation_preallocated(input, &mut output, function);
 
         output
     }
 
-    pub fn softmax_preallocated(input: &Tensor2D, output: &mut Tensor2D) {
-        let mut max: f32 = f32::NEG_INFINITY;
+    pub fn activation_preallocated(
+        input: &Tensor2D,
+        output: &mut Tensor2D,
+        function: ActivationFunction,
+    ) {
         for index in 0..(output.column_count * output.row_count) {
-            if max < input.data[index] {
-                max = input.data[index];
-            }
+            output.data[index] = function.apply(input.data[index]);
         }
+    }
 
-        let mut sum: f32 = 0.0;
-        for index in 0..(output.column_count * output.row_count) {
-            sum += (input.data[index] - max).exp();
+    pub fn activation_inplace(data: &mut Tensor2D, function: ActivationFunction) {
+        for index in 0..(data.column_count * data.row_count) {
+            data.data[index] = function.apply(data.data[index]);
         }
+    }
 
-        let offset: f32 = max + sum.ln();
+    pub fn softmax(input: &Tensor2D) -> Tensor2D {
+        let mut output: Tensor2D = Tensor2D::new(0.0, input.row_count, input.column_count);
 
-        for index in 0..(output.column_count * output.row_count) {
-            output.data[index] = (input.data[index] - offset).exp();
-        }
+        Self::softmax_preallocated(input, &mut output);
+
+        output
     }
 
-    pub fn softmax_inplace(out: &mut Tensor2D) {
-        let mut max: f32 = f32::NEG_INFINITY;
-        for index in 0..(out.column_count * out.row_count) {
-            if max < out.data[index] {
-                max = out.data[index];
+    // Softmax is computed per row, every row being a sample in the batch.
+    // See max_and_exp_sum for why the max of the row is subtracted.
+    pub fn softmax_preallocated(input: &Tensor2D, output: &mut Tensor2D) {
+        let column_count: usize = output.column_count;
+        for row in 0..output.row_count {
+            let row_offset: usize = row * column_count;
+            let (max, sum): (f32, f32) =
+                Self::max_and_exp_sum(&input.data[row_offset..(row_offset + column_count)]);
+
+            for index in row_offset..(row_offset + column_count) {
+                output.data[index] = (input.data[index] - max).exp() / sum;
             }
         }
+    }
 
-        let mut sum: f32 = 0.0;
-        for index in 0..(out.column_count * out.row_count) {
-            sum += (out.data[index] - max).exp();
-        }
-
-        let offset: f32 = max + sum.ln();
-
-        for index in 0..(out.column_count * out.row_count) {
-            out.data[index] = (out.data[index] - offset).exp();
+    pub fn softmax_inplace(out: &mut Tensor2D) {
+        let column_count: usize = out.column_count;
+        for row in 0..out.row_count {
+            let row_offset: usize = row * column_count;
+            let (max, sum): (f32, f32) =
+                Self::max_and_exp_sum(&out.data[row_offset..(row_offset + column_count)]);
+
+            for index in row_offset..(row_offset + column_count) {
+                out.data[index] = (out.data[index] - max).exp() / sum;
+            }
         }
     }
 
     #[inline(always)]
     pub fn softmax_inplace_inline(out: &mut Tensor2D) {
-        let mut max: f32 = f32::NEG_INFINITY;
-        for index in 0..(out.column_count * out.row_count) {
-            if max < out.data[index] {
-                max = out.data[index];
+        let column_count: usize = out.column_count;
+        for row in 0..out.row_count {
+            let row_offset: usize = row * column_count;
+            let (max, sum): (f32, f32) =
+                Self::max_and_exp_sum(&out.data[row_offset..(row_offset + column_count)]);
+
+            for index in row_offset..(row_offset + column_count) {
+                out.data[index] = (out.data[index] - max).exp() / sum;
             }
         }
-
-        let mut sum: f32 = 0.0;
-        for index in 0..(out.column_count * out.row_count) {
-            sum += (out.data[index] - max).exp();
-        }
-
-        let offset: f32 = max + sum.ln();
-
-        for index in 0..(out.column_count * out.row_count) {
-            out.data[index] = (out.data[index] - offset).exp();
-        }
     }
 
     #[inline]
@@ -414,6 +763,33 @@ impl Tensor2D {
         }
     }
 
+    #[inline]
+    pub fn linear_optimized_activation(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+        function: ActivationFunction,
+    ) {
+        Self::linear_assert(input, weights, bias, output);
+
+        for row_output in 0..output.row_count {
+            for column_output in 0..output.column_count {
+                let mut result: f32 = 0.0;
+                let mut index_input: usize = row_output * input.column_count;
+                let mut index_weights: usize = column_output;
+                for _ in 0..input.column_count {
+                    result += input.data[index_input] * weights.data[index_weights];
+                    index_input += 1;
+                    index_weights += weights.column_count;
+                }
+
+                let index: usize = row_output * output.column_count + column_output;
+                output.data[index] = function.apply(result + bias.data[index]);
+            }
+        }
+    }
+
     // Maybe just inline
     #[inline]
     pub fn linear_relu_softmax_fused_fission(
@@ -440,27 +816,211 @@ impl Tensor2D {
             }
         }
 
-        for index in 0..(bias.row_count * bias.column_count) {
-            output.data[index] += bias.data[index];
+        for row in 0..output.row_count {
+            let row_offset: usize = row * output.column_count;
+            let row_end: usize = row_offset + output.column_count;
+
+            let mut max: f32 = f32::NEG_INFINITY;
+            for index in row_offset..row_end {
+                let result: f32 = (output.data[index] + bias.data[index]).max(0.0);
+                max = max.max(result);
+                output.data[index] = result;
+            }
+
+            let mut sum: f32 = 0.0;
+            for index in row_offset..row_end {
+                sum += (output.data[index] - max).exp();
+            }
+
+            for index in row_offset..row_end {
+                output.data[index] = (output.data[index] - max).exp() / sum;
+            }
         }
+    }
 
-        let mut max: f32 = f32::NEG_INFINITY;
-        for index in 0..(bias.row_count * bias.column_count) {
-            let result: f32 = (output.data[index] + bias.data[index]).max(0.0);
-            max = max.max(result);
-            output.data[index] = result;
+    // The rows of the output are independent of each other, so they can be split
+    // across the threads of the current rayon pool. Every thread gets whole rows,
+    // which means no two threads write to the same part of the output.
+    pub fn linear_optimized_parallel(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+    ) {
+        Self::linear_assert(input, weights, bias, output);
+
+        let column_count: usize = output.column_count;
+        let element_count: usize = output.len();
+        output.data[0..element_count]
+            .par_chunks_mut(column_count)
+            .zip(bias.data[0..element_count].par_chunks(column_count))
+            .enumerate()
+            .for_each(|(row_output, (output_row, bias_row))| {
+                Self::linear_optimized_row(input, weights, row_output, bias_row, output_row);
+            });
+    }
+
+    pub fn linear_optimized_relu_parallel(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+    ) {
+        Self::linear_assert(input, weights, bias, output);
+
+        let column_count: usize = output.column_count;
+        let element_count: usize = output.len();
+        output.data[0..element_count]
+            .par_chunks_mut(column_count)
+            .zip(bias.data[0..element_count].par_chunks(column_count))
+            .enumerate()
+            .for_each(|(row_output, (output_row, bias_row))| {
+                Self::linear_optimized_row(input, weights, row_output, bias_row, output_row);
+                for value in output_row.iter_mut() {
+                    *value = value.max(0.0);
+                }
+            });
+    }
+
+    pub fn linear_optimized_activation_parallel(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+        function: ActivationFunction,
+    ) {
+        Self::linear_assert(input, weights, bias, output);
+
+        let column_count: usize = output.column_count;
+        let element_count: usize = output.len();
+        output.data[0..element_count]
+            .par_chunks_mut(column_count)
+            .zip(bias.data[0..element_count].par_chunks(column_count))
+            .enumerate()
+            .for_each(|(row_output, (output_row, bias_row))| {
+                Self::linear_optimized_row(input, weights, row_output, bias_row, output_row);
+                for value in output_row.iter_mut() {
+                    *value = function.apply(*value);
+                }
+            });
+    }
+
+    // A single row of linear_optimized, used by the parallel kernels
+    #[inline(always)]
+    fn linear_optimized_row(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        row_output: usize,
+        bias_row: &[f32],
+        output_row: &mut [f32],
+    ) {
+        let input_row: &[f32] =
+            &input.data[row_output * input.column_count..(row_output + 1) * input.column_count];
+        for (column_output, (output_value, bias_value)) in
+            output_row.iter_mut().zip(bias_row).enumerate()
+        {
+            let weights_column = weights
+                .data
+                .iter()
+                .skip(column_output)
+                .step_by(weights.column_count);
+
+            let mut result: f32 = 0.0;
+            for (input_value, weights_value) in input_row.iter().zip(weights_column) {
+                result += input_value * weights_value;
+            }
+            *output_value = result + bias_value;
         }
+    }
 
-        let mut sum: f32 = 0.0;
-        for index in 0..(output.column_count * output.row_count) {
-            sum += (output.data[index] - max).exp();
+    pub fn relu_parallel(input: &Tensor2D, output: &mut Tensor2D) {
+        // There are no rows to split, and par_chunks_mut panics on chunks of 0 elements
+        if output.is_empty() {
+            return;
         }
 
-        let offset: f32 = max + sum.ln();
+        let column_count: usize = output.column_count;
+        let element_count: usize = output.len();
+        output.data[0..element_count]
+            .par_chunks_mut(column_count)
+            .zip(input.data[0..element_count].par_chunks(column_count))
+            .for_each(|(output_row, input_row)| {
+                for (output_value, input_value) in output_row.iter_mut().zip(input_row) {
+                    *output_value = input_value.max(0.0);
+                }
+            });
+    }
 
-        for index in 0..(output.column_count * output.row_count) {
-            output.data[index] = (output.data[index] - offset).exp();
+    pub fn activation_parallel(
+        input: &Tensor2D,
+        output: &mut Tensor2D,
+        function: ActivationFunction,
+    ) {
+        if output.is_empty() {
+            return;
         }
+
+        let column_count: usize = output.column_count;
+        let element_count: usize = output.len();
+        output.data[0..element_count]
+            .par_chunks_mut(column_count)
+            .zip(input.data[0..element_count].par_chunks(column_count))
+            .for_each(|(output_row, input_row)| {
+                for (output_value, input_value) in output_row.iter_mut().zip(input_row) {
+                    *output_value = function.apply(*input_value);
+                }
+            });
+    }
+
+    // Every row is its own softmax, so the rows are split across the threads
+    // of the current rayon pool just like in linear_optimized_parallel.
+    pub fn softmax_parallel(input: &Tensor2D, output: &mut Tensor2D) {
+        if output.is_empty() {
+            return;
+        }
+
+        let column_count: usize = output.column_count;
+        let element_count: usize = output.len();
+
+        output.data[0..element_count]
+            .par_chunks_mut(column_count)
+            .zip(input.data[0..element_count].par_chunks(column_count))
+            .for_each(|(output_row, input_row)| {
+                let (max, sum): (f32, f32) = Self::max_and_exp_sum(input_row);
+                for (output_value, input_value) in output_row.iter_mut().zip(input_row) {
+                    *output_value = (input_value - max).exp() / sum;
+                }
+            });
+    }
+
+    pub fn softmax_inplace_parallel(data: &mut Tensor2D) {
+        if data.is_empty() {
+            return;
+        }
+
+        let column_count: usize = data.column_count;
+        let element_count: usize = data.len();
+
+        data.data[0..element_count]
+            .par_chunks_mut(column_count)
+            .for_each(|row| {
+                let (max, sum): (f32, f32) = Self::max_and_exp_sum(row);
+                for value in row.iter_mut() {
+                    *value = (*value - max).exp() / sum;
+                }
+            });
+    }
+
+    pub fn linear_relu_softmax_parallel(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        bias: &Tensor2D,
+        output: &mut Tensor2D,
+    ) {
+        Self::linear_relu_softmax_assert(input, weights, bias, output);
+
+        Self::linear_optimized_relu_parallel(input, weights, bias, output);
+        Self::softmax_inplace_parallel(output);
     }
 
     // Maybe just inline
@@ -473,35 +1033,434 @@ impl Tensor2D {
     ) {
         Self::linear_relu_softmax_assert(input, weights, bias, output);
 
-        let mut max: f32 = f32::NEG_INFINITY;
+        // The softmax of a row only needs that row, so it is done
+        // right after the row is computed, while it is still in cache.
         for row_output in 0..output.row_count {
+            let row_offset: usize = row_output * output.column_count;
+            let input_row: &[f32] =
+                &input.data[row_output * input.column_count..(row_output + 1) * input.column_count];
+            let mut max: f32 = f32::NEG_INFINITY;
             for column_output in 0..output.column_count {
+                let weights_column = weights
+                    .data
+                    .iter()
+                    .skip(column_output)
+                    .step_by(weights.column_count);
+
                 let mut result: f32 = 0.0;
-                let mut index_input: usize = row_output * input.column_count;
-                let mut index_weights: usize = column_output;
-                for _ in 0..input.column_count {
-                    result += input.data[index_input] * weights.data[index_weights];
-                    index_input += 1;
-                    index_weights += weights.column_count;
+                for (input_value, weights_value) in input_row.iter().zip(weights_column) {
+                    result += input_value * weights_value;
                 }
 
-                let index: usize = row_output * output.column_count + column_output;
+                let index: usize = row_offset + column_output;
                 result = (result + bias.data[index]).max(0.0);
                 max = max.max(result);
 
                 output.data[index] = result;
             }
+
+            let mut sum: f32 = 0.0;
+            for index in row_offset..(row_offset + output.column_count) {
+                sum += (output.data[index] - max).exp();
+            }
+
+            for index in row_offset..(row_offset + output.column_count) {
+                output.data[index] = (output.data[index] - max).exp() / sum;
+            }
         }
+    }
+
+    // The backward kernels all accumulate into their gradient outputs instead of overwriting them.
+    // A tensor which is read by more than one operator receives a contribution from each of them,
+    // so the gradients have to be zeroed before a backward pass, not by the kernels.
+    pub fn linear_backward(
+        input: &Tensor2D,
+        weights: &Tensor2D,
+        output_gradient: &Tensor2D,
+        input_gradient: &mut Tensor2D,
+        weights_gradient: &mut Tensor2D,
+        bias_gradient: &mut Tensor2D,
+    ) {
+        debug_assert_eq!(input.len(), input_gradient.len(), "\nMismatch - input.len() & input_gradient.len()\ninput - rows: {} columns: {}.\n input_gradient - rows: {} columns: {}.", input.row_count, input.column_count, input_gradient.row_count, input_gradient.column_count);
+        debug_assert_eq!(weights.len(), weights_gradient.len(), "\nMismatch - weights.len() & weights_gradient.len()\nweights - rows: {} columns: {}.\n weights_gradient - rows: {} columns: {}.", weights.row_count, weights.column_count, weights_gradient.row_count, weights_gradient.column_count);
+        debug_assert_eq!(output_gradient.len(), bias_gradient.len(), "\nMismatch - output_gradient.len() & bias_gradient.len()\noutput_gradient - rows: {} columns: {}.\n bias_gradient - rows: {} columns: {}.", output_gradient.row_count, output_gradient.column_count, bias_gradient.row_count, bias_gradient.column_count);
+        debug_assert_eq!(input.row_count, output_gradient.row_count, "\nMismatch - input.row_count & output_gradient.row_count\ninput - rows: {} columns: {}.\n output_gradient - rows: {} columns: {}.", input.row_count, input.column_count, output_gradient.row_count, output_gradient.column_count);
+        debug_assert_eq!(weights.column_count, output_gradient.column_count, "\nMismatch - weights.column_count & output_gradient.column_count\nweights - rows: {} columns: {}.\n output_gradient - rows: {} columns: {}.", weights.row_count, weights.column_count, output_gradient.row_count, output_gradient.column_count);
+
+        // input_gradient = output_gradient * weights^T
+        for row in 0..input.row_count {
+            for inner_dimension in 0..input.column_count {
+                let mut result: f32 = 0.0;
+                for column in 0..output_gradient.column_count {
+                    result += output_gradient.data[row * output_gradient.column_count + column]
+                        * weights.data[inner_dimension * weights.column_count + column];
+                }
+                input_gradient.data[row * input_gradient.column_count + inner_dimension] += result;
+            }
+        }
+
+        // weights_gradient = input^T * output_gradient
+        for inner_dimension in 0..weights.row_count {
+            for column in 0..weights.column_count {
+                let mut result: f32 = 0.0;
+                for row in 0..input.row_count {
+                    result += input.data[row * input.column_count + inner_dimension]
+                        * output_gradient.data[row * output_gradient.column_count + column];
+                }
+                weights_gradient.data[inner_dimension * weights_gradient.column_count + column] +=
+                    result;
+            }
+        }
+
+        // The bias has the same shape as the output, so every element
+        // of the bias only ever touches a single element of the output.
+        for index in 0..output_gradient.len() {
+            bias_gradient.data[index] += output_gradient.data[index];
+        }
+    }
+
+    // We use the output of the ReLU to decide where the gradient flows.
+    // output > 0.0 if and only if input > 0.0, and unlike the input, the output
+    // is also available for the fused operators.
+    pub fn relu_backward(
+        output: &Tensor2D,
+        output_gradient: &Tensor2D,
+        input_gradient: &mut Tensor2D,
+    ) {
+        debug_assert_eq!(output.len(), output_gradient.len());
+        debug_assert_eq!(output.len(), input_gradient.len());
+
+        for index in 0..output.len() {
+            if 0.0 < output.data[index] {
+                input_gradient.data[index] += output_gradient.data[index];
+            }
+        }
+    }
+
+    // Unlike relu_backward this needs the input of the activation, as the derivatives
+    // of most of the functions can't be recovered from their output. The fused
+    // operators don't keep the input around, so they have to recompute it.
+    pub fn activation_backward(
+        input: &Tensor2D,
+        output_gradient: &Tensor2D,
+        input_gradient: &mut Tensor2D,
+        function: ActivationFunction,
+    ) {
+        debug_assert_eq!(input.len(), output_gradient.len());
+        debug_assert_eq!(input.len(), input_gradient.len());
+
+        for index in 0..input.len() {
+            input_gradient.data[index] +=
+                function.derivative(input.data[index]) * output_gradient.data[index];
+        }
+    }
+
+    // Softmax is computed per row, so every input element
+    // affects every output element in the same row.
+    // input_gradient_i = output_i * (output_gradient_i - sum_j(output_gradient_j * output_j))
+    pub fn softmax_backward(
+        output: &Tensor2D,
+        output_gradient: &Tensor2D,
+        input_gradient: &mut Tensor2D,
+    ) {
+        debug_assert_eq!(output.len(), output_gradient.len());
+        debug_assert_eq!(output.len(), input_gradient.len());
+
+        let column_count: usize = output.column_count;
+        for row in 0..output.row_count {
+            let row_offset: usize = row * column_count;
+            let mut dot: f32 = 0.0;
+            for index in row_offset..(row_offset + column_count) {
+                dot += output_gradient.data[index] * output.data[index];
+            }
+
+            for index in row_offset..(row_offset + column_count) {
+                input_gradient.data[index] +=
+                    output.data[index] * (output_gradient.data[index] - dot);
+            }
+        }
+    }
+
+    // log(softmax(x)) computed directly as (x - max) - ln(sum). Taking the log of the
+    // softmax instead rounds the very small probabilities to 0, which gives -inf.
+    pub fn log_softmax(input: &Tensor2D) -> Tensor2D {
+        let mut output: Tensor2D = Tensor2D::new(0.0, input.row_count, input.column_count);
+
+        Self::log_softmax_preallocated(input, &mut output);
+
+        output
+    }
+
+    pub fn log_softmax_preallocated(input: &Tensor2D, output: &mut Tensor2D) {
+        debug_assert_eq!(input.len(), output.len(), "\nMismatch - input.len() & output.len()\ninput - rows: {} columns: {}.\n output - rows: {} columns: {}.", input.row_count, input.column_count, output.row_count, output.column_count);
+
+        let column_count: usize = output.column_count;
+        for row in 0..output.row_count {
+            let row_offset: usize = row * column_count;
+            let (max, sum): (f32, f32) =
+                Self::max_and_exp_sum(&input.data[row_offset..(row_offset + column_count)]);
+            let log_sum: f32 = sum.ln();
+
+            for index in row_offset..(row_offset + column_count) {
+                output.data[index] = (input.data[index] - max) - log_sum;
+            }
+        }
+    }
+
+    pub fn log_softmax_parallel(input: &Tensor2D, output: &mut Tensor2D) {
+        if output.is_empty() {
+            return;
+        }
+
+        let column_count: usize = output.column_count;
+        let element_count: usize = output.len();
+
+        output.data[0..element_count]
+            .par_chunks_mut(column_count)
+            .zip(input.data[0..element_count].par_chunks(column_count))
+            .for_each(|(output_row, input_row)| {
+                let (max, sum): (f32, f32) = Self::max_and_exp_sum(input_row);
+                let log_sum: f32 = sum.ln();
+                for (output_value, input_value) in output_row.iter_mut().zip(input_row) {
+                    *output_value = (input_value - max) - log_sum;
+                }
+            });
+    }
+
+    // The mean of the squared differences over every element
+    pub fn mean_squared_error(input: &Tensor2D, target: &Tensor2D) -> f32 {
+        debug_assert_eq!(input.len(), target.len(), "\nMismatch - input.len() & target.len()\ninput - rows: {} columns: {}.\n target - rows: {} columns: {}.", input.row_count, input.column_count, target.row_count, target.column_count);
 
         let mut sum: f32 = 0.0;
-        for index in 0..(output.column_count * output.row_count) {
-            sum += (output.data[index] - max).exp();
+        for index in 0..input.len() {
+            let difference: f32 = input.data[index] - target.data[index];
+            sum += difference * difference;
+        }
+
+        sum / input.len() as f32
+    }
+
+    // output_gradient is the gradient of the scalar loss, usually just 1.0
+    pub fn mean_squared_error_backward(
+        input: &Tensor2D,
+        target: &Tensor2D,
+        output_gradient: f32,
+        input_gradient: &mut Tensor2D,
+    ) {
+        debug_assert_eq!(input.len(), target.len());
+        debug_assert_eq!(input.len(), input_gradient.len());
+
+        let scale: f32 = 2.0 * output_gradient / input.len() as f32;
+        for index in 0..input.len() {
+            input_gradient.data[index] += scale * (input.data[index] - target.data[index]);
+        }
+    }
+
+    // Every row of the input is a sample of unnormalized log probabilities (logits) and
+    // labels is a column with the index of the correct class for every row.
+    // The labels are stored as f32 so they can live in the data buffers of the graphs like
+    // any other tensor. The loss is the mean over the rows of -log(softmax(row)[label]).
+    //
+    // Computing the softmax first and taking the logarithm afterwards would take the log of
+    // values which can underflow to 0.0. Instead we use the log-sum-exp trick
+    // log(softmax(x)[i]) = x[i] - max - log(sum(exp(x - max)))
+    // where subtracting the max keeps exp() from overflowing.
+    pub fn softmax_cross_entropy(input: &Tensor2D, labels: &Tensor2D) -> f32 {
+        Self::softmax_cross_entropy_assert(input, labels);
+
+        let mut loss: f32 = 0.0;
+        for row in 0..input.row_count {
+            let row_data: &[f32] =
+                &input.data[row * input.column_count..(row + 1) * input.column_count];
+            let label: usize = labels.data[row] as usize;
+            loss -= row_data[label] - Self::log_sum_exp(row_data);
         }
 
-        let offset: f32 = max + sum.ln();
+        loss / input.row_count as f32
+    }
 
-        for index in 0..(output.column_count * output.row_count) {
-            output.data[index] = (output.data[index] - offset).exp();
+    // The gradient of the fused operator is famously simple, softmax(row) - one_hot(label),
+    // averaged over the rows.
+    pub fn softmax_cross_entropy_backward(
+        input: &Tensor2D,
+        labels: &Tensor2D,
+        output_gradient: f32,
+        input_gradient: &mut Tensor2D,
+    ) {
+        Self::softmax_cross_entropy_assert(input, labels);
+        debug_assert_eq!(input.len(), input_gradient.len());
+
+        let scale: f32 = output_gradient / input.row_count as f32;
+        for row in 0..input.row_count {
+            let row_offset: usize = row * input.column_count;
+            let row_data: &[f32] = &input.data[row_offset..row_offset + input.column_count];
+            let offset: f32 = Self::log_sum_exp(row_data);
+            let label: usize = labels.data[row] as usize;
+
+            for (column, value) in row_data.iter().enumerate() {
+                let mut gradient: f32 = (value - offset).exp();
+                if column == label {
+                    gradient -= 1.0;
+                }
+                input_gradient.data[row_offset + column] += scale * gradient;
+            }
+        }
+    }
+
+    // The max of the data and the sum of exp(value - max). Subtracting the max keeps every
+    // exponential between 0 and 1, so large inputs can't overflow, and the sum is at least 1.
+    // The max and the sum are kept apart, as max + ln(sum) rounds away most of ln(sum)
+    // once the max is large, which is then off by a lot more after the exponential.
+    #[inline(always)]
+    fn max_and_exp_sum(data: &[f32]) -> (f32, f32) {
+        let mut max: f32 = f32::NEG_INFINITY;
+        for value in data {
+            max = max.max(*value);
+        }
+
+        let mut sum: f32 = 0.0;
+        for value in data {
+            sum += (value - max).exp();
+        }
+
+        (max, sum)
+    }
+
+    #[inline(always)]
+    fn log_sum_exp(data: &[f32]) -> f32 {
+        let (max, sum): (f32, f32) = Self::max_and_exp_sum(data);
+        max + sum.ln()
+    }
+
+    #[inline(always)]
+    fn softmax_cross_entropy_assert(input: &Tensor2D, labels: &Tensor2D) {
+        debug_assert_eq!(
+            input.row_count,
+            labels.len(),
+            "\nMismatch - input.row_count & labels.len()\ninput - rows: {} columns: {}.\n labels - rows: {} columns: {}.",
+            input.row_count,
+            input.column_count,
+            labels.row_count,
+            labels.column_count
+        );
+        debug_assert!(
+            labels.data[0..labels.len()]
+                .iter()
+                .all(|label| 0.0 <= *label && (*label as usize) < input.column_count),
+            "\nsoftmax_cross_entropy received a label outside of 0..{}.",
+            input.column_count
+        );
+    }
+
+    // Used for residual connections, output = left + right.
+    pub fn add_preallocated(left: &Tensor2D, right: &Tensor2D, output: &mut Tensor2D) {
+        debug_assert!(
+            left.row_count == right.row_count && left.column_count == right.column_count,
+            "\nMismatch - left & right\nleft - rows: {} columns: {}.\n right - rows: {} columns: {}.",
+            left.row_count,
+            left.column_count,
+            right.row_count,
+            right.column_count
+        );
+        debug_assert_eq!(left.len(), output.len());
+
+        for ((output, left), right) in output.data[0..left.len()]
+            .iter_mut()
+            .zip(&left.data[0..left.len()])
+            .zip(&right.data[0..right.len()])
+        {
+            *output = left + right;
+        }
+    }
+
+    // output += other, used for the residual in the fused linear-add operator
+    pub fn add_inplace(output: &mut Tensor2D, other: &Tensor2D) {
+        debug_assert!(
+            output.row_count == other.row_count && output.column_count == other.column_count,
+            "\nMismatch - output & other\noutput - rows: {} columns: {}.\n other - rows: {} columns: {}.",
+            output.row_count,
+            output.column_count,
+            other.row_count,
+            other.column_count
+        );
+
+        let length: usize = output.len();
+        for (output, other) in output.data[0..length]
+            .iter_mut()
+            .zip(&other.data[0..other.len()])
+        {
+            *output += other;
+        }
+    }
+
+    pub fn add_relu_preallocated(left: &Tensor2D, right: &Tensor2D, output: &mut Tensor2D) {
+        debug_assert!(
+            left.row_count == right.row_count && left.column_count == right.column_count,
+            "\nMismatch - left & right\nleft - rows: {} columns: {}.\n right - rows: {} columns: {}.",
+            left.row_count,
+            left.column_count,
+            right.row_count,
+            right.column_count
+        );
+        debug_assert_eq!(left.len(), output.len());
+
+        for ((output, left), right) in output.data[0..left.len()]
+            .iter_mut()
+            .zip(&left.data[0..left.len()])
+            .zip(&right.data[0..right.len()])
+        {
+            *output = (left + right).max(0.0);
+        }
+    }
+
+    // Places the columns of right after the columns of left, row by row.
+    pub fn concat_columns_preallocated(left: &Tensor2D, right: &Tensor2D, output: &mut Tensor2D) {
+        debug_assert_eq!(
+            left.row_count,
+            right.row_count,
+            "\nMismatch - left.row_count & right.row_count\nleft - rows: {} columns: {}.\n right - rows: {} columns: {}.",
+            left.row_count,
+            left.column_count,
+            right.row_count,
+            right.column_count
+        );
+        debug_assert_eq!(left.row_count, output.row_count);
+        debug_assert_eq!(left.column_count + right.column_count, output.column_count);
+
+        for row in 0..output.row_count {
+            let output_offset: usize = row * output.column_count;
+            let left_offset: usize = row * left.column_count;
+            let right_offset: usize = row * right.column_count;
+
+            output.data[output_offset..output_offset + left.column_count]
+                .copy_from_slice(&left.data[left_offset..left_offset + left.column_count]);
+            output.data[output_offset + left.column_count..output_offset + output.column_count]
+                .copy_from_slice(&right.data[right_offset..right_offset + right.column_count]);
+        }
+    }
+
+    // The gradient of a concatenation is the output gradient split back into its parts.
+    // This accumulates the columns column_offset..column_offset + input_gradient.column_count
+    // of output_gradient into input_gradient. Accumulating, instead of assigning, is needed
+    // as a named tensor can be consumed by more than one operator.
+    pub fn concat_columns_backward(
+        output_gradient: &Tensor2D,
+        column_offset: usize,
+        input_gradient: &mut Tensor2D,
+    ) {
+        debug_assert_eq!(output_gradient.row_count, input_gradient.row_count);
+        debug_assert!(column_offset + input_gradient.column_count <= output_gradient.column_count);
+
+        for row in 0..input_gradient.row_count {
+            let output_offset: usize = row * output_gradient.column_count + column_offset;
+            let input_offset: usize = row * input_gradient.column_count;
+            for (input, output) in input_gradient.data
+                [input_offset..input_offset + input_gradient.column_count]
+                .iter_mut()
+                .zip(&output_gradient.data[output_offset..])
+            {
+                *input += output;
+            }
         }
     }
 
@@ -530,3 +1489,116 @@ impl Tensor2D {
         sum
     }
 }
+
+impl<T: Element> Tensor2D<T> {
+    pub fn len(&self) -> usize {
+        self.row_count * self.column_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Changes the dimensions without touching the data. This is how the memory planner
+    // lets tensors of different shapes share a buffer, which only has to be large enough
+    // for the active data in indices 0 to row_count*column_count.
+    pub fn set_dimensions(&mut self, row_count: usize, column_count: usize) {
+        assert!(
+            row_count * column_count <= self.data.len(),
+            "\nTensor2D::set_dimensions - rows: {} columns: {} does not fit in {} elements.",
+            row_count,
+            column_count,
+            self.data.len()
+        );
+
+        self.row_count = row_count;
+        self.column_count = column_count;
+    }
+
+    // Converts every element by way of f64, which is exact for every supported
+    // type, to the nearest representable value of the new type.
+    pub fn convert<U: Element>(&self) -> Tensor2D<U> {
+        Tensor2D {
+            data: self.data[0..self.len()]
+                .iter()
+                .map(|element| U::from_f64(element.to_f64()))
+                .collect(),
+            row_count: self.row_count,
+            column_count: self.column_count,
+        }
+    }
+
+    // Like linear_local_accumulation, but for any element type, with the products
+    // summed up in the accumulator type A. The bias is added in the accumulator type
+    // as well, and only the final result is converted back to the element type.
+    // Tensor2D::<f16>::linear_generic::<f32> stores f16, but accumulates in f32.
+    pub fn linear_generic<A: Accumulator>(
+        input: &Tensor2D<T>,
+        weights: &Tensor2D<T>,
+        bias: &Tensor2D<T>,
+        output: &mut Tensor2D<T>,
+    ) {
+        debug_assert_eq!(input.column_count, weights.row_count, "\nMismatch - input.column_count & weights.row_count\ninput - rows: {} columns: {}.\n weights - rows: {} columns: {}.", input.row_count, input.column_count, weights.row_count, weights.column_count);
+        debug_assert_eq!(input.row_count, output.row_count, "\nMismatch - input.row_count & output.row_count\ninput - rows: {} columns: {}.\n out - rows: {} columns: {}.", input.row_count, input.column_count, output.row_count, output.column_count);
+        debug_assert_eq!(weights.column_count, output.column_count, "\nMismatch - weights.column_count & output.column_count\nweights - rows: {} columns: {}.\n out - rows: {} columns: {}.", weights.row_count, weights.column_count, output.row_count, output.column_count);
+        debug_assert_eq!(bias.len(), output.len(), "\nMismatch - bias.len() & output.len()\nbias - rows: {} columns: {}.\n out - rows: {} columns: {}.", bias.row_count, bias.column_count, output.row_count, output.column_count);
+
+        for row_output in 0..output.row_count {
+            for column_output in 0..output.column_count {
+                let mut result: A = A::zero();
+                for inner_dimension in 0..input.column_count {
+                    result = result
+                        + A::from_element(
+                            input.data[row_output * input.column_count + inner_dimension],
+                        ) * A::from_element(
+                            weights.data[inner_dimension * weights.column_count + column_output],
+                        );
+                }
+
+                let index: usize = row_output * output.column_count + column_output;
+                output.data[index] = (result + A::from_element(bias.data[index])).to_element();
+            }
+        }
+    }
+
+    // No accumulation needed, zero is the same in every element type
+    pub fn relu_generic(input: &Tensor2D<T>, output: &mut Tensor2D<T>) {
+        debug_assert_eq!(input.len(), output.len());
+
+        let zero: T = T::default();
+        for index in 0..output.len() {
+            let value: T = input.data[index];
+            output.data[index] = if zero < value { value } else { zero };
+        }
+    }
+
+    // The same numerically stable, row-wise softmax as softmax_preallocated, with
+    // the max, the sum and the exponentials computed in the accumulator type A.
+    pub fn softmax_generic<A: FloatAccumulator>(input: &Tensor2D<T>, output: &mut Tensor2D<T>) {
+        debug_assert_eq!(input.len(), output.len());
+
+        let column_count: usize = output.column_count;
+        for row in 0..output.row_count {
+            let row_offset: usize = row * column_count;
+            let row_end: usize = row_offset + column_count;
+
+            let mut max: A = A::negative_infinity();
+            for index in row_offset..row_end {
+                let value: A = A::from_element(input.data[index]);
+                if max < value {
+                    max = value;
+                }
+            }
+
+            let mut sum: A = A::zero();
+            for index in row_offset..row_end {
+                sum = sum + (A::from_element(input.data[index]) - max).exp();
+            }
+
+            for index in row_offset..row_end {
+                output.data[index] =
+                    ((A::from_element(input.data[index]) - max).exp() / sum).to_element();
+            }
+        }
+    }
+}
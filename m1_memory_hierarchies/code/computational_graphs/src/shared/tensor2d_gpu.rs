@@ -1,7 +1,7 @@
 use futures_intrusive::channel::shared::{OneshotReceiver, OneshotSender};
 use wgpu::{util::DeviceExt, Buffer, BufferAsyncError, BufferSlice, BufferView, CommandEncoder};
 
-use super::{gpu_utilities::GPUHandles, tensor2d::Tensor2D};
+use super::{activation::ActivationFunction, gpu_utilities::GPUHandles, tensor2d::Tensor2D};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -181,10 +181,12 @@ impl SumUniform {
 
 }
 
+// The element count, the row count and the column count of the input.
+// Softmax is computed per row. The last value is padding.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct SoftmaxDimensions {
-    pub data: [u32; 1],
+    pub data: [u32; 4],
 }
 
 pub struct SoftmaxUniform {
@@ -193,9 +195,14 @@ pub struct SoftmaxUniform {
 }
 
 impl SoftmaxUniform {
-    pub fn new(handles: &GPUHandles, label: &str, element_count: usize) -> Self {
+    pub fn new(handles: &GPUHandles, label: &str, row_count: usize, column_count: usize) -> Self {
         let dimensions: SoftmaxDimensions = SoftmaxDimensions {
-            data: [element_count as u32],
+            data: [
+                (row_count * column_count) as u32,
+                row_count as u32,
+                column_count as u32,
+                0,
+            ],
         };
 
         let storage_buffer: Buffer =
@@ -218,7 +225,113 @@ impl SoftmaxUniform {
     pub fn size(&self) -> u64 {
         std::mem::size_of::<SoftmaxDimensions>() as u64
     }
+}
+
+// The dimensions of tensor_a, tensor_b and the output of the operators taking two
+// tensors, such as add and concat. The last two values are padding.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BinaryDimensions {
+    pub data: [u32; 8],
+}
 
+pub struct BinaryUniform {
+    pub dimensions: BinaryDimensions,
+    pub storage_buffer: Buffer,
+}
+
+impl BinaryUniform {
+    pub fn new(
+        handles: &GPUHandles,
+        label: &str,
+        tensor_a: &Tensor2D,
+        tensor_b: &Tensor2D,
+        output: &Tensor2D,
+    ) -> Self {
+        let dimensions: BinaryDimensions = BinaryDimensions {
+            data: [
+                tensor_a.row_count as u32,
+                tensor_a.column_count as u32,
+                tensor_b.row_count as u32,
+                tensor_b.column_count as u32,
+                output.row_count as u32,
+                output.column_count as u32,
+                0,
+                0,
+            ],
+        };
+
+        let storage_buffer: Buffer =
+            handles
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: bytemuck::cast_slice(&dimensions.data),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+        Self {
+            dimensions,
+            storage_buffer,
+        }
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> u64 {
+        std::mem::size_of::<BinaryDimensions>() as u64
+    }
+
+}
+
+// The uniform of activation.wgsl and of the main_with_activation entry point of
+// linear.wgsl. The values are element_count, the shader_index of the function,
+// the bits of alpha, as the shader reads it as an f32, and padding.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ActivationParameters {
+    pub data: [u32; 4],
+}
+
+pub struct ActivationUniform {
+    pub parameters: ActivationParameters,
+    pub storage_buffer: Buffer,
+}
+
+impl ActivationUniform {
+    pub fn new(
+        handles: &GPUHandles,
+        label: &str,
+        element_count: usize,
+        function: ActivationFunction,
+    ) -> Self {
+        let parameters: ActivationParameters = ActivationParameters {
+            data: [
+                element_count as u32,
+                function.shader_index(),
+                function.alpha().to_bits(),
+                0,
+            ],
+        };
+
+        let storage_buffer: Buffer =
+            handles
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: bytemuck::cast_slice(&parameters.data),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+        Self {
+            parameters,
+            storage_buffer,
+        }
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> u64 {
+        std::mem::size_of::<ActivationParameters>() as u64
+    }
 }
 
 #[derive(Debug)]
@@ -554,6 +667,15 @@ impl Tensor2DGPU {
         );
     }
 
+    // Changes the dimensions, but not the buffers, see Tensor2D::set_dimensions.
+    // The shaders get the dimensions through their uniforms, so a buffer which
+    // is larger than the dimensions is never read or written past them.
+    pub fn set_dimensions(&mut self, row_count: usize, column_count: usize) {
+        self.data.set_dimensions(row_count, column_count);
+        self.row_count = row_count;
+        self.column_count = column_count;
+    }
+
     #[inline(always)]
     pub fn len(&self) -> usize {
         self.row_count * self.column_count
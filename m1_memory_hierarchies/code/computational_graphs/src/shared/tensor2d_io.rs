@@ -0,0 +1,410 @@
+use std::{fmt, fs, path::Path};
+
+use half::{bf16, f16};
+use safetensors::{tensor::TensorView, Dtype, SafeTensorError, SafeTensors};
+
+use super::tensor2d::Tensor2D;
+
+// Reading and writing Tensor2D as NumPy .npy files and as safetensors files.
+// Both formats store a shape and a data type along with the data. f16, bf16 (safetensors only)
+// and f64 data is converted to f32 when it is read, which is exact for f16 and bf16,
+// but rounds f64. Tensors are always written as f32, so a tensor written and read back
+// again is bit-for-bit identical. Only the active part of a tensor is written, see Tensor2D.
+//
+// A 1D array is read as a single row. Arrays with more than 2 dimensions are rejected.
+
+#[derive(Debug)]
+pub enum TensorIoError {
+    Io(std::io::Error),
+    InvalidFormat(String),
+    UnsupportedDataType(String),
+    UnsupportedShape(Vec<usize>),
+    ShapeMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    MissingTensor(String),
+}
+
+impl fmt::Display for TensorIoError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TensorIoError::Io(error) => write!(formatter, "failed to access the file: {}", error),
+            TensorIoError::InvalidFormat(message) => write!(formatter, "invalid file: {}", message),
+            TensorIoError::UnsupportedDataType(data_type) => write!(
+                formatter,
+                "unsupported data type {}, only f16, bf16, f32 and f64 are supported",
+                data_type
+            ),
+            TensorIoError::UnsupportedShape(shape) => write!(
+                formatter,
+                "unsupported shape {:?}, only 1D and 2D arrays can be read into a Tensor2D",
+                shape
+            ),
+            TensorIoError::ShapeMismatch { expected, found } => write!(
+                formatter,
+                "expected {} rows and {} columns, found {} rows and {} columns",
+                expected.0, expected.1, found.0, found.1
+            ),
+            TensorIoError::MissingTensor(name) => {
+                write!(formatter, "the file has no tensor named {}", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TensorIoError {}
+
+impl From<std::io::Error> for TensorIoError {
+    fn from(error: std::io::Error) -> Self {
+        TensorIoError::Io(error)
+    }
+}
+
+impl From<SafeTensorError> for TensorIoError {
+    fn from(error: SafeTensorError) -> Self {
+        match error {
+            SafeTensorError::TensorNotFound(name) => TensorIoError::MissingTensor(name),
+            SafeTensorError::IoError(error) => TensorIoError::Io(error),
+            error => TensorIoError::InvalidFormat(error.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum DataType {
+    F16,
+    BF16,
+    F32,
+    F64,
+}
+
+impl DataType {
+    fn size(self) -> usize {
+        match self {
+            DataType::F16 | DataType::BF16 => 2,
+            DataType::F32 => 4,
+            DataType::F64 => 8,
+        }
+    }
+}
+
+fn dimensions_from_shape(shape: &[usize]) -> Result<(usize, usize), TensorIoError> {
+    match shape {
+        [column_count] => Ok((1, *column_count)),
+        [row_count, column_count] => Ok((*row_count, *column_count)),
+        _ => Err(TensorIoError::UnsupportedShape(shape.to_vec())),
+    }
+}
+
+fn check_dimensions(
+    tensor: Tensor2D,
+    row_count: usize,
+    column_count: usize,
+) -> Result<Tensor2D, TensorIoError> {
+    if tensor.row_count != row_count || tensor.column_count != column_count {
+        return Err(TensorIoError::ShapeMismatch {
+            expected: (row_count, column_count),
+            found: (tensor.row_count, tensor.column_count),
+        });
+    }
+    Ok(tensor)
+}
+
+// Converts the raw data to f32, little_endian is false for big endian .npy files
+fn decode_elements(bytes: &[u8], data_type: DataType, little_endian: bool) -> Vec<f32> {
+    bytes
+        .chunks_exact(data_type.size())
+        .map(|element| match (data_type, little_endian) {
+            (DataType::F16, true) => f16::from_le_bytes([element[0], element[1]]).to_f32(),
+            (DataType::F16, false) => f16::from_be_bytes([element[0], element[1]]).to_f32(),
+            (DataType::BF16, true) => bf16::from_le_bytes([element[0], element[1]]).to_f32(),
+            (DataType::BF16, false) => bf16::from_be_bytes([element[0], element[1]]).to_f32(),
+            (DataType::F32, true) => f32::from_le_bytes(element.try_into().unwrap()),
+            (DataType::F32, false) => f32::from_be_bytes(element.try_into().unwrap()),
+            (DataType::F64, true) => f64::from_le_bytes(element.try_into().unwrap()) as f32,
+            (DataType::F64, false) => f64::from_be_bytes(element.try_into().unwrap()) as f32,
+        })
+        .collect()
+}
+
+fn active_bytes(tensor: &Tensor2D) -> Vec<u8> {
+    tensor.data[0..tensor.len()]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+//
+// NumPy .npy
+//
+// The format is described in numpy/lib/format.py. A file is
+// magic: b"\x93NUMPY"
+// major_version: u8, minor_version: u8
+// header_length: u16 in version 1, u32 in version 2 and 3
+// header: a Python dict literal such as {'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }
+// followed by the data.
+//
+
+const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+// NumPy pads the header with spaces so the data starts at a multiple of 64 bytes
+const NPY_ALIGNMENT: usize = 64;
+
+// The value of a key in the header dict, up to the next comma at the top level
+fn npy_header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, TensorIoError> {
+    let quoted_key: String = format!("'{}':", key);
+    let start: usize = header
+        .find(&quoted_key)
+        .map(|index| index + quoted_key.len())
+        .ok_or_else(|| {
+            TensorIoError::InvalidFormat(format!("the .npy header has no {}: {}", key, header))
+        })?;
+    let value: &str = header[start..].trim_start();
+
+    // The shape is a tuple, which has commas in it
+    let end: usize = if value.starts_with('(') {
+        value.find(')').map(|index| index + 1)
+    } else {
+        value.find(',')
+    }
+    .ok_or_else(|| {
+        TensorIoError::InvalidFormat(format!("the .npy header is malformed: {}", header))
+    })?;
+
+    Ok(value[0..end].trim())
+}
+
+fn npy_shape(value: &str) -> Result<Vec<usize>, TensorIoError> {
+    value
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(',')
+        .map(str::trim)
+        .filter(|dimension| !dimension.is_empty())
+        .map(|dimension| {
+            dimension
+                .parse::<usize>()
+                .map_err(|_| TensorIoError::InvalidFormat(format!("invalid .npy shape {}", value)))
+        })
+        .collect()
+}
+
+pub fn npy_from_bytes(bytes: &[u8]) -> Result<Tensor2D, TensorIoError> {
+    if bytes.len() < 10 || &bytes[0..6] != NPY_MAGIC {
+        return Err(TensorIoError::InvalidFormat("not a .npy file".to_string()));
+    }
+
+    let major_version: u8 = bytes[6];
+    let (header_length, header_start): (usize, usize) = match major_version {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+            12,
+        ),
+        _ => {
+            return Err(TensorIoError::InvalidFormat(format!(
+                "unsupported .npy version {}",
+                major_version
+            )))
+        }
+    };
+    let data_start: usize = header_start + header_length;
+    if bytes.len() < data_start {
+        return Err(TensorIoError::InvalidFormat(
+            "the .npy header is longer than the file".to_string(),
+        ));
+    }
+    let header: &str = std::str::from_utf8(&bytes[header_start..data_start])
+        .map_err(|_| TensorIoError::InvalidFormat("the .npy header is not text".to_string()))?;
+
+    let descr: &str = npy_header_value(header, "descr")?.trim_matches('\'');
+    let (byte_order, type_code): (&str, &str) = descr.split_at(descr.len().min(1));
+    let little_endian: bool = match byte_order {
+        "<" | "=" | "|" => true,
+        ">" => false,
+        _ => return Err(TensorIoError::UnsupportedDataType(descr.to_string())),
+    };
+    let data_type: DataType = match type_code {
+        "f2" => DataType::F16,
+        "f4" => DataType::F32,
+        "f8" => DataType::F64,
+        _ => return Err(TensorIoError::UnsupportedDataType(descr.to_string())),
+    };
+
+    let fortran_order: bool = match npy_header_value(header, "fortran_order")? {
+        "True" => true,
+        "False" => false,
+        value => {
+            return Err(TensorIoError::InvalidFormat(format!(
+                "invalid .npy fortran_order {}",
+                value
+            )))
+        }
+    };
+
+    let shape: Vec<usize> = npy_shape(npy_header_value(header, "shape")?)?;
+    let (row_count, column_count): (usize, usize) = dimensions_from_shape(&shape)?;
+
+    let data_length: usize = row_count
+        .checked_mul(column_count)
+        .and_then(|element_count| element_count.checked_mul(data_type.size()))
+        .ok_or_else(|| {
+            TensorIoError::InvalidFormat(format!(
+                "the .npy shape {:?} is too large for type {}",
+                shape, descr
+            ))
+        })?;
+    if bytes.len() - data_start != data_length {
+        return Err(TensorIoError::InvalidFormat(format!(
+            "the .npy file has {} bytes of data, expected {} for shape {:?} and type {}",
+            bytes.len() - data_start,
+            data_length,
+            shape,
+            descr
+        )));
+    }
+    let elements: Vec<f32> = decode_elements(&bytes[data_start..], data_type, little_endian);
+
+    let mut tensor: Tensor2D = Tensor2D {
+        data: elements,
+        row_count,
+        column_count,
+    };
+
+    // Column major data is transposed into the row major layout of Tensor2D
+    if fortran_order {
+        let mut row_major: Vec<f32> = vec![0.0; tensor.data.len()];
+        for row_index in 0..row_count {
+            for column_index in 0..column_count {
+                row_major[row_index * column_count + column_index] =
+                    tensor.data[column_index * row_count + row_index];
+            }
+        }
+        tensor.data = row_major;
+    }
+
+    Ok(tensor)
+}
+
+// Writes the tensor as a version 1.0 .npy file of little endian f32, the same bytes
+// numpy.save writes for a 2D float32 array
+pub fn npy_to_bytes(tensor: &Tensor2D) -> Vec<u8> {
+    let mut header: String = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        tensor.row_count, tensor.column_count
+    );
+    // Magic, version, header length and the newline ending the header
+    let unpadded_length: usize = NPY_MAGIC.len() + 2 + 2 + header.len() + 1;
+    let padding: usize = (NPY_ALIGNMENT - unpadded_length % NPY_ALIGNMENT) % NPY_ALIGNMENT;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut bytes: Vec<u8> = Vec::<u8>::new();
+    bytes.extend_from_slice(NPY_MAGIC);
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(&active_bytes(tensor));
+    bytes
+}
+
+pub fn read_npy(path: &Path) -> Result<Tensor2D, TensorIoError> {
+    npy_from_bytes(&fs::read(path)?)
+}
+
+// Fails with ShapeMismatch unless the file has exactly these dimensions
+pub fn read_npy_with_dimensions(
+    path: &Path,
+    row_count: usize,
+    column_count: usize,
+) -> Result<Tensor2D, TensorIoError> {
+    check_dimensions(read_npy(path)?, row_count, column_count)
+}
+
+pub fn write_npy(path: &Path, tensor: &Tensor2D) -> Result<(), TensorIoError> {
+    fs::write(path, npy_to_bytes(tensor))?;
+    Ok(())
+}
+
+//
+// safetensors
+//
+// A file holds any number of named tensors, see https://github.com/huggingface/safetensors
+//
+
+fn tensor_from_view(view: &TensorView) -> Result<Tensor2D, TensorIoError> {
+    let data_type: DataType = match view.dtype() {
+        Dtype::F16 => DataType::F16,
+        Dtype::BF16 => DataType::BF16,
+        Dtype::F32 => DataType::F32,
+        Dtype::F64 => DataType::F64,
+        dtype => return Err(TensorIoError::UnsupportedDataType(format!("{:?}", dtype))),
+    };
+    let (row_count, column_count): (usize, usize) = dimensions_from_shape(view.shape())?;
+
+    Ok(Tensor2D {
+        data: decode_elements(view.data(), data_type, true),
+        row_count,
+        column_count,
+    })
+}
+
+pub fn safetensors_from_bytes(bytes: &[u8], name: &str) -> Result<Tensor2D, TensorIoError> {
+    let file: SafeTensors = SafeTensors::deserialize(bytes)?;
+    tensor_from_view(&file.tensor(name)?)
+}
+
+// Every tensor in the file, sorted by name
+pub fn all_safetensors_from_bytes(bytes: &[u8]) -> Result<Vec<(String, Tensor2D)>, TensorIoError> {
+    let file: SafeTensors = SafeTensors::deserialize(bytes)?;
+    let mut tensors: Vec<(String, Tensor2D)> = Vec::<(String, Tensor2D)>::new();
+    for (name, view) in file.tensors() {
+        let tensor: Tensor2D = tensor_from_view(&view)?;
+        tensors.push((name, tensor));
+    }
+    tensors.sort_by(|left, right| left.0.cmp(&right.0));
+    Ok(tensors)
+}
+
+pub fn safetensors_to_bytes(tensors: &[(&str, &Tensor2D)]) -> Result<Vec<u8>, TensorIoError> {
+    let data: Vec<Vec<u8>> = tensors
+        .iter()
+        .map(|(_, tensor)| active_bytes(tensor))
+        .collect();
+
+    let mut views: Vec<(&str, TensorView)> = Vec::<(&str, TensorView)>::new();
+    for ((name, tensor), data) in tensors.iter().zip(data.iter()) {
+        let view: TensorView = TensorView::new(
+            Dtype::F32,
+            vec![tensor.row_count, tensor.column_count],
+            data,
+        )?;
+        views.push((name, view));
+    }
+
+    Ok(safetensors::serialize(views, &None)?)
+}
+
+pub fn read_safetensors(path: &Path, name: &str) -> Result<Tensor2D, TensorIoError> {
+    safetensors_from_bytes(&fs::read(path)?, name)
+}
+
+// Fails with ShapeMismatch unless the tensor has exactly these dimensions
+pub fn read_safetensors_with_dimensions(
+    path: &Path,
+    name: &str,
+    row_count: usize,
+    column_count: usize,
+) -> Result<Tensor2D, TensorIoError> {
+    check_dimensions(read_safetensors(path, name)?, row_count, column_count)
+}
+
+pub fn read_all_safetensors(path: &Path) -> Result<Vec<(String, Tensor2D)>, TensorIoError> {
+    all_safetensors_from_bytes(&fs::read(path)?)
+}
+
+pub fn write_safetensors(path: &Path, tensors: &[(&str, &Tensor2D)]) -> Result<(), TensorIoError> {
+    fs::write(path, safetensors_to_bytes(tensors)?)?;
+    Ok(())
+}
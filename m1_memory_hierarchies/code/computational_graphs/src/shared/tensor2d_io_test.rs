@@ -0,0 +1,260 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use half::{bf16, f16};
+    use safetensors::{tensor::TensorView, Dtype};
+
+    use crate::shared::{
+        tensor2d::Tensor2D,
+        tensor2d_io::{
+            all_safetensors_from_bytes, npy_from_bytes, npy_to_bytes, read_npy_with_dimensions,
+            read_safetensors, read_safetensors_with_dimensions, safetensors_from_bytes,
+            safetensors_to_bytes, write_npy, write_safetensors, TensorIoError,
+        },
+    };
+
+    fn temporary_path(file_name: &str) -> PathBuf {
+        let directory: PathBuf = std::env::temp_dir().join("computational_graphs_tensor2d_io_test");
+        std::fs::create_dir_all(&directory).unwrap();
+        directory.join(file_name)
+    }
+
+    // Values which are easy to get subtly wrong, the comparisons below are on the bits
+    fn tricky_tensor() -> Tensor2D {
+        Tensor2D {
+            data: vec![
+                0.1,
+                -0.0,
+                f32::MIN_POSITIVE / 2.0,
+                f32::MAX,
+                f32::NAN,
+                f32::NEG_INFINITY,
+            ],
+            row_count: 2,
+            column_count: 3,
+        }
+    }
+
+    fn assert_bits_equal(expected: &Tensor2D, found: &Tensor2D) {
+        assert_eq!(expected.row_count, found.row_count);
+        assert_eq!(expected.column_count, found.column_count);
+        let expected_bits: Vec<u32> = expected.data[0..expected.len()]
+            .iter()
+            .map(|value| value.to_bits())
+            .collect();
+        let found_bits: Vec<u32> = found.data.iter().map(|value| value.to_bits()).collect();
+        assert_eq!(expected_bits, found_bits);
+    }
+
+    // A version 1.0 .npy file with the given header values, without the padding numpy adds
+    fn npy_bytes(descr: &str, fortran_order: bool, shape: &str, data: &[u8]) -> Vec<u8> {
+        let header: String = format!(
+            "{{'descr': '{}', 'fortran_order': {}, 'shape': {}, }}\n",
+            descr,
+            if fortran_order { "True" } else { "False" },
+            shape
+        );
+        let mut bytes: Vec<u8> = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn npy_matches_numpy_save() {
+        // numpy.save of np.arange(6, dtype=np.float32).reshape(2, 3)
+        let mut expected: Vec<u8> = b"\x93NUMPY\x01\x00\x76\x00".to_vec();
+        expected.extend_from_slice(b"{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }");
+        expected.extend_from_slice(&[b' '; 58]);
+        expected.push(b'\n');
+        for value in 0..6 {
+            expected.extend_from_slice(&(value as f32).to_le_bytes());
+        }
+
+        let tensor: Tensor2D = Tensor2D::new(1.0, 2, 3);
+        assert_eq!(npy_to_bytes(&tensor), expected);
+
+        // Only the active part of the tensor is written
+        let mut larger_buffer: Tensor2D = Tensor2D::new(1.0, 4, 3);
+        larger_buffer.row_count = 2;
+        assert_eq!(npy_to_bytes(&larger_buffer), expected);
+    }
+
+    #[test]
+    fn npy_round_trip() {
+        let tensor: Tensor2D = tricky_tensor();
+        assert_bits_equal(&tensor, &npy_from_bytes(&npy_to_bytes(&tensor)).unwrap());
+
+        let path: PathBuf = temporary_path("round_trip.npy");
+        write_npy(&path, &tensor).unwrap();
+        assert_bits_equal(&tensor, &read_npy_with_dimensions(&path, 2, 3).unwrap());
+        assert!(matches!(
+            read_npy_with_dimensions(&path, 3, 2),
+            Err(TensorIoError::ShapeMismatch {
+                expected: (3, 2),
+                found: (2, 3)
+            })
+        ));
+    }
+
+    #[test]
+    fn npy_data_types() {
+        let values: [f32; 4] = [1.5, -2.25, 0.0, 1024.0];
+        let expected: Tensor2D = Tensor2D {
+            data: values.to_vec(),
+            row_count: 2,
+            column_count: 2,
+        };
+
+        let f64_data: Vec<u8> = values
+            .iter()
+            .flat_map(|value| (*value as f64).to_le_bytes())
+            .collect();
+        let f16_data: Vec<u8> = values
+            .iter()
+            .flat_map(|value| f16::from_f32(*value).to_le_bytes())
+            .collect();
+        let big_endian_data: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        for bytes in [
+            npy_bytes("<f8", false, "(2, 2)", &f64_data),
+            npy_bytes("<f2", false, "(2, 2)", &f16_data),
+            npy_bytes(">f4", false, "(2, 2)", &big_endian_data),
+        ] {
+            assert_bits_equal(&expected, &npy_from_bytes(&bytes).unwrap());
+        }
+
+        // Column major
+        let column_major: Vec<u8> = [values[0], values[2], values[1], values[3]]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        assert_bits_equal(
+            &expected,
+            &npy_from_bytes(&npy_bytes("<f4", true, "(2, 2)", &column_major)).unwrap(),
+        );
+
+        // A 1D array is a single row
+        let little_endian_data: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let row: Tensor2D =
+            npy_from_bytes(&npy_bytes("<f4", false, "(4,)", &little_endian_data)).unwrap();
+        assert_eq!((row.row_count, row.column_count), (1, 4));
+    }
+
+    #[test]
+    fn npy_errors() {
+        let data: Vec<u8> = vec![0; 16];
+        assert!(matches!(
+            npy_from_bytes(&npy_bytes("<i4", false, "(2, 2)", &data)),
+            Err(TensorIoError::UnsupportedDataType(_))
+        ));
+        assert!(matches!(
+            npy_from_bytes(&npy_bytes("<f4", false, "(2, 1, 2)", &data)),
+            Err(TensorIoError::UnsupportedShape(_))
+        ));
+        assert!(matches!(
+            npy_from_bytes(&npy_bytes("<f4", false, "(2, 3)", &data)),
+            Err(TensorIoError::InvalidFormat(_))
+        ));
+        // The number of bytes would overflow a usize
+        assert!(matches!(
+            npy_from_bytes(&npy_bytes("<f4", false, "(4294967296, 4294967296)", &data)),
+            Err(TensorIoError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            npy_from_bytes(b"not a numpy file"),
+            Err(TensorIoError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn safetensors_round_trip() {
+        let weights: Tensor2D = tricky_tensor();
+        let bias: Tensor2D = Tensor2D::new(0.5, 1, 3);
+
+        let path: PathBuf = temporary_path("round_trip.safetensors");
+        write_safetensors(&path, &[("weights", &weights), ("bias", &bias)]).unwrap();
+        assert_bits_equal(&weights, &read_safetensors(&path, "weights").unwrap());
+        assert_bits_equal(
+            &bias,
+            &read_safetensors_with_dimensions(&path, "bias", 1, 3).unwrap(),
+        );
+
+        assert!(matches!(
+            read_safetensors_with_dimensions(&path, "bias", 3, 1),
+            Err(TensorIoError::ShapeMismatch { .. })
+        ));
+        assert!(matches!(
+            read_safetensors(&path, "missing"),
+            Err(TensorIoError::MissingTensor(_))
+        ));
+
+        let bytes: Vec<u8> =
+            safetensors_to_bytes(&[("weights", &weights), ("bias", &bias)]).unwrap();
+        let tensors: Vec<(String, Tensor2D)> = all_safetensors_from_bytes(&bytes).unwrap();
+        assert_eq!(tensors[0].0, "bias");
+        assert_eq!(tensors[1].0, "weights");
+    }
+
+    #[test]
+    fn safetensors_data_types() {
+        let values: [f32; 4] = [1.5, -2.25, 0.0, 1024.0];
+        let expected: Tensor2D = Tensor2D {
+            data: values.to_vec(),
+            row_count: 2,
+            column_count: 2,
+        };
+
+        let f64_data: Vec<u8> = values
+            .iter()
+            .flat_map(|value| (*value as f64).to_le_bytes())
+            .collect();
+        let f16_data: Vec<u8> = values
+            .iter()
+            .flat_map(|value| f16::from_f32(*value).to_le_bytes())
+            .collect();
+        let bf16_data: Vec<u8> = values
+            .iter()
+            .flat_map(|value| bf16::from_f32(*value).to_le_bytes())
+            .collect();
+        let i32_data: Vec<u8> = vec![0; 16];
+        let views: Vec<(&str, TensorView)> = vec![
+            (
+                "f64",
+                TensorView::new(Dtype::F64, vec![2, 2], &f64_data).unwrap(),
+            ),
+            (
+                "f16",
+                TensorView::new(Dtype::F16, vec![2, 2], &f16_data).unwrap(),
+            ),
+            (
+                "bf16",
+                TensorView::new(Dtype::BF16, vec![2, 2], &bf16_data).unwrap(),
+            ),
+            (
+                "i32",
+                TensorView::new(Dtype::I32, vec![2, 2], &i32_data).unwrap(),
+            ),
+        ];
+        let bytes: Vec<u8> = safetensors::serialize(views, &None).unwrap();
+
+        for name in ["f64", "f16", "bf16"] {
+            assert_bits_equal(&expected, &safetensors_from_bytes(&bytes, name).unwrap());
+        }
+        assert!(matches!(
+            safetensors_from_bytes(&bytes, "i32"),
+            Err(TensorIoError::UnsupportedDataType(_))
+        ));
+        assert!(matches!(
+            all_safetensors_from_bytes(&bytes),
+            Err(TensorIoError::UnsupportedDataType(_))
+        ));
+    }
+}
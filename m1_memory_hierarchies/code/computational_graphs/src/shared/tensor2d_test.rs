@@ -1,6 +1,16 @@
 #[cfg(test)]
 mod tests {
-    use crate::shared::tensor2d::Tensor2D;
+    use half::{bf16, f16};
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+    use wide::f32x8;
+
+    use crate::shared::{
+        activation::ActivationFunction,
+        element::{Accumulator, Element},
+        tensor2d::Tensor2D,
+        tensor2d_test_utilities::{random_tensor, random_tensor_with_magnitude},
+    };
 
     const ERROR_TOLERANCE: f32 = 0.00001;
 
@@ -205,6 +215,227 @@ mod tests {
         }
     }
 
+    #[test]
+    fn linear_blocked() {
+        let outer_dimension_input_max: usize = 10;
+        let outer_dimension_weights_max: usize = 10;
+        let inner_dimension_max: usize = 10;
+
+        for outer_dimension_input in 1..outer_dimension_input_max {
+            for outer_dimension_weights in 1..outer_dimension_weights_max {
+                for inner_dimension in 1..inner_dimension_max {
+                    let abs_result_difference: f32 = test_function_preallocated(
+                        outer_dimension_input,
+                        outer_dimension_weights,
+                        inner_dimension,
+                        Tensor2D::linear,
+                        Tensor2D::linear_blocked,
+                    );
+                    assert!(abs_result_difference < ERROR_TOLERANCE);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn linear_blocked_two_level() {
+        let outer_dimension_input_max: usize = 10;
+        let outer_dimension_weights_max: usize = 10;
+        let inner_dimension_max: usize = 10;
+
+        for outer_dimension_input in 1..outer_dimension_input_max {
+            for outer_dimension_weights in 1..outer_dimension_weights_max {
+                for inner_dimension in 1..inner_dimension_max {
+                    let abs_result_difference: f32 = test_function_preallocated(
+                        outer_dimension_input,
+                        outer_dimension_weights,
+                        inner_dimension,
+                        Tensor2D::linear,
+                        Tensor2D::linear_blocked_two_level,
+                    );
+                    assert!(abs_result_difference < ERROR_TOLERANCE);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn linear_simd() {
+        let outer_dimension_input_max: usize = 10;
+        let outer_dimension_weights_max: usize = 10;
+        let inner_dimension_max: usize = 10;
+
+        for outer_dimension_input in 1..outer_dimension_input_max {
+            for outer_dimension_weights in 1..outer_dimension_weights_max {
+                for inner_dimension in 1..inner_dimension_max {
+                    let abs_result_difference: f32 = test_function_preallocated(
+                        outer_dimension_input,
+                        outer_dimension_weights,
+                        inner_dimension,
+                        Tensor2D::linear,
+                        Tensor2D::linear_simd,
+                    );
+                    assert!(abs_result_difference < ERROR_TOLERANCE);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn linear_packed() {
+        let outer_dimension_input_max: usize = 10;
+        let outer_dimension_weights_max: usize = 10;
+        let inner_dimension_max: usize = 10;
+
+        for outer_dimension_input in 1..outer_dimension_input_max {
+            for outer_dimension_weights in 1..outer_dimension_weights_max {
+                for inner_dimension in 1..inner_dimension_max {
+                    let abs_result_difference: f32 = test_function_preallocated(
+                        outer_dimension_input,
+                        outer_dimension_weights,
+                        inner_dimension,
+                        Tensor2D::linear,
+                        Tensor2D::linear_packed,
+                    );
+                    assert!(abs_result_difference < ERROR_TOLERANCE);
+                }
+            }
+        }
+    }
+
+    // Sizes which don't line up with the block sizes, the SIMD width or the microkernel,
+    // so every kernel has to deal with partial blocks in every dimension.
+    #[test]
+    fn linear_kernels_partial_blocks() {
+        let sizes: [(usize, usize, usize); 4] =
+            [(33, 17, 45), (130, 9, 70), (5, 140, 129), (1, 1, 300)];
+        let kernels: [fn(&Tensor2D, &Tensor2D, &Tensor2D, &mut Tensor2D); 4] = [
+            Tensor2D::linear_blocked,
+            Tensor2D::linear_blocked_two_level,
+            Tensor2D::linear_simd,
+            Tensor2D::linear_packed,
+        ];
+
+        for (row_count, column_count, inner_dimension) in sizes {
+            let input: Tensor2D = Tensor2D::new(0.001, row_count, inner_dimension);
+            let weights: Tensor2D = Tensor2D::new(-0.002, inner_dimension, column_count);
+            let bias: Tensor2D = Tensor2D::new(0.1, row_count, column_count);
+            let expected: Tensor2D = Tensor2D::linear(&input, &weights, &bias);
+
+            for kernel in kernels {
+                let mut output: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+                kernel(&input, &weights, &bias, &mut output);
+
+                // The sums are accumulated in a different order, so we compare relative to the size of the values
+                for index in 0..expected.len() {
+                    let tolerance: f32 = ERROR_TOLERANCE * expected.data[index].abs().max(1.0);
+                    assert!(
+                        (expected.data[index] - output.data[index]).abs() < tolerance,
+                        "rows: {} columns: {} inner: {} index: {} expected: {} found: {}",
+                        row_count,
+                        column_count,
+                        inner_dimension,
+                        index,
+                        expected.data[index],
+                        output.data[index]
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn linear_parallel() {
+        for (row_count, column_count, inner_dimension) in [(1, 1, 1), (7, 5, 3), (33, 17, 45)] {
+            let input: Tensor2D = Tensor2D::new(0.01, row_count, inner_dimension);
+            let weights: Tensor2D = Tensor2D::new(-0.02, inner_dimension, column_count);
+            let bias: Tensor2D = Tensor2D::new(0.1, row_count, column_count);
+
+            let expected: Tensor2D = Tensor2D::linear(&input, &weights, &bias);
+            let mut output: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+            Tensor2D::linear_optimized_parallel(&input, &weights, &bias, &mut output);
+            assert!(subtract_tensors(&expected, &output).sum().abs() < ERROR_TOLERANCE);
+
+            let expected: Tensor2D = Tensor2D::relu(&expected);
+            Tensor2D::linear_optimized_relu_parallel(&input, &weights, &bias, &mut output);
+            assert!(subtract_tensors(&expected, &output).sum().abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn relu_softmax_parallel() {
+        for (row_count, column_count) in [(1, 1), (4, 3), (19, 6)] {
+            // Scaled down to keep softmax from underflowing to zero everywhere
+            let input: Tensor2D = Tensor2D::new(-0.05, row_count, column_count);
+            let mut output: Tensor2D = Tensor2D::new(1.0, row_count, column_count);
+
+            Tensor2D::relu_parallel(&input, &mut output);
+            assert!(
+                subtract_tensors(&Tensor2D::relu(&input), &output)
+                    .sum()
+                    .abs()
+                    < ERROR_TOLERANCE
+            );
+
+            Tensor2D::softmax_parallel(&input, &mut output);
+            let expected: Tensor2D = Tensor2D::softmax(&input);
+            assert!(subtract_tensors(&expected, &output).sum().abs() < ERROR_TOLERANCE);
+
+            let mut inplace: Tensor2D = input.clone();
+            Tensor2D::softmax_inplace_parallel(&mut inplace);
+            assert!(subtract_tensors(&expected, &inplace).sum().abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn parallel_empty_tensors() {
+        for (row_count, column_count) in [(0, 0), (3, 0), (0, 4)] {
+            let input: Tensor2D = Tensor2D::new(1.0, row_count, column_count);
+            let mut output: Tensor2D = Tensor2D::new(1.0, row_count, column_count);
+
+            Tensor2D::relu_parallel(&input, &mut output);
+            Tensor2D::activation_parallel(&input, &mut output, ActivationFunction::Sigmoid);
+            Tensor2D::softmax_parallel(&input, &mut output);
+            Tensor2D::log_softmax_parallel(&input, &mut output);
+            Tensor2D::softmax_inplace_parallel(&mut output);
+            assert!(output.is_empty());
+        }
+    }
+
+    #[test]
+    fn pack_weights_panels() {
+        let weights: Tensor2D = Tensor2D::new(1.0, 2, 10);
+        let panels: Vec<f32x8> = Tensor2D::pack_weights_panels(&weights);
+        assert_eq!(panels.len(), 4);
+        assert_eq!(
+            panels[0].to_array(),
+            [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]
+        );
+        assert_eq!(
+            panels[1].to_array(),
+            [10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 16.0, 17.0]
+        );
+        // The last panel is padded with zeros
+        assert_eq!(
+            panels[2].to_array(),
+            [8.0, 9.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+        assert_eq!(
+            panels[3].to_array(),
+            [18.0, 19.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn linear_packed_panels_empty_inner_dimension() {
+        let input: Tensor2D = Tensor2D::new(1.0, 5, 0);
+        let bias: Tensor2D = Tensor2D::new(0.5, 5, 11);
+        let mut output: Tensor2D = Tensor2D::new(0.0, 5, 11);
+
+        Tensor2D::linear_packed_panels(&input, &[], &bias, &mut output);
+        assert_eq!(output.data, bias.data);
+    }
+
     #[test]
     fn relu() {
         let row_count: usize = 4;
@@ -306,27 +537,174 @@ mod tests {
         }
     }
 
+    // Every row is a sample, so every row has to sum to 1 on its own
+    fn assert_rows_are_distributions(output: &Tensor2D) {
+        for row in output.data[0..output.len()].chunks(output.column_count) {
+            let mut sum: f32 = 0.0;
+            for value in row {
+                assert!(value.is_finite());
+                assert!((0.0..=1.0).contains(value));
+                sum += value;
+            }
+            assert!((1.0 - sum).abs() < ERROR_TOLERANCE);
+        }
+    }
+
     #[test]
     fn softmax() {
         let row_count: usize = 4;
         let column_count: usize = 3;
-        let scale: f32 = 0.5;
-        let expected_result: f32 = 1.0;
 
-        let input: Tensor2D = Tensor2D::new(scale, row_count, column_count);
-        let output: Tensor2D = Tensor2D::softmax(&input);
-        let abs_result_difference: f32 = (expected_result - output.sum()).abs();
-        assert!(abs_result_difference < ERROR_TOLERANCE);
+        for scale in [0.5, -0.5] {
+            // Tensor2D::new counts up through the rows, so every row is the first row
+            // plus a constant. Softmax doesn't change when a constant is added to a row.
+            let input: Tensor2D = Tensor2D::new(scale, row_count, column_count);
+            let output: Tensor2D = Tensor2D::softmax(&input);
+            assert_rows_are_distributions(&output);
 
-        let row_count: usize = 4;
-        let column_count: usize = 3;
-        let scale: f32 = -0.5;
-        let expected_result: f32 = 1.0;
+            for row in 1..row_count {
+                for column in 0..column_count {
+                    let abs_result_difference: f32 =
+                        (output.data[column] - output.data[row * column_count + column]).abs();
+                    assert!(abs_result_difference < ERROR_TOLERANCE);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn softmax_is_per_row() {
+        let mut input: Tensor2D = Tensor2D::new(0.0, 2, 2);
+        input.data = vec![0.0, 0.0, 0.0, 2.0_f32.ln()];
+        let expected: [f32; 4] = [0.5, 0.5, 1.0 / 3.0, 2.0 / 3.0];
 
-        let input: Tensor2D = Tensor2D::new(scale, row_count, column_count);
         let output: Tensor2D = Tensor2D::softmax(&input);
-        let abs_result_difference: f32 = (expected_result - output.sum()).abs();
-        assert!(abs_result_difference < ERROR_TOLERANCE);
+        for (expected, found) in expected.iter().zip(&output.data) {
+            assert!((expected - found).abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn softmax_large_magnitudes() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(19);
+        let magnitudes: [f32; 5] = [1.0, 1.0e2, 1.0e4, 1.0e30, f32::MAX / 2.0];
+
+        for magnitude in magnitudes {
+            for row_count in 1..6 {
+                for column_count in 1..40 {
+                    let input: Tensor2D =
+                        random_tensor_with_magnitude(&mut rng, row_count, column_count, magnitude);
+                    let expected: Tensor2D = Tensor2D::softmax(&input);
+                    assert_rows_are_distributions(&expected);
+
+                    let mut output: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+                    Tensor2D::softmax_parallel(&input, &mut output);
+                    assert_eq!(expected.data, output.data);
+
+                    let mut output: Tensor2D = input.clone();
+                    Tensor2D::softmax_inplace(&mut output);
+                    assert_eq!(expected.data, output.data);
+
+                    let mut output: Tensor2D = input.clone();
+                    Tensor2D::softmax_inplace_parallel(&mut output);
+                    assert_eq!(expected.data, output.data);
+
+                    let mut output: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+                    Tensor2D::softmax_generic::<f32>(&input, &mut output);
+                    assert_rows_are_distributions(&output);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn softmax_shift_invariance() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(20);
+        let shifts: [f32; 4] = [-1.0e4, -100.0, 100.0, 1.0e4];
+
+        for shift in shifts {
+            let input: Tensor2D = random_tensor_with_magnitude(&mut rng, 5, 7, 4.0);
+            let expected: Tensor2D = Tensor2D::softmax(&input);
+
+            let mut shifted: Tensor2D = input.clone();
+            for value in &mut shifted.data {
+                *value += shift;
+            }
+            let output: Tensor2D = Tensor2D::softmax(&shifted);
+
+            // The shift itself rounds the inputs, by up to half an ulp of the shift
+            let tolerance: f32 = shift.abs() * f32::EPSILON;
+            for (expected, found) in expected.data.iter().zip(&output.data) {
+                assert!((expected - found).abs() < ERROR_TOLERANCE + tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn softmax_backward_is_per_row() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(21);
+        let output: Tensor2D =
+            Tensor2D::softmax(&random_tensor_with_magnitude(&mut rng, 3, 4, 2.0));
+        let output_gradient: Tensor2D = random_tensor_with_magnitude(&mut rng, 3, 4, 1.0);
+
+        let mut input_gradient: Tensor2D = Tensor2D::new(0.0, 3, 4);
+        Tensor2D::softmax_backward(&output, &output_gradient, &mut input_gradient);
+
+        // Adding a constant to a row doesn't change its softmax, so the gradient of every row sums to 0
+        for row in input_gradient.data.chunks(4) {
+            assert!(row.iter().sum::<f32>().abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn log_softmax() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(22);
+
+        for row_count in 1..6 {
+            for column_count in 1..20 {
+                let input: Tensor2D =
+                    random_tensor_with_magnitude(&mut rng, row_count, column_count, 10.0);
+                let softmax: Tensor2D = Tensor2D::softmax(&input);
+                let output: Tensor2D = Tensor2D::log_softmax(&input);
+
+                for (expected, found) in softmax.data.iter().zip(&output.data) {
+                    assert!(*found <= 0.0);
+                    assert!((expected - found.exp()).abs() < ERROR_TOLERANCE);
+                }
+
+                let mut output_parallel: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+                Tensor2D::log_softmax_parallel(&input, &mut output_parallel);
+                assert_eq!(output.data, output_parallel.data);
+            }
+        }
+    }
+
+    #[test]
+    fn log_softmax_large_magnitudes() {
+        // softmax rounds exp(-2000.0) to 0, the log of which is -inf
+        let mut input: Tensor2D = Tensor2D::new(0.0, 2, 2);
+        input.data = vec![1000.0, -1000.0, -1.0e30, 1.0e30];
+        let expected: [f32; 4] = [0.0, -2000.0, -2.0e30, 0.0];
+
+        let output: Tensor2D = Tensor2D::log_softmax(&input);
+        for (expected, found) in expected.iter().zip(&output.data) {
+            assert!(found.is_finite());
+            assert!((expected - found).abs() <= expected.abs() * f32::EPSILON);
+        }
+
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(23);
+        for magnitude in [1.0e4, 1.0e30, f32::MAX / 2.0] {
+            let input: Tensor2D = random_tensor_with_magnitude(&mut rng, 4, 9, magnitude);
+            let output: Tensor2D = Tensor2D::log_softmax(&input);
+            for row in output.data.chunks(9) {
+                // The largest element of a row is the most likely one and gets a log probability of about 0
+                let max: f32 = row
+                    .iter()
+                    .fold(f32::NEG_INFINITY, |max, value| max.max(*value));
+                assert!(max.abs() < ERROR_TOLERANCE);
+                assert!(row.iter().all(|value| value.is_finite() && *value <= 0.0));
+            }
+        }
     }
 
     #[test]
@@ -425,12 +803,7 @@ mod tests {
 
                     let mut output_not_fused: Tensor2D =
                         Tensor2D::new(0.0, outer_dimension_input, outer_dimension_weights);
-                    Tensor2D::linear_optimized(
-                        &input,
-                        &weights,
-                        &bias,
-                        &mut output_not_fused,
-                    );
+                    Tensor2D::linear_optimized(&input, &weights, &bias, &mut output_not_fused);
                     Tensor2D::relu_inplace(&mut output_not_fused);
                     Tensor2D::softmax_inplace_inline(&mut output_not_fused);
 
@@ -451,4 +824,293 @@ mod tests {
             }
         }
     }
+
+    // The bias is only added once, in the same loop as the ReLU. Random values, as the
+    // softmax of a row of equal values is the same no matter the bias.
+    #[test]
+    fn linear_relu_softmax_fused_fission() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(24);
+        for (row_count, inner_dimension, column_count) in [(1, 1, 1), (3, 5, 4), (7, 2, 9)] {
+            let input: Tensor2D = random_tensor(&mut rng, row_count, inner_dimension);
+            let weights: Tensor2D = random_tensor(&mut rng, inner_dimension, column_count);
+            let bias: Tensor2D = random_tensor(&mut rng, row_count, column_count);
+
+            let expected: Tensor2D =
+                Tensor2D::softmax(&Tensor2D::relu(&Tensor2D::linear(&input, &weights, &bias)));
+            let mut output: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+            Tensor2D::linear_relu_softmax_fused_fission(&input, &weights, &bias, &mut output);
+
+            for index in 0..expected.len() {
+                assert!((expected.data[index] - output.data[index]).abs() < ERROR_TOLERANCE);
+            }
+        }
+    }
+
+    #[test]
+    fn mean_squared_error() {
+        let input: Tensor2D = Tensor2D::new(1.0, 2, 3);
+        let target: Tensor2D = Tensor2D::new(0.5, 2, 3);
+
+        // The differences are 0.0, 0.5, 1.0, 1.5, 2.0, 2.5
+        let expected: f32 = (0.0 + 0.25 + 1.0 + 2.25 + 4.0 + 6.25) / 6.0;
+        let found: f32 = Tensor2D::mean_squared_error(&input, &target);
+        assert!((expected - found).abs() < ERROR_TOLERANCE);
+
+        let mut input_gradient: Tensor2D = Tensor2D::new(0.0, 2, 3);
+        Tensor2D::mean_squared_error_backward(&input, &target, 1.0, &mut input_gradient);
+        for index in 0..input.len() {
+            let expected: f32 = 2.0 * (input.data[index] - target.data[index]) / 6.0;
+            assert!((expected - input_gradient.data[index]).abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn softmax_cross_entropy() {
+        // Equal logits means every class has the probability 1 / column_count
+        let input: Tensor2D = Tensor2D::new(0.0, 3, 4);
+        let labels: Tensor2D = Tensor2D::from_labels(&[0, 1, 3]);
+        let found: f32 = Tensor2D::softmax_cross_entropy(&input, &labels);
+        assert!((4.0f32.ln() - found).abs() < ERROR_TOLERANCE);
+
+        // Compare against the unfused, and unstable, version for reasonable values
+        let input: Tensor2D = Tensor2D::new(0.1, 3, 4);
+        let mut expected: f32 = 0.0;
+        for row in 0..input.row_count {
+            let row_data: &[f32] = &input.data[row * 4..(row + 1) * 4];
+            let sum: f32 = row_data.iter().map(|x| x.exp()).sum();
+            expected -= (row_data[labels.data[row] as usize].exp() / sum).ln();
+        }
+        expected /= input.row_count as f32;
+        let found: f32 = Tensor2D::softmax_cross_entropy(&input, &labels);
+        assert!((expected - found).abs() < ERROR_TOLERANCE);
+
+        // The gradient of every row is softmax(row) - one_hot(label), which sums to 0.0
+        let mut input_gradient: Tensor2D = Tensor2D::new(0.0, 3, 4);
+        Tensor2D::softmax_cross_entropy_backward(&input, &labels, 1.0, &mut input_gradient);
+        for row in 0..input.row_count {
+            let row_sum: f32 = input_gradient.data[row * 4..(row + 1) * 4].iter().sum();
+            assert!(row_sum.abs() < ERROR_TOLERANCE);
+            assert!(input_gradient.data[row * 4 + labels.data[row] as usize] < 0.0);
+        }
+    }
+
+    #[test]
+    fn softmax_cross_entropy_large_magnitudes() {
+        // exp(1000.0) overflows f32, the log-sum-exp formulation has to handle it
+        let mut input: Tensor2D = Tensor2D::new(0.0, 2, 2);
+        input.data = vec![1000.0, -1000.0, 1000.0, -1000.0];
+        let labels: Tensor2D = Tensor2D::from_labels(&[0, 1]);
+
+        let found: f32 = Tensor2D::softmax_cross_entropy(&input, &labels);
+        assert!(found.is_finite());
+        assert!((1000.0 - found).abs() < ERROR_TOLERANCE);
+
+        let mut input_gradient: Tensor2D = Tensor2D::new(0.0, 2, 2);
+        Tensor2D::softmax_cross_entropy_backward(&input, &labels, 1.0, &mut input_gradient);
+        let expected: [f32; 4] = [0.0, 0.0, 0.5, -0.5];
+        for (expected, found) in expected.iter().zip(&input_gradient.data) {
+            assert!(found.is_finite());
+            assert!((expected - found).abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    fn max_difference<T: Element>(expected: &Tensor2D<f64>, found: &Tensor2D<T>) -> f64 {
+        let mut max: f64 = 0.0;
+        for index in 0..expected.len() {
+            max = max.max((expected.data[index] - found.data[index].to_f64()).abs());
+        }
+        max
+    }
+
+    fn linear_generic_difference<T: Element, A: Accumulator>(size: usize) -> f64 {
+        let input: Tensor2D = Tensor2D::new(0.01, size, size);
+        let weights: Tensor2D = Tensor2D::new(-0.005, size, size);
+        let bias: Tensor2D = Tensor2D::new(0.1, size, size);
+
+        // The reference is computed from the values as they are stored in T,
+        // which makes the difference the error of the accumulation alone.
+        let input: Tensor2D<T> = input.convert();
+        let weights: Tensor2D<T> = weights.convert();
+        let bias: Tensor2D<T> = bias.convert();
+        let mut expected: Tensor2D<f64> = Tensor2D::new(0.0, size, size).convert();
+        Tensor2D::linear_generic::<f64>(
+            &input.convert(),
+            &weights.convert(),
+            &bias.convert(),
+            &mut expected,
+        );
+
+        let mut output: Tensor2D<T> = Tensor2D::new(0.0, size, size).convert();
+        Tensor2D::linear_generic::<A>(&input, &weights, &bias, &mut output);
+
+        max_difference(&expected, &output)
+            / expected.data.iter().fold(1.0, |max, x| x.abs().max(max))
+    }
+
+    #[test]
+    fn linear_generic() {
+        for size in 1..10 {
+            let input: Tensor2D = Tensor2D::new(0.5, size, size);
+            let weights: Tensor2D = Tensor2D::new(1.0, size, size);
+            let bias: Tensor2D = Tensor2D::new(0.1, size, size);
+
+            let expected: Tensor2D = Tensor2D::linear(&input, &weights, &bias);
+            let mut output: Tensor2D = Tensor2D::new(0.0, size, size);
+            Tensor2D::linear_generic::<f32>(&input, &weights, &bias, &mut output);
+            assert!(subtract_tensors(&expected, &output).sum().abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn linear_generic_element_types() {
+        let size: usize = 32;
+        assert!(linear_generic_difference::<f32, f32>(size) < 0.00001);
+        assert!(linear_generic_difference::<f32, f64>(size) < 0.00001);
+
+        // Rounding the result to f16 or bf16 is unavoidable, but accumulating
+        // in the small type on top of that makes the error a lot worse.
+        let f16_f32_accumulation: f64 = linear_generic_difference::<f16, f32>(size);
+        let f16_f16_accumulation: f64 = linear_generic_difference::<f16, f16>(size);
+        assert!(f16_f32_accumulation < 0.001);
+        assert!(f16_f32_accumulation < f16_f16_accumulation);
+
+        let bf16_f32_accumulation: f64 = linear_generic_difference::<bf16, f32>(size);
+        let bf16_bf16_accumulation: f64 = linear_generic_difference::<bf16, bf16>(size);
+        assert!(bf16_f32_accumulation < 0.01);
+        assert!(bf16_f32_accumulation < bf16_bf16_accumulation);
+    }
+
+    #[test]
+    fn linear_generic_integers() {
+        // Small integers, so the i32 accumulation is exact and fits back into i8
+        let mut input: Tensor2D<i8> = Tensor2D::new(0.0, 2, 3).convert();
+        input.data = vec![1, -2, 3, 4, 5, -6];
+        let mut weights: Tensor2D<i8> = Tensor2D::new(0.0, 3, 2).convert();
+        weights.data = vec![1, 2, 3, 4, -5, 6];
+        let mut bias: Tensor2D<i8> = Tensor2D::new(0.0, 2, 2).convert();
+        bias.data = vec![1, 1, -1, -1];
+
+        let mut output: Tensor2D<i8> = Tensor2D::new(0.0, 2, 2).convert();
+        Tensor2D::linear_generic::<i32>(&input, &weights, &bias, &mut output);
+        assert_eq!(output.data, vec![-19, 13, 48, -9]);
+
+        // And the i8 values saturate instead of wrapping around
+        let large: Tensor2D<i8> = Tensor2D::new(100.0, 2, 2).convert();
+        assert_eq!(large.data, vec![0, 100, 127, 127]);
+    }
+
+    // Every kernel has to apply the function to each element, see activation_test
+    // for the functions themselves
+    #[test]
+    fn activation() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(11);
+        let functions: [ActivationFunction; 6] = [
+            ActivationFunction::GELUErf,
+            ActivationFunction::GELUTanh,
+            ActivationFunction::SiLU,
+            ActivationFunction::Tanh,
+            ActivationFunction::Sigmoid,
+            ActivationFunction::LeakyReLU { alpha: 0.1 },
+        ];
+        for function in functions {
+            let input: Tensor2D = random_tensor_with_magnitude(&mut rng, 5, 7, 4.0);
+            let mut expected: Tensor2D = input.clone();
+            for value in expected.data.iter_mut() {
+                *value = function.apply(*value);
+            }
+
+            assert_eq!(Tensor2D::activation(&input, function).data, expected.data);
+
+            let mut output: Tensor2D = Tensor2D::new(0.0, 5, 7);
+            Tensor2D::activation_parallel(&input, &mut output, function);
+            assert_eq!(output.data, expected.data);
+
+            let mut output: Tensor2D = input.clone();
+            Tensor2D::activation_inplace(&mut output, function);
+            assert_eq!(output.data, expected.data);
+
+            // The fused kernels against linear followed by the activation function
+            let weights: Tensor2D = random_tensor_with_magnitude(&mut rng, 7, 3, 1.0);
+            let bias: Tensor2D = random_tensor_with_magnitude(&mut rng, 5, 3, 1.0);
+            let expected: Tensor2D =
+                Tensor2D::activation(&Tensor2D::linear(&input, &weights, &bias), function);
+
+            let mut output: Tensor2D = Tensor2D::new(0.0, 5, 3);
+            Tensor2D::linear_optimized_activation(&input, &weights, &bias, &mut output, function);
+            assert!(subtract_tensors(&expected, &output)
+                .data
+                .iter()
+                .all(|x| x.abs() < ERROR_TOLERANCE));
+
+            let mut output: Tensor2D = Tensor2D::new(0.0, 5, 3);
+            Tensor2D::linear_optimized_activation_parallel(
+                &input,
+                &weights,
+                &bias,
+                &mut output,
+                function,
+            );
+            assert!(subtract_tensors(&expected, &output)
+                .data
+                .iter()
+                .all(|x| x.abs() < ERROR_TOLERANCE));
+        }
+    }
+
+    // The gradients are added to what is already in input_gradient
+    #[test]
+    fn activation_backward() {
+        let mut rng: ChaCha8Rng = ChaCha8Rng::seed_from_u64(12);
+        let function: ActivationFunction = ActivationFunction::SiLU;
+        let input: Tensor2D = random_tensor_with_magnitude(&mut rng, 3, 4, 4.0);
+        let output_gradient: Tensor2D = random_tensor_with_magnitude(&mut rng, 3, 4, 1.0);
+        let initial_gradient: Tensor2D = random_tensor_with_magnitude(&mut rng, 3, 4, 1.0);
+        let mut input_gradient: Tensor2D = initial_gradient.clone();
+
+        Tensor2D::activation_backward(&input, &output_gradient, &mut input_gradient, function);
+        for index in 0..input.len() {
+            let expected: f32 = initial_gradient.data[index]
+                + function.derivative(input.data[index]) * output_gradient.data[index];
+            assert!((expected - input_gradient.data[index]).abs() < ERROR_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn relu_generic() {
+        let input: Tensor2D = Tensor2D::new(-0.5, 4, 3);
+        let expected: Tensor2D = Tensor2D::relu(&input);
+
+        let input_f16: Tensor2D<f16> = input.convert();
+        let mut output_f16: Tensor2D<f16> = Tensor2D::new(1.0, 4, 3).convert();
+        Tensor2D::relu_generic(&input_f16, &mut output_f16);
+        assert_eq!(output_f16.convert::<f32>().data, expected.data);
+
+        let input_f64: Tensor2D<f64> = input.convert();
+        let mut output_f64: Tensor2D<f64> = Tensor2D::new(1.0, 4, 3).convert();
+        Tensor2D::relu_generic(&input_f64, &mut output_f64);
+        assert_eq!(output_f64.convert::<f32>().data, expected.data);
+    }
+
+    #[test]
+    fn softmax_generic() {
+        for size in 1..10 {
+            let input: Tensor2D = Tensor2D::new(0.3, size, size);
+            let expected: Tensor2D = Tensor2D::softmax(&input);
+            let expected_f64: Tensor2D<f64> = expected.convert();
+
+            let mut output: Tensor2D = Tensor2D::new(0.0, size, size);
+            Tensor2D::softmax_generic::<f32>(&input, &mut output);
+            assert!(subtract_tensors(&expected, &output).sum().abs() < ERROR_TOLERANCE);
+
+            let input_f16: Tensor2D<f16> = input.convert();
+            let mut output_f16: Tensor2D<f16> = Tensor2D::new(0.0, size, size).convert();
+            Tensor2D::softmax_generic::<f32>(&input_f16, &mut output_f16);
+            assert!(max_difference(&expected_f64, &output_f16) < 0.01);
+
+            let input_f64: Tensor2D<f64> = input.convert();
+            let mut output_f64: Tensor2D<f64> = Tensor2D::new(0.0, size, size).convert();
+            Tensor2D::softmax_generic::<f64>(&input_f64, &mut output_f64);
+            assert!(max_difference(&expected_f64, &output_f64) < ERROR_TOLERANCE as f64);
+        }
+    }
 }
@@ -0,0 +1,41 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::shared::tensor2d::Tensor2D;
+
+// Uniformly distributed in -1.0..1.0
+pub fn random_tensor(rng: &mut ChaCha8Rng, row_count: usize, column_count: usize) -> Tensor2D {
+    random_tensor_with_magnitude(rng, row_count, column_count, 1.0)
+}
+
+// Uniformly distributed in -magnitude..magnitude
+pub fn random_tensor_with_magnitude(
+    rng: &mut ChaCha8Rng,
+    row_count: usize,
+    column_count: usize,
+    magnitude: f32,
+) -> Tensor2D {
+    let mut tensor: Tensor2D = Tensor2D::new(0.0, row_count, column_count);
+    for element in &mut tensor.data {
+        *element = rng.gen_range(-magnitude..magnitude);
+    }
+    tensor
+}
+
+// Kernels which accumulate their sums in a different order don't give the exact same
+// results, so the values are compared relative to their size. label names the values
+// in the panic message.
+pub fn assert_close(label: &str, expected: &[f32], found: &[f32], tolerance: f32) {
+    assert_eq!(expected.len(), found.len(), "{}", label);
+    for index in 0..expected.len() {
+        let relative_tolerance: f32 = tolerance * expected[index].abs().max(1.0);
+        assert!(
+            (expected[index] - found[index]).abs() < relative_tolerance,
+            "{} index: {} expected: {} found: {}",
+            label,
+            index,
+            expected[index],
+            found[index]
+        );
+    }
+}
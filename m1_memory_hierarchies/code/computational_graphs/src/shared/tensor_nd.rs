@@ -0,0 +1,432 @@
+use super::tensor2d::Tensor2D;
+
+// Tensor2D only knows about rows and columns. TensorND can have any number
+// of dimensions, but the owned tensor is always stored contiguously in
+// row-major order, the last dimension being the one that changes the fastest.
+// Anything which would require moving data around, like permuting the
+// dimensions or broadcasting, gives a TensorNDView instead, which just
+// reinterprets the data of the tensor it came from.
+// Calling to_tensor_nd() on the view copies it into a new contiguous TensorND.
+#[derive(Clone, Debug, Default)]
+pub struct TensorND {
+    pub data: Vec<f32>,
+    pub shape: Vec<usize>,
+}
+
+// The strides say how many elements we need to move in data to get to the next
+// element in each dimension. A stride of 0 repeats the same elements, which is
+// how broadcasting works without copying anything.
+#[derive(Clone, Debug)]
+pub struct TensorNDView<'a> {
+    data: &'a [f32],
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    offset: usize,
+}
+
+// The strides of a tensor stored contiguously in row-major order
+pub fn contiguous_strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides: Vec<usize> = vec![1; shape.len()];
+    for dimension in (0..shape.len().saturating_sub(1)).rev() {
+        strides[dimension] = strides[dimension + 1] * shape[dimension + 1];
+    }
+    strides
+}
+
+// Broadcasting follows the same rules as NumPy. The shapes are aligned at their
+// last dimension, and every pair of dimensions has to either be equal or one of them
+// has to be 1. Missing dimensions are treated as 1.
+pub fn broadcast_shapes(left: &[usize], right: &[usize]) -> Option<Vec<usize>> {
+    let rank: usize = left.len().max(right.len());
+    let mut shape: Vec<usize> = vec![1; rank];
+
+    for index in 0..rank {
+        let left_dimension: usize = if index < left.len() {
+            left[left.len() - 1 - index]
+        } else {
+            1
+        };
+        let right_dimension: usize = if index < right.len() {
+            right[right.len() - 1 - index]
+        } else {
+            1
+        };
+
+        shape[rank - 1 - index] = if left_dimension == right_dimension || right_dimension == 1 {
+            left_dimension
+        } else if left_dimension == 1 {
+            right_dimension
+        } else {
+            return None;
+        };
+    }
+
+    Some(shape)
+}
+
+// Walks through every element described by shape, strides and offset in row-major order
+// and hands the position of the element in data to function.
+fn for_each_offset(
+    shape: &[usize],
+    strides: &[usize],
+    offset: usize,
+    mut function: impl FnMut(usize),
+) {
+    if shape.contains(&0) {
+        return;
+    }
+
+    let mut index: Vec<usize> = vec![0; shape.len()];
+    let mut current_offset: usize = offset;
+    loop {
+        function(current_offset);
+
+        // Count up like an odometer, starting with the last dimension
+        let mut dimension: usize = shape.len();
+        loop {
+            if dimension == 0 {
+                return;
+            }
+            dimension -= 1;
+
+            index[dimension] += 1;
+            current_offset += strides[dimension];
+            if index[dimension] < shape[dimension] {
+                break;
+            }
+
+            current_offset -= strides[dimension] * index[dimension];
+            index[dimension] = 0;
+        }
+    }
+}
+
+impl TensorND {
+    // Just like Tensor2D::new, every element is set to its index times scale
+    pub fn new(scale: f32, shape: &[usize]) -> Self {
+        let element_count: usize = shape.iter().product();
+        let mut data: Vec<f32> = Vec::<f32>::with_capacity(element_count);
+        for index in 0..element_count {
+            data.push(index as f32 * scale);
+        }
+
+        TensorND {
+            data,
+            shape: shape.to_vec(),
+        }
+    }
+
+    pub fn from_data(data: Vec<f32>, shape: &[usize]) -> Self {
+        let element_count: usize = shape.iter().product();
+        assert_eq!(
+            data.len(),
+            element_count,
+            "\nTensorND::from_data received {} elements, but the shape {:?} requires {}.",
+            data.len(),
+            shape,
+            element_count
+        );
+
+        TensorND {
+            data,
+            shape: shape.to_vec(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn rank(&self) -> usize {
+        self.shape.len()
+    }
+
+    pub fn strides(&self) -> Vec<usize> {
+        contiguous_strides(&self.shape)
+    }
+
+    pub fn view(&self) -> TensorNDView<'_> {
+        TensorNDView {
+            data: &self.data[0..self.len()],
+            shape: self.shape.clone(),
+            strides: self.strides(),
+            offset: 0,
+        }
+    }
+
+    pub fn get(&self, index: &[usize]) -> f32 {
+        self.view().get(index)
+    }
+
+    // The data is already contiguous, so reshaping is free
+    pub fn reshape(mut self, shape: &[usize]) -> TensorND {
+        let element_count: usize = shape.iter().product();
+        assert_eq!(
+            self.len(),
+            element_count,
+            "\nTensorND::reshape can't reshape {:?} with {} elements into {:?} with {} elements.",
+            self.shape,
+            self.len(),
+            shape,
+            element_count
+        );
+
+        self.shape = shape.to_vec();
+        self
+    }
+
+    pub fn permute(&self, axes: &[usize]) -> TensorNDView<'_> {
+        self.view().permute(axes)
+    }
+
+    pub fn slice(&self, axis: usize, start: usize, end: usize) -> TensorNDView<'_> {
+        self.view().slice(axis, start, end)
+    }
+
+    pub fn broadcast_to(&self, shape: &[usize]) -> TensorNDView<'_> {
+        self.view().broadcast_to(shape)
+    }
+
+    // Applies operation to every pair of elements after broadcasting both
+    // tensors to a common shape.
+    pub fn elementwise(
+        left: &TensorNDView,
+        right: &TensorNDView,
+        operation: impl Fn(f32, f32) -> f32,
+    ) -> TensorND {
+        let shape: Vec<usize> = broadcast_shapes(&left.shape, &right.shape).unwrap_or_else(|| {
+            panic!(
+                "\nTensorND::elementwise can't broadcast the shapes {:?} and {:?} together.",
+                left.shape, right.shape
+            )
+        });
+        let left: TensorNDView = left.broadcast_to(&shape);
+        let right: TensorNDView = right.broadcast_to(&shape);
+
+        let mut right_offsets: Vec<usize> = Vec::<usize>::with_capacity(shape.iter().product());
+        for_each_offset(&right.shape, &right.strides, right.offset, |offset| {
+            right_offsets.push(offset)
+        });
+
+        let mut data: Vec<f32> = Vec::<f32>::with_capacity(right_offsets.len());
+        let mut element_index: usize = 0;
+        for_each_offset(&left.shape, &left.strides, left.offset, |offset| {
+            data.push(operation(
+                left.data[offset],
+                right.data[right_offsets[element_index]],
+            ));
+            element_index += 1;
+        });
+
+        TensorND { data, shape }
+    }
+
+    pub fn add(left: &TensorND, right: &TensorND) -> TensorND {
+        Self::elementwise(&left.view(), &right.view(), |left, right| left + right)
+    }
+
+    pub fn multiply(left: &TensorND, right: &TensorND) -> TensorND {
+        Self::elementwise(&left.view(), &right.view(), |left, right| left * right)
+    }
+
+    // Only the active data, the first row_count * column_count elements, is copied
+    pub fn from_tensor2d(tensor: &Tensor2D) -> Self {
+        TensorND {
+            data: tensor.data[0..tensor.len()].to_vec(),
+            shape: vec![tensor.row_count, tensor.column_count],
+        }
+    }
+
+    pub fn to_tensor2d(&self) -> Tensor2D {
+        assert_eq!(
+            self.rank(),
+            2,
+            "\nTensorND::to_tensor2d requires a tensor with 2 dimensions, found shape {:?}. Try to_tensor2d_flattened.",
+            self.shape
+        );
+
+        self.to_tensor2d_flattened(1)
+    }
+
+    // Every dimension before axis becomes a row and every dimension from axis and onwards
+    // becomes a column. A batch of images with shape [batch, height, width] flattened at
+    // axis 1 gives a Tensor2D with a row per image, ready for the linear kernels.
+    pub fn to_tensor2d_flattened(&self, axis: usize) -> Tensor2D {
+        assert!(
+            axis <= self.rank(),
+            "\nTensorND::to_tensor2d_flattened received axis {} for a tensor with shape {:?}.",
+            axis,
+            self.shape
+        );
+
+        Tensor2D {
+            data: self.data[0..self.len()].to_vec(),
+            row_count: self.shape[0..axis].iter().product(),
+            column_count: self.shape[axis..].iter().product(),
+        }
+    }
+}
+
+impl<'a> TensorNDView<'a> {
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn strides(&self) -> &[usize] {
+        &self.strides
+    }
+
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn rank(&self) -> usize {
+        self.shape.len()
+    }
+
+    pub fn is_contiguous(&self) -> bool {
+        self.strides == contiguous_strides(&self.shape)
+    }
+
+    pub fn get(&self, index: &[usize]) -> f32 {
+        assert_eq!(
+            index.len(),
+            self.rank(),
+            "\nTensorNDView::get received the index {:?} for a view with shape {:?}.",
+            index,
+            self.shape
+        );
+
+        let mut offset: usize = self.offset;
+        for dimension in 0..self.rank() {
+            assert!(
+                index[dimension] < self.shape[dimension],
+                "\nTensorNDView::get received the index {:?} for a view with shape {:?}.",
+                index,
+                self.shape
+            );
+            offset += index[dimension] * self.strides[dimension];
+        }
+
+        self.data[offset]
+    }
+
+    // Reorders the dimensions, axes[new_dimension] = old_dimension.
+    // Permuting [0, 1] to [1, 0] is a transpose.
+    pub fn permute(&self, axes: &[usize]) -> TensorNDView<'a> {
+        let mut sorted_axes: Vec<usize> = axes.to_vec();
+        sorted_axes.sort();
+        assert!(
+            sorted_axes.iter().copied().eq(0..self.rank()),
+            "\nTensorNDView::permute received the axes {:?} for a view with shape {:?}.",
+            axes,
+            self.shape
+        );
+
+        TensorNDView {
+            data: self.data,
+            shape: axes.iter().map(|axis| self.shape[*axis]).collect(),
+            strides: axes.iter().map(|axis| self.strides[*axis]).collect(),
+            offset: self.offset,
+        }
+    }
+
+    // Keeps the elements from start up to, but not including, end along axis
+    pub fn slice(&self, axis: usize, start: usize, end: usize) -> TensorNDView<'a> {
+        assert!(
+            axis < self.rank() && start <= end && end <= self.shape[axis],
+            "\nTensorNDView::slice received axis: {} start: {} end: {} for a view with shape {:?}.",
+            axis,
+            start,
+            end,
+            self.shape
+        );
+
+        let mut shape: Vec<usize> = self.shape.clone();
+        shape[axis] = end - start;
+
+        TensorNDView {
+            data: self.data,
+            shape,
+            strides: self.strides.clone(),
+            offset: self.offset + start * self.strides[axis],
+        }
+    }
+
+    pub fn broadcast_to(&self, shape: &[usize]) -> TensorNDView<'a> {
+        let compatible: bool = broadcast_shapes(&self.shape, shape)
+            .map(|broadcast_shape| broadcast_shape == shape)
+            .unwrap_or(false);
+        assert!(
+            compatible,
+            "\nTensorNDView::broadcast_to can't broadcast the shape {:?} to {:?}.",
+            self.shape, shape
+        );
+
+        // New dimensions are added in front, and every dimension of size 1
+        // which has to be repeated gets a stride of 0.
+        let added_dimensions: usize = shape.len() - self.rank();
+        let mut strides: Vec<usize> = vec![0; shape.len()];
+        for dimension in 0..self.rank() {
+            if self.shape[dimension] == shape[added_dimensions + dimension] {
+                strides[added_dimensions + dimension] = self.strides[dimension];
+            }
+        }
+
+        TensorNDView {
+            data: self.data,
+            shape: shape.to_vec(),
+            strides,
+            offset: self.offset,
+        }
+    }
+
+    // Only contiguous views can be reshaped without copying. Call to_tensor_nd()
+    // first to reshape anything else.
+    pub fn reshape(&self, shape: &[usize]) -> TensorNDView<'a> {
+        assert!(
+            self.is_contiguous(),
+            "\nTensorNDView::reshape can't reshape a view which isn't contiguous. Shape: {:?} strides: {:?}.",
+            self.shape,
+            self.strides
+        );
+        let element_count: usize = shape.iter().product();
+        assert_eq!(
+            self.len(),
+            element_count,
+            "\nTensorNDView::reshape can't reshape {:?} with {} elements into {:?} with {} elements.",
+            self.shape,
+            self.len(),
+            shape,
+            element_count
+        );
+
+        TensorNDView {
+            data: self.data,
+            shape: shape.to_vec(),
+            strides: contiguous_strides(shape),
+            offset: self.offset,
+        }
+    }
+
+    // Copies the elements of the view, in row-major order, into a new contiguous tensor
+    pub fn to_tensor_nd(&self) -> TensorND {
+        let mut data: Vec<f32> = Vec::<f32>::with_capacity(self.len());
+        for_each_offset(&self.shape, &self.strides, self.offset, |offset| {
+            data.push(self.data[offset])
+        });
+
+        TensorND {
+            data,
+            shape: self.shape.clone(),
+        }
+    }
+}
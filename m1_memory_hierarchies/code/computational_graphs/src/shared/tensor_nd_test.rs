@@ -0,0 +1,202 @@
+#[cfg(test)]
+mod tests {
+    use crate::shared::{
+        tensor2d::Tensor2D,
+        tensor_nd::{broadcast_shapes, contiguous_strides, TensorND, TensorNDView},
+    };
+
+    const ERROR_TOLERANCE: f32 = 0.00001;
+
+    #[test]
+    fn strides() {
+        assert_eq!(contiguous_strides(&[2, 3, 4]), vec![12, 4, 1]);
+        assert_eq!(contiguous_strides(&[5]), vec![1]);
+        assert_eq!(contiguous_strides(&[]), Vec::<usize>::new());
+
+        let tensor: TensorND = TensorND::new(1.0, &[2, 3, 4]);
+        assert_eq!(tensor.len(), 24);
+        assert_eq!(tensor.get(&[1, 2, 3]), 23.0);
+        assert_eq!(tensor.get(&[1, 0, 2]), 14.0);
+    }
+
+    #[test]
+    fn tensor2d_round_trip() {
+        for row_count in 1..5 {
+            for column_count in 1..5 {
+                let mut tensor: Tensor2D = Tensor2D::new(0.3, row_count, column_count);
+                // Only the active data is part of the tensor
+                tensor.data.push(100.0);
+
+                let tensor_nd: TensorND = TensorND::from_tensor2d(&tensor);
+                assert_eq!(tensor_nd.shape, vec![row_count, column_count]);
+                assert_eq!(
+                    tensor_nd.get(&[row_count - 1, column_count - 1]),
+                    tensor.data[tensor.len() - 1]
+                );
+
+                let round_trip: Tensor2D = tensor_nd.to_tensor2d();
+                assert_eq!(round_trip.row_count, row_count);
+                assert_eq!(round_trip.column_count, column_count);
+                assert_eq!(round_trip.data, tensor.data[0..tensor.len()]);
+            }
+        }
+    }
+
+    #[test]
+    fn flattened_batch_through_linear() {
+        // A batch of 3 images of 2x4 pixels
+        let images: TensorND = TensorND::new(0.1, &[3, 2, 4]);
+        let input: Tensor2D = images.to_tensor2d_flattened(1);
+        assert_eq!(input.row_count, 3);
+        assert_eq!(input.column_count, 8);
+
+        let weights: Tensor2D = Tensor2D::new(0.2, 8, 5);
+        let bias: Tensor2D = Tensor2D::new(0.1, 3, 5);
+        let mut output: Tensor2D = Tensor2D::new(0.0, 3, 5);
+        Tensor2D::linear_optimized(&input, &weights, &bias, &mut output);
+
+        let expected: Tensor2D = Tensor2D::linear(&input, &weights, &bias);
+        let difference: Tensor2D = Tensor2D::subtraction(&expected, &output);
+        assert!(difference.sum().abs() < ERROR_TOLERANCE);
+
+        // And back into a tensor with a batch dimension
+        let output: TensorND = TensorND::from_tensor2d(&output).reshape(&[3, 1, 5]);
+        assert_eq!(output.get(&[2, 0, 4]), expected.data[14]);
+    }
+
+    #[test]
+    fn reshape() {
+        let tensor: TensorND = TensorND::new(1.0, &[2, 6]);
+        let reshaped: TensorND = tensor.clone().reshape(&[3, 2, 2]);
+        assert_eq!(reshaped.data, tensor.data);
+        assert_eq!(reshaped.get(&[2, 1, 0]), 10.0);
+
+        let view: TensorNDView = tensor.view().reshape(&[4, 3]);
+        assert_eq!(view.get(&[3, 0]), 9.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn reshape_wrong_element_count() {
+        TensorND::new(1.0, &[2, 6]).reshape(&[5, 2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn reshape_non_contiguous_view() {
+        let tensor: TensorND = TensorND::new(1.0, &[2, 3]);
+        tensor.permute(&[1, 0]).reshape(&[6]);
+    }
+
+    #[test]
+    fn permute() {
+        let tensor: TensorND = TensorND::new(1.0, &[2, 3, 4]);
+        let permuted: TensorNDView = tensor.permute(&[2, 0, 1]);
+        assert_eq!(permuted.shape(), &[4, 2, 3]);
+        assert!(!permuted.is_contiguous());
+
+        for first in 0..2 {
+            for second in 0..3 {
+                for third in 0..4 {
+                    assert_eq!(
+                        permuted.get(&[third, first, second]),
+                        tensor.get(&[first, second, third])
+                    );
+                }
+            }
+        }
+
+        let copied: TensorND = permuted.to_tensor_nd();
+        assert_eq!(copied.shape, vec![4, 2, 3]);
+        assert!(copied.view().is_contiguous());
+        assert_eq!(copied.get(&[3, 1, 2]), tensor.get(&[1, 2, 3]));
+
+        // Permuting back gives the original tensor
+        let back: TensorND = copied.permute(&[1, 2, 0]).to_tensor_nd();
+        assert_eq!(back.data, tensor.data);
+    }
+
+    #[test]
+    fn transpose_matches_tensor2d() {
+        let tensor: Tensor2D = Tensor2D::new(1.0, 3, 5);
+        let transposed: Tensor2D = TensorND::from_tensor2d(&tensor)
+            .permute(&[1, 0])
+            .to_tensor_nd()
+            .to_tensor2d();
+        assert_eq!(transposed.row_count, 5);
+        assert_eq!(transposed.column_count, 3);
+        for row in 0..3 {
+            for column in 0..5 {
+                assert_eq!(
+                    transposed.data[column * 3 + row],
+                    tensor.data[row * 5 + column]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn slice() {
+        let tensor: TensorND = TensorND::new(1.0, &[4, 5]);
+        let slice: TensorNDView = tensor.slice(0, 1, 3).slice(1, 2, 5);
+        assert_eq!(slice.shape(), &[2, 3]);
+        assert_eq!(
+            slice.to_tensor_nd().data,
+            vec![7.0, 8.0, 9.0, 12.0, 13.0, 14.0]
+        );
+
+        let empty: TensorNDView = tensor.slice(1, 2, 2);
+        assert!(empty.is_empty());
+        assert!(empty.to_tensor_nd().data.is_empty());
+    }
+
+    #[test]
+    fn broadcasting() {
+        assert_eq!(broadcast_shapes(&[2, 3], &[3]), Some(vec![2, 3]));
+        assert_eq!(broadcast_shapes(&[4, 1, 3], &[2, 1]), Some(vec![4, 2, 3]));
+        assert_eq!(broadcast_shapes(&[], &[2, 2]), Some(vec![2, 2]));
+        assert_eq!(broadcast_shapes(&[2, 3], &[2]), None);
+
+        let row: TensorND = TensorND::new(1.0, &[3]);
+        let broadcast: TensorND = row.broadcast_to(&[2, 3]).to_tensor_nd();
+        assert_eq!(broadcast.data, vec![0.0, 1.0, 2.0, 0.0, 1.0, 2.0]);
+
+        let column: TensorND = TensorND::new(1.0, &[2, 1]);
+        let broadcast: TensorNDView = column.broadcast_to(&[2, 3]);
+        assert_eq!(broadcast.strides(), &[1, 0]);
+        assert_eq!(
+            broadcast.to_tensor_nd().data,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn broadcasting_incompatible() {
+        TensorND::new(1.0, &[2, 3]).broadcast_to(&[2, 4]);
+    }
+
+    #[test]
+    fn elementwise() {
+        // Adding a bias to every row, like the bias in a linear layer
+        let input: TensorND = TensorND::new(1.0, &[2, 3]);
+        let bias: TensorND = TensorND::from_data(vec![10.0, 20.0, 30.0], &[3]);
+        let output: TensorND = TensorND::add(&input, &bias);
+        assert_eq!(output.shape, vec![2, 3]);
+        assert_eq!(output.data, vec![10.0, 21.0, 32.0, 13.0, 24.0, 35.0]);
+
+        // Both operands broadcast, an outer product
+        let left: TensorND = TensorND::from_data(vec![1.0, 2.0], &[2, 1]);
+        let right: TensorND = TensorND::from_data(vec![3.0, 4.0, 5.0], &[1, 3]);
+        let output: TensorND = TensorND::multiply(&left, &right);
+        assert_eq!(output.shape, vec![2, 3]);
+        assert_eq!(output.data, vec![3.0, 4.0, 5.0, 6.0, 8.0, 10.0]);
+
+        // Views work as operands too
+        let transposed: TensorNDView = input.permute(&[1, 0]);
+        let output: TensorND =
+            TensorND::elementwise(&transposed, &transposed, |left, right| left - right);
+        assert_eq!(output.shape, vec![3, 2]);
+        assert!(output.data.iter().all(|element| *element == 0.0));
+    }
+}
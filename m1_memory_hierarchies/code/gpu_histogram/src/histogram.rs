@@ -7,7 +7,8 @@ use crate::{
         are_vectors_equivalent, 
         run_compute_shader
     }, 
-    gpu_vector::GPUVector
+    gpu_vector::GPUVector,
+    specialization::specialize_shader
 };
 
 fn histogram_cpu(input: &Vec<f32>, bin_count: usize) -> Vec<u32> {
@@ -52,17 +53,7 @@ pub fn histogram(
     let launch_blocks_x: u32 = ((element_count / elements_per_thread + block_size_x - 1) / block_size_x) as u32;
     let block_size_y: usize = 1;
     let launch_blocks_y: u32 = 1;
-    let bin_count_specialization: String = format!("const BIN_COUNT: u32 = {}u;\n", bin_count);
-    let elements_per_thread_specialization: String = format!("const ELEMENTS_PER_THREAD: u32 = {}u;\n", elements_per_thread);
-    let sparse_array_specialization: String = format!("const SPARSE_ARRAY_SIZE: u32 = {}u;\n", 2*elements_per_thread);
-    let shader_file: String = 
-        format!(
-            "{}{}{}{}", 
-            bin_count_specialization, 
-            elements_per_thread_specialization,
-            sparse_array_specialization, 
-            base_shader_file
-        );        
+    let shader_file: String = specialize_shader(base_shader_file, bin_count, elements_per_thread);
     let shader_function: &str = "histogram";
 
     run_compute_shader(
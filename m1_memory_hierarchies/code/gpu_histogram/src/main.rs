@@ -1,6 +1,7 @@
 mod utility;
 mod gpu_vector;
 mod histogram;
+mod specialization;
 use std::time::Instant;
 
 use crate::histogram::histogram;
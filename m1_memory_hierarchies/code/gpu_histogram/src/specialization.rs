@@ -0,0 +1,20 @@
+// WGSL needs the sizes of arrays to be known when the shader is compiled, but we
+// don't know the number of bins or the elements per thread until we run it.
+// So we specialize the shader by writing the constants in front of the shader code.
+pub fn specialize_shader(
+    base_shader_file: &str,
+    bin_count: usize,
+    elements_per_thread: usize
+) -> String {
+    let bin_count_specialization: String = format!("const BIN_COUNT: u32 = {}u;\n", bin_count);
+    let elements_per_thread_specialization: String = format!("const ELEMENTS_PER_THREAD: u32 = {}u;\n", elements_per_thread);
+    let sparse_array_specialization: String = format!("const SPARSE_ARRAY_SIZE: u32 = {}u;\n", 2*elements_per_thread);
+
+    format!(
+        "{}{}{}{}", 
+        bin_count_specialization, 
+        elements_per_thread_specialization,
+        sparse_array_specialization, 
+        base_shader_file
+    )
+}